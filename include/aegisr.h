@@ -0,0 +1,46 @@
+/* C interface to aegisrlib. Build the library with `--features ffi` and
+ * link against the resulting cdylib or staticlib. Kept in the layout
+ * cbindgen produces for src/ffi.rs. */
+
+#ifndef AEGISR_H
+#define AEGISR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/* The call succeeded. */
+#define AEG_OK 0
+/* A required pointer was null or a string was not valid UTF-8. */
+#define AEG_ERR_ARGUMENT -1
+/* The store refused the operation. */
+#define AEG_ERR_FAILED -2
+/* The library panicked; the store may be unusable for the rest of the process. */
+#define AEG_ERR_PANIC -3
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Open the store in `store_dir` (NULL for the default location), creating
+ * it if needed. Call once before any other function. */
+int32_t aeg_init(const char *store_dir);
+
+/* Store `value` under `key` in the active collection. */
+int32_t aeg_put(const char *key, const char *value);
+
+/* The value of `key` as a new NUL-terminated buffer, or NULL when the key
+ * does not exist. Its length without the terminator is written to
+ * `out_len` unless that is NULL. Release it with aeg_free. */
+uint8_t *aeg_get(const char *key, size_t *out_len);
+
+/* Wipe and release a buffer from aeg_get; `len` is the length it reported. */
+void aeg_free(uint8_t *buf, size_t len);
+
+/* Write every collection with unsaved changes to disk. */
+int32_t aeg_flush(void);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* AEGISR_H */
@@ -0,0 +1,83 @@
+use crate::core::AegCore;
+use crate::memory_engine::AegMemoryEngine;
+use crate::secret::SecretValue;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// Async front end to `AegCore` for tokio services. Every call runs on the
+/// blocking pool, so encryption, file IO and lock waits never stall the
+/// runtime's worker threads.
+pub struct AegCoreAsync;
+
+/// Runs `f` on tokio's blocking pool, re-raising any panic from it.
+async fn blocking<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(r) => r,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+impl AegCoreAsync {
+    pub async fn put_value(key: impl Into<String>, value: impl Into<String>) -> String {
+        let (key, value) = (key.into(), value.into());
+        blocking(move || AegCore::put_value(&key, &value)).await
+    }
+
+    pub async fn get_value(key: impl Into<String>) -> Option<SecretValue> {
+        let key = key.into();
+        blocking(move || AegCore::get_value(&key)).await
+    }
+
+    pub async fn delete_value(key: impl Into<String>) -> String {
+        let key = key.into();
+        blocking(move || AegCore::delete_value(&key)).await
+    }
+
+    /// Save every dirty collection; returns how many were written.
+    pub async fn flush() -> usize {
+        blocking(AegMemoryEngine::save_all).await
+    }
+
+    /// Spawn a tokio task that saves autosave-enabled collections every
+    /// `interval`. Use instead of `AegCore::start_background_saver` inside a
+    /// runtime; stop it with `AegAsyncSaver::shutdown`.
+    pub fn start_background_saver(interval: Duration) -> AegAsyncSaver {
+        let stop = Arc::new(Notify::new());
+        let signal = Arc::clone(&stop);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(10)));
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        blocking(AegMemoryEngine::save_autosave).await;
+                    }
+                    _ = signal.notified() => break,
+                }
+            }
+            // final flush once stopped
+            blocking(AegMemoryEngine::save_autosave).await;
+        });
+        AegAsyncSaver { stop, task }
+    }
+}
+
+/// Handle to the saver task started by `AegCoreAsync::start_background_saver`.
+pub struct AegAsyncSaver {
+    stop: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl AegAsyncSaver {
+    /// Stop the saver immediately, wait for its final save to finish, and
+    /// return once it has.
+    pub async fn shutdown(self) {
+        self.stop.notify_one();
+        if let Err(e) = self.task.await
+            && e.is_panic()
+        {
+            std::panic::resume_unwind(e.into_panic());
+        }
+    }
+}
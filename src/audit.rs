@@ -0,0 +1,575 @@
+use crate::constant::STORE_AUDIT_LOG;
+use crate::crypto::AegCrypto;
+use crate::file_system::AegFileSystem;
+use crate::plain::{AegPlain, PlainFormat};
+use crate::watch::ChangeKind;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// `prev` of the first entry.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+static APPEND: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static SOURCE: RefCell<Option<AuditSource>> = const { RefCell::new(None) };
+}
+
+/// What an audit entry records.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Put,
+    Delete,
+    /// Every key of the collection was removed at once.
+    Clear,
+    /// A value was handed out (a read receipt).
+    Read,
+    CreateCollection,
+    DeleteCollection,
+    /// `target` holds the new name.
+    RenameCollection,
+    /// `target` holds the name of the copy.
+    CloneCollection,
+    /// The keys of `target` were merged into the collection.
+    MergeCollection,
+}
+
+impl From<ChangeKind> for AuditAction {
+    fn from(kind: ChangeKind) -> Self {
+        match kind {
+            ChangeKind::Put => Self::Put,
+            ChangeKind::Delete => Self::Delete,
+            ChangeKind::Clear => Self::Clear,
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Put => write!(f, "put"),
+            Self::Delete => write!(f, "delete"),
+            Self::Clear => write!(f, "clear"),
+            Self::Read => write!(f, "read"),
+            Self::CreateCollection => write!(f, "create_collection"),
+            Self::DeleteCollection => write!(f, "delete_collection"),
+            Self::RenameCollection => write!(f, "rename_collection"),
+            Self::CloneCollection => write!(f, "clone_collection"),
+            Self::MergeCollection => write!(f, "merge_collection"),
+        }
+    }
+}
+
+/// Which front end an audited operation came through, set around the
+/// operation with `AegAudit::with_source`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    Cli,
+    Daemon,
+    /// The HTTP API, identified by `AegServer::token_id` rather than the
+    /// token itself.
+    Token(String),
+}
+
+impl fmt::Display for AuditSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cli => write!(f, "cli"),
+            Self::Daemon => write!(f, "daemon"),
+            Self::Token(id) => write!(f, "token:{}", id),
+        }
+    }
+}
+
+/// One recorded operation. Like `ChangeEvent` it never carries the value.
+/// `hash` covers every other field, `prev` included, so editing, removing
+/// or reordering an entry breaks the chain from that entry on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 1.
+    pub seq: u64,
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub collection: String,
+    /// `None` for `Clear` and the collection operations.
+    pub key: Option<String>,
+    pub kind: AuditAction,
+    /// The OS user the process ran as, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// `None` when the store was used directly as a library.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<AuditSource>,
+    /// The other collection of a rename, clone or merge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// `hash` of the previous entry.
+    pub prev: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// `user`, `source` and `target` are only hashed when present, so
+    /// entries written before they were recorded still verify.
+    fn compute_hash(&self) -> String {
+        let (seq, time) = (self.seq.to_string(), self.time.to_string());
+        let kind = serde_json::to_string(&self.kind).unwrap_or_default();
+        let source = self.source.as_ref().map(|s| format!("source={}", s));
+        let user = self.user.as_ref().map(|u| format!("user={}", u));
+        let target = self.target.as_ref().map(|t| format!("target={}", t));
+        let mut hasher = blake3::Hasher::new_derive_key("aegisr audit chain v1");
+        let fields = [
+            Some(seq.as_str()),
+            Some(time.as_str()),
+            Some(self.collection.as_str()),
+            Some(self.key.as_deref().unwrap_or("")),
+            Some(kind.as_str()),
+            Some(self.prev.as_str()),
+            user.as_deref(),
+            source.as_deref(),
+            target.as_deref(),
+        ];
+        for field in fields.into_iter().flatten() {
+            hasher.update(&(field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// The entry as a report row, in `EXPORT_COLUMNS` order.
+    fn row(&self) -> [String; 8] {
+        [
+            self.seq.to_string(),
+            self.time.to_string(),
+            self.user.clone().unwrap_or_default(),
+            self.source
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "library".to_string()),
+            self.kind.to_string(),
+            self.collection.clone(),
+            self.key.clone().unwrap_or_default(),
+            self.hash.clone(),
+        ]
+    }
+}
+
+const EXPORT_COLUMNS: [&str; 8] = [
+    "seq",
+    "time",
+    "user",
+    "source",
+    "action",
+    "collection",
+    "key",
+    "hash",
+];
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.seq, self.time, self.kind, self.collection
+        )?;
+        if let Some(key) = &self.key {
+            write!(f, "/{}", key)?;
+        }
+        if let Some(target) = &self.target {
+            write!(f, " -> {}", target)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " via {}", source)?;
+        }
+        if let Some(user) = &self.user {
+            write!(f, " by {}", user)?;
+        }
+        Ok(())
+    }
+}
+
+/// The last entry of the chain, as handed to an external timestamping
+/// service. Written and parsed as `<seq>:<hash>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditHead {
+    pub seq: u64,
+    pub hash: String,
+}
+
+impl fmt::Display for AuditHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.seq, self.hash)
+    }
+}
+
+impl FromStr for AuditHead {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid anchor '{}' (expected <seq>:<hash>)", s);
+        let (seq, hash) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let hash = hash.to_ascii_lowercase();
+        if hash.len() != GENESIS_HASH.len() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        match seq.parse() {
+            Ok(seq) if seq > 0 => Ok(Self { seq, hash }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Which audit entries `AegAudit::filtered` returns. A field left `None`
+/// matches every entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditFilter {
+    /// Only entries at or after this time (Unix seconds).
+    pub since: Option<u64>,
+    /// Only entries at or before this time (Unix seconds).
+    pub until: Option<u64>,
+    pub collection: Option<String>,
+    pub key: Option<String>,
+    pub kind: Option<AuditAction>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since.is_none_or(|since| entry.time >= since)
+            && self.until.is_none_or(|until| entry.time <= until)
+            && self
+                .collection
+                .as_ref()
+                .is_none_or(|c| *c == entry.collection)
+            && self
+                .key
+                .as_ref()
+                .is_none_or(|k| entry.key.as_ref() == Some(k))
+            && self.kind.is_none_or(|kind| kind == entry.kind)
+    }
+}
+
+/// Append-only log of the puts, deletes and clears made through
+/// `AegMemoryEngine`, of the collections created, deleted, renamed, cloned
+/// and merged through `AegCore`, and of the values read through
+/// `AegCore::get_value` and the HTTP API, kept in the store's `audit.log`
+/// while `StoreConfig::audit` is on. Each line is an entry encrypted with
+/// a key derived from the store key (see `AegCrypto::derive_audit_key`);
+/// lines written as plain JSON before that are still read. Each entry
+/// hashes the one before it, so any change to history shows up in
+/// `verify`; anchoring the head somewhere outside the store (`head`) also
+/// catches entries cut off the end. Appends are serialized within a
+/// process only.
+pub struct AegAudit;
+
+impl AegAudit {
+    pub fn log_path() -> PathBuf {
+        AegFileSystem::get_config_path().join(STORE_AUDIT_LOG)
+    }
+
+    /// Record a change if the store has auditing on. The engine has no way
+    /// to report a failure here; a change that cannot be appended is missing
+    /// from the log, which an anchor taken after it will not match.
+    pub(crate) fn record(collection: &str, key: Option<&str>, kind: impl Into<AuditAction>) {
+        if !AegFileSystem::read_store_config().audit {
+            return;
+        }
+        let _ = Self::append_entry(collection, key, kind.into(), None);
+    }
+
+    /// Record an operation on a whole collection; `target` is the other
+    /// collection of a rename, clone or merge.
+    pub(crate) fn record_collection(collection: &str, kind: AuditAction, target: Option<&str>) {
+        if !AegFileSystem::read_store_config().audit {
+            return;
+        }
+        let _ = Self::append_entry(collection, None, kind, target);
+    }
+
+    /// Record that the value of `key` was handed out.
+    pub(crate) fn record_read(collection: &str, key: &str) {
+        Self::record(collection, Some(key), AuditAction::Read);
+    }
+
+    /// Run `f` with the operations it audits attributed to `source`.
+    /// A source set further up the call stack wins, so a front end that
+    /// goes through another (the HTTP API's command route through
+    /// `AegDispatch`) keeps its own.
+    pub fn with_source<R>(source: AuditSource, f: impl FnOnce() -> R) -> R {
+        let outer = SOURCE.with(|s| s.borrow().is_some());
+        if outer {
+            return f();
+        }
+        SOURCE.with(|s| *s.borrow_mut() = Some(source));
+        struct Reset;
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                SOURCE.with(|s| *s.borrow_mut() = None);
+            }
+        }
+        let _reset = Reset;
+        f()
+    }
+
+    /// Key the lines of the current store's log are encrypted with.
+    pub fn log_key() -> Result<Zeroizing<String>, String> {
+        AegCrypto::derive_audit_key(&AegFileSystem::try_read_authorization_key()?)
+    }
+
+    fn append_entry(
+        collection: &str,
+        key: Option<&str>,
+        kind: AuditAction,
+        target: Option<&str>,
+    ) -> Result<AuditEntry, String> {
+        AegFileSystem::ensure_writable()?;
+        let _guard = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+        let (seq, prev) = match Self::head()? {
+            Some(head) => (head.seq + 1, head.hash),
+            None => (1, GENESIS_HASH.to_string()),
+        };
+        let mut entry = AuditEntry {
+            seq,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            collection: collection.to_string(),
+            key: key.map(str::to_string),
+            kind,
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok()
+                .filter(|u| !u.is_empty()),
+            source: SOURCE.with(|s| s.borrow().clone()),
+            target: target.map(str::to_string),
+            prev,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        let json = Zeroizing::new(serde_json::to_vec(&entry).map_err(|e| e.to_string())?);
+        let cipher = AegFileSystem::read_store_config().cipher;
+        let line = AegCrypto::seal(cipher, &Self::log_key()?, &json)?;
+        AegFileSystem::append_record(&Self::log_path(), &line)?;
+        Ok(entry)
+    }
+
+    /// The log as text; empty when there is none yet.
+    fn read_log() -> Result<String, String> {
+        let path = Self::log_path();
+        match AegFileSystem::storage().read(&path) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map_err(|_| format!("{} is not a text file", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Every entry, oldest first. Fails on a line that is not an entry or
+    /// does not decrypt.
+    pub fn entries() -> Result<Vec<AuditEntry>, String> {
+        let text = Self::read_log()?;
+        let mut key = None;
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let line = line.trim();
+                if line.starts_with('{') {
+                    return serde_json::from_str(line).map_err(|e| {
+                        format!("line {} of the audit log is not an entry: {}", i + 1, e)
+                    });
+                }
+                if key.is_none() {
+                    key = Some(Self::log_key()?);
+                }
+                let json =
+                    AegCrypto::decrypt_record(key.as_deref().map_or("", String::as_str), line)
+                        .map_err(|e| {
+                            format!("line {} of the audit log does not decrypt: {}", i + 1, e)
+                        })?;
+                serde_json::from_slice(&json)
+                    .map_err(|e| format!("line {} of the audit log is not an entry: {}", i + 1, e))
+            })
+            .collect()
+    }
+
+    /// The entries `filter` matches, oldest first.
+    pub fn filtered(filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+        Ok(Self::entries()?
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect())
+    }
+
+    /// Re-encrypt the log from the store key `old_key` to `new_key`, for a
+    /// store re-key. Lines still in plain JSON are left as they are.
+    /// Returns the previous contents, `None` when there is no log.
+    pub(crate) fn rekey(old_key: &str, new_key: &str) -> Result<Option<Vec<u8>>, String> {
+        let text = Self::read_log()?;
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+        let old_log_key = AegCrypto::derive_audit_key(old_key)?;
+        let new_log_key = AegCrypto::derive_audit_key(new_key)?;
+        let mut out = String::with_capacity(text.len());
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match AegCrypto::envelope_cipher(line) {
+                Some(cipher) => {
+                    let json = AegCrypto::decrypt_record(&old_log_key, line)?;
+                    out.push_str(&AegCrypto::seal(cipher, &new_log_key, &json)?);
+                }
+                None => out.push_str(line),
+            }
+            out.push('\n');
+        }
+        AegFileSystem::storage()
+            .write(&Self::log_path(), out.as_bytes())
+            .map_err(|e| format!("write {}: {}", Self::log_path().display(), e))?;
+        Ok(Some(text.into_bytes()))
+    }
+
+    /// The current head of the chain, `None` while the log is empty.
+    pub fn head() -> Result<Option<AuditHead>, String> {
+        Ok(Self::entries()?.pop().map(|e| AuditHead {
+            seq: e.seq,
+            hash: e.hash,
+        }))
+    }
+
+    /// Check every link of the chain and, given an `anchor` taken earlier,
+    /// that the chain still passes through it. Returns the verified head,
+    /// or a description of the first entry that does not hold.
+    pub fn verify(anchor: Option<&AuditHead>) -> Result<Option<AuditHead>, String> {
+        let entries = Self::entries()?;
+        let mut prev = GENESIS_HASH.to_string();
+        for (i, entry) in entries.iter().enumerate() {
+            let expected_seq = i as u64 + 1;
+            if entry.seq != expected_seq {
+                return Err(format!(
+                    "entry {} has sequence number {}; entries were removed or reordered",
+                    expected_seq, entry.seq
+                ));
+            }
+            if entry.prev != prev {
+                return Err(format!(
+                    "entry {} does not follow the entry before it",
+                    entry.seq
+                ));
+            }
+            if entry.compute_hash() != entry.hash {
+                return Err(format!(
+                    "entry {} was modified after it was written",
+                    entry.seq
+                ));
+            }
+            prev = entry.hash.clone();
+        }
+        if let Some(anchor) = anchor {
+            match entries.get((anchor.seq as usize).saturating_sub(1)) {
+                Some(entry) if entry.hash == anchor.hash => {}
+                Some(_) => {
+                    return Err(format!(
+                        "entry {} differs from the anchored chain",
+                        anchor.seq
+                    ));
+                }
+                None => {
+                    return Err(format!(
+                        "the log ends at entry {} but entry {} was anchored; history was truncated",
+                        entries.len(),
+                        anchor.seq
+                    ));
+                }
+            }
+        }
+        Ok(entries.last().map(|e| AuditHead {
+            seq: e.seq,
+            hash: e.hash.clone(),
+        }))
+    }
+
+    /// A compliance report of the entries recorded between `from` and `to`
+    /// (Unix seconds, both inclusive) as CSV or a JSON array: sequence,
+    /// time, user, source, action, collection, key and entry hash. Built
+    /// from the log alone, which never holds values, so no report can
+    /// contain one. The chain is verified first; a tampered log is not
+    /// reported from.
+    pub fn export(
+        format: PlainFormat,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<String, String> {
+        Self::verify(None)?;
+        let entries = Self::filtered(&AuditFilter {
+            since: from,
+            until: to,
+            ..AuditFilter::default()
+        })?;
+        match format {
+            PlainFormat::Csv => {
+                let mut out = EXPORT_COLUMNS.join(",");
+                out.push('\n');
+                for entry in &entries {
+                    let row: Vec<String> =
+                        entry.row().iter().map(|c| AegPlain::csv_field(c)).collect();
+                    out.push_str(&row.join(","));
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+            PlainFormat::Json => {
+                let rows: Vec<serde_json::Map<String, serde_json::Value>> = entries
+                    .iter()
+                    .map(|entry| {
+                        EXPORT_COLUMNS
+                            .iter()
+                            .zip(entry.row())
+                            .map(|(column, cell)| (column.to_string(), cell.into()))
+                            .collect()
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&rows).map_err(|e| e.to_string())
+            }
+            PlainFormat::Dotenv => Err("audit reports are exported as csv or json".to_string()),
+        }
+    }
+
+    /// Unix seconds for a `--from`/`--to` bound: either seconds already or
+    /// a `YYYY-MM-DD` date (midnight UTC; with `end_of_day`, its last second).
+    pub fn parse_time(s: &str, end_of_day: bool) -> Result<u64, String> {
+        if let Ok(secs) = s.parse() {
+            return Ok(secs);
+        }
+        let invalid = || format!("invalid time '{}' (expected Unix seconds or YYYY-MM-DD)", s);
+        let parts: Vec<&str> = s.split('-').collect();
+        let [year, month, day] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let (year, month, day): (i64, i64, i64) = (
+            year.parse().map_err(|_| invalid())?,
+            month.parse().map_err(|_| invalid())?,
+            day.parse().map_err(|_| invalid())?,
+        );
+        if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(invalid());
+        }
+        // days since the epoch for a proleptic Gregorian date
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let start = days as u64 * 86_400;
+        Ok(if end_of_day { start + 86_399 } else { start })
+    }
+}
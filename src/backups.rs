@@ -0,0 +1,96 @@
+use crate::constant::STORE_BACKUPS_DIR;
+use crate::file_system::AegFileSystem;
+use std::path::{Path, PathBuf};
+
+/// Rolling backups of collection files. Before a save overwrites
+/// `collection_<name>.aekv`, the previous file becomes generation 1 in the
+/// store's `backups` directory as `collection_<name>.aekv.1`, and older
+/// generations move up one, up to `StoreConfig::backup_generations`.
+/// Backups are copies of the encrypted files, so they are only readable
+/// with the store's key. Record and index files are not backed up.
+pub struct AegBackups;
+
+impl AegBackups {
+    pub fn backups_dir(store_dir: &Path) -> PathBuf {
+        store_dir.join(STORE_BACKUPS_DIR)
+    }
+
+    /// Path of generation `generation` of `collection` in `store_dir`.
+    pub fn backup_file(store_dir: &Path, collection: &str, generation: usize) -> PathBuf {
+        Self::backups_dir(store_dir).join(format!("{}.{}", Self::live_name(collection), generation))
+    }
+
+    fn live_name(collection: &str) -> String {
+        format!("collection_{}.aekv", collection)
+    }
+
+    /// Generations kept for `collection`, newest (1) first.
+    pub fn generations(store_dir: &Path, collection: &str) -> Vec<usize> {
+        let prefix = format!("{}.", Self::live_name(collection));
+        let mut generations: Vec<usize> = AegFileSystem::storage()
+            .list(&Self::backups_dir(store_dir))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| name.strip_prefix(&prefix)?.parse().ok())
+            .collect();
+        generations.sort_unstable();
+        generations
+    }
+
+    /// Every backup file in `store_dir`, with the name of the live file it
+    /// is a copy of.
+    pub fn files(store_dir: &Path) -> Vec<(PathBuf, String)> {
+        let dir = Self::backups_dir(store_dir);
+        let mut files: Vec<(PathBuf, String)> = AegFileSystem::storage()
+            .list(&dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|name| {
+                let (live, generation) = name.rsplit_once('.')?;
+                generation.parse::<usize>().ok()?;
+                AegFileSystem::collection_of_file(live)?;
+                Some((dir.join(&name), live.to_string()))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Shift the backups of `collection` up one generation and copy its
+    /// current file in as generation 1, keeping at most `keep`. Generations
+    /// beyond `keep` are removed, so lowering the setting prunes on the next
+    /// save. Does nothing when `keep` is 0 or the collection has no file
+    /// yet. The caller holds the store lock.
+    pub(crate) fn rotate(store_dir: &Path, collection: &str, keep: usize) -> Result<(), String> {
+        let storage = AegFileSystem::storage();
+        let live = store_dir.join(Self::live_name(collection));
+        if keep == 0 || !storage.exists(&live) {
+            return Ok(());
+        }
+        let generations = Self::generations(store_dir, collection);
+        for &generation in generations.iter().rev() {
+            let path = Self::backup_file(store_dir, collection, generation);
+            if generation >= keep {
+                storage
+                    .remove(&path)
+                    .map_err(|e| format!("remove {}: {}", path.display(), e))?;
+                continue;
+            }
+            let content = storage
+                .read(&path)
+                .map_err(|e| format!("read {}: {}", path.display(), e))?;
+            let next = Self::backup_file(store_dir, collection, generation + 1);
+            storage
+                .write(&next, &content)
+                .map_err(|e| format!("write {}: {}", next.display(), e))?;
+        }
+        let content = storage
+            .read(&live)
+            .map_err(|e| format!("read {}: {}", live.display(), e))?;
+        let first = Self::backup_file(store_dir, collection, 1);
+        storage
+            .create_dir_all(&Self::backups_dir(store_dir))
+            .and_then(|_| storage.write(&first, &content))
+            .map_err(|e| format!("write {}: {}", first.display(), e))
+    }
+}
@@ -0,0 +1,205 @@
+use crate::core::AegCore;
+use crate::crypto::Cipher;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::{AegMemoryEngine, MemoryCache, PersistencePolicy};
+use crate::storage::StorageBackend;
+use crate::store::KeyValueStore;
+use crate::verbosity::Verbosity;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Arc<StoreContext>>> = const { RefCell::new(None) };
+}
+
+/// Configuration of a store, given explicitly instead of through
+/// `AEGISR_HOME`, `set_store_dir` and the persistence setters:
+///
+/// ```no_run
+/// use aegisrlib::{AegCoreBuilder, Cipher, KeyValueStore, PersistencePolicy};
+///
+/// let store = AegCoreBuilder::new()
+///     .base_dir("/srv/app/secrets")
+///     .cipher(Cipher::ChaCha20Poly1305)
+///     .persistence(PersistencePolicy::Debounced(500))
+///     .build()
+///     .unwrap();
+/// store.put("api_key", "s3cr3t").unwrap();
+/// ```
+#[derive(Default)]
+pub struct AegCoreBuilder {
+    base_dir: Option<PathBuf>,
+    storage: Option<Arc<dyn StorageBackend>>,
+    cipher: Option<Cipher>,
+    persistence: Option<PersistencePolicy>,
+}
+
+impl AegCoreBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory of the store; `AEGISR_HOME` or `~/.aegisr` by default.
+    pub fn base_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Where the store files are kept; `AegFileSystem::default_storage` by
+    /// default.
+    pub fn storage(mut self, storage: Arc<dyn StorageBackend>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Algorithm for newly written collection files (see `AegCore::set_cipher`).
+    /// The store's own setting is kept by default.
+    pub fn cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// When the store's changes are saved; `Manual` by default.
+    pub fn persistence(mut self, policy: PersistencePolicy) -> Self {
+        self.persistence = Some(policy);
+        self
+    }
+
+    /// Open the store, creating it if needed.
+    pub fn build(self) -> Result<AegStore, String> {
+        let store = AegStore {
+            inner: Arc::new(StoreInner {
+                context: Arc::new(StoreContext {
+                    dir: self
+                        .base_dir
+                        .unwrap_or_else(AegFileSystem::default_store_dir),
+                    storage: self.storage.unwrap_or_else(AegFileSystem::default_storage),
+                    cache: Arc::default(),
+                }),
+                persistence: self.persistence.unwrap_or(PersistencePolicy::Manual),
+            }),
+        };
+        store.run(|| {
+            AegFileSystem::initialize_config(Some(false), Verbosity::default());
+            if let Some(cipher) = self.cipher
+                && let Some(e) = AegCore::set_cipher(cipher).strip_prefix("✗ ")
+            {
+                return Err(e.to_string());
+            }
+            AegMemoryEngine::set_persistence_policy(store.inner.persistence);
+            Ok(())
+        })?;
+        Ok(store)
+    }
+}
+
+/// The store a thread works on inside `AegStore::run`, in place of the
+/// process-wide store directory, storage and cache.
+pub(crate) struct StoreContext {
+    pub(crate) dir: PathBuf,
+    pub(crate) storage: Arc<dyn StorageBackend>,
+    pub(crate) cache: Arc<MemoryCache>,
+}
+
+impl StoreContext {
+    /// The store this thread is running, if any.
+    pub(crate) fn current() -> Option<Arc<StoreContext>> {
+        CONTEXT.with(|c| c.borrow().clone())
+    }
+
+    /// Work on `context` on this thread until the guard is dropped, which
+    /// puts back the store the thread was on before.
+    pub(crate) fn enter(context: Arc<StoreContext>) -> ContextGuard {
+        ContextGuard {
+            previous: CONTEXT.with(|c| c.borrow_mut().replace(context)),
+        }
+    }
+}
+
+#[must_use = "the context is left as soon as the guard is dropped"]
+pub(crate) struct ContextGuard {
+    previous: Option<Arc<StoreContext>>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CONTEXT.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+struct StoreInner {
+    context: Arc<StoreContext>,
+    persistence: PersistencePolicy,
+}
+
+impl Drop for StoreInner {
+    fn drop(&mut self) {
+        let _context = StoreContext::enter(Arc::clone(&self.context));
+        AegMemoryEngine::set_persistence_policy(PersistencePolicy::Manual);
+        // still running if the policy could not change (read-only)
+        AegMemoryEngine::stop_background_saver();
+        AegCore::flush_now();
+    }
+}
+
+/// An open store from `AegCoreBuilder`, cheap to clone. Each store has its
+/// own directory, storage, cache of loaded collections and background
+/// saver, so any number of them can be open in one process and used from
+/// different threads at once. Pending changes are saved when the last
+/// clone is dropped.
+///
+/// The static `AegCore` API works on the store of `AEGISR_HOME` or
+/// `set_store_dir`, except inside `run`. Read-only mode
+/// (`AegCore::set_read_only`), the passphrase given with
+/// `AegFileSystem::set_passphrase` and `pause_saver` stay process-wide.
+#[derive(Clone)]
+pub struct AegStore {
+    inner: Arc<StoreInner>,
+}
+
+impl AegStore {
+    pub fn builder() -> AegCoreBuilder {
+        AegCoreBuilder::new()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.inner.context.dir
+    }
+
+    pub fn persistence(&self) -> PersistencePolicy {
+        self.inner.persistence
+    }
+
+    /// Run `f` with the static `AegCore` API working on this store. Only
+    /// the calling thread is affected: other threads, including ones `f`
+    /// spawns, keep their own store, and calls can nest.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _context = StoreContext::enter(Arc::clone(&self.inner.context));
+        f()
+    }
+}
+
+impl KeyValueStore for AegStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.run(|| AegCore::load().get(key))
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), String> {
+        self.run(|| AegCore::load().put(key, value))
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, String> {
+        self.run(|| AegCore::load().delete(key))
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.run(|| AegCore::load().list())
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        self.run(AegCore::flush_now);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use crate::age::{AegAge, SshIdentity, SshRecipient};
+use crate::crypto::AegCrypto;
+use crate::memory_engine::KeyMeta;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const BUNDLE_FORMAT: &str = "aegisr-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A standalone, password-encrypted export of one collection. The key is
+/// derived from the password alone, so the file opens on any machine.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AegBundle {
+    pub format: String,
+    pub version: u32,
+    pub kdf: String,
+    /// base64 Argon2id salt
+    pub salt: String,
+    /// base64(nonce || ciphertext) of the JSON `BundlePayload`
+    pub payload: String,
+}
+
+/// Decrypted bundle content.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BundlePayload {
+    pub collection: String,
+    pub entries: HashMap<String, String>,
+    /// Per-key settings such as environment variable names.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub key_meta: HashMap<String, KeyMeta>,
+}
+
+impl AegBundle {
+    pub fn seal(payload: &BundlePayload, password: &str) -> Result<Self, String> {
+        let salt = AegCrypto::generate_random_bytes();
+        let key = AegCrypto::derive_password_key(password, &salt)?;
+        let json = serde_json::to_vec(payload).map_err(|e| format!("serialize error: {}", e))?;
+        Ok(Self {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            kdf: "argon2id".to_string(),
+            salt: general_purpose::STANDARD.encode(salt),
+            payload: AegCrypto::encrypt_record(&key, &json)?,
+        })
+    }
+
+    pub fn open(&self, password: &str) -> Result<BundlePayload, String> {
+        if self.format != BUNDLE_FORMAT {
+            return Err(format!("not an Aegisr bundle (format '{}')", self.format));
+        }
+        if self.version > BUNDLE_VERSION {
+            return Err(format!("unsupported bundle version {}", self.version));
+        }
+        let salt = general_purpose::STANDARD
+            .decode(&self.salt)
+            .map_err(|e| format!("invalid salt: {}", e))?;
+        let key = AegCrypto::derive_password_key(password, &salt)?;
+        let json = AegCrypto::decrypt_record(&key, &self.payload)
+            .map_err(|_| "wrong password or corrupted bundle".to_string())?;
+        serde_json::from_slice(&json).map_err(|e| format!("corrupt bundle payload: {}", e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| format!("serialize error: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("write {}: {}", path.display(), e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let json =
+            fs::read_to_string(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("invalid bundle file: {}", e))
+    }
+}
+
+impl BundlePayload {
+    /// The payload as an age file (age-encryption.org/v1) that any of
+    /// `recipients` can open with their SSH private key, including with
+    /// `age -d -i ~/.ssh/id_ed25519`. No password is involved.
+    pub fn seal_for_recipients(&self, recipients: &[SshRecipient]) -> Result<Vec<u8>, String> {
+        let json = serde_json::to_vec(self).map_err(|e| format!("serialize error: {}", e))?;
+        AegAge::encrypt(&json, recipients)
+    }
+
+    pub fn open_with_identity(bytes: &[u8], identity: &SshIdentity) -> Result<Self, String> {
+        let json = AegAge::decrypt(bytes, identity)?;
+        serde_json::from_slice(&json).map_err(|e| format!("corrupt bundle payload: {}", e))
+    }
+}
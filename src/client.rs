@@ -0,0 +1,207 @@
+use crate::emergency::EmergencyBundle;
+use reqwest::{Client, Method, StatusCode};
+
+/// Async client for the HTTP API served by `AegServer`.
+pub struct AegClient {
+    base_url: String,
+    token: String,
+    http: Client,
+}
+
+impl AegClient {
+    /// `base_url` is the server root (e.g. `http://127.0.0.1:7878`); `token`
+    /// is the server's bearer token (`AegServer::token` on the server host).
+    pub fn new(base_url: &str, token: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            http: Client::new(),
+        }
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<String>, String> {
+        let body = self.send(Method::GET, &["collections"], None).await?;
+        Self::parse_list(&body)
+    }
+
+    pub async fn create_collection(&self, name: &str) -> Result<(), String> {
+        self.send(Method::PUT, &["collections", name], None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn delete_collection(&self, name: &str) -> Result<(), String> {
+        self.send(Method::DELETE, &["collections", name], None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn list_keys(&self, collection: &str) -> Result<Vec<String>, String> {
+        let body = self
+            .send(Method::GET, &["collections", collection, "keys"], None)
+            .await?;
+        Self::parse_list(&body)
+    }
+
+    /// The value of `key`, or `None` if the collection has no such key.
+    pub async fn get(&self, collection: &str, key: &str) -> Result<Option<String>, String> {
+        let (status, body) = self
+            .request(Method::GET, &["collections", collection, "keys", key], None)
+            .await?;
+        if status.is_success() {
+            return Ok(Some(body));
+        }
+        let message = Self::error_message(&body);
+        // a missing collection is a 404 too, but names the collection
+        if status == StatusCode::NOT_FOUND && message.starts_with("key ") {
+            return Ok(None);
+        }
+        Err(format!("{}: {}", status.as_u16(), message))
+    }
+
+    pub async fn put(&self, collection: &str, key: &str, value: &str) -> Result<(), String> {
+        self.send(
+            Method::PUT,
+            &["collections", collection, "keys", key],
+            Some(value.to_string()),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn delete(&self, collection: &str, key: &str) -> Result<(), String> {
+        self.send(
+            Method::DELETE,
+            &["collections", collection, "keys", key],
+            None,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// As a trusted contact, start the waiting period of the emergency
+    /// grant `bundle` names. Returns when its escrow key becomes available
+    /// (seconds since the Unix epoch). The bundle's access key stands in
+    /// for the token, so a client made with any token will do.
+    pub async fn emergency_request(&self, bundle: &EmergencyBundle) -> Result<u64, String> {
+        let body = self
+            .send_as(
+                Method::POST,
+                &["emergency", &bundle.grant, "request"],
+                None,
+                &bundle.access,
+            )
+            .await?;
+        let response: serde_json::Value =
+            serde_json::from_str(&body).map_err(|e| format!("unexpected response: {}", e))?;
+        response["available_at"]
+            .as_u64()
+            .ok_or_else(|| format!("unexpected response: {}", body))
+    }
+
+    /// As a trusted contact, the escrow key that opens `bundle` once its
+    /// grant's waiting period has passed (see `AegEmergency::open`).
+    pub async fn emergency_release(&self, bundle: &EmergencyBundle) -> Result<String, String> {
+        let body = self
+            .send_as(
+                Method::POST,
+                &["emergency", &bundle.grant, "release"],
+                None,
+                &bundle.access,
+            )
+            .await?;
+        let response: serde_json::Value =
+            serde_json::from_str(&body).map_err(|e| format!("unexpected response: {}", e))?;
+        response["escrow_key"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| format!("unexpected response: {}", body))
+    }
+
+    /// Like `request`, but a non-2xx response is an error
+    /// `"<status>: <server message>"`.
+    async fn send(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<String>,
+    ) -> Result<String, String> {
+        self.send_as(method, segments, body, &self.token).await
+    }
+
+    /// `send` with `bearer` in place of the token.
+    async fn send_as(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<String>,
+        bearer: &str,
+    ) -> Result<String, String> {
+        let (status, body) = self.request_as(method, segments, body, bearer).await?;
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err(format!(
+                "{}: {}",
+                status.as_u16(),
+                Self::error_message(&body)
+            ))
+        }
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<String>,
+    ) -> Result<(StatusCode, String), String> {
+        self.request_as(method, segments, body, &self.token).await
+    }
+
+    async fn request_as(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<String>,
+        bearer: &str,
+    ) -> Result<(StatusCode, String), String> {
+        let path: Vec<String> = segments.iter().map(|s| percent_encode(s)).collect();
+        let url = format!("{}/{}", self.base_url, path.join("/"));
+        let mut request = self.http.request(method, &url).bearer_auth(bearer);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("request to {} failed: {}", url, e))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        Ok((status, body))
+    }
+
+    fn error_message(body: &str) -> String {
+        serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(String::from))
+            .unwrap_or_else(|| body.to_string())
+    }
+
+    fn parse_list(body: &str) -> Result<Vec<String>, String> {
+        serde_json::from_str(body).map_err(|e| format!("unexpected response: {}", e))
+    }
+}
+
+/// Encode everything but unreserved characters, so `/` in a key name stays
+/// inside its path segment (the server decodes it again).
+fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
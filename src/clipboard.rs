@@ -0,0 +1,253 @@
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// The system clipboard, or whatever stands in for it. Set another one with
+/// `AegClipboard::set_backend`, e.g. `MemoryClipboard` in tests.
+pub trait ClipboardBackend: Send + Sync {
+    /// The text on the clipboard, or `None` when it holds none.
+    fn get(&self) -> Result<Option<String>, String>;
+    fn set(&self, text: &str) -> Result<(), String>;
+    fn clear(&self) -> Result<(), String> {
+        self.set("")
+    }
+}
+
+/// The platform clipboard, reached through its command-line tools so
+/// nothing links against a windowing system: `pbcopy` and `pbpaste` on
+/// macOS, `wl-copy` and `wl-paste` under Wayland, `xclip` or `xsel` under
+/// X11, `clip` and PowerShell on Windows. The text goes over standard
+/// input, never on a command line. Needs the `clipboard` feature; without
+/// it every call fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsClipboard;
+
+#[cfg(feature = "clipboard")]
+impl ClipboardBackend for OsClipboard {
+    fn get(&self) -> Result<Option<String>, String> {
+        let (program, args) = Self::paste_command()?;
+        let text = Self::run(program, args, None)?;
+        Ok(Some(text.to_string()).filter(|t| !t.is_empty()))
+    }
+
+    fn set(&self, text: &str) -> Result<(), String> {
+        let (program, args) = Self::copy_command()?;
+        Self::run(program, args, Some(text)).map(drop)
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        if Self::wayland() {
+            return Self::run("wl-copy", &["--clear"], None).map(drop);
+        }
+        self.set("")
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl OsClipboard {
+    fn wayland() -> bool {
+        cfg!(all(unix, not(target_os = "macos"))) && std::env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    fn copy_command() -> Result<(&'static str, &'static [&'static str]), String> {
+        if cfg!(target_os = "macos") {
+            Ok(("pbcopy", &[]))
+        } else if cfg!(windows) {
+            Ok(("clip", &[]))
+        } else if Self::wayland() {
+            Ok(("wl-copy", &[]))
+        } else {
+            Self::x11_command(
+                &["-selection", "clipboard", "-in"],
+                &["--clipboard", "--input"],
+            )
+        }
+    }
+
+    fn paste_command() -> Result<(&'static str, &'static [&'static str]), String> {
+        if cfg!(target_os = "macos") {
+            Ok(("pbpaste", &[]))
+        } else if cfg!(windows) {
+            Ok((
+                "powershell",
+                &["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+            ))
+        } else if Self::wayland() {
+            Ok(("wl-paste", &["--no-newline"]))
+        } else {
+            Self::x11_command(
+                &["-selection", "clipboard", "-out"],
+                &["--clipboard", "--output"],
+            )
+        }
+    }
+
+    /// `xclip` with `xclip_args`, or `xsel` with `xsel_args` when only that
+    /// is installed.
+    fn x11_command(
+        xclip_args: &'static [&'static str],
+        xsel_args: &'static [&'static str],
+    ) -> Result<(&'static str, &'static [&'static str]), String> {
+        let installed = |program: &str| {
+            std::process::Command::new(program)
+                .arg("-version")
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .is_ok()
+        };
+        if installed("xclip") {
+            Ok(("xclip", xclip_args))
+        } else if installed("xsel") {
+            Ok(("xsel", xsel_args))
+        } else {
+            Err("no clipboard tool found (install wl-clipboard, xclip or xsel)".into())
+        }
+    }
+
+    /// Run `program` with `args`, feeding it `input`; returns its standard
+    /// output, which is only collected when there is no input, since the
+    /// X11 tools stay behind to serve the selection.
+    fn run(program: &str, args: &[&str], input: Option<&str>) -> Result<Zeroizing<String>, String> {
+        use std::io::{Read, Write};
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(if input.is_some() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("run {}: {}", program, e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.unwrap_or_default().as_bytes())
+                .map_err(|e| format!("write to {}: {}", program, e))?;
+        }
+        let mut output = Zeroizing::new(String::new());
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout
+                .read_to_string(&mut output)
+                .map_err(|e| format!("read from {}: {}", program, e))?;
+        }
+        let status = child
+            .wait()
+            .map_err(|e| format!("run {}: {}", program, e))?;
+        if !status.success() {
+            return Err(format!("{} exited with {}", program, status));
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+impl ClipboardBackend for OsClipboard {
+    fn get(&self) -> Result<Option<String>, String> {
+        Err(Self::UNSUPPORTED.into())
+    }
+
+    fn set(&self, _text: &str) -> Result<(), String> {
+        Err(Self::UNSUPPORTED.into())
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+impl OsClipboard {
+    const UNSUPPORTED: &str =
+        "this build has no clipboard support (enable the `clipboard` feature)";
+}
+
+/// A clipboard kept in the process, for tests and for embedders with a
+/// clipboard of their own to bridge.
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    text: Mutex<Option<Zeroizing<String>>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn get(&self) -> Result<Option<String>, String> {
+        let text = self.text.lock().expect("Failed to lock clipboard");
+        Ok(text.as_ref().map(|t| t.to_string()))
+    }
+
+    fn set(&self, text: &str) -> Result<(), String> {
+        *self.text.lock().expect("Failed to lock clipboard") =
+            Some(Zeroizing::new(text.to_string())).filter(|t| !t.is_empty());
+        Ok(())
+    }
+}
+
+static BACKEND: OnceLock<RwLock<Arc<dyn ClipboardBackend>>> = OnceLock::new();
+/// Clears not yet run, so a process can wait for them before it exits.
+static PENDING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Values copied to the clipboard (`aegisr get --clip`) instead of printed,
+/// and cleared again after a while.
+pub struct AegClipboard;
+
+impl AegClipboard {
+    /// Use `backend` for the clipboard from now on. Applies to the whole
+    /// process.
+    pub fn set_backend(backend: Arc<dyn ClipboardBackend>) {
+        *Self::backend_slot()
+            .write()
+            .expect("Failed to lock clipboard backend") = backend;
+    }
+
+    pub fn backend() -> Arc<dyn ClipboardBackend> {
+        Arc::clone(
+            &Self::backend_slot()
+                .read()
+                .expect("Failed to lock clipboard backend"),
+        )
+    }
+
+    fn backend_slot() -> &'static RwLock<Arc<dyn ClipboardBackend>> {
+        BACKEND.get_or_init(|| RwLock::new(Arc::new(OsClipboard)))
+    }
+
+    /// Put `text` on the clipboard and, with `clear_after`, clear it on a
+    /// thread once that has passed, unless something else was copied in
+    /// the meantime. The thread dies with the process, so a short-lived
+    /// one should `wait_pending` before it exits.
+    pub fn copy(text: &str, clear_after: Option<Duration>) -> Result<(), String> {
+        let backend = Self::backend();
+        backend.set(text)?;
+        let Some(delay) = clear_after else {
+            return Ok(());
+        };
+        let copied = Zeroizing::new(text.to_string());
+        let handle = thread::spawn(move || {
+            thread::sleep(delay);
+            // a clipboard that cannot be read is cleared all the same
+            let current = backend.get().map(|text| text.map(Zeroizing::new));
+            if !matches!(current, Ok(Some(ref text)) if *text != copied) {
+                let _ = backend.clear();
+            }
+        });
+        let mut pending = PENDING.lock().expect("Failed to lock clipboard clears");
+        pending.retain(|handle| !handle.is_finished());
+        pending.push(handle);
+        Ok(())
+    }
+
+    /// Block until every clear scheduled by `copy` has run.
+    pub fn wait_pending() {
+        let pending =
+            std::mem::take(&mut *PENDING.lock().expect("Failed to lock clipboard clears"));
+        for handle in pending {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::constant::{CLOCK_HIGH_WATER_STEP_SECS, CLOCK_SKEW_THRESHOLD_SECS, STORE_CLOCK_FILE};
+use crate::file_system::AegFileSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// What expiry checks do while the clock is known to be off by more than
+/// `CLOCK_SKEW_THRESHOLD_SECS`, when a wall-clock deadline cannot be
+/// trusted either way. Nothing is deleted as expired meanwhile.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClockSkewPolicy {
+    /// Keep serving items that have an expiry.
+    #[default]
+    FailOpen,
+    /// Refuse to serve items that have an expiry.
+    FailClosed,
+}
+
+impl FromStr for ClockSkewPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fail-open" | "open" => Ok(Self::FailOpen),
+            "fail-closed" | "closed" => Ok(Self::FailClosed),
+            other => Err(format!(
+                "unknown clock skew policy '{}' (expected fail-open or fail-closed)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClockSkewPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FailOpen => write!(f, "fail-open"),
+            Self::FailClosed => write!(f, "fail-closed"),
+        }
+    }
+}
+
+/// The wall clock as seen by this process when it first asked, paired
+/// with the monotonic clock, so later jumps of the wall clock show up as
+/// the two drifting apart.
+static ANCHOR: OnceLock<(Instant, SystemTime)> = OnceLock::new();
+
+/// Leases granted in this process: the monotonic deadline, and the
+/// wall-clock deadline handed out with it.
+static LEASES: OnceLock<Mutex<HashMap<String, (Instant, u64)>>> = OnceLock::new();
+
+/// Time for expiry: wall-clock seconds for deadlines that are stored, and
+/// monotonic leases for the ones granted in this process.
+///
+/// Skew is noticed two ways: the wall clock drifting from the monotonic
+/// one since this process started, and the wall clock reading earlier
+/// than the latest time any process recorded in the store's `clock` file
+/// (a jump backwards between runs).
+pub struct AegClock;
+
+impl AegClock {
+    fn anchor() -> &'static (Instant, SystemTime) {
+        ANCHOR.get_or_init(|| (Instant::now(), SystemTime::now()))
+    }
+
+    fn leases() -> &'static Mutex<HashMap<String, (Instant, u64)>> {
+        LEASES.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    fn wall_secs() -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        }
+    }
+
+    /// Wall-clock seconds since the Unix epoch, also advancing the store's
+    /// record of the latest time seen.
+    pub fn now() -> u64 {
+        let now = Self::wall_secs().max(0) as u64;
+        let recorded = Self::high_water();
+        if recorded.is_none_or(|seen| now >= seen + CLOCK_HIGH_WATER_STEP_SECS)
+            && !AegFileSystem::is_read_only()
+        {
+            let _ = AegFileSystem::storage().write(&Self::clock_path(), now.to_string().as_bytes());
+        }
+        now
+    }
+
+    fn clock_path() -> std::path::PathBuf {
+        AegFileSystem::get_config_path().join(STORE_CLOCK_FILE)
+    }
+
+    fn high_water() -> Option<u64> {
+        let bytes = AegFileSystem::storage().read(&Self::clock_path()).ok()?;
+        String::from_utf8(bytes).ok()?.trim().parse().ok()
+    }
+
+    /// How far the wall clock is off, in seconds: positive when it jumped
+    /// ahead, negative when it went back. Whichever of the two checks
+    /// shows more wins; small differences are normal and also reported.
+    pub fn skew() -> i64 {
+        let (mono, wall) = Self::anchor();
+        let wall_elapsed = match SystemTime::now().duration_since(*wall) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let in_process = wall_elapsed - mono.elapsed().as_secs() as i64;
+        let behind_record = Self::high_water()
+            .map(|seen| (Self::wall_secs() - seen as i64).min(0))
+            .unwrap_or(0);
+        if in_process.abs() >= behind_record.abs() {
+            in_process
+        } else {
+            behind_record
+        }
+    }
+
+    /// The skew, when it is more than `CLOCK_SKEW_THRESHOLD_SECS`.
+    pub fn skew_detected() -> Option<i64> {
+        let skew = Self::skew();
+        (skew.unsigned_abs() > CLOCK_SKEW_THRESHOLD_SECS).then_some(skew)
+    }
+
+    /// A warning for `status` while skew is detected.
+    pub fn skew_warning() -> Option<String> {
+        Self::skew_detected().map(|skew| {
+            format!(
+                "System clock is {} s {} (policy {}); expiry is not enforced from the wall clock until it settles",
+                skew.unsigned_abs(),
+                if skew > 0 {
+                    "ahead of the time this process measured"
+                } else {
+                    "behind the latest time recorded in the store"
+                },
+                AegFileSystem::read_store_config().clock_skew
+            )
+        })
+    }
+
+    /// Grant `name` a lease of `ttl`, timed on the monotonic clock for the
+    /// life of this process. Returns the wall-clock deadline to store.
+    pub fn lease(name: &str, ttl: Duration) -> u64 {
+        let deadline = Self::now() + ttl.as_secs();
+        Self::leases()
+            .lock()
+            .expect("Failed to lock leases")
+            .insert(name.to_string(), (Instant::now() + ttl, deadline));
+        deadline
+    }
+
+    pub fn release(name: &str) {
+        Self::leases()
+            .lock()
+            .expect("Failed to lock leases")
+            .remove(name);
+    }
+
+    /// Whether `name`, stored with wall-clock deadline `deadline`, has
+    /// expired. A lease granted in this process is decided by the
+    /// monotonic clock, whatever the wall clock says, as long as the
+    /// stored deadline is still the one it was granted with; a deadline
+    /// changed since (by another process) drops the lease. Otherwise the
+    /// wall clock decides, unless skew is detected: then `Ok(false)` under
+    /// fail-open and an error under fail-closed.
+    pub fn expired(name: &str, deadline: u64) -> Result<bool, String> {
+        {
+            let mut leases = Self::leases().lock().expect("Failed to lock leases");
+            match leases.get(name) {
+                Some((at, granted)) if *granted == deadline => return Ok(Instant::now() >= *at),
+                Some(_) => {
+                    leases.remove(name);
+                }
+                None => {}
+            }
+        }
+        let Some(skew) = Self::skew_detected() else {
+            return Ok(Self::now() >= deadline);
+        };
+        match AegFileSystem::read_store_config().clock_skew {
+            ClockSkewPolicy::FailOpen => Ok(false),
+            ClockSkewPolicy::FailClosed => Err(format!(
+                "the system clock is off by {} s, so '{}' may have expired (clock skew policy is fail-closed)",
+                skew, name
+            )),
+        }
+    }
+}
@@ -1,20 +1,77 @@
-use clap::{Args, Subcommand};
+use clap::{ArgAction, Args, Subcommand};
+use crate::clock::ClockSkewPolicy;
+use crate::constant::{DEFAULT_BACKUP_GENERATIONS, DEFAULT_COMPRESS_MIN_BYTES, DEFAULT_HSM_KEY_LABEL};
+use crate::crypto::{Cipher, SecretFormat};
+use crate::hook::Shell;
+use crate::keyring::KeyBackend;
+use crate::lint::LintLevel;
+use crate::merge::MergeStrategy;
+use crate::naming::KeyConvention;
+use crate::plain::{ExportFormat, PlainFormat};
+use crate::sync::SyncStrategy;
+use crate::terminal_log::TerminalSubscriber;
+use crate::verbosity::Verbosity;
+use crate::wire::{OutputFormat, ValueFormat};
 use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::PathBuf;
+
+// GLOBAL
+/// Options shared by every subcommand; flatten into the top-level parser,
+/// pass `home` to `AegFileSystem::set_base_dir` and `read_only` to
+/// `AegCore::set_read_only` before running the command,
+/// call `install_subscriber` (or install a tracing subscriber of your own
+/// filtered to `verbosity().level_filter()`) and print the command's response with `AegisrResponse::render(output)`.
+#[derive(Args, Debug)]
+pub struct StoreArgs {
+    #[arg(long, global = true, help = "Store directory (overrides AEGISR_HOME and ~/.aegisr)")]
+    pub home: Option<PathBuf>,
+    #[arg(long, global = true, help = "Named profile to use (see `profile list`)")]
+    pub profile: Option<String>,
+    #[arg(long, global = true, help = "Ignore any .aegisr.toml project manifest")]
+    pub no_project: bool,
+    #[arg(long, global = true, help = "Open the store read-only: refuse every change and never write to it")]
+    pub read_only: bool,
+    #[arg(short, long, global = true, action = ArgAction::Count, help = "More output (-v, -vv, -vvv)")]
+    pub verbose: u8,
+    #[arg(short, long, global = true, conflicts_with = "verbose", help = "Only print errors")]
+    pub quiet: bool,
+    #[arg(long, global = true, default_value = "plain", help = "Result format (plain, table or json)")]
+    pub output: OutputFormat,
+}
+
+impl StoreArgs {
+    /// Level to pass to library calls and to filter the tracing subscriber.
+    pub fn verbosity(&self) -> Verbosity {
+        Verbosity::from_flags(self.verbose, self.quiet)
+    }
+
+    /// Print the library's `tracing` events to standard error at
+    /// `verbosity()`. Returns false when a global subscriber was already set.
+    pub fn install_subscriber(&self) -> bool {
+        let subscriber = TerminalSubscriber::new(self.verbosity().level_filter());
+        tracing::subscriber::set_global_default(subscriber).is_ok()
+    }
+}
 
 // INIT
 #[derive(Args, Debug)]
 pub struct InitArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
     #[arg(short, long, help = "Reset configuration files")]
     pub reset: bool,
+    #[arg(long, help = "Bind the store to this machine's identifier")]
+    pub bind_machine: bool,
+    #[arg(long, help = "Cipher for collection files (aes-gcm or chacha20)")]
+    pub cipher: Option<Cipher>,
+    #[arg(long, help = "Where to keep the authorization key (file, keyring or hardware)")]
+    pub key_backend: Option<KeyBackend>,
+    #[arg(long, help = "Challenge-response slot of the hardware token (default: 2)")]
+    pub token_slot: Option<u8>,
 }
 
 // USE
 #[derive(Args, Debug)]
 pub struct UseArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
     #[arg(help = "Name of the collection to activate")]
     pub name: String,
 }
@@ -22,8 +79,6 @@ pub struct UseArgs {
 // NEW
 #[derive(Args, Debug)]
 pub struct NewArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
     #[arg(help = "Name of the new collection to create")]
     pub name: String,
 }
@@ -31,8 +86,6 @@ pub struct NewArgs {
 // DELETE
 #[derive(Args, Debug)]
 pub struct DeleteArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
     #[arg(help = "Name of the collection to delete")]
     pub name: String,
 }
@@ -40,44 +93,801 @@ pub struct DeleteArgs {
 // RENAME
 #[derive(Args, Debug)]
 pub struct RenameArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
     #[arg(help = "Name of the collection to rename")]
     pub name: String,
     #[arg(help = "New name for the collection")]
     pub new_name: String,
 }
 
+// CLONE
+#[derive(Args, Debug)]
+pub struct CloneArgs {
+    #[arg(help = "Collection to copy")]
+    pub src: String,
+    #[arg(help = "Name of the new collection")]
+    pub dest: String,
+}
+
+// STATS
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[arg(help = "Collection to report on (defaults to the active collection)")]
+    pub collection: Option<String>,
+    #[arg(long, conflicts_with = "collection", help = "Report on every collection")]
+    pub all: bool,
+}
+
+// METRICS
+#[derive(Args, Debug)]
+pub struct MetricsArgs {
+    #[arg(long, help = "Print in the Prometheus text format")]
+    pub prometheus: bool,
+}
+
+// MERGE
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    #[arg(help = "Collection to copy keys from")]
+    pub src: String,
+    #[arg(help = "Collection to merge the keys into")]
+    pub dest: String,
+    #[arg(
+        long,
+        default_value = "fail-on-conflict",
+        help = "How to settle keys both hold with different values (keep-dest, overwrite or fail-on-conflict)"
+    )]
+    pub strategy: MergeStrategy,
+}
+
+// AUTOSAVE
+#[derive(Args, Debug)]
+pub struct AutosaveArgs {
+    #[arg(help = "Name of the collection to configure")]
+    pub name: String,
+    #[arg(long, help = "Exclude the collection from background saves")]
+    pub off: bool,
+}
+
+// NAMING
+#[derive(Args, Debug)]
+pub struct NamingArgs {
+    #[arg(help = "Name of the collection to configure")]
+    pub name: String,
+    #[arg(conflicts_with = "off", help = "Convention for new keys (snake, kebab, screaming or regex:<pattern>)")]
+    pub convention: Option<KeyConvention>,
+    #[arg(long, help = "Allow any key name again")]
+    pub off: bool,
+    #[arg(long, help = "List existing keys breaking the convention with suggested names")]
+    pub fix: bool,
+}
+
+// SEAL
+#[derive(Args, Debug)]
+pub struct SealArgs {
+    #[arg(help = "Name of the collection to configure")]
+    pub name: String,
+    #[arg(long, help = "Keep the values in plaintext in memory again")]
+    pub off: bool,
+    #[arg(
+        long,
+        conflicts_with = "off",
+        help = "Instead, seal the collection with a passphrase of its own"
+    )]
+    pub passphrase: Option<String>,
+}
+
+// UNSEAL
+#[derive(Args, Debug)]
+pub struct UnsealArgs {
+    #[arg(help = "Name of the sealed collection")]
+    pub name: String,
+    #[arg(long, help = "The collection's passphrase (prompted for if omitted)")]
+    pub passphrase: Option<String>,
+}
+
+// EXPORT
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, help = "Export the whole store re-encrypted without machine binding")]
+    pub portable: bool,
+    #[arg(short, long, help = "Collection to export (defaults to the active one)")]
+    pub collection: Option<String>,
+    #[arg(long, help = "Bundle password (prompted for if omitted)")]
+    pub password: Option<String>,
+    #[arg(long = "recipient", help = "Seal to an ssh-ed25519 public key or .pub file instead of a password (repeatable)")]
+    pub recipients: Vec<String>,
+    #[arg(long, help = "Write the active collection unencrypted")]
+    pub plain: bool,
+    #[arg(long, default_value = "json", help = "Plain export format (json, csv or dotenv), or html for a passphrase-protected viewer page")]
+    pub format: ExportFormat,
+    #[arg(long, help = "Confirm writing secrets unencrypted")]
+    pub yes: bool,
+    #[arg(help = "Destination path")]
+    pub path: String,
+}
+
+// IMPORT
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    #[arg(long, help = "Bundle password (prompted for if omitted)")]
+    pub password: Option<String>,
+    #[arg(short, long, help = "SSH private key opening a bundle sealed to recipients")]
+    pub identity: Option<String>,
+    #[arg(long, help = "Private key (from `keypair new`) or its file, opening a bundle from `share`")]
+    pub key: Option<String>,
+    #[arg(long, help = "Import an unencrypted file into the active collection")]
+    pub plain: bool,
+    #[arg(long, default_value = "json", help = "Plain import format (json, csv or dotenv)")]
+    pub format: PlainFormat,
+    #[arg(help = "Path of the bundle to import")]
+    pub path: String,
+}
+
+// IMPORT ENV
+#[derive(Args, Debug)]
+pub struct ImportEnvArgs {
+    #[arg(help = "Path of the .env file")]
+    pub path: String,
+    #[arg(long, help = "Collection to import into (defaults to the active one)")]
+    pub collection: Option<String>,
+}
+
+// DURESS
+#[derive(Args, Debug)]
+pub struct DuressArgs {
+    #[arg(long, help = "Duress passphrase (prompted for if omitted)")]
+    pub passphrase: Option<String>,
+}
+
+// PASSWD
+#[derive(Args, Debug)]
+pub struct PasswdArgs {
+    #[arg(long, help = "Current passphrase, if the store has one (prompted for if omitted)")]
+    pub current: Option<String>,
+    #[arg(long, help = "New passphrase (prompted for if omitted)")]
+    pub new: Option<String>,
+    #[arg(long, conflicts_with = "new", help = "Remove the passphrase instead of changing it")]
+    pub remove: bool,
+}
+
+// NUKE
+#[derive(Args, Debug)]
+pub struct NukeArgs {
+    #[arg(long, help = "Confirmation token to type back (prompted for if omitted)")]
+    pub confirm: Option<String>,
+    #[arg(long, default_value_t = crate::constant::NUKE_CONFIRM_DELAY_SECS, help = "Seconds to wait before wiping, to allow aborting")]
+    pub delay: u64,
+}
+
+/// Pass the value through `read_value` so that `-` takes it from stdin.
 #[derive(Args, Debug)]
 pub struct PutArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
-    #[arg(help = "Key to store in the active collection")]
+    #[arg(help = "Key to store in the active collection, or collection::key")]
     pub key: String,
-    #[arg(help = "Value to associate with the key")]
+    #[arg(help = "Value to associate with the key, or - to read it from stdin")]
     pub value: String,
+    #[arg(long, help = "Environment variable name to export the key under")]
+    pub env_name: Option<String>,
+    #[arg(long, help = "The value is a JSON document; reject it if it does not parse")]
+    pub json: bool,
+}
+
+impl PutArgs {
+    /// `value`, or everything read from `stdin` when it is `-`, byte for
+    /// byte (a trailing newline is kept), so files round-trip through
+    /// `get --raw` and secrets stay out of shell history.
+    pub fn read_value(&self, mut stdin: impl Read) -> Result<String, String> {
+        if self.value != "-" {
+            return Ok(self.value.clone());
+        }
+        let mut bytes = Vec::new();
+        stdin
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("could not read the value from stdin: {}", e))?;
+        String::from_utf8(bytes).map_err(|_| "the value on stdin is not valid UTF-8".to_string())
+    }
 }
 
+/// With `raw`, print `AegisrResponse::raw` as is instead of `render`; with
+/// `as_format`, print `AegisrResponse::value_as` (a conversion error goes
+/// to stderr like any other). Send `AegisrCommand::GetMany` when `more`
+/// holds further keys.
 #[derive(Args, Debug)]
 pub struct GetArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
-    #[arg(help = "Key to retrieve from the active collection")]
+    #[arg(help = "Key to retrieve from the active collection, or collection::key")]
     pub key: String,
+    #[arg(help = "Further keys to retrieve, each optionally collection::key")]
+    pub more: Vec<String>,
+    #[arg(long, help = "Read from the on-disk index, bypassing the in-memory cache")]
+    pub no_cache: bool,
+    #[arg(long, help = "Print only the value, with no trailing newline, for piping")]
+    pub raw: bool,
+    #[arg(long = "as", value_name = "FORMAT", conflicts_with = "raw", help = "Print a JSON value as json, yaml or toml (raw prints it as stored)")]
+    pub as_format: Option<ValueFormat>,
+    #[arg(long, conflicts_with_all = ["more", "raw", "as_format"], help = "Copy the value to the clipboard instead of printing it")]
+    pub clip: bool,
+    #[arg(long, value_name = "SECS", requires = "clip", help = "Clear the clipboard after this many seconds (default: 30, 0 keeps it)")]
+    pub clear_after: Option<u64>,
 }
 
+/// Send `AegisrCommand::DelMany` when `more` holds further keys.
 #[derive(Args, Debug)]
 pub struct DelArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
-    #[arg(help = "Key to delete from the active collection")]
+    #[arg(help = "Key to delete from the active collection, or collection::key")]
+    pub key: String,
+    #[arg(help = "Further keys to delete, each optionally collection::key")]
+    pub more: Vec<String>,
+    #[arg(short, long, help = "Delete every key under each path (app/ deletes app/db/password) instead")]
+    pub recursive: bool,
+}
+
+// MV
+#[derive(Args, Debug)]
+pub struct MvArgs {
+    #[arg(help = "Key to rename in the active collection")]
+    pub key: String,
+    #[arg(help = "New name for the key")]
+    pub new_key: String,
+}
+
+// LINT
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    #[arg(help = "What put does with suspicious values (off, warn or deny)")]
+    pub level: LintLevel,
+}
+
+// CLOCK SKEW
+#[derive(Args, Debug)]
+pub struct ClockSkewArgs {
+    #[arg(help = "What expiry does while the clock is off (fail-open or fail-closed)")]
+    pub policy: ClockSkewPolicy,
+}
+
+// COMPRESS
+#[derive(Args, Debug)]
+pub struct CompressArgs {
+    #[arg(long, default_value_t = DEFAULT_COMPRESS_MIN_BYTES, help = "Only compress collections at least this large")]
+    pub min_bytes: usize,
+    #[arg(long, help = "Stop compressing collection files")]
+    pub off: bool,
+}
+
+// BACKUPS
+#[derive(Args, Debug)]
+pub struct BackupsArgs {
+    #[arg(long, default_value_t = DEFAULT_BACKUP_GENERATIONS, help = "Previous versions of each collection file to keep")]
+    pub keep: usize,
+    #[arg(long, help = "Stop taking backups before saves")]
+    pub off: bool,
+}
+
+// RESTORE
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    #[arg(help = "Collection to roll back")]
+    pub collection: String,
+    #[arg(long = "gen", default_value_t = 1, help = "Backup generation to restore (1 is the newest)")]
+    pub generation: usize,
+}
+
+// HSM
+#[derive(Args, Debug)]
+pub struct HsmArgs {
+    #[arg(long, help = "PKCS#11 module of the HSM or smartcard to bind the store key to")]
+    pub module: Option<String>,
+    #[arg(long, help = "Token slot (defaults to the first slot with a token)")]
+    pub slot: Option<u64>,
+    #[arg(long, default_value = DEFAULT_HSM_KEY_LABEL, help = "Label of the HMAC secret key on the token")]
+    pub key_label: String,
+    #[arg(long, help = "Unbind the store key from the HSM")]
+    pub off: bool,
+}
+
+// DUPLICATES
+#[derive(Args, Debug)]
+pub struct DuplicatesArgs {
+    #[arg(long, help = "Stop warning about reused values")]
+    pub off: bool,
+}
+
+// CLEAR SNAPSHOT
+#[derive(Args, Debug)]
+pub struct ClearSnapshotArgs {
+    #[arg(long, help = "Let clear wipe collections without snapshotting first")]
+    pub off: bool,
+}
+
+// TAG
+#[derive(Args, Debug)]
+pub struct TagArgs {
+    #[arg(help = "Key to tag in the active collection")]
+    pub key: String,
+    #[arg(help = "Tag to add (e.g. prod)")]
+    pub tag: String,
+    #[arg(long, help = "Remove the tag instead of adding it")]
+    pub remove: bool,
+}
+
+// KEYS
+#[derive(Args, Debug)]
+pub struct KeysArgs {
+    #[arg(long, help = "Only list keys carrying this tag")]
+    pub tag: Option<String>,
+    #[arg(short, long, conflicts_with = "tag", help = "Collection to list (defaults to the active one)")]
+    pub collection: Option<String>,
+}
+
+// TREE
+#[derive(Args, Debug)]
+pub struct TreeArgs {
+    #[arg(help = "Only show keys under this path, optionally collection::path")]
+    pub path: Option<String>,
+}
+
+// SEARCH
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[arg(help = "Text to look for in key names")]
+    pub pattern: String,
+    #[arg(long, help = "Treat the pattern as a regular expression")]
+    pub regex: bool,
+    #[arg(long, help = "Also match the pattern against values")]
+    pub values: bool,
+    #[arg(long, help = "Search every collection instead of the active one")]
+    pub all_collections: bool,
+}
+
+// DUMP
+#[derive(Args, Debug)]
+pub struct DumpArgs {
+    #[arg(short, long, help = "Collection to dump (defaults to the active one)")]
+    pub collection: Option<String>,
+    #[arg(long, help = "Confirm printing every value in the clear")]
+    pub show_values: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    #[arg(help = "Key to show the recorded values of")]
+    pub key: String,
+}
+
+#[derive(Args, Debug)]
+pub struct RollbackArgs {
+    #[arg(help = "Key to roll back")]
+    pub key: String,
+    #[arg(help = "Version to restore (see `history`)")]
+    pub version: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct PendingArgs {
+    #[arg(help = "Collection to inspect (defaults to every loaded collection)")]
+    pub name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CaptureEnvArgs {
+    #[arg(long, help = "Only capture variables whose name starts with this prefix")]
+    pub prefix: Option<String>,
+}
+
+// ENV
+#[derive(Args, Debug)]
+pub struct EnvArgs {
+    #[arg(long, help = "Prepend this prefix to every variable name")]
+    pub prefix: Option<String>,
+    #[arg(long, help = "Print a .env file instead of export statements")]
+    pub dotenv: bool,
+}
+
+// EXEC
+/// Run with `AegEnv::exec` and exit with the code it returns.
+#[derive(Args, Debug)]
+pub struct ExecArgs {
+    #[arg(long, help = "Prepend this prefix to every variable name")]
+    pub prefix: Option<String>,
+    #[arg(last = true, required = true, help = "Command to run, after --")]
+    pub command: Vec<String>,
+}
+
+// SERVE
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    #[arg(long, default_value_t = 7878, help = "Port to listen on (127.0.0.1 only)")]
+    pub port: u16,
+    #[arg(long, help = "Print the bearer token clients must send and exit")]
+    pub print_token: bool,
+}
+
+// HOOK
+#[derive(Args, Debug)]
+pub struct HookArgs {
+    #[arg(help = "Shell to integrate with (bash, zsh or fish)")]
+    pub shell: Shell,
+    #[arg(long, help = "Print the export/unset statements for the current directory")]
+    pub emit: bool,
+}
+
+// WATCH
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    #[arg(help = "Only report keys starting with this prefix")]
+    pub prefix: Option<String>,
+    #[arg(long, default_value_t = 1000, help = "How often to check the store, in milliseconds")]
+    pub interval_ms: u64,
+}
+
+// LOADTEST
+#[derive(Args, Debug)]
+pub struct LoadtestArgs {
+    #[arg(long, default_value_t = 4, help = "Number of worker threads")]
+    pub workers: usize,
+    #[arg(long, default_value_t = 10_000, help = "Operations per worker")]
+    pub ops: usize,
+    #[arg(long, default_value_t = 1_000, help = "Number of distinct keys")]
+    pub keys: usize,
+    #[arg(long, default_value_t = 64, help = "Value size in bytes")]
+    pub value_size: usize,
+    #[arg(long, default_value_t = 80, help = "Percentage of reads")]
+    pub read_pct: u8,
+    #[arg(long, default_value_t = 15, help = "Percentage of puts (the rest are deletes)")]
+    pub put_pct: u8,
+    #[arg(long, default_value_t = 1000, help = "Saver interval in milliseconds (0 disables saving)")]
+    pub saver_interval_ms: u64,
+}
+
+// PROFILE
+#[derive(Args, Debug)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileNameArgs {
+    #[arg(help = "Name of the profile")]
+    pub name: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommands {
+    #[command(about = "Create a new profile with its own isolated store")]
+    New(ProfileNameArgs),
+    #[command(about = "List all profiles")]
+    List,
+    #[command(about = "Shred a profile and everything in it")]
+    Delete(ProfileNameArgs),
+}
+
+// KEY
+#[derive(Args, Debug)]
+pub struct KeyArgs {
+    #[command(subcommand)]
+    pub command: KeyCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct KeyCheckArgs {
+    #[arg(long, help = "Replace key material longer than a key with a key derived from it (HKDF)")]
+    pub rederive: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeyCommands {
+    #[command(about = "Check the authorization key's encoding and length, and that it opens the store")]
+    Check(KeyCheckArgs),
+}
+
+// BACKUP
+#[derive(Args, Debug)]
+pub struct BackupArgs {
+    #[arg(help = "Directory to write the backup to (must be empty or absent)")]
+    pub path: String,
+}
+
+// SYNC
+#[derive(Args, Debug)]
+pub struct SyncArgs {
+    #[arg(help = "Other store: a store directory, or s3://bucket/prefix with the s3 feature")]
+    pub target: String,
+    #[arg(
+        long,
+        default_value = "last-writer-wins",
+        help = "How to settle keys whose values differ (last-writer-wins, prefer-local or prefer-remote)"
+    )]
+    pub strategy: SyncStrategy,
+}
+
+// GEN
+#[derive(Args, Debug)]
+pub struct GenArgs {
+    #[arg(long, default_value = "chars", help = "What to generate (chars, passphrase, hex or base64)")]
+    pub format: SecretFormat,
+    #[arg(short, long, help = "Characters, passphrase words or bytes (default: 24, 6 or 32)")]
+    pub length: Option<usize>,
+    #[arg(long, help = "Include symbols")]
+    pub symbols: bool,
+    #[arg(long, help = "Leave out lowercase letters")]
+    pub no_lowercase: bool,
+    #[arg(long, help = "Leave out uppercase letters")]
+    pub no_uppercase: bool,
+    #[arg(long, help = "Leave out digits")]
+    pub no_digits: bool,
+    #[arg(long, default_value = "-", help = "Separator between passphrase words")]
+    pub separator: String,
+    #[arg(long, value_name = "KEY", help = "Store the secret under KEY instead of printing it")]
+    pub save: Option<String>,
+}
+
+// TOTP
+#[derive(Args, Debug)]
+pub struct TotpArgs {
+    #[arg(help = "Key holding an otpauth://totp/ URI or base32 secret (collection::key or key)")]
     pub key: String,
 }
 
+// SNIPPET
+#[derive(Args, Debug)]
+pub struct SnippetArgs {
+    #[command(subcommand)]
+    pub command: SnippetCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct SnippetAddArgs {
+    #[arg(help = "Text to stash")]
+    pub text: String,
+    #[arg(long, help = "Delete the snippet after this long (e.g. 90s, 30m, 12h, 7d)")]
+    pub expires: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct SnippetIdArgs {
+    #[arg(help = "ID of the snippet")]
+    pub id: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnippetCommands {
+    #[command(about = "Stash text under a generated ID and print the ID")]
+    Add(SnippetAddArgs),
+    #[command(about = "Print the text of a snippet")]
+    Get(SnippetIdArgs),
+    #[command(about = "List snippet IDs and expiry times, without their text")]
+    List,
+    #[command(visible_alias = "rm", about = "Delete a snippet")]
+    Delete(SnippetIdArgs),
+}
+
+// EMERGENCY
+#[derive(Args, Debug)]
+pub struct EmergencyArgs {
+    #[command(subcommand)]
+    pub command: EmergencyCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct EmergencySetupArgs {
+    #[arg(short, long, help = "Collection to grant (defaults to the active one)")]
+    pub collection: Option<String>,
+    #[arg(long = "contact", required = true, help = "The contact's ssh-ed25519 public key or .pub file (repeatable)")]
+    pub contacts: Vec<String>,
+    #[arg(long, default_value = "7d", help = "Waiting period after a request (e.g. 12h, 7d)")]
+    pub wait: String,
+    #[arg(help = "Where to write the bundle to give the contact")]
+    pub path: String,
+}
+
+#[derive(Args, Debug)]
+pub struct EmergencyIdArgs {
+    #[arg(help = "ID of the emergency grant")]
+    pub id: String,
+}
+
+#[derive(Args, Debug)]
+pub struct EmergencyContactArgs {
+    #[arg(short, long, help = "SSH private key the bundle was sealed to")]
+    pub identity: String,
+    #[arg(help = "Path of the emergency bundle")]
+    pub path: String,
+}
+
 #[derive(Args, Debug)]
-pub struct ClearArgs {
-    #[arg(short, long, help = "Enable verbose output")]
-    pub verbose: bool,
+pub struct EmergencyImportArgs {
+    #[arg(short, long, help = "SSH private key the bundle was sealed to")]
+    pub identity: String,
+    #[arg(long, help = "Escrow key released by `emergency release`")]
+    pub key: Option<String>,
+    #[arg(help = "Path of the emergency bundle")]
+    pub path: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EmergencyCommands {
+    #[command(about = "Write a bundle of a collection that a trusted contact can open after a waiting period")]
+    Setup(EmergencySetupArgs),
+    #[command(about = "As the contact, start the waiting period of the grant your bundle names")]
+    Request(EmergencyContactArgs),
+    #[command(about = "Cancel a pending request")]
+    Deny(EmergencyIdArgs),
+    #[command(about = "As the contact, print the escrow key of your bundle once its waiting period has passed")]
+    Release(EmergencyContactArgs),
+    #[command(about = "Delete a grant, so its bundle can no longer be opened")]
+    Revoke(EmergencyIdArgs),
+    #[command(about = "List grants and pending requests")]
+    List,
+    #[command(about = "As the contact, import a bundle with your key and the released escrow key")]
+    Import(EmergencyImportArgs),
+}
+
+// KEYPAIR
+#[derive(Args, Debug)]
+pub struct KeypairArgs {
+    #[command(subcommand)]
+    pub command: KeypairCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct KeypairNewArgs {
+    #[arg(short, long, help = "Write the private key to this new file, readable only by you, instead of printing it")]
+    pub output: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeypairCommands {
+    #[command(about = "Create an X25519 key pair; give the public key to whoever shares collections with you")]
+    New(KeypairNewArgs),
+}
+
+// SHARE
+#[derive(Args, Debug)]
+pub struct ShareArgs {
+    #[arg(help = "Collection to share")]
+    pub collection: String,
+    #[arg(long, help = "The recipient's public key (aegpub:...) or a file holding it")]
+    pub to: String,
+    #[arg(short, long, help = "Write the bundle to this file instead of printing it")]
+    pub output: Option<String>,
+}
+
+// FEDERATION
+#[derive(Args, Debug)]
+pub struct FedArgs {
+    #[command(subcommand)]
+    pub command: FedCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct FedAddArgs {
+    #[arg(help = "Name to address the store by, as in name::collection::key")]
+    pub name: String,
+    #[arg(help = "Directory of the store")]
+    pub dir: String,
+    #[arg(long, conflicts_with = "passphrase_env", help = "Environment variable holding the store's key, instead of its key file")]
+    pub key_env: Option<String>,
+    #[arg(long, help = "Environment variable holding the store's passphrase")]
+    pub passphrase_env: Option<String>,
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true, help = "Lookup order for unqualified keys; lower goes first")]
+    pub precedence: i32,
+}
+
+#[derive(Args, Debug)]
+pub struct FedNameArgs {
+    #[arg(help = "Name of the federated store")]
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct FedGetArgs {
+    #[arg(help = "store::collection::key, collection::key or key")]
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct FedPutArgs {
+    #[arg(help = "store::collection::key, collection::key or key")]
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FedCommands {
+    #[command(about = "Register a store, or update a registered one")]
+    Add(FedAddArgs),
+    #[command(visible_alias = "rm", about = "Unregister a store, leaving its files alone")]
+    Remove(FedNameArgs),
+    #[command(about = "List registered stores in lookup order")]
+    List,
+    #[command(about = "Read a key through the federation")]
+    Get(FedGetArgs),
+    #[command(about = "Write a key through the federation")]
+    Put(FedPutArgs),
+    #[command(about = "List every key of every store as store::collection::key")]
+    Keys,
+}
+
+// SNAPSHOT
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+    #[command(subcommand)]
+    pub command: SnapshotCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotLabelArgs {
+    #[arg(help = "Label of the snapshot")]
+    pub label: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommands {
+    #[command(about = "Copy every collection into a new labelled snapshot")]
+    Create(SnapshotLabelArgs),
+    #[command(about = "List all snapshots")]
+    List,
+    #[command(about = "Replace the store with a snapshot, discarding later changes")]
+    Restore(SnapshotRestoreArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotRestoreArgs {
+    #[arg(required_unless_present = "last", help = "Label of the snapshot")]
+    pub label: Option<String>,
+    #[arg(long, conflicts_with = "label", help = "Restore the most recent snapshot (such as the one taken before `clear`)")]
+    pub last: bool,
+}
+
+// AUDIT
+#[derive(Args, Debug)]
+pub struct AuditArgs {
+    #[command(subcommand)]
+    pub command: AuditCommands,
+}
+
+#[derive(Args, Debug)]
+pub struct AuditVerifyArgs {
+    #[arg(long, help = "Also check the chain still contains a head printed by `audit anchor` (<seq>:<hash>)")]
+    pub anchor: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct AuditLogArgs {
+    #[arg(long, help = "Only entries at or after this time (Unix seconds or YYYY-MM-DD)")]
+    pub since: Option<String>,
+    #[arg(long, help = "Only entries at or before this time (Unix seconds or YYYY-MM-DD)")]
+    pub until: Option<String>,
+    #[arg(short, long, help = "Only entries of this collection")]
+    pub collection: Option<String>,
+    #[arg(short, long, help = "Only entries for this key")]
+    pub key: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct AuditExportArgs {
+    #[arg(long, default_value = "csv", help = "Report format (csv or json)")]
+    pub format: PlainFormat,
+    #[arg(long, help = "Only entries at or after this time (Unix seconds or YYYY-MM-DD)")]
+    pub from: Option<String>,
+    #[arg(long, help = "Only entries at or before this time (Unix seconds or YYYY-MM-DD)")]
+    pub to: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuditCommands {
+    #[command(about = "Start recording every change in the audit log")]
+    Enable,
+    #[command(about = "Stop recording changes; the existing log is kept")]
+    Disable,
+    #[command(about = "Show the audit log, optionally filtered by time, collection or key")]
+    Log(AuditLogArgs),
+    #[command(about = "Check that no audit log entry was changed or removed")]
+    Verify(AuditVerifyArgs),
+    #[command(about = "Print the head of the audit chain for external timestamping")]
+    Anchor,
+    #[command(about = "Write an access report of who read or changed which keys, and when")]
+    Export(AuditExportArgs),
 }
 
 // ===========================
@@ -98,33 +908,531 @@ pub enum Commands {
     Delete(DeleteArgs),
     #[command(about = "Rename an existing collection")]
     Rename(RenameArgs),
+    #[command(about = "Copy a collection, with its settings and key history, into a new collection")]
+    Clone(CloneArgs),
+    #[command(about = "Copy the keys of one collection into another, settling conflicts by a strategy")]
+    Merge(MergeArgs),
+    #[command(about = "Show entry counts, sizes and save state of collections")]
+    Stats(StatsArgs),
+    #[command(about = "Show operation counts and latencies of this process (puts, gets, cache, saves)")]
+    Metrics(MetricsArgs),
+    #[command(about = "Enable or disable background saving for a collection")]
+    Autosave(AutosaveArgs),
+    #[command(about = "Enforce a naming convention on the keys of a collection")]
+    Naming(NamingArgs),
+    #[command(about = "Keep a collection's values encrypted in memory until they are read")]
+    Seal(SealArgs),
+    #[command(about = "Unseal a collection sealed with a passphrase for this session")]
+    Unseal(UnsealArgs),
+    #[command(about = "Export a collection as an encrypted bundle, or the whole store")]
+    Export(ExportArgs),
+    #[command(about = "Import a collection from an encrypted bundle")]
+    Import(ImportArgs),
+    #[command(about = "Import the pairs of a .env file into a collection")]
+    ImportEnv(ImportEnvArgs),
+    #[command(about = "Create or replace the decoy store opened by a duress passphrase")]
+    Duress(DuressArgs),
+    #[command(about = "Set, change or remove the store passphrase, re-encrypting every file")]
+    Passwd(PasswdArgs),
+    #[command(about = "Securely shred the entire store after confirmation")]
+    Nuke(NukeArgs),
     #[command(about = "Show the current status")]
     Status,
+    #[command(about = "Describe the store's files, formats, collections and saver (use --output json)")]
+    Inspect,
     #[command(about = "Store a key/value pair in the active collection")]
     Put(PutArgs),
-    #[command(about = "Retrieve the value of a key from the active collection")]
+    #[command(about = "Retrieve the value of one or more keys, from the active collection or as collection::key")]
     Get(GetArgs),
-    #[command(about = "Delete a key/value pair from the active collection")]
+    #[command(about = "Delete one or more keys, from the active collection or as collection::key")]
     Del(DelArgs),
+    #[command(about = "Rename a key in the active collection")]
+    Mv(MvArgs),
+    #[command(about = "Configure checks on values stored with put")]
+    Lint(LintArgs),
+    #[command(about = "Warn when put stores a value another key already holds")]
+    Duplicates(DuplicatesArgs),
+    #[command(about = "Choose whether expiring items are served while the system clock is skewed")]
+    ClockSkew(ClockSkewArgs),
+    #[command(about = "Snapshot the store before every clear (on by default)")]
+    ClearSnapshot(ClearSnapshotArgs),
+    #[command(about = "Compress large collections before encrypting them")]
+    Compress(CompressArgs),
+    #[command(about = "Keep previous versions of collection files, taken before every save")]
+    Backups(BackupsArgs),
+    #[command(about = "Roll a collection back to one of its backups")]
+    Restore(RestoreArgs),
+    #[command(about = "Keep the store key on an HSM or smartcard, or check the token")]
+    Hsm(HsmArgs),
+    #[command(about = "Add or remove a tag on a key")]
+    Tag(TagArgs),
+    #[command(visible_alias = "ls", about = "List the keys of the active collection")]
+    Keys(KeysArgs),
+    #[command(about = "Print keys with / in their names (app/db/password) as an indented tree")]
+    Tree(TreeArgs),
+    #[command(about = "Find keys whose name or value matches a pattern")]
+    Search(SearchArgs),
+    #[command(about = "Print every key and value of a collection")]
+    Dump(DumpArgs),
+    #[command(about = "Show the recorded values of a key")]
+    History(HistoryArgs),
+    #[command(about = "Restore an earlier value of a key")]
+    Rollback(RollbackArgs),
     #[command(about = "Clear all key/value pairs from the active collection")]
-    Clear(ClearArgs),
+    Clear,
+    #[command(about = "Show keys changed in memory but not yet saved to disk")]
+    Pending(PendingArgs),
+    #[command(about = "Store the current environment variables in the active collection")]
+    CaptureEnv(CaptureEnvArgs),
+    #[command(about = "Print the active collection as export statements or a .env file")]
+    Env(EnvArgs),
+    #[command(about = "Run a command with the active collection's keys as environment variables")]
+    Exec(ExecArgs),
+    #[command(about = "Manage named profiles")]
+    Profile(ProfileArgs),
+    #[command(about = "Inspect the store's authorization key")]
+    Key(KeyArgs),
+    #[command(about = "Copy the whole store, unsaved changes included, without pausing writers")]
+    Backup(BackupArgs),
+    #[command(about = "Two-way sync with another store, settling differing values and reporting conflicts")]
+    Sync(SyncArgs),
+    #[command(about = "Drop stale records and move files of deleted collections aside as .orphaned, reporting the bytes reclaimed")]
+    Compact,
+    #[command(about = "Decrypt every collection and check its checksums and structure, without modifying anything")]
+    Verify,
+    #[command(about = "Generate a random password, passphrase or key, optionally storing it")]
+    Gen(GenArgs),
+    #[command(about = "Show the current two-factor code for a stored otpauth:// secret")]
+    Totp(TotpArgs),
+    #[command(about = "Stash one-off secrets under generated IDs, optionally expiring")]
+    Snippet(SnippetArgs),
+    #[command(about = "Give a trusted contact access to a collection after a waiting period")]
+    Emergency(EmergencyArgs),
+    #[command(about = "Create key pairs for receiving shared collections")]
+    Keypair(KeypairArgs),
+    #[command(about = "Seal a collection to someone's public key; only their private key opens it")]
+    Share(ShareArgs),
+    #[command(about = "Address several stores (personal, team, project) as one namespace")]
+    Fed(FedArgs),
+    #[command(about = "Create, list and restore snapshots of the whole store")]
+    Snapshot(SnapshotArgs),
+    #[command(about = "Manage the hash-chained audit log of changes")]
+    Audit(AuditArgs),
+    #[command(about = "Serve the store over a local HTTP API")]
+    Serve(ServeArgs),
+    #[command(about = "Keep the store decrypted in memory and serve local clients over a unix socket")]
+    Daemon,
+    #[command(about = "Show the project manifest in effect and check its required keys")]
+    Project,
+    #[command(about = "Print a shell hook that exports project secrets on cd")]
+    Hook(HookArgs),
+    #[command(about = "Run a concurrent put/get/delete workload and report latencies")]
+    Loadtest(LoadtestArgs),
+    #[command(about = "Print changes to keys as they are saved, until interrupted")]
+    Watch(WatchArgs),
+    #[command(about = "Open an interactive prompt for put/get/del/use/ls")]
+    Shell,
 }
 
 // ===========================
 // AegisrCommand ENUM
 // ===========================
 
-#[derive(Serialize, Deserialize, Debug)]
+/// See `AegWire` for the versioned encoding; variant and field names are
+/// part of that format, so rename with `#[serde(rename)]` rather than in place.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", content = "args", rename_all = "snake_case")]
 pub enum AegisrCommand {
-    Init { verbose: bool, reset: bool },
+    Init {
+        reset: bool,
+        #[serde(default)]
+        bind_machine: bool,
+        #[serde(default)]
+        cipher: Option<Cipher>,
+        #[serde(default)]
+        key_backend: Option<KeyBackend>,
+        #[serde(default)]
+        token_slot: Option<u8>,
+    },
     List,
-    Use { verbose: bool, name: String },
-    New { verbose: bool, name: String },
-    Delete { verbose: bool, name: String },
-    Rename { verbose: bool, name: String, new_name: String },
+    Use { name: String },
+    New { name: String },
+    Delete { name: String },
+    Rename { name: String, new_name: String },
+    Clone { src: String, dest: String },
+    Merge {
+        src: String,
+        dest: String,
+        #[serde(default)]
+        strategy: MergeStrategy,
+    },
+    Stats {
+        #[serde(default)]
+        collection: Option<String>,
+        #[serde(default)]
+        all: bool,
+    },
+    Metrics {
+        #[serde(default)]
+        prometheus: bool,
+    },
+    Autosave { name: String, off: bool },
+    Naming {
+        name: String,
+        #[serde(default)]
+        convention: Option<KeyConvention>,
+        #[serde(default)]
+        off: bool,
+        #[serde(default)]
+        fix: bool,
+    },
+    Seal {
+        name: String,
+        off: bool,
+        #[serde(default)]
+        passphrase: Option<String>,
+    },
+    Unseal {
+        name: String,
+        #[serde(default)]
+        passphrase: Option<String>,
+    },
+    Export {
+        portable: bool,
+        #[serde(default)]
+        collection: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        recipients: Vec<String>,
+        #[serde(default)]
+        plain: bool,
+        #[serde(default)]
+        format: Option<ExportFormat>,
+        #[serde(default)]
+        yes: bool,
+        path: String,
+    },
+    Import {
+        password: Option<String>,
+        #[serde(default)]
+        identity: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+        #[serde(default)]
+        plain: bool,
+        #[serde(default)]
+        format: Option<PlainFormat>,
+        path: String,
+    },
+    ImportEnv {
+        path: String,
+        #[serde(default)]
+        collection: Option<String>,
+    },
+    Duress { passphrase: Option<String> },
+    Passwd {
+        #[serde(default)]
+        current: Option<String>,
+        #[serde(default)]
+        new: Option<String>,
+        #[serde(default)]
+        remove: bool,
+    },
+    Nuke { confirm: Option<String>, delay: u64 },
     Status,
-    Put { verbose: bool, key: String, value: String },
-    Get { verbose: bool, key: String },
-    Del { verbose: bool, key: String },
-    Clear { verbose: bool },
+    Inspect,
+    Put {
+        key: String,
+        value: String,
+        #[serde(default)]
+        env_name: Option<String>,
+        #[serde(default)]
+        json: bool,
+    },
+    Get {
+        key: String,
+        #[serde(default)]
+        no_cache: bool,
+        /// Copy the value to the clipboard instead of returning it.
+        #[serde(default)]
+        clip: bool,
+        #[serde(default)]
+        clear_after: Option<u64>,
+    },
+    Del { key: String },
+    /// `get` with several keys, which may name different collections.
+    GetMany {
+        keys: Vec<String>,
+        #[serde(default)]
+        no_cache: bool,
+    },
+    DelMany { keys: Vec<String> },
+    Mv { key: String, new_key: String },
+    Lint { level: LintLevel },
+    Duplicates { off: bool },
+    ClockSkew { policy: ClockSkewPolicy },
+    ClearSnapshot { off: bool },
+    Compress {
+        #[serde(default)]
+        min_bytes: Option<usize>,
+        #[serde(default)]
+        off: bool,
+    },
+    Backups {
+        #[serde(default)]
+        keep: Option<usize>,
+        #[serde(default)]
+        off: bool,
+    },
+    Restore {
+        collection: String,
+        #[serde(default)]
+        generation: Option<usize>,
+    },
+    Hsm {
+        #[serde(default)]
+        module: Option<String>,
+        #[serde(default)]
+        slot: Option<u64>,
+        #[serde(default)]
+        key_label: Option<String>,
+        #[serde(default)]
+        off: bool,
+    },
+    Tag {
+        key: String,
+        tag: String,
+        #[serde(default)]
+        remove: bool,
+    },
+    Keys {
+        #[serde(default)]
+        tag: Option<String>,
+        #[serde(default)]
+        collection: Option<String>,
+    },
+    Tree {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// `del --recursive`: every key under `path`.
+    DelTree { path: String },
+    Search {
+        pattern: String,
+        #[serde(default)]
+        regex: bool,
+        #[serde(default)]
+        values: bool,
+        #[serde(default)]
+        all_collections: bool,
+    },
+    Dump {
+        #[serde(default)]
+        collection: Option<String>,
+        #[serde(default)]
+        show_values: bool,
+    },
+    History { key: String },
+    Rollback { key: String, version: u64 },
+    Clear,
+    Pending { name: Option<String> },
+    CaptureEnv { prefix: Option<String> },
+    Env {
+        #[serde(default)]
+        prefix: Option<String>,
+        #[serde(default)]
+        dotenv: bool,
+    },
+    ProfileNew { name: String },
+    ProfileList,
+    ProfileDelete { name: String },
+    KeyCheck {
+        #[serde(default)]
+        rederive: bool,
+    },
+    Backup { path: String },
+    Sync {
+        target: String,
+        #[serde(default)]
+        strategy: SyncStrategy,
+    },
+    Compact,
+    Verify,
+    Gen {
+        #[serde(default)]
+        format: SecretFormat,
+        #[serde(default)]
+        length: Option<usize>,
+        #[serde(default)]
+        symbols: bool,
+        #[serde(default)]
+        no_lowercase: bool,
+        #[serde(default)]
+        no_uppercase: bool,
+        #[serde(default)]
+        no_digits: bool,
+        #[serde(default)]
+        separator: Option<String>,
+        #[serde(default)]
+        save: Option<String>,
+    },
+    Totp { key: String },
+    SnippetAdd {
+        text: String,
+        #[serde(default)]
+        expires: Option<String>,
+    },
+    SnippetGet { id: String },
+    SnippetList,
+    SnippetDelete { id: String },
+    EmergencySetup {
+        #[serde(default)]
+        collection: Option<String>,
+        contacts: Vec<String>,
+        wait: String,
+        path: String,
+    },
+    EmergencyRequest { identity: String, path: String },
+    EmergencyDeny { id: String },
+    EmergencyRelease { identity: String, path: String },
+    EmergencyRevoke { id: String },
+    EmergencyList,
+    EmergencyImport {
+        identity: String,
+        #[serde(default)]
+        key: Option<String>,
+        path: String,
+    },
+    KeypairNew {
+        #[serde(default)]
+        output: Option<String>,
+    },
+    Share {
+        collection: String,
+        to: String,
+        #[serde(default)]
+        output: Option<String>,
+    },
+    FedAdd {
+        name: String,
+        dir: String,
+        #[serde(default)]
+        key_env: Option<String>,
+        #[serde(default)]
+        passphrase_env: Option<String>,
+        #[serde(default)]
+        precedence: i32,
+    },
+    FedRemove { name: String },
+    FedList,
+    FedGet { name: String },
+    FedPut { name: String, value: String },
+    FedKeys,
+    SnapshotCreate { label: String },
+    SnapshotList,
+    SnapshotRestore {
+        #[serde(default)]
+        label: String,
+        #[serde(default)]
+        last: bool,
+    },
+    AuditEnable,
+    AuditDisable,
+    AuditLog {
+        #[serde(default)]
+        since: Option<String>,
+        #[serde(default)]
+        until: Option<String>,
+        #[serde(default)]
+        collection: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+    },
+    AuditVerify {
+        #[serde(default)]
+        anchor: Option<String>,
+    },
+    AuditAnchor,
+    AuditExport {
+        format: PlainFormat,
+        #[serde(default)]
+        from: Option<String>,
+        #[serde(default)]
+        to: Option<String>,
+    },
+    Serve {
+        port: u16,
+        #[serde(default)]
+        print_token: bool,
+    },
+    Daemon,
+    Project,
+    Hook { shell: Shell, emit: bool },
+    Loadtest {
+        workers: usize,
+        ops: usize,
+        keys: usize,
+        value_size: usize,
+        read_pct: u8,
+        put_pct: u8,
+        saver_interval_ms: u64,
+    },
+    Watch {
+        #[serde(default)]
+        prefix: Option<String>,
+        #[serde(default)]
+        interval_ms: u64,
+    },
+}
+
+impl AegisrCommand {
+    /// Whether the command may change the store. These are refused while
+    /// the store is open read-only; anything not known to only read counts
+    /// as a change.
+    pub fn mutates_store(&self) -> bool {
+        !matches!(
+            self,
+            Self::List
+                | Self::Stats { .. }
+                | Self::Metrics { .. }
+                | Self::Status
+                | Self::Inspect
+                | Self::Export { .. }
+                | Self::Get { .. }
+                | Self::Unseal { .. }
+                | Self::GetMany { .. }
+                | Self::Lint { .. }
+                | Self::Keys { .. }
+                | Self::Tree { .. }
+                | Self::Search { .. }
+                | Self::Dump { .. }
+                | Self::History { .. }
+                | Self::Pending { .. }
+                | Self::Env { .. }
+                | Self::ProfileList
+                | Self::KeyCheck { rederive: false }
+                | Self::Backup { .. }
+                | Self::Verify
+                | Self::Gen { save: None, .. }
+                | Self::Totp { .. }
+                | Self::SnippetGet { .. }
+                | Self::SnippetList
+                | Self::EmergencyList
+                | Self::KeypairNew { .. }
+                | Self::Share { .. }
+                | Self::FedList
+                | Self::FedGet { .. }
+                | Self::FedKeys
+                | Self::SnapshotList
+                | Self::AuditLog { .. }
+                | Self::AuditVerify { .. }
+                | Self::AuditAnchor
+                | Self::AuditExport { .. }
+                | Self::Serve { .. }
+                | Self::Daemon
+                | Self::Hook { emit: false, .. }
+                | Self::Watch { .. }
+        )
+    }
 }
@@ -0,0 +1,29 @@
+/// LZ4 block compression for collection payloads, applied before
+/// encryption (ciphertext does not compress). The output starts with the
+/// uncompressed length as a little-endian `u32`, followed by one LZ4 block
+/// (`lz4_flex::compress_prepend_size`).
+pub struct AegCompress;
+
+impl AegCompress {
+    pub fn compress(input: &[u8]) -> Result<Vec<u8>, String> {
+        if u32::try_from(input.len()).is_err() {
+            return Err("payload too large to compress (over 4 GiB)".to_string());
+        }
+        Ok(lz4_flex::block::compress_prepend_size(input))
+    }
+
+    pub fn decompress(input: &[u8]) -> Result<Vec<u8>, String> {
+        let corrupt = || "corrupt compressed payload".to_string();
+        let (size, block) = input.split_first_chunk::<4>().ok_or_else(corrupt)?;
+        let size = u32::from_le_bytes(*size) as usize;
+        // a block expands at most ~255x; don't allocate what the prefix claims beyond that
+        if size > block.len().saturating_mul(255) {
+            return Err(corrupt());
+        }
+        let out = lz4_flex::block::decompress(block, size).map_err(|_| corrupt())?;
+        if out.len() != size {
+            return Err(corrupt());
+        }
+        Ok(out)
+    }
+}
@@ -3,6 +3,58 @@ pub const ENGINE_NAME: &str = "Aegisr Engine (Dusk)";
 pub const ENGINE_DEVELOPER: &[&str] = &["surelle-ha"];
 pub const ENGINE_VERSION: &str = "1.0.2-beta"; /// TODO: Use Cargo app version
 pub const STORE_DIR: &str = ".aegisr";
+pub const STORE_HOME_ENV: &str = "AEGISR_HOME";
+pub const STORE_PROFILES_DIR: &str = "profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+pub const PROJECT_MANIFEST_FILE: &str = ".aegisr.toml";
 pub const STORE_COLLECTION: &str = "collection.lock";
 pub const STORE_CONFIG_AEG: &str = "config.aeg";
-pub const STORE_AUTHORIZATION_KEY: &str = "AUTHORIZATION_KEY";
\ No newline at end of file
+pub const STORE_AUTHORIZATION_KEY: &str = "AUTHORIZATION_KEY";
+pub const STORE_DECOY_DIR: &str = "cache";
+pub const STORE_SNAPSHOTS_DIR: &str = "snapshots";
+pub const STORE_LOCK_FILE: &str = "aegisr.lock";
+pub const STORE_LOCK_TIMEOUT_MS: u64 = 5000;
+pub const DEFAULT_COMPRESS_MIN_BYTES: usize = 4096;
+pub const STORE_DURESS_SALT: &str = "seed";
+pub const NUKE_CONFIRM_DELAY_SECS: u64 = 10;
+pub const KEY_HISTORY_DEPTH: usize = 10;
+pub const HSM_PIN_ENV: &str = "AEGISR_HSM_PIN";
+pub const DEFAULT_HSM_KEY_LABEL: &str = "aegisr";
+pub const STORE_AUDIT_LOG: &str = "audit.log";
+pub const STORE_SHELL_HISTORY: &str = "shell_history";
+pub const AUTH_KEY_BYTES: usize = 32;
+pub const AUTH_KEY_BASE64_LEN: usize = 44;
+pub const SNIPPET_COLLECTION: &str = "snippets";
+pub const SNIPPET_ID_LEN: usize = 8;
+pub const CLEAR_SNAPSHOT_PREFIX: &str = "pre-clear";
+pub const STORE_CLOCK_FILE: &str = "clock";
+pub const CLOCK_SKEW_THRESHOLD_SECS: u64 = 300;
+pub const CLOCK_HIGH_WATER_STEP_SECS: u64 = 60;
+pub const STORE_DAEMON_SOCKET: &str = "daemon.sock";
+pub const DAEMON_MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+pub const QUALIFIED_KEY_SEPARATOR: &str = "::";
+pub const S3_ENDPOINT_ENV: &str = "AEGISR_S3_ENDPOINT";
+pub const S3_BUCKET_ENV: &str = "AEGISR_S3_BUCKET";
+pub const S3_REGION_ENV: &str = "AEGISR_S3_REGION";
+pub const S3_PREFIX_ENV: &str = "AEGISR_S3_PREFIX";
+pub const S3_ACCESS_KEY_ENV: &str = "AWS_ACCESS_KEY_ID";
+pub const S3_SECRET_KEY_ENV: &str = "AWS_SECRET_ACCESS_KEY";
+pub const S3_SESSION_TOKEN_ENV: &str = "AWS_SESSION_TOKEN";
+pub const STORE_PASSPHRASE_ENV: &str = "AEGISR_PASSPHRASE";
+pub const STORE_CORRUPT_SUFFIX: &str = ".corrupt";
+pub const STORE_ORPHANED_SUFFIX: &str = ".orphaned";
+pub const VIEWER_PBKDF2_ITERATIONS: u32 = 600_000;
+pub const STORE_BACKUPS_DIR: &str = "backups";
+pub const DEFAULT_BACKUP_GENERATIONS: usize = 3;
+pub const EMERGENCY_COLLECTION: &str = "emergency";
+pub const STORE_EMERGENCY_FILE: &str = "emergency.lock";
+pub const STORE_FEDERATION_FILE: &str = "federation.json";
+pub const FEDERATION_LOCAL_STORE: &str = "local";
+pub const DEBOUNCE_MAX_WINDOWS: u32 = 10;
+pub const KEY_PATH_SEPARATOR: char = '/';
+pub const READ_ONLY_ERROR: &str = "✗ The store is open read-only";
+pub const STORE_MANIFEST: &str = "store.manifest";
+pub const KEYRING_SERVICE: &str = "aegisr";
+pub const DEFAULT_TOKEN_SLOT: u8 = 2;
+pub const CLIPBOARD_CLEAR_SECS: u64 = 30;
+pub const STORE_STORAGE_ENV: &str = "AEGISR_STORAGE";
\ No newline at end of file
@@ -1,37 +1,178 @@
-use crate::constant::STORE_COLLECTION;
-use crate::file_system::{AegFileSystem, CollectionLock};
-use crate::memory_engine::AegMemoryEngine;
-use rand_core::TryRngCore;
+use crate::age::{AegAge, SshIdentity, SshRecipient};
+use crate::audit::{AegAudit, AuditAction, AuditEntry, AuditFilter, AuditHead};
+use crate::backups::AegBackups;
+use crate::builder::StoreContext;
+use crate::bundle::{AegBundle, BundlePayload};
+use crate::clock::{AegClock, ClockSkewPolicy};
+use crate::constant::{
+    CLEAR_SNAPSHOT_PREFIX, DEFAULT_TOKEN_SLOT, KEY_PATH_SEPARATOR, QUALIFIED_KEY_SEPARATOR,
+    STORE_AUTHORIZATION_KEY, STORE_BACKUPS_DIR, STORE_COLLECTION, STORE_CONFIG_AEG,
+    STORE_DURESS_SALT, STORE_EMERGENCY_FILE,
+};
+use crate::crypto::{AegCrypto, Cipher};
+use crate::emergency::AegEmergency;
+use crate::env::AegEnv;
+use crate::file_format::AEKV_FORMAT_VERSION;
+use crate::file_system::{
+    AegFileSystem, CollectionLock, CollectionMeta, CollectionSeal, PassphraseConfig,
+    ProfileManager, RekeyProgress, StoreConfig,
+};
+use crate::hardware_key::AegHardwareKey;
+use crate::hsm::{AegHsm, HsmConfig};
+use crate::integrity::{AegIntegrity, ManifestMismatch, ManifestReport};
+use crate::introspect::{AegIntrospect, StoreStatus};
+use crate::keyring::{AegKeyring, KeyBackend};
+use crate::lint::{AegLint, LintLevel};
+use crate::manifest::ProjectManifest;
+use crate::memory_engine::{
+    AegMemoryEngine, CollectionStats, Entry, PendingChanges, PersistencePolicy, SaverHandle,
+    SaverPause, TierStats, ValueVersion,
+};
+use crate::merge::{AegMerge, MergeReport, MergeStrategy};
+use crate::metrics::{AegMetrics, MetricsSnapshot};
+use crate::naming::KeyConvention;
+use crate::plain::{AegPlain, PlainFormat};
+use crate::recovery::{AegRecovery, RecoveryReport};
+use crate::secret::SecretValue;
+use crate::share::{SharePrivateKey, SharePublicKey, SharedBundle};
+use crate::snapshot::{SnapshotInfo, SnapshotManager};
+use crate::storage::StorageBackend;
+use crate::store::KeyValueStore;
+use crate::sync::{AegSync, SyncReport, SyncStrategy};
+use crate::totp::{TotpCode, TotpSecret};
+use crate::transaction::AegTransaction;
+use crate::verbosity::Verbosity;
+use crate::verify::{AegVerifier, IntegrityReport, VerificationReport};
+use crate::viewer::AegViewer;
+use crate::watch::{AegWatch, ChangeEvent};
+use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AegCore {
     pub active_collection: String,
     pub collections: Vec<String>,
+    #[serde(default)]
+    pub collection_meta: HashMap<String, CollectionMeta>,
 }
 
+/// Collection selected for this process only (e.g. by a project manifest),
+/// shadowing the persisted active collection without changing it.
+static SESSION_COLLECTION: OnceLock<RwLock<Option<String>>> = OnceLock::new();
+
 impl AegCore {
+    fn session_collection() -> &'static RwLock<Option<String>> {
+        SESSION_COLLECTION.get_or_init(|| RwLock::new(None))
+    }
+
+    /// The process's session collection; an `AegStore` running on this
+    /// thread has none.
+    pub(crate) fn session_collection_name() -> Option<String> {
+        if StoreContext::current().is_some() {
+            return None;
+        }
+        Self::session_collection()
+            .read()
+            .expect("Failed to lock session collection")
+            .clone()
+    }
+
+    pub(crate) fn set_session_collection_name(name: Option<String>) {
+        if StoreContext::current().is_some() {
+            return;
+        }
+        *Self::session_collection()
+            .write()
+            .expect("Failed to lock session collection") = name;
+    }
+
+    /// Make `name` the active collection for this process only. The
+    /// persisted active collection is left as is; `set_active_collection`
+    /// ends the override. Not available inside `AegStore::run`.
+    pub fn use_collection_for_session(name: &str) -> Result<(), String> {
+        if StoreContext::current().is_some() {
+            return Err("Session collections are not available for an AegStore".into());
+        }
+        if !Self::load().collections.iter().any(|c| c == name) {
+            return Err(format!("Collection '{}' does not exist", name));
+        }
+        Self::set_session_collection_name(Some(name.to_string()));
+        Ok(())
+    }
+
+    pub fn clear_session_collection() {
+        Self::set_session_collection_name(None);
+    }
+
+    /// Open the store read-only for this process (see `set_read_only`) and
+    /// load it.
+    pub fn load_read_only() -> Self {
+        Self::set_read_only(true);
+        Self::load()
+    }
+
+    /// While set, every operation that would change the store fails with
+    /// `READ_ONLY_ERROR`, nothing is written to the store directory, and
+    /// no background saver starts. Lets a store be audited with no chance
+    /// of modifying it. Stops a running saver without a final save.
+    pub fn set_read_only(enabled: bool) {
+        AegFileSystem::set_read_only(enabled);
+        if enabled {
+            Self::stop_background_saver();
+        }
+    }
+
+    pub fn is_read_only() -> bool {
+        AegFileSystem::is_read_only()
+    }
+
+    /// Panics if collection.lock cannot be decrypted; see `try_load`.
     pub fn load() -> Self {
-        let lock = AegFileSystem::read_collection_lock_obj();
-        Self {
-            active_collection: lock.active,
+        Self::try_load().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `load`, with an error if collection.lock cannot be read or
+    /// decrypted (corrupted, or another key).
+    pub fn try_load() -> Result<Self, String> {
+        let lock = AegFileSystem::read_collection_lock_obj()?;
+        let session = Self::session_collection_name().filter(|s| lock.collections.contains(s));
+        Ok(Self {
+            active_collection: session.unwrap_or(lock.active),
             collections: lock.collections,
-        }
+            collection_meta: lock.meta,
+        })
     }
 
     pub fn save(&self) {
+        if AegFileSystem::is_read_only() {
+            return;
+        }
+        // never persist a session-only selection as the active collection
+        let active = match Self::session_collection_name() {
+            Some(session) if session == self.active_collection => {
+                match AegFileSystem::read_collection_lock_obj() {
+                    Ok(lock) if self.collections.contains(&lock.active) => lock.active,
+                    _ => self.active_collection.clone(),
+                }
+            }
+            _ => self.active_collection.clone(),
+        };
         let lock = CollectionLock {
-            active: self.active_collection.clone(),
+            active,
             collections: self.collections.clone(),
+            meta: self.collection_meta.clone(),
         };
         let json = serde_json::to_string_pretty(&lock).expect("Serialize failed");
         let auth_key = AegFileSystem::read_authorization_key();
-
-        let path = AegFileSystem::get_config_path().join(STORE_COLLECTION);
-        fs::write(&path, json.clone()).expect("Write failed");
-
         AegFileSystem::write_collection_lock_json(&json, &auth_key);
     }
 
@@ -40,15 +181,20 @@ impl AegCore {
     }
 
     pub fn set_active_collection(&mut self, name: &str) -> Result<(), String> {
+        AegFileSystem::ensure_writable()?;
         if !self.collections.contains(&name.to_string()) {
             return Err(format!("Collection '{}' does not exist", name));
         }
+        Self::clear_session_collection();
         self.active_collection = name.to_string();
         self.save();
         Ok(())
     }
 
     pub fn create_collection(name: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
         let mut core = Self::load();
         if core.collections.contains(&name.to_string()) {
             return format!("✗ Collection '{}' already exists", name);
@@ -56,6 +202,7 @@ impl AegCore {
 
         core.collections.push(name.to_string());
         core.save();
+        AegAudit::record_collection(name, AuditAction::CreateCollection, None);
 
         let _ = Self::load();
 
@@ -63,98 +210,2574 @@ impl AegCore {
     }
 
     pub fn delete_collection(name: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
         let mut core = Self::load();
         if core.collections.len() == 1 {
             return "✗ Cannot delete the last collection".into();
         }
         if let Some(pos) = core.collections.iter().position(|x| x == name) {
             core.collections.remove(pos);
+            core.collection_meta.remove(name);
+            if Self::session_collection_name().as_deref() == Some(name) {
+                Self::clear_session_collection();
+            }
             if core.active_collection == name {
                 core.active_collection = core.collections[0].clone();
             }
             core.save();
+            AegAudit::record_collection(name, AuditAction::DeleteCollection, None);
             format!("✓ Collection '{}' deleted", name)
         } else {
             format!("✗ Collection '{}' does not exist", name)
         }
     }
 
+    /// Rename collection `name` with its settings, unsaved changes and
+    /// files. Collection files are encrypted under a key derived from the
+    /// collection's name, so its data is re-encrypted and written under
+    /// `new_name` before the files of `name` are removed. Its backups are
+    /// deleted, since they are under the old name's key.
     pub fn rename_collection(name: &str, new_name: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
         let mut core = Self::load();
         if core.collections.contains(&new_name.to_string()) {
             return format!("✗ Collection '{}' already exists", new_name);
         }
-        if let Some(pos) = core.collections.iter().position(|x| x == name) {
-            core.collections[pos] = new_name.to_string();
-            if core.active_collection == name {
-                core.active_collection = new_name.to_string();
+        let Some(pos) = core.collections.iter().position(|x| x == name) else {
+            return format!("✗ Collection '{}' does not exist", name);
+        };
+        if let Err(e) = core.ensure_unsealed(name) {
+            return format!("✗ {}", e);
+        }
+        let Some(data) = AegMemoryEngine::capture_consistent(&[name.to_string()]).pop() else {
+            return format!("✗ Collection '{}' could not be read", name);
+        };
+        let was_active = core.active_collection == name;
+        let was_session = Self::session_collection_name().as_deref() == Some(name);
+        core.collections[pos] = new_name.to_string();
+        if let Some(meta) = core.collection_meta.remove(name) {
+            core.collection_meta.insert(new_name.to_string(), meta);
+        }
+        if was_active {
+            core.active_collection = new_name.to_string();
+        }
+        let stretched = AegFileSystem::unsealed_passphrase(name);
+        AegFileSystem::set_unsealed(new_name, stretched.clone());
+        core.save();
+
+        if let Err(e) = AegMemoryEngine::move_collection(name, new_name, &data) {
+            // the files of `name` are still there; point the lock back at them
+            AegMemoryEngine::evict(new_name);
+            AegFileSystem::set_unsealed(new_name, None);
+            core.collections[pos] = name.to_string();
+            if let Some(meta) = core.collection_meta.remove(new_name) {
+                core.collection_meta.insert(name.to_string(), meta);
+            }
+            if was_active {
+                core.active_collection = name.to_string();
             }
             core.save();
-            format!("✓ Collection '{}' renamed to '{}'", name, new_name)
+            return format!("✗ Could not rename collection '{}': {}", name, e);
+        }
+        if stretched.is_some() {
+            AegFileSystem::set_unsealed(name, None);
+        }
+        if was_session {
+            Self::set_session_collection_name(Some(new_name.to_string()));
+        }
+        AegAudit::record_collection(name, AuditAction::RenameCollection, Some(new_name));
+        format!("✓ Collection '{}' renamed to '{}'", name, new_name)
+    }
+
+    /// Create collection `dest` as a copy of `src`: every entry with its
+    /// timestamps and tags, per-key settings and history, and the settings
+    /// of `src` itself. The copy includes unsaved changes and is written
+    /// like any other change.
+    pub fn duplicate_collection(src: &str, dest: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.iter().any(|c| c == src) {
+            return format!("✗ Collection '{}' does not exist", src);
+        }
+        if core.collections.iter().any(|c| c == dest) {
+            return format!("✗ Collection '{}' already exists", dest);
+        }
+        if let Err(e) = core.ensure_unsealed(src) {
+            return format!("✗ {}", e);
+        }
+        let Some(copy) = AegMemoryEngine::capture_consistent(&[src.to_string()]).pop() else {
+            return format!("✗ Collection '{}' could not be read", src);
+        };
+        core.collections.push(dest.to_string());
+        if let Some(meta) = core.collection_meta.get(src).cloned() {
+            core.collection_meta.insert(dest.to_string(), meta);
+        }
+        // a copy of a sealed collection is sealed with the same passphrase
+        if let Some(stretched) = AegFileSystem::unsealed_passphrase(src) {
+            AegFileSystem::set_unsealed(dest, Some(stretched));
+        }
+        core.save();
+        AegAudit::record_collection(src, AuditAction::CloneCollection, Some(dest));
+        let keys = AegMemoryEngine::with_engine(dest, |engine| engine.copy_from(&copy));
+        format!(
+            "✓ Collection '{}' cloned to '{}' ({} keys, in-memory)",
+            src, dest, keys
+        )
+    }
+
+    /// Copy the keys of `src` into the existing collection `dest` (see
+    /// `AegMerge`). With `FailOnConflict` the report lists the conflicts
+    /// and `dest` is left unchanged.
+    pub fn merge_collections(
+        src: &str,
+        dest: &str,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport, String> {
+        AegMerge::merge(src, dest, strategy)
+    }
+
+    /// Health of the store as this process sees it: location, active and
+    /// known collections, the background saver and its last flush, file
+    /// format versions on disk, and the unsaved changes of loaded
+    /// collections. Nothing is loaded that is not loaded already.
+    pub fn status() -> StoreStatus {
+        let core = Self::load();
+        let files = AegIntrospect::files(&AegFileSystem::get_config_path());
+        let mut file_format_versions: Vec<u8> =
+            files.iter().filter_map(|f| f.format_version).collect();
+        file_format_versions.sort_unstable();
+        file_format_versions.dedup();
+        let pending = AegMemoryEngine::cached_collections()
+            .iter()
+            .filter(|name| AegMemoryEngine::cached_dirty(name) == Some(true))
+            .filter_map(|name| AegMemoryEngine::pending_changes(name).ok())
+            .filter(|changes| !changes.is_empty())
+            .collect();
+        StoreStatus {
+            store_dir: AegFileSystem::get_real_config_path(),
+            profile: ProfileManager::current(),
+            active_collection: core.get_active_collection().to_string(),
+            collections: core.collections.clone(),
+            saver: AegIntrospect::saver(),
+            format_version: AEKV_FORMAT_VERSION,
+            file_format_versions,
+            pending,
+            clock_skew_secs: AegClock::skew_detected(),
+        }
+    }
+
+    /// Entry count, key and value bytes, encrypted size on disk, and save
+    /// state of one collection. Loads the collection if it is not loaded.
+    pub fn collection_stats(name: &str) -> Result<CollectionStats, String> {
+        if !Self::load().collections.iter().any(|c| c == name) {
+            return Err(format!("Collection '{}' does not exist", name));
+        }
+        let (entries, key_bytes, value_bytes, dirty) =
+            AegMemoryEngine::read_engine(name, |engine| {
+                let (entries, key_bytes, value_bytes) = engine.content_size();
+                (entries, key_bytes, value_bytes, engine.is_dirty())
+            });
+        let disk_bytes = AegIntrospect::files(&AegFileSystem::get_config_path())
+            .iter()
+            .filter(|f| f.collection.as_deref() == Some(name))
+            .map(|f| f.bytes)
+            .sum();
+        Ok(CollectionStats {
+            name: name.to_string(),
+            entries,
+            key_bytes,
+            value_bytes,
+            disk_bytes,
+            last_saved: AegMemoryEngine::last_saved(name),
+            dirty,
+        })
+    }
+
+    /// `collection_stats` for every collection, in collection.lock order.
+    pub fn all_collection_stats() -> Vec<CollectionStats> {
+        Self::load()
+            .collections
+            .iter()
+            .filter_map(|name| Self::collection_stats(name).ok())
+            .collect()
+    }
+
+    /// Include or exclude a collection from the periodic background save.
+    /// Collections with autosave disabled are only persisted by `flush_now`.
+    pub fn set_autosave(name: &str, enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .skip_autosave = !enabled;
+        core.save();
+        format!(
+            "✓ Autosave {} for collection '{}'",
+            if enabled { "enabled" } else { "disabled" },
+            name
+        )
+    }
+
+    pub fn is_autosave_enabled(&self, name: &str) -> bool {
+        self.collection_meta
+            .get(name)
+            .map(|m| !m.skip_autosave)
+            .unwrap_or(true)
+    }
+
+    /// Limit how many entries of a collection stay decrypted in memory.
+    /// `None` disables tiering and keeps the whole collection warm.
+    pub fn set_warm_capacity(name: &str, capacity: Option<usize>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .warm_capacity = capacity;
+        core.save();
+        // re-apply the capacity to the cached engine right away
+        let _ = AegMemoryEngine::shared(name);
+        match capacity {
+            Some(n) => format!("✓ Collection '{}' keeps at most {} warm entries", name, n),
+            None => format!("✓ Tiering disabled for collection '{}'", name),
+        }
+    }
+
+    /// Enable or disable the on-disk record index for a collection.
+    /// Indexed collections append every write to their record file, which lets
+    /// `get_value_uncached` read single keys without loading the collection.
+    pub fn set_indexed(name: &str, enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .indexed = enabled;
+        core.save();
+        let _ = AegMemoryEngine::shared(name);
+        format!(
+            "✓ On-disk index {} for collection '{}'",
+            if enabled { "enabled" } else { "disabled" },
+            name
+        )
+    }
+
+    /// Keep a collection's values sealed in memory (see
+    /// `CollectionMeta::sealed_values`), or in plaintext again.
+    pub fn set_sealed_values(name: &str, enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .sealed_values = enabled;
+        core.save();
+        // reseal the cached engine right away
+        let _ = AegMemoryEngine::shared(name);
+        if enabled {
+            format!(
+                "✓ Values of collection '{}' stay sealed in memory until read",
+                name
+            )
         } else {
-            format!("✗ Collection '{}' does not exist", name)
+            format!(
+                "✓ Values of collection '{}' are kept in plaintext in memory",
+                name
+            )
+        }
+    }
+
+    /// Seal a collection with a passphrase of its own: its files are
+    /// re-encrypted under its subkey bound to the stretched passphrase, and
+    /// until `unseal_collection` is given that passphrase in a process, the
+    /// collection reads as empty and refuses changes. Its backups are
+    /// deleted, since they are under the old key; snapshots taken before
+    /// sealing keep their copy. A store re-key needs sealed collections
+    /// unsealed in the process doing it.
+    pub fn seal_collection(name: &str, passphrase: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.iter().any(|c| c == name) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        if core.is_collection_sealed(name) {
+            return format!("✗ Collection '{}' is already sealed", name);
+        }
+        if passphrase.is_empty() {
+            return "✗ The passphrase must not be empty".into();
+        }
+        // load it under its current key before that changes
+        let _ = AegMemoryEngine::shared(name);
+        let old_key = AegMemoryEngine::current_file_key(name);
+        let salt = AegCrypto::generate_random_bytes();
+        let stretched = match AegCrypto::derive_password_key(passphrase, &salt) {
+            Ok(stretched) => stretched,
+            Err(e) => return format!("✗ {}", e),
+        };
+        let check = match AegCrypto::passphrase_check(&stretched) {
+            Ok(check) => check,
+            Err(e) => return format!("✗ {}", e),
+        };
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .seal = Some(CollectionSeal {
+            salt: AegCrypto::encode_base64(salt),
+            check,
+        });
+        core.save();
+        AegFileSystem::set_unsealed(name, Some(stretched));
+        if let Err(e) = AegMemoryEngine::rekey_collection(name, &old_key) {
+            AegFileSystem::set_unsealed(name, None);
+            if let Some(meta) = core.collection_meta.get_mut(name) {
+                meta.seal = None;
+            }
+            core.save();
+            return format!("✗ Could not seal collection '{}': {}", name, e);
+        }
+        let store_dir = AegFileSystem::get_config_path();
+        for generation in AegBackups::generations(&store_dir, name) {
+            let _ = AegFileSystem::storage()
+                .remove(&AegBackups::backup_file(&store_dir, name, generation));
+        }
+        AegFileSystem::set_unsealed(name, None);
+        AegMemoryEngine::evict(name);
+        format!(
+            "✓ Collection '{}' sealed; unseal it with its passphrase to use it",
+            name
+        )
+    }
+
+    /// Give a sealed collection its passphrase for the rest of this process
+    /// (see `seal_collection`).
+    pub fn unseal_collection(name: &str, passphrase: &str) -> String {
+        let core = Self::load();
+        let Some(seal) = core
+            .collection_meta
+            .get(name)
+            .and_then(|meta| meta.seal.clone())
+        else {
+            return format!("✗ Collection '{}' is not sealed", name);
+        };
+        if AegFileSystem::is_unsealed(name) {
+            return format!("✓ Collection '{}' is already unsealed", name);
+        }
+        let stretched = match AegCrypto::decode_key(&seal.salt)
+            .and_then(|salt| AegCrypto::derive_password_key(passphrase, &salt))
+        {
+            Ok(stretched) => stretched,
+            Err(e) => return format!("✗ {}", e),
+        };
+        if AegCrypto::passphrase_check(&stretched).ok() != Some(seal.check) {
+            return format!("✗ Wrong passphrase for collection '{}'", name);
+        }
+        AegFileSystem::set_unsealed(name, Some(stretched));
+        AegMemoryEngine::evict(name);
+        format!("✓ Collection '{}' unsealed for this session", name)
+    }
+
+    /// Forget a sealed collection's passphrase in this process, saving its
+    /// unsaved changes first, so it is locked again until the next
+    /// `unseal_collection`.
+    pub fn lock_collection(name: &str) -> String {
+        if !Self::load().is_collection_sealed(name) {
+            return format!("✗ Collection '{}' is not sealed", name);
+        }
+        AegMemoryEngine::save_collection(name);
+        AegFileSystem::set_unsealed(name, None);
+        AegMemoryEngine::evict(name);
+        format!("✓ Collection '{}' locked", name)
+    }
+
+    /// Whether a collection is sealed with its own passphrase, unsealed or
+    /// not.
+    pub fn is_collection_sealed(&self, name: &str) -> bool {
+        self.collection_meta
+            .get(name)
+            .is_some_and(|meta| meta.seal.is_some())
+    }
+
+    /// Errors for a sealed collection not unsealed in this process.
+    pub(crate) fn ensure_unsealed(&self, collection: &str) -> Result<(), String> {
+        if self.is_collection_sealed(collection) && !AegFileSystem::is_unsealed(collection) {
+            return Err(format!(
+                "Collection '{}' is sealed; unseal it with its passphrase first",
+                collection
+            ));
+        }
+        Ok(())
+    }
+
+    /// Require new keys of a collection to follow `convention`, or allow any
+    /// name again with `None`. Existing keys are left alone; see
+    /// `key_name_fixes` for what they would be renamed to.
+    pub fn set_key_convention(name: &str, convention: Option<KeyConvention>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        let msg = match &convention {
+            Some(c) => format!(
+                "✓ Keys of collection '{}' must follow the {} convention",
+                name, c
+            ),
+            None => format!("✓ Key naming convention removed from collection '{}'", name),
+        };
+        core.collection_meta
+            .entry(name.to_string())
+            .or_default()
+            .key_convention = convention;
+        core.save();
+        msg
+    }
+
+    pub fn key_convention(&self, name: &str) -> Option<&KeyConvention> {
+        self.collection_meta
+            .get(name)
+            .and_then(|m| m.key_convention.as_ref())
+    }
+
+    /// Keys of a collection that break its naming convention, sorted, each
+    /// with the name `KeyConvention::fix` suggests (`None` for a pattern).
+    pub fn key_name_fixes(name: &str) -> Result<Vec<(String, Option<String>)>, String> {
+        let core = Self::load();
+        if !core.collections.contains(&name.to_string()) {
+            return Err(format!("Collection '{}' does not exist", name));
+        }
+        let Some(convention) = core.key_convention(name) else {
+            return Err(format!(
+                "Collection '{}' has no key naming convention",
+                name
+            ));
+        };
+        let mut fixes: Vec<(String, Option<String>)> =
+            AegMemoryEngine::read_engine(name, |engine| {
+                engine
+                    .keys()
+                    .into_iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .filter(|k| convention.check(k).is_err())
+            .map(|k| {
+                let fixed = convention.fix(&k);
+                (k, fixed)
+            })
+            .collect();
+        fixes.sort();
+        Ok(fixes)
+    }
+
+    /// Read a key of the active collection from disk through its index,
+    /// bypassing the in-memory cache. Reflects the last flushed state only.
+    pub fn get_value_uncached(key: &str) -> Result<Option<String>, String> {
+        Self::get_uncached_in(&Self::load().active_collection, key)
+    }
+
+    fn get_uncached_in(collection: &str, key: &str) -> Result<Option<String>, String> {
+        let value = AegMemoryEngine::read_from_disk(collection, key)?;
+        if value.is_some() {
+            AegAudit::record_read(collection, key);
         }
+        Ok(value)
+    }
+
+    /// Operation counts and latencies of this process so far (see
+    /// `AegMetrics`).
+    pub fn metrics_snapshot() -> MetricsSnapshot {
+        AegMetrics::snapshot()
+    }
+
+    /// Warm/cold hit, miss, and eviction counters for the active collection.
+    pub fn tier_stats() -> TierStats {
+        AegMemoryEngine::read_active(|engine| engine.stats())
     }
 
     /// Insert into memory (non-blocking). Does not perform immediate disk save.
     /// Background saver (if started) will persist this later.
+    /// Values are first checked against the store's lint level: findings
+    /// are appended as warnings, or refuse the value under `deny`. With
+    /// `warn_duplicates`, other keys already holding the value are named.
+    /// A new key must follow the collection's naming convention, if any.
     pub fn put_value(key: &str, value: &str) -> String {
-        let mut engine = AegMemoryEngine::load();
-        engine.insert(key, value);
-        // no engine.save() here - background saver will persist
-        format!(
+        let core = Self::load();
+        Self::put_value_in(&core, &core.active_collection, key, value)
+    }
+
+    /// `put_value` for `collection::key`, or the active collection when the
+    /// key is not qualified (see `split_qualified`).
+    pub fn put_qualified(qualified: &str, value: &str) -> String {
+        let core = Self::load();
+        match core.resolve_qualified(qualified) {
+            Ok((collection, key)) => Self::put_value_in(&core, &collection, key, value),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    fn put_value_in(core: &AegCore, collection: &str, key: &str, value: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if let Err(e) = core.ensure_unsealed(collection) {
+            return format!("✗ {}", e);
+        }
+        if let Some(convention) = core.key_convention(collection)
+            && AegMemoryEngine::read_engine(collection, |engine| !engine.contains(key))
+            && let Err(e) = convention.check(key)
+        {
+            return format!("✗ {}", e);
+        }
+        let config = AegFileSystem::read_store_config();
+        let level = config.lint;
+        let findings = if level == LintLevel::Off {
+            Vec::new()
+        } else {
+            AegLint::check(value)
+        };
+        if level == LintLevel::Deny && !findings.is_empty() {
+            let reasons: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+            return format!("✗ Value for '{}' rejected: {}", key, reasons.join("; "));
+        }
+
+        let (collection, duplicates) = AegMemoryEngine::with_engine(collection, |engine| {
+            let duplicates: Vec<String> = if config.warn_duplicates {
+                engine
+                    .keys_with_value(value)
+                    .into_iter()
+                    .filter(|k| k != key)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            engine.insert(key, value);
+            (engine.collection_name.clone(), duplicates)
+        });
+        // no save here - background saver will persist
+        let mut msg = format!(
             "✓ Key '{}' saved in collection '{}' (in-memory)",
-            key, engine.collection_name
-        )
+            key, collection
+        );
+        for finding in findings {
+            msg.push_str(&format!("\n⚠ {}", finding.message));
+        }
+        if !duplicates.is_empty() {
+            let names: Vec<String> = duplicates.iter().map(|k| format!("'{}'", k)).collect();
+            msg.push_str(&format!(
+                "\n⚠ same value is already stored under {}",
+                names.join(", ")
+            ));
+        }
+        msg
     }
 
-    /// Read from memory (plaintext in RAM).
-    pub fn get_value(key: &str) -> Option<String> {
-        let engine = AegMemoryEngine::load();
-        engine.get(key)
+    /// Export `key` from the active collection under the environment variable
+    /// `env_name` (or go back to the derived name with `None`).
+    pub fn set_env_name(key: &str, env_name: Option<&str>) -> String {
+        Self::set_env_name_in(&Self::load().active_collection, key, env_name)
     }
 
-    /// Delete in-memory (non-blocking). Background saver will persist deletion later.
-    pub fn delete_value(key: &str) -> String {
-        let mut engine = AegMemoryEngine::load();
-        if engine.get(key).is_some() {
-            engine.delete(key);
-            // no engine.save() here
+    /// `set_env_name` for a key that may be qualified with its collection.
+    pub fn set_env_name_qualified(qualified: &str, env_name: Option<&str>) -> String {
+        match Self::load().resolve_qualified(qualified) {
+            Ok((collection, key)) => Self::set_env_name_in(&collection, key, env_name),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    fn set_env_name_in(collection: &str, key: &str, env_name: Option<&str>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        AegMemoryEngine::with_engine(collection, |engine| {
+            if !engine.contains(key) {
+                return format!("✗ Key '{}' not found", key);
+            }
+            if let Err(e) = engine.set_env_name(key, env_name.map(str::to_string)) {
+                return format!("✗ {}", e);
+            }
             format!(
-                "✓ Key '{}' deleted from collection '{}' (in-memory)",
-                key, engine.collection_name
+                "✓ Key '{}' exports as '{}' (in-memory)",
+                key,
+                engine.env_name_for(key)
             )
+        })
+    }
+
+    /// The value of `key` in the active collection with its timestamps and
+    /// tags.
+    pub fn get_metadata(key: &str) -> Option<Entry> {
+        AegMemoryEngine::read_active(|engine| engine.entry(key))
+    }
+
+    /// Tag `key` in the active collection (e.g. `prod`), for `list_by_tag`.
+    pub fn tag_key(key: &str, tag: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',') {
+            return format!("✗ '{}' is not a valid tag (no spaces or commas)", tag);
+        }
+        AegMemoryEngine::with_active(|engine| {
+            if engine.tag(key, tag) {
+                format!("✓ Key '{}' tagged '{}' (in-memory)", key, tag)
+            } else {
+                format!("✗ Key '{}' not found", key)
+            }
+        })
+    }
+
+    pub fn untag_key(key: &str, tag: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        AegMemoryEngine::with_active(|engine| {
+            if engine.untag(key, tag) {
+                format!("✓ Tag '{}' removed from key '{}' (in-memory)", tag, key)
+            } else {
+                format!("✗ Key '{}' not found", key)
+            }
+        })
+    }
+
+    /// Keys of the active collection, sorted.
+    pub fn list_keys() -> Vec<String> {
+        let mut keys: Vec<String> = AegMemoryEngine::read_active(|engine| {
+            engine.keys().iter().map(|k| k.to_string()).collect()
+        });
+        keys.sort();
+        keys
+    }
+
+    /// Every entry of collection `name` as (key, value) pairs sorted by key,
+    /// decrypted; callers decide whether to show the values.
+    pub fn collection_entries(name: &str) -> Result<Vec<(String, String)>, String> {
+        if !Self::load().collections.contains(&name.to_string()) {
+            return Err(format!("Collection '{}' does not exist", name));
+        }
+        let mut entries: Vec<(String, String)> = AegMemoryEngine::read_engine(name, |engine| {
+            engine
+                .list()
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        });
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Keys matching `pattern` in the active collection, or in every
+    /// collection with `all_collections`, as (collection, key) pairs. The
+    /// pattern is a substring unless `regex` is set; `values` also matches
+    /// it against the values, though only keys are returned.
+    pub fn search(
+        pattern: &str,
+        regex: bool,
+        values: bool,
+        all_collections: bool,
+    ) -> Result<Vec<(String, String)>, String> {
+        let regex = if regex {
+            Some(Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?)
         } else {
-            format!(
-                "✗ Key '{}' not found in collection '{}' (in-memory)",
-                key, engine.collection_name
-            )
+            None
+        };
+        let matches = |text: &str| match &regex {
+            Some(regex) => regex.is_match(text),
+            None => text.contains(pattern),
+        };
+        let core = Self::load();
+        let collections = if all_collections {
+            core.collections
+        } else {
+            vec![core.active_collection]
+        };
+        Ok(collections
+            .into_iter()
+            .flat_map(|name| {
+                AegMemoryEngine::read_engine(&name, |engine| engine.search(matches, values))
+                    .into_iter()
+                    .map(move |key| (name.clone(), key))
+            })
+            .collect())
+    }
+
+    /// Keys of the active collection tagged `tag`, sorted.
+    pub fn list_by_tag(tag: &str) -> Vec<String> {
+        AegMemoryEngine::read_active(|engine| engine.keys_with_tag(tag))
+    }
+
+    /// The active collection as (environment variable name, value) pairs,
+    /// honouring each key's `env_name`.
+    pub fn env_vars() -> Vec<(String, String)> {
+        AegMemoryEngine::read_active(|engine| engine.env_vars())
+    }
+
+    /// Read from memory (plaintext in RAM), paging in from the cold tier if
+    /// needed. The returned copy is wiped when dropped.
+    pub fn get_value(key: &str) -> Option<SecretValue> {
+        Self::get_value_in(&Self::load().active_collection, key)
+    }
+
+    fn get_value_in(collection: &str, key: &str) -> Option<SecretValue> {
+        let value = AegMemoryEngine::fetch_shared(collection, key)?;
+        AegAudit::record_read(collection, key);
+        Some(SecretValue::new(&*value))
+    }
+
+    /// Split `collection::key` into the collection and the key. Without
+    /// `QUALIFIED_KEY_SEPARATOR` there is no collection, meaning the active
+    /// one; with it, everything before the first separator names the
+    /// collection, so such keys can only be reached qualified.
+    pub fn split_qualified(qualified: &str) -> (Option<&str>, &str) {
+        match qualified.split_once(QUALIFIED_KEY_SEPARATOR) {
+            Some((collection, key)) => (Some(collection), key),
+            None => (None, qualified),
         }
     }
 
-    /// Clear in-memory values (non-blocking). Background saver will persist later.
-    pub fn clear_values() -> String {
-        let mut engine = AegMemoryEngine::load();
-        engine.clear();
+    /// The collection (defaulting to the active one) and key `qualified`
+    /// refers to. Errors if the named collection does not exist.
+    fn resolve_qualified<'a>(&self, qualified: &'a str) -> Result<(String, &'a str), String> {
+        match Self::split_qualified(qualified) {
+            (Some(collection), key) => {
+                if !self.collections.iter().any(|c| c == collection) {
+                    return Err(format!("Collection '{}' does not exist", collection));
+                }
+                if key.is_empty() {
+                    return Err(format!("'{}' names no key", qualified));
+                }
+                self.ensure_unsealed(collection)?;
+                Ok((collection.to_string(), key))
+            }
+            (None, key) => {
+                self.ensure_unsealed(&self.active_collection)?;
+                Ok((self.active_collection.clone(), key))
+            }
+        }
+    }
+
+    /// The collection and path of a key path that may be qualified with its
+    /// collection; unlike a key, the path may be empty.
+    fn resolve_path<'a>(&self, qualified: &'a str) -> Result<(String, &'a str), String> {
+        match Self::split_qualified(qualified) {
+            (Some(collection), path) => {
+                if !self.collections.iter().any(|c| c == collection) {
+                    return Err(format!("Collection '{}' does not exist", collection));
+                }
+                self.ensure_unsealed(collection)?;
+                Ok((collection.to_string(), path))
+            }
+            (None, path) => {
+                self.ensure_unsealed(&self.active_collection)?;
+                Ok((self.active_collection.clone(), path))
+            }
+        }
+    }
+
+    /// The next level of keys below path `path` (`app/db`, or
+    /// `collection::app/db`): key names, and branch names ending in `/`
+    /// (see `AegMemoryEngine::children`).
+    pub fn list_children(path: &str) -> Result<Vec<String>, String> {
+        let (collection, path) = Self::load().resolve_path(path)?;
+        Ok(AegMemoryEngine::with_engine(&collection, |engine| {
+            engine.children(path)
+        }))
+    }
+
+    /// Every key under path `path`, in order.
+    pub fn list_subtree(path: &str) -> Result<Vec<String>, String> {
+        let (collection, path) = Self::load().resolve_path(path)?;
+        Ok(AegMemoryEngine::with_engine(&collection, |engine| {
+            engine.keys_under(path)
+        }))
+    }
+
+    /// Delete every key under path `path` (in memory). An empty path is
+    /// refused rather than taken to mean the whole collection; use
+    /// `clear_values` for that.
+    pub fn delete_subtree(path: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let core = Self::load();
+        let (collection, path) = match core.resolve_path(path) {
+            Ok(resolved) => resolved,
+            Err(e) => return format!("✗ {}", e),
+        };
+        if path.trim_matches(KEY_PATH_SEPARATOR).is_empty() {
+            return "✗ Name the path to delete (e.g. app/)".to_string();
+        }
+        let deleted =
+            AegMemoryEngine::with_engine(&collection, |engine| engine.delete_subtree(path));
+        if deleted == 0 {
+            return format!("✗ No keys under '{}'", path);
+        }
         format!(
-            "✓ All keys cleared from collection '{}' (in-memory)",
-            engine.collection_name
+            "✓ Deleted {} key(s) under '{}' in collection '{}' (in-memory)",
+            deleted, path, collection
         )
     }
 
-    /// Force immediate flush (saves all collections to disk synchronously).
-    pub fn flush_now() {
-        AegMemoryEngine::save_all();
+    /// The keys under path `path` as an indented tree, one line per branch
+    /// (ending in `/`) and key, two spaces per level.
+    pub fn key_tree(path: &str) -> Result<Vec<String>, String> {
+        let keys = Self::list_subtree(path)?;
+        let (_, path) = Self::split_qualified(path);
+        let skip = match path.trim_end_matches(KEY_PATH_SEPARATOR) {
+            "" => 0,
+            branch => branch.len() + 1,
+        };
+        let mut lines = Vec::new();
+        let mut open: Vec<&str> = Vec::new();
+        for key in &keys {
+            let segments: Vec<&str> = key[skip..].split(KEY_PATH_SEPARATOR).collect();
+            let (leaf, branches) = segments.split_last().expect("split yields one segment");
+            let shared = open
+                .iter()
+                .zip(branches)
+                .take_while(|(a, b)| a == b)
+                .count();
+            open.truncate(shared);
+            for (depth, branch) in branches.iter().enumerate().skip(shared) {
+                lines.push(format!(
+                    "{}{}{}",
+                    "  ".repeat(depth),
+                    branch,
+                    KEY_PATH_SEPARATOR
+                ));
+                open.push(branch);
+            }
+            lines.push(format!("{}{}", "  ".repeat(branches.len()), leaf));
+        }
+        Ok(lines)
     }
 
-    /// Start background saver thread. Safe to call multiple times.
-    /// interval_seconds: how often to persist (e.g. 1).
-    pub fn start_background_saver(interval_seconds: u64) {
-        AegMemoryEngine::start_background_saver(interval_seconds);
+    /// Read `collection::key` (or `key` from the active collection) without
+    /// switching the active collection, like `get_value`.
+    pub fn get_qualified(qualified: &str) -> Result<Option<String>, String> {
+        let (collection, key) = Self::load().resolve_qualified(qualified)?;
+        Ok(Self::get_value_in(&collection, key).map(|value| value.to_plain_string()))
     }
 
-    /// Signal background saver to stop. Returns immediately.
-    pub fn stop_background_saver() {
-        AegMemoryEngine::stop_background_saver();
+    /// `get_value_uncached` for a key that may be qualified with its collection.
+    pub fn get_qualified_uncached(qualified: &str) -> Result<Option<String>, String> {
+        let (collection, key) = Self::load().resolve_qualified(qualified)?;
+        Self::get_uncached_in(&collection, key)
+    }
+
+    /// Store `value` as JSON under `collection::key` (or `key` in the active
+    /// collection), like `put_qualified`. Read it back with `get_typed`.
+    pub fn put_typed<T: Serialize + ?Sized>(qualified: &str, value: &T) -> String {
+        match serde_json::to_string(value) {
+            Ok(json) => Self::put_qualified(qualified, &json),
+            Err(e) => format!(
+                "✗ Value for '{}' cannot be stored as JSON: {}",
+                qualified, e
+            ),
+        }
+    }
+
+    /// The value under `collection::key` (or `key` in the active
+    /// collection) decoded from JSON into `T`. A value that is not JSON of
+    /// that shape is an error naming the key.
+    pub fn get_typed<T: DeserializeOwned>(qualified: &str) -> Result<Option<T>, String> {
+        let Some(json) = Self::get_qualified(qualified)? else {
+            return Ok(None);
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Value of '{}' is not the expected JSON: {}", qualified, e))
+    }
+
+    /// The current TOTP code (RFC 6238) for the `otpauth://totp/` URI or
+    /// base32 secret stored under `collection::key` (or `key`), and how
+    /// many seconds it stays valid.
+    pub fn totp_now(qualified: &str) -> Result<TotpCode, String> {
+        let value = Self::get_qualified(qualified)?
+            .map(Zeroizing::new)
+            .ok_or_else(|| format!("Key '{}' not found", qualified))?;
+        let secret: TotpSecret = value
+            .parse()
+            .map_err(|e| format!("'{}' does not hold a TOTP secret: {}", qualified, e))?;
+        Ok(secret.code_at(AegClock::now()))
+    }
+
+    /// `put_qualified` for a value that must be a JSON document; it is
+    /// checked and stored compacted, ready for `get_typed`.
+    pub fn put_json(qualified: &str, json: &str) -> String {
+        match serde_json::from_str::<serde_json::Value>(json) {
+            Ok(value) => Self::put_typed(qualified, &value),
+            Err(e) => format!("✗ Value for '{}' is not valid JSON: {}", qualified, e),
+        }
+    }
+
+    /// Recorded values of `key` in the active collection, oldest first. The
+    /// history survives deleting the key.
+    pub fn get_history(key: &str) -> Vec<ValueVersion> {
+        AegMemoryEngine::read_active(|engine| engine.history(key))
+    }
+
+    /// Make version `version` of `key` its current value again.
+    pub fn restore_version(key: &str, version: u64) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        AegMemoryEngine::with_active(|engine| match engine.restore_version(key, version) {
+            Ok(_) => format!(
+                "✓ Key '{}' restored to version {} in collection '{}' (in-memory)",
+                key, version, engine.collection_name
+            ),
+            Err(e) => format!("✗ {}", e),
+        })
+    }
+
+    /// Delete in-memory (non-blocking). Background saver will persist deletion later.
+    pub fn delete_value(key: &str) -> String {
+        Self::delete_value_in(&Self::load().active_collection, key)
+    }
+
+    /// `delete_value` for `collection::key`, or the active collection when
+    /// the key is not qualified.
+    pub fn delete_qualified(qualified: &str) -> String {
+        match Self::load().resolve_qualified(qualified) {
+            Ok((collection, key)) => Self::delete_value_in(&collection, key),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    fn delete_value_in(collection: &str, key: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if let Err(e) = Self::load().ensure_unsealed(collection) {
+            return format!("✗ {}", e);
+        }
+        AegMemoryEngine::with_engine(collection, |engine| {
+            if engine.contains(key) {
+                engine.delete(key);
+                // no save here
+                format!(
+                    "✓ Key '{}' deleted from collection '{}' (in-memory)",
+                    key, engine.collection_name
+                )
+            } else {
+                format!(
+                    "✗ Key '{}' not found in collection '{}' (in-memory)",
+                    key, engine.collection_name
+                )
+            }
+        })
+    }
+
+    /// Rename a key of the active collection, keeping its metadata and
+    /// history. The new name must follow the collection's naming convention.
+    pub fn rename_key(key: &str, new_key: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let core = Self::load();
+        if let Some(convention) = core.key_convention(&core.active_collection)
+            && let Err(e) = convention.check(new_key)
+        {
+            return format!("✗ {}", e);
+        }
+        AegMemoryEngine::with_active(|engine| match engine.rename_key(key, new_key) {
+            Ok(()) => format!(
+                "✓ Key '{}' renamed to '{}' in collection '{}' (in-memory)",
+                key, new_key, engine.collection_name
+            ),
+            Err(e) => format!("✗ {}", e),
+        })
+    }
+
+    /// Clear in-memory values (non-blocking). Background saver will persist later.
+    ///
+    /// Unless turned off with `set_clear_snapshot(false)`, pending changes
+    /// are saved and the store is snapshotted first, so
+    /// `restore_last_snapshot` undoes the clear. If the snapshot cannot be
+    /// taken nothing is cleared.
+    pub fn clear_values() -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut note = String::new();
+        if !AegFileSystem::read_store_config().skip_clear_snapshot {
+            let collection = Self::load().active_collection;
+            match Self::clear_snapshot(&collection) {
+                Ok(label) => {
+                    note = format!(
+                        "\n  Snapshot '{}' taken first; undo with `snapshot restore --last`",
+                        label
+                    )
+                }
+                Err(e) => {
+                    return format!(
+                        "✗ Nothing cleared: could not snapshot the store first: {}",
+                        e
+                    );
+                }
+            }
+        }
+        AegMemoryEngine::with_active(|engine| {
+            engine.clear();
+            format!(
+                "✓ All keys cleared from collection '{}' (in-memory){}",
+                engine.collection_name, note
+            )
+        })
+    }
+
+    /// Snapshot the store ahead of clearing `collection`, labelled
+    /// `pre-clear-<collection>-<time>`.
+    fn clear_snapshot(collection: &str) -> Result<String, String> {
+        Self::flush_now();
+        let name: String = collection
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let base = format!("{}-{}-{}", CLEAR_SNAPSHOT_PREFIX, name, now);
+        let mut label = base.clone();
+        let mut n = 1;
+        while SnapshotManager::find(&label).is_some() {
+            n += 1;
+            label = format!("{}-{}", base, n);
+        }
+        SnapshotManager::create(&AegFileSystem::get_config_path(), &label).map(|s| s.label)
+    }
+
+    /// Snapshot the store before every `clear` (the default), or stop.
+    pub fn set_clear_snapshot(enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.skip_clear_snapshot = !enabled;
+        AegFileSystem::write_store_config(&config);
+        format!(
+            "✓ Snapshots before clear {}",
+            if enabled { "enabled" } else { "disabled" }
+        )
+    }
+
+    /// Begin a transaction on the active collection. Staged puts/deletes are
+    /// applied together on `commit()` and discarded on `rollback()`.
+    /// Unsaved in-memory changes for `name`, or for every loaded collection
+    /// when `None`. Only key names are reported, never values.
+    pub fn pending_changes(name: Option<&str>) -> Result<Vec<PendingChanges>, String> {
+        let names = match name {
+            Some(n) => vec![n.to_string()],
+            None => AegMemoryEngine::cached_collections(),
+        };
+        names
+            .iter()
+            .map(|n| AegMemoryEngine::pending_changes(n))
+            .collect()
+    }
+
+    /// Changes made in this process to keys starting with `prefix`, in any
+    /// collection, as they happen in memory.
+    pub fn subscribe(prefix: &str) -> Receiver<ChangeEvent> {
+        AegWatch::subscribe(prefix)
+    }
+
+    /// Changes to keys starting with `prefix` saved by any process, polled
+    /// from disk every `interval`.
+    pub fn watch_store(prefix: &str, interval: Duration) -> Receiver<ChangeEvent> {
+        AegWatch::watch_disk(prefix, interval)
+    }
+
+    pub fn begin_transaction() -> AegTransaction {
+        AegTransaction::begin()
+    }
+
+    /// Flush memory and check that every store file decrypts with the current key.
+    pub fn verify_store() -> VerificationReport {
+        Self::flush_now();
+        AegVerifier::verify_all(&AegFileSystem::read_authorization_key())
+    }
+
+    /// Collections found unreadable and recovered since the last call
+    /// (see `AegRecovery`).
+    pub fn take_recovery_reports() -> Vec<RecoveryReport> {
+        AegRecovery::take_reports()
+    }
+
+    /// Decrypt every collection's files as they are on disk and check their
+    /// checksums and structure, reported per collection, and check the files
+    /// against the store manifest. Nothing is written, so changes not yet
+    /// saved are not covered.
+    pub fn verify() -> IntegrityReport {
+        let dir = AegFileSystem::get_config_path();
+        let key = AegFileSystem::read_authorization_key();
+        let mut report = AegVerifier::verify_collections(&dir, &key);
+        report.manifest = Some(AegIntegrity::verify(&dir, &key));
+        report
+    }
+
+    /// Check every store file against the store manifest (see
+    /// `AegIntegrity`), catching files changed, swapped or rolled back
+    /// behind the store's back.
+    pub fn verify_manifest() -> ManifestReport {
+        AegIntegrity::verify(
+            &AegFileSystem::get_config_path(),
+            &AegFileSystem::read_authorization_key(),
+        )
+    }
+
+    /// Store files found not to match the manifest when loaded since the
+    /// last call.
+    pub fn take_manifest_mismatches() -> Vec<ManifestMismatch> {
+        AegIntegrity::take_mismatches()
+    }
+
+    /// Bind the store to this machine (or undo it). Every encrypted file is
+    /// re-encrypted with the new effective key and verified before the switch
+    /// is recorded; on any failure the files are restored and nothing changes.
+    /// Stop the background saver before calling this.
+    pub fn set_machine_binding(enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let config = AegFileSystem::read_store_config();
+        let state = if enabled { "enabled" } else { "disabled" };
+        if config.machine_binding == enabled {
+            return format!("✓ Machine binding already {}", state);
+        }
+
+        let stored = AegFileSystem::read_stored_authorization_key();
+        let mut new_config = config.clone();
+        new_config.machine_binding = enabled;
+        let keys = AegFileSystem::effective_key(&stored, &config).and_then(|old| {
+            AegFileSystem::effective_key(&stored, &new_config).map(|new| (old, new))
+        });
+        let (old_key, new_key) = match keys {
+            Ok(keys) => keys,
+            Err(e) => return format!("✗ Cannot bind to this machine: {}", e),
+        };
+        if let Err(e) = Self::rekey_store(&old_key, &new_key) {
+            return e;
+        }
+        AegFileSystem::write_store_config(&new_config);
+        format!("✓ Machine binding {}", state)
+    }
+
+    /// Bind the store's key to a PKCS#11 token (or undo it with `None`),
+    /// re-encrypting and verifying every file like `set_machine_binding`.
+    /// The token must be present, and `AEGISR_HSM_PIN` set if it needs a PIN.
+    pub fn set_hsm(hsm: Option<HsmConfig>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let config = AegFileSystem::read_store_config();
+        if config.hsm == hsm {
+            return match hsm {
+                Some(_) => "✓ Store key already bound to this HSM token".to_string(),
+                None => "✓ Store key is not bound to an HSM".to_string(),
+            };
+        }
+        let stored = AegFileSystem::read_stored_authorization_key();
+        let mut new_config = config.clone();
+        new_config.hsm = hsm;
+        let keys = AegFileSystem::effective_key(&stored, &config).and_then(|old| {
+            AegFileSystem::effective_key(&stored, &new_config).map(|new| (old, new))
+        });
+        let (old_key, new_key) = match keys {
+            Ok(keys) => keys,
+            Err(e) => return format!("✗ Cannot use the HSM: {}", e),
+        };
+        if let Err(e) = Self::rekey_store(&old_key, &new_key) {
+            return e;
+        }
+        let message = match &new_config.hsm {
+            Some(hsm) => format!(
+                "✓ Store key bound to HSM key '{}' via {}",
+                hsm.key_label, hsm.module
+            ),
+            None => "✓ Store key unbound from the HSM".to_string(),
+        };
+        AegFileSystem::write_store_config(&new_config);
+        message
+    }
+
+    /// Ask the store's HSM token for its key now, so a missing token or
+    /// wrong PIN is reported here instead of failing the first read.
+    pub fn unlock_hsm() -> String {
+        let config = AegFileSystem::read_store_config();
+        let Some(hsm) = &config.hsm else {
+            return "✓ Store key is not bound to an HSM".to_string();
+        };
+        match AegHsm::bind_key(hsm, &AegFileSystem::read_stored_authorization_key()) {
+            Ok(_) => format!("✓ HSM key '{}' unlocked", hsm.key_label),
+            Err(e) => format!("✗ HSM unavailable: {}", e),
+        }
+    }
+
+    /// Where the stored key of the current store is kept.
+    pub fn key_backend() -> KeyBackend {
+        let config = AegFileSystem::read_store_config();
+        if config.hardware_key.is_some() {
+            KeyBackend::Hardware
+        } else if config.keyring.is_some() {
+            KeyBackend::Keyring
+        } else {
+            KeyBackend::File
+        }
+    }
+
+    /// Move the stored key to `backend`: the AUTHORIZATION_KEY file, the OS
+    /// keyring, or wrapped by the hardware token in the default slot (see
+    /// `set_hardware_key`). The key itself does not change, so nothing is
+    /// re-encrypted. The old place is cleared only once the new one holds
+    /// the key; when it cannot take it, the key stays where it was and the
+    /// error is returned.
+    pub fn set_key_backend(backend: KeyBackend) -> String {
+        Self::move_stored_key(backend, DEFAULT_TOKEN_SLOT)
+    }
+
+    /// Wrap the stored key with the challenge-response secret in `slot` of
+    /// a hardware token (see `AegHardwareKey`); from then on the store does
+    /// not open without the token.
+    pub fn set_hardware_key(slot: u8) -> String {
+        Self::move_stored_key(KeyBackend::Hardware, slot)
+    }
+
+    fn move_stored_key(backend: KeyBackend, slot: u8) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        let rewrap = config.hardware_key.as_ref().is_some_and(|w| w.slot != slot);
+        if Self::key_backend() == backend && !(backend == KeyBackend::Hardware && rewrap) {
+            return format!(
+                "✓ Authorization key already kept in the {}",
+                AegFileSystem::key_provider().name()
+            );
+        }
+        let stored = match AegFileSystem::load_stored_key() {
+            Ok(stored) => stored,
+            Err(e) => return format!("✗ {}", e),
+        };
+        let current = AegFileSystem::key_provider();
+        let path = AegFileSystem::get_config_path().join(STORE_AUTHORIZATION_KEY);
+        let storage = AegFileSystem::storage();
+        let old_keyring = config.keyring.take();
+        config.hardware_key = None;
+        let moved = match backend {
+            KeyBackend::File => storage
+                .write(&path, stored.as_bytes())
+                .map_err(|e| format!("Failed to write authorization key: {}", e)),
+            KeyBackend::Keyring => AegKeyring::store_key(&stored)
+                .map(|keyring| config.keyring = Some(keyring))
+                .map_err(|e| format!("The OS keyring cannot hold the key ({})", e)),
+            KeyBackend::Hardware => AegHardwareKey::wrap(&stored, slot)
+                .map(|wrapped| config.hardware_key = Some(wrapped))
+                .map_err(|e| format!("The hardware token cannot wrap the key ({})", e)),
+        };
+        if let Err(e) = moved {
+            return format!("✗ {}; it stays in the {}", e, current.name());
+        }
+        AegFileSystem::write_store_config(&config);
+
+        if backend != KeyBackend::File
+            && storage.exists(&path)
+            && AegFileSystem::shred_file(&path).is_err()
+        {
+            let _ = storage.remove(&path);
+        }
+        let moved_to = format!(
+            "✓ Authorization key moved to the {}",
+            AegFileSystem::key_provider().name()
+        );
+        match old_keyring.map(|keyring| (AegKeyring::delete_key(&keyring), keyring)) {
+            Some((Err(e), keyring)) => format!(
+                "{}, but its old keyring entry '{}' could not be removed: {}",
+                moved_to, keyring.account, e
+            ),
+            _ => moved_to,
+        }
+    }
+
+    /// Protect the store with a passphrase, change it, or remove it with
+    /// `new` set to `None`; `current` is the passphrase in effect, if any.
+    /// Every store file and snapshot is re-encrypted under the new key,
+    /// calling `progress` after each file, and verified. The old salt stays
+    /// in config.aeg until then, and on any failure the files are restored
+    /// and nothing changes. Stop the background saver before calling this.
+    pub fn change_passphrase(
+        current: Option<&str>,
+        new: Option<&str>,
+        mut progress: impl FnMut(&RekeyProgress),
+    ) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if AegFileSystem::in_duress_session() {
+            return "✗ Cannot change the passphrase while the decoy store is open".into();
+        }
+        let config = AegFileSystem::read_store_config();
+        if config.passphrase.is_none() && new.is_none() {
+            return "✓ Store is not protected by a passphrase".into();
+        }
+        if config.passphrase.is_some() && current.is_none() {
+            return "✗ The current passphrase is required".into();
+        }
+        if new.is_some_and(str::is_empty) {
+            return "✗ The new passphrase must not be empty".into();
+        }
+
+        let stored = AegFileSystem::read_stored_authorization_key();
+        let old_key = match AegFileSystem::effective_key_with(&stored, &config, current) {
+            Ok(key) => key,
+            Err(e) => return format!("✗ Cannot derive the current key: {}", e),
+        };
+        let lock = AegFileSystem::get_config_path().join(STORE_COLLECTION);
+        if AegFileSystem::storage().exists(&lock)
+            && !AegVerifier::verify_file(&lock, &old_key).passed()
+        {
+            return "✗ Wrong passphrase".into();
+        }
+        // unsaved changes are flushed under the current key
+        if config.passphrase.is_some() {
+            AegFileSystem::set_passphrase(current);
+        }
+
+        let mut new_config = config.clone();
+        new_config.passphrase = new.map(|_| PassphraseConfig {
+            salt: AegCrypto::encode_base64(AegCrypto::generate_random_bytes()),
+        });
+        let new_key = match AegFileSystem::effective_key_with(&stored, &new_config, new) {
+            Ok(key) => key,
+            Err(e) => return format!("✗ Cannot derive the new key: {}", e),
+        };
+        let rekeyed = match Self::rekey_store_with(&old_key, &new_key, &mut progress) {
+            Ok(n) => n,
+            Err(e) => return e,
+        };
+        AegFileSystem::write_store_config(&new_config);
+        AegFileSystem::set_passphrase(new);
+        let done = match (config.passphrase.is_some(), new.is_some()) {
+            (true, true) => "Passphrase changed",
+            (false, true) => "Store protected by a passphrase",
+            _ => "Passphrase removed",
+        };
+        format!("✓ {}, {} files re-encrypted", done, rekeyed)
+    }
+
+    /// Re-encrypt every store file and snapshot from `old_key` to `new_key`
+    /// and verify the result; on failure the files are restored and the
+    /// error is returned as a "✗" message.
+    fn rekey_store(old_key: &str, new_key: &str) -> Result<(), String> {
+        Self::rekey_store_with(old_key, new_key, &mut |_| {}).map(|_| ())
+    }
+
+    /// `rekey_store`, reporting each re-encrypted file to `progress`.
+    /// Returns how many files were re-encrypted.
+    fn rekey_store_with(
+        old_key: &str,
+        new_key: &str,
+        progress: &mut dyn FnMut(&RekeyProgress),
+    ) -> Result<usize, String> {
+        Self::flush_now();
+        let dir = AegFileSystem::get_config_path();
+        let snapshots = SnapshotManager::list();
+        let backups = AegBackups::files(&dir);
+        let total = AegFileSystem::list_store_files(&dir).len()
+            + backups.len()
+            + snapshots
+                .iter()
+                .map(|s| AegFileSystem::list_store_files(&s.path).len())
+                .sum::<usize>();
+        let mut done = 0;
+        let mut report = |prefix: &Path, name: &str| {
+            done += 1;
+            progress(&RekeyProgress {
+                done,
+                total,
+                file: prefix.join(name).to_string_lossy().into_owned(),
+            });
+        };
+
+        let mut originals = AegFileSystem::capture_store_files(&dir);
+        if let Err(e) =
+            AegFileSystem::rekey_directory_with(&dir, &dir, old_key, new_key, &mut |n| {
+                report(Path::new(""), n)
+            })
+        {
+            return Err(format!(
+                "✗ Re-encryption failed, store left unchanged: {}",
+                e
+            ));
+        }
+        // snapshots must stay restorable under the new key
+        for snapshot in &snapshots {
+            originals.extend(AegFileSystem::capture_store_files(&snapshot.path));
+            let prefix = snapshot.path.strip_prefix(&dir).unwrap_or(&snapshot.path);
+            if let Err(e) = AegFileSystem::rekey_directory_with(
+                &snapshot.path,
+                &snapshot.path,
+                old_key,
+                new_key,
+                &mut |n| report(prefix, n),
+            ) {
+                AegFileSystem::restore_files(&originals);
+                return Err(format!(
+                    "✗ Re-encrypting snapshot '{}' failed, store left unchanged: {}",
+                    snapshot.label, e
+                ));
+            }
+        }
+
+        // the audit log's key is derived from the store key
+        match AegAudit::rekey(old_key, new_key) {
+            Ok(Some(original)) => originals.push((AegAudit::log_path(), Some(original))),
+            Ok(None) => {}
+            Err(e) => {
+                AegFileSystem::restore_files(&originals);
+                return Err(format!(
+                    "✗ Re-encrypting the audit log failed, store left unchanged: {}",
+                    e
+                ));
+            }
+        }
+
+        // and so must rolling backups
+        let storage = AegFileSystem::storage();
+        for (path, live) in &backups {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let rekeyed = storage
+                .read(path)
+                .map_err(|e| format!("read: {}", e))
+                .and_then(|content| {
+                    let rekeyed = AegFileSystem::rekey_content(live, &content, old_key, new_key)?;
+                    report(Path::new(STORE_BACKUPS_DIR), &name);
+                    originals.push((path.clone(), Some(content)));
+                    storage
+                        .write(path, &rekeyed)
+                        .map_err(|e| format!("write: {}", e))
+                });
+            if let Err(e) = rekeyed {
+                AegFileSystem::restore_files(&originals);
+                return Err(format!(
+                    "✗ Re-encrypting backup '{}' failed, store left unchanged: {}",
+                    name, e
+                ));
+            }
+        }
+
+        let report = AegVerifier::verify_all(new_key);
+        if !report.passed() {
+            AegFileSystem::restore_files(&originals);
+            return Err(format!(
+                "✗ Verification failed, store left unchanged:\n{}",
+                report.summary()
+            ));
+        }
+        // the manifest is signed with a key derived from the store key
+        let _ = AegIntegrity::refresh(&dir, new_key);
+        Ok(done)
+    }
+
+    /// Choose the algorithm for collection files written from now on. Every
+    /// file records its own algorithm, so existing files stay readable and
+    /// switch over the next time their collection is saved.
+    pub fn set_cipher(cipher: Cipher) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        if config.cipher == cipher {
+            return format!("✓ Cipher already {}", cipher);
+        }
+        config.cipher = cipher;
+        AegFileSystem::write_store_config(&config);
+        format!("✓ Cipher set to {}", cipher)
+    }
+
+    /// How `put_value` treats values that look like mistakes.
+    pub fn set_lint_level(level: LintLevel) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.lint = level;
+        AegFileSystem::write_store_config(&config);
+        format!("✓ Value linting set to {}", level)
+    }
+
+    /// What expiry does while the system clock is detected to be off.
+    pub fn set_clock_skew_policy(policy: ClockSkewPolicy) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.clock_skew = policy;
+        AegFileSystem::write_store_config(&config);
+        format!("✓ Clock skew policy set to {}", policy)
+    }
+
+    /// Turn the duplicate-value warning of `put_value` on or off.
+    pub fn set_duplicate_warning(enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.warn_duplicates = enabled;
+        AegFileSystem::write_store_config(&config);
+        format!(
+            "✓ Duplicate value warnings {}",
+            if enabled { "enabled" } else { "disabled" }
+        )
+    }
+
+    /// Compress collection files whose serialized payload is at least
+    /// `min_bytes` long, or stop compressing with `None`. Applies from each
+    /// collection's next save; files already written stay readable either way.
+    pub fn set_compression(min_bytes: Option<usize>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.compress_min_bytes = min_bytes;
+        AegFileSystem::write_store_config(&config);
+        match min_bytes {
+            Some(min) => format!("✓ Compressing collections of {} bytes or more", min),
+            None => "✓ Compression disabled".to_string(),
+        }
+    }
+
+    /// Keep `generations` previous versions of each collection file, taken
+    /// before every save (see `AegBackups`); 0 stops taking them. Lowering
+    /// the number prunes each collection's extra backups on its next save.
+    pub fn set_backup_generations(generations: usize) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.backup_generations = generations;
+        AegFileSystem::write_store_config(&config);
+        match generations {
+            0 => "✓ Rolling backups disabled".to_string(),
+            n => format!("✓ Keeping {} backup generation(s) of each collection", n),
+        }
+    }
+
+    /// Roll collection `name` back to backup `generation` (1 is the file
+    /// replaced by the latest save). Changes not yet saved are discarded.
+    /// The file being replaced becomes generation 1 in turn, so a restore
+    /// can itself be undone.
+    pub fn restore_backup(name: &str, generation: usize) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if !Self::load().collections.contains(&name.to_string()) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        let dir = AegFileSystem::get_config_path();
+        let storage = AegFileSystem::storage();
+        let backup = AegBackups::backup_file(&dir, name, generation);
+        let Ok(content) = storage.read(&backup) else {
+            let kept = AegBackups::generations(&dir, name);
+            return match kept.last() {
+                Some(last) => format!(
+                    "✗ Collection '{}' has no backup generation {} (1 to {} kept)",
+                    name, generation, last
+                ),
+                None => format!("✗ Collection '{}' has no backups", name),
+            };
+        };
+        if let Err(e) = AegMemoryEngine::open_collection_file(name, &content) {
+            return format!(
+                "✗ Backup generation {} of '{}' is unreadable: {}",
+                generation, name, e
+            );
+        }
+
+        let restored = AegFileSystem::lock_store(&dir).and_then(|_lock| {
+            let keep = AegFileSystem::read_store_config().backup_generations;
+            AegBackups::rotate(&dir, name, keep)?;
+            AegMemoryEngine::evict(name);
+            let file = format!("collection_{}.aekv", name);
+            let path = dir.join(&file);
+            storage
+                .write(&path, &content)
+                .map_err(|e| format!("write {}: {}", path.display(), e))?;
+            let _ = AegIntegrity::record(&dir, &[file]);
+            Ok(())
+        });
+        match restored {
+            Ok(()) => format!(
+                "✓ Collection '{}' restored from backup generation {}",
+                name, generation
+            ),
+            Err(e) => format!("✗ Restore failed: {}", e),
+        }
+    }
+
+    /// Check the store's authorization key: that it is base64 of exactly
+    /// 32 bytes and opens `collection.lock`. With `rederive`, key material
+    /// longer than a key is replaced by a key derived from it with HKDF, so
+    /// material from elsewhere can be dropped into the key file; keep a
+    /// copy of the material if anything else needs it.
+    pub fn check_key(rederive: bool) -> String {
+        let dir = AegFileSystem::get_config_path();
+        let storage = AegFileSystem::storage();
+        let stored = match AegFileSystem::load_stored_key() {
+            Ok(stored) => stored,
+            Err(e) => return format!("✗ {}", e),
+        };
+        if let Err(problem) = AegCrypto::validate_key(&stored) {
+            if !rederive {
+                return format!("✗ {}", problem);
+            }
+            let derived = match AegCrypto::rederive_key(&stored) {
+                Ok(derived) => derived,
+                Err(e) => return format!("✗ {}; cannot re-derive: {}", problem, e),
+            };
+            if let Err(e) = AegFileSystem::write_stored_key(&derived) {
+                return format!("✗ Failed to write authorization key: {}", e);
+            }
+            return "✓ Authorization key re-derived from the key material with HKDF-SHA256"
+                .to_string();
+        }
+        let key = match AegFileSystem::try_read_authorization_key() {
+            Ok(key) => key,
+            Err(e) => return format!("✗ {}", e),
+        };
+        let lock = dir.join(STORE_COLLECTION);
+        if storage.exists(&lock) && !AegVerifier::verify_file(&lock, &key).passed() {
+            return format!(
+                "✗ Authorization key is well-formed but does not open {}",
+                STORE_COLLECTION
+            );
+        }
+        "✓ Authorization key is valid (32 bytes) and opens the store".to_string()
+    }
+
+    /// Whether `dir` is absent or has nothing in it.
+    fn is_empty_dir(dir: &Path) -> bool {
+        let storage = AegFileSystem::storage();
+        storage.list(dir).unwrap_or_default().is_empty()
+            && storage.list_dirs(dir).unwrap_or_default().is_empty()
+    }
+
+    /// Write a point-in-time copy of every collection to `dest`, unsaved
+    /// changes included, without stopping writers while it is encrypted and
+    /// written (see `AegMemoryEngine::capture_consistent`). The copy has the
+    /// layout of a snapshot, still encrypted with this store's key, and
+    /// appears at `dest` only once complete. `dest` must be empty or absent.
+    pub fn backup(dest: &Path) -> String {
+        let storage = AegFileSystem::storage();
+        if !Self::is_empty_dir(dest) {
+            return format!("✗ Destination '{}' is not empty", dest.display());
+        }
+        let Some(name) = dest.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            return format!("✗ Invalid destination '{}'", dest.display());
+        };
+
+        let dir = AegFileSystem::get_config_path();
+        let collection_lock = match storage.read(&dir.join(STORE_COLLECTION)) {
+            Ok(bytes) => bytes,
+            Err(e) => return format!("✗ Backup failed: read {}: {}", STORE_COLLECTION, e),
+        };
+        let emergency_lock = storage.read(&dir.join(STORE_EMERGENCY_FILE)).ok();
+        let engines = AegMemoryEngine::capture_consistent(&Self::load().collections);
+
+        let staging = dest.with_file_name(format!(".{}.tmp", name));
+        let written = storage
+            .create_dir_all(&staging)
+            .map_err(|e| format!("create {}: {}", staging.display(), e))
+            .and_then(|_| {
+                storage
+                    .write(&staging.join(STORE_COLLECTION), &collection_lock)
+                    .map_err(|e| format!("write {}: {}", STORE_COLLECTION, e))
+            })
+            .and_then(|_| match &emergency_lock {
+                Some(bytes) => storage
+                    .write(&staging.join(STORE_EMERGENCY_FILE), bytes)
+                    .map_err(|e| format!("write {}: {}", STORE_EMERGENCY_FILE, e)),
+                None => Ok(()),
+            })
+            .and_then(|_| {
+                engines.iter().try_for_each(|engine| {
+                    let file = format!("collection_{}.aekv", engine.collection_name);
+                    engine
+                        .encode_detached()
+                        .and_then(|bytes| {
+                            storage
+                                .write(&staging.join(&file), &bytes)
+                                .map_err(|e| e.to_string())
+                        })
+                        .map_err(|e| format!("write {}: {}", file, e))
+                })
+            })
+            .and_then(|_| {
+                // dest is empty or absent
+                if storage.exists(dest) {
+                    let _ = storage.remove_dir_all(dest);
+                }
+                storage
+                    .rename(&staging, dest)
+                    .map_err(|e| format!("finish backup: {}", e))
+            });
+        match written {
+            Ok(()) => format!(
+                "✓ Backup of {} collections written to '{}'",
+                engines.len(),
+                dest.display()
+            ),
+            Err(e) => {
+                let _ = storage.remove_dir_all(&staging);
+                format!("✗ Backup failed: {}", e)
+            }
+        }
+    }
+
+    /// Write a copy of the whole store to `dest` that is not bound to this
+    /// machine or an HSM token, so it can be opened elsewhere. `dest` must be empty or absent.
+    pub fn export_portable(dest: &Path) -> String {
+        if !Self::is_empty_dir(dest) {
+            return format!("✗ Destination '{}' is not empty", dest.display());
+        }
+
+        Self::flush_now();
+        let dir = AegFileSystem::get_config_path();
+        let current = AegFileSystem::read_authorization_key();
+        let stored = AegFileSystem::read_stored_authorization_key();
+        let written = match AegFileSystem::rekey_directory(&dir, dest, &current, &stored) {
+            Ok(n) => n,
+            Err(e) => return format!("✗ Export failed: {}", e),
+        };
+
+        let mut config = AegFileSystem::read_store_config();
+        config.machine_binding = false;
+        config.hsm = None;
+        config.keyring = None;
+        config.hardware_key = None;
+        let config_json = serde_json::to_string_pretty(&config).expect("Serialize failed");
+        let storage = AegFileSystem::storage();
+        if let Err(e) = storage
+            .write(&dest.join(STORE_AUTHORIZATION_KEY), stored.as_bytes())
+            .and_then(|_| storage.write(&dest.join(STORE_CONFIG_AEG), config_json.as_bytes()))
+        {
+            return format!("✗ Export failed: {}", e);
+        }
+
+        format!(
+            "✓ Portable store ({} files) exported to '{}'",
+            written,
+            dest.display()
+        )
+    }
+
+    /// Copy every collection file and collection.lock into a new snapshot
+    /// labelled `label`. Pending changes are saved first.
+    pub fn snapshot(label: &str) -> String {
+        Self::flush_now();
+        match SnapshotManager::create(&AegFileSystem::get_config_path(), label) {
+            Ok(s) => format!("✓ Snapshot '{}' created ({} files)", s.label, s.files),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    pub fn list_snapshots() -> Vec<SnapshotInfo> {
+        SnapshotManager::list()
+    }
+
+    /// Put the store back to snapshot `label`. Everything changed since,
+    /// saved or not, is discarded. The snapshot is checked against the
+    /// current key before anything is replaced.
+    pub fn restore_snapshot(label: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let Some(snapshot) = SnapshotManager::find(label) else {
+            return format!("✗ Snapshot '{}' does not exist", label);
+        };
+        let report =
+            AegVerifier::verify_dir(&snapshot.path, &AegFileSystem::read_authorization_key());
+        if !report.passed() {
+            return format!(
+                "✗ Snapshot '{}' cannot be read with the current key:\n{}",
+                label,
+                report.summary()
+            );
+        }
+
+        AegMemoryEngine::reset_cache();
+        let dir = AegFileSystem::get_config_path();
+        let result = SnapshotManager::restore(&dir, &snapshot);
+        if result.is_ok() {
+            let _ = AegIntegrity::refresh(&dir, &AegFileSystem::read_authorization_key());
+        }
+        AegMemoryEngine::reset_cache();
+        match result {
+            Ok(n) => format!("✓ Store restored to snapshot '{}' ({} files)", label, n),
+            Err(e) => format!("✗ Restore failed, store left unchanged: {}", e),
+        }
+    }
+
+    /// Put the store back to the most recent snapshot, such as the one
+    /// `clear_values` takes.
+    pub fn restore_last_snapshot() -> String {
+        match SnapshotManager::list().pop() {
+            Some(snapshot) => Self::restore_snapshot(&snapshot.label),
+            None => "✗ There are no snapshots to restore".into(),
+        }
+    }
+
+    /// Two-way sync with the store at `dir` in `remote` (see `AegSync`).
+    pub fn sync_with(
+        remote: Arc<dyn StorageBackend>,
+        dir: PathBuf,
+        strategy: SyncStrategy,
+    ) -> Result<SyncReport, String> {
+        AegSync::sync(remote, dir, strategy)
+    }
+
+    /// Turn the audit log on or off. Turning it off keeps the entries
+    /// recorded so far; turning it back on continues the same chain.
+    pub fn set_audit(enabled: bool) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let mut config = AegFileSystem::read_store_config();
+        config.audit = enabled;
+        AegFileSystem::write_store_config(&config);
+        if enabled {
+            "✓ Audit log enabled".to_string()
+        } else {
+            "✓ Audit log disabled".to_string()
+        }
+    }
+
+    pub fn audit_log() -> Result<Vec<AuditEntry>, String> {
+        AegAudit::entries()
+    }
+
+    /// The audit entries `filter` matches, oldest first.
+    pub fn read_audit_log(filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+        AegAudit::filtered(filter)
+    }
+
+    /// Check the audit chain, and that it still contains `anchor`
+    /// (`<seq>:<hash>` as printed by `audit_anchor`) when one is given.
+    pub fn verify_audit(anchor: Option<&str>) -> String {
+        let anchor = match anchor.map(str::parse::<AuditHead>).transpose() {
+            Ok(anchor) => anchor,
+            Err(e) => return format!("✗ {}", e),
+        };
+        match AegAudit::verify(anchor.as_ref()) {
+            Ok(Some(head)) => format!("✓ Audit log intact ({} entries, head {})", head.seq, head),
+            Ok(None) => "✓ Audit log is empty".to_string(),
+            Err(e) => format!("✗ Audit log tampered with: {}", e),
+        }
+    }
+
+    /// An access report of the audit log between `from` and `to` (Unix
+    /// seconds or `YYYY-MM-DD`, inclusive); see `AegAudit::export`.
+    pub fn export_audit(
+        format: PlainFormat,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<String, String> {
+        let from = from.map(|t| AegAudit::parse_time(t, false)).transpose()?;
+        let to = to.map(|t| AegAudit::parse_time(t, true)).transpose()?;
+        AegAudit::export(format, from, to)
+    }
+
+    /// The verified head of the audit chain, to be timestamped outside the
+    /// store so truncating the log can be detected later.
+    pub fn audit_anchor() -> Result<AuditHead, String> {
+        AegAudit::verify(None)?.ok_or_else(|| "the audit log is empty".to_string())
+    }
+
+    /// Write collection `name` to `path` as a bundle encrypted with a key
+    /// derived from `password` (not the machine authorization key).
+    pub fn export_collection(name: &str, path: &Path, password: &str) -> String {
+        let payload = match Self::bundle_payload(name) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+        let count = payload.entries.len();
+        match AegBundle::seal(&payload, password).and_then(|b| b.write(path)) {
+            Ok(()) => format!(
+                "✓ Exported {} key(s) from collection '{}' to '{}'",
+                count,
+                name,
+                path.display()
+            ),
+            Err(e) => format!("✗ Export failed: {}", e),
+        }
+    }
+
+    /// Write collection `name` to `path` as an age file sealed to
+    /// `recipients`' SSH keys, so each of them can import it with their own
+    /// private key instead of a shared password.
+    pub fn export_collection_to(name: &str, path: &Path, recipients: &[SshRecipient]) -> String {
+        let payload = match Self::bundle_payload(name) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+        let sealed = payload.seal_for_recipients(recipients).and_then(|bytes| {
+            fs::write(path, bytes).map_err(|e| format!("write {}: {}", path.display(), e))
+        });
+        match sealed {
+            Ok(()) => format!(
+                "✓ Exported {} key(s) from collection '{}' to '{}' for {} recipient(s)",
+                payload.entries.len(),
+                name,
+                path.display(),
+                recipients.len()
+            ),
+            Err(e) => format!("✗ Export failed: {}", e),
+        }
+    }
+
+    /// Seal collection `name` to `recipient`'s public key (see
+    /// `SharePrivateKey`), so only the matching private key opens the
+    /// bundle.
+    pub fn export_shared(name: &str, recipient: &SharePublicKey) -> Result<SharedBundle, String> {
+        let payload = Self::bundle_payload(name)?;
+        SharedBundle::seal(&payload, recipient).map_err(|e| format!("✗ Share failed: {}", e))
+    }
+
+    /// Like `import_collection`, for a bundle shared with the public half
+    /// of `key`.
+    pub fn import_shared(bundle: &SharedBundle, key: &SharePrivateKey) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        match bundle.open(key) {
+            Ok(payload) => Self::merge_bundle(payload),
+            Err(e) => format!("✗ Import failed: {}", e),
+        }
+    }
+
+    /// Give `contacts` emergency access to collection `name` after `wait`:
+    /// writes the bundle to hand them to `path` (see `AegEmergency`).
+    pub fn emergency_setup(
+        name: &str,
+        contacts: &[SshRecipient],
+        wait: Duration,
+        path: &Path,
+    ) -> String {
+        let written = AegEmergency::setup(name, contacts, wait).and_then(|(grant, bytes)| {
+            fs::write(path, bytes)
+                .map(|_| grant)
+                .map_err(|e| format!("write {}: {}", path.display(), e))
+        });
+        match written {
+            Ok(grant) => format!(
+                "✓ Emergency grant {} for collection '{}': {} contact(s) can open '{}' {} s after requesting access",
+                grant.id,
+                name,
+                grant.contacts.len(),
+                path.display(),
+                grant.wait_secs
+            ),
+            Err(e) => format!("✗ Emergency setup failed: {}", e),
+        }
+    }
+
+    /// Import an emergency bundle, as the contact, with the private key at
+    /// `identity` and the escrow key released by the owner's store.
+    pub fn emergency_import(path: &Path, identity: &Path, escrow_key: &str) -> String {
+        let opened = SshIdentity::read(identity).and_then(|identity| {
+            let bytes = fs::read(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+            AegEmergency::open(&bytes, &identity, escrow_key)
+        });
+        match opened {
+            Ok(payload) => Self::merge_bundle(payload),
+            Err(e) => format!("✗ Import failed: {}", e),
+        }
+    }
+
+    /// Write collection `name` to `path` as a self-contained HTML page that
+    /// shows it read-only in a browser after entering `passphrase`.
+    pub fn export_viewer(name: &str, path: &Path, passphrase: &str) -> String {
+        let payload = match Self::bundle_payload(name) {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+        let written = AegViewer::seal(&payload, passphrase)
+            .and_then(|viewer| viewer.render(name))
+            .and_then(|html| {
+                fs::write(path, html).map_err(|e| format!("write {}: {}", path.display(), e))
+            });
+        match written {
+            Ok(()) => format!(
+                "✓ Exported {} key(s) from collection '{}' to viewer page '{}'",
+                payload.entries.len(),
+                name,
+                path.display()
+            ),
+            Err(e) => format!("✗ Export failed: {}", e),
+        }
+    }
+
+    pub(crate) fn bundle_payload(name: &str) -> Result<BundlePayload, String> {
+        if !Self::load().collections.contains(&name.to_string()) {
+            return Err(format!("✗ Collection '{}' does not exist", name));
+        }
+        Ok(AegMemoryEngine::read_engine(name, |engine| BundlePayload {
+            collection: name.to_string(),
+            entries: engine.list_owned(),
+            key_meta: engine.key_meta.clone(),
+        }))
+    }
+
+    /// Read a bundle and merge its entries into the collection it was
+    /// exported from, creating the collection if needed. Existing keys are
+    /// overwritten by the bundle's values.
+    pub fn import_collection(path: &Path, password: &str) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        if fs::read(path).is_ok_and(|bytes| AegAge::is_age(&bytes)) {
+            return "✗ Import failed: the file is sealed to SSH keys; import it with an identity"
+                .into();
+        }
+        if SharedBundle::read(path).is_ok() {
+            return "✗ Import failed: the file is shared with a public key; import it with its private key"
+                .into();
+        }
+        match AegBundle::read(path).and_then(|b| b.open(password)) {
+            Ok(payload) => Self::merge_bundle(payload),
+            Err(e) => format!("✗ Import failed: {}", e),
+        }
+    }
+
+    /// Like `import_collection`, for a file exported to the SSH key whose
+    /// private half is at `identity` (such as `~/.ssh/id_ed25519`).
+    pub fn import_collection_with_identity(path: &Path, identity: &Path) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let opened = SshIdentity::read(identity).and_then(|identity| {
+            let bytes = fs::read(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+            if !AegAge::is_age(&bytes) {
+                return Err("not an age file; use the bundle password instead".into());
+            }
+            BundlePayload::open_with_identity(&bytes, &identity)
+        });
+        match opened {
+            Ok(payload) => Self::merge_bundle(payload),
+            Err(e) => format!("✗ Import failed: {}", e),
+        }
+    }
+
+    fn merge_bundle(payload: BundlePayload) -> String {
+        let hostile = payload.key_meta.iter().find_map(|(key, meta)| {
+            meta.env_name
+                .as_deref()
+                .filter(|n| !AegEnv::is_valid_name(n))
+                .map(|n| (key, n))
+        });
+        if let Some((key, env_name)) = hostile {
+            return format!(
+                "✗ Import failed: key '{}' has an invalid environment variable name '{}'",
+                key, env_name
+            );
+        }
+        let name = payload.collection.clone();
+        if !Self::load().collections.contains(&name) {
+            Self::create_collection(&name);
+        }
+        let (created, overwritten) = AegMemoryEngine::with_engine(&name, |engine| {
+            let overwritten = payload
+                .entries
+                .keys()
+                .filter(|k| engine.contains(k))
+                .count();
+            let total = payload.entries.len();
+            let key_meta = payload.key_meta;
+            engine.apply_batch(payload.entries.into_iter().map(|(k, v)| (k, Some(v))));
+            for (key, meta) in key_meta {
+                if engine.contains(&key) {
+                    // checked above
+                    let _ = engine.set_env_name(&key, meta.env_name);
+                }
+            }
+            (total - overwritten, overwritten)
+        });
+        format!(
+            "✓ Imported into collection '{}': {} created, {} overwritten (in-memory)",
+            name, created, overwritten
+        )
+    }
+
+    /// Render the active collection as unencrypted `format` text.
+    pub fn export_plain(format: PlainFormat) -> String {
+        let entries: HashMap<String, String> =
+            AegMemoryEngine::read_active(|engine| match format {
+                PlainFormat::Dotenv => engine.env_vars().into_iter().collect(),
+                _ => engine.list_owned(),
+            });
+        AegPlain::encode(&entries, format)
+    }
+
+    /// Write the active collection to `path` unencrypted. Refuses unless the
+    /// caller has explicitly `confirmed` that secrets will leave the store
+    /// in the clear.
+    pub fn export_plain_to(path: &Path, format: PlainFormat, confirmed: bool) -> String {
+        if !confirmed {
+            return "✗ Plain export writes secrets unencrypted; confirm to continue".into();
+        }
+        match fs::write(path, Self::export_plain(format)) {
+            Ok(()) => format!(
+                "✓ Exported active collection as unencrypted {} to '{}'",
+                format,
+                path.display()
+            ),
+            Err(e) => format!("✗ Export failed: {}", e),
+        }
+    }
+
+    /// Merge a plain JSON or CSV file into the active collection. Existing
+    /// keys are overwritten by the file's values.
+    pub fn import_plain(path: &Path, format: PlainFormat) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let entries = match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| AegPlain::decode(&text, format))
+        {
+            Ok(e) => e,
+            Err(e) => return format!("✗ Import failed: {}", e),
+        };
+        let (created, overwritten) =
+            AegMemoryEngine::with_active(|engine| Self::insert_counting(engine, entries));
+        format!(
+            "✓ Imported {}: {} created, {} overwritten (in-memory)",
+            format, created, overwritten
+        )
+    }
+
+    /// Bulk-insert the pairs of the `.env` file at `path` into `collection`
+    /// (the active one by default). Quoted values may span lines.
+    pub fn import_dotenv(path: &Path, collection: Option<&str>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let name = match collection {
+            Some(name) => name.to_string(),
+            None => Self::load().active_collection,
+        };
+        if !Self::load().collections.contains(&name) {
+            return format!("✗ Collection '{}' does not exist", name);
+        }
+        let entries = match fs::read_to_string(path)
+            .map_err(|e| format!("read {}: {}", path.display(), e))
+            .and_then(|text| AegPlain::decode(&text, PlainFormat::Dotenv))
+        {
+            Ok(e) => e,
+            Err(e) => return format!("✗ Import failed: {}", e),
+        };
+        let (created, overwritten) =
+            AegMemoryEngine::with_engine(&name, |engine| Self::insert_counting(engine, entries));
+        format!(
+            "✓ Imported {} into collection '{}': {} created, {} overwritten (in-memory)",
+            path.display(),
+            name,
+            created,
+            overwritten
+        )
+    }
+
+    /// Insert `entries`, returning how many keys were (created, overwritten).
+    fn insert_counting(
+        engine: &mut AegMemoryEngine,
+        entries: HashMap<String, String>,
+    ) -> (usize, usize) {
+        let overwritten = entries.keys().filter(|k| engine.contains(k)).count();
+        let total = entries.len();
+        engine.apply_batch(entries.into_iter().map(|(k, v)| (k, Some(v))));
+        (total - overwritten, overwritten)
+    }
+
+    /// Snapshot this process's environment into the active collection,
+    /// keeping only variables whose name starts with `prefix_filter` when
+    /// given. Variables that are not valid UTF-8 are skipped.
+    pub fn capture_env(prefix_filter: Option<&str>) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let vars: Vec<(String, String)> = std::env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .filter(|(k, _)| prefix_filter.is_none_or(|p| k.starts_with(p)))
+            .collect();
+        let count = vars.len();
+        AegMemoryEngine::with_active(|engine| {
+            engine.apply_batch(vars.into_iter().map(|(k, v)| (k, Some(v))))
+        });
+        format!("✓ Captured {} environment variable(s) (in-memory)", count)
+    }
+
+    /// Create (or replace) the decoy store opened by `passphrase`. The decoy
+    /// looks like an ordinary store and its key never involves the real one.
+    pub fn setup_duress(passphrase: &str) -> String {
+        if AegFileSystem::in_duress_session() {
+            return "✗ Cannot configure duress from inside the decoy store".into();
+        }
+        let storage = AegFileSystem::storage();
+        let dir = AegFileSystem::get_decoy_path();
+        if storage.exists(&dir)
+            && let Err(e) = storage.remove_dir_all(&dir)
+        {
+            return format!("✗ Failed to replace decoy store: {}", e);
+        }
+
+        let salt = AegCrypto::generate_random_bytes();
+        let key = match AegCrypto::derive_duress_key(passphrase, &salt) {
+            Ok(k) => k,
+            Err(e) => return format!("✗ {}", e),
+        };
+        let lock = CollectionLock {
+            active: "default".to_string(),
+            collections: vec!["default".to_string()],
+            meta: HashMap::new(),
+        };
+        let lock_json = serde_json::to_string_pretty(&lock).expect("Serialize failed");
+        let config_json =
+            serde_json::to_string_pretty(&StoreConfig::default()).expect("Serialize failed");
+
+        let result = storage
+            .create_dir_all(&dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| {
+                storage
+                    .write(
+                        &dir.join(STORE_DURESS_SALT),
+                        AegCrypto::encode_base64(salt).as_bytes(),
+                    )
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|_| AegCrypto::seal(Cipher::Aes256Gcm, &key, lock_json.as_bytes()))
+            .and_then(|blob| {
+                storage
+                    .write(&dir.join(STORE_COLLECTION), blob.as_bytes())
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|_| {
+                // unused by the decoy, but makes it indistinguishable from a real store
+                storage
+                    .write(
+                        &dir.join(STORE_AUTHORIZATION_KEY),
+                        AegCrypto::create_authorization_key(Verbosity::default()).as_bytes(),
+                    )
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|_| {
+                storage
+                    .write(&dir.join(STORE_CONFIG_AEG), config_json.as_bytes())
+                    .map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => "✓ Decoy store configured".into(),
+            Err(e) => format!("✗ Failed to create decoy store: {}", e),
+        }
+    }
+
+    /// Open the decoy store for the rest of this process if `passphrase` is
+    /// the duress passphrase. Pending real data is flushed and dropped from
+    /// memory first; the real store's files are never touched afterwards.
+    pub fn unlock_duress(passphrase: &str) -> String {
+        if AegFileSystem::in_duress_session() {
+            return "✓ Unlocked".into();
+        }
+        let dir = AegFileSystem::get_decoy_path();
+        let key = AegFileSystem::storage()
+            .read(&dir.join(STORE_DURESS_SALT))
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+            .and_then(|salt| {
+                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, salt.trim())
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|salt| AegCrypto::derive_duress_key(passphrase, &salt));
+        let Ok(key) = key else {
+            return "✗ Invalid passphrase".into();
+        };
+        if !AegVerifier::verify_file(&dir.join(STORE_COLLECTION), &key).passed() {
+            return "✗ Invalid passphrase".into();
+        }
+
+        Self::flush_now();
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::begin_duress_session(dir, key);
+        // anything loaded from the real store while switching is dropped too
+        AegMemoryEngine::reset_cache();
+        "✓ Unlocked".into()
+    }
+
+    /// Leave the decoy store and return to the real one.
+    pub fn lock_duress() -> String {
+        if !AegFileSystem::in_duress_session() {
+            return "✗ The decoy store is not open".into();
+        }
+        Self::flush_now();
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::end_duress_session();
+        AegMemoryEngine::reset_cache();
+        "✓ Locked".into()
+    }
+
+    /// A fresh token the user must type back to confirm `nuke`.
+    pub fn nuke_token() -> String {
+        let bytes = AegCrypto::generate_random_bytes();
+        let suffix: String = bytes[..3].iter().map(|b| format!("{:02X}", b)).collect();
+        format!("DESTROY-{}", suffix)
+    }
+
+    /// Interactive wipe: refuses unless `typed` matches `expected` (from
+    /// `nuke_token`), then waits `delay` before calling `destroy_all`, giving
+    /// the caller a last chance to abort the process.
+    pub fn nuke(expected: &str, typed: &str, delay: Duration) -> String {
+        if expected.is_empty() || typed.trim() != expected {
+            return "✗ Confirmation token did not match; nothing was deleted".into();
+        }
+        thread::sleep(delay);
+        Self::destroy_all()
+    }
+
+    /// Irreversibly destroy the whole store: every collection, key, config,
+    /// the decoy store and the OS keyring entry of the stored key. Unsaved
+    /// changes are discarded, not flushed.
+    pub fn destroy_all() -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        Self::stop_background_saver();
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::end_duress_session();
+        let keyring = AegFileSystem::read_store_config().keyring;
+        let dir = AegFileSystem::get_real_config_path();
+        let mut result = AegFileSystem::shred_directory(&dir);
+        AegMemoryEngine::reset_cache();
+        // the stored key may live outside the directory
+        if let Some(keyring) = keyring
+            && let Err(e) = AegKeyring::delete_key(&keyring)
+        {
+            let e = format!("keyring entry: {}", e);
+            result = match result {
+                Ok(_) => Err(e),
+                Err(other) => Err(format!("{}; {}", other, e)),
+            };
+        }
+        match result {
+            Ok(n) => format!("✓ Store destroyed ({} files shredded)", n),
+            Err(e) => format!("✗ Store partially destroyed: {}", e),
+        }
+    }
+
+    /// Switch this process to the store in `dir`. Pending changes are saved
+    /// to the current store and its collections dropped from memory first.
+    pub fn set_store_dir(dir: PathBuf) -> String {
+        if AegFileSystem::in_duress_session() {
+            return "✗ Cannot switch stores while the decoy store is open".into();
+        }
+        Self::flush_now();
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::set_base_dir(dir);
+        AegMemoryEngine::reset_cache();
+        format!(
+            "✓ Using store at '{}'",
+            AegFileSystem::get_real_config_path().display()
+        )
+    }
+
+    pub fn create_profile(name: &str) -> String {
+        match ProfileManager::create(name) {
+            Ok(_) => format!("✓ Profile '{}' created", name),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    pub fn list_profiles() -> Vec<String> {
+        ProfileManager::list()
+    }
+
+    pub fn delete_profile(name: &str) -> String {
+        match ProfileManager::delete(name) {
+            Ok(_) => format!("✓ Profile '{}' deleted", name),
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    /// Switch this process to profile `name` and make sure its store files
+    /// exist.
+    pub fn use_profile(name: &str) -> String {
+        if !ProfileManager::exists(name) {
+            return format!("✗ Profile '{}' does not exist", name);
+        }
+        let path = match ProfileManager::path_for(name) {
+            Ok(p) => p,
+            Err(e) => return format!("✗ {}", e),
+        };
+        let msg = Self::set_store_dir(path);
+        if !msg.starts_with('✓') {
+            return msg;
+        }
+        AegFileSystem::initialize_config(Some(false), Verbosity::default());
+        format!("✓ Using profile '{}'", name)
+    }
+
+    /// Apply the nearest project manifest at or above `dir`: switch to its
+    /// profile, select its collection for this process, and check that its
+    /// required keys exist. Returns `None` when there is no manifest.
+    pub fn enter_project(dir: &Path) -> Result<Option<ProjectManifest>, String> {
+        let Some(manifest) = ProjectManifest::discover(dir) else {
+            return Ok(None);
+        };
+        let manifest = manifest?;
+        if let Some(profile) = &manifest.profile
+            && ProfileManager::current().as_deref() != Some(profile.as_str())
+        {
+            let msg = Self::use_profile(profile);
+            if !msg.starts_with('✓') {
+                return Err(msg.trim_start_matches("✗ ").to_string());
+            }
+        }
+        if let Some(collection) = &manifest.collection {
+            Self::use_collection_for_session(collection)?;
+        }
+        let missing = Self::missing_required_keys(&manifest);
+        if !missing.is_empty() {
+            return Err(format!(
+                "{} requires missing key(s): {}",
+                manifest.path.display(),
+                missing.join(", ")
+            ));
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Required keys of `manifest` absent from the active collection.
+    pub fn missing_required_keys(manifest: &ProjectManifest) -> Vec<String> {
+        AegMemoryEngine::read_active(|engine| {
+            manifest
+                .required
+                .iter()
+                .filter(|k| !engine.contains(k))
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// Force immediate flush (saves all collections to disk synchronously).
+    pub fn flush_now() {
+        AegMemoryEngine::save_all();
+    }
+
+    /// Start background saver thread. Safe to call multiple times: a running
+    /// saver is returned rather than started again.
+    /// interval_seconds: how often to persist (e.g. 1).
+    pub fn start_background_saver(interval_seconds: u64) -> SaverHandle {
+        AegMemoryEngine::start_background_saver(interval_seconds)
+    }
+
+    /// Choose when changes are persisted: on an interval, shortly after a
+    /// burst of writes, on every write, or only on `flush_now` (see
+    /// `PersistencePolicy`). Returns the background saver, if the policy
+    /// uses one.
+    pub fn set_persistence_policy(policy: PersistencePolicy) -> Option<SaverHandle> {
+        AegMemoryEngine::set_persistence_policy(policy)
+    }
+
+    pub fn persistence_policy() -> PersistencePolicy {
+        AegMemoryEngine::persistence_policy()
+    }
+
+    /// Stop the background saver after its final save, waiting for it.
+    pub fn stop_background_saver() {
+        AegMemoryEngine::stop_background_saver();
+    }
+
+    /// Hold off background saves during a bulk operation; dropping the
+    /// guard, or passing it to `resume_saver`, saves once (see `SaverPause`).
+    pub fn pause_saver() -> SaverPause {
+        AegMemoryEngine::pause_saver()
+    }
+
+    /// End a pause; returns how many collections the consolidated save wrote.
+    pub fn resume_saver(pause: SaverPause) -> usize {
+        pause.resume()
+    }
+}
+
+impl KeyValueStore for AegCore {
+    fn get(&self, key: &str) -> Option<String> {
+        Self::get_value_in(&self.active_collection, key).map(|value| value.to_plain_string())
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), String> {
+        let msg = Self::put_value_in(self, &self.active_collection, key, value);
+        match msg.strip_prefix("✗ ") {
+            Some(e) => Err(e.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, String> {
+        if !AegMemoryEngine::read_engine(&self.active_collection, |engine| engine.contains(key)) {
+            return Ok(false);
+        }
+        let msg = Self::delete_value_in(&self.active_collection, key);
+        match msg.strip_prefix("✗ ") {
+            Some(e) => Err(e.to_string()),
+            None => Ok(true),
+        }
+    }
+
+    fn list(&self) -> Vec<String> {
+        let mut keys: Vec<String> =
+            AegMemoryEngine::read_engine(&self.active_collection, |engine| {
+                engine.keys().iter().map(|k| k.to_string()).collect()
+            });
+        keys.sort();
+        keys
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        Self::flush_now();
+        Ok(())
     }
 }
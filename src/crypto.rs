@@ -1,24 +1,812 @@
+use crate::constant::{AUTH_KEY_BASE64_LEN, AUTH_KEY_BYTES};
+use crate::secret::SecretValue;
+use crate::verbosity::Verbosity;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
 use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
 use rand_core::{OsRng, TryRngCore};
-use zeroize::Zeroize;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::str::FromStr;
+use tracing::{Level, debug};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Marks content written by `AegCrypto::seal`. Never valid base64, so it
+/// cannot be confused with the older unprefixed formats.
+const ENVELOPE_PREFIX: &str = "aeg:";
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&*+-.:=?@^_~";
+/// Passphrase words, one per line: 1024 short common words, 10 bits each.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Where a store's stored key (the key AUTHORIZATION_KEY holds by default)
+/// is kept: the file (`FileKeyProvider`), the OS keyring
+/// (`KeyringKeyProvider`) or wrapped by a hardware token
+/// (`HardwareKeyProvider`). `AegFileSystem::key_provider` picks the one the
+/// store's config names; bindings (machine, passphrase, HSM) apply on top
+/// of whatever it returns.
+pub trait MasterKeyProvider: Send + Sync {
+    /// Where the key is, for messages (`key file`, `OS keyring`,
+    /// `hardware token`).
+    fn name(&self) -> &'static str;
+    fn load(&self) -> Result<Zeroizing<String>, String>;
+    /// Replace the stored key.
+    fn store(&self, key: &str) -> Result<(), String>;
+}
+
+/// AEAD algorithm for store files. The algorithm is recorded in every file
+/// written with `AegCrypto::seal`, so changing it never strands old files.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cipher {
+    #[default]
+    #[serde(rename = "aes256gcm")]
+    Aes256Gcm,
+    /// Faster than AES-GCM on CPUs without AES instructions.
+    #[serde(rename = "chacha20poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn tag(self) -> &'static str {
+        match self {
+            Self::Aes256Gcm => "aes256gcm",
+            Self::ChaCha20Poly1305 => "chacha20poly1305",
+        }
+    }
+
+    /// Identifier stored in binary file headers.
+    pub fn id(self) -> u8 {
+        match self {
+            Self::Aes256Gcm => 1,
+            Self::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Aes256Gcm),
+            2 => Some(Self::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "aes256gcm" => Some(Self::Aes256Gcm),
+            "chacha20poly1305" => Some(Self::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Cipher {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes" | "aes-gcm" | "aes256gcm" | "aes-256-gcm" => Ok(Self::Aes256Gcm),
+            "chacha20" | "chacha20poly1305" | "chacha20-poly1305" => Ok(Self::ChaCha20Poly1305),
+            other => Err(format!(
+                "unsupported cipher '{}' (expected aes-gcm or chacha20)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tag())
+    }
+}
+
+/// What `AegCrypto::generate_secret` produces.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretFormat {
+    /// Random characters from the selected classes.
+    #[default]
+    Chars,
+    /// Random words from the built-in list, diceware style.
+    Passphrase,
+    /// Random bytes, hex encoded.
+    Hex,
+    /// Random bytes, base64 encoded.
+    Base64,
+}
+
+impl SecretFormat {
+    /// Length used when none is given: characters, words or bytes.
+    pub fn default_length(self) -> usize {
+        match self {
+            Self::Chars => 24,
+            Self::Passphrase => 6,
+            Self::Hex | Self::Base64 => 32,
+        }
+    }
+}
+
+impl FromStr for SecretFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chars" | "password" => Ok(Self::Chars),
+            "passphrase" | "words" | "diceware" => Ok(Self::Passphrase),
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            other => Err(format!(
+                "unsupported secret format '{}' (expected chars, passphrase, hex or base64)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SecretFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Chars => "chars",
+            Self::Passphrase => "passphrase",
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+        })
+    }
+}
+
+/// Parameters for `AegCrypto::generate_secret`. The character classes
+/// apply to `SecretFormat::Chars` only; every selected class appears at
+/// least once.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecretSpec {
+    pub format: SecretFormat,
+    /// Characters, words or bytes, depending on the format.
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Between passphrase words.
+    pub separator: String,
+}
+
+impl Default for SecretSpec {
+    fn default() -> Self {
+        Self::new(SecretFormat::Chars)
+    }
+}
+
+impl SecretSpec {
+    /// `format` at its default length, with letters and digits.
+    pub fn new(format: SecretFormat) -> Self {
+        Self {
+            format,
+            length: format.default_length(),
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: false,
+            separator: "-".to_string(),
+        }
+    }
+
+    fn charset(&self) -> Vec<&'static str> {
+        [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .into_iter()
+        .filter_map(|(on, class)| on.then_some(class))
+        .collect()
+    }
+
+    /// Entropy of a secret generated from this spec, in bits.
+    pub fn entropy_bits(&self) -> f64 {
+        let per_item = match self.format {
+            SecretFormat::Chars => {
+                let size: usize = self.charset().iter().map(|class| class.len()).sum();
+                (size as f64).log2()
+            }
+            SecretFormat::Passphrase => (WORDLIST.lines().count() as f64).log2(),
+            SecretFormat::Hex | SecretFormat::Base64 => 8.0,
+        };
+        per_item * self.length as f64
+    }
+}
 
 pub struct AegCrypto;
 
 impl AegCrypto {
-    pub fn generate_random_bytes(_verbose: Option<bool>) -> [u8; 32] {
+    pub fn generate_random_bytes() -> [u8; 32] {
         let mut key = [0u8; 32];
         OsRng.try_fill_bytes(&mut key).unwrap();
         key
     }
 
-    pub fn encode_base64(input: impl AsRef<[u8]>, _verbose: Option<bool>) -> String {
+    /// A new random secret as described by `spec`.
+    pub fn generate_secret(spec: &SecretSpec) -> Result<SecretValue, String> {
+        if spec.length == 0 {
+            return Err("the length must be at least 1".into());
+        }
+        match spec.format {
+            SecretFormat::Chars => {
+                let classes = spec.charset();
+                if classes.is_empty() {
+                    return Err("select at least one character class".into());
+                }
+                if spec.length < classes.len() {
+                    return Err(format!(
+                        "{} characters cannot include all {} selected classes",
+                        spec.length,
+                        classes.len()
+                    ));
+                }
+                let alphabet: Vec<char> = classes.concat().chars().collect();
+                // drawn again until every class appears, which keeps the
+                // pick uniform among the secrets that qualify
+                loop {
+                    let secret = Zeroizing::new(
+                        (0..spec.length)
+                            .map(|_| Self::random_below(alphabet.len()).map(|i| alphabet[i]))
+                            .collect::<Result<String, String>>()?,
+                    );
+                    if classes
+                        .iter()
+                        .all(|class| secret.chars().any(|c| class.contains(c)))
+                    {
+                        return Ok(SecretValue::new(secret.as_str()));
+                    }
+                }
+            }
+            SecretFormat::Passphrase => {
+                let words: Vec<&str> = WORDLIST.lines().collect();
+                let picked = (0..spec.length)
+                    .map(|_| Self::random_below(words.len()).map(|i| words[i]))
+                    .collect::<Result<Vec<&str>, String>>()?;
+                Ok(SecretValue::new(picked.join(&spec.separator)))
+            }
+            SecretFormat::Hex | SecretFormat::Base64 => {
+                let mut bytes = Zeroizing::new(vec![0u8; spec.length]);
+                OsRng
+                    .try_fill_bytes(&mut bytes)
+                    .map_err(|e| format!("random generation: {}", e))?;
+                Ok(SecretValue::new(match spec.format {
+                    SecretFormat::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                    _ => Self::encode_base64(bytes.as_slice()),
+                }))
+            }
+        }
+    }
+
+    /// A uniform random index below `n`.
+    fn random_below(n: usize) -> Result<usize, String> {
+        let n = n as u64;
+        // values past the last whole multiple of `n` would favour the
+        // lowest indexes, so they are drawn again
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let value = OsRng
+                .try_next_u64()
+                .map_err(|e| format!("random generation: {}", e))?;
+            if value < limit {
+                return Ok((value % n) as usize);
+            }
+        }
+    }
+
+    pub fn encode_base64(input: impl AsRef<[u8]>) -> String {
         general_purpose::STANDARD.encode(input.as_ref())
     }
 
-    pub fn create_authorization_key(_verbose: Option<bool>) -> String {
-        let mut bytes = Self::generate_random_bytes(None);
+    pub fn create_authorization_key(verbosity: Verbosity) -> String {
+        let mut bytes = Self::generate_random_bytes();
         let hash = blake3::hash(&bytes);
         bytes.zeroize();
-        Self::encode_base64(hash.as_bytes(), None)
+        if verbosity.enabled(Level::DEBUG) {
+            debug!("generated a new authorization key");
+        }
+        Self::encode_base64(hash.as_bytes())
+    }
+
+    /// Decrypt an envelope from `seal`, or a base64 blob in the original
+    /// store format, where the nonce was the first 12 bytes of the
+    /// authorization key. Only kept to read and migrate old files; nothing
+    /// writes that format any more.
+    pub fn decrypt_blob(auth_key: &str, encoded: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        if let Some(opened) = Self::open_envelope(auth_key, encoded) {
+            return opened;
+        }
+        let key_bytes = Self::decode_key(auth_key)?;
+        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
+        let cipher = Aes256Gcm::new(key);
+        let nonce = Nonce::from_slice(&key_bytes[..12]);
+
+        let decoded = general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("base64 decode content: {}", e))?;
+
+        cipher
+            .decrypt(nonce, decoded.as_ref())
+            .map(Zeroizing::new)
+            .map_err(|e| format!("decrypt error: {:?}", e))
+    }
+
+    /// Encrypt a standalone record with a fresh random nonce.
+    /// Output is base64(nonce || ciphertext), safe to store one per line.
+    pub fn encrypt_record(auth_key: &str, plaintext: &[u8]) -> Result<String, String> {
+        let key_bytes = Self::decode_key(auth_key)?;
+        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
+        let cipher = Aes256Gcm::new(key);
+
+        let mut nonce_bytes = [0u8; 12];
+        OsRng
+            .try_fill_bytes(&mut nonce_bytes)
+            .map_err(|e| format!("nonce generation: {}", e))?;
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        let encrypted = cipher
+            .encrypt(nonce, plaintext)
+            .map_err(|e| format!("encrypt error: {:?}", e))?;
+
+        let mut out = Vec::with_capacity(nonce_bytes.len() + encrypted.len());
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&encrypted);
+        Ok(general_purpose::STANDARD.encode(out))
+    }
+
+    /// Decrypt a record produced by `encrypt_record` (or `seal`).
+    pub fn decrypt_record(auth_key: &str, record: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        if let Some(opened) = Self::open_envelope(auth_key, record) {
+            return opened;
+        }
+        let key_bytes = Self::decode_key(auth_key)?;
+        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
+        let cipher = Aes256Gcm::new(key);
+
+        let decoded = general_purpose::STANDARD
+            .decode(record.trim())
+            .map_err(|e| format!("base64 decode record: {}", e))?;
+        if decoded.len() < 12 {
+            return Err("record too short".into());
+        }
+        let (nonce_bytes, ciphertext) = decoded.split_at(12);
+
+        cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map(Zeroizing::new)
+            .map_err(|e| format!("decrypt error: {:?}", e))
+    }
+
+    /// Encrypt with `cipher` and a fresh random nonce into a single-line
+    /// envelope naming the algorithm: `aeg:<cipher>:base64(nonce || ciphertext)`.
+    /// `decrypt_blob` and `decrypt_record` both read it.
+    pub fn seal(cipher: Cipher, auth_key: &str, plaintext: &[u8]) -> Result<String, String> {
+        let nonce = Self::random_nonce()?;
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&Self::seal_with_nonce(
+            cipher,
+            auth_key,
+            &nonce,
+            &[],
+            plaintext,
+        )?);
+        Ok(format!(
+            "{}{}:{}",
+            ENVELOPE_PREFIX,
+            cipher.tag(),
+            general_purpose::STANDARD.encode(out)
+        ))
+    }
+
+    pub fn random_nonce() -> Result<[u8; 12], String> {
+        let mut nonce = [0u8; 12];
+        OsRng
+            .try_fill_bytes(&mut nonce)
+            .map_err(|e| format!("nonce generation: {}", e))?;
+        Ok(nonce)
+    }
+
+    /// Encrypt with `cipher` under `nonce`, authenticating `aad` along with
+    /// the plaintext. Returns ciphertext || tag. The nonce must never repeat
+    /// for the same key; callers that store it should use `random_nonce`.
+    pub fn seal_with_nonce(
+        cipher: Cipher,
+        auth_key: &str,
+        nonce: &[u8; 12],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let key_bytes = Self::decode_key(auth_key)?;
+        match cipher {
+            Cipher::Aes256Gcm => {
+                let key: &aes_gcm::Key<Aes256Gcm> =
+                    aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
+                Aes256Gcm::new(key)
+                    .encrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: plaintext,
+                            aad,
+                        },
+                    )
+                    .map_err(|e| format!("encrypt error: {:?}", e))
+            }
+            Cipher::ChaCha20Poly1305 => Self::chacha_key(&key_bytes)?
+                .encrypt(
+                    chacha20poly1305::Nonce::from_slice(nonce),
+                    Payload {
+                        msg: plaintext,
+                        aad,
+                    },
+                )
+                .map_err(|_| "encrypt error".to_string()),
+        }
+    }
+
+    /// Inverse of `seal_with_nonce`.
+    pub fn open_with_nonce(
+        cipher: Cipher,
+        auth_key: &str,
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, String> {
+        if nonce.len() != 12 {
+            return Err(format!("nonce must be 12 bytes, got {}", nonce.len()));
+        }
+        let key_bytes = Self::decode_key(auth_key)?;
+        match cipher {
+            Cipher::Aes256Gcm => {
+                let key: &aes_gcm::Key<Aes256Gcm> =
+                    aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
+                Aes256Gcm::new(key)
+                    .decrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: ciphertext,
+                            aad,
+                        },
+                    )
+                    .map(Zeroizing::new)
+                    .map_err(|e| format!("decrypt error: {:?}", e))
+            }
+            Cipher::ChaCha20Poly1305 => Self::chacha_key(&key_bytes)?
+                .decrypt(
+                    chacha20poly1305::Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad,
+                    },
+                )
+                .map(Zeroizing::new)
+                .map_err(|_| "decrypt error".to_string()),
+        }
+    }
+
+    /// Algorithm named by an envelope from `seal`; `None` for content in the
+    /// older unprefixed AES-GCM formats or with an unknown algorithm.
+    pub fn envelope_cipher(content: &str) -> Option<Cipher> {
+        let rest = content.trim().strip_prefix(ENVELOPE_PREFIX)?;
+        Cipher::from_tag(rest.split_once(':')?.0)
+    }
+
+    /// `None` unless `content` is an envelope.
+    fn open_envelope(auth_key: &str, content: &str) -> Option<Result<Zeroizing<Vec<u8>>, String>> {
+        let rest = content.trim().strip_prefix(ENVELOPE_PREFIX)?;
+        Some(Self::open_envelope_body(auth_key, rest))
+    }
+
+    fn open_envelope_body(auth_key: &str, rest: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        let (tag, body) = rest
+            .split_once(':')
+            .ok_or_else(|| "malformed envelope".to_string())?;
+        let cipher = Cipher::from_tag(tag).ok_or_else(|| format!("unknown cipher '{}'", tag))?;
+        let decoded = general_purpose::STANDARD
+            .decode(body)
+            .map_err(|e| format!("base64 decode content: {}", e))?;
+        if decoded.len() < 12 {
+            return Err("envelope too short".into());
+        }
+        let (nonce_bytes, ciphertext) = decoded.split_at(12);
+        Self::open_with_nonce(cipher, auth_key, nonce_bytes, &[], ciphertext)
+    }
+
+    /// Check that `auth_key` can be used to encrypt: standard base64 of
+    /// exactly 32 bytes. The error says which part is wrong.
+    pub fn validate_key(auth_key: &str) -> Result<(), String> {
+        Self::decode_key(auth_key).map(|_| ())
+    }
+
+    /// The 32 key bytes of `auth_key`, wiped when dropped.
+    pub(crate) fn decode_key(auth_key: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        let encoded = auth_key.trim();
+        if encoded.is_empty() {
+            return Err("auth key is empty".into());
+        }
+        let key_bytes = Zeroizing::new(general_purpose::STANDARD.decode(encoded).map_err(|e| {
+            format!(
+                "auth key is not valid base64 ({}); expected {} characters of standard base64",
+                e, AUTH_KEY_BASE64_LEN
+            )
+        })?);
+        match key_bytes.len() {
+            AUTH_KEY_BYTES => Ok(key_bytes),
+            len if len > AUTH_KEY_BYTES => Err(format!(
+                "auth key decodes to {} bytes but must be exactly {}; longer key material can be \
+                 reduced to a key of the right size with `key check --rederive`",
+                len, AUTH_KEY_BYTES
+            )),
+            len => Err(format!(
+                "auth key decodes to {} bytes but must be exactly {}; it is truncated or was \
+                 not generated by Aegisr",
+                len, AUTH_KEY_BYTES
+            )),
+        }
+    }
+
+    /// A valid authorization key derived from base64 key material longer
+    /// than a key (e.g. exported from a KMS), with HKDF-SHA256. The same
+    /// material always gives the same key.
+    pub fn rederive_key(material: &str) -> Result<Zeroizing<String>, String> {
+        let mut bytes = general_purpose::STANDARD
+            .decode(material.trim())
+            .map_err(|e| format!("key material is not valid base64: {}", e))?;
+        if bytes.len() < AUTH_KEY_BYTES {
+            let len = bytes.len();
+            bytes.zeroize();
+            return Err(format!(
+                "key material is {} bytes; at least {} are needed to derive a key",
+                len, AUTH_KEY_BYTES
+            ));
+        }
+        let prk = Hkdf::<Sha256>::new(Some(b"aegisr key rederive v1"), &bytes);
+        bytes.zeroize();
+        let mut derived = Zeroizing::new([0u8; AUTH_KEY_BYTES]);
+        prk.expand(b"authorization key", derived.as_mut())
+            .map_err(|_| "HKDF expansion failed".to_string())?;
+        Ok(Self::encode_derived(*derived))
+    }
+
+    fn chacha_key(key_bytes: &[u8]) -> Result<ChaCha20Poly1305, String> {
+        ChaCha20Poly1305::new_from_slice(key_bytes)
+            .map_err(|_| "invalid ChaCha20-Poly1305 key".to_string())
+    }
+
+    /// A stable identifier for the current host, used for machine binding.
+    pub fn machine_fingerprint() -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+                if let Ok(id) = std::fs::read_to_string(path)
+                    && !id.trim().is_empty()
+                {
+                    return Ok(id.trim().to_string());
+                }
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let output = std::process::Command::new("ioreg")
+                .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+                .output()
+                .map_err(|e| format!("ioreg: {}", e))?;
+            let text = String::from_utf8_lossy(&output.stdout);
+            if let Some(line) = text.lines().find(|l| l.contains("IOPlatformUUID"))
+                && let Some(id) = line.split('"').nth(3)
+            {
+                return Ok(id.to_string());
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let output = std::process::Command::new("reg")
+                .args([
+                    "query",
+                    r"HKLM\SOFTWARE\Microsoft\Cryptography",
+                    "/v",
+                    "MachineGuid",
+                ])
+                .output()
+                .map_err(|e| format!("reg query: {}", e))?;
+            let text = String::from_utf8_lossy(&output.stdout);
+            if let Some(id) = text
+                .lines()
+                .find(|l| l.contains("MachineGuid"))
+                .and_then(|l| l.split_whitespace().last())
+            {
+                return Ok(id.to_string());
+            }
+        }
+
+        Err("machine identifier unavailable on this platform".into())
+    }
+
+    /// Derive the key actually used for encryption when the store is bound to
+    /// this machine: blake3 keyed derivation over the stored key and the
+    /// machine fingerprint. A copied store cannot be opened without both.
+    pub fn bind_key_to_machine(
+        auth_key: &str,
+        fingerprint: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        let key_bytes = Self::decode_key(auth_key)?;
+        let mut material = Zeroizing::new(Vec::with_capacity(key_bytes.len() + fingerprint.len()));
+        material.extend_from_slice(&key_bytes);
+        material.extend_from_slice(fingerprint.as_bytes());
+        Ok(Self::encode_derived(blake3::derive_key(
+            "aegisr machine binding v1",
+            &material,
+        )))
+    }
+
+    /// Derive the key actually used for encryption when the store is bound
+    /// to a PKCS#11 token: blake3 keyed derivation over the stored key and
+    /// the token's HMAC of it (see `AegHsm`).
+    pub fn bind_key_to_hsm(auth_key: &str, token_mac: &[u8]) -> Result<Zeroizing<String>, String> {
+        let key_bytes = Self::decode_key(auth_key)?;
+        let mut material = Zeroizing::new(Vec::with_capacity(key_bytes.len() + token_mac.len()));
+        material.extend_from_slice(&key_bytes);
+        material.extend_from_slice(token_mac);
+        Ok(Self::encode_derived(blake3::derive_key(
+            "aegisr hsm binding v1",
+            &material,
+        )))
+    }
+
+    /// Derive the key actually used for encryption when the store is
+    /// protected by a passphrase: blake3 keyed derivation over the stored
+    /// key and the passphrase stretched with `derive_password_key`.
+    pub fn bind_key_to_passphrase(
+        auth_key: &str,
+        stretched: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        Self::bind_keys("aegisr passphrase binding v1", auth_key, stretched)
+    }
+
+    /// Key for the files of a collection sealed with its own passphrase:
+    /// the same binding over the collection subkey, under its own context,
+    /// so opening the collection takes both the store key and the
+    /// collection's passphrase.
+    pub fn bind_key_to_collection_passphrase(
+        subkey: &str,
+        stretched: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        Self::bind_keys("aegisr collection passphrase v1", subkey, stretched)
+    }
+
+    /// Public check value for a stretched collection passphrase, stored to
+    /// tell a wrong passphrase from a right one without decrypting files.
+    pub fn passphrase_check(stretched: &str) -> Result<String, String> {
+        let mut stretched_bytes = general_purpose::STANDARD
+            .decode(stretched.trim())
+            .map_err(|e| format!("base64 decode passphrase key: {}", e))?;
+        let check = blake3::derive_key("aegisr collection passphrase check v1", &stretched_bytes);
+        stretched_bytes.zeroize();
+        Ok(Self::encode_base64(check))
+    }
+
+    fn bind_keys(
+        context: &str,
+        auth_key: &str,
+        stretched: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        let key_bytes = Self::decode_key(auth_key)?;
+        let stretched_bytes = Zeroizing::new(
+            general_purpose::STANDARD
+                .decode(stretched.trim())
+                .map_err(|e| format!("base64 decode passphrase key: {}", e))?,
+        );
+        let mut material =
+            Zeroizing::new(Vec::with_capacity(key_bytes.len() + stretched_bytes.len()));
+        material.extend_from_slice(&key_bytes);
+        material.extend_from_slice(&stretched_bytes);
+        Ok(Self::encode_derived(blake3::derive_key(context, &material)))
+    }
+
+    /// Base64 of derived key bytes, wiping both once encoded.
+    fn encode_derived(derived: [u8; 32]) -> Zeroizing<String> {
+        let derived = Zeroizing::new(derived);
+        Zeroizing::new(Self::encode_base64(*derived))
+    }
+
+    /// Subkey for one collection's files, derived from the master key with
+    /// the collection name as context. Compromising one collection's key
+    /// reveals nothing about the master key or any other collection.
+    pub fn derive_collection_key(
+        master_key: &str,
+        collection_name: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        let key_bytes = Self::decode_key(master_key)?;
+        let mut material = Zeroizing::new(Vec::with_capacity(
+            key_bytes.len() + 1 + collection_name.len(),
+        ));
+        material.extend_from_slice(&key_bytes);
+        // separator so ("ab", "c") and ("a", "bc") style inputs cannot collide
+        material.push(0);
+        material.extend_from_slice(collection_name.as_bytes());
+        Ok(Self::encode_derived(blake3::derive_key(
+            "aegisr collection key v1",
+            &material,
+        )))
+    }
+
+    /// Key for the lines of the audit log, derived from the master key
+    /// under its own context.
+    pub fn derive_audit_key(master_key: &str) -> Result<Zeroizing<String>, String> {
+        let key_bytes = Self::decode_key(master_key)?;
+        Ok(Self::encode_derived(blake3::derive_key(
+            "aegisr audit log key v1",
+            &key_bytes,
+        )))
+    }
+
+    /// Key of the MAC over the store manifest (see `AegIntegrity`), derived
+    /// from the master key under its own context.
+    pub fn derive_manifest_key(master_key: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+        let key_bytes = Self::decode_key(master_key)?;
+        Ok(Zeroizing::new(blake3::derive_key(
+            "aegisr store manifest key v1",
+            &key_bytes,
+        )))
+    }
+
+    /// Key wrapping the stored key of a store bound to a hardware token,
+    /// derived from the token's response to the store's challenge.
+    pub fn derive_token_wrap_key(response: &[u8]) -> Zeroizing<String> {
+        let mut derived = blake3::derive_key("aegisr hardware key wrap v1", response);
+        let encoded = Zeroizing::new(Self::encode_base64(derived));
+        derived.zeroize();
+        encoded
+    }
+
+    /// Derive a base64 key (usable with `encrypt_record`) from a password
+    /// with Argon2id. Used for material that must not depend on this store's
+    /// authorization key, such as exported bundles.
+    pub fn derive_password_key(password: &str, salt: &[u8]) -> Result<Zeroizing<String>, String> {
+        let mut out = Zeroizing::new([0u8; 32]);
+        argon2::Argon2::default()
+            .hash_password_into(password.as_bytes(), salt, out.as_mut())
+            .map_err(|e| format!("key derivation failed: {}", e))?;
+        Ok(Zeroizing::new(Self::encode_base64(*out)))
+    }
+
+    /// Like `derive_password_key`, with PBKDF2-HMAC-SHA256 instead of
+    /// Argon2id: for files that are opened by a browser, where WebCrypto
+    /// offers nothing stronger.
+    pub fn derive_pbkdf2_key(
+        password: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<Zeroizing<String>, String> {
+        if iterations == 0 {
+            return Err("key derivation failed: zero iterations".to_string());
+        }
+        let mut out = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, out.as_mut());
+        Ok(Self::encode_derived(*out))
+    }
+
+    /// Key for the decoy store, derived only from the duress passphrase and
+    /// the decoy's own salt. The context string keeps this derivation separate
+    /// from every other key in the store; the real authorization key is never
+    /// an input.
+    pub fn derive_duress_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<String>, String> {
+        let mut stretched = Zeroizing::new([0u8; 32]);
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, stretched.as_mut())
+            .map_err(|e| format!("key derivation failed: {}", e))?;
+        Ok(Self::encode_derived(blake3::derive_key(
+            "aegisr duress store v1",
+            stretched.as_ref(),
+        )))
     }
 }
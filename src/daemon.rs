@@ -0,0 +1,428 @@
+use crate::audit::{AegAudit, AuditSource};
+use crate::commands::AegisrCommand;
+use crate::constant::{DAEMON_MAX_FRAME_BYTES, STORE_DAEMON_SOCKET};
+use crate::core::AegCore;
+use crate::dispatch::AegDispatch;
+use crate::file_system::AegFileSystem;
+use crate::wire::{AegWire, AegisrResponse};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// A long-running process that keeps the store decrypted in memory and
+/// executes commands for local clients, so repeated CLI calls do not
+/// re-read and re-decrypt collection files each time.
+///
+/// The protocol is a stream of frames over a local connection: a 4-byte
+/// big-endian length, then that many bytes of a wire-encoded `AegisrCommand`
+/// (see `AegWire`), answered by a frame holding the encoded `AegisrResponse`.
+/// A connection may send any number of commands. Changes are persisted by
+/// the background saver and flushed when the daemon stops.
+///
+/// On unix the connection is a socket at the given path (`daemon.sock` in the
+/// store directory), created inside a directory only the owner can enter and
+/// moved into place once it is 0600, so no other user can connect even
+/// briefly. On Windows it is a named pipe whose name is derived from that
+/// path, owned by and open to the current user only and refusing remote
+/// clients.
+pub struct AegDaemon {
+    listener: transport::Listener,
+    path: PathBuf,
+}
+
+/// A daemon running on a background thread (see `AegDaemon::spawn`).
+pub struct DaemonHandle {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AegDaemon {
+    pub fn default_socket() -> PathBuf {
+        AegFileSystem::get_config_path().join(STORE_DAEMON_SOCKET)
+    }
+
+    /// Listen on `path`. A socket left behind by a daemon that died is
+    /// replaced; one that still answers is an error.
+    pub fn bind(path: &Path) -> Result<Self, String> {
+        if transport::connect(path).is_ok() {
+            return Err(format!(
+                "a daemon is already listening on {}",
+                path.display()
+            ));
+        }
+        let listener = transport::Listener::bind(path)?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Serve connections until the process exits, one thread per connection.
+    pub fn run(self) {
+        self.run_until(&AtomicBool::new(false));
+    }
+
+    fn run_until(self, stop: &AtomicBool) {
+        AegCore::start_background_saver(1);
+        loop {
+            let stream = self.listener.accept();
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = stream {
+                thread::spawn(move || Self::handle_connection(stream));
+            }
+        }
+        AegCore::flush_now();
+        transport::remove(&self.path);
+    }
+
+    /// Bind to the store's `daemon.sock` and serve.
+    pub fn serve() -> Result<(), String> {
+        Self::bind(&Self::default_socket())?.run();
+        Ok(())
+    }
+
+    /// Serve on a background thread until the handle is stopped or dropped.
+    pub fn spawn(path: &Path) -> Result<DaemonHandle, String> {
+        let daemon = Self::bind(path)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || daemon.run_until(&stop))
+        };
+        Ok(DaemonHandle {
+            path: path.to_path_buf(),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn handle_connection(mut stream: transport::Stream) {
+        while let Ok(Some(request)) = read_frame(&mut stream) {
+            let reply = match String::from_utf8(request) {
+                Ok(input) => AegAudit::with_source(AuditSource::Daemon, || {
+                    AegDispatch::execute_encoded(&input)
+                }),
+                Err(_) => AegWire::encode_response(&AegisrResponse::Error {
+                    message: "✗ command is not valid UTF-8".into(),
+                })
+                .unwrap_or_default(),
+            };
+            if write_frame(&mut stream, reply.as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl DaemonHandle {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop accepting connections, save pending changes and remove the
+    /// socket. Connections already open finish on their own threads.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        self.stop.store(true, Ordering::SeqCst);
+        // wake the accept loop so it sees the flag
+        let _ = transport::connect(&self.path);
+        let _ = thread.join();
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// A connection to a running `AegDaemon`.
+pub struct AegDaemonClient {
+    stream: transport::Stream,
+}
+
+impl AegDaemonClient {
+    pub fn connect(path: &Path) -> Result<Self, String> {
+        transport::connect(path)
+            .map(|stream| Self { stream })
+            .map_err(|e| format!("connect {}: {}", path.display(), e))
+    }
+
+    /// Execute `command` in the daemon.
+    pub fn execute(&mut self, command: &AegisrCommand) -> Result<AegisrResponse, String> {
+        let request = AegWire::encode_command(command)?;
+        write_frame(&mut self.stream, request.as_bytes())
+            .map_err(|e| format!("send to daemon: {}", e))?;
+        let reply = read_frame(&mut self.stream)
+            .map_err(|e| format!("read from daemon: {}", e))?
+            .ok_or("the daemon closed the connection")?;
+        let reply = String::from_utf8(reply).map_err(|_| "daemon reply is not valid UTF-8")?;
+        AegWire::decode_response(&reply)
+    }
+
+    /// Execute `command` in the store's daemon if one is running, or in
+    /// this process otherwise.
+    pub fn execute_or_local(command: AegisrCommand) -> AegisrResponse {
+        match Self::connect(&AegDaemon::default_socket()) {
+            Ok(mut client) => client
+                .execute(&command)
+                .unwrap_or_else(|e| AegisrResponse::Error {
+                    message: format!("✗ {}", e),
+                }),
+            Err(_) => AegDispatch::execute(command),
+        }
+    }
+}
+
+/// The next frame, or `None` at a clean end of stream.
+fn read_frame(stream: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match stream.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > DAEMON_MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds the limit", len),
+        ));
+    }
+    let mut frame = vec![0u8; len];
+    stream.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
+
+fn write_frame(stream: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    if frame.len() > DAEMON_MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes exceeds the limit", frame.len()),
+        ));
+    }
+    stream.write_all(&(frame.len() as u32).to_be_bytes())?;
+    stream.write_all(frame)?;
+    stream.flush()
+}
+
+#[cfg(unix)]
+mod transport {
+    use std::fs::{self, DirBuilder};
+    use std::io;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+
+    pub(super) type Stream = UnixStream;
+
+    pub(super) struct Listener(UnixListener);
+
+    impl Listener {
+        /// Bind in a fresh 0700 directory next to `path`, restrict the
+        /// socket to 0600 and only then move it to `path`: until then no
+        /// other user can reach it.
+        pub(super) fn bind(path: &Path) -> Result<Self, String> {
+            let name = path
+                .file_name()
+                .ok_or_else(|| format!("{} is not a socket path", path.display()))?;
+            let mut staging_name = std::ffi::OsString::from(".");
+            staging_name.push(name);
+            staging_name.push(format!(".{}", std::process::id()));
+            let staging = path.with_file_name(staging_name);
+            let _ = fs::remove_dir_all(&staging);
+            DirBuilder::new()
+                .mode(0o700)
+                .create(&staging)
+                .map_err(|e| format!("create {}: {}", staging.display(), e))?;
+            let staged = staging.join(name);
+            let bound = UnixListener::bind(&staged)
+                .map_err(|e| format!("bind {}: {}", path.display(), e))
+                .and_then(|listener| {
+                    fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))
+                        .map_err(|e| format!("restrict {}: {}", path.display(), e))?;
+                    // replaces a socket left behind by a daemon that died
+                    fs::rename(&staged, path)
+                        .map_err(|e| format!("move socket to {}: {}", path.display(), e))?;
+                    Ok(Self(listener))
+                });
+            let _ = fs::remove_dir_all(&staging);
+            bound
+        }
+
+        pub(super) fn accept(&self) -> io::Result<Stream> {
+            self.0.accept().map(|(stream, _)| stream)
+        }
+    }
+
+    pub(super) fn connect(path: &Path) -> io::Result<Stream> {
+        UnixStream::connect(path)
+    }
+
+    pub(super) fn remove(path: &Path) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(windows)]
+mod transport {
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+    use std::path::Path;
+    use std::ptr;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+    use windows_sys::Win32::Foundation::{
+        ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE, LocalFree,
+    };
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    /// Full control for the pipe's owner, nothing for anyone else.
+    const OWNER_ONLY: &str = "D:P(A;;GA;;;OW)";
+    const PIPE_BUFFER_BYTES: u32 = 64 * 1024;
+    /// How long a client retries while every pipe instance is taken.
+    const BUSY_RETRIES: u32 = 50;
+
+    pub(super) type Stream = File;
+
+    /// The pipe instance the next client connects to; a new one is created
+    /// as soon as a client takes it.
+    pub(super) struct Listener {
+        name: Vec<u16>,
+        pending: Mutex<Option<File>>,
+    }
+
+    impl Listener {
+        pub(super) fn bind(path: &Path) -> Result<Self, String> {
+            let name = wide(&pipe_name(path));
+            // the first instance fails if another process already holds the name
+            let first = create_instance(&name, true)
+                .map_err(|e| format!("bind {}: {}", pipe_name(path), e))?;
+            Ok(Self {
+                name,
+                pending: Mutex::new(Some(first)),
+            })
+        }
+
+        pub(super) fn accept(&self) -> io::Result<Stream> {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let instance = match pending.take() {
+                Some(instance) => instance,
+                None => create_instance(&self.name, false)?,
+            };
+            // SAFETY: the handle is the open pipe instance `instance` owns
+            let connected =
+                unsafe { ConnectNamedPipe(instance.as_raw_handle() as _, ptr::null_mut()) } != 0;
+            if !connected {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                    return Err(error);
+                }
+            }
+            *pending = create_instance(&self.name, false).ok();
+            Ok(instance)
+        }
+    }
+
+    pub(super) fn connect(path: &Path) -> io::Result<Stream> {
+        let name = pipe_name(path);
+        let mut attempts = 0;
+        loop {
+            match OpenOptions::new().read(true).write(true).open(&name) {
+                Err(e)
+                    if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32)
+                        && attempts < BUSY_RETRIES =>
+                {
+                    attempts += 1;
+                    thread::sleep(Duration::from_millis(20));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// A pipe goes away with its last handle; there is nothing to remove.
+    pub(super) fn remove(_path: &Path) {}
+
+    /// `\\.\pipe\aegisr-…`, named after a hash of `path` so each store
+    /// directory gets its own pipe.
+    fn pipe_name(path: &Path) -> String {
+        let hash = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+        format!(r"\\.\pipe\aegisr-{}", &hash[..32])
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn create_instance(name: &[u16], first: bool) -> io::Result<File> {
+        let sddl = wide(OWNER_ONLY);
+        let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+        // SAFETY: `sddl` is NUL-terminated and `descriptor` receives a buffer
+        // we free with LocalFree below
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor,
+            bInheritHandle: 0,
+        };
+        let mut open_mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        // SAFETY: `name` is NUL-terminated and `attributes` outlives the call
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                PIPE_BUFFER_BYTES,
+                PIPE_BUFFER_BYTES,
+                0,
+                &attributes,
+            )
+        };
+        let error = io::Error::last_os_error();
+        // SAFETY: allocated by ConvertStringSecurityDescriptorToSecurityDescriptorW
+        unsafe { LocalFree(descriptor as _) };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(error);
+        }
+        // SAFETY: a new pipe handle nothing else owns
+        Ok(unsafe { File::from_raw_handle(handle as RawHandle) })
+    }
+}
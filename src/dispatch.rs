@@ -0,0 +1,1173 @@
+use crate::age::SshRecipient;
+use crate::audit::{AegAudit, AuditFilter, AuditSource};
+use crate::clipboard::AegClipboard;
+use crate::clock::AegClock;
+use crate::commands::AegisrCommand;
+use crate::constant::{
+    CLIPBOARD_CLEAR_SECS, DEFAULT_BACKUP_GENERATIONS, DEFAULT_COMPRESS_MIN_BYTES,
+    DEFAULT_HSM_KEY_LABEL, DEFAULT_TOKEN_SLOT, READ_ONLY_ERROR,
+};
+use crate::core::AegCore;
+use crate::crypto::{AegCrypto, SecretSpec};
+use crate::emergency::AegEmergency;
+use crate::env::AegEnv;
+use crate::federation::{AegFederation, FederatedStore, KeyProvider};
+use crate::file_system::AegFileSystem;
+use crate::hook::{AegHook, HookState};
+use crate::hsm::HsmConfig;
+use crate::introspect::AegIntrospect;
+use crate::keyring::KeyBackend;
+use crate::loadtest::{AegLoadtest, LoadtestConfig};
+use crate::memory_engine::{CollectionStats, PersistencePolicy};
+use crate::plain::{ExportFormat, PlainFormat};
+use crate::share::{SharePrivateKey, SharePublicKey, SharedBundle};
+use crate::snippet::AegSnippet;
+use crate::sync::AegSync;
+use crate::verbosity::Verbosity;
+use crate::wire::{AegWire, AegisrResponse, DecodedCommand};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{Level, info};
+use zeroize::Zeroizing;
+
+/// The one place an `AegisrCommand` is executed. The CLI, the daemon, the
+/// HTTP server and tests all go through `execute`, so a command behaves the
+/// same whichever way it arrives.
+///
+/// Commands that need a prompt (a missing password or passphrase, nuke
+/// confirmation) or that never return (`serve`, `watch`) are refused here; the CLI
+/// handles those itself before dispatching.
+pub struct AegDispatch;
+
+impl AegDispatch {
+    pub fn execute(command: AegisrCommand) -> AegisrResponse {
+        Self::execute_with(command, Verbosity::default())
+    }
+
+    /// `execute`, reporting through `tracing` at `verbosity`. Audited
+    /// operations are attributed to the CLI unless the caller (the HTTP
+    /// API, the daemon) set its own `AuditSource`.
+    pub fn execute_with(command: AegisrCommand, verbosity: Verbosity) -> AegisrResponse {
+        AegAudit::with_source(AuditSource::Cli, || Self::run(command, verbosity))
+    }
+
+    fn run(command: AegisrCommand, verbosity: Verbosity) -> AegisrResponse {
+        if AegCore::is_read_only() && command.mutates_store() {
+            return Self::error(READ_ONLY_ERROR.to_string());
+        }
+        match command {
+            AegisrCommand::Init {
+                reset,
+                bind_machine,
+                cipher,
+                key_backend,
+                token_slot,
+            } => {
+                let path = AegFileSystem::initialize_config(Some(reset), verbosity);
+                if bind_machine {
+                    let msg = AegCore::set_machine_binding(true);
+                    if !msg.starts_with('✓') {
+                        return AegisrResponse::from_message(msg);
+                    }
+                }
+                if let Some(cipher) = cipher {
+                    let msg = AegCore::set_cipher(cipher);
+                    if !msg.starts_with('✓') {
+                        return AegisrResponse::from_message(msg);
+                    }
+                }
+                let initialized = format!("✓ Store initialized at '{}'", path.display());
+                if let Some(backend) = key_backend {
+                    let msg = match backend {
+                        KeyBackend::Hardware => {
+                            AegCore::set_hardware_key(token_slot.unwrap_or(DEFAULT_TOKEN_SLOT))
+                        }
+                        _ => AegCore::set_key_backend(backend),
+                    };
+                    // a missing token is an error, as it was asked for to
+                    // lock the store; without a usable keyring the store
+                    // keeps its key file
+                    if backend == KeyBackend::Hardware && !msg.starts_with('✓') {
+                        return AegisrResponse::from_message(msg);
+                    }
+                    if !msg.starts_with('✓') {
+                        return Self::ok(format!(
+                            "{}\n⚠ {}",
+                            initialized,
+                            msg.trim_start_matches('✗').trim()
+                        ));
+                    }
+                }
+                Self::ok(initialized)
+            }
+            AegisrCommand::List => {
+                let core = AegCore::load();
+                Self::with_data(
+                    core.collections.join("\n"),
+                    json!({
+                        "active": core.get_active_collection(),
+                        "collections": core.collections,
+                    }),
+                )
+            }
+            AegisrCommand::Use { name } => {
+                let mut core = AegCore::load();
+                match core.set_active_collection(&name) {
+                    Ok(()) => Self::ok(format!("✓ Using collection '{}'", name)),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::New { name } => {
+                AegisrResponse::from_message(AegCore::create_collection(&name))
+            }
+            AegisrCommand::Delete { name } => {
+                AegisrResponse::from_message(AegCore::delete_collection(&name))
+            }
+            AegisrCommand::Rename { name, new_name } => {
+                AegisrResponse::from_message(AegCore::rename_collection(&name, &new_name))
+            }
+            AegisrCommand::Clone { src, dest } => {
+                AegisrResponse::from_message(AegCore::duplicate_collection(&src, &dest))
+            }
+            AegisrCommand::Merge {
+                src,
+                dest,
+                strategy,
+            } => match AegCore::merge_collections(&src, &dest, strategy) {
+                Ok(report) if report.aborted => Self::error(report.summary()),
+                Ok(report) => Self::with_data(report.summary(), json!(report)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::Stats { collection, all } => {
+                let stats = if all {
+                    Ok(AegCore::all_collection_stats())
+                } else {
+                    let name = collection
+                        .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                    AegCore::collection_stats(&name).map(|s| vec![s])
+                };
+                match stats {
+                    Ok(stats) => Self::with_data(CollectionStats::table(&stats), json!(stats)),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Metrics { prometheus } => {
+                let metrics = AegCore::metrics_snapshot();
+                let text = if prometheus {
+                    metrics.to_prometheus()
+                } else {
+                    metrics.summary()
+                };
+                Self::with_data(text, json!(metrics))
+            }
+            AegisrCommand::Autosave { name, off } => {
+                AegisrResponse::from_message(AegCore::set_autosave(&name, !off))
+            }
+            AegisrCommand::Naming {
+                name,
+                convention,
+                off,
+                fix,
+            } => {
+                if convention.is_some() || off {
+                    let msg = AegCore::set_key_convention(&name, convention);
+                    if !fix || !msg.starts_with('✓') {
+                        return AegisrResponse::from_message(msg);
+                    }
+                } else if !fix {
+                    return Self::error("give a convention, --off or --fix".into());
+                }
+                match AegCore::key_name_fixes(&name) {
+                    Ok(fixes) => {
+                        let lines: Vec<String> = fixes
+                            .iter()
+                            .map(|(key, fixed)| match fixed {
+                                Some(fixed) => format!("{} -> {}", key, fixed),
+                                None => format!("{} (no suggestion)", key),
+                            })
+                            .collect();
+                        let data: Vec<_> = fixes
+                            .iter()
+                            .map(|(key, fixed)| json!({ "key": key, "suggestion": fixed }))
+                            .collect();
+                        Self::with_data(lines.join("\n"), json!(data))
+                    }
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Seal {
+                name,
+                off,
+                passphrase,
+            } => AegisrResponse::from_message(match passphrase {
+                Some(passphrase) => AegCore::seal_collection(&name, &passphrase),
+                None => AegCore::set_sealed_values(&name, !off),
+            }),
+            AegisrCommand::Unseal { name, passphrase } => {
+                let Some(passphrase) = passphrase else {
+                    return Self::error("a passphrase is required to unseal a collection".into());
+                };
+                AegisrResponse::from_message(AegCore::unseal_collection(&name, &passphrase))
+            }
+            AegisrCommand::Export {
+                portable,
+                collection,
+                password,
+                recipients,
+                plain,
+                format,
+                yes,
+                path,
+            } => {
+                let path = Path::new(&path);
+                let message = if portable {
+                    AegCore::export_portable(path)
+                } else if format == Some(ExportFormat::Html) {
+                    let Some(password) = password else {
+                        return Self::error("a passphrase is required to export a viewer".into());
+                    };
+                    let name = collection
+                        .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                    AegCore::export_viewer(&name, path, &password)
+                } else if plain {
+                    let format = format.and_then(ExportFormat::plain);
+                    AegCore::export_plain_to(path, format.unwrap_or(PlainFormat::Json), yes)
+                } else if !recipients.is_empty() {
+                    let recipients: Result<Vec<_>, String> =
+                        recipients.iter().map(|r| SshRecipient::load(r)).collect();
+                    let recipients = match recipients {
+                        Ok(r) => r.concat(),
+                        Err(e) => return Self::error(e),
+                    };
+                    let name = collection
+                        .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                    AegCore::export_collection_to(&name, path, &recipients)
+                } else {
+                    let Some(password) = password else {
+                        return Self::error("a password is required to export a bundle".into());
+                    };
+                    let name = collection
+                        .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                    AegCore::export_collection(&name, path, &password)
+                };
+                AegisrResponse::from_message(message)
+            }
+            AegisrCommand::Import {
+                password,
+                identity,
+                key,
+                plain,
+                format,
+                path,
+            } => {
+                let path = Path::new(&path);
+                let message = if plain {
+                    AegCore::import_plain(path, format.unwrap_or(PlainFormat::Json))
+                } else if let Some(identity) = identity {
+                    AegCore::import_collection_with_identity(path, Path::new(&identity))
+                } else if let Some(key) = key {
+                    let opened = SharePrivateKey::load(&key)
+                        .and_then(|key| SharedBundle::read(path).map(|bundle| (bundle, key)));
+                    match opened {
+                        Ok((bundle, key)) => AegCore::import_shared(&bundle, &key),
+                        Err(e) => format!("✗ Import failed: {}", e),
+                    }
+                } else {
+                    let Some(password) = password else {
+                        return Self::error("a password is required to import a bundle".into());
+                    };
+                    AegCore::import_collection(path, &password)
+                };
+                AegisrResponse::from_message(message)
+            }
+            AegisrCommand::ImportEnv { path, collection } => AegisrResponse::from_message(
+                AegCore::import_dotenv(Path::new(&path), collection.as_deref()),
+            ),
+            AegisrCommand::Duress { passphrase } => match passphrase {
+                Some(passphrase) => {
+                    AegisrResponse::from_message(AegCore::setup_duress(&passphrase))
+                }
+                None => Self::error("a duress passphrase is required".into()),
+            },
+            AegisrCommand::Passwd {
+                current,
+                new,
+                remove,
+            } => {
+                if new.is_none() && !remove {
+                    return Self::error("a new passphrase is required (or remove it)".into());
+                }
+                let new = new.filter(|_| !remove);
+                // progress goes to stderr; the response is the outcome
+                AegisrResponse::from_message(AegCore::change_passphrase(
+                    current.as_deref(),
+                    new.as_deref(),
+                    |p| {
+                        if verbosity.enabled(Level::INFO) {
+                            info!(done = p.done, total = p.total, file = %p.file, "re-encrypted");
+                        }
+                    },
+                ))
+            }
+            AegisrCommand::Nuke { .. } => {
+                Self::error("nuke must be confirmed interactively from the CLI".into())
+            }
+            AegisrCommand::Status => {
+                let status = AegCore::status();
+                let mut message = format!(
+                    "Store: {}\nProfile: {}\nActive collection: {}\nCollections: {}\nBackground saver: {}, last flush {}\nFormat: v{} (files on disk: {})",
+                    status.store_dir.display(),
+                    status.profile.as_deref().unwrap_or("(none)"),
+                    status.active_collection,
+                    status.collections.join(", "),
+                    Self::describe_policy(status.saver.policy),
+                    status
+                        .saver
+                        .last_flush
+                        .map(|t| t.to_string())
+                        .unwrap_or_else(|| "never".into()),
+                    status.format_version,
+                    if status.file_format_versions.is_empty() {
+                        "none".to_string()
+                    } else {
+                        status
+                            .file_format_versions
+                            .iter()
+                            .map(|v| format!("v{}", v))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    }
+                );
+                if status.pending.is_empty() {
+                    message.push_str("\nUnsaved changes: none");
+                }
+                for changes in &status.pending {
+                    message.push_str(&format!(
+                        "\nUnsaved changes in '{}': {} added, {} modified, {} removed",
+                        changes.collection,
+                        changes.added.len(),
+                        changes.modified.len(),
+                        changes.removed.len()
+                    ));
+                }
+                if let Some(warning) = AegClock::skew_warning() {
+                    message.push_str(&format!("\n⚠ {}", warning));
+                }
+                Self::with_data(message, json!(status))
+            }
+            AegisrCommand::Inspect => {
+                let store = AegIntrospect::describe();
+                let bytes: u64 = store.files.iter().map(|f| f.bytes).sum();
+                Self::with_data(
+                    format!(
+                        "Store: {}\nFormat: v{}, cipher {}\nCollections: {} ({} loaded, {} unsaved)\nFiles: {} ({} bytes)\nSnapshots: {}\nBackground saver: {}",
+                        store.store_dir.display(),
+                        store.format_version,
+                        store.cipher,
+                        store.collections.len(),
+                        store.collections.iter().filter(|c| c.loaded).count(),
+                        store.collections.iter().filter(|c| c.unsaved).count(),
+                        store.files.len(),
+                        bytes,
+                        store.snapshots,
+                        Self::describe_policy(store.saver.policy)
+                    ),
+                    json!(store),
+                )
+            }
+            AegisrCommand::Put {
+                key,
+                value,
+                env_name,
+                json,
+            } => {
+                let msg = if json {
+                    AegCore::put_json(&key, &value)
+                } else {
+                    AegCore::put_qualified(&key, &value)
+                };
+                if msg.starts_with('✓')
+                    && let Some(env_name) = env_name
+                {
+                    let env_msg = AegCore::set_env_name_qualified(&key, Some(&env_name));
+                    if !env_msg.starts_with('✓') {
+                        return AegisrResponse::from_message(env_msg);
+                    }
+                }
+                AegisrResponse::from_message(msg)
+            }
+            AegisrCommand::Get {
+                key,
+                no_cache,
+                clip: true,
+                clear_after,
+            } => match Self::get_qualified(&key, no_cache) {
+                Ok(Some(value)) => {
+                    let value = Zeroizing::new(value);
+                    let secs = clear_after.unwrap_or(CLIPBOARD_CLEAR_SECS);
+                    let delay = (secs > 0).then(|| Duration::from_secs(secs));
+                    match AegClipboard::copy(&value, delay) {
+                        // the value is not echoed, only where it went
+                        Ok(()) if delay.is_some() => Self::with_data(
+                            format!(
+                                "✓ Copied '{}' to the clipboard; it clears in {} s",
+                                key, secs
+                            ),
+                            json!({ "key": key, "clear_after": secs }),
+                        ),
+                        Ok(()) => Self::with_data(
+                            format!("✓ Copied '{}' to the clipboard", key),
+                            json!({ "key": key, "clear_after": null }),
+                        ),
+                        Err(e) => Self::error(format!("Clipboard unavailable: {}", e)),
+                    }
+                }
+                Ok(None) => Self::error(format!("Key '{}' not found", key)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::Get { key, no_cache, .. } => match Self::get_qualified(&key, no_cache) {
+                Ok(Some(value)) => Self::with_data(value.clone(), json!(value)),
+                Ok(None) => Self::error(format!("Key '{}' not found", key)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::GetMany { keys, no_cache } => {
+                let mut lines = Vec::new();
+                let mut data = serde_json::Map::new();
+                let mut missing = Vec::new();
+                for key in keys {
+                    match Self::get_qualified(&key, no_cache) {
+                        Ok(Some(value)) => {
+                            lines.push(format!("{} = {}", key, value));
+                            data.insert(key, json!(value));
+                        }
+                        Ok(None) => missing.push(format!("'{}'", key)),
+                        Err(e) => return Self::error(e),
+                    }
+                }
+                if !missing.is_empty() {
+                    return Self::error(format!("Keys not found: {}", missing.join(", ")));
+                }
+                Self::with_data(lines.join("\n"), serde_json::Value::Object(data))
+            }
+            AegisrCommand::Del { key } => {
+                AegisrResponse::from_message(AegCore::delete_qualified(&key))
+            }
+            AegisrCommand::DelMany { keys } => {
+                let messages: Vec<String> = keys
+                    .iter()
+                    .map(|key| AegCore::delete_qualified(key))
+                    .collect();
+                let failed = messages.iter().any(|m| m.starts_with('✗'));
+                let message = messages.join("\n");
+                if failed {
+                    AegisrResponse::Error { message }
+                } else {
+                    AegisrResponse::from_message(message)
+                }
+            }
+            AegisrCommand::Mv { key, new_key } => {
+                AegisrResponse::from_message(AegCore::rename_key(&key, &new_key))
+            }
+            AegisrCommand::Lint { level } => {
+                AegisrResponse::from_message(AegCore::set_lint_level(level))
+            }
+            AegisrCommand::Duplicates { off } => {
+                AegisrResponse::from_message(AegCore::set_duplicate_warning(!off))
+            }
+            AegisrCommand::ClockSkew { policy } => {
+                AegisrResponse::from_message(AegCore::set_clock_skew_policy(policy))
+            }
+            AegisrCommand::ClearSnapshot { off } => {
+                AegisrResponse::from_message(AegCore::set_clear_snapshot(!off))
+            }
+            AegisrCommand::Compress { min_bytes, off } => {
+                let min_bytes = (!off).then(|| min_bytes.unwrap_or(DEFAULT_COMPRESS_MIN_BYTES));
+                AegisrResponse::from_message(AegCore::set_compression(min_bytes))
+            }
+            AegisrCommand::Backups { keep, off } => {
+                let keep = if off {
+                    0
+                } else {
+                    keep.unwrap_or(DEFAULT_BACKUP_GENERATIONS)
+                };
+                AegisrResponse::from_message(AegCore::set_backup_generations(keep))
+            }
+            AegisrCommand::Restore {
+                collection,
+                generation,
+            } => AegisrResponse::from_message(AegCore::restore_backup(
+                &collection,
+                generation.unwrap_or(1),
+            )),
+            AegisrCommand::Hsm {
+                module,
+                slot,
+                key_label,
+                off,
+            } => AegisrResponse::from_message(match module {
+                _ if off => AegCore::set_hsm(None),
+                Some(module) => AegCore::set_hsm(Some(HsmConfig {
+                    module,
+                    slot,
+                    key_label: key_label.unwrap_or_else(|| DEFAULT_HSM_KEY_LABEL.to_string()),
+                })),
+                None => AegCore::unlock_hsm(),
+            }),
+            AegisrCommand::Tag { key, tag, remove } => AegisrResponse::from_message(if remove {
+                AegCore::untag_key(&key, &tag)
+            } else {
+                AegCore::tag_key(&key, &tag)
+            }),
+            AegisrCommand::Keys { tag, collection } => {
+                let keys = match (tag, collection) {
+                    (Some(tag), _) => AegCore::list_by_tag(&tag),
+                    (None, Some(name)) => match AegCore::collection_entries(&name) {
+                        Ok(entries) => entries.into_iter().map(|(k, _)| k).collect(),
+                        Err(e) => return Self::error(e),
+                    },
+                    (None, None) => AegCore::list_keys(),
+                };
+                Self::with_data(keys.join("\n"), json!(keys))
+            }
+            AegisrCommand::Tree { path } => {
+                match AegCore::key_tree(path.as_deref().unwrap_or_default()) {
+                    Ok(lines) => {
+                        let keys = AegCore::list_subtree(path.as_deref().unwrap_or_default())
+                            .unwrap_or_default();
+                        Self::with_data(lines.join("\n"), json!(keys))
+                    }
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::DelTree { path } => {
+                AegisrResponse::from_message(AegCore::delete_subtree(&path))
+            }
+            AegisrCommand::Search {
+                pattern,
+                regex,
+                values,
+                all_collections,
+            } => match AegCore::search(&pattern, regex, values, all_collections) {
+                Ok(found) => {
+                    let lines: Vec<String> = found
+                        .iter()
+                        .map(|(collection, key)| {
+                            if all_collections {
+                                format!("{}/{}", collection, key)
+                            } else {
+                                key.clone()
+                            }
+                        })
+                        .collect();
+                    let data: Vec<_> = found
+                        .iter()
+                        .map(|(collection, key)| json!({ "collection": collection, "key": key }))
+                        .collect();
+                    Self::with_data(lines.join("\n"), json!(data))
+                }
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::Dump {
+                collection,
+                show_values,
+            } => {
+                if !show_values {
+                    return Self::error(
+                        "dump prints every value in the clear; pass --show-values to confirm"
+                            .into(),
+                    );
+                }
+                let name = collection
+                    .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                match AegCore::collection_entries(&name) {
+                    Ok(entries) => {
+                        for (key, _) in &entries {
+                            AegAudit::record_read(&name, key);
+                        }
+                        let lines: Vec<String> = entries
+                            .iter()
+                            .map(|(k, v)| format!("{}={}", k, v))
+                            .collect();
+                        let data: Vec<_> = entries
+                            .iter()
+                            .map(|(key, value)| json!({ "key": key, "value": value }))
+                            .collect();
+                        Self::with_data(lines.join("\n"), json!(data))
+                    }
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::History { key } => {
+                let versions = AegCore::get_history(&key);
+                if versions.is_empty() {
+                    return Self::error(format!("Key '{}' has no history", key));
+                }
+                let lines: Vec<String> = versions
+                    .iter()
+                    .map(|v| format!("{}: {}", v.version, v.value))
+                    .collect();
+                Self::with_data(lines.join("\n"), json!(versions))
+            }
+            AegisrCommand::Rollback { key, version } => {
+                AegisrResponse::from_message(AegCore::restore_version(&key, version))
+            }
+            AegisrCommand::Clear => AegisrResponse::from_message(AegCore::clear_values()),
+            AegisrCommand::Pending { name } => match AegCore::pending_changes(name.as_deref()) {
+                Ok(pending) => {
+                    let lines: Vec<String> = pending
+                        .iter()
+                        .map(|p| {
+                            format!(
+                                "{}: {} added, {} modified, {} removed",
+                                p.collection,
+                                p.added.len(),
+                                p.modified.len(),
+                                p.removed.len()
+                            )
+                        })
+                        .collect();
+                    Self::with_data(lines.join("\n"), json!(pending))
+                }
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::CaptureEnv { prefix } => {
+                AegisrResponse::from_message(AegCore::capture_env(prefix.as_deref()))
+            }
+            AegisrCommand::Env { prefix, dotenv } => {
+                match AegEnv::render(prefix.as_deref(), dotenv) {
+                    Ok(rendered) => Self::ok(rendered),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::ProfileNew { name } => {
+                AegisrResponse::from_message(AegCore::create_profile(&name))
+            }
+            AegisrCommand::ProfileList => {
+                let profiles = AegCore::list_profiles();
+                Self::with_data(profiles.join("\n"), json!(profiles))
+            }
+            AegisrCommand::ProfileDelete { name } => {
+                AegisrResponse::from_message(AegCore::delete_profile(&name))
+            }
+            AegisrCommand::KeyCheck { rederive } => {
+                AegisrResponse::from_message(AegCore::check_key(rederive))
+            }
+            AegisrCommand::Backup { path } => {
+                AegisrResponse::from_message(AegCore::backup(Path::new(&path)))
+            }
+            AegisrCommand::Sync { target, strategy } => {
+                match AegSync::remote_for(&target)
+                    .and_then(|(remote, dir)| AegCore::sync_with(remote, dir, strategy))
+                {
+                    Ok(report) => Self::with_data(report.summary(), json!(report)),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Compact => match AegFileSystem::compact() {
+                Ok(report) => Self::with_data(report.summary(), json!(report)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::Verify => {
+                let report = AegCore::verify();
+                if report.passed() {
+                    Self::with_data(report.summary(), json!(report))
+                } else {
+                    // the per-collection lines already carry their own marks
+                    AegisrResponse::Error {
+                        message: report.summary(),
+                    }
+                }
+            }
+            AegisrCommand::Gen {
+                format,
+                length,
+                symbols,
+                no_lowercase,
+                no_uppercase,
+                no_digits,
+                separator,
+                save,
+            } => {
+                let mut spec = SecretSpec::new(format);
+                spec.length = length.unwrap_or(spec.length);
+                spec.symbols = symbols;
+                spec.lowercase = !no_lowercase;
+                spec.uppercase = !no_uppercase;
+                spec.digits = !no_digits;
+                if let Some(separator) = separator {
+                    spec.separator = separator;
+                }
+                let secret = match AegCrypto::generate_secret(&spec) {
+                    Ok(secret) => secret,
+                    Err(e) => return Self::error(e),
+                };
+                match save {
+                    // the secret is not echoed once it is stored
+                    Some(key) => {
+                        AegisrResponse::from_message(AegCore::put_qualified(&key, &secret))
+                    }
+                    None => Self::with_data(
+                        secret.to_plain_string(),
+                        json!({ "secret": secret.expose(), "bits": spec.entropy_bits().floor() }),
+                    ),
+                }
+            }
+            AegisrCommand::Totp { key } => match AegCore::totp_now(&key) {
+                Ok(totp) => Self::with_data(
+                    format!("{} (valid for {} s)", totp.code, totp.remaining_secs),
+                    json!(totp),
+                ),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::SnippetAdd { text, expires } => {
+                let ttl = match expires.as_deref().map(AegSnippet::parse_ttl).transpose() {
+                    Ok(ttl) => ttl,
+                    Err(e) => return Self::error(e),
+                };
+                match AegSnippet::add(&text, ttl) {
+                    Ok(snippet) => Self::with_data(
+                        format!("✓ Snippet stored as {}", snippet.id),
+                        json!({ "id": snippet.id, "expires_at": snippet.expires_at }),
+                    ),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::SnippetGet { id } => match AegSnippet::get(&id) {
+                Ok(Some(snippet)) => Self::with_data(snippet.text.clone(), json!(snippet)),
+                Ok(None) => Self::error(format!("No snippet '{}' (it may have expired)", id)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::SnippetList => {
+                let snippets = AegSnippet::list();
+                let lines: Vec<String> = snippets
+                    .iter()
+                    .map(|s| match s.expires_at {
+                        Some(at) => format!("{} (created {}, expires {})", s.id, s.created_at, at),
+                        None => format!("{} (created {}, never expires)", s.id, s.created_at),
+                    })
+                    .collect();
+                let data: Vec<_> = snippets
+                    .iter()
+                    .map(|s| json!({ "id": s.id, "created_at": s.created_at, "expires_at": s.expires_at }))
+                    .collect();
+                Self::with_data(lines.join("\n"), json!(data))
+            }
+            AegisrCommand::SnippetDelete { id } => {
+                if AegSnippet::delete(&id) {
+                    Self::ok(format!("✓ Snippet '{}' deleted", id))
+                } else {
+                    Self::error(format!("No snippet '{}'", id))
+                }
+            }
+            AegisrCommand::EmergencySetup {
+                collection,
+                contacts,
+                wait,
+                path,
+            } => {
+                let wait = match AegSnippet::parse_ttl(&wait) {
+                    Ok(wait) => wait,
+                    Err(e) => return Self::error(e),
+                };
+                let contacts: Result<Vec<_>, String> =
+                    contacts.iter().map(|c| SshRecipient::load(c)).collect();
+                let contacts = match contacts {
+                    Ok(c) => c.concat(),
+                    Err(e) => return Self::error(e),
+                };
+                let name = collection
+                    .unwrap_or_else(|| AegCore::load().get_active_collection().to_string());
+                AegisrResponse::from_message(AegCore::emergency_setup(
+                    &name,
+                    &contacts,
+                    wait,
+                    Path::new(&path),
+                ))
+            }
+            AegisrCommand::EmergencyRequest { identity, path } => {
+                let requested = AegEmergency::peek_file(Path::new(&path), Path::new(&identity))
+                    .and_then(|bundle| AegEmergency::request(&bundle.grant, &bundle.access));
+                match requested {
+                    Ok(grant) => Self::with_data(
+                        format!(
+                            "✓ Emergency access to '{}' requested; the key is released at {} unless the owner denies it",
+                            grant.collection,
+                            grant.available_at.unwrap_or_default()
+                        ),
+                        json!({ "id": grant.id, "requested_at": grant.requested_at, "available_at": grant.available_at }),
+                    ),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::EmergencyDeny { id } => match AegEmergency::deny(&id) {
+                Ok(true) => Self::ok(format!("✓ Emergency request for '{}' denied", id)),
+                Ok(false) => Self::error(format!("No pending request for '{}'", id)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::EmergencyRelease { identity, path } => {
+                let released = AegEmergency::peek_file(Path::new(&path), Path::new(&identity))
+                    .and_then(|bundle| {
+                        AegEmergency::release(&bundle.grant, &bundle.access)
+                            .map(|key| (bundle.grant, key))
+                    });
+                match released {
+                    Ok((id, key)) => Self::with_data(key.clone(), json!({ "id": id, "escrow_key": key })),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::EmergencyRevoke { id } => {
+                if AegEmergency::revoke(&id) {
+                    Self::ok(format!("✓ Emergency grant '{}' revoked", id))
+                } else {
+                    Self::error(format!("No emergency grant '{}'", id))
+                }
+            }
+            AegisrCommand::EmergencyList => {
+                let grants = AegEmergency::list();
+                let lines: Vec<String> = grants
+                    .iter()
+                    .map(|g| match g.available_at {
+                        Some(at) => format!(
+                            "{} {} (wait {} s, requested {}, available {})",
+                            g.id,
+                            g.collection,
+                            g.wait_secs,
+                            g.requested_at.unwrap_or_default(),
+                            at
+                        ),
+                        None => format!("{} {} (wait {} s)", g.id, g.collection, g.wait_secs),
+                    })
+                    .collect();
+                Self::with_data(lines.join("\n"), json!(grants))
+            }
+            AegisrCommand::EmergencyImport {
+                identity,
+                key,
+                path,
+            } => {
+                let Some(key) = key else {
+                    let grant = AegEmergency::peek_file(Path::new(&path), Path::new(&identity));
+                    return match grant {
+                        Ok(bundle) => Self::error(format!(
+                            "the bundle needs the escrow key of grant '{}': request access from the owner's store (`emergency request`, or POST /emergency/{}/request to its HTTP API), then release it once the waiting period has passed",
+                            bundle.grant, bundle.grant
+                        )),
+                        Err(e) => Self::error(e),
+                    };
+                };
+                AegisrResponse::from_message(AegCore::emergency_import(
+                    Path::new(&path),
+                    Path::new(&identity),
+                    &key,
+                ))
+            }
+            AegisrCommand::KeypairNew { output } => {
+                let key = SharePrivateKey::generate();
+                let public_key = key.public_key().to_string();
+                match output {
+                    Some(output) => match key.write(Path::new(&output)) {
+                        Ok(()) => Self::with_data(
+                            format!(
+                                "✓ Private key written to '{}'\nPublic key: {}",
+                                output, public_key
+                            ),
+                            json!({ "public_key": public_key, "path": output }),
+                        ),
+                        Err(e) => Self::error(e),
+                    },
+                    None => {
+                        let private_key = key.to_text();
+                        Self::with_data(
+                            format!(
+                                "Public key: {}\nPrivate key: {}",
+                                public_key,
+                                private_key.as_str()
+                            ),
+                            json!({ "public_key": public_key, "private_key": private_key.as_str() }),
+                        )
+                    }
+                }
+            }
+            AegisrCommand::Share {
+                collection,
+                to,
+                output,
+            } => {
+                let recipient = match SharePublicKey::load(&to) {
+                    Ok(recipient) => recipient,
+                    Err(e) => return Self::error(e),
+                };
+                let bundle = match AegCore::export_shared(&collection, &recipient) {
+                    Ok(bundle) => bundle,
+                    Err(e) => return AegisrResponse::from_message(e),
+                };
+                match output {
+                    Some(output) => match bundle.write(Path::new(&output)) {
+                        Ok(()) => Self::ok(format!(
+                            "✓ Shared collection '{}' with key {} in '{}'",
+                            collection,
+                            recipient.fingerprint(),
+                            output
+                        )),
+                        Err(e) => Self::error(e),
+                    },
+                    None => Self::with_data(bundle.to_json(), json!(bundle)),
+                }
+            }
+            AegisrCommand::FedAdd {
+                name,
+                dir,
+                key_env,
+                passphrase_env,
+                precedence,
+            } => {
+                let key = match (key_env, passphrase_env) {
+                    (Some(var), _) => KeyProvider::Env { var },
+                    (None, Some(var)) => KeyProvider::Passphrase { var },
+                    (None, None) => KeyProvider::Store,
+                };
+                let store = FederatedStore {
+                    name: name.clone(),
+                    dir: PathBuf::from(dir),
+                    key,
+                    precedence,
+                };
+                match AegFederation::register(store) {
+                    Ok(()) => Self::ok(format!("✓ Store '{}' added to the federation", name)),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::FedRemove { name } => match AegFederation::unregister(&name) {
+                Ok(true) => Self::ok(format!("✓ Store '{}' removed from the federation", name)),
+                Ok(false) => Self::error(format!("No store '{}' in the federation", name)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::FedList => {
+                let stores = AegFederation::stores();
+                let lines: Vec<String> = stores
+                    .iter()
+                    .map(|s| {
+                        format!(
+                            "{} {} (precedence {})",
+                            s.name,
+                            s.dir.display(),
+                            s.precedence
+                        )
+                    })
+                    .collect();
+                Self::with_data(lines.join("\n"), json!(stores))
+            }
+            AegisrCommand::FedGet { name } => match AegFederation::get(&name) {
+                Ok(Some(found)) => Self::with_data(found.value.clone(), json!(found)),
+                Ok(None) => Self::error(format!("Key '{}' not found in any store", name)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::FedPut { name, value } => {
+                AegisrResponse::from_message(AegFederation::put(&name, &value))
+            }
+            AegisrCommand::FedKeys => match AegFederation::keys() {
+                Ok(keys) => Self::with_data(keys.join("\n"), json!(keys)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::SnapshotCreate { label } => {
+                AegisrResponse::from_message(AegCore::snapshot(&label))
+            }
+            AegisrCommand::SnapshotList => {
+                let snapshots = AegCore::list_snapshots();
+                let lines: Vec<String> = snapshots
+                    .iter()
+                    .map(|s| format!("{} ({} files, created {})", s.label, s.files, s.created_at))
+                    .collect();
+                Self::with_data(lines.join("\n"), json!(snapshots))
+            }
+            AegisrCommand::SnapshotRestore { label, last } => {
+                AegisrResponse::from_message(if last {
+                    AegCore::restore_last_snapshot()
+                } else {
+                    AegCore::restore_snapshot(&label)
+                })
+            }
+            AegisrCommand::AuditEnable => AegisrResponse::from_message(AegCore::set_audit(true)),
+            AegisrCommand::AuditDisable => AegisrResponse::from_message(AegCore::set_audit(false)),
+            AegisrCommand::AuditLog {
+                since,
+                until,
+                collection,
+                key,
+            } => match Self::audit_filter(since, until, collection, key)
+                .and_then(|filter| AegCore::read_audit_log(&filter))
+            {
+                Ok(entries) => {
+                    let lines: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+                    Self::with_data(lines.join("\n"), json!(entries))
+                }
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::AuditVerify { anchor } => {
+                AegisrResponse::from_message(AegCore::verify_audit(anchor.as_deref()))
+            }
+            AegisrCommand::AuditAnchor => match AegCore::audit_anchor() {
+                Ok(head) => Self::with_data(head.to_string(), json!(head)),
+                Err(e) => Self::error(e),
+            },
+            AegisrCommand::AuditExport { format, from, to } => {
+                match AegCore::export_audit(format, from.as_deref(), to.as_deref()) {
+                    Ok(report) => Self::ok(report),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Serve { print_token, .. } => {
+                #[cfg(feature = "server")]
+                if print_token {
+                    return match crate::server::AegServer::token() {
+                        Ok(token) => Self::ok(token),
+                        Err(e) => Self::error(e),
+                    };
+                }
+                #[cfg(not(feature = "server"))]
+                let _ = print_token;
+                Self::error("serve runs until the process exits; start it from the CLI".into())
+            }
+            AegisrCommand::Project => {
+                let dir = match std::env::current_dir() {
+                    Ok(dir) => dir,
+                    Err(e) => return Self::error(e.to_string()),
+                };
+                match AegCore::enter_project(&dir) {
+                    Ok(Some(manifest)) => Self::with_data(
+                        format!("✓ Using project '{}'", manifest.path.display()),
+                        json!({
+                            "path": manifest.path,
+                            "profile": manifest.profile,
+                            "collection": manifest.collection,
+                            "required": manifest.required,
+                        }),
+                    ),
+                    Ok(None) => Self::error(format!(
+                        "No project manifest found at or above '{}'",
+                        dir.display()
+                    )),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Hook { shell, emit } => {
+                if !emit {
+                    return Self::ok(AegHook::script(shell));
+                }
+                let dir = match std::env::current_dir() {
+                    Ok(dir) => dir,
+                    Err(e) => return Self::error(e.to_string()),
+                };
+                match AegHook::emit(shell, &dir, &HookState::from_env()) {
+                    Ok(script) => Self::ok(script),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Loadtest {
+                workers,
+                ops,
+                keys,
+                value_size,
+                read_pct,
+                put_pct,
+                saver_interval_ms,
+            } => {
+                let config = LoadtestConfig {
+                    workers,
+                    ops_per_worker: ops,
+                    key_space: keys,
+                    value_size,
+                    read_pct,
+                    put_pct,
+                    saver_interval: (saver_interval_ms > 0)
+                        .then(|| Duration::from_millis(saver_interval_ms)),
+                    ..LoadtestConfig::default()
+                };
+                match AegLoadtest::run(&config) {
+                    Ok(report) => Self::with_data(report.summary(), json!(report)),
+                    Err(e) => Self::error(e),
+                }
+            }
+            AegisrCommand::Watch { .. } => {
+                Self::error("watch streams until interrupted; start it from the CLI".into())
+            }
+            AegisrCommand::Daemon => {
+                Self::error("the daemon runs until stopped; start it from the CLI".into())
+            }
+        }
+    }
+
+    /// Decode a wire-encoded command, execute it and encode the response.
+    /// Commands from a newer peer are answered `Unsupported`.
+    pub fn execute_encoded(input: &str) -> String {
+        let response = match AegWire::decode_command(input) {
+            Ok(DecodedCommand::Command(command)) => Self::execute(command),
+            Ok(DecodedCommand::Unsupported { name, .. }) => {
+                AegisrResponse::Unsupported { command: name }
+            }
+            Err(e) => Self::error(e),
+        };
+        AegWire::encode_response(&response).unwrap_or_else(|e| {
+            json!({ "version": crate::wire::WIRE_VERSION, "status": "error", "message": e })
+                .to_string()
+        })
+    }
+
+    fn describe_policy(policy: PersistencePolicy) -> String {
+        match policy {
+            PersistencePolicy::Interval(secs) => format!("every {}s", secs),
+            PersistencePolicy::Debounced(ms) => format!("{}ms after the last write", ms),
+            PersistencePolicy::EveryWrite => "stopped (saving on every write)".to_string(),
+            PersistencePolicy::Manual => "stopped".to_string(),
+        }
+    }
+
+    /// The filter of `audit log`; times are Unix seconds or `YYYY-MM-DD`
+    /// (see `AegAudit::parse_time`).
+    fn audit_filter(
+        since: Option<String>,
+        until: Option<String>,
+        collection: Option<String>,
+        key: Option<String>,
+    ) -> Result<AuditFilter, String> {
+        Ok(AuditFilter {
+            since: since.map(|t| AegAudit::parse_time(&t, false)).transpose()?,
+            until: until.map(|t| AegAudit::parse_time(&t, true)).transpose()?,
+            collection,
+            key,
+            kind: None,
+        })
+    }
+
+    fn ok(message: String) -> AegisrResponse {
+        AegisrResponse::Ok {
+            message,
+            data: None,
+        }
+    }
+
+    fn with_data(message: String, data: serde_json::Value) -> AegisrResponse {
+        AegisrResponse::Ok {
+            message,
+            data: Some(data),
+        }
+    }
+
+    fn get_qualified(key: &str, no_cache: bool) -> Result<Option<String>, String> {
+        if no_cache {
+            AegCore::get_qualified_uncached(key)
+        } else {
+            AegCore::get_qualified(key)
+        }
+    }
+
+    fn error(message: String) -> AegisrResponse {
+        let message = if message.starts_with('✗') {
+            message
+        } else {
+            format!("✗ {}", message)
+        };
+        AegisrResponse::Error { message }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::age::{AegAge, SshIdentity, SshRecipient};
+use crate::bundle::BundlePayload;
+use crate::clock::AegClock;
+use crate::constant::{EMERGENCY_COLLECTION, STORE_EMERGENCY_FILE};
+use crate::core::AegCore;
+use crate::crypto::{AegCrypto, Cipher};
+use crate::file_system::AegFileSystem;
+use crate::integrity::AegIntegrity;
+use crate::memory_engine::AegMemoryEngine;
+use crate::snippet::AegSnippet;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+pub const EMERGENCY_FORMAT: &str = "aegisr-emergency";
+pub const EMERGENCY_VERSION: u32 = 2;
+
+/// A trusted contact's standing access to one collection. Grants are kept
+/// in emergency.lock, encrypted with the store's key, and never in a
+/// collection, so nothing that reads collections (get, dump, export, env,
+/// sync) can see an escrow key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EmergencyGrant {
+    pub id: String,
+    pub collection: String,
+    /// Fingerprints of the contact's SSH keys.
+    pub contacts: Vec<String>,
+    /// How long after a request the escrow key is released.
+    pub wait_secs: u64,
+    /// Key of the inner layer of the contact's bundle; blank in `list`.
+    pub escrow_key: String,
+    /// blake3 hash of the access key in the contact's bundle, which
+    /// authenticates their requests; blank in `list`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub access_hash: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_at: Option<u64>,
+    /// When the escrow key becomes available, once requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_at: Option<u64>,
+}
+
+impl EmergencyGrant {
+    /// The grant without its secrets, as shown to the owner and the contact.
+    fn redacted(self) -> Self {
+        Self {
+            escrow_key: String::new(),
+            access_hash: String::new(),
+            ..self
+        }
+    }
+}
+
+/// What the contact keeps: sealed to their SSH keys, holding the
+/// collection encrypted again under the grant's escrow key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmergencyBundle {
+    pub format: String,
+    pub version: u32,
+    /// Grant to request access through.
+    pub grant: String,
+    pub collection: String,
+    /// Key the contact presents to request and release access; blank in
+    /// version 1 bundles, which predate contact requests.
+    #[serde(default)]
+    pub access: String,
+    /// base64(nonce || ciphertext) of the JSON `BundlePayload`
+    pub payload: String,
+}
+
+/// Serializes read-modify-write of emergency.lock within this process; the
+/// store lock covers other processes.
+static UPDATES: Mutex<()> = Mutex::new(());
+
+/// Emergency access, as password managers offer it: the owner hands a
+/// trusted contact a bundle of a collection that the contact cannot open
+/// alone. It is sealed to the contact's SSH key, and inside that encrypted
+/// under an escrow key that only this store holds. The contact asks for
+/// access with the access key from the bundle's outer layer (`request`,
+/// over the HTTP API's `/emergency` routes while the owner is away); the
+/// store timestamps the request and releases the escrow key to the same
+/// key once the grant's waiting period has passed, unless the owner denies
+/// the request or revokes the grant first.
+///
+/// Waiting periods are timed like expiry (see `AegClock`): on the monotonic
+/// clock in the process that took the request, so a long-running daemon
+/// cannot be hurried by moving the wall clock, and never released while
+/// the clock is detected to be skewed.
+pub struct AegEmergency;
+
+impl AegEmergency {
+    fn path() -> PathBuf {
+        AegFileSystem::get_config_path().join(STORE_EMERGENCY_FILE)
+    }
+
+    fn lease_name(id: &str) -> String {
+        format!("emergency/{}", id)
+    }
+
+    /// Every grant in emergency.lock, and those that earlier versions kept
+    /// in the emergency collection. With `migrate` (under `update`), the
+    /// latter are moved out of the collection on the way.
+    fn read_grants(migrate: bool) -> Result<Vec<EmergencyGrant>, String> {
+        let storage = AegFileSystem::storage();
+        let path = Self::path();
+        let mut grants: Vec<EmergencyGrant> = if storage.exists(&path) {
+            let key = AegFileSystem::try_read_authorization_key()?;
+            let sealed = {
+                let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())?;
+                storage
+                    .read(&path)
+                    .map_err(|e| format!("read {}: {}", STORE_EMERGENCY_FILE, e))?
+            };
+            let sealed = String::from_utf8(sealed)
+                .map_err(|_| format!("{}: not valid UTF-8", STORE_EMERGENCY_FILE))?;
+            let json = AegCrypto::decrypt_blob(&key, &sealed)
+                .map_err(|e| format!("{}: {}", STORE_EMERGENCY_FILE, e))?;
+            serde_json::from_slice(&json)
+                .map_err(|e| format!("{} is corrupt: {}", STORE_EMERGENCY_FILE, e))?
+        } else {
+            Vec::new()
+        };
+        let legacy = Self::legacy_grants();
+        if !legacy.is_empty() {
+            grants.extend(legacy.iter().map(|(_, grant)| grant.clone()));
+            if migrate {
+                Self::write_grants(&grants)?;
+                Self::remove_legacy(&legacy);
+            }
+        }
+        Ok(grants)
+    }
+
+    fn write_grants(grants: &[EmergencyGrant]) -> Result<(), String> {
+        let key = AegFileSystem::try_read_authorization_key()?;
+        let json = Zeroizing::new(
+            serde_json::to_vec(grants).map_err(|e| format!("serialize error: {}", e))?,
+        );
+        let sealed = AegCrypto::seal(Cipher::Aes256Gcm, &key, &json)?;
+        let dir = AegFileSystem::get_config_path();
+        AegFileSystem::storage()
+            .write(&dir.join(STORE_EMERGENCY_FILE), sealed.as_bytes())
+            .map_err(|e| format!("write {}: {}", STORE_EMERGENCY_FILE, e))?;
+        let _ = AegIntegrity::record(&dir, &[STORE_EMERGENCY_FILE.to_string()]);
+        Ok(())
+    }
+
+    /// Grants stored as values of the emergency collection, by key.
+    fn legacy_grants() -> Vec<(String, EmergencyGrant)> {
+        let exists = AegCore::load()
+            .collections
+            .iter()
+            .any(|c| c == EMERGENCY_COLLECTION);
+        if !exists {
+            return Vec::new();
+        }
+        AegMemoryEngine::read_engine(EMERGENCY_COLLECTION, |engine| {
+            engine
+                .list()
+                .into_iter()
+                .filter_map(|(key, json)| {
+                    Some((key.to_string(), serde_json::from_str(&json).ok()?))
+                })
+                .collect()
+        })
+    }
+
+    /// Delete moved grants from the emergency collection, and the
+    /// collection once nothing else is left in it.
+    fn remove_legacy(legacy: &[(String, EmergencyGrant)]) {
+        let empty = AegMemoryEngine::with_engine(EMERGENCY_COLLECTION, |engine| {
+            for (key, _) in legacy {
+                engine.delete(key);
+            }
+            engine.list().is_empty()
+        });
+        AegMemoryEngine::save_collection(EMERGENCY_COLLECTION);
+        if empty {
+            AegCore::delete_collection(EMERGENCY_COLLECTION);
+        }
+    }
+
+    /// Run `f` on every grant and save what it leaves.
+    fn update<T>(
+        f: impl FnOnce(&mut Vec<EmergencyGrant>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        AegFileSystem::ensure_writable()?;
+        let _updating = UPDATES.lock().unwrap_or_else(|e| e.into_inner());
+        let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())?;
+        let mut grants = Self::read_grants(true)?;
+        let before = grants.clone();
+        let out = f(&mut grants)?;
+        if grants != before {
+            Self::write_grants(&grants)?;
+        }
+        Ok(out)
+    }
+
+    /// Run `f` on grant `id` and save what it leaves.
+    fn update_grant<T>(
+        id: &str,
+        f: impl FnOnce(&mut EmergencyGrant) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let id = id.trim().to_ascii_lowercase();
+        Self::update(|grants| {
+            let grant = grants
+                .iter_mut()
+                .find(|g| g.id == id)
+                .ok_or_else(|| format!("No emergency grant '{}'", id))?;
+            f(grant)
+        })
+    }
+
+    /// Check that `access` is the access key of `grant`'s bundle.
+    fn authorize(grant: &EmergencyGrant, access: &str) -> Result<(), String> {
+        let expected = blake3::Hash::from_hex(&grant.access_hash).map_err(|_| {
+            format!(
+                "emergency grant '{}' predates contact requests; the owner must set it up again",
+                grant.id
+            )
+        })?;
+        // blake3::Hash compares in constant time
+        if blake3::hash(access.trim().as_bytes()) != expected {
+            return Err(format!(
+                "wrong access key for emergency grant '{}'",
+                grant.id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Grant `contacts` access to `collection` after `wait`. Returns the
+    /// grant and the age-encrypted bundle to give the contact.
+    pub fn setup(
+        collection: &str,
+        contacts: &[SshRecipient],
+        wait: Duration,
+    ) -> Result<(EmergencyGrant, Vec<u8>), String> {
+        AegFileSystem::ensure_writable()?;
+        if contacts.is_empty() {
+            return Err("at least one contact key is required".into());
+        }
+        let payload = AegCore::bundle_payload(collection)?;
+        let escrow_key = AegCrypto::encode_base64(AegCrypto::generate_random_bytes());
+        let access = AegCrypto::encode_base64(AegCrypto::generate_random_bytes());
+        let json = Zeroizing::new(
+            serde_json::to_vec(&payload).map_err(|e| format!("serialize error: {}", e))?,
+        );
+        let sealed = AegCrypto::encrypt_record(&escrow_key, &json)?;
+        let now = AegClock::now();
+        Self::update(|grants| {
+            let grant = EmergencyGrant {
+                id: AegSnippet::new_id(|id| grants.iter().any(|g| g.id == id)),
+                collection: collection.to_string(),
+                contacts: contacts.iter().map(SshRecipient::fingerprint).collect(),
+                wait_secs: wait.as_secs(),
+                escrow_key,
+                access_hash: blake3::hash(access.as_bytes()).to_hex().to_string(),
+                created_at: now,
+                requested_at: None,
+                available_at: None,
+            };
+            let bundle = EmergencyBundle {
+                format: EMERGENCY_FORMAT.to_string(),
+                version: EMERGENCY_VERSION,
+                grant: grant.id.clone(),
+                collection: collection.to_string(),
+                access,
+                payload: sealed,
+            };
+            let bundle = Zeroizing::new(
+                serde_json::to_vec(&bundle).map_err(|e| format!("serialize error: {}", e))?,
+            );
+            let bytes = AegAge::encrypt(&bundle, contacts)?;
+            grants.push(grant.clone());
+            Ok((grant.redacted(), bytes))
+        })
+    }
+
+    /// As the contact, with the access key from their bundle: start the
+    /// waiting period of grant `id`. A pending request keeps its original
+    /// time.
+    pub fn request(id: &str, access: &str) -> Result<EmergencyGrant, String> {
+        Self::update_grant(id, |grant| {
+            Self::authorize(grant, access)?;
+            if grant.requested_at.is_none() {
+                grant.requested_at = Some(AegClock::now());
+                grant.available_at = Some(AegClock::lease(
+                    &Self::lease_name(&grant.id),
+                    Duration::from_secs(grant.wait_secs),
+                ));
+            }
+            Ok(grant.clone().redacted())
+        })
+    }
+
+    /// Cancel the pending request of grant `id`; `false` if there was none.
+    pub fn deny(id: &str) -> Result<bool, String> {
+        Self::update_grant(id, |grant| {
+            AegClock::release(&Self::lease_name(&grant.id));
+            grant.available_at = None;
+            Ok(grant.requested_at.take().is_some())
+        })
+    }
+
+    /// As the contact, with the access key from their bundle: the escrow
+    /// key of grant `id`, once a request's waiting period has passed.
+    pub fn release(id: &str, access: &str) -> Result<String, String> {
+        Self::update_grant(id, |grant| {
+            Self::authorize(grant, access)?;
+            let Some(available_at) = grant.available_at else {
+                return Err(format!(
+                    "emergency access to '{}' has not been requested",
+                    grant.id
+                ));
+            };
+            if !AegClock::expired(&Self::lease_name(&grant.id), available_at)? {
+                let remaining = available_at.saturating_sub(AegClock::now());
+                return Err(format!(
+                    "emergency access to '{}' is available in {} s",
+                    grant.id, remaining
+                ));
+            }
+            Ok(grant.escrow_key.clone())
+        })
+    }
+
+    /// Whether `access` is the access key of grant `id`.
+    pub fn is_authorized(id: &str, access: &str) -> bool {
+        let id = id.trim().to_ascii_lowercase();
+        Self::read_grants(false).is_ok_and(|grants| {
+            grants
+                .iter()
+                .any(|g| g.id == id && Self::authorize(g, access).is_ok())
+        })
+    }
+
+    /// Delete grant `id`; bundles already handed out can no longer be
+    /// opened. `false` if there was none.
+    pub fn revoke(id: &str) -> bool {
+        let id = id.trim().to_ascii_lowercase();
+        Self::update(|grants| {
+            let before = grants.len();
+            grants.retain(|g| g.id != id);
+            AegClock::release(&Self::lease_name(&id));
+            Ok(grants.len() < before)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Every grant, oldest first, with its secrets left out.
+    pub fn list() -> Vec<EmergencyGrant> {
+        let mut grants: Vec<EmergencyGrant> = Self::read_grants(false)
+            .unwrap_or_default()
+            .into_iter()
+            .map(EmergencyGrant::redacted)
+            .collect();
+        grants.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        grants
+    }
+
+    /// The outer layer of a bundle, which the contact can open alone: it
+    /// names the grant to request access through.
+    pub fn peek(bytes: &[u8], identity: &SshIdentity) -> Result<EmergencyBundle, String> {
+        let json = AegAge::decrypt(bytes, identity)?;
+        let bundle: EmergencyBundle =
+            serde_json::from_slice(&json).map_err(|e| format!("not an emergency bundle: {}", e))?;
+        if bundle.format != EMERGENCY_FORMAT {
+            return Err(format!(
+                "not an emergency bundle (format '{}')",
+                bundle.format
+            ));
+        }
+        if bundle.version > EMERGENCY_VERSION {
+            return Err(format!(
+                "unsupported emergency bundle version {}",
+                bundle.version
+            ));
+        }
+        Ok(bundle)
+    }
+
+    /// `peek` at the bundle at `path` with the private key at `identity`.
+    pub fn peek_file(path: &Path, identity: &Path) -> Result<EmergencyBundle, String> {
+        let identity = SshIdentity::read(identity)?;
+        let bytes = fs::read(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+        Self::peek(&bytes, &identity)
+    }
+
+    /// Open a bundle with the contact's identity and the escrow key the
+    /// store released.
+    pub fn open(
+        bytes: &[u8],
+        identity: &SshIdentity,
+        escrow_key: &str,
+    ) -> Result<BundlePayload, String> {
+        let bundle = Self::peek(bytes, identity)?;
+        let json = AegCrypto::decrypt_record(escrow_key.trim(), &bundle.payload)
+            .map_err(|_| format!("wrong escrow key for grant '{}'", bundle.grant))?;
+        serde_json::from_slice(&json).map_err(|e| format!("corrupt bundle payload: {}", e))
+    }
+}
@@ -0,0 +1,110 @@
+use crate::audit::AegAudit;
+use crate::core::AegCore;
+use crate::hook::AegHook;
+use crate::memory_engine::AegMemoryEngine;
+use crate::plain::{AegPlain, PlainFormat};
+use std::collections::HashMap;
+use std::process::Command;
+use tracing::warn;
+
+/// The active collection as environment variables, for using the store as
+/// a development secrets manager: printed as `export` statements or a
+/// `.env` file, or handed straight to a child process. Names follow each
+/// key's `env_name` (see `AegMemoryEngine::env_name_for`).
+pub struct AegEnv;
+
+impl AegEnv {
+    /// Whether `name` may be exported: `[A-Za-z_][A-Za-z0-9_]*`. Names are
+    /// written unquoted into shell statements users `eval`, so nothing else
+    /// is ever let through.
+    pub fn is_valid_name(name: &str) -> bool {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// A prefix is valid when prepending it to a valid name keeps it valid.
+    fn check_prefix(prefix: &str) -> Result<(), String> {
+        if prefix.is_empty() || Self::is_valid_name(prefix) {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' is not a valid environment variable prefix",
+                prefix
+            ))
+        }
+    }
+
+    /// (name, value) pairs sorted by name, with `prefix` prepended to every
+    /// name. Each key handed out is recorded as a read; keys whose name is
+    /// not a valid variable name are skipped.
+    pub fn vars(prefix: Option<&str>) -> Result<Vec<(String, String)>, String> {
+        let prefix = prefix.unwrap_or("");
+        Self::check_prefix(prefix)?;
+        let collection = AegCore::load().active_collection;
+        let mut vars: Vec<(String, String, String)> = AegMemoryEngine::read_active(|engine| {
+            engine
+                .list()
+                .into_iter()
+                .map(|(key, value)| {
+                    let name = format!("{}{}", prefix, engine.env_name_for(&key));
+                    (name, key.to_string(), value.to_string())
+                })
+                .collect()
+        });
+        vars.sort();
+        Ok(vars
+            .into_iter()
+            .filter(|(name, key, _)| {
+                let valid = Self::is_valid_name(name);
+                if !valid {
+                    warn!(key = %key, "skipped key with an invalid environment variable name");
+                }
+                valid
+            })
+            .map(|(name, key, value)| {
+                AegAudit::record_read(&collection, &key);
+                (name, value)
+            })
+            .collect())
+    }
+
+    /// `export NAME='value'` lines for a POSIX shell to `eval`, or a
+    /// `.env` file with `dotenv`.
+    pub fn render(prefix: Option<&str>, dotenv: bool) -> Result<String, String> {
+        let vars = Self::vars(prefix)?;
+        if dotenv {
+            let entries: HashMap<String, String> = vars.into_iter().collect();
+            return Ok(AegPlain::encode(&entries, PlainFormat::Dotenv));
+        }
+        Ok(vars
+            .iter()
+            .map(|(name, value)| format!("export {}={}\n", name, AegHook::posix_quote(value)))
+            .collect())
+    }
+
+    /// Run `command` (program then arguments) with the collection's
+    /// variables added to this process's environment, waiting for it to
+    /// finish. Returns its exit code; a child killed by a signal reports
+    /// 128 plus the signal number, as shells do.
+    pub fn exec(prefix: Option<&str>, command: &[String]) -> Result<i32, String> {
+        let (program, args) = command
+            .split_first()
+            .ok_or("no command given (use `aegisr exec -- <command> [args…]`)")?;
+        let status = Command::new(program)
+            .args(args)
+            .envs(Self::vars(prefix)?)
+            .status()
+            .map_err(|e| format!("could not run '{}': {}", program, e))?;
+        if let Some(code) = status.code() {
+            return Ok(code);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Ok(128 + signal);
+            }
+        }
+        Ok(1)
+    }
+}
@@ -0,0 +1,379 @@
+use crate::constant::{
+    FEDERATION_LOCAL_STORE, QUALIFIED_KEY_SEPARATOR, STORE_COLLECTION, STORE_FEDERATION_FILE,
+};
+use crate::core::AegCore;
+use crate::crypto::AegCrypto;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use crate::storage::FsStorage;
+use crate::sync::StoreSwitch;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Where the key of a federated store comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum KeyProvider {
+    /// The store's own AUTHORIZATION_KEY file, with the bindings its config
+    /// sets up, like any store.
+    #[default]
+    Store,
+    /// The stored key is in environment variable `var` instead of a file,
+    /// e.g. a team key handed out as a CI secret.
+    Env { var: String },
+    /// The store is passphrase-protected and the passphrase is in
+    /// environment variable `var`.
+    Passphrase { var: String },
+}
+
+/// A store registered with the federation under `name`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FederatedStore {
+    pub name: String,
+    pub dir: PathBuf,
+    #[serde(default)]
+    pub key: KeyProvider,
+    /// Order in which unqualified keys are looked up; lower goes first,
+    /// ties by name.
+    #[serde(default)]
+    pub precedence: i32,
+}
+
+/// A value found through the federation, with where it came from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FederatedValue {
+    /// `local` for the current store.
+    pub store: String,
+    pub collection: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// A federated name taken apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    /// `store::collection::key`, or `store::key` in the store's active
+    /// collection.
+    Store {
+        store: String,
+        collection: Option<String>,
+        key: String,
+    },
+    /// `collection::key` or `key`, in the current store.
+    Local(String),
+    /// A bare key, looked up across stores.
+    Any(String),
+}
+
+/// One namespace over several stores: the current one (`local`) plus the
+/// stores registered in its `federation.json`, such as a personal, a team
+/// and a project store. Each registered store stays a complete store with
+/// its own key (see `KeyProvider`); the federation only routes names.
+///
+/// Names resolve as follows:
+/// - `store::collection::key` is that key of that store; `local::...`
+///   addresses the current store explicitly.
+/// - `first::rest` is `collection::key` in the current store when it has a
+///   collection `first`, which shadows a store of the same name; otherwise
+///   `first` must be a registered store and `rest` is looked up there
+///   (as `collection::key`, or as a key of its active collection).
+/// - a bare key is looked up in the active collection of the current
+///   store, then of each registered store by precedence; the first hit
+///   wins.
+///
+/// Writes with a bare key go to the current store.
+pub struct AegFederation;
+
+impl AegFederation {
+    fn registry_path() -> PathBuf {
+        AegFileSystem::get_real_config_path().join(STORE_FEDERATION_FILE)
+    }
+
+    /// Registered stores, by precedence.
+    pub fn stores() -> Vec<FederatedStore> {
+        let mut stores: Vec<FederatedStore> = AegFileSystem::storage()
+            .read(&Self::registry_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        stores.sort_by(|a, b| (a.precedence, &a.name).cmp(&(b.precedence, &b.name)));
+        stores
+    }
+
+    fn write_stores(stores: &[FederatedStore]) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(stores).map_err(|e| e.to_string())?;
+        AegFileSystem::storage()
+            .write(&Self::registry_path(), &json)
+            .map_err(|e| format!("write {}: {}", STORE_FEDERATION_FILE, e))
+    }
+
+    /// Add `store`, replacing a store registered under the same name.
+    pub fn register(store: FederatedStore) -> Result<(), String> {
+        let valid = !store.name.is_empty()
+            && store
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!(
+                "invalid store name '{}' (use letters, digits, '-' or '_')",
+                store.name
+            ));
+        }
+        if store.name == FEDERATION_LOCAL_STORE {
+            return Err(format!(
+                "'{}' always names the current store",
+                FEDERATION_LOCAL_STORE
+            ));
+        }
+        if !store.dir.join(STORE_COLLECTION).exists() {
+            return Err(format!("no store at {}", store.dir.display()));
+        }
+        let mut stores = Self::stores();
+        stores.retain(|s| s.name != store.name);
+        stores.push(store);
+        Self::write_stores(&stores)
+    }
+
+    /// Forget store `name`; its files are left alone. `false` if it was not
+    /// registered.
+    pub fn unregister(name: &str) -> Result<bool, String> {
+        let mut stores = Self::stores();
+        let before = stores.len();
+        stores.retain(|s| s.name != name);
+        if stores.len() == before {
+            return Ok(false);
+        }
+        Self::write_stores(&stores).map(|_| true)
+    }
+
+    fn find(name: &str) -> Option<FederatedStore> {
+        Self::stores().into_iter().find(|s| s.name == name)
+    }
+
+    fn target(name: &str) -> Target {
+        let Some((first, rest)) = name.split_once(QUALIFIED_KEY_SEPARATOR) else {
+            return Target::Any(name.to_string());
+        };
+        if first == FEDERATION_LOCAL_STORE {
+            return Target::Local(rest.to_string());
+        }
+        let local_collection = AegCore::load().collections.iter().any(|c| c == first);
+        if local_collection || Self::find(first).is_none() {
+            return Target::Local(name.to_string());
+        }
+        let (collection, key) = match rest.split_once(QUALIFIED_KEY_SEPARATOR) {
+            Some((collection, key)) => (Some(collection.to_string()), key.to_string()),
+            None => (None, rest.to_string()),
+        };
+        Target::Store {
+            store: first.to_string(),
+            collection,
+            key,
+        }
+    }
+
+    /// Run `f` with the process pointed at `store`, then point it back.
+    /// Unsaved changes of the current store are saved first.
+    fn in_store<T>(
+        store: &FederatedStore,
+        f: impl FnOnce() -> Result<T, String>,
+    ) -> Result<T, String> {
+        if AegFileSystem::in_duress_session() {
+            return Err("Cannot reach other stores while the decoy store is open".into());
+        }
+        let stored_key = match &store.key {
+            KeyProvider::Env { var } => {
+                let key = std::env::var(var).map_err(|_| {
+                    format!("the key of store '{}' is not set in {}", store.name, var)
+                })?;
+                AegCrypto::validate_key(key.trim())
+                    .map_err(|e| format!("the key of store '{}' in {}: {}", store.name, var, e))?;
+                Some(Zeroizing::new(key.trim().to_string()))
+            }
+            _ => None,
+        };
+        let passphrase = match &store.key {
+            KeyProvider::Passphrase { var } => {
+                Some(Zeroizing::new(std::env::var(var).map_err(|_| {
+                    format!(
+                        "the passphrase of store '{}' is not set in {}",
+                        store.name, var
+                    )
+                })?))
+            }
+            _ => None,
+        };
+        let provided = stored_key.is_some();
+
+        AegCore::flush_now();
+        let _keys = KeySwitch::to(stored_key, passphrase);
+        let _switch = StoreSwitch::to(Arc::new(FsStorage), store.dir.clone());
+        // the key file, or the keyring entry its config names
+        if !provided && !AegFileSystem::has_stored_key() {
+            return Err(format!(
+                "store '{}' has no key file in {}",
+                store.name,
+                store.dir.display()
+            ));
+        }
+        let out = f();
+        AegCore::flush_now();
+        out
+    }
+
+    /// Look up `collection::key` (or `key` in the active collection) in the
+    /// store the process points at.
+    fn lookup(
+        store: &str,
+        collection: Option<&str>,
+        key: &str,
+    ) -> Result<Option<FederatedValue>, String> {
+        let collection = match collection {
+            Some(c) => c.to_string(),
+            None => AegCore::load().get_active_collection().to_string(),
+        };
+        let qualified = format!("{}{}{}", collection, QUALIFIED_KEY_SEPARATOR, key);
+        Ok(
+            AegCore::get_qualified(&qualified)?.map(|value| FederatedValue {
+                store: store.to_string(),
+                collection,
+                key: key.to_string(),
+                value,
+            }),
+        )
+    }
+
+    fn split_local(name: &str) -> (Option<&str>, &str) {
+        match name.split_once(QUALIFIED_KEY_SEPARATOR) {
+            Some((collection, key)) => (Some(collection), key),
+            None => (None, name),
+        }
+    }
+
+    fn registered(name: &str) -> Result<FederatedStore, String> {
+        Self::find(name).ok_or_else(|| format!("no store '{}' in the federation", name))
+    }
+
+    /// The value of federated `name` (see `AegFederation` for how names
+    /// resolve).
+    pub fn get(name: &str) -> Result<Option<FederatedValue>, String> {
+        match Self::target(name) {
+            Target::Local(name) => {
+                let (collection, key) = Self::split_local(&name);
+                Self::lookup(FEDERATION_LOCAL_STORE, collection, key)
+            }
+            Target::Store {
+                store,
+                collection,
+                key,
+            } => {
+                let store = Self::registered(&store)?;
+                Self::in_store(&store, || {
+                    Self::lookup(&store.name, collection.as_deref(), &key)
+                })
+            }
+            Target::Any(key) => {
+                if let Some(found) = Self::lookup(FEDERATION_LOCAL_STORE, None, &key)? {
+                    return Ok(Some(found));
+                }
+                for store in Self::stores() {
+                    let found = Self::in_store(&store, || Self::lookup(&store.name, None, &key))?;
+                    if found.is_some() {
+                        return Ok(found);
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Store `value` under federated `name`; a bare key goes to the active
+    /// collection of the current store. Saved before this returns when the
+    /// target is another store.
+    pub fn put(name: &str, value: &str) -> String {
+        let (store, qualified) = match Self::target(name) {
+            Target::Local(name) | Target::Any(name) => {
+                return AegCore::put_qualified(&name, value);
+            }
+            Target::Store {
+                store,
+                collection,
+                key,
+            } => match collection {
+                Some(collection) => (
+                    store,
+                    format!("{}{}{}", collection, QUALIFIED_KEY_SEPARATOR, key),
+                ),
+                None => (store, key),
+            },
+        };
+        let store = match Self::registered(&store) {
+            Ok(store) => store,
+            Err(e) => return format!("✗ {}", e),
+        };
+        match Self::in_store(&store, || Ok(AegCore::put_qualified(&qualified, value))) {
+            Ok(message) => message,
+            Err(e) => format!("✗ {}", e),
+        }
+    }
+
+    /// Every key of every store as `store::collection::key`, the current
+    /// store first.
+    pub fn keys() -> Result<Vec<String>, String> {
+        let mut keys = Self::store_keys(FEDERATION_LOCAL_STORE);
+        for store in Self::stores() {
+            keys.extend(Self::in_store(&store, || {
+                Ok(Self::store_keys(&store.name))
+            })?);
+        }
+        Ok(keys)
+    }
+
+    fn store_keys(store: &str) -> Vec<String> {
+        let mut keys = Vec::new();
+        for collection in AegCore::load().collections {
+            let mut names: Vec<String> = AegMemoryEngine::read_engine(&collection, |engine| {
+                engine.keys().iter().map(|k| k.to_string()).collect()
+            });
+            names.sort();
+            keys.extend(names.into_iter().map(|key| {
+                [store, collection.as_str(), key.as_str()].join(QUALIFIED_KEY_SEPARATOR)
+            }));
+        }
+        keys
+    }
+}
+
+/// Sets the key material of a federated store for the process, and drops
+/// any session-only collection choice, which belongs to the current store;
+/// restores both when dropped.
+struct KeySwitch {
+    stored_key: Option<Zeroizing<String>>,
+    passphrase: Option<Zeroizing<String>>,
+    session_collection: Option<String>,
+}
+
+impl KeySwitch {
+    fn to(stored_key: Option<Zeroizing<String>>, passphrase: Option<Zeroizing<String>>) -> Self {
+        let switch = Self {
+            stored_key: AegFileSystem::stored_key(),
+            passphrase: AegFileSystem::passphrase_override(),
+            session_collection: AegCore::session_collection_name(),
+        };
+        AegFileSystem::set_stored_key(stored_key);
+        AegFileSystem::set_passphrase(passphrase.as_deref().map(String::as_str));
+        AegCore::clear_session_collection();
+        switch
+    }
+}
+
+impl Drop for KeySwitch {
+    fn drop(&mut self) {
+        AegFileSystem::set_stored_key(self.stored_key.take());
+        AegFileSystem::set_passphrase(self.passphrase.as_deref().map(String::as_str));
+        AegCore::set_session_collection_name(self.session_collection.take());
+    }
+}
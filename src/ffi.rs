@@ -0,0 +1,144 @@
+//! C interface to the store, for applications that are not written in
+//! Rust. `include/aegisr.h` declares these functions; link against the
+//! `cdylib` or `staticlib` built with the `ffi` feature.
+//!
+//! Every call works on the active collection of the process-wide store, as
+//! `AegCore` does. Strings passed in are NUL-terminated UTF-8 and are only
+//! borrowed for the duration of the call. Panics never cross the boundary:
+//! they are reported as `AEG_ERR_PANIC`.
+
+use crate::core::AegCore;
+use crate::file_system::AegFileSystem;
+use crate::verbosity::Verbosity;
+use std::ffi::{CStr, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use zeroize::Zeroize;
+
+/// The call succeeded.
+pub const AEG_OK: i32 = 0;
+/// A required pointer was null or a string was not valid UTF-8.
+pub const AEG_ERR_ARGUMENT: i32 = -1;
+/// The store refused the operation (e.g. the key breaks the collection's
+/// naming convention).
+pub const AEG_ERR_FAILED: i32 = -2;
+/// The library panicked; the store may be unusable for the rest of the process.
+pub const AEG_ERR_PANIC: i32 = -3;
+
+/// Borrow `ptr` as a `&str`, or `None` when it is null or not UTF-8.
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string that stays valid
+/// for `'a`.
+unsafe fn borrow_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+fn guarded(f: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(AEG_ERR_PANIC)
+}
+
+fn status(message: &str) -> i32 {
+    if message.starts_with('✗') {
+        AEG_ERR_FAILED
+    } else {
+        AEG_OK
+    }
+}
+
+/// Open the store in `store_dir`, creating it if needed; null uses the
+/// default location. Call once before any other function.
+///
+/// # Safety
+/// `store_dir` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aeg_init(store_dir: *const c_char) -> i32 {
+    let dir = if store_dir.is_null() {
+        None
+    } else {
+        match unsafe { borrow_str(store_dir) } {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => return AEG_ERR_ARGUMENT,
+        }
+    };
+    guarded(|| {
+        if let Some(dir) = dir
+            && AegCore::set_store_dir(dir).starts_with('✗')
+        {
+            return AEG_ERR_FAILED;
+        }
+        AegFileSystem::initialize_config(Some(false), Verbosity::default());
+        AEG_OK
+    })
+}
+
+/// Store `value` under `key`. Held in memory until `aeg_flush` or the
+/// background saver writes it.
+///
+/// # Safety
+/// `key` and `value` must be valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aeg_put(key: *const c_char, value: *const c_char) -> i32 {
+    let (Some(key), Some(value)) = (unsafe { borrow_str(key) }, unsafe { borrow_str(value) })
+    else {
+        return AEG_ERR_ARGUMENT;
+    };
+    guarded(|| status(&AegCore::put_value(key, value)))
+}
+
+/// The value of `key` as a newly allocated, NUL-terminated buffer, or null
+/// when the key does not exist or an argument is invalid. Its length
+/// without the terminator is written to `out_len` when that is not null.
+/// Release it with `aeg_free`.
+///
+/// # Safety
+/// `key` must be a valid NUL-terminated string and `out_len` null or valid
+/// for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aeg_get(key: *const c_char, out_len: *mut usize) -> *mut u8 {
+    let Some(key) = (unsafe { borrow_str(key) }) else {
+        return std::ptr::null_mut();
+    };
+    let value = panic::catch_unwind(|| AegCore::get_value(key))
+        .ok()
+        .flatten();
+    let Some(value) = value else {
+        return std::ptr::null_mut();
+    };
+    if !out_len.is_null() {
+        unsafe { *out_len = value.len() };
+    }
+    // sized up front so no copy of the value is left behind by a regrow
+    let mut bytes = Vec::with_capacity(value.len() + 1);
+    bytes.extend_from_slice(value.as_bytes());
+    bytes.push(0);
+    // `value` is wiped when dropped here
+    Box::into_raw(bytes.into_boxed_slice()) as *mut u8
+}
+
+/// Wipe and release a buffer returned by `aeg_get`. `len` is the length
+/// it reported. Null is ignored.
+///
+/// # Safety
+/// `buf` must be null or a buffer from `aeg_get` with its reported `len`,
+/// not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aeg_free(buf: *mut u8, len: usize) {
+    if buf.is_null() {
+        return;
+    }
+    let mut bytes = unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(buf, len + 1)) };
+    bytes.zeroize();
+}
+
+/// Write every collection with unsaved changes to disk.
+#[unsafe(no_mangle)]
+pub extern "C" fn aeg_flush() -> i32 {
+    guarded(|| {
+        AegCore::flush_now();
+        AEG_OK
+    })
+}
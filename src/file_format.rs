@@ -0,0 +1,405 @@
+use crate::compress::AegCompress;
+use crate::crypto::{AegCrypto, Cipher};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// First bytes of every `.aekv` file written in a versioned format.
+pub const AEKV_MAGIC: &[u8; 4] = b"AEKV";
+/// Format version written by this build. Version 4 added the wrapped data
+/// key, version 3 the plaintext checksum, version 2 the codec flag; version
+/// 1 payloads are always JSON.
+pub const AEKV_FORMAT_VERSION: u8 = 4;
+
+const FLAG_COMPRESSED: u8 = 0b0000_0001;
+const FLAG_CBOR: u8 = 0b0000_0010;
+const KNOWN_FLAGS: u8 = FLAG_COMPRESSED | FLAG_CBOR;
+const NONCE_LEN: usize = 12;
+const CHECKSUM_LEN: usize = 32;
+/// Nonce, 32-byte data key and tag.
+const WRAPPED_KEY_LEN: usize = NONCE_LEN + 32 + 16;
+/// blake3 context the checksum key is derived under.
+const CHECKSUM_CONTEXT: &str = "aegisr 2025 aekv plaintext checksum";
+
+/// Fixed-size header at the start of a `.aekv` file:
+///
+/// | bytes | field                                  |
+/// |-------|----------------------------------------|
+/// | 0..4  | magic `AEKV`                           |
+/// | 4     | format version                         |
+/// | 5     | cipher id (`Cipher::id`)               |
+/// | 6     | flags (bit 0: compressed, bit 1: CBOR) |
+/// | 7     | nonce length (12)                      |
+/// | 8..20 | nonce                                  |
+/// | 20..52| checksum (version 3 and later)         |
+/// | 52..112| wrapped data key (version 4 and later) |
+///
+/// The ciphertext follows. The header is authenticated as associated data,
+/// so it cannot be altered without the file failing to decrypt.
+///
+/// From version 4 the payload is encrypted with a random data key of its
+/// own, stored wrapped (nonce || ciphertext || tag) under the key the file
+/// is opened with. The wrapped key is left out of the payload's associated
+/// data and authenticates the rest of the header itself, so changing keys
+/// only re-wraps it (see `AegFileFormat::rewrap`).
+///
+/// The checksum is a blake3 hash of the serialized plaintext, before
+/// compression, keyed with a key derived from the file's encryption key
+/// (the data key from version 4) so that it reveals nothing about the
+/// contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AekvHeader {
+    pub version: u8,
+    pub cipher: Cipher,
+    pub compressed: bool,
+    pub codec: Codec,
+    pub nonce: [u8; NONCE_LEN],
+    /// Keyed checksum of the plaintext; `None` before version 3.
+    pub checksum: Option<[u8; CHECKSUM_LEN]>,
+    /// The payload's data key, wrapped; `None` before version 4.
+    pub wrapped_key: Option<[u8; WRAPPED_KEY_LEN]>,
+}
+
+impl AekvHeader {
+    /// Length of a header without a checksum (versions 1 and 2).
+    pub const LEN: usize = 8 + NONCE_LEN;
+    /// Length of the longest header, so reading this many bytes is enough
+    /// to parse any of them.
+    pub const MAX_LEN: usize = Self::LEN + CHECKSUM_LEN + WRAPPED_KEY_LEN;
+
+    /// Length of this header in the file.
+    pub fn encoded_len(&self) -> usize {
+        self.authenticated_bytes().len() + self.wrapped_key.map_or(0, |k| k.len())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.authenticated_bytes();
+        if let Some(wrapped) = &self.wrapped_key {
+            out.extend_from_slice(wrapped);
+        }
+        out
+    }
+
+    /// The header without its wrapped data key: the associated data of the
+    /// payload and of the wrapped key.
+    pub fn authenticated_bytes(&self) -> Vec<u8> {
+        let mut out = vec![0u8; Self::LEN];
+        out[..4].copy_from_slice(AEKV_MAGIC);
+        out[4] = self.version;
+        out[5] = self.cipher.id();
+        out[6] = if self.compressed { FLAG_COMPRESSED } else { 0 }
+            | if self.codec == Codec::Cbor {
+                FLAG_CBOR
+            } else {
+                0
+            };
+        out[7] = NONCE_LEN as u8;
+        out[8..].copy_from_slice(&self.nonce);
+        if let Some(checksum) = &self.checksum {
+            out.extend_from_slice(checksum);
+        }
+        out
+    }
+
+    /// The checksum of `plaintext` for a payload encrypted with `auth_key`.
+    pub fn checksum_of(auth_key: &str, plaintext: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let key = blake3::derive_key(CHECKSUM_CONTEXT, auth_key.as_bytes());
+        *blake3::keyed_hash(&key, plaintext).as_bytes()
+    }
+
+    /// `Ok(None)` if `bytes` does not start with the magic (a legacy file).
+    pub fn parse(bytes: &[u8]) -> Result<Option<Self>, String> {
+        if !bytes.starts_with(AEKV_MAGIC) {
+            return Ok(None);
+        }
+        if bytes.len() < Self::LEN {
+            return Err("truncated file header".into());
+        }
+        let version = bytes[4];
+        if version == 0 || version > AEKV_FORMAT_VERSION {
+            return Err(format!(
+                "file format version {} is not supported by this build (up to {})",
+                version, AEKV_FORMAT_VERSION
+            ));
+        }
+        let cipher =
+            Cipher::from_id(bytes[5]).ok_or_else(|| format!("unknown cipher id {}", bytes[5]))?;
+        let flags = bytes[6];
+        if flags & !KNOWN_FLAGS != 0 || (version == 1 && flags & FLAG_CBOR != 0) {
+            return Err(format!("unknown file flags {:#010b}", flags));
+        }
+        if bytes[7] as usize != NONCE_LEN {
+            return Err(format!("unsupported nonce length {}", bytes[7]));
+        }
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&bytes[8..Self::LEN]);
+        let checksum = if version >= 3 {
+            let stored = bytes
+                .get(Self::LEN..Self::LEN + CHECKSUM_LEN)
+                .ok_or_else(|| "truncated file header".to_string())?;
+            let mut checksum = [0u8; CHECKSUM_LEN];
+            checksum.copy_from_slice(stored);
+            Some(checksum)
+        } else {
+            None
+        };
+        let wrapped_key = if version >= 4 {
+            let stored = bytes
+                .get(Self::LEN + CHECKSUM_LEN..Self::MAX_LEN)
+                .ok_or_else(|| "truncated file header".to_string())?;
+            let mut wrapped = [0u8; WRAPPED_KEY_LEN];
+            wrapped.copy_from_slice(stored);
+            Some(wrapped)
+        } else {
+            None
+        };
+        Ok(Some(Self {
+            version,
+            cipher,
+            compressed: flags & FLAG_COMPRESSED != 0,
+            codec: if flags & FLAG_CBOR != 0 {
+                Codec::Cbor
+            } else {
+                Codec::Json
+            },
+            nonce,
+            checksum,
+            wrapped_key,
+        }))
+    }
+
+    /// This header with `data_key` wrapped under `auth_key`.
+    pub fn wrap_data_key(&self, auth_key: &str, data_key: &str) -> Result<Self, String> {
+        let raw = AegCrypto::decode_key(data_key)?;
+        let nonce = AegCrypto::random_nonce()?;
+        let sealed = AegCrypto::seal_with_nonce(
+            self.cipher,
+            auth_key,
+            &nonce,
+            &self.authenticated_bytes(),
+            &raw,
+        )?;
+        let mut wrapped = [0u8; WRAPPED_KEY_LEN];
+        if sealed.len() != WRAPPED_KEY_LEN - NONCE_LEN {
+            return Err(format!("data key wraps to {} bytes", sealed.len()));
+        }
+        wrapped[..NONCE_LEN].copy_from_slice(&nonce);
+        wrapped[NONCE_LEN..].copy_from_slice(&sealed);
+        Ok(Self {
+            wrapped_key: Some(wrapped),
+            ..*self
+        })
+    }
+
+    /// The data key wrapped in this header, unwrapped with `auth_key`.
+    pub fn unwrap_data_key(&self, auth_key: &str) -> Result<Zeroizing<String>, String> {
+        let wrapped = self
+            .wrapped_key
+            .ok_or_else(|| format!("a version {} file has no data key", self.version))?;
+        let (nonce, sealed) = wrapped.split_at(NONCE_LEN);
+        let raw = AegCrypto::open_with_nonce(
+            self.cipher,
+            auth_key,
+            nonce,
+            &self.authenticated_bytes(),
+            sealed,
+        )?;
+        Ok(Zeroizing::new(AegCrypto::encode_base64(&*raw)))
+    }
+}
+
+/// How a collection is serialized before compression and encryption.
+/// New files are written as CBOR; the header says which one a file uses,
+/// and files without a codec flag (including every older file) are JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Json,
+    #[default]
+    Cbor,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Cbor => write!(f, "cbor"),
+        }
+    }
+}
+
+impl Codec {
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec(value).map_err(|e| format!("serialize error: {}", e)),
+            Self::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out)
+                    .map_err(|e| format!("serialize error: {}", e))?;
+                Ok(out)
+            }
+        }
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|e| format!("invalid JSON: {}", e)),
+            Self::Cbor => ciborium::from_reader(bytes).map_err(|e| format!("invalid CBOR: {}", e)),
+        }
+    }
+}
+
+/// Reading and writing `.aekv` collection files.
+pub struct AegFileFormat;
+
+impl AegFileFormat {
+    /// Encrypt JSON `plaintext` into the current format with a fresh nonce.
+    pub fn encode(cipher: Cipher, auth_key: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        Self::encode_with(cipher, auth_key, plaintext, Codec::Json, false)
+    }
+
+    /// `encode` for a `plaintext` serialized with `codec`, compressing it
+    /// first when `compress` is set and compression actually makes it
+    /// smaller.
+    pub fn encode_with(
+        cipher: Cipher,
+        auth_key: &str,
+        plaintext: &[u8],
+        codec: Codec,
+        compress: bool,
+    ) -> Result<Vec<u8>, String> {
+        let compressed = if compress {
+            Some(AegCompress::compress(plaintext)?).filter(|c| c.len() < plaintext.len())
+        } else {
+            None
+        };
+        let data_key = Zeroizing::new(AegCrypto::encode_base64(
+            Zeroizing::new(AegCrypto::generate_random_bytes()).as_slice(),
+        ));
+        let header = AekvHeader {
+            version: AEKV_FORMAT_VERSION,
+            cipher,
+            compressed: compressed.is_some(),
+            codec,
+            nonce: AegCrypto::random_nonce()?,
+            checksum: Some(AekvHeader::checksum_of(&data_key, plaintext)),
+            wrapped_key: None,
+        }
+        .wrap_data_key(auth_key, &data_key)?;
+        let header_bytes = header.to_bytes();
+        let payload = compressed.as_deref().unwrap_or(plaintext);
+        let ciphertext = AegCrypto::seal_with_nonce(
+            cipher,
+            &data_key,
+            &header.nonce,
+            &header.authenticated_bytes(),
+            payload,
+        )?;
+        let mut out = Vec::with_capacity(header_bytes.len() + ciphertext.len());
+        out.extend_from_slice(&header_bytes);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypt a file in any format: the current header format, or the
+    /// older unheadered base64 text (see `AegCrypto::decrypt_blob`).
+    /// Fails when the plaintext does not match the header's checksum. The
+    /// plaintext is wiped when dropped.
+    pub fn decode(auth_key: &str, bytes: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let Some(header) = AekvHeader::parse(bytes)? else {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|_| "not a collection file (no header, not text)".to_string())?;
+            return AegCrypto::decrypt_blob(auth_key, text);
+        };
+        let data_key = match header.wrapped_key {
+            Some(_) => header.unwrap_data_key(auth_key)?,
+            None => Zeroizing::new(auth_key.to_string()),
+        };
+        let ciphertext = bytes
+            .get(header.encoded_len()..)
+            .ok_or_else(|| "truncated file header".to_string())?;
+        let payload = AegCrypto::open_with_nonce(
+            header.cipher,
+            &data_key,
+            &header.nonce,
+            &header.authenticated_bytes(),
+            ciphertext,
+        )?;
+        let plain = if header.compressed {
+            Zeroizing::new(AegCompress::decompress(&payload)?)
+        } else {
+            payload
+        };
+        if let Some(expected) = header.checksum
+            && AekvHeader::checksum_of(&data_key, &plain) != expected
+        {
+            return Err(
+                "checksum mismatch: the decrypted contents are not what was written".into(),
+            );
+        }
+        Ok(plain)
+    }
+
+    /// Move a file from `old_key` to `new_key`. Files with a data key only
+    /// have it re-wrapped, leaving the payload as it is; older ones are
+    /// decrypted and written again in the current format.
+    pub fn rewrap(old_key: &str, new_key: &str, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        match AekvHeader::parse(bytes)? {
+            Some(header) if header.wrapped_key.is_some() => {
+                let data_key = header.unwrap_data_key(old_key)?;
+                let mut out = header.wrap_data_key(new_key, &data_key)?.to_bytes();
+                out.extend_from_slice(&bytes[header.encoded_len()..]);
+                Ok(out)
+            }
+            _ => {
+                let plain = Self::decode(old_key, bytes)?;
+                Self::encode_with(
+                    Self::cipher_of(bytes),
+                    new_key,
+                    &plain,
+                    Self::codec_of(bytes),
+                    Self::is_compressed(bytes),
+                )
+            }
+        }
+    }
+
+    /// The data key of a file, unwrapped with `auth_key`. Anyone holding it
+    /// can read that one file and nothing else.
+    pub fn data_key(auth_key: &str, bytes: &[u8]) -> Result<Zeroizing<String>, String> {
+        AekvHeader::parse(bytes)?
+            .ok_or_else(|| "a file without a header has no data key".to_string())?
+            .unwrap_data_key(auth_key)
+    }
+
+    /// Codec of a file's payload; JSON for files without a header.
+    pub fn codec_of(bytes: &[u8]) -> Codec {
+        match AekvHeader::parse(bytes) {
+            Ok(Some(header)) => header.codec,
+            _ => Codec::Json,
+        }
+    }
+
+    /// Whether a file's payload was compressed before encryption.
+    pub fn is_compressed(bytes: &[u8]) -> bool {
+        matches!(AekvHeader::parse(bytes), Ok(Some(h)) if h.compressed)
+    }
+
+    /// Whether a file predates the current format and should be rewritten.
+    pub fn needs_upgrade(bytes: &[u8]) -> bool {
+        !matches!(AekvHeader::parse(bytes), Ok(Some(h)) if h.version == AEKV_FORMAT_VERSION)
+    }
+
+    /// Cipher a file was written with; unheadered files name theirs in the
+    /// text envelope or are AES-GCM.
+    pub fn cipher_of(bytes: &[u8]) -> Cipher {
+        match AekvHeader::parse(bytes) {
+            Ok(Some(header)) => header.cipher,
+            _ => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(AegCrypto::envelope_cipher)
+                .unwrap_or_default(),
+        }
+    }
+}
@@ -1,188 +1,1364 @@
-use crate::constant::{STORE_AUTHORIZATION_KEY, STORE_COLLECTION, STORE_CONFIG_AEG, STORE_DIR};
-use crate::crypto::AegCrypto;
-use aes_gcm::aead::Aead;
-use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use crate::backups::AegBackups;
+use crate::builder::StoreContext;
+use crate::clock::ClockSkewPolicy;
+use crate::constant::{
+    DEFAULT_PROFILE, READ_ONLY_ERROR, STORE_AUTHORIZATION_KEY, STORE_COLLECTION, STORE_CONFIG_AEG,
+    STORE_DECOY_DIR, STORE_DIR, STORE_DURESS_SALT, STORE_EMERGENCY_FILE, STORE_HOME_ENV, STORE_LOCK_FILE,
+    STORE_LOCK_TIMEOUT_MS, STORE_ORPHANED_SUFFIX, STORE_PASSPHRASE_ENV, STORE_PROFILES_DIR, STORE_STORAGE_ENV,
+};
+use crate::crypto::{AegCrypto, Cipher, MasterKeyProvider};
+use crate::file_format::AegFileFormat;
+use crate::hardware_key::{HardwareKeyConfig, HardwareKeyProvider};
+use crate::hsm::{AegHsm, HsmConfig};
+use crate::integrity::AegIntegrity;
+use crate::keyring::{AegKeyring, KeyringConfig, KeyringKeyProvider};
+use crate::lint::LintLevel;
+use crate::memory_engine::AegMemoryEngine;
+use crate::naming::KeyConvention;
+use crate::recovery::AegRecovery;
+use crate::storage::{FsStorage, MemoryStorage, StorageBackend};
+use crate::verbosity::Verbosity;
+use crate::verify::AegVerifier;
 use base64::{Engine as _, engine::general_purpose};
 use dirs_next::home_dir;
-use rand_core::TryRngCore;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
-use std::fs;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{Level, error, info, warn};
+use zeroize::Zeroizing;
 
 pub struct AegFileSystem;
 
+/// While set, every path and key lookup resolves to the decoy store.
+#[derive(Clone)]
+struct DuressSession {
+    dir: PathBuf,
+    key: Zeroizing<String>,
+}
+
+/// Where `setup_duress` used to put the decoy store and its salt.
+const LEGACY_DECOY_DIR: &str = "decoy";
+const LEGACY_DURESS_SALT: &str = "DURESS_SALT";
+
+static DURESS_SESSION: OnceLock<RwLock<Option<DuressSession>>> = OnceLock::new();
+static BASE_DIR: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+static LOCK_TIMEOUT: OnceLock<RwLock<Duration>> = OnceLock::new();
+static HELD_LOCKS: OnceLock<Mutex<HashMap<PathBuf, HeldLock>>> = OnceLock::new();
+static STORAGE: OnceLock<RwLock<Arc<dyn StorageBackend>>> = OnceLock::new();
+static PASSPHRASE: OnceLock<RwLock<Option<Zeroizing<String>>>> = OnceLock::new();
+/// Stored key used instead of the AUTHORIZATION_KEY file (see `set_stored_key`).
+static STORED_KEY: RwLock<Option<Zeroizing<String>>> = RwLock::new(None);
+/// Passphrases of the sealed collections unsealed in this process.
+static UNSEALED: OnceLock<Mutex<UnsealedKeys>> = OnceLock::new();
+/// Set by `AegCore::set_read_only`; see `AegFileSystem::ensure_writable`.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+static STRETCHED_PASSPHRASE: OnceLock<Mutex<Option<StretchedPassphrase>>> = OnceLock::new();
+
+/// The last passphrase stretched with Argon2, which is slow by design while
+/// the key is needed on every save.
+struct StretchedPassphrase {
+    salt: String,
+    passphrase: Zeroizing<String>,
+    key: Zeroizing<String>,
+}
+
+/// Stretched collection passphrases by store directory and collection.
+type UnsealedKeys = HashMap<(PathBuf, String), Zeroizing<String>>;
+
+/// An OS lock on a store's lock file, shared by every guard in this process.
+/// `flock` locks belong to the open file, so a second handle opened by the
+/// same process would block on the first; guards count holders instead.
+struct HeldLock {
+    _guard: Box<dyn Send>,
+    holders: usize,
+}
+
+/// Advisory lock on a store directory, taken around reads and writes of its
+/// collection files and collection.lock so that two processes never see each
+/// other's half-written files. Re-entrant within a process; released when the
+/// last guard is dropped.
+#[must_use = "the store is unlocked as soon as the guard is dropped"]
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let mut held = AegFileSystem::held_locks()
+            .lock()
+            .expect("Failed to lock held store locks");
+        if let Some(lock) = held.get_mut(&self.path) {
+            lock.holders -= 1;
+            if lock.holders == 0 {
+                // closing the file releases the OS lock
+                held.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Per-collection settings stored alongside the collection list.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CollectionMeta {
+    /// When true, the background saver skips this collection; it is only
+    /// persisted by an explicit flush.
+    #[serde(default)]
+    pub skip_autosave: bool,
+    /// Maximum number of entries kept decrypted in memory. Older entries are
+    /// paged out to the cold record file. `None` keeps everything warm.
+    #[serde(default)]
+    pub warm_capacity: Option<usize>,
+    /// Keep an encrypted key -> record index on disk so single keys can be
+    /// read without decrypting the whole collection.
+    #[serde(default)]
+    pub indexed: bool,
+    /// Naming rule for new keys (see `KeyConvention`); `None` allows any.
+    #[serde(default)]
+    pub key_convention: Option<KeyConvention>,
+    /// Keep values encrypted in memory as well, under a key that lives only
+    /// in this process, and open each one just while it is read. Less
+    /// plaintext sits on the heap of a long-running process, at the cost of
+    /// a decryption per access.
+    #[serde(default)]
+    pub sealed_values: bool,
+    /// Set while the collection is sealed with its own passphrase (see
+    /// `AegCore::seal_collection`).
+    #[serde(default)]
+    pub seal: Option<CollectionSeal>,
+}
+
+/// How a collection's own passphrase is stretched and checked. The
+/// passphrase itself is never stored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionSeal {
+    /// Base64 Argon2 salt.
+    pub salt: String,
+    /// `AegCrypto::passphrase_check` of the stretched passphrase.
+    pub check: String,
+}
+
+/// Plaintext store settings (config.aeg). Must stay readable before any
+/// decryption, so it never contains secrets.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StoreConfig {
+    /// Mix this machine's identifier into the encryption key.
+    #[serde(default)]
+    pub machine_binding: bool,
+    /// Algorithm for newly written collection files; existing files keep
+    /// theirs until rewritten.
+    #[serde(default)]
+    pub cipher: Cipher,
+    /// How `put` treats values that look like mistakes (see `AegLint`).
+    #[serde(default)]
+    pub lint: LintLevel,
+    /// Warn when `put` stores a value another key of the collection holds.
+    #[serde(default)]
+    pub warn_duplicates: bool,
+    /// Compress collection payloads of at least this many bytes before
+    /// encrypting them; `None` never compresses.
+    #[serde(default)]
+    pub compress_min_bytes: Option<usize>,
+    /// PKCS#11 token the encryption key is bound to (see `AegHsm`).
+    #[serde(default)]
+    pub hsm: Option<HsmConfig>,
+    /// Record every change in the hash-chained audit log (see `AegAudit`).
+    #[serde(default)]
+    pub audit: bool,
+    /// Let `clear` wipe a collection without snapshotting the store first.
+    #[serde(default)]
+    pub skip_clear_snapshot: bool,
+    /// How expiry behaves while the system clock is detected to be off
+    /// (see `AegClock`).
+    #[serde(default)]
+    pub clock_skew: ClockSkewPolicy,
+    /// Mix a passphrase into the encryption key (see
+    /// `AegCore::change_passphrase`).
+    #[serde(default)]
+    pub passphrase: Option<PassphraseConfig>,
+    /// Previous versions of each collection file kept in `backups` (see
+    /// `AegBackups`); 0 keeps none.
+    #[serde(default)]
+    pub backup_generations: usize,
+    /// OS keyring entry holding the stored key in place of the
+    /// AUTHORIZATION_KEY file (see `AegKeyring`).
+    #[serde(default)]
+    pub keyring: Option<KeyringConfig>,
+    /// The stored key wrapped by a hardware token, in place of the
+    /// AUTHORIZATION_KEY file (see `AegHardwareKey`).
+    #[serde(default)]
+    pub hardware_key: Option<HardwareKeyConfig>,
+}
+
+/// The stored key in the AUTHORIZATION_KEY file of the current store.
+pub struct FileKeyProvider;
+
+impl MasterKeyProvider for FileKeyProvider {
+    fn name(&self) -> &'static str {
+        "key file"
+    }
+
+    fn load(&self) -> Result<Zeroizing<String>, String> {
+        let path = AegFileSystem::get_config_path().join(STORE_AUTHORIZATION_KEY);
+        AegFileSystem::read_store_text(&path)
+            .map(Zeroizing::new)
+            .map_err(|e| format!("Failed to read authorization key {}: {}", path.display(), e))
+    }
+
+    fn store(&self, key: &str) -> Result<(), String> {
+        let path = AegFileSystem::get_config_path().join(STORE_AUTHORIZATION_KEY);
+        AegFileSystem::storage()
+            .write(&path, key.as_bytes())
+            .map_err(|e| format!("write {}: {}", path.display(), e))
+    }
+}
+
+/// How the store's passphrase is stretched. The passphrase itself is never
+/// stored; see `AegFileSystem::set_passphrase`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PassphraseConfig {
+    /// Base64 Argon2 salt, replaced whenever the passphrase changes.
+    pub salt: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CollectionLock {
     pub active: String,
     pub collections: Vec<String>,
+    #[serde(default)]
+    pub meta: HashMap<String, CollectionMeta>,
+}
+
+/// One file of a store-wide re-encryption, for progress reporting.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RekeyProgress {
+    /// Files re-encrypted so far, this one included.
+    pub done: usize,
+    pub total: usize,
+    /// Path of the file relative to the store directory.
+    pub file: String,
+}
+
+/// What `AegFileSystem::compact` reclaimed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    /// Files (and backups) of collections no longer in collection.lock,
+    /// now moved aside as `<file>.orphaned` (see `compact`).
+    pub quarantined_files: Vec<String>,
+    /// Collections whose record file held stale records, now rewritten.
+    pub compacted: Vec<String>,
+    pub reclaimed_bytes: u64,
+}
+
+impl CompactReport {
+    pub fn summary(&self) -> String {
+        if self.reclaimed_bytes == 0 && self.quarantined_files.is_empty() {
+            return "✓ Nothing to compact".into();
+        }
+        let mut out = format!(
+            "✓ Reclaimed {} bytes: {} orphaned files moved aside, {} record files compacted",
+            self.reclaimed_bytes,
+            self.quarantined_files.len(),
+            self.compacted.len()
+        );
+        for name in &self.quarantined_files {
+            out.push_str(&format!("\n  moved aside {}", name));
+        }
+        for name in &self.compacted {
+            out.push_str(&format!("\n  compacted {}", name));
+        }
+        out
+    }
+}
+
+/// Resolves named profiles to their store directories. Every profile is a
+/// complete, independent store (own key, config and collections) under
+/// `<default store>/profiles/<name>`; `default` is the default store itself.
+pub struct ProfileManager;
+
+impl ProfileManager {
+    pub fn profiles_dir() -> PathBuf {
+        AegFileSystem::default_store_dir().join(STORE_PROFILES_DIR)
+    }
+
+    fn validate_name(name: &str) -> Result<(), String> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "invalid profile name '{}' (use letters, digits, '-' or '_')",
+                name
+            ))
+        }
+    }
+
+    /// Store directory for `name`, whether or not it exists yet.
+    pub fn path_for(name: &str) -> Result<PathBuf, String> {
+        if name == DEFAULT_PROFILE {
+            return Ok(AegFileSystem::default_store_dir());
+        }
+        Self::validate_name(name)?;
+        Ok(Self::profiles_dir().join(name))
+    }
+
+    pub fn exists(name: &str) -> bool {
+        name == DEFAULT_PROFILE
+            || Self::path_for(name).is_ok_and(|p| AegFileSystem::storage().exists(&p))
+    }
+
+    /// Create the directory for a new profile. Its files are written the
+    /// first time the profile is initialized.
+    pub fn create(name: &str) -> Result<PathBuf, String> {
+        let path = Self::path_for(name)?;
+        if Self::exists(name) {
+            return Err(format!("profile '{}' already exists", name));
+        }
+        AegFileSystem::storage()
+            .create_dir_all(&path)
+            .map_err(|e| format!("create {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// `default` followed by every named profile, sorted.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = AegFileSystem::storage()
+            .list_dirs(&Self::profiles_dir())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|n| Self::validate_name(n).is_ok())
+            .collect();
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+
+    /// The profile the current store directory belongs to, if any.
+    pub fn current() -> Option<String> {
+        let current = AegFileSystem::get_real_config_path();
+        Self::list()
+            .into_iter()
+            .find(|n| Self::path_for(n).is_ok_and(|p| p == current))
+    }
+
+    /// Shred a named profile's store. The default profile and the profile
+    /// in use cannot be deleted.
+    pub fn delete(name: &str) -> Result<usize, String> {
+        if name == DEFAULT_PROFILE {
+            return Err("the default profile cannot be deleted".into());
+        }
+        if !Self::exists(name) {
+            return Err(format!("profile '{}' does not exist", name));
+        }
+        if Self::current().as_deref() == Some(name) {
+            return Err(format!("profile '{}' is in use", name));
+        }
+        AegFileSystem::shred_directory(&Self::path_for(name)?)
+    }
 }
 
 impl AegFileSystem {
+    fn duress_session() -> &'static RwLock<Option<DuressSession>> {
+        DURESS_SESSION.get_or_init(|| RwLock::new(None))
+    }
+
+    /// The decoy store the process has open, which an `AegStore` running
+    /// on this thread is not affected by.
+    fn open_duress_session() -> Option<DuressSession> {
+        if StoreContext::current().is_some() {
+            return None;
+        }
+        Self::duress_session()
+            .read()
+            .expect("Failed to lock duress session")
+            .clone()
+    }
+
     pub fn get_config_path() -> PathBuf {
-        let mut config_path = home_dir().expect("Failed to get home directory");
-        config_path.push(STORE_DIR);
-        if !config_path.exists() {
-            fs::create_dir_all(&config_path).expect("Failed to create config directory");
+        if let Some(session) = Self::open_duress_session() {
+            return session.dir;
+        }
+        Self::get_real_config_path()
+    }
+
+    fn base_dir() -> &'static RwLock<Option<PathBuf>> {
+        BASE_DIR.get_or_init(|| RwLock::new(None))
+    }
+
+    /// Use `dir` as the store directory for the rest of this process,
+    /// overriding `AEGISR_HOME` and `~/.aegisr`. This only changes where
+    /// paths resolve; use `AegCore::set_store_dir` to switch stores after
+    /// collections have been loaded.
+    pub fn set_base_dir(dir: PathBuf) {
+        *Self::base_dir().write().expect("Failed to lock base dir") = Some(dir);
+    }
+
+    /// Go back to `AEGISR_HOME` or `~/.aegisr`.
+    pub fn clear_base_dir() {
+        *Self::base_dir().write().expect("Failed to lock base dir") = None;
+    }
+
+    /// The directory given to `set_base_dir`, if any, or that of the
+    /// `AegStore` this thread runs.
+    pub fn base_dir_override() -> Option<PathBuf> {
+        if let Some(context) = StoreContext::current() {
+            return Some(context.dir.clone());
+        }
+        Self::base_dir()
+            .read()
+            .expect("Failed to lock base dir")
+            .clone()
+    }
+
+    fn lock_timeout() -> Duration {
+        *LOCK_TIMEOUT
+            .get_or_init(|| RwLock::new(Duration::from_millis(STORE_LOCK_TIMEOUT_MS)))
+            .read()
+            .expect("Failed to lock store lock timeout")
+    }
+
+    /// How long `lock_store` waits for another process to release a store
+    /// before giving up. Applies to the rest of this process.
+    pub fn set_lock_timeout(timeout: Duration) {
+        *LOCK_TIMEOUT
+            .get_or_init(|| RwLock::new(Duration::from_millis(STORE_LOCK_TIMEOUT_MS)))
+            .write()
+            .expect("Failed to lock store lock timeout") = timeout;
+    }
+
+    fn held_locks() -> &'static Mutex<HashMap<PathBuf, HeldLock>> {
+        HELD_LOCKS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    /// Lock the store at `dir` against other processes, waiting up to the
+    /// lock timeout. Readers take the same exclusive lock as writers: the
+    /// critical sections are single file reads and writes, so there is
+    /// little to gain from sharing.
+    pub fn lock_store(dir: &Path) -> Result<StoreLock, String> {
+        let path = dir.join(STORE_LOCK_FILE);
+        let storage = Self::storage();
+        let timeout = Self::lock_timeout();
+        let started = Instant::now();
+        loop {
+            {
+                let mut held = Self::held_locks()
+                    .lock()
+                    .expect("Failed to lock held store locks");
+                if let Some(lock) = held.get_mut(&path) {
+                    lock.holders += 1;
+                    return Ok(StoreLock { path });
+                }
+                match storage.try_lock(&path) {
+                    Ok(Some(guard)) => {
+                        held.insert(
+                            path.clone(),
+                            HeldLock {
+                                _guard: guard,
+                                holders: 1,
+                            },
+                        );
+                        return Ok(StoreLock { path });
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        return Err(format!("lock {}: {}", path.display(), e));
+                    }
+                }
+            }
+            if started.elapsed() >= timeout {
+                return Err(format!(
+                    "store at '{}' is locked by another process (gave up after {} ms)",
+                    dir.display(),
+                    timeout.as_millis()
+                ));
+            }
+            // not holding the map while waiting, so other threads can take
+            // and release their locks meanwhile
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Keep store files in `storage` instead of the local file system (see
+    /// `StorageBackend`). Applies to the whole process outside
+    /// `AegStore::run`; flush and
+    /// `AegMemoryEngine::reset_cache` first, as cached collections belong
+    /// to the old storage.
+    pub fn set_storage(storage: Arc<dyn StorageBackend>) {
+        *Self::storage_slot()
+            .write()
+            .expect("Failed to lock storage backend") = storage;
+    }
+
+    /// The backend store files are read from and written to: that of the
+    /// `AegStore` this thread runs, or the one set for the process.
+    pub fn storage() -> Arc<dyn StorageBackend> {
+        if let Some(context) = StoreContext::current() {
+            return Arc::clone(&context.storage);
+        }
+        Arc::clone(
+            &Self::storage_slot()
+                .read()
+                .expect("Failed to lock storage backend"),
+        )
+    }
+
+    fn storage_slot() -> &'static RwLock<Arc<dyn StorageBackend>> {
+        STORAGE.get_or_init(|| RwLock::new(Self::default_storage()))
+    }
+
+    /// The backend a process starts with: `MemoryStorage` when the
+    /// `AEGISR_STORAGE` environment variable is `memory`, so test and bench
+    /// runs never touch a real store, otherwise the local file system.
+    pub fn default_storage() -> Arc<dyn StorageBackend> {
+        if Self::memory_storage_selected() {
+            Arc::new(MemoryStorage::new())
+        } else {
+            Arc::new(FsStorage)
+        }
+    }
+
+    fn memory_storage_selected() -> bool {
+        std::env::var(STORE_STORAGE_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("memory"))
+    }
+
+    /// The real store directory, ignoring any duress session. Resolved from
+    /// `set_base_dir`, then the `AEGISR_HOME` environment variable, then
+    /// `~/.aegisr`.
+    pub fn get_real_config_path() -> PathBuf {
+        let config_path = Self::base_dir_override().unwrap_or_else(Self::default_store_dir);
+        let storage = Self::storage();
+        if !storage.exists(&config_path) {
+            storage
+                .create_dir_all(&config_path)
+                .expect("Failed to create config directory");
         }
         config_path
     }
 
+    /// `AEGISR_HOME` or `~/.aegisr`, ignoring `set_base_dir`. This is the
+    /// default profile and the root that named profiles live under. With
+    /// `AEGISR_STORAGE=memory` and no `AEGISR_HOME`, a path of this process
+    /// that only exists in memory.
+    pub fn default_store_dir() -> PathBuf {
+        std::env::var_os(STORE_HOME_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                if Self::memory_storage_selected() {
+                    return std::env::temp_dir()
+                        .join(format!("aegisr_memory_{}", std::process::id()));
+                }
+                home_dir()
+                    .expect("Failed to get home directory")
+                    .join(STORE_DIR)
+            })
+    }
+
+    /// Where the decoy store lives, next to the real one. Its names say
+    /// nothing about duress; a decoy set up under the old `decoy/` and
+    /// `DURESS_SALT` names is moved to them.
+    pub fn get_decoy_path() -> PathBuf {
+        let dir = Self::get_real_config_path().join(STORE_DECOY_DIR);
+        let legacy = Self::get_real_config_path().join(LEGACY_DECOY_DIR);
+        let storage = Self::storage();
+        if !storage.exists(&dir)
+            && storage.exists(&legacy.join(LEGACY_DURESS_SALT))
+            && !Self::is_read_only()
+        {
+            let _ = storage.rename(&legacy, &dir).and_then(|_| {
+                storage.rename(&dir.join(LEGACY_DURESS_SALT), &dir.join(STORE_DURESS_SALT))
+            });
+        }
+        dir
+    }
+
+    /// Redirect all store access to the decoy store using `key`.
+    /// Callers must flush and drop the in-memory cache first.
+    pub fn begin_duress_session(dir: PathBuf, key: Zeroizing<String>) {
+        *Self::duress_session()
+            .write()
+            .expect("Failed to lock duress session") = Some(DuressSession { dir, key });
+    }
+
+    pub fn end_duress_session() {
+        *Self::duress_session()
+            .write()
+            .expect("Failed to lock duress session") = None;
+    }
+
+    pub fn in_duress_session() -> bool {
+        Self::open_duress_session().is_some()
+    }
+
+    pub fn set_read_only(enabled: bool) {
+        READ_ONLY.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_read_only() -> bool {
+        READ_ONLY.load(Ordering::SeqCst)
+    }
+
+    /// `Err(READ_ONLY_ERROR)` while the store is open read-only. Checked
+    /// before anything is written to the store directory, so a read-only
+    /// process cannot change the store even through a path that forgot to
+    /// refuse earlier.
+    pub fn ensure_writable() -> Result<(), String> {
+        if Self::is_read_only() {
+            return Err(READ_ONLY_ERROR.to_string());
+        }
+        Ok(())
+    }
+
     pub fn reset_files() {
         let path = Self::get_config_path();
-        if path.exists() {
-            fs::remove_dir_all(&path).expect("Failed to delete .aegisr configuration directory");
+        let storage = Self::storage();
+        if storage.exists(&path) {
+            storage
+                .remove_dir_all(&path)
+                .expect("Failed to delete .aegisr configuration directory");
         }
-        fs::create_dir_all(&path).expect("Failed to recreate config directory");
+        storage
+            .create_dir_all(&path)
+            .expect("Failed to recreate config directory");
     }
 
     pub fn validate_files() {
         let path = Self::get_config_path();
         let collection_lock: PathBuf = path.join(STORE_COLLECTION);
         let config_file = path.join(STORE_CONFIG_AEG);
-        let auth_file = path.join(STORE_AUTHORIZATION_KEY);
-        if !config_file.exists() || !auth_file.exists() || !collection_lock.exists() {
-            println!("Missing file. Running initialize config.");
-            Self::initialize_config(None, None);
+        let storage = Self::storage();
+        if !storage.exists(&config_file)
+            || !Self::has_stored_key()
+            || !storage.exists(&collection_lock)
+        {
+            warn!("store files missing; initializing the store");
+            Self::initialize_config(None, Verbosity::default());
         } else {
             if let Err(e) = Self::maybe_migrate_collection_lock() {
-                eprintln!("Migration failed: {}. Reinitializing.", e);
-                Self::initialize_config(None, None);
+                error!(error = %e, "collection lock migration failed; reinitializing");
+                Self::initialize_config(None, Verbosity::default());
             }
         }
     }
 
-    pub fn initialize_config(overwrite: Option<bool>, verbose_mode: Option<bool>) -> PathBuf {
+    pub fn initialize_config(overwrite: Option<bool>, verbosity: Verbosity) -> PathBuf {
         let overwrite_mode = overwrite.unwrap_or(false);
-        let _verbose_mode = verbose_mode.unwrap_or(false);
         let dir = Self::get_config_path();
+        let storage = Self::storage();
 
-        if overwrite_mode && dir.exists() {
-            fs::remove_dir_all(&dir).expect("Failed to remove existing config directory");
+        if overwrite_mode && storage.exists(&dir) {
+            if let Some(keyring) = Self::read_store_config().keyring {
+                let _ = AegKeyring::delete_key(&keyring);
+            }
+            storage
+                .remove_dir_all(&dir)
+                .expect("Failed to remove existing config directory");
+            if verbosity.enabled(Level::INFO) {
+                info!(dir = %dir.display(), "removed existing store");
+            }
         }
 
-        if !dir.exists() {
-            fs::create_dir_all(&dir).expect("Failed to create config directory");
+        if !storage.exists(&dir) {
+            storage
+                .create_dir_all(&dir)
+                .expect("Failed to create config directory");
+            if verbosity.enabled(Level::INFO) {
+                info!(dir = %dir.display(), "created store directory");
+            }
         }
 
         let key_path = dir.join(STORE_AUTHORIZATION_KEY);
-        let auth_key = if key_path.exists() {
-            fs::read_to_string(&key_path).expect("Failed to read AUTHORIZATION_KEY")
-        } else {
-            let k = AegCrypto::create_authorization_key(Some(_verbose_mode));
-            fs::write(&key_path, &k).expect("Failed to write AUTHORIZATION_KEY");
-            k
-        };
+        if !Self::has_stored_key() {
+            let k = AegCrypto::create_authorization_key(verbosity);
+            storage
+                .write(&key_path, k.as_bytes())
+                .expect("Failed to write AUTHORIZATION_KEY");
+            if verbosity.enabled(Level::INFO) {
+                info!(path = %key_path.display(), "wrote authorization key");
+            }
+        }
+
+        let config_path = dir.join(STORE_CONFIG_AEG);
+        if !storage.exists(&config_path) {
+            Self::write_store_config(&StoreConfig::default());
+        }
 
         let collection_path = dir.join(STORE_COLLECTION);
-        if !collection_path.exists() {
-            Self::write_collection_lock_default(&auth_key);
+        if !storage.exists(&collection_path) {
+            // the effective key may differ from the stored one (machine binding)
+            Self::write_collection_lock_default(&Self::read_authorization_key());
         }
 
         dir
     }
 
+    /// Encrypt the collection list into collection.lock, with a fresh
+    /// random nonce on every write (a `seal` envelope).
     pub fn write_collection_lock_json(data: &str, auth_key: &str) {
-        let key_bytes = general_purpose::STANDARD
-            .decode(auth_key)
-            .expect("Invalid base64");
-        let key_arr: [u8; 32] = key_bytes
-            .as_slice()
-            .try_into()
-            .expect("Auth key must be 32 bytes");
-        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
-        let cipher = Aes256Gcm::new(key);
-        let nonce = Nonce::from_slice(&key_arr[..12]);
-
-        let encrypted = cipher
-            .encrypt(nonce, data.as_bytes())
-            .expect("Encrypt failed");
-        let encoded = general_purpose::STANDARD.encode(&encrypted);
+        if Self::is_read_only() {
+            return;
+        }
+        let encoded = AegCrypto::seal(Cipher::Aes256Gcm, auth_key, data.as_bytes())
+            .unwrap_or_else(|e| panic!("{}", e));
 
-        let path = Self::get_config_path().join(STORE_COLLECTION);
-        let mut file = fs::File::create(&path).expect("Failed to open file");
-        use std::io::Write;
-        file.write_all(encoded.as_bytes()).expect("Write failed");
-        file.sync_all().expect("Flush failed");
+        let dir = Self::get_config_path();
+        let _lock = Self::lock_store(&dir).unwrap_or_else(|e| panic!("{}", e));
+        let path = dir.join(STORE_COLLECTION);
+        Self::storage()
+            .write(&path, encoded.as_bytes())
+            .expect("Write failed");
+        let _ = AegIntegrity::record(&dir, &[STORE_COLLECTION.to_string()]);
     }
 
-    pub fn read_collection_lock() -> String {
+    /// The decrypted collection.lock, empty if there is none. A lock in the
+    /// original fixed-nonce format is read and rewritten as an envelope.
+    pub fn read_collection_lock() -> Result<String, String> {
         let path = Self::get_config_path().join(STORE_COLLECTION);
-        if !path.exists() {
-            return String::new();
+        let storage = Self::storage();
+        if !storage.exists(&path) {
+            return Ok(String::new());
         }
 
         let auth_key = Self::read_authorization_key();
-        let key_bytes = general_purpose::STANDARD
-            .decode(auth_key)
-            .expect("Invalid auth key");
-
-        let key_arr: [u8; 32] = key_bytes
-            .as_slice()
-            .try_into()
-            .expect("Auth key must be 32 bytes");
-        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
-        let cipher = Aes256Gcm::new(key);
-        let nonce = Nonce::from_slice(&key_arr[..12]);
-
-        let encrypted = fs::read_to_string(&path).unwrap_or_default();
+        let encrypted = {
+            let _lock = Self::lock_store(&Self::get_config_path())?;
+            storage
+                .read(&path)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .unwrap_or_default()
+        };
         if encrypted.is_empty() {
-            return String::new();
+            return Ok(String::new());
         }
+        AegIntegrity::check(
+            &Self::get_config_path(),
+            STORE_COLLECTION,
+            encrypted.as_bytes(),
+            &auth_key,
+        );
 
-        let encrypted_bytes = general_purpose::STANDARD
-            .decode(encrypted)
-            .expect("Invalid base64 content");
-
-        let decrypted = cipher
-            .decrypt(nonce, encrypted_bytes.as_ref())
-            .expect("Decrypt failed");
-
-        String::from_utf8(decrypted).expect("Invalid UTF-8")
+        let decrypted = AegCrypto::decrypt_blob(&auth_key, &encrypted)
+            .map_err(|e| format!("{}: {}", STORE_COLLECTION, e))?;
+        let json = String::from_utf8(decrypted.to_vec())
+            .map_err(|_| format!("{}: not valid UTF-8", STORE_COLLECTION))?;
+        if AegCrypto::envelope_cipher(&encrypted).is_none() {
+            Self::write_collection_lock_json(&json, &auth_key);
+        }
+        Ok(json)
     }
 
-    pub fn read_collection_lock_obj() -> CollectionLock {
-        let json_str = Self::read_collection_lock();
+    pub fn read_collection_lock_obj() -> Result<CollectionLock, String> {
+        let json_str = Self::read_collection_lock()?;
         if json_str.trim().is_empty() {
-            return CollectionLock {
+            return Ok(CollectionLock {
                 active: "default".to_string(),
                 collections: vec!["default".to_string()],
-            };
+                meta: HashMap::new(),
+            });
         }
 
         match serde_json::from_str::<CollectionLock>(&json_str) {
-            Ok(lock) => lock,
+            Ok(lock) => Ok(lock),
             Err(_) => {
                 let s = json_str.trim().trim_matches('"').to_string();
                 let lock = CollectionLock {
                     active: s.clone(),
                     collections: vec![s],
+                    meta: HashMap::new(),
                 };
 
                 let auth_key = Self::read_authorization_key();
                 let serialized = serde_json::to_string_pretty(&lock).expect("Serialize failed");
                 Self::write_collection_lock_json(&serialized, &auth_key);
-                lock
+                Ok(lock)
             }
         }
     }
 
     fn maybe_migrate_collection_lock() -> Result<(), String> {
-        let _ = Self::read_collection_lock_obj();
-        Ok(())
+        Self::read_collection_lock_obj()?;
+        // make sure the (possibly rewritten) lock still decrypts
+        let check = AegVerifier::verify_file(
+            &Self::get_config_path().join(STORE_COLLECTION),
+            &Self::read_authorization_key(),
+        );
+        match check.error {
+            None => Ok(()),
+            Some(e) => Err(format!("{}: {}", check.file, e)),
+        }
     }
 
     pub fn write_collection_lock_default(auth_key: &str) {
         let lock = CollectionLock {
             active: "default".to_string(),
             collections: vec!["default".to_string()],
+            meta: HashMap::new(),
         };
         let serialized = serde_json::to_string_pretty(&lock).expect("Serialize failed");
         Self::write_collection_lock_json(&serialized, auth_key);
     }
 
-    pub fn read_authorization_key() -> String {
+    /// The key used to encrypt store files. Equal to the stored key unless
+    /// the store is bound to this machine.
+    pub fn read_authorization_key() -> Zeroizing<String> {
+        Self::try_read_authorization_key().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `read_authorization_key`, reporting a missing or malformed key file
+    /// (see `AegCrypto::validate_key`) or a failed binding as an error.
+    pub fn try_read_authorization_key() -> Result<Zeroizing<String>, String> {
+        if let Some(session) = Self::open_duress_session() {
+            return Ok(session.key);
+        }
         let path = Self::get_config_path().join(STORE_AUTHORIZATION_KEY);
-        fs::read_to_string(&path).expect("Failed to read authorization key")
+        let stored = match Self::stored_key() {
+            Some(stored) => stored,
+            None => Self::load_stored_key()?,
+        };
+        AegCrypto::validate_key(&stored)
+            .map_err(|e| format!("Invalid authorization key {}: {}", path.display(), e))?;
+        Self::effective_key(&stored, &Self::read_store_config())
+            .map_err(|e| format!("Failed to derive bound key: {}", e))
+    }
+
+    /// The encryption key for `stored` under `config`: bound to the HSM
+    /// token first, then to the passphrase, then to this machine, as each is
+    /// enabled.
+    pub fn effective_key(stored: &str, config: &StoreConfig) -> Result<Zeroizing<String>, String> {
+        let passphrase = Self::passphrase();
+        Self::effective_key_with(stored, config, passphrase.as_deref().map(String::as_str))
+    }
+
+    /// `effective_key` with `passphrase` instead of the one set for the process.
+    pub(crate) fn effective_key_with(
+        stored: &str,
+        config: &StoreConfig,
+        passphrase: Option<&str>,
+    ) -> Result<Zeroizing<String>, String> {
+        let mut key = Zeroizing::new(stored.to_string());
+        if let Some(hsm) = &config.hsm {
+            key = AegHsm::bind_key(hsm, &key)?;
+        }
+        if let Some(protection) = &config.passphrase {
+            let passphrase = passphrase.ok_or_else(|| {
+                format!(
+                    "the store is protected by a passphrase; set {} or call AegFileSystem::set_passphrase",
+                    STORE_PASSPHRASE_ENV
+                )
+            })?;
+            let stretched = Self::stretch_passphrase(passphrase, &protection.salt)?;
+            key = AegCrypto::bind_key_to_passphrase(&key, &stretched)?;
+        }
+        if config.machine_binding {
+            key = AegCrypto::bind_key_to_machine(&key, &AegCrypto::machine_fingerprint()?)?;
+        }
+        Ok(key)
+    }
+
+    /// Use `passphrase` to open passphrase-protected stores for the rest of
+    /// this process, or go back to `AEGISR_PASSPHRASE` with `None`.
+    pub fn set_passphrase(passphrase: Option<&str>) {
+        *Self::passphrase_slot()
+            .write()
+            .expect("Failed to lock passphrase") =
+            passphrase.map(|p| Zeroizing::new(p.to_string()));
+    }
+
+    /// The passphrase given to `set_passphrase`, if any.
+    pub(crate) fn passphrase_override() -> Option<Zeroizing<String>> {
+        Self::passphrase_slot()
+            .read()
+            .expect("Failed to lock passphrase")
+            .clone()
+    }
+
+    fn passphrase_slot() -> &'static RwLock<Option<Zeroizing<String>>> {
+        PASSPHRASE.get_or_init(|| RwLock::new(None))
+    }
+
+    fn passphrase() -> Option<Zeroizing<String>> {
+        Self::passphrase_slot()
+            .read()
+            .expect("Failed to lock passphrase")
+            .clone()
+            .or_else(|| std::env::var(STORE_PASSPHRASE_ENV).ok().map(Zeroizing::new))
+    }
+
+    /// `passphrase` stretched with `salt`, reusing the last result.
+    fn stretch_passphrase(passphrase: &str, salt: &str) -> Result<Zeroizing<String>, String> {
+        let mut last = STRETCHED_PASSPHRASE
+            .get_or_init(|| Mutex::new(None))
+            .lock()
+            .expect("Failed to lock stretched passphrase");
+        if let Some(hit) = last
+            .as_ref()
+            .filter(|l| l.salt == salt && l.passphrase.as_str() == passphrase)
+        {
+            return Ok(hit.key.clone());
+        }
+        let salt_bytes = general_purpose::STANDARD
+            .decode(salt.trim())
+            .map_err(|e| format!("base64 decode passphrase salt: {}", e))?;
+        let key = AegCrypto::derive_password_key(passphrase, &salt_bytes)?;
+        *last = Some(StretchedPassphrase {
+            salt: salt.to_string(),
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            key: key.clone(),
+        });
+        Ok(key)
+    }
+
+    /// The raw contents of AUTHORIZATION_KEY, before any machine binding.
+    pub fn read_stored_authorization_key() -> String {
+        if let Some(stored) = Self::stored_key() {
+            return stored.to_string();
+        }
+        Self::load_stored_key()
+            .unwrap_or_else(|e| panic!("{}", e))
+            .to_string()
+    }
+
+    /// Where the current store keeps its stored key, as its config says.
+    pub fn key_provider() -> Box<dyn MasterKeyProvider> {
+        let config = Self::read_store_config();
+        match (config.hardware_key, config.keyring) {
+            (Some(wrapped), _) => Box::new(HardwareKeyProvider(wrapped)),
+            (None, Some(keyring)) => Box::new(KeyringKeyProvider(keyring)),
+            (None, None) => Box::new(FileKeyProvider),
+        }
+    }
+
+    /// The stored key of the current store from its `key_provider`, or from
+    /// the AUTHORIZATION_KEY file when that provider cannot be reached but
+    /// the file is still there.
+    pub(crate) fn load_stored_key() -> Result<Zeroizing<String>, String> {
+        let provider = Self::key_provider();
+        match provider.load() {
+            Ok(stored) => Ok(stored),
+            Err(e) if provider.name() == FileKeyProvider.name() => Err(e),
+            Err(e) => {
+                let path = Self::get_config_path().join(STORE_AUTHORIZATION_KEY);
+                if !Self::storage().exists(&path) {
+                    return Err(format!(
+                        "Failed to read authorization key from the {}: {}",
+                        provider.name(),
+                        e
+                    ));
+                }
+                FileKeyProvider.load()
+            }
+        }
+    }
+
+    /// Replace the stored key of the current store wherever it is kept.
+    pub(crate) fn write_stored_key(stored: &str) -> Result<(), String> {
+        Self::key_provider().store(stored)
+    }
+
+    /// Whether the current store has a stored key, in a file or elsewhere.
+    pub fn has_stored_key() -> bool {
+        let config = Self::read_store_config();
+        config.keyring.is_some()
+            || config.hardware_key.is_some()
+            || Self::storage().exists(&Self::get_config_path().join(STORE_AUTHORIZATION_KEY))
+    }
+
+    /// Use `stored` in place of the AUTHORIZATION_KEY file, for a store whose
+    /// key is kept elsewhere (see `KeyProvider`), or read the file again
+    /// with `None`. Bindings in the store's config still apply.
+    pub(crate) fn set_stored_key(stored: Option<Zeroizing<String>>) {
+        *STORED_KEY.write().expect("Failed to lock stored key") = stored;
+    }
+
+    /// The key given to `set_stored_key`; an `AegStore` reads its own.
+    pub(crate) fn stored_key() -> Option<Zeroizing<String>> {
+        if StoreContext::current().is_some() {
+            return None;
+        }
+        STORED_KEY.read().expect("Failed to lock stored key").clone()
+    }
+
+    fn unsealed() -> &'static Mutex<UnsealedKeys> {
+        UNSEALED.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    /// Remember the stretched passphrase of a sealed collection of the
+    /// current store for this process, or forget it with `None`.
+    pub(crate) fn set_unsealed(collection: &str, stretched: Option<Zeroizing<String>>) {
+        let id = (Self::get_config_path(), collection.to_string());
+        let mut unsealed = Self::unsealed().lock().expect("Failed to lock unsealed keys");
+        match stretched {
+            Some(stretched) => {
+                unsealed.insert(id, stretched);
+            }
+            None => {
+                unsealed.remove(&id);
+            }
+        }
+    }
+
+    pub(crate) fn unsealed_passphrase(collection: &str) -> Option<Zeroizing<String>> {
+        Self::unsealed()
+            .lock()
+            .expect("Failed to lock unsealed keys")
+            .get(&(Self::get_config_path(), collection.to_string()))
+            .cloned()
+    }
+
+    /// Whether `collection` was unsealed in this process.
+    pub fn is_unsealed(collection: &str) -> bool {
+        Self::unsealed_passphrase(collection).is_some()
+    }
+
+    /// Key for the files of `collection`: its subkey of `master_key`,
+    /// bound to the collection's passphrase while it is unsealed.
+    pub fn collection_file_key(
+        master_key: &str,
+        collection: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        let subkey = AegCrypto::derive_collection_key(master_key, collection)?;
+        match Self::unsealed_passphrase(collection) {
+            Some(stretched) => AegCrypto::bind_key_to_collection_passphrase(&subkey, &stretched),
+            None => Ok(subkey),
+        }
+    }
+
+    /// A text file of the store directory, through the storage backend.
+    fn read_store_text(path: &Path) -> Result<String, String> {
+        let bytes = Self::storage().read(path).map_err(|e| e.to_string())?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn read_store_config() -> StoreConfig {
+        let path = Self::get_config_path().join(STORE_CONFIG_AEG);
+        Self::read_store_text(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write_store_config(config: &StoreConfig) {
+        if Self::is_read_only() {
+            return;
+        }
+        let path = Self::get_config_path().join(STORE_CONFIG_AEG);
+        let json = serde_json::to_string_pretty(config).expect("Serialize failed");
+        Self::storage()
+            .write(&path, json.as_bytes())
+            .expect("Failed to write store config");
+    }
+
+    /// Encrypted store files in `dir`: collection.lock first, then
+    /// emergency.lock, then collection data, index, and record files sorted
+    /// by name.
+    pub fn list_store_files(dir: &Path) -> Vec<String> {
+        let storage = Self::storage();
+        let mut names: Vec<String> = storage
+            .list(dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|n| {
+                n.starts_with("collection_")
+                    && (n.ends_with(".aekv") || n.ends_with(".idx") || n.ends_with(".cold"))
+            })
+            .collect();
+        names.sort();
+        if storage.exists(&dir.join(STORE_EMERGENCY_FILE)) {
+            names.insert(0, STORE_EMERGENCY_FILE.to_string());
+        }
+        if storage.exists(&dir.join(STORE_COLLECTION)) {
+            names.insert(0, STORE_COLLECTION.to_string());
+        }
+        names
+    }
+
+    /// Reclaim space in the current store: drop the records changed and
+    /// deleted keys left in record files, and move the files of collections
+    /// no longer in collection.lock, and their backups, aside as
+    /// `<file>.orphaned[.n]`. Nothing is deleted: remove the `.orphaned`
+    /// files once they are known to be unneeded. Unsaved changes of the
+    /// remaining collections are saved on the way.
+    pub fn compact() -> Result<CompactReport, String> {
+        let dir = Self::get_config_path();
+        let live = Self::read_collection_lock_obj()?.collections;
+        let mut report = CompactReport::default();
+        {
+            let _lock = Self::lock_store(&dir)?;
+            for name in Self::list_store_files(&dir) {
+                let Some(collection) = Self::collection_of_file(&name) else {
+                    continue;
+                };
+                if live.iter().any(|c| c == collection) {
+                    continue;
+                }
+                AegMemoryEngine::evict(collection);
+                AegRecovery::quarantine(&dir.join(&name), STORE_ORPHANED_SUFFIX)?;
+                report.quarantined_files.push(name);
+            }
+            for (path, file) in AegBackups::files(&dir) {
+                let collection = Self::collection_of_file(&file).unwrap_or_default();
+                if live.iter().any(|c| c == collection) {
+                    continue;
+                }
+                AegRecovery::quarantine(&path, STORE_ORPHANED_SUFFIX)?;
+                let name = path.strip_prefix(&dir).unwrap_or(&path);
+                report
+                    .quarantined_files
+                    .push(name.to_string_lossy().into_owned());
+            }
+        }
+        let _ = AegIntegrity::record(&dir, &report.quarantined_files);
+        for collection in live {
+            let reclaimed =
+                AegMemoryEngine::with_engine(&collection, |engine| engine.compact_records())
+                    .map_err(|e| format!("compact '{}': {}", collection, e))?;
+            if reclaimed > 0 {
+                report.reclaimed_bytes += reclaimed;
+                report.compacted.push(collection);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Collection a store file belongs to (`collection_<name>.<ext>`).
+    pub fn collection_of_file(file_name: &str) -> Option<&str> {
+        let stem = file_name.strip_prefix("collection_")?;
+        [".aekv", ".idx", ".cold"]
+            .iter()
+            .find_map(|ext| stem.strip_suffix(ext))
+    }
+
+    /// Keys that may have encrypted a store file, preferred first: a
+    /// collection file's key (see `collection_file_key`), its plain subkey
+    /// when that differs (files written before the collection was sealed),
+    /// then `master_key` (collection.lock, and collection files written
+    /// before per-collection keys).
+    pub fn store_file_keys(
+        file_name: &str,
+        master_key: &str,
+    ) -> Result<Vec<Zeroizing<String>>, String> {
+        match Self::collection_of_file(file_name) {
+            Some(collection) => {
+                let mut keys = vec![Self::collection_file_key(master_key, collection)?];
+                let subkey = AegCrypto::derive_collection_key(master_key, collection)?;
+                if subkey != keys[0] {
+                    keys.push(subkey);
+                }
+                keys.push(Zeroizing::new(master_key.to_string()));
+                Ok(keys)
+            }
+            None => Ok(vec![Zeroizing::new(master_key.to_string())]),
+        }
+    }
+
+    /// Re-encrypt one store file's content from `old_key` to `new_key`
+    /// (master keys; collection files use their subkeys). Files still under
+    /// the old master key are migrated to subkeys on the way.
+    /// Record sizes do not change, so cold-file offsets stay valid.
+    pub fn rekey_content(
+        name: &str,
+        content: &[u8],
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<Vec<u8>, String> {
+        let new_file_key = Self::store_file_keys(name, new_key)?.remove(0);
+        let mut first_err = None;
+        for old_file_key in Self::store_file_keys(name, old_key)? {
+            match Self::rekey_file_content(name, content, &old_file_key, &new_file_key) {
+                Ok(c) => return Ok(c),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_err.unwrap_or_default())
+    }
+
+    fn rekey_file_content(
+        name: &str,
+        content: &[u8],
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<Vec<u8>, String> {
+        if content.trim_ascii().is_empty() {
+            return Ok(content.to_vec());
+        }
+        if name.ends_with(".aekv") {
+            // only the data key is re-wrapped; older files are rewritten in
+            // the current format, with the algorithm they had
+            return AegFileFormat::rewrap(old_key, new_key, content);
+        }
+        let content = std::str::from_utf8(content).map_err(|_| "not a text file".to_string())?;
+        // each file and record keeps its format and algorithm, except that
+        // fixed-nonce blobs from the original format are sealed afresh
+        let reseal =
+            |original: &str, plain: &[u8], legacy: fn(&str, &[u8]) -> Result<String, String>| {
+                match AegCrypto::envelope_cipher(original) {
+                    Some(cipher) => AegCrypto::seal(cipher, new_key, plain),
+                    None => legacy(new_key, plain),
+                }
+            };
+        if name.ends_with(".cold") {
+            let mut out = String::with_capacity(content.len());
+            for line in content.lines() {
+                let plain = AegCrypto::decrypt_record(old_key, line)?;
+                out.push_str(&reseal(line, &plain, AegCrypto::encrypt_record)?);
+                out.push('\n');
+            }
+            return Ok(out.into_bytes());
+        }
+        if name.ends_with(".idx") {
+            let plain = AegCrypto::decrypt_record(old_key, content)?;
+            return reseal(content, &plain, AegCrypto::encrypt_record).map(String::into_bytes);
+        }
+        let plain = AegCrypto::decrypt_blob(old_key, content)?;
+        reseal(content, &plain, |key, plain| {
+            AegCrypto::seal(Cipher::Aes256Gcm, key, plain)
+        })
+        .map(String::into_bytes)
+    }
+
+    /// Re-encrypt every store file of `src` into `dest` (which may be the same
+    /// directory). All files are re-encrypted in memory before anything is
+    /// written; if a write fails midway, files already replaced in `dest` are
+    /// restored. Returns the number of files written.
+    pub fn rekey_directory(
+        src: &Path,
+        dest: &Path,
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<usize, String> {
+        Self::rekey_directory_with(src, dest, old_key, new_key, &mut |_| {})
+    }
+
+    /// `rekey_directory`, calling `progress` with each file's name once it
+    /// has been re-encrypted in memory (before anything is written).
+    pub fn rekey_directory_with(
+        src: &Path,
+        dest: &Path,
+        old_key: &str,
+        new_key: &str,
+        progress: &mut dyn FnMut(&str),
+    ) -> Result<usize, String> {
+        let storage = Self::storage();
+        let mut rekeyed = Vec::new();
+        for name in Self::list_store_files(src) {
+            let content = storage
+                .read(&src.join(&name))
+                .map_err(|e| format!("read {}: {}", name, e))?;
+            let new_content = Self::rekey_content(&name, &content, old_key, new_key)
+                .map_err(|e| format!("{}: {}", name, e))?;
+            progress(&name);
+            rekeyed.push((name, new_content));
+        }
+
+        storage
+            .create_dir_all(dest)
+            .map_err(|e| format!("create {}: {}", dest.display(), e))?;
+        let mut originals: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+        for (name, content) in &rekeyed {
+            let target = dest.join(name);
+            originals.push((target.clone(), storage.read(&target).ok()));
+            if let Err(e) = storage.write(&target, content) {
+                Self::restore_files(&originals);
+                return Err(format!("write {}: {}", name, e));
+            }
+        }
+        Ok(rekeyed.len())
+    }
+
+    /// Put back file contents captured before an in-place rewrite.
+    pub fn restore_files(originals: &[(PathBuf, Option<Vec<u8>>)]) {
+        let storage = Self::storage();
+        for (path, content) in originals {
+            let result = match content {
+                Some(c) => storage.write(path, c),
+                None => storage.remove(path),
+            };
+            if let Err(e) = result {
+                error!(path = %path.display(), error = %e, "failed to restore file");
+            }
+        }
+    }
+
+    /// Snapshot the current contents of every store file in `dir`.
+    pub fn capture_store_files(dir: &Path) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+        let storage = Self::storage();
+        Self::list_store_files(dir)
+            .into_iter()
+            .map(|name| {
+                let path = dir.join(name);
+                let content = storage.read(&path).ok();
+                (path, content)
+            })
+            .collect()
+    }
+
+    /// Overwrite a file with random bytes, sync it, then unlink it (see
+    /// `StorageBackend::shred`). A symlink is only unlinked: what it points
+    /// to may be outside the store.
+    pub fn shred_file(path: &Path) -> Result<(), String> {
+        Self::storage()
+            .shred(path)
+            .map_err(|e| format!("shred {}: {}", path.display(), e))
+    }
+
+    /// Shred every file below `dir` and remove the directory itself.
+    /// Symlinks are unlinked, never followed. Keeps going past individual
+    /// failures so as much as possible is destroyed; returns the number of
+    /// files shredded.
+    pub fn shred_directory(dir: &Path) -> Result<usize, String> {
+        let storage = Self::storage();
+        let mut shredded = 0;
+        let mut errors = Vec::new();
+        let files = storage
+            .list(dir)
+            .map_err(|e| format!("read {}: {}", dir.display(), e))?;
+        for name in files {
+            match Self::shred_file(&dir.join(name)) {
+                Ok(()) => shredded += 1,
+                Err(e) => errors.push(e),
+            }
+        }
+        for name in storage.list_dirs(dir).unwrap_or_default() {
+            match Self::shred_directory(&dir.join(name)) {
+                Ok(n) => shredded += n,
+                Err(e) => errors.push(e),
+            }
+        }
+        // whatever is left (symlinks and the like) is only unlinked
+        if let Err(e) = storage.remove_dir_all(dir) {
+            errors.push(format!("remove {}: {}", dir.display(), e));
+        }
+        if errors.is_empty() {
+            Ok(shredded)
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Append a single line to a record file, returning its byte offset.
+    pub fn append_record(path: &Path, line: &str) -> Result<u64, String> {
+        let mut record = Vec::with_capacity(line.len() + 1);
+        record.extend_from_slice(line.as_bytes());
+        record.push(b'\n');
+        Self::storage()
+            .append(path, &record)
+            .map_err(|e| format!("append record: {}", e))
+    }
+
+    /// Read `len` bytes at `offset` from a record file.
+    pub fn read_record(path: &Path, offset: u64, len: u64) -> Result<String, String> {
+        let buf = Self::storage()
+            .read_at(path, offset, len)
+            .map_err(|e| format!("read record: {}", e))?;
+        String::from_utf8(buf).map_err(|e| format!("record is not UTF-8: {}", e))
     }
 }
@@ -0,0 +1,183 @@
+use crate::crypto::{AegCrypto, MasterKeyProvider};
+use crate::file_system::AegFileSystem;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use zeroize::Zeroizing;
+
+/// A stored key wrapped by a hardware token (config.aeg `hardware_key`).
+/// Only the challenge and the wrapped key are stored; the key that unwraps
+/// it is the token's response, which needs the token.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HardwareKeyConfig {
+    /// Token slot holding the challenge-response secret.
+    pub slot: u8,
+    /// Base64 challenge sent to the token.
+    pub challenge: String,
+    /// The stored key, sealed under the key derived from the response.
+    pub wrapped: String,
+}
+
+/// A device that answers a challenge with a keyed hash of it under a
+/// secret that never leaves the device, such as a YubiKey's HMAC-SHA1
+/// challenge-response slot. Set another one with `AegHardwareKey::set_token`.
+pub trait HardwareToken: Send + Sync {
+    fn respond(&self, slot: u8, challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>, String>;
+}
+
+/// A YubiKey (or compatible key) answering through `ykchalresp` from
+/// yubikey-personalization, so nothing links against its libraries. The
+/// slot must be programmed for HMAC-SHA1 challenge-response; one set to
+/// require a touch waits for it. Needs the `yubikey` feature; without it
+/// every call fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YubiKeyToken;
+
+impl HardwareToken for YubiKeyToken {
+    #[cfg(feature = "yubikey")]
+    fn respond(&self, slot: u8, challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let hex: String = challenge.iter().map(|b| format!("{:02x}", b)).collect();
+        let output = std::process::Command::new("ykchalresp")
+            .args([format!("-{}", slot), "-H".into(), "-x".into(), hex])
+            .output()
+            .map_err(|e| format!("run ykchalresp: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "ykchalresp: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let response = Zeroizing::new(output.stdout);
+        let hex = std::str::from_utf8(&response)
+            .map_err(|e| format!("ykchalresp answered with non-text: {}", e))?
+            .trim();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| "ykchalresp answered with non-hex output".to_string())
+            })
+            .collect::<Result<Vec<u8>, String>>()
+            .map(Zeroizing::new)
+    }
+
+    #[cfg(not(feature = "yubikey"))]
+    fn respond(&self, slot: u8, _challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        Err(format!(
+            "the store's key is wrapped by the hardware token in slot {}, but this build \
+             has no YubiKey support (enable the `yubikey` feature)",
+            slot
+        ))
+    }
+}
+
+static TOKEN: OnceLock<RwLock<Arc<dyn HardwareToken>>> = OnceLock::new();
+/// Unwrapped keys by wrapped key, so the token is asked once per process.
+static UNWRAPPED: Mutex<Option<HashMap<String, Zeroizing<String>>>> = Mutex::new(None);
+
+/// Stored keys wrapped by a hardware token (see `AegCore::set_key_backend`).
+/// The token answers a random challenge, kept in the config with the
+/// wrapped key; the answer is the key-encryption key. Without the token
+/// the store does not open, and unlike `AegHsm`, which mixes the token
+/// into the encryption key, the stored key itself is never on disk.
+pub struct AegHardwareKey;
+
+impl AegHardwareKey {
+    /// Use `token` for hardware-wrapped keys from now on. Applies to the
+    /// whole process.
+    pub fn set_token(token: Arc<dyn HardwareToken>) {
+        *Self::token_slot()
+            .write()
+            .expect("Failed to lock hardware token") = token;
+        Self::forget_keys();
+    }
+
+    pub fn token() -> Arc<dyn HardwareToken> {
+        Arc::clone(
+            &Self::token_slot()
+                .read()
+                .expect("Failed to lock hardware token"),
+        )
+    }
+
+    fn token_slot() -> &'static RwLock<Arc<dyn HardwareToken>> {
+        TOKEN.get_or_init(|| RwLock::new(Arc::new(YubiKeyToken)))
+    }
+
+    /// Forget every key unwrapped so far, so the next use needs the token
+    /// again.
+    pub fn forget_keys() {
+        *UNWRAPPED.lock().expect("Failed to lock unwrapped keys") = None;
+    }
+
+    /// Wrap `key` with the token in `slot`, under a fresh challenge, and
+    /// check that it unwraps again.
+    pub fn wrap(key: &str, slot: u8) -> Result<HardwareKeyConfig, String> {
+        let challenge = AegCrypto::generate_random_bytes();
+        let response = Self::token().respond(slot, &challenge)?;
+        let wrap_key = AegCrypto::derive_token_wrap_key(&response);
+        let config = HardwareKeyConfig {
+            slot,
+            challenge: AegCrypto::encode_base64(challenge),
+            wrapped: AegCrypto::encrypt_record(&wrap_key, key.as_bytes())?,
+        };
+        match Self::unwrap(&config) {
+            Ok(unwrapped) if unwrapped.as_str() == key => Ok(config),
+            Ok(_) => Err("the token answered the same challenge differently".into()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The stored key wrapped in `config`, asking the token for it.
+    pub fn unwrap(config: &HardwareKeyConfig) -> Result<Zeroizing<String>, String> {
+        let mut unwrapped = UNWRAPPED.lock().expect("Failed to lock unwrapped keys");
+        if let Some(key) = unwrapped.as_ref().and_then(|u| u.get(&config.wrapped)) {
+            return Ok(key.clone());
+        }
+        let challenge = general_purpose::STANDARD
+            .decode(config.challenge.trim())
+            .map_err(|e| format!("base64 decode token challenge: {}", e))?;
+        let response = Self::token().respond(config.slot, &challenge)?;
+        let wrap_key = AegCrypto::derive_token_wrap_key(&response);
+        let plain = AegCrypto::decrypt_record(&wrap_key, &config.wrapped).map_err(|_| {
+            format!(
+                "the token in slot {} does not unwrap the store's key (another token?)",
+                config.slot
+            )
+        })?;
+        let key = Zeroizing::new(
+            std::str::from_utf8(&plain)
+                .map_err(|e| format!("unwrapped key is not UTF-8: {}", e))?
+                .to_string(),
+        );
+        unwrapped
+            .get_or_insert_with(HashMap::new)
+            .insert(config.wrapped.clone(), key.clone());
+        Ok(key)
+    }
+}
+
+/// The stored key, wrapped by a hardware token.
+pub struct HardwareKeyProvider(pub HardwareKeyConfig);
+
+impl MasterKeyProvider for HardwareKeyProvider {
+    fn name(&self) -> &'static str {
+        "hardware token"
+    }
+
+    fn load(&self) -> Result<Zeroizing<String>, String> {
+        AegHardwareKey::unwrap(&self.0)
+    }
+
+    /// Wraps `key` under a new challenge and records it in the config.
+    fn store(&self, key: &str) -> Result<(), String> {
+        AegFileSystem::ensure_writable()?;
+        let wrapped = AegHardwareKey::wrap(key, self.0.slot)?;
+        let mut config = AegFileSystem::read_store_config();
+        config.hardware_key = Some(wrapped);
+        AegFileSystem::write_store_config(&config);
+        Ok(())
+    }
+}
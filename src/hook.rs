@@ -0,0 +1,183 @@
+use crate::core::AegCore;
+use crate::env::AegEnv;
+use crate::manifest::ProjectManifest;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Environment variable listing the names the hook exported, so they can be
+/// unset again when leaving the project.
+pub const HOOK_VARS_ENV: &str = "AEGISR_HOOK_VARS";
+/// Environment variable holding the manifest the current exports came from.
+pub const HOOK_MANIFEST_ENV: &str = "AEGISR_HOOK_MANIFEST";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            other => Err(format!(
+                "unsupported shell '{}' (expected bash, zsh or fish)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bash => write!(f, "bash"),
+            Self::Zsh => write!(f, "zsh"),
+            Self::Fish => write!(f, "fish"),
+        }
+    }
+}
+
+/// What the hook exported last time, as recorded in the shell's environment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookState {
+    pub manifest: Option<PathBuf>,
+    pub vars: Vec<String>,
+}
+
+impl HookState {
+    /// Read the state the hook left in this process's environment.
+    pub fn from_env() -> Self {
+        Self {
+            manifest: std::env::var_os(HOOK_MANIFEST_ENV)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            vars: std::env::var(HOOK_VARS_ENV)
+                .map(|v| {
+                    v.split(':')
+                        .filter(|n| !n.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Shell integration that exports a project's secrets on `cd` into a
+/// directory with a `.aegisr.toml`, and unsets them again on leaving.
+pub struct AegHook;
+
+impl AegHook {
+    /// The snippet to `eval` from the shell's rc file. It runs
+    /// `aegisr hook <shell> --emit` whenever the directory changes and
+    /// evaluates the statements that prints (see `emit`).
+    pub fn script(shell: Shell) -> String {
+        match shell {
+            Shell::Bash => r#"_aegisr_hook() {
+  local previous_exit_status=$?
+  eval "$(aegisr hook bash --emit)"
+  return $previous_exit_status
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";_aegisr_hook;"* ]]; then
+  PROMPT_COMMAND="_aegisr_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#
+            .to_string(),
+            Shell::Zsh => r#"_aegisr_hook() {
+  eval "$(aegisr hook zsh --emit)"
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd _aegisr_hook
+_aegisr_hook
+"#
+            .to_string(),
+            Shell::Fish => r#"function __aegisr_hook --on-variable PWD
+  aegisr hook fish --emit | source
+end
+__aegisr_hook
+"#
+            .to_string(),
+        }
+    }
+
+    /// Shell statements moving the environment from `previous` to the state
+    /// for `dir`: unset what the last project exported, then export the
+    /// variables of the manifest governing `dir` (if any). Emits nothing
+    /// while staying inside the same project.
+    pub fn emit(shell: Shell, dir: &Path, previous: &HookState) -> Result<String, String> {
+        let manifest = ProjectManifest::discover(dir).transpose()?;
+        let manifest_path = manifest.as_ref().map(|m| m.path.clone());
+        if manifest_path == previous.manifest {
+            return Ok(String::new());
+        }
+
+        let vars: Vec<(String, String)> = match &manifest {
+            Some(_) => {
+                AegCore::enter_project(dir)?;
+                AegCore::env_vars()
+                    .into_iter()
+                    .filter(|(name, _)| AegEnv::is_valid_name(name))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut out = String::new();
+        // names come back from the shell environment; never emit one unchecked
+        for name in previous.vars.iter().filter(|n| AegEnv::is_valid_name(n)) {
+            if !vars.iter().any(|(n, _)| n == name) {
+                out.push_str(&Self::unset(shell, name));
+            }
+        }
+        for (name, value) in &vars {
+            out.push_str(&Self::export(shell, name, value));
+        }
+        match manifest_path {
+            Some(path) => {
+                let names: Vec<&str> = vars.iter().map(|(n, _)| n.as_str()).collect();
+                out.push_str(&Self::export(shell, HOOK_VARS_ENV, &names.join(":")));
+                out.push_str(&Self::export(
+                    shell,
+                    HOOK_MANIFEST_ENV,
+                    &path.to_string_lossy(),
+                ));
+            }
+            None => {
+                out.push_str(&Self::unset(shell, HOOK_VARS_ENV));
+                out.push_str(&Self::unset(shell, HOOK_MANIFEST_ENV));
+            }
+        }
+        Ok(out)
+    }
+
+    fn export(shell: Shell, name: &str, value: &str) -> String {
+        match shell {
+            Shell::Bash | Shell::Zsh => format!("export {}={};\n", name, Self::posix_quote(value)),
+            Shell::Fish => format!("set -gx {} {};\n", name, Self::fish_quote(value)),
+        }
+    }
+
+    fn unset(shell: Shell, name: &str) -> String {
+        match shell {
+            Shell::Bash | Shell::Zsh => format!("unset {};\n", name),
+            Shell::Fish => format!("set -e {};\n", name),
+        }
+    }
+
+    pub(crate) fn posix_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+
+    fn fish_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+    }
+}
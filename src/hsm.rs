@@ -0,0 +1,80 @@
+use crate::crypto::AegCrypto;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use zeroize::Zeroizing;
+
+/// Where the token holding a store's key lives (config.aeg `hsm`). The PIN
+/// is never stored: it is read from `AEGISR_HSM_PIN` when the token is used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HsmConfig {
+    /// Path of the vendor's PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`.
+    pub module: String,
+    /// Slot to use; `None` picks the first slot with a token present.
+    #[serde(default)]
+    pub slot: Option<u64>,
+    /// Label of the HMAC-capable secret key on the token.
+    pub key_label: String,
+}
+
+static BOUND_KEYS: OnceLock<Mutex<HashMap<String, Zeroizing<String>>>> = OnceLock::new();
+
+/// Keys kept in an HSM or smartcard over PKCS#11 (the `pkcs11` feature).
+/// The stored authorization key is HMACed by a secret key that never
+/// leaves the token, and the result is mixed into the encryption key, so
+/// the store files cannot be opened without the token. Builds without the
+/// feature refuse such stores with an explanation rather than a wrong key.
+pub struct AegHsm;
+
+impl AegHsm {
+    /// The key the store is encrypted with when bound to the token in
+    /// `config`. The token is asked once per process and stored key.
+    pub fn bind_key(config: &HsmConfig, stored_key: &str) -> Result<Zeroizing<String>, String> {
+        let cache_id = blake3::hash(
+            format!(
+                "{}\0{:?}\0{}\0{}",
+                config.module, config.slot, config.key_label, stored_key
+            )
+            .as_bytes(),
+        )
+        .to_hex()
+        .to_string();
+        let cache = BOUND_KEYS.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Some(key) = cache
+            .lock()
+            .expect("Failed to lock HSM keys")
+            .get(&cache_id)
+        {
+            return Ok(key.clone());
+        }
+        let mac = Self::token_hmac(config, stored_key.trim().as_bytes())?;
+        let key = AegCrypto::bind_key_to_hsm(stored_key, &mac)?;
+        cache
+            .lock()
+            .expect("Failed to lock HSM keys")
+            .insert(cache_id, key.clone());
+        Ok(key)
+    }
+
+    /// Forget every key derived from a token, so the next use asks it again.
+    pub fn forget_keys() {
+        if let Some(cache) = BOUND_KEYS.get() {
+            cache.lock().expect("Failed to lock HSM keys").clear();
+        }
+    }
+
+    #[cfg(feature = "pkcs11")]
+    fn token_hmac(config: &HsmConfig, message: &[u8]) -> Result<Vec<u8>, String> {
+        let pin = std::env::var(crate::constant::HSM_PIN_ENV).ok();
+        crate::pkcs11::hmac_sha256(config, pin.as_deref(), message)
+    }
+
+    #[cfg(not(feature = "pkcs11"))]
+    fn token_hmac(config: &HsmConfig, _message: &[u8]) -> Result<Vec<u8>, String> {
+        Err(format!(
+            "the store's key is kept on a PKCS#11 token ({}), but this build has no \
+             PKCS#11 support (enable the `pkcs11` feature)",
+            config.module
+        ))
+    }
+}
@@ -0,0 +1,308 @@
+use crate::constant::STORE_MANIFEST;
+use crate::crypto::AegCrypto;
+use crate::file_system::AegFileSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+static MISMATCHES: Mutex<Vec<ManifestMismatch>> = Mutex::new(Vec::new());
+
+/// The manifest file: the blake3 hash of every store file as last written,
+/// and a MAC over the list under a key derived from the store key.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct StoreManifest {
+    files: BTreeMap<String, String>,
+    #[serde(default)]
+    mac: String,
+}
+
+impl StoreManifest {
+    fn mac_for(&self, master_key: &str) -> Result<blake3::Hash, String> {
+        let key = AegCrypto::derive_manifest_key(master_key)?;
+        let body = serde_json::to_vec(&self.files).map_err(|e| format!("serialize: {}", e))?;
+        Ok(blake3::keyed_hash(&key, &body))
+    }
+
+    fn sign(&mut self, master_key: &str) -> Result<(), String> {
+        self.mac = self.mac_for(master_key)?.to_hex().to_string();
+        Ok(())
+    }
+
+    /// Whether the MAC matches; `blake3::Hash` compares in constant time.
+    fn signed_by(&self, master_key: &str) -> bool {
+        match (blake3::Hash::from_hex(&self.mac), self.mac_for(master_key)) {
+            (Ok(stored), Ok(expected)) => stored == expected,
+            _ => false,
+        }
+    }
+}
+
+/// How a store file disagrees with the manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestProblem {
+    /// Its contents are not the ones last written (edited, or swapped with
+    /// another or an older file).
+    Modified,
+    /// Listed, but gone.
+    Missing,
+    /// Present, but never written by the store.
+    Unlisted,
+    /// The manifest itself does not carry a valid MAC for the store key.
+    BadSignature,
+}
+
+impl fmt::Display for ManifestProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Modified => "changed since the store last wrote it",
+            Self::Missing => "listed in the manifest but missing",
+            Self::Unlisted => "not listed in the manifest",
+            Self::BadSignature => "signature does not match the store key",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestMismatch {
+    pub file: String,
+    pub problem: ManifestProblem,
+}
+
+impl fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.problem)
+    }
+}
+
+/// Outcome of checking every store file against the manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ManifestReport {
+    /// Whether the store has a manifest at all.
+    pub present: bool,
+    /// Whether its MAC matches the store key; the file list is not trusted
+    /// otherwise.
+    pub signed: bool,
+    /// Files listed in the manifest.
+    pub files: usize,
+    pub mismatches: Vec<ManifestMismatch>,
+}
+
+impl ManifestReport {
+    pub fn passed(&self) -> bool {
+        self.present && self.signed && self.mismatches.is_empty()
+    }
+
+    /// One line per mismatch plus a totals line.
+    pub fn summary(&self) -> String {
+        if !self.present {
+            return "✗ The store has no manifest".to_string();
+        }
+        if !self.signed {
+            return format!("✗ {}: {}", STORE_MANIFEST, ManifestProblem::BadSignature);
+        }
+        let mut out = String::new();
+        for m in &self.mismatches {
+            out.push_str(&format!("✗ {}\n", m));
+        }
+        match self.mismatches.len() {
+            0 => out.push_str(&format!("✓ Manifest matches all {} files", self.files)),
+            n => out.push_str(&format!(
+                "{} files in the manifest, {} mismatched",
+                self.files, n
+            )),
+        }
+        out
+    }
+}
+
+/// The store manifest, `store.manifest`: every store file (see
+/// `AegFileSystem::list_store_files`) with its blake3 hash, signed with a
+/// key derived from the store key. It is updated whenever the store writes
+/// one of those files, and checked when collection files and
+/// collection.lock are loaded, so a file edited, swapped for another or
+/// rolled back to an older copy while the store was not looking is
+/// reported instead of being decrypted as if nothing happened. Load-time
+/// mismatches are printed and kept until `take_mismatches`; `verify` checks
+/// the whole store.
+///
+/// Stores written before the manifest existed get one on their next save.
+pub struct AegIntegrity;
+
+impl AegIntegrity {
+    pub fn manifest_path(dir: &Path) -> PathBuf {
+        dir.join(STORE_MANIFEST)
+    }
+
+    fn hash(bytes: &[u8]) -> String {
+        blake3::hash(bytes).to_hex().to_string()
+    }
+
+    fn load(dir: &Path) -> Result<Option<StoreManifest>, String> {
+        let path = Self::manifest_path(dir);
+        let storage = AegFileSystem::storage();
+        if !storage.exists(&path) {
+            return Ok(None);
+        }
+        let bytes = storage
+            .read(&path)
+            .map_err(|e| format!("read {}: {}", path.display(), e))?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("corrupt {}: {}", STORE_MANIFEST, e))
+    }
+
+    fn write(dir: &Path, manifest: &StoreManifest) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(manifest).map_err(|e| format!("serialize: {}", e))?;
+        let path = Self::manifest_path(dir);
+        AegFileSystem::storage()
+            .write(&path, &json)
+            .map_err(|e| format!("write {}: {}", path.display(), e))
+    }
+
+    /// Rebuild the manifest of `dir` from the files as they are now, signed
+    /// with `master_key`. For operations that rewrite the store wholesale
+    /// (re-keying, restores); anything changed on disk before is accepted.
+    pub fn refresh(dir: &Path, master_key: &str) -> Result<(), String> {
+        AegFileSystem::ensure_writable()?;
+        let _lock = AegFileSystem::lock_store(dir)?;
+        let storage = AegFileSystem::storage();
+        let mut manifest = StoreManifest::default();
+        for name in AegFileSystem::list_store_files(dir) {
+            let bytes = storage
+                .read(&dir.join(&name))
+                .map_err(|e| format!("read {}: {}", name, e))?;
+            manifest.files.insert(name, Self::hash(&bytes));
+        }
+        manifest.sign(master_key)?;
+        Self::write(dir, &manifest)
+    }
+
+    /// Record the current contents of the store files `names` in `dir`,
+    /// dropping the ones that no longer exist. Without a manifest one is
+    /// built from every file; a manifest whose signature does not match is
+    /// left alone, so it keeps pointing at whatever happened to it.
+    pub(crate) fn record(dir: &Path, names: &[String]) -> Result<(), String> {
+        AegFileSystem::ensure_writable()?;
+        let master_key = AegFileSystem::try_read_authorization_key()?;
+        let _lock = AegFileSystem::lock_store(dir)?;
+        let Some(mut manifest) = Self::load(dir)? else {
+            return Self::refresh(dir, &master_key);
+        };
+        if !manifest.signed_by(&master_key) {
+            return Err(format!(
+                "{}: {}; not updating it",
+                STORE_MANIFEST,
+                ManifestProblem::BadSignature
+            ));
+        }
+        let storage = AegFileSystem::storage();
+        for name in names {
+            match storage.read(&dir.join(name)) {
+                Ok(bytes) => {
+                    manifest.files.insert(name.clone(), Self::hash(&bytes));
+                }
+                Err(_) => {
+                    manifest.files.remove(name);
+                }
+            }
+        }
+        manifest.sign(&master_key)?;
+        Self::write(dir, &manifest)
+    }
+
+    /// Check every store file in `dir` against its manifest. Nothing is
+    /// modified.
+    pub fn verify(dir: &Path, master_key: &str) -> ManifestReport {
+        let manifest = match Self::load(dir) {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) => return ManifestReport::default(),
+            Err(_) => {
+                return ManifestReport {
+                    present: true,
+                    ..ManifestReport::default()
+                };
+            }
+        };
+        let mut report = ManifestReport {
+            present: true,
+            signed: manifest.signed_by(master_key),
+            files: manifest.files.len(),
+            mismatches: Vec::new(),
+        };
+        if !report.signed {
+            return report;
+        }
+        let storage = AegFileSystem::storage();
+        let on_disk = AegFileSystem::list_store_files(dir);
+        for (name, hash) in &manifest.files {
+            let problem = match storage.read(&dir.join(name)) {
+                Ok(bytes) if Self::hash(&bytes) == *hash => continue,
+                Ok(_) => ManifestProblem::Modified,
+                Err(_) => ManifestProblem::Missing,
+            };
+            report.mismatches.push(ManifestMismatch {
+                file: name.clone(),
+                problem,
+            });
+        }
+        for name in on_disk {
+            if !manifest.files.contains_key(&name) {
+                report.mismatches.push(ManifestMismatch {
+                    file: name,
+                    problem: ManifestProblem::Unlisted,
+                });
+            }
+        }
+        report
+    }
+
+    /// Check `bytes`, just read from the store file `name` in `dir`,
+    /// against the manifest, and report a mismatch. Stores without a
+    /// manifest are not checked.
+    pub(crate) fn check(dir: &Path, name: &str, bytes: &[u8], master_key: &str) {
+        let Ok(Some(manifest)) = Self::load(dir) else {
+            return;
+        };
+        let problem = if !manifest.signed_by(master_key) {
+            ManifestProblem::BadSignature
+        } else {
+            match manifest.files.get(name) {
+                Some(hash) if Self::hash(bytes) == *hash => return,
+                Some(_) => ManifestProblem::Modified,
+                None => ManifestProblem::Unlisted,
+            }
+        };
+        let file = match problem {
+            ManifestProblem::BadSignature => STORE_MANIFEST,
+            _ => name,
+        };
+        Self::publish(ManifestMismatch {
+            file: file.to_string(),
+            problem,
+        });
+    }
+
+    /// Print a mismatch and keep it for `take_mismatches`, once until taken.
+    fn publish(mismatch: ManifestMismatch) {
+        let mut found = MISMATCHES
+            .lock()
+            .expect("Failed to lock manifest mismatches");
+        if !found.contains(&mismatch) {
+            warn!(file = %mismatch.file, problem = %mismatch.problem, "store file does not match the manifest");
+            found.push(mismatch);
+        }
+    }
+
+    /// Mismatches found while loading since the last call, oldest first.
+    pub fn take_mismatches() -> Vec<ManifestMismatch> {
+        std::mem::take(
+            &mut *MISMATCHES
+                .lock()
+                .expect("Failed to lock manifest mismatches"),
+        )
+    }
+}
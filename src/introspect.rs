@@ -0,0 +1,215 @@
+use crate::constant::STORE_COLLECTION;
+use crate::core::AegCore;
+use crate::crypto::Cipher;
+use crate::file_format::{AEKV_FORMAT_VERSION, AekvHeader, Codec};
+use crate::file_system::{AegFileSystem, ProfileManager};
+use crate::memory_engine::{AegMemoryEngine, PendingChanges, PersistencePolicy};
+use crate::naming::KeyConvention;
+use crate::snapshot::SnapshotManager;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What a store file holds, from its name.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreFileKind {
+    /// collection.lock: the collection list and the key check.
+    Lock,
+    /// `collection_<name>.aekv`: a collection's entries.
+    Data,
+    /// `collection_<name>.idx`: the key -> record index.
+    Index,
+    /// `collection_<name>.cold`: records paged out of memory.
+    Cold,
+}
+
+/// One encrypted file of the store. Format fields come from the file's
+/// header and are `None` for files without one (cold record files and
+/// files written before the header existed).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoreFile {
+    pub name: String,
+    pub kind: StoreFileKind,
+    pub collection: Option<String>,
+    pub bytes: u64,
+    pub format_version: Option<u8>,
+    pub cipher: Option<Cipher>,
+    pub codec: Option<Codec>,
+    pub compressed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionInfo {
+    pub name: String,
+    pub active: bool,
+    pub autosave: bool,
+    pub warm_capacity: Option<usize>,
+    pub indexed: bool,
+    pub key_convention: Option<KeyConvention>,
+    /// Values are kept encrypted in memory.
+    pub sealed_values: bool,
+    /// Loaded into this process's memory.
+    pub loaded: bool,
+    /// Loaded with changes not yet saved.
+    pub unsaved: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaverState {
+    pub running: bool,
+    pub interval_seconds: Option<u64>,
+    pub policy: PersistencePolicy,
+    /// Unix seconds of the most recent save by this process.
+    pub last_flush: Option<u64>,
+}
+
+/// Everything `AegIntrospect::describe` reports about a store.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoreDescription {
+    pub store_dir: PathBuf,
+    pub profile: Option<String>,
+    /// `.aekv` format version this build writes.
+    pub format_version: u8,
+    /// Cipher for newly written files.
+    pub cipher: Cipher,
+    pub machine_binding: bool,
+    pub hsm: bool,
+    pub audit: bool,
+    pub compress_min_bytes: Option<usize>,
+    pub active_collection: String,
+    pub collections: Vec<CollectionInfo>,
+    pub files: Vec<StoreFile>,
+    pub snapshots: usize,
+    pub saver: SaverState,
+    pub loaded_collections: Vec<String>,
+}
+
+/// What `AegCore::status` reports: where the store is, what is active,
+/// how it is being saved, and what a crash right now would lose.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoreStatus {
+    pub store_dir: PathBuf,
+    pub profile: Option<String>,
+    pub active_collection: String,
+    pub collections: Vec<String>,
+    pub saver: SaverState,
+    /// `.aekv` format version this build writes.
+    pub format_version: u8,
+    /// Format versions found in the headers of the store's files, oldest
+    /// first.
+    pub file_format_versions: Vec<u8>,
+    /// Loaded collections whose unsaved changes differ from disk.
+    pub pending: Vec<PendingChanges>,
+    pub clock_skew_secs: Option<i64>,
+}
+
+/// A read-only description of the store for GUIs and monitoring agents,
+/// so they never parse internal files themselves. Nothing is decrypted,
+/// loaded or written: file details come from headers and collection state
+/// from what this process already holds.
+pub struct AegIntrospect;
+
+impl AegIntrospect {
+    pub fn describe() -> StoreDescription {
+        let dir = AegFileSystem::get_config_path();
+        let config = AegFileSystem::read_store_config();
+        let core = AegCore::load();
+        let active = core.get_active_collection().to_string();
+        let collections = core
+            .collections
+            .iter()
+            .map(|name| {
+                let meta = core.collection_meta.get(name).cloned().unwrap_or_default();
+                let unsaved = AegMemoryEngine::cached_dirty(name);
+                CollectionInfo {
+                    name: name.clone(),
+                    active: *name == active,
+                    autosave: !meta.skip_autosave,
+                    warm_capacity: meta.warm_capacity,
+                    indexed: meta.indexed,
+                    key_convention: meta.key_convention,
+                    sealed_values: meta.sealed_values,
+                    loaded: unsaved.is_some(),
+                    unsaved: unsaved.unwrap_or(false),
+                }
+            })
+            .collect();
+        StoreDescription {
+            store_dir: AegFileSystem::get_real_config_path(),
+            profile: ProfileManager::current(),
+            format_version: AEKV_FORMAT_VERSION,
+            cipher: config.cipher,
+            machine_binding: config.machine_binding,
+            hsm: config.hsm.is_some(),
+            audit: config.audit,
+            compress_min_bytes: config.compress_min_bytes,
+            active_collection: active,
+            collections,
+            files: Self::files(&dir),
+            snapshots: SnapshotManager::list().len(),
+            saver: Self::saver(),
+            loaded_collections: AegMemoryEngine::cached_collections(),
+        }
+    }
+
+    /// The background saver as set by the persistence policy.
+    pub fn saver() -> SaverState {
+        let policy = AegMemoryEngine::persistence_policy();
+        SaverState {
+            running: matches!(
+                policy,
+                PersistencePolicy::Interval(_) | PersistencePolicy::Debounced(_)
+            ),
+            interval_seconds: match policy {
+                PersistencePolicy::Interval(secs) => Some(secs),
+                _ => None,
+            },
+            policy,
+            last_flush: AegMemoryEngine::last_flush(),
+        }
+    }
+
+    /// The store files in `dir`, in `AegFileSystem::list_store_files` order.
+    pub fn files(dir: &Path) -> Vec<StoreFile> {
+        AegFileSystem::list_store_files(dir)
+            .into_iter()
+            .map(|name| Self::file(&dir.join(&name), name))
+            .collect()
+    }
+
+    fn file(path: &Path, name: String) -> StoreFile {
+        let collection = AegFileSystem::collection_of_file(&name).map(str::to_string);
+        let kind = if name == STORE_COLLECTION {
+            StoreFileKind::Lock
+        } else if name.ends_with(".idx") {
+            StoreFileKind::Index
+        } else if name.ends_with(".cold") {
+            StoreFileKind::Cold
+        } else {
+            StoreFileKind::Data
+        };
+        let storage = AegFileSystem::storage();
+        let bytes = storage.size(path).unwrap_or(0);
+        let header = match kind {
+            StoreFileKind::Cold => None,
+            _ => Self::read_header(path, bytes),
+        };
+        StoreFile {
+            bytes,
+            format_version: header.map(|h| h.version),
+            cipher: header.map(|h| h.cipher),
+            codec: header.map(|h| h.codec),
+            compressed: header.is_some_and(|h| h.compressed),
+            name,
+            kind,
+            collection,
+        }
+    }
+
+    /// Only the first bytes are read, however large the file.
+    fn read_header(path: &Path, size: u64) -> Option<AekvHeader> {
+        let len = size.min(AekvHeader::MAX_LEN as u64);
+        let bytes = AegFileSystem::storage().read_at(path, 0, len).ok()?;
+        AekvHeader::parse(&bytes).ok().flatten()
+    }
+}
@@ -0,0 +1,245 @@
+use crate::constant::KEYRING_SERVICE;
+use crate::crypto::MasterKeyProvider;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use zeroize::Zeroizing;
+
+/// Where a store keeps its stored authorization key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyBackend {
+    /// The AUTHORIZATION_KEY file in the store directory.
+    #[default]
+    File,
+    /// The operating system's secret store (see `AegKeyring`).
+    Keyring,
+    /// Wrapped by a hardware token (see `AegHardwareKey`).
+    Hardware,
+}
+
+impl FromStr for KeyBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "file" => Ok(Self::File),
+            "keyring" | "os" => Ok(Self::Keyring),
+            "hardware" | "token" | "yubikey" => Ok(Self::Hardware),
+            other => Err(format!(
+                "unsupported key backend '{}' (expected file, keyring or hardware)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeyBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Keyring => "keyring",
+            Self::Hardware => "hardware",
+        })
+    }
+}
+
+/// The keyring entry holding a store's key (config.aeg `keyring`). The
+/// account is random, so the entry follows the store when it is moved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyringConfig {
+    pub account: String,
+}
+
+/// A secret store of the operating system, addressed by service and
+/// account. Set another one with `AegKeyring::set_backend`, e.g.
+/// `MemoryKeyring` in tests.
+pub trait KeyringBackend: Send + Sync {
+    /// The secret, or `None` when there is no such entry.
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String>;
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String>;
+    /// Remove the entry; succeeds when there is none.
+    fn delete(&self, service: &str, account: &str) -> Result<(), String>;
+}
+
+/// The platform keyring: the macOS Keychain, the Secret Service on Linux
+/// and BSD, the Windows Credential Manager. Needs the `keyring` feature;
+/// without it every call fails, so stores fall back to the key file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsKeyring;
+
+#[cfg(feature = "keyring")]
+impl KeyringBackend for OsKeyring {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        crate::os_keyring::get(service, account)
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        crate::os_keyring::set(service, account, secret)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), String> {
+        crate::os_keyring::delete(service, account)
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+impl KeyringBackend for OsKeyring {
+    fn get(&self, _service: &str, _account: &str) -> Result<Option<String>, String> {
+        Err(Self::UNSUPPORTED.into())
+    }
+
+    fn set(&self, _service: &str, _account: &str, _secret: &str) -> Result<(), String> {
+        Err(Self::UNSUPPORTED.into())
+    }
+
+    fn delete(&self, _service: &str, _account: &str) -> Result<(), String> {
+        Err(Self::UNSUPPORTED.into())
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+impl OsKeyring {
+    const UNSUPPORTED: &str = "this build has no OS keyring support (enable the `keyring` feature)";
+}
+
+/// Entries kept in the process, for tests and for embedders with a
+/// keyring of their own to bridge.
+#[derive(Debug, Default)]
+pub struct MemoryKeyring {
+    entries: Mutex<HashMap<(String, String), Zeroizing<String>>>,
+}
+
+impl MemoryKeyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KeyringBackend for MemoryKeyring {
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        let entries = self.entries.lock().expect("Failed to lock keyring");
+        Ok(entries
+            .get(&(service.to_string(), account.to_string()))
+            .map(|secret| secret.to_string()))
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        self.entries.lock().expect("Failed to lock keyring").insert(
+            (service.to_string(), account.to_string()),
+            Zeroizing::new(secret.to_string()),
+        );
+        Ok(())
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), String> {
+        self.entries
+            .lock()
+            .expect("Failed to lock keyring")
+            .remove(&(service.to_string(), account.to_string()));
+        Ok(())
+    }
+}
+
+static BACKEND: OnceLock<RwLock<Arc<dyn KeyringBackend>>> = OnceLock::new();
+/// Keys read from the keyring by account, so it is asked once per process.
+static KEYS: Mutex<Option<HashMap<String, Zeroizing<String>>>> = Mutex::new(None);
+
+/// Stored authorization keys kept in the OS keyring instead of the
+/// AUTHORIZATION_KEY file (see `AegCore::set_key_backend`), under the
+/// service `aegisr` and the account in the store's config. Whatever binds
+/// the key (machine, passphrase, HSM) still applies on top.
+pub struct AegKeyring;
+
+impl AegKeyring {
+    /// Use `backend` for keyring entries from now on. Applies to the whole
+    /// process.
+    pub fn set_backend(backend: Arc<dyn KeyringBackend>) {
+        *Self::backend_slot()
+            .write()
+            .expect("Failed to lock keyring backend") = backend;
+        Self::forget_keys();
+    }
+
+    pub fn backend() -> Arc<dyn KeyringBackend> {
+        Arc::clone(
+            &Self::backend_slot()
+                .read()
+                .expect("Failed to lock keyring backend"),
+        )
+    }
+
+    fn backend_slot() -> &'static RwLock<Arc<dyn KeyringBackend>> {
+        BACKEND.get_or_init(|| RwLock::new(Arc::new(OsKeyring)))
+    }
+
+    /// Forget the keys read so far, so the next use asks the keyring again.
+    pub fn forget_keys() {
+        *KEYS.lock().expect("Failed to lock keyring keys") = None;
+    }
+
+    /// The key stored for `config`.
+    pub fn read_key(config: &KeyringConfig) -> Result<Zeroizing<String>, String> {
+        let mut keys = KEYS.lock().expect("Failed to lock keyring keys");
+        if let Some(key) = keys.as_ref().and_then(|k| k.get(&config.account)) {
+            return Ok(key.clone());
+        }
+        let key = Self::backend()
+            .get(KEYRING_SERVICE, &config.account)?
+            .map(Zeroizing::new)
+            .ok_or_else(|| format!("no keyring entry '{}'", config.account))?;
+        keys.get_or_insert_with(HashMap::new)
+            .insert(config.account.clone(), key.clone());
+        Ok(key)
+    }
+
+    /// Store `key` under a new account and read it back, so a keyring that
+    /// accepts but does not keep entries is caught before anything relies
+    /// on it.
+    pub fn store_key(key: &str) -> Result<KeyringConfig, String> {
+        let config = KeyringConfig {
+            account: format!("store-{}", uuid::Uuid::new_v4()),
+        };
+        let backend = Self::backend();
+        backend.set(KEYRING_SERVICE, &config.account, key)?;
+        match backend.get(KEYRING_SERVICE, &config.account) {
+            Ok(Some(stored)) if stored.trim() == key.trim() => Ok(config),
+            Ok(_) => {
+                let _ = backend.delete(KEYRING_SERVICE, &config.account);
+                Err("the keyring did not keep the key".into())
+            }
+            Err(e) => {
+                let _ = backend.delete(KEYRING_SERVICE, &config.account);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn delete_key(config: &KeyringConfig) -> Result<(), String> {
+        if let Some(keys) = KEYS.lock().expect("Failed to lock keyring keys").as_mut() {
+            keys.remove(&config.account);
+        }
+        Self::backend().delete(KEYRING_SERVICE, &config.account)
+    }
+}
+
+/// The stored key, in the OS keyring.
+pub struct KeyringKeyProvider(pub KeyringConfig);
+
+impl MasterKeyProvider for KeyringKeyProvider {
+    fn name(&self) -> &'static str {
+        "OS keyring"
+    }
+
+    fn load(&self) -> Result<Zeroizing<String>, String> {
+        AegKeyring::read_key(&self.0)
+    }
+
+    fn store(&self, key: &str) -> Result<(), String> {
+        AegKeyring::backend().set(KEYRING_SERVICE, &self.0.account, key)?;
+        AegKeyring::forget_keys();
+        Ok(())
+    }
+}
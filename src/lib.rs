@@ -1,13 +1,138 @@
 pub mod constant;
+pub mod verbosity;
+#[cfg(feature = "cli")]
 pub mod commands;
+#[cfg(feature = "cli")]
+pub mod wire;
+#[cfg(feature = "cli")]
+pub mod terminal_log;
 pub mod memory_engine;
 pub mod file_system;
+pub mod storage;
+pub mod store;
+#[cfg(feature = "s3")]
+pub mod s3;
 pub mod crypto;
+pub mod secret;
+pub mod compress;
+pub mod file_format;
+pub mod hsm;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+pub mod keyring;
+#[cfg(feature = "keyring")]
+mod os_keyring;
+pub mod hardware_key;
+pub mod clipboard;
+pub mod metrics;
 pub mod core;
+pub mod builder;
+pub mod transaction;
+pub mod verify;
+pub mod integrity;
+pub mod bundle;
+pub mod viewer;
+pub mod age;
+pub mod share;
+pub mod totp;
+pub mod plain;
+pub mod lint;
+pub mod naming;
+pub mod snapshot;
+pub mod backups;
+pub mod recovery;
+pub mod sync;
+pub mod watch;
+pub mod audit;
+pub mod clock;
+pub mod snippet;
+pub mod emergency;
+pub mod federation;
+pub mod merge;
+pub mod introspect;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod manifest;
+pub mod hook;
+pub mod env;
+pub mod loadtest;
+pub mod test_harness;
+#[cfg(feature = "tokio")]
+pub mod async_core;
+#[cfg(feature = "cli")]
+pub mod dispatch;
+#[cfg(feature = "cli")]
+pub mod repl;
+#[cfg(feature = "cli")]
+pub mod daemon;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod prelude;
 
 pub use constant::*;
+pub use verbosity::*;
+#[cfg(feature = "cli")]
 pub use commands::*;
+#[cfg(feature = "cli")]
+pub use wire::*;
+#[cfg(feature = "cli")]
+pub use terminal_log::*;
 pub use memory_engine::*;
 pub use file_system::*;
+pub use storage::*;
+pub use store::*;
+#[cfg(feature = "s3")]
+pub use s3::*;
 pub use crypto::*;
+pub use secret::*;
+pub use compress::*;
+pub use file_format::*;
+pub use hsm::*;
+pub use keyring::*;
+pub use hardware_key::*;
+pub use clipboard::*;
+pub use metrics::*;
 pub use core::*;
+pub use builder::*;
+pub use transaction::*;
+pub use verify::*;
+pub use integrity::*;
+pub use bundle::*;
+pub use viewer::*;
+pub use age::*;
+pub use share::*;
+pub use totp::*;
+pub use plain::*;
+pub use lint::*;
+pub use naming::*;
+pub use snapshot::*;
+pub use backups::*;
+pub use recovery::*;
+pub use sync::*;
+pub use watch::*;
+pub use audit::*;
+pub use clock::*;
+pub use snippet::*;
+pub use emergency::*;
+pub use federation::*;
+pub use merge::*;
+pub use introspect::*;
+#[cfg(feature = "server")]
+pub use server::*;
+#[cfg(feature = "client")]
+pub use client::*;
+pub use manifest::*;
+pub use hook::*;
+pub use env::*;
+pub use loadtest::*;
+pub use test_harness::*;
+#[cfg(feature = "tokio")]
+pub use async_core::*;
+#[cfg(feature = "cli")]
+pub use dispatch::*;
+#[cfg(feature = "cli")]
+pub use repl::*;
+#[cfg(feature = "cli")]
+pub use daemon::*;
\ No newline at end of file
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What `put` does with a value that trips a lint rule.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Do not check values.
+    #[default]
+    Off,
+    /// Store the value and report the findings.
+    Warn,
+    /// Refuse to store the value.
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            other => Err(format!(
+                "unknown lint level '{}' (expected off, warn or deny)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    Empty,
+    Placeholder,
+    /// Leading or trailing spaces, tabs or newlines.
+    SurroundingWhitespace,
+    /// A PEM private key whose line breaks were lost or mangled.
+    PrivateKeyLineEndings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub message: String,
+}
+
+const PLACEHOLDERS: &[&str] = &[
+    "changeme",
+    "change_me",
+    "change-me",
+    "replaceme",
+    "replace_me",
+    "replace-me",
+    "placeholder",
+    "todo",
+    "fixme",
+    "tbd",
+    "null",
+    "none",
+    "undefined",
+];
+
+/// Checks for values that are almost certainly not the secret the user
+/// meant to store.
+pub struct AegLint;
+
+impl AegLint {
+    pub fn check(value: &str) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            findings.push(LintFinding {
+                rule: LintRule::Empty,
+                message: "value is empty".into(),
+            });
+            return findings;
+        }
+        if Self::is_placeholder(trimmed) {
+            findings.push(LintFinding {
+                rule: LintRule::Placeholder,
+                message: format!("value '{}' looks like a placeholder", trimmed),
+            });
+        }
+        if trimmed.len() != value.len() {
+            findings.push(LintFinding {
+                rule: LintRule::SurroundingWhitespace,
+                message: "value has leading or trailing whitespace or newlines".into(),
+            });
+        }
+        if let Some(problem) = Self::private_key_problem(trimmed) {
+            findings.push(LintFinding {
+                rule: LintRule::PrivateKeyLineEndings,
+                message: format!("private key {}", problem),
+            });
+        }
+        findings
+    }
+
+    fn is_placeholder(value: &str) -> bool {
+        let lower = value.to_ascii_lowercase();
+        PLACEHOLDERS.contains(&lower.as_str())
+            || (lower.len() >= 3 && lower.chars().all(|c| c == 'x' || c == '*'))
+            || (value.starts_with('<') && value.ends_with('>'))
+            || (value.starts_with("${") && value.ends_with('}'))
+    }
+
+    fn private_key_problem(value: &str) -> Option<&'static str> {
+        if !(value.starts_with("-----BEGIN") && value.contains("PRIVATE KEY-----")) {
+            return None;
+        }
+        if value.contains('\r') {
+            Some("has Windows (CRLF) line endings")
+        } else if value.contains("\\n") {
+            Some("has escaped \\n sequences instead of line breaks")
+        } else if !value.contains('\n') {
+            Some("is on a single line; its line breaks were lost")
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::core::AegCore;
+use crate::memory_engine::AegMemoryEngine;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Parameters for `AegLoadtest::run`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoadtestConfig {
+    /// Collection to run against; created for the run and deleted after.
+    pub collection: String,
+    pub workers: usize,
+    pub ops_per_worker: usize,
+    /// Number of distinct keys the workers pick from.
+    pub key_space: usize,
+    pub value_size: usize,
+    /// Share of operations that are reads, puts and deletes, in percent.
+    /// Whatever `read_pct + put_pct` leaves is deletes.
+    pub read_pct: u8,
+    pub put_pct: u8,
+    /// Run a saver alongside the workers at this interval; `None` runs
+    /// without persistence.
+    pub saver_interval: Option<Duration>,
+}
+
+impl Default for LoadtestConfig {
+    fn default() -> Self {
+        Self {
+            collection: format!("loadtest_{}", std::process::id()),
+            workers: 4,
+            ops_per_worker: 10_000,
+            key_space: 1_000,
+            value_size: 64,
+            read_pct: 80,
+            put_pct: 15,
+            saver_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// Latency percentiles in microseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencySummary {
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let at = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Self {
+            count: samples.len(),
+            p50_us: at(0.50),
+            p99_us: at(0.99),
+            max_us: *samples.last().unwrap_or(&0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoadtestReport {
+    pub total_ops: usize,
+    pub elapsed: Duration,
+    pub ops_per_sec: f64,
+    pub reads: LatencySummary,
+    pub puts: LatencySummary,
+    pub deletes: LatencySummary,
+    /// Operations that started while no save was running.
+    pub idle: LatencySummary,
+    /// Operations that started while the saver was writing; compare with
+    /// `idle` to see how much the saver interval costs.
+    pub during_save: LatencySummary,
+    pub saves: LatencySummary,
+}
+
+impl LoadtestReport {
+    pub fn summary(&self) -> String {
+        let row = |name: &str, l: &LatencySummary| {
+            format!(
+                "{:<12} {:>9} {:>9} {:>9} {:>9}\n",
+                name, l.count, l.p50_us, l.p99_us, l.max_us
+            )
+        };
+        let mut out = format!(
+            "{} ops in {:.2?} ({:.0} ops/s)\n{:<12} {:>9} {:>9} {:>9} {:>9}\n",
+            self.total_ops,
+            self.elapsed,
+            self.ops_per_sec,
+            "",
+            "count",
+            "p50 µs",
+            "p99 µs",
+            "max µs"
+        );
+        out.push_str(&row("get", &self.reads));
+        out.push_str(&row("put", &self.puts));
+        out.push_str(&row("delete", &self.deletes));
+        out.push_str(&row("idle", &self.idle));
+        out.push_str(&row("during save", &self.during_save));
+        out.push_str(&row("save", &self.saves));
+        out
+    }
+}
+
+#[derive(Default)]
+struct WorkerSamples {
+    reads: Vec<u64>,
+    puts: Vec<u64>,
+    deletes: Vec<u64>,
+    idle: Vec<u64>,
+    during_save: Vec<u64>,
+}
+
+/// Multi-threaded put/get/delete workload against the embedded engine.
+pub struct AegLoadtest;
+
+impl AegLoadtest {
+    pub fn run(config: &LoadtestConfig) -> Result<LoadtestReport, String> {
+        if config.workers == 0 || config.key_space == 0 {
+            return Err("workers and key_space must be at least 1".into());
+        }
+        if config.read_pct as u16 + config.put_pct as u16 > 100 {
+            return Err("read_pct + put_pct must not exceed 100".into());
+        }
+        let msg = AegCore::create_collection(&config.collection);
+        if !msg.starts_with('✓') {
+            return Err(msg.trim_start_matches("✗ ").to_string());
+        }
+
+        let saving = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+        let saver = config.saver_interval.map(|interval| {
+            let (saving, done) = (Arc::clone(&saving), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut samples = Vec::new();
+                while !done.load(Ordering::SeqCst) {
+                    thread::sleep(interval);
+                    saving.store(true, Ordering::SeqCst);
+                    let started = Instant::now();
+                    AegMemoryEngine::save_all();
+                    samples.push(started.elapsed().as_micros() as u64);
+                    saving.store(false, Ordering::SeqCst);
+                }
+                samples
+            })
+        });
+
+        let started = Instant::now();
+        let workers: Vec<_> = (0..config.workers)
+            .map(|worker| {
+                let config = config.clone();
+                let saving = Arc::clone(&saving);
+                thread::spawn(move || Self::worker(worker, &config, &saving))
+            })
+            .collect();
+        let mut all = WorkerSamples::default();
+        for handle in workers {
+            let samples = handle.join().map_err(|_| "worker panicked".to_string())?;
+            all.reads.extend(samples.reads);
+            all.puts.extend(samples.puts);
+            all.deletes.extend(samples.deletes);
+            all.idle.extend(samples.idle);
+            all.during_save.extend(samples.during_save);
+        }
+        let elapsed = started.elapsed();
+
+        done.store(true, Ordering::SeqCst);
+        let saves = match saver {
+            Some(handle) => handle.join().map_err(|_| "saver panicked".to_string())?,
+            None => Vec::new(),
+        };
+
+        AegMemoryEngine::with_engine(&config.collection, |engine| engine.clear());
+        AegMemoryEngine::save_all();
+        AegCore::delete_collection(&config.collection);
+
+        let total_ops = config.workers * config.ops_per_worker;
+        Ok(LoadtestReport {
+            total_ops,
+            elapsed,
+            ops_per_sec: total_ops as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            reads: LatencySummary::from_samples(all.reads),
+            puts: LatencySummary::from_samples(all.puts),
+            deletes: LatencySummary::from_samples(all.deletes),
+            idle: LatencySummary::from_samples(all.idle),
+            during_save: LatencySummary::from_samples(all.during_save),
+            saves: LatencySummary::from_samples(saves),
+        })
+    }
+
+    fn worker(worker: usize, config: &LoadtestConfig, saving: &AtomicBool) -> WorkerSamples {
+        // xorshift: cheap, and deterministic per worker
+        let mut state = 0x9E37_79B9_7F4A_7C15u64 ^ (worker as u64 + 1);
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let value = "x".repeat(config.value_size);
+        let mut samples = WorkerSamples::default();
+
+        for _ in 0..config.ops_per_worker {
+            let key = format!("key_{}", next() % config.key_space as u64);
+            let roll = (next() % 100) as u8;
+            let during_save = saving.load(Ordering::SeqCst);
+            let started = Instant::now();
+            let bucket = if roll < config.read_pct {
+                let _ = AegMemoryEngine::fetch_shared(&config.collection, &key);
+                &mut samples.reads
+            } else if roll < config.read_pct + config.put_pct {
+                AegMemoryEngine::with_engine(&config.collection, |engine| {
+                    engine.insert(key, value.as_str())
+                });
+                &mut samples.puts
+            } else {
+                AegMemoryEngine::with_engine(&config.collection, |engine| engine.delete(&key));
+                &mut samples.deletes
+            };
+            let micros = started.elapsed().as_micros() as u64;
+            bucket.push(micros);
+            if during_save {
+                samples.during_save.push(micros);
+            } else {
+                samples.idle.push(micros);
+            }
+        }
+        samples
+    }
+}
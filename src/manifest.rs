@@ -0,0 +1,115 @@
+use crate::constant::PROJECT_MANIFEST_FILE;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A project-local `.aegisr.toml` pinning the profile and collection a
+/// project uses and the keys it cannot run without:
+///
+/// ```toml
+/// profile = "work"
+/// collection = "my-service"
+/// required = ["db/password", "api_token"]
+/// ```
+///
+/// Only top-level string and string-array values are understood.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectManifest {
+    /// File the manifest was read from.
+    pub path: PathBuf,
+    pub profile: Option<String>,
+    pub collection: Option<String>,
+    pub required: Vec<String>,
+}
+
+impl ProjectManifest {
+    /// Find the nearest manifest in `start` or any of its parents.
+    pub fn discover(start: &Path) -> Option<Result<Self, String>> {
+        let path = start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_MANIFEST_FILE))
+            .find(|p| p.is_file())?;
+        Some(Self::read(&path))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+        let mut manifest = Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        manifest.path = path.to_path_buf();
+        Ok(manifest)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut manifest = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, raw)) = line.split_once('=') else {
+                return Err(format!("line {}: expected key = value", i + 1));
+            };
+            let raw = raw.trim();
+            match name.trim() {
+                "profile" => manifest.profile = Some(parse_string(raw, i)?),
+                "collection" => manifest.collection = Some(parse_string(raw, i)?),
+                "required" => manifest.required = parse_string_array(raw, i)?,
+                other => return Err(format!("line {}: unknown setting '{}'", i + 1, other)),
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// A basic `"..."` string; the rest of the line may only be a comment.
+fn parse_string(raw: &str, line: usize) -> Result<String, String> {
+    let (value, rest) = split_string(raw, line)?;
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("line {}: unexpected '{}'", line + 1, rest));
+    }
+    Ok(value)
+}
+
+fn parse_string_array(raw: &str, line: usize) -> Result<Vec<String>, String> {
+    let mut rest = raw
+        .strip_prefix('[')
+        .ok_or_else(|| format!("line {}: expected an array", line + 1))?
+        .trim_start();
+    let mut values = Vec::new();
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            let after = after.trim();
+            if !after.is_empty() && !after.starts_with('#') {
+                return Err(format!("line {}: unexpected '{}'", line + 1, after));
+            }
+            return Ok(values);
+        }
+        let (value, after) = split_string(rest, line)?;
+        values.push(value);
+        let after = after.trim_start();
+        rest = after.strip_prefix(',').unwrap_or(after).trim_start();
+    }
+}
+
+/// Split a leading quoted string off `raw`, returning it and the remainder.
+fn split_string(raw: &str, line: usize) -> Result<(String, &str), String> {
+    let inner = raw
+        .strip_prefix('"')
+        .ok_or_else(|| format!("line {}: expected a quoted string", line + 1))?;
+    let mut value = String::new();
+    let mut chars = inner.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &inner[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, other)) => value.push(other),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+    Err(format!("line {}: unterminated string", line + 1))
+}
@@ -1,250 +1,2645 @@
+use crate::audit::AegAudit;
+use crate::backups::AegBackups;
+use crate::builder::StoreContext;
+use crate::constant::{DEBOUNCE_MAX_WINDOWS, KEY_HISTORY_DEPTH, KEY_PATH_SEPARATOR};
 use crate::core::AegCore;
-use crate::file_system::AegFileSystem;
-use aes_gcm::aead::Aead;
-use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
-use base64::{Engine as _, engine::general_purpose};
-use rand_core::TryRngCore;
+use crate::crypto::{AegCrypto, Cipher};
+use crate::env::AegEnv;
+use crate::file_format::{AegFileFormat, Codec};
+use crate::file_system::{AegFileSystem, CollectionMeta};
+use crate::integrity::AegIntegrity;
+use crate::metrics::AegMetrics;
+use crate::recovery::AegRecovery;
+use crate::watch::{AegWatch, ChangeKind};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Mutex, OnceLock};
-use std::thread;
-use std::thread::sleep;
-use std::time::Duration;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, debug_span, error, info, info_span, warn};
+use zeroize::{Zeroize, Zeroizing};
 
 /// IN-MEMORY KEY-VALUE STORE ENGINE
+///
+/// Methods mutate the engine in place. Engines in the global cache are
+/// reached through `with_engine`/`read_engine`; `load` returns a detached
+/// snapshot whose changes are not published.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AegMemoryEngine {
-    pub store: HashMap<String, String>,
+    /// Warm tier: entries currently decrypted in memory. In a collection
+    /// with sealed values the values here are ciphertext; read them through
+    /// `get`/`entry`/`entries`, which open them. Keys are interned: the
+    /// warm tier, the LRU order and the value index share one allocation
+    /// per key.
+    pub store: HashMap<Arc<str>, Entry>,
     pub collection_name: String,
+    /// Cold tier: key -> location of its encrypted record in the cold file.
+    /// A key may be both warm and cold when its value has not changed since
+    /// it was paged in; the warm copy always wins.
+    /// Persisted separately in `collection_<name>.idx`.
+    #[serde(default, skip_serializing)]
+    pub cold_index: HashMap<String, ColdLocation>,
+    /// Optional per-key settings, persisted with the collection.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub key_meta: HashMap<String, KeyMeta>,
+    /// The last `KEY_HISTORY_DEPTH` values of each key, oldest first,
+    /// including the current one. Kept when a key is deleted.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub history: HashMap<String, Vec<ValueVersion>>,
+    #[serde(skip)]
+    pub warm_capacity: Option<usize>,
+    /// When set, every write is also appended to the record file so any key
+    /// can be read from disk through the index alone.
+    #[serde(skip)]
+    pub indexed: bool,
+    #[serde(skip)]
+    pub tier_stats: TierStats,
+    /// Bumped on every mutation; compared against the last saved generation
+    /// to decide whether the collection needs writing.
+    #[serde(skip)]
+    pub generation: u64,
+    #[serde(skip)]
+    lru: LruTracker,
+    /// Built on first use by `keys_with_value`, then kept in step with
+    /// every change.
+    #[serde(skip)]
+    value_index: Option<ValueIndex>,
+    /// Every key in order, for prefix queries over `app/db/...` paths.
+    /// Built on first use by `keys_with_prefix`, then kept in step.
+    #[serde(skip)]
+    key_index: Option<BTreeSet<Arc<str>>>,
+    /// Set while the collection keeps its values sealed in memory (see
+    /// `CollectionMeta::sealed_values`); seals `store` and `history` values.
+    #[serde(skip)]
+    sealer: Option<ValueSealer>,
+    /// Stands in for a collection sealed with a passphrase that was not
+    /// given in this process: empty, never cached, and never saved.
+    #[serde(skip)]
+    locked: bool,
 }
 
-/// SAFE GLOBAL IN-MEMORY CACHE (OnceLock + Mutex)
-static MEMORY_CACHE: OnceLock<Mutex<HashMap<String, AegMemoryEngine>>> = OnceLock::new();
+/// A stored value and its metadata.
+///
+/// Collections written before metadata was kept store bare strings; those
+/// load as entries without timestamps or tags.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredEntry")]
+pub struct Entry {
+    /// Shared, so copying an entry (or a snapshot of the engine) does not
+    /// copy the value.
+    pub value: Arc<str>,
+    /// Unix seconds when the key was first stored.
+    pub created_at: Option<u64>,
+    /// Unix seconds when the value last changed (tag changes do not count).
+    pub updated_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Legacy(String),
+    Current {
+        value: String,
+        #[serde(default)]
+        created_at: Option<u64>,
+        #[serde(default)]
+        updated_at: Option<u64>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+impl From<StoredEntry> for Entry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Legacy(value) => Self {
+                value: value.into(),
+                created_at: None,
+                updated_at: None,
+                tags: Vec::new(),
+            },
+            StoredEntry::Current {
+                value,
+                created_at,
+                updated_at,
+                tags,
+            } => Self {
+                value: value.into(),
+                created_at,
+                updated_at,
+                tags,
+            },
+        }
+    }
+}
+
+impl Entry {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+fn unix_now() -> Option<u64> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .ok()
+}
+
+/// Per-key settings that are not part of the value itself.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct KeyMeta {
+    /// Variable name to use when the key is exported into an environment
+    /// (dotenv export, environment injection) instead of the derived one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_name: Option<String>,
+}
+
+/// One value a key has held.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValueVersion {
+    /// Increases by one with every new value of the key.
+    pub version: u64,
+    pub value: String,
+    /// Unix seconds when the value was written; `None` for a value stored
+    /// before history was kept.
+    pub set_at: Option<u64>,
+}
+
+/// Position of a single encrypted record inside `collection_<name>.cold`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ColdLocation {
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Warm/cold tier counters for a collection (in-memory only, reset on restart).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TierStats {
+    /// Reads served from the warm tier.
+    pub hits: u64,
+    /// Reads that had to page the value in from the cold file.
+    pub misses: u64,
+    /// Entries moved out of the warm tier.
+    pub evictions: u64,
+    pub warm_entries: usize,
+    pub cold_entries: usize,
+}
+
+/// Size and save state of one collection, from `AegCore::collection_stats`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CollectionStats {
+    pub name: String,
+    pub entries: usize,
+    /// UTF-8 bytes of every key and every (decrypted) value.
+    pub key_bytes: u64,
+    pub value_bytes: u64,
+    /// Encrypted size on disk: data, index and cold record files.
+    pub disk_bytes: u64,
+    /// Unix seconds of the last save, when known.
+    pub last_saved: Option<u64>,
+    /// Changed in memory since the last save.
+    pub dirty: bool,
+}
+
+impl CollectionStats {
+    /// One row per collection under a header, for `aegisr stats`.
+    pub fn table(stats: &[CollectionStats]) -> String {
+        let width = stats
+            .iter()
+            .map(|s| s.name.len())
+            .chain(["COLLECTION".len()])
+            .max()
+            .unwrap_or(0);
+        let mut out = format!(
+            "{:<width$}  {:>8}  {:>10}  {:>12}  {:>10}  {:<10}  {}",
+            "COLLECTION", "ENTRIES", "KEY BYTES", "VALUE BYTES", "ON DISK", "LAST SAVED", "STATE"
+        );
+        for s in stats {
+            out.push_str(&format!(
+                "\n{:<width$}  {:>8}  {:>10}  {:>12}  {:>10}  {:<10}  {}",
+                s.name,
+                s.entries,
+                s.key_bytes,
+                s.value_bytes,
+                s.disk_bytes,
+                s.last_saved
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "never".into()),
+                if s.dirty { "unsaved" } else { "saved" }
+            ));
+        }
+        out
+    }
+}
+
+/// Keys whose in-memory state differs from what is on disk, i.e. what the
+/// next save would write and what a crash right now would lose.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PendingChanges {
+    pub collection: String,
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl PendingChanges {
+    fn between(
+        collection: &str,
+        persisted: &HashMap<String, String>,
+        current: &HashMap<String, String>,
+    ) -> Self {
+        let mut changes = Self {
+            collection: collection.to_string(),
+            ..Self::default()
+        };
+        for (k, v) in current {
+            match persisted.get(k) {
+                None => changes.added.push(k.clone()),
+                Some(old) if old != v => changes.modified.push(k.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = persisted
+            .keys()
+            .filter(|k| !current.contains_key(*k))
+            .cloned()
+            .collect();
+        changes.added.sort();
+        changes.modified.sort();
+        changes.removed.sort();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Serialized engine waiting to be encrypted and written.
+struct PreparedSave {
+    collection_name: String,
+    generation: u64,
+    /// Store directory and key captured when the save was prepared, so a
+    /// store switch in between cannot redirect the write.
+    dir: PathBuf,
+    auth_key: Zeroizing<String>,
+    cipher: Cipher,
+    /// Compress `payload` before encrypting it (see `StoreConfig::compress_min_bytes`).
+    compress: bool,
+    codec: Codec,
+    payload: Zeroizing<Vec<u8>>,
+    index: Option<Zeroizing<Vec<u8>>>,
+    /// Backup generations to keep of the file being replaced.
+    backups: usize,
+}
+
+/// Least-recently-used ordering of warm keys.
+#[derive(Debug, Clone, Default)]
+struct LruTracker {
+    clock: u64,
+    by_key: HashMap<Arc<str>, u64>,
+    by_tick: BTreeMap<u64, Arc<str>>,
+}
+
+impl LruTracker {
+    fn touch(&mut self, key: &Arc<str>) {
+        self.clock += 1;
+        if let Some(old) = self.by_key.insert(Arc::clone(key), self.clock) {
+            self.by_tick.remove(&old);
+        }
+        self.by_tick.insert(self.clock, Arc::clone(key));
+    }
+
+    fn forget(&mut self, key: &str) {
+        if let Some(old) = self.by_key.remove(key) {
+            self.by_tick.remove(&old);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.by_key.clear();
+        self.by_tick.clear();
+    }
+
+    /// Pick the next key to evict. Keys never touched since load are
+    /// considered older than any tracked key.
+    fn oldest(&self, store: &HashMap<Arc<str>, Entry>) -> Option<Arc<str>> {
+        if self.by_key.len() < store.len()
+            && let Some(k) = store.keys().find(|k| !self.by_key.contains_key(*k))
+        {
+            return Some(k.clone());
+        }
+        self.by_tick.values().next().cloned()
+    }
+}
+
+/// Reverse index from values to the keys holding them. Stores hashes keyed
+/// with `value_index_key`, not values or their plain digests, so it adds no
+/// plaintext copies to memory and a dump of it cannot be checked against
+/// guessed values.
+#[derive(Debug, Clone, Default)]
+struct ValueIndex {
+    by_hash: HashMap<blake3::Hash, BTreeSet<Arc<str>>>,
+    by_key: HashMap<Arc<str>, blake3::Hash>,
+}
+
+impl ValueIndex {
+    fn insert(&mut self, key: &Arc<str>, value: &str) {
+        self.remove(key);
+        let hash = Self::digest(value);
+        self.by_hash
+            .entry(hash)
+            .or_default()
+            .insert(Arc::clone(key));
+        self.by_key.insert(Arc::clone(key), hash);
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(hash) = self.by_key.remove(key) else {
+            return;
+        };
+        if let Some(keys) = self.by_hash.get_mut(&hash) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.by_hash.remove(&hash);
+            }
+        }
+    }
+
+    fn keys(&self, value: &str) -> Vec<String> {
+        self.by_hash
+            .get(&Self::digest(value))
+            .map(|keys| keys.iter().map(|k| k.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn digest(value: &str) -> blake3::Hash {
+        blake3::keyed_hash(value_index_key(), value.as_bytes())
+    }
+}
+
+/// Key of every value index in this process. Made up on first use and never
+/// written anywhere, like a `ValueSealer` key.
+fn value_index_key() -> &'static [u8; 32] {
+    VALUE_INDEX_KEY.get_or_init(|| Zeroizing::new(AegCrypto::generate_random_bytes()))
+}
+
+/// Key that seals a collection's values while they sit in memory. Made up
+/// when sealing is turned on and never written anywhere: values are opened
+/// again before a save, so files look the same either way.
+#[derive(Clone)]
+struct ValueSealer {
+    key: Zeroizing<String>,
+}
+
+impl std::fmt::Debug for ValueSealer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueSealer(..)")
+    }
+}
+
+impl ValueSealer {
+    fn new() -> Self {
+        let bytes = Zeroizing::new(AegCrypto::generate_random_bytes());
+        Self {
+            key: Zeroizing::new(AegCrypto::encode_base64(bytes.as_slice())),
+        }
+    }
+
+    fn seal(&self, value: &str) -> String {
+        AegCrypto::encrypt_record(&self.key, value.as_bytes()).expect("Invalid in-memory key")
+    }
+
+    fn open(&self, sealed: &str) -> Zeroizing<String> {
+        let plain = AegCrypto::decrypt_record(&self.key, sealed)
+            .expect("Value was not sealed by this collection");
+        Zeroizing::new(
+            std::str::from_utf8(&plain)
+                .expect("Sealed value is not UTF-8")
+                .to_string(),
+        )
+    }
+}
+
+/// A cached collection; writers lock only their own collection.
+pub type SharedEngine = Arc<RwLock<AegMemoryEngine>>;
+
+/// The collections loaded from one store, when they were saved, and the
+/// saver that saves them. Each `AegStore` owns one; the static API works
+/// on the default instance outside `AegStore::run`.
+#[derive(Default)]
+pub(crate) struct MemoryCache {
+    /// The map lock is only held to look up or add a collection, never
+    /// while a collection is being used.
+    collections: RwLock<HashMap<String, SharedEngine>>,
+    /// Generation of each collection as of its last successful save.
+    saved_generations: Mutex<HashMap<String, u64>>,
+    /// Unix seconds of each collection's last save by this process.
+    saved_at: Mutex<HashMap<String, u64>>,
+    /// The running background saver, if any.
+    saver: Mutex<Option<SaverHandle>>,
+    saver_interval: AtomicU64,
+    /// Debounce window of the running saver in milliseconds; 0 when it
+    /// saves on an interval.
+    saver_debounce_ms: AtomicU64,
+    /// Set under `PersistencePolicy::EveryWrite`.
+    save_every_write: AtomicBool,
+}
+
+static DEFAULT_CACHE: OnceLock<Arc<MemoryCache>> = OnceLock::new();
+static VALUE_INDEX_KEY: OnceLock<Zeroizing<[u8; 32]>> = OnceLock::new();
+
+static SAVER_IDS: AtomicU64 = AtomicU64::new(0);
+/// Outstanding `SaverPause` guards; background saves are skipped while any exist.
+static SAVER_PAUSES: AtomicUsize = AtomicUsize::new(0);
+
+impl AegMemoryEngine {
+    /// The cache the static API works on: that of the `AegStore` this
+    /// thread runs, or the process's own.
+    fn cache() -> Arc<MemoryCache> {
+        match StoreContext::current() {
+            Some(context) => Arc::clone(&context.cache),
+            None => Arc::clone(DEFAULT_CACHE.get_or_init(Arc::default)),
+        }
+    }
+
+    /// True when the collection changed in memory since it was last saved.
+    /// Engines loaded from disk start clean at generation 0.
+    pub fn is_dirty(&self) -> bool {
+        let cache = Self::cache();
+        let guard = cache
+            .saved_generations
+            .lock()
+            .expect("Failed to lock saved generations");
+        self.generation > guard.get(&self.collection_name).copied().unwrap_or(0)
+    }
+
+    fn mark_saved(collection_name: &str, generation: u64) {
+        let cache = Self::cache();
+        let mut guard = cache
+            .saved_generations
+            .lock()
+            .expect("Failed to lock saved generations");
+        let saved = guard.entry(collection_name.to_string()).or_insert(0);
+        // a newer snapshot may already have been saved by someone else
+        *saved = (*saved).max(generation);
+        if let Some(now) = unix_now() {
+            cache
+                .saved_at
+                .lock()
+                .expect("Failed to lock save times")
+                .insert(collection_name.to_string(), now);
+        }
+    }
+
+    /// Unix seconds when `collection_name` was last saved: by this process
+    /// if it has saved it, otherwise the modification time of its data
+    /// file. `None` when it was never written.
+    pub fn last_saved(collection_name: &str) -> Option<u64> {
+        let recorded = Self::cache()
+            .saved_at
+            .lock()
+            .expect("Failed to lock save times")
+            .get(collection_name)
+            .copied();
+        recorded.or_else(|| {
+            std::fs::metadata(Self::engine_file_path(collection_name))
+                .and_then(|m| m.modified())
+                .ok()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .ok()
+        })
+    }
+
+    /// Unix seconds of the most recent save of any collection by this
+    /// process.
+    pub fn last_flush() -> Option<u64> {
+        Self::cache()
+            .saved_at
+            .lock()
+            .expect("Failed to lock save times")
+            .values()
+            .copied()
+            .max()
+    }
+
+    /// Entry count and total key and value bytes, cold entries included.
+    pub fn content_size(&self) -> (usize, u64, u64) {
+        let entries = self.entries();
+        let key_bytes = entries.iter().map(|(k, _)| k.len() as u64).sum();
+        let value_bytes = entries.iter().map(|(_, e)| e.value.len() as u64).sum();
+        (entries.len(), key_bytes, value_bytes)
+    }
+
+    pub fn new(collection_name: &str) -> Self {
+        Self {
+            store: HashMap::new(),
+            collection_name: collection_name.to_string(),
+            cold_index: HashMap::new(),
+            key_meta: HashMap::new(),
+            history: HashMap::new(),
+            warm_capacity: None,
+            indexed: false,
+            tier_stats: TierStats::default(),
+            generation: 0,
+            lru: LruTracker::default(),
+            value_index: None,
+            key_index: None,
+            sealer: None,
+            locked: false,
+        }
+    }
+
+    /// Run `f` on the plaintext of a value held in `store` or `history`,
+    /// opened into a buffer that is wiped afterwards when values are sealed.
+    fn with_plain<R>(&self, stored: &str, f: impl FnOnce(&str) -> R) -> R {
+        match &self.sealer {
+            Some(sealer) => f(&sealer.open(stored)),
+            None => f(stored),
+        }
+    }
+
+    fn reveal(&self, stored: &str) -> String {
+        self.with_plain(stored, str::to_string)
+    }
+
+    /// A warm value in plaintext: the stored value itself, or a new copy
+    /// opened from it when values are sealed.
+    fn reveal_shared(&self, stored: &Arc<str>) -> Arc<str> {
+        match &self.sealer {
+            Some(sealer) => Arc::from(sealer.open(stored).as_str()),
+            None => Arc::clone(stored),
+        }
+    }
+
+    fn reveal_entry(&self, entry: &Entry) -> Entry {
+        Entry {
+            value: self.reveal_shared(&entry.value),
+            ..entry.clone()
+        }
+    }
+
+    /// The stored key equal to `key` if there is one, so every structure
+    /// indexing it shares that allocation; a new one otherwise.
+    fn intern(&self, key: &str) -> Arc<str> {
+        match self.store.get_key_value(key) {
+            Some((key, _)) => Arc::clone(key),
+            None => Arc::from(key),
+        }
+    }
+
+    fn seal_value(&self, value: String) -> String {
+        match &self.sealer {
+            Some(sealer) => sealer.seal(&Zeroizing::new(value)),
+            None => value,
+        }
+    }
+
+    /// `entry` with its value sealed; the plaintext is wiped unless it is
+    /// still shared with someone else.
+    fn seal_entry(&self, mut entry: Entry) -> Entry {
+        let Some(sealer) = &self.sealer else {
+            return entry;
+        };
+        let sealed = sealer.seal(&entry.value);
+        if let Some(plain) = Arc::get_mut(&mut entry.value) {
+            plain.zeroize();
+        }
+        Entry {
+            value: sealed.into(),
+            ..entry
+        }
+    }
+
+    /// Re-encode every warm and historical value for `sealer` (`None`
+    /// leaves them in plaintext). Cold records are untouched: they are
+    /// encrypted on disk and opened into plaintext entries when read.
+    fn reseal(&mut self, sealer: Option<ValueSealer>) {
+        let old = std::mem::replace(&mut self.sealer, sealer);
+        let convert = |value: &str| {
+            let plain = match &old {
+                Some(old) => old.open(value),
+                None => Zeroizing::new(value.to_string()),
+            };
+            match &self.sealer {
+                Some(sealer) => sealer.seal(&plain),
+                None => plain.to_string(),
+            }
+        };
+        self.store
+            .values_mut()
+            .for_each(|e| e.value = convert(&e.value).into());
+        self.history
+            .values_mut()
+            .flatten()
+            .for_each(|v| v.value = convert(&v.value));
+    }
+
+    /// Whether values are kept sealed in memory.
+    pub fn is_sealed(&self) -> bool {
+        self.sealer.is_some()
+    }
+
+    /// Whether this engine stands in for a sealed collection that is
+    /// still locked.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Key for this collection's files, derived from the current master key
+    /// (see `AegFileSystem::collection_file_key`).
+    fn collection_key(collection_name: &str) -> Zeroizing<String> {
+        AegFileSystem::collection_file_key(
+            &AegFileSystem::read_authorization_key(),
+            collection_name,
+        )
+        .expect("Invalid authorization key")
+    }
+
+    /// Decrypt a collection data file, returning the plaintext and the key
+    /// that opened it, tried in `AegFileSystem::store_file_keys` order.
+    fn decrypt_collection_file(
+        collection_name: &str,
+        encrypted: &[u8],
+    ) -> Result<(Zeroizing<Vec<u8>>, Zeroizing<String>), String> {
+        let file_name = format!("collection_{}.aekv", collection_name);
+        let keys =
+            AegFileSystem::store_file_keys(&file_name, &AegFileSystem::read_authorization_key())?;
+        let mut first_err = None;
+        for key in keys {
+            match AegFileFormat::decode(&key, encrypted) {
+                Ok(plain) => return Ok((plain, key)),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_err.unwrap_or_default())
+    }
+
+    /// Decrypt and parse a collection file; also returns the key that opened it.
+    pub(crate) fn open_collection_file(
+        collection_name: &str,
+        encrypted: &[u8],
+    ) -> Result<(Self, Zeroizing<String>), String> {
+        let (decrypted, auth_key) = Self::decrypt_collection_file(collection_name, encrypted)?;
+        let engine = AegFileFormat::codec_of(encrypted)
+            .deserialize(&decrypted)
+            .map_err(|e| format!("corrupt collection file: {}", e))?;
+        Ok((engine, auth_key))
+    }
+
+    fn collection_file(dir: &Path, collection_name: &str, ext: &str) -> PathBuf {
+        dir.join(format!("collection_{}.{}", collection_name, ext))
+    }
+
+    fn engine_file_path(collection_name: &str) -> PathBuf {
+        Self::collection_file(&AegFileSystem::get_config_path(), collection_name, "aekv")
+    }
+
+    fn cold_file_path(collection_name: &str) -> PathBuf {
+        Self::collection_file(&AegFileSystem::get_config_path(), collection_name, "cold")
+    }
+
+    fn index_file_path(collection_name: &str) -> PathBuf {
+        Self::collection_file(&AegFileSystem::get_config_path(), collection_name, "idx")
+    }
+
+    /// Record the current files of a collection in the store manifest. A
+    /// failure leaves the manifest stale, which `verify` reports.
+    fn record_in_manifest(dir: &Path, collection_name: &str) {
+        let names =
+            ["aekv", "idx", "cold"].map(|ext| format!("collection_{}.{}", collection_name, ext));
+        let _ = AegIntegrity::record(dir, &names);
+    }
+
+    /// Insert into the engine (memory only, fast).
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let started = Instant::now();
+        self.insert_local(key.into(), value.into());
+        self.generation += 1;
+        self.enforce_warm_capacity();
+        // intentionally not saving here; the background saver persists it
+        AegMetrics::record_put(started.elapsed());
+    }
+
+    /// Insert `entry` as given, keeping its timestamps and tags, e.g. when
+    /// copying it from another store.
+    pub fn insert_entry(&mut self, key: &str, entry: Entry) {
+        self.record_version(key, &entry.value);
+        self.changed(Some(key), ChangeKind::Put);
+        self.store_entry(self.intern(key), entry);
+        self.generation += 1;
+        self.enforce_warm_capacity();
+    }
+
+    fn insert_local(&mut self, key: String, value: String) {
+        self.record_version(&key, &value);
+        let now = unix_now();
+        let entry = match self.entry(&key) {
+            Some(mut entry) => {
+                entry.value = value.into();
+                entry.updated_at = now;
+                entry
+            }
+            None => Entry {
+                value: value.into(),
+                created_at: now,
+                updated_at: now,
+                tags: Vec::new(),
+            },
+        };
+        self.changed(Some(&key), ChangeKind::Put);
+        let key = self.intern(&key);
+        self.store_entry(key, entry);
+    }
+
+    /// Put `entry` in the warm tier (and the record file when indexed).
+    /// `entry` holds plaintext; it is sealed here if the collection seals values.
+    fn store_entry(&mut self, key: Arc<str>, entry: Entry) {
+        if let Some(index) = &mut self.value_index {
+            index.insert(&key, &entry.value);
+        }
+        if let Some(index) = &mut self.key_index {
+            index.insert(Arc::clone(&key));
+        }
+        // the cold record (if any) is now stale
+        self.cold_index.remove(&*key);
+        if self.warm_capacity.is_some() {
+            self.lru.touch(&key);
+        }
+        if self.indexed {
+            match self.write_cold(&key, &entry) {
+                Ok(loc) => {
+                    self.cold_index.insert(key.to_string(), loc);
+                }
+                Err(e) => error!(
+                    collection = %self.collection_name,
+                    key = %key,
+                    error = %e,
+                    "failed to index entry"
+                ),
+            }
+        }
+        let entry = self.seal_entry(entry);
+        self.store.insert(key, entry);
+    }
+
+    /// Add `tag` to `key`. Returns false if the key does not exist.
+    pub fn tag(&mut self, key: &str, tag: &str) -> bool {
+        self.update_tags(key, |tags| {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+                tags.sort();
+            }
+        })
+    }
+
+    /// Remove `tag` from `key`. Returns false if the key does not exist.
+    pub fn untag(&mut self, key: &str, tag: &str) -> bool {
+        self.update_tags(key, |tags| tags.retain(|t| t != tag))
+    }
+
+    fn update_tags(&mut self, key: &str, f: impl FnOnce(&mut Vec<String>)) -> bool {
+        let Some(mut entry) = self.entry(key) else {
+            return false;
+        };
+        let before = entry.tags.clone();
+        f(&mut entry.tags);
+        if entry.tags != before {
+            self.store_entry(self.intern(key), entry);
+            self.generation += 1;
+            self.enforce_warm_capacity();
+        }
+        true
+    }
+
+    /// Keys whose current value is `value`, sorted.
+    pub fn keys_with_value(&mut self, value: &str) -> Vec<String> {
+        if self.value_index.is_none() {
+            let mut index = ValueIndex::default();
+            for (key, value) in self.list() {
+                index.insert(&key, &value);
+            }
+            self.value_index = Some(index);
+        }
+        self.value_index
+            .as_ref()
+            .map(|index| index.keys(value))
+            .unwrap_or_default()
+    }
+
+    /// Keys starting with `prefix`, in order. Served from a sorted index of
+    /// the keys, so only the matching range is visited.
+    pub fn keys_with_prefix(&mut self, prefix: &str) -> Vec<String> {
+        if self.key_index.is_none() {
+            self.key_index = Some(self.keys().into_iter().collect());
+        }
+        let Some(index) = &self.key_index else {
+            return Vec::new();
+        };
+        index
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|key| key.starts_with(prefix))
+            .map(|key| key.to_string())
+            .collect()
+    }
+
+    /// The keys under path `path` (`app/db` covers `app/db/password` but not
+    /// `app/dbx`), in order; every key for an empty path.
+    pub fn keys_under(&mut self, path: &str) -> Vec<String> {
+        self.keys_with_prefix(&Self::branch(path))
+    }
+
+    /// The next level below path `path`: the name of each key directly
+    /// under it, and of each branch, with a trailing `/`, once. A name can
+    /// appear both ways when a key is also the parent of others.
+    pub fn children(&mut self, path: &str) -> Vec<String> {
+        let branch = Self::branch(path);
+        let mut children: Vec<String> = Vec::new();
+        for key in self.keys_with_prefix(&branch) {
+            let rest = &key[branch.len()..];
+            let child = match rest.split_once(KEY_PATH_SEPARATOR) {
+                Some((name, _)) => format!("{}{}", name, KEY_PATH_SEPARATOR),
+                None => rest.to_string(),
+            };
+            // keys under one branch are adjacent in key order
+            if children.last() != Some(&child) {
+                children.push(child);
+            }
+        }
+        children
+    }
+
+    /// Delete every key under path `path`; `path` itself, if it is a key,
+    /// is kept. Returns how many keys were deleted.
+    pub fn delete_subtree(&mut self, path: &str) -> usize {
+        let keys = self.keys_under(path);
+        for key in &keys {
+            self.delete_local(key);
+        }
+        if !keys.is_empty() {
+            self.generation += 1;
+        }
+        keys.len()
+    }
+
+    /// `path` as a key prefix: with a trailing separator, or empty.
+    fn branch(path: &str) -> String {
+        if path.is_empty() || path.ends_with(KEY_PATH_SEPARATOR) {
+            path.to_string()
+        } else {
+            format!("{}{}", path, KEY_PATH_SEPARATOR)
+        }
+    }
+
+    /// Keys whose name, or with `values` whose current value, satisfies
+    /// `matches`, sorted. Walks the tiers in place rather than through
+    /// `entries`: warm values are borrowed, and cold records are decrypted
+    /// one at a time, only when values are searched and the key did not
+    /// already match.
+    pub fn search(&self, matches: impl Fn(&str) -> bool, values: bool) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .store
+            .iter()
+            .filter(|(k, e)| matches(k) || (values && self.with_plain(&e.value, &matches)))
+            .map(|(k, _)| k.to_string())
+            .collect();
+        for (k, loc) in self.cold_index.iter() {
+            if self.store.contains_key(k.as_str()) {
+                continue;
+            }
+            let hit = matches(k)
+                || (values
+                    && match self.read_cold(k, *loc) {
+                        Ok(entry) => matches(&entry.value),
+                        Err(e) => {
+                            error!(
+                                collection = %self.collection_name,
+                                key = %k,
+                                error = %e,
+                                "failed to read cold entry"
+                            );
+                            false
+                        }
+                    });
+            if hit {
+                keys.push(k.clone());
+            }
+        }
+        keys.sort();
+        keys
+    }
+
+    /// Keys carrying `tag`, sorted.
+    pub fn keys_with_tag(&self, tag: &str) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .entries()
+            .into_iter()
+            .filter(|(_, e)| e.has_tag(tag))
+            .map(|(k, _)| k.to_string())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Append `value` to the key's history unless it is already the latest.
+    /// A key without history yet gets its current value recorded first, so
+    /// the first overwrite can still be undone.
+    fn record_version(&mut self, key: &str, value: &str) {
+        let untracked = self.history.get(key).is_none_or(Vec::is_empty);
+        let current = if untracked { self.get(key) } else { None };
+        let unchanged = match &current {
+            Some(current) => current == value,
+            None => self
+                .history
+                .get(key)
+                .and_then(|versions| versions.last())
+                .is_some_and(|v| self.with_plain(&v.value, |latest| latest == value)),
+        };
+        let current = current.map(|current| self.seal_value(current));
+        let value = (!unchanged).then(|| self.seal_value(value.to_string()));
+        let versions = self.history.entry(key.to_string()).or_default();
+        if let Some(current) = current {
+            versions.push(ValueVersion {
+                version: 1,
+                value: current,
+                set_at: None,
+            });
+        }
+        let Some(value) = value else {
+            return;
+        };
+        let set_at = unix_now();
+        versions.push(ValueVersion {
+            version: versions.last().map_or(1, |v| v.version + 1),
+            value,
+            set_at,
+        });
+        if versions.len() > KEY_HISTORY_DEPTH {
+            versions.drain(..versions.len() - KEY_HISTORY_DEPTH);
+        }
+    }
+
+    /// Recorded values of `key`, oldest first.
+    pub fn history(&self, key: &str) -> Vec<ValueVersion> {
+        self.history
+            .get(key)
+            .map(|versions| {
+                versions
+                    .iter()
+                    .map(|v| ValueVersion {
+                        value: self.reveal(&v.value),
+                        ..v.clone()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Make an earlier value of `key` current again (as a new version).
+    /// Returns the restored value.
+    pub fn restore_version(&mut self, key: &str, version: u64) -> Result<String, String> {
+        let value = self
+            .history
+            .get(key)
+            .and_then(|versions| versions.iter().find(|v| v.version == version))
+            .map(|v| self.reveal(&v.value))
+            .ok_or_else(|| format!("key '{}' has no version {}", key, version))?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Tell watchers and the audit log about a change to this collection.
+    fn changed(&self, key: Option<&str>, kind: ChangeKind) {
+        AegWatch::notify(&self.collection_name, key, kind);
+        AegAudit::record(&self.collection_name, key, kind);
+    }
+
+    fn delete_local(&mut self, key: &str) {
+        if let Some(index) = &mut self.value_index {
+            index.remove(key);
+        }
+        if let Some(index) = &mut self.key_index {
+            index.remove(key);
+        }
+        let warm = self.store.remove(key).is_some();
+        let cold = self.cold_index.remove(key).is_some();
+        self.key_meta.remove(key);
+        self.lru.forget(key);
+        if warm || cold {
+            self.changed(Some(key), ChangeKind::Delete);
+        }
+    }
+
+    /// Apply a set of puts (`Some`) and deletes (`None`). On a cached engine
+    /// this runs under one write lock, so readers never see a partial batch.
+    pub fn apply_batch(&mut self, changes: impl IntoIterator<Item = (String, Option<String>)>) {
+        for (key, change) in changes {
+            match change {
+                Some(value) => self.insert_local(key, value),
+                None => self.delete_local(&key),
+            }
+        }
+        self.generation += 1;
+        self.enforce_warm_capacity();
+    }
+
+    /// Read a key from the warm tier, falling back to the cold file.
+    /// Does not promote cold entries; use `fetch` for that.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.get_shared(key).map(|v| v.to_string())
+    }
+
+    /// `get` without copying: a warm value is returned as stored, unless
+    /// values are sealed and it has to be opened.
+    pub fn get_shared(&self, key: &str) -> Option<Arc<str>> {
+        match self.store.get(key) {
+            Some(entry) => Some(self.reveal_shared(&entry.value)),
+            None => self.entry(key).map(|e| e.value),
+        }
+    }
+
+    /// Whether `key` exists, without reading its value.
+    pub fn contains(&self, key: &str) -> bool {
+        self.store.contains_key(key) || self.cold_index.contains_key(key)
+    }
+
+    /// `get` into a buffer that is wiped when dropped. With sealed values
+    /// this is the only plaintext copy the read makes.
+    pub fn get_secret(&self, key: &str) -> Option<Zeroizing<String>> {
+        match (self.store.get(key), &self.sealer) {
+            (Some(entry), Some(sealer)) => Some(sealer.open(&entry.value)),
+            (Some(entry), None) => Some(Zeroizing::new(entry.value.to_string())),
+            (None, _) => self.entry(key).map(|e| Zeroizing::new(e.value.to_string())),
+        }
+    }
+
+    /// `get`, with the entry's metadata.
+    pub fn entry(&self, key: &str) -> Option<Entry> {
+        if let Some(entry) = self.store.get(key) {
+            return Some(self.reveal_entry(entry));
+        }
+        let loc = self.cold_index.get(key)?;
+        match self.read_cold(key, *loc) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                error!(
+                    collection = %self.collection_name,
+                    key,
+                    error = %e,
+                    "failed to read cold entry"
+                );
+                None
+            }
+        }
+    }
+
+    /// Read a key, paging it into the warm tier on a miss and recording
+    /// hit/miss statistics. Behaves like `get` when tiering is disabled.
+    pub fn fetch(&mut self, key: &str) -> Option<Arc<str>> {
+        if self.warm_capacity.is_none() {
+            return self.get_shared(key);
+        }
+
+        if let Some((key, entry)) = self.store.get_key_value(key) {
+            let key = Arc::clone(key);
+            let value = self.reveal_shared(&entry.value);
+            self.tier_stats.hits += 1;
+            self.lru.touch(&key);
+            return Some(value);
+        }
+
+        let loc = *self.cold_index.get(key)?;
+        self.tier_stats.misses += 1;
+        let entry = match self.read_cold(key, loc) {
+            Ok(entry) => entry,
+            Err(e) => {
+                error!(
+                    collection = %self.collection_name,
+                    key,
+                    error = %e,
+                    "failed to page in entry"
+                );
+                return None;
+            }
+        };
+        // keep the cold location: the record stays valid until the value changes
+        let value = Arc::clone(&entry.value);
+        let entry = self.seal_entry(entry);
+        let key: Arc<str> = Arc::from(key);
+        self.store.insert(Arc::clone(&key), entry);
+        self.lru.touch(&key);
+        self.enforce_warm_capacity();
+        Some(value)
+    }
+
+    pub fn delete(&mut self, key: &str) {
+        self.delete_local(key);
+        self.generation += 1;
+        AegMetrics::record_delete();
+    }
+
+    /// Copy every entry of `other` into this engine with its metadata and
+    /// history, replacing keys it already holds. Returns how many entries
+    /// were copied.
+    pub fn copy_from(&mut self, other: &AegMemoryEngine) -> usize {
+        let entries = other.entries();
+        for (key, entry) in &entries {
+            self.changed(Some(key), ChangeKind::Put);
+            self.store_entry(self.intern(key), entry.clone());
+        }
+        for (key, meta) in &other.key_meta {
+            self.key_meta.insert(key.clone(), meta.clone());
+        }
+        for key in other.history.keys() {
+            let versions = other
+                .history(key)
+                .into_iter()
+                .map(|v| ValueVersion {
+                    value: self.seal_value(v.value),
+                    ..v
+                })
+                .collect();
+            self.history.insert(key.clone(), versions);
+        }
+        self.generation += 1;
+        self.enforce_warm_capacity();
+        entries.len()
+    }
+
+    /// Move `key` to `new_key` with its metadata and history.
+    pub fn rename_key(&mut self, key: &str, new_key: &str) -> Result<(), String> {
+        if self.entry(new_key).is_some() {
+            return Err(format!("Key '{}' already exists", new_key));
+        }
+        let Some(entry) = self.entry(key) else {
+            return Err(format!("Key '{}' not found", key));
+        };
+        let meta = self.key_meta.remove(key);
+        let history = self.history.remove(key);
+        self.delete_local(key);
+        self.changed(Some(new_key), ChangeKind::Put);
+        self.store_entry(Arc::from(new_key), entry);
+        if let Some(meta) = meta {
+            self.key_meta.insert(new_key.to_string(), meta);
+        }
+        if let Some(history) = history {
+            self.history.insert(new_key.to_string(), history);
+        }
+        self.generation += 1;
+        self.enforce_warm_capacity();
+        Ok(())
+    }
+
+    /// Every key and value. Warm ones are shared with the store rather
+    /// than copied (see `get_shared`).
+    pub fn list(&self) -> Vec<(Arc<str>, Arc<str>)> {
+        let mut list: Vec<(Arc<str>, Arc<str>)> = self
+            .store
+            .iter()
+            .map(|(k, e)| (Arc::clone(k), self.reveal_shared(&e.value)))
+            .collect();
+        list.extend(self.cold_entries().map(|(k, e)| (k, e.value)));
+        list
+    }
+
+    /// `list`, with each entry's metadata.
+    pub fn entries(&self) -> Vec<(Arc<str>, Entry)> {
+        let mut entries: Vec<(Arc<str>, Entry)> = self
+            .store
+            .iter()
+            .map(|(k, e)| (Arc::clone(k), self.reveal_entry(e)))
+            .collect();
+        entries.extend(self.cold_entries());
+        entries
+    }
+
+    /// Every key, without reading any value.
+    pub fn keys(&self) -> Vec<Arc<str>> {
+        let mut keys: Vec<Arc<str>> = self.store.keys().cloned().collect();
+        keys.extend(
+            self.cold_index
+                .keys()
+                .filter(|k| !self.store.contains_key(k.as_str()))
+                .map(|k| Arc::from(k.as_str())),
+        );
+        keys
+    }
 
-/// Background saver control
-static SAVER_RUNNING: OnceLock<AtomicBool> = OnceLock::new();
-static SAVER_STARTED: OnceLock<AtomicBool> = OnceLock::new();
+    /// Entries only in the cold tier, each decrypted as it is reached.
+    fn cold_entries(&self) -> impl Iterator<Item = (Arc<str>, Entry)> + '_ {
+        self.cold_index
+            .iter()
+            .filter(|(k, _)| !self.store.contains_key(k.as_str()))
+            .filter_map(|(k, loc)| match self.read_cold(k, *loc) {
+                Ok(v) => Some((Arc::from(k.as_str()), v)),
+                Err(e) => {
+                    error!(
+                        collection = %self.collection_name,
+                        key = %k,
+                        error = %e,
+                        "failed to read cold entry"
+                    );
+                    None
+                }
+            })
+    }
 
-impl AegMemoryEngine {
-    /// Returns a reference to the global Mutex<HashMap<...>>.
-    fn global_memory_mutex() -> &'static Mutex<HashMap<String, AegMemoryEngine>> {
-        MEMORY_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+    pub fn clear(&mut self) {
+        self.store.clear();
+        self.generation += 1;
+        self.cold_index.clear();
+        self.key_meta.clear();
+        self.history.clear();
+        self.lru.clear();
+        self.value_index = None;
+        self.key_index = None;
+        self.changed(None, ChangeKind::Clear);
+        let cold_path = Self::cold_file_path(&self.collection_name);
+        let storage = AegFileSystem::storage();
+        if storage.exists(&cold_path) {
+            let _ = storage.remove(&cold_path);
+            Self::record_in_manifest(&AegFileSystem::get_config_path(), &self.collection_name);
+        }
     }
 
-    pub fn new(collection_name: &str) -> Self {
-        Self {
-            store: HashMap::new(),
-            collection_name: collection_name.to_string(),
+    /// Set or clear the environment variable name `key` exports under.
+    /// Fails for names that are not valid variable names (see
+    /// `AegEnv::is_valid_name`).
+    pub fn set_env_name(&mut self, key: &str, env_name: Option<String>) -> Result<(), String> {
+        if let Some(name) = env_name.as_deref().filter(|n| !AegEnv::is_valid_name(n)) {
+            return Err(format!(
+                "'{}' is not a valid environment variable name",
+                name
+            ));
         }
+        let meta = self.key_meta.entry(key.to_string()).or_default();
+        meta.env_name = env_name;
+        if *meta == KeyMeta::default() {
+            self.key_meta.remove(key);
+        }
+        self.generation += 1;
+        Ok(())
     }
 
-    fn engine_file_path(collection_name: &str) -> PathBuf {
-        let mut path = AegFileSystem::get_config_path();
-        path.push(format!("collection_{}.aekv", collection_name));
-        path
+    /// The environment variable name for `key`: its configured `env_name`,
+    /// or the key upper-cased with every other character replaced by `_`
+    /// (`service/db-url` becomes `SERVICE_DB_URL`). A configured name that
+    /// is not a valid variable name is ignored.
+    pub fn env_name_for(&self, key: &str) -> String {
+        if let Some(name) = self
+            .key_meta
+            .get(key)
+            .and_then(|m| m.env_name.clone())
+            .filter(|n| AegEnv::is_valid_name(n))
+        {
+            return name;
+        }
+        let mut name: String = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            name.insert(0, '_');
+        }
+        name
     }
 
-    /// Insert into current engine and update global in-memory cache (fast).
-    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.store.insert(key.into(), value.into());
-        // persist to global in-memory cache (only memory)
-        let mutex = Self::global_memory_mutex();
-        let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-        guard.insert(self.collection_name.clone(), self.clone());
-        // intentionally not calling self.save() here
+    /// Every entry as (environment variable name, value), sorted by name.
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self
+            .list()
+            .into_iter()
+            .map(|(k, v)| (self.env_name_for(&k), v.to_string()))
+            .collect();
+        vars.sort();
+        vars
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
-        self.store.get(key).cloned()
+    /// Current warm/cold statistics for this engine.
+    pub fn stats(&self) -> TierStats {
+        TierStats {
+            warm_entries: self.store.len(),
+            cold_entries: self
+                .cold_index
+                .keys()
+                .filter(|k| !self.store.contains_key(k.as_str()))
+                .count(),
+            ..self.tier_stats
+        }
     }
 
-    pub fn delete(&mut self, key: &str) {
-        self.store.remove(key);
-        let mutex = Self::global_memory_mutex();
-        let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-        guard.insert(self.collection_name.clone(), self.clone());
+    /// Page out least-recently-used entries until the warm tier fits its capacity.
+    fn enforce_warm_capacity(&mut self) {
+        let Some(capacity) = self.warm_capacity else {
+            return;
+        };
+        while self.store.len() > capacity {
+            let Some(victim) = self.lru.oldest(&self.store) else {
+                break;
+            };
+            if !self.cold_index.contains_key(&*victim)
+                && let Some(entry) = self.store.get(&victim)
+            {
+                match self.write_cold(&victim, &self.reveal_entry(entry)) {
+                    Ok(loc) => {
+                        self.cold_index.insert(victim.to_string(), loc);
+                    }
+                    Err(e) => {
+                        // keep it warm rather than lose it
+                        warn!(
+                            collection = %self.collection_name,
+                            key = %victim,
+                            error = %e,
+                            "failed to page out entry; keeping it in memory"
+                        );
+                        break;
+                    }
+                }
+            }
+            self.store.remove(&victim);
+            self.lru.forget(&victim);
+            self.tier_stats.evictions += 1;
+            // the on-disk layout should follow the smaller warm set
+            self.generation += 1;
+        }
     }
 
-    pub fn list(&self) -> Vec<(String, String)> {
-        self.store
+    fn write_cold(&self, key: &str, entry: &Entry) -> Result<ColdLocation, String> {
+        let payload = Zeroizing::new(
+            serde_json::to_vec(&(key, entry)).map_err(|e| format!("serialize error: {}", e))?,
+        );
+        let auth_key = Self::collection_key(&self.collection_name);
+        let cipher = AegFileSystem::read_store_config().cipher;
+        let record = AegCrypto::seal(cipher, &auth_key, &payload)?;
+        let path = Self::cold_file_path(&self.collection_name);
+        let offset = AegFileSystem::append_record(&path, &record)?;
+        Self::record_in_manifest(&AegFileSystem::get_config_path(), &self.collection_name);
+        Ok(ColdLocation {
+            offset,
+            len: record.len() as u64,
+        })
+    }
+
+    fn read_cold(&self, key: &str, loc: ColdLocation) -> Result<Entry, String> {
+        let auth_key = Self::collection_key(&self.collection_name);
+        Self::read_cold_record(&self.collection_name, key, loc, &auth_key)
+    }
+
+    fn read_cold_record(
+        collection_name: &str,
+        key: &str,
+        loc: ColdLocation,
+        auth_key: &str,
+    ) -> Result<Entry, String> {
+        let path = Self::cold_file_path(collection_name);
+        let record = AegFileSystem::read_record(&path, loc.offset, loc.len)?;
+        let plain = AegCrypto::decrypt_record(auth_key, &record)?;
+        let (stored_key, entry): (String, Entry) =
+            serde_json::from_slice(&plain).map_err(|e| format!("corrupt record: {}", e))?;
+        if stored_key != key {
+            return Err(format!("record belongs to '{}'", stored_key));
+        }
+        Ok(entry)
+    }
+
+    /// Rewrite the record file with only the records the index points at,
+    /// dropping the ones left behind by changed and deleted keys, and save
+    /// the collection so its index matches. Returns the bytes reclaimed.
+    pub fn compact_records(&mut self) -> Result<u64, String> {
+        let path = Self::cold_file_path(&self.collection_name);
+        let storage = AegFileSystem::storage();
+        if !storage.exists(&path) {
+            return Ok(0);
+        }
+        let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())?;
+        let before = storage
+            .read(&path)
+            .map_err(|e| format!("read records: {}", e))?;
+        let mut live: Vec<(String, ColdLocation)> = self
+            .cold_index
             .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
+            .map(|(k, loc)| (k.clone(), *loc))
+            .collect();
+        live.sort_by_key(|(_, loc)| loc.offset);
+
+        let mut after = Vec::new();
+        let mut index = HashMap::with_capacity(live.len());
+        for (key, loc) in live {
+            let record = usize::try_from(loc.offset)
+                .ok()
+                .zip(usize::try_from(loc.offset + loc.len).ok())
+                .and_then(|(start, end)| before.get(start..end))
+                .ok_or_else(|| format!("record of '{}' lies past the end of the file", key))?;
+            index.insert(
+                key,
+                ColdLocation {
+                    offset: after.len() as u64,
+                    len: loc.len,
+                },
+            );
+            after.extend_from_slice(record);
+            after.push(b'\n');
+        }
+        if after.len() == before.len() {
+            return Ok(0);
+        }
+
+        if after.is_empty() {
+            storage.remove(&path)
+        } else {
+            storage.write(&path, &after)
+        }
+        .map_err(|e| format!("write records: {}", e))?;
+        self.cold_index = index;
+        self.generation += 1;
+        Self::save_to_disk(self)?;
+        Self::mark_saved(&self.collection_name, self.generation);
+        Ok((before.len() - after.len()) as u64)
+    }
+
+    /// Write the encrypted key -> record index, or remove it if there is nothing to index.
+    fn save_index(path: &Path, record: Option<&str>) -> Result<(), String> {
+        let Some(record) = record else {
+            let storage = AegFileSystem::storage();
+            if storage.exists(path) {
+                storage
+                    .remove(path)
+                    .map_err(|e| format!("remove index: {}", e))?;
+            }
+            return Ok(());
+        };
+        AegFileSystem::storage()
+            .write(path, record.as_bytes())
+            .map_err(|e| format!("write index: {}", e))
+    }
+
+    /// Read and decrypt a collection's index file, if it has one.
+    fn load_index(
+        collection_name: &str,
+        auth_key: &str,
+    ) -> Result<Option<HashMap<String, ColdLocation>>, String> {
+        let path = Self::index_file_path(collection_name);
+        let storage = AegFileSystem::storage();
+        if !storage.exists(&path) {
+            return Ok(None);
+        }
+        let record = storage
+            .read(&path)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| format!("read index: {}", path.display()))?;
+        let json = AegCrypto::decrypt_record(auth_key, &record)?;
+        serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| format!("corrupt index: {}", e))
+    }
+
+    /// Read a single key straight from disk using the index, without loading
+    /// or decrypting the whole collection and without touching the cache.
+    /// Only sees data persisted by the last save.
+    pub fn read_from_disk(collection_name: &str, key: &str) -> Result<Option<String>, String> {
+        let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())?;
+        let subkey = Self::collection_key(collection_name);
+        // collections saved before per-collection keys are still under the master key
+        let (index, auth_key) = match Self::load_index(collection_name, &subkey) {
+            Ok(index) => (index, subkey),
+            Err(e) => {
+                let master = AegFileSystem::read_authorization_key();
+                match Self::load_index(collection_name, &master) {
+                    Ok(index) => (index, master),
+                    Err(_) => return Err(e),
+                }
+            }
+        };
+        let index = index.ok_or_else(|| {
+            format!(
+                "collection '{}' has no on-disk index (enable indexing first)",
+                collection_name
+            )
+        })?;
+        match index.get(key) {
+            Some(loc) => Self::read_cold_record(collection_name, key, *loc, &auth_key)
+                .map(|e| Some(e.value.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Every entry persisted for a collection as of its last save.
+    pub(crate) fn read_persisted(collection_name: &str) -> Result<HashMap<String, String>, String> {
+        let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())?;
+        let mut auth_key = Self::collection_key(collection_name);
+        let mut engine =
+            match AegFileSystem::storage().read(&Self::engine_file_path(collection_name)) {
+                Ok(encrypted) if !encrypted.is_empty() => {
+                    let (plain, key) = Self::decrypt_collection_file(collection_name, &encrypted)?;
+                    auth_key = key;
+                    AegFileFormat::codec_of(&encrypted)
+                        .deserialize(&plain)
+                        .map_err(|e| format!("corrupt collection file: {}", e))?
+                }
+                _ => Self::new(collection_name),
+            };
+        if let Some(index) = Self::load_index(collection_name, &auth_key)? {
+            engine.cold_index = index;
+        }
+        Ok(engine.list_owned())
+    }
+
+    /// Diff a cached collection against its files on disk. Collections that
+    /// are not loaded, or loaded and clean, have nothing pending.
+    pub fn pending_changes(collection_name: &str) -> Result<PendingChanges, String> {
+        let handle = Self::cache()
+            .collections
+            .read()
+            .expect("Failed to lock memory cache")
+            .get(collection_name)
+            .cloned();
+        let nothing = || PendingChanges {
+            collection: collection_name.to_string(),
+            ..PendingChanges::default()
+        };
+        let Some(handle) = handle else {
+            return Ok(nothing());
+        };
+        let current: HashMap<String, String> = {
+            let engine = handle.read().expect("Failed to lock collection");
+            if !engine.is_dirty() {
+                return Ok(nothing());
+            }
+            engine.list_owned()
+        };
+        let persisted = Self::read_persisted(collection_name)?;
+        Ok(PendingChanges::between(
+            collection_name,
+            &persisted,
+            &current,
+        ))
+    }
+
+    /// Whether `collection_name` has unsaved changes; `None` when it is not
+    /// loaded. Never loads it.
+    pub fn cached_dirty(collection_name: &str) -> Option<bool> {
+        let handle = Self::cache()
+            .collections
+            .read()
+            .expect("Failed to lock memory cache")
+            .get(collection_name)
+            .cloned()?;
+        let dirty = handle.read().expect("Failed to lock collection").is_dirty();
+        Some(dirty)
+    }
+
+    /// Names of the collections currently loaded in memory, sorted.
+    pub fn cached_collections() -> Vec<String> {
+        let mut names: Vec<String> = Self::cache()
+            .collections
+            .read()
+            .expect("Failed to lock memory cache")
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// `list` as owned strings.
+    pub(crate) fn list_owned(&self) -> HashMap<String, String> {
+        self.list()
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect()
     }
 
-    pub fn clear(&mut self) {
-        self.store.clear();
-        let mutex = Self::global_memory_mutex();
-        let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-        guard.insert(self.collection_name.clone(), self.clone());
+    /// Serialize the engine (the cheap part of a save), so callers holding a
+    /// lock can release it before encryption and file IO.
+    fn prepare_save(&self) -> Result<PreparedSave, String> {
+        if self.locked {
+            return Err(format!(
+                "collection '{}' is sealed; unseal it first",
+                self.collection_name
+            ));
+        }
+        let codec = Codec::default();
+        // files hold the plaintext values; the in-memory key is never saved
+        let payload = Zeroizing::new(match self.sealer {
+            Some(_) => {
+                let mut unsealed = self.clone();
+                unsealed.reseal(None);
+                codec.serialize(&unsealed)?
+            }
+            None => codec.serialize(self)?,
+        });
+        let index = if self.cold_index.is_empty() {
+            None
+        } else {
+            Some(Zeroizing::new(
+                serde_json::to_vec(&self.cold_index)
+                    .map_err(|e| format!("serialize index: {}", e))?,
+            ))
+        };
+        let config = AegFileSystem::read_store_config();
+        Ok(PreparedSave {
+            collection_name: self.collection_name.clone(),
+            generation: self.generation,
+            dir: AegFileSystem::get_config_path(),
+            auth_key: Self::collection_key(&self.collection_name),
+            cipher: config.cipher,
+            compress: config
+                .compress_min_bytes
+                .is_some_and(|min| payload.len() >= min),
+            codec,
+            payload,
+            index,
+            backups: config.backup_generations,
+        })
+    }
+
+    /// Encrypt and write a prepared save, recording it in `AegMetrics`.
+    fn write_prepared(prepared: &PreparedSave) -> Result<(), String> {
+        let started = Instant::now();
+        match Self::write_prepared_files(prepared) {
+            Ok(bytes) => {
+                AegMetrics::record_save(started.elapsed(), bytes);
+                Ok(())
+            }
+            Err(e) => {
+                AegMetrics::record_save_failure();
+                Err(e)
+            }
+        }
+    }
+
+    /// The index is written first so a crash in between never loses paged-out keys.
+    /// Returns the size of the collection file written.
+    fn write_prepared_files(prepared: &PreparedSave) -> Result<u64, String> {
+        let path = Self::collection_file(&prepared.dir, &prepared.collection_name, "aekv");
+        let index_path = Self::collection_file(&prepared.dir, &prepared.collection_name, "idx");
+        let _span = info_span!("save", collection = %prepared.collection_name).entered();
+        AegFileSystem::ensure_writable()?;
+
+        // encrypt first: the store lock, which keeps other processes from
+        // reading the store, is only held for the writes
+        let index = prepared
+            .index
+            .as_deref()
+            .map(|json| AegCrypto::seal(prepared.cipher, &prepared.auth_key, json))
+            .transpose()?;
+        let encoded = AegFileFormat::encode_with(
+            prepared.cipher,
+            &prepared.auth_key,
+            &prepared.payload,
+            prepared.codec,
+            prepared.compress,
+        )?;
+
+        let _lock = AegFileSystem::lock_store(&prepared.dir)?;
+        Self::save_index(&index_path, index.as_deref())?;
+        AegBackups::rotate(&prepared.dir, &prepared.collection_name, prepared.backups)?;
+        AegFileSystem::storage()
+            .write(&path, &encoded)
+            .map_err(|e| format!("write error: {}", e))?;
+        Self::record_in_manifest(&prepared.dir, &prepared.collection_name);
+        info!(bytes = encoded.len(), "saved collection");
+
+        Ok(encoded.len() as u64)
+    }
+
+    /// The collection file `save_to_disk` would write for this engine,
+    /// without writing it. Cold entries are not included, so this is meant
+    /// for the self-contained copies made by `capture_consistent`.
+    pub(crate) fn encode_detached(&self) -> Result<Vec<u8>, String> {
+        let prepared = self.prepare_save()?;
+        AegFileFormat::encode_with(
+            prepared.cipher,
+            &prepared.auth_key,
+            &prepared.payload,
+            prepared.codec,
+            prepared.compress,
+        )
     }
 
     /// Persist single engine to disk (synchronous) — same encryption as before.
     pub fn save_to_disk(engine: &AegMemoryEngine) -> Result<(), String> {
-        let path = Self::engine_file_path(&engine.collection_name);
+        Self::write_prepared(&engine.prepare_save()?)
+    }
 
-        let json =
-            serde_json::to_string_pretty(engine).map_err(|e| format!("serialize error: {}", e))?;
+    /// Save every collection in memory that changed since its last save.
+    /// Returns how many collections were written.
+    pub fn save_all() -> usize {
+        Self::save_dirty(|_| true)
+    }
 
-        let auth_key = AegFileSystem::read_authorization_key();
-        let key_bytes = general_purpose::STANDARD
-            .decode(auth_key)
-            .map_err(|e| format!("base64 decode auth key: {}", e))?;
+    /// Save one collection if it is in memory and changed since its last
+    /// save. Returns whether it was written.
+    pub fn save_collection(collection_name: &str) -> bool {
+        Self::save_dirty(|name| name == collection_name) > 0
+    }
 
-        let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
-        let cipher = Aes256Gcm::new(key);
-        let nonce = Nonce::from_slice(&key_bytes[..12]);
+    /// Save the dirty collections that have not opted out of autosave.
+    /// Used by the background saver; `save_all` covers every collection.
+    /// Does nothing while the saver is paused.
+    pub fn save_autosave() -> usize {
+        if Self::saver_paused() {
+            return 0;
+        }
+        let core = AegCore::load();
+        Self::save_dirty(|name| core.is_autosave_enabled(name))
+    }
 
-        let encrypted = cipher
-            .encrypt(nonce, json.as_bytes())
-            .map_err(|e| format!("encrypt error: {:?}", e))?;
+    /// Serializes each dirty engine under its own read lock (readers are not
+    /// blocked) and performs the expensive encryption/write work outside of it.
+    fn save_dirty(include: impl Fn(&str) -> bool) -> usize {
+        if AegFileSystem::is_read_only() {
+            return 0;
+        }
+        // 1) Grab the handles; the map lock is released right away
+        let handles: Vec<SharedEngine> = {
+            let cache = Self::cache();
+            let guard = cache
+                .collections
+                .read()
+                .expect("Failed to lock memory cache");
+            guard
+                .iter()
+                .filter(|(name, _)| include(name))
+                .map(|(_, handle)| Arc::clone(handle))
+                .collect()
+        };
 
-        let encoded = general_purpose::STANDARD.encode(&encrypted);
+        let mut written = 0;
+        for handle in handles {
+            // 2) Serialize dirty collections under a shared lock
+            let prepared = {
+                let engine = handle.read().expect("Failed to lock collection");
+                if !engine.is_dirty() {
+                    continue;
+                }
+                engine.prepare_save()
+            };
 
-        fs::write(&path, encoded).map_err(|e| format!("write error: {}", e))?;
+            // 3) Encrypt and write outside the lock
+            // best-effort: log errors but continue (the collection stays dirty)
+            match prepared.and_then(|p| Self::write_prepared(&p).map(|_| p)) {
+                Ok(p) => {
+                    Self::mark_saved(&p.collection_name, p.generation);
+                    written += 1;
+                }
+                Err(e) => {
+                    let name = handle
+                        .read()
+                        .map(|e| e.collection_name.clone())
+                        .unwrap_or_default();
+                    error!(collection = %name, error = %e, "failed to save collection");
+                }
+            }
+        }
+        written
+    }
 
-        Ok(())
+    /// Drop every collection of the current cache without saving. Used when
+    /// switching to a different store; flush first if the data should be kept.
+    pub fn reset_cache() {
+        let cache = Self::cache();
+        cache
+            .collections
+            .write()
+            .expect("Failed to lock memory cache")
+            .clear();
+        cache
+            .saved_generations
+            .lock()
+            .expect("Failed to lock saved generations")
+            .clear();
+        cache
+            .saved_at
+            .lock()
+            .expect("Failed to lock save times")
+            .clear();
+    }
+
+    /// Drop one collection from the cache without saving it, so that a
+    /// collection deleted from collection.lock is not written back.
+    pub(crate) fn evict(collection_name: &str) {
+        let cache = Self::cache();
+        cache
+            .collections
+            .write()
+            .expect("Failed to lock memory cache")
+            .remove(collection_name);
+        cache
+            .saved_generations
+            .lock()
+            .expect("Failed to lock saved generations")
+            .remove(collection_name);
+        cache
+            .saved_at
+            .lock()
+            .expect("Failed to lock save times")
+            .remove(collection_name);
+    }
+
+    /// Cached handle for a collection, loading it from disk on first use and
+    /// applying its tiering/index settings.
+    pub fn shared(collection_name: &str) -> SharedEngine {
+        let core = AegCore::load();
+        let meta = core
+            .collection_meta
+            .get(collection_name)
+            .cloned()
+            .unwrap_or_default();
+        Self::shared_with_meta(collection_name, &meta)
+    }
+
+    /// Detached copies of the collections in `names` as of one moment: the
+    /// read locks of all of them are held together while they are copied,
+    /// so no write lands in one copy but not another. Writers wait only for
+    /// the in-memory copy; encrypting and writing the copies happens after
+    /// the locks are released. Cold entries are paged into the copies, and
+    /// unsaved changes are included.
+    pub fn capture_consistent(names: &[String]) -> Vec<AegMemoryEngine> {
+        let mut names = names.to_vec();
+        names.sort();
+        names.dedup();
+        let handles: Vec<SharedEngine> = names.iter().map(|name| Self::shared(name)).collect();
+        let engines: Vec<_> = handles
+            .iter()
+            .map(|handle| handle.read().expect("Failed to lock collection"))
+            .collect();
+        engines
+            .iter()
+            .map(|engine| engine.detached_copy())
+            .collect()
+    }
+
+    fn detached_copy(&self) -> Self {
+        let mut copy = Self::new(&self.collection_name);
+        copy.store = self.entries().into_iter().collect();
+        copy.key_meta = self.key_meta.clone();
+        copy.history = self
+            .history
+            .keys()
+            .map(|key| (key.clone(), self.history(key)))
+            .collect();
+        copy.generation = self.generation;
+        copy
+    }
+
+    /// Cached handle for the active collection.
+    pub fn shared_active() -> SharedEngine {
+        let core = AegCore::load();
+        let meta = core
+            .collection_meta
+            .get(&core.active_collection)
+            .cloned()
+            .unwrap_or_default();
+        Self::shared_with_meta(&core.active_collection, &meta)
+    }
+
+    fn shared_with_meta(collection_name: &str, meta: &CollectionMeta) -> SharedEngine {
+        if meta.seal.is_some() && !AegFileSystem::is_unsealed(collection_name) {
+            let mut locked = Self::new(collection_name);
+            locked.locked = true;
+            return Arc::new(RwLock::new(locked));
+        }
+        let handle = Self::cached_or_load(collection_name);
+        let needs_update = {
+            let engine = handle.read().expect("Failed to lock collection");
+            engine.warm_capacity != meta.warm_capacity
+                || engine.indexed != meta.indexed
+                || engine.is_sealed() != meta.sealed_values
+        };
+        if needs_update {
+            handle
+                .write()
+                .expect("Failed to lock collection")
+                .apply_meta(meta);
+        }
+        handle
+    }
+
+    /// Run `f` with exclusive access to a cached collection.
+    pub fn with_engine<R>(collection_name: &str, f: impl FnOnce(&mut AegMemoryEngine) -> R) -> R {
+        Self::write_through(Self::shared(collection_name), f)
     }
 
-    /// Save ALL collections currently in memory to disk.
-    /// This function clones the cache under the mutex and performs expensive work outside the lock.
-    pub fn save_all() {
-        // 1) Clone the memory map under the lock (minimize lock time)
-        let snapshot: HashMap<String, AegMemoryEngine> = {
-            let mutex = Self::global_memory_mutex();
-            let guard = mutex.lock().expect("Failed to lock global memory mutex");
-            guard.clone()
+    /// Run `f` under the write lock of `handle`, then apply the persistence
+    /// policy if it changed anything. The lock is released first, so saving
+    /// never waits on the writer.
+    fn write_through<R>(handle: SharedEngine, f: impl FnOnce(&mut AegMemoryEngine) -> R) -> R {
+        let (out, changed) = {
+            let mut engine = handle.write().expect("Failed to lock collection");
+            let before = engine.generation;
+            let out = f(&mut engine);
+            let changed = (engine.generation != before).then(|| engine.collection_name.clone());
+            (out, changed)
         };
+        if let Some(name) = changed {
+            Self::note_write(&name);
+        }
+        out
+    }
 
-        // 2) For each collection, perform serialization/encryption/write outside the lock
-        for (_name, engine) in snapshot.into_iter() {
-            // best-effort: log errors but continue
-            if let Err(e) = Self::save_to_disk(&engine) {
-                eprintln!(
-                    "Failed to save collection '{}': {}",
-                    engine.collection_name, e
-                );
+    /// Run `f` with shared (read-only) access to a cached collection.
+    pub fn read_engine<R>(collection_name: &str, f: impl FnOnce(&AegMemoryEngine) -> R) -> R {
+        let handle = Self::shared(collection_name);
+        let engine = handle.read().expect("Failed to lock collection");
+        f(&engine)
+    }
+
+    /// `with_engine` for the active collection.
+    pub fn with_active<R>(f: impl FnOnce(&mut AegMemoryEngine) -> R) -> R {
+        Self::write_through(Self::shared_active(), f)
+    }
+
+    /// `read_engine` for the active collection.
+    pub fn read_active<R>(f: impl FnOnce(&AegMemoryEngine) -> R) -> R {
+        let handle = Self::shared_active();
+        let engine = handle.read().expect("Failed to lock collection");
+        f(&engine)
+    }
+
+    /// Read a key from a cached collection. Only takes the write lock when
+    /// tiering needs to record the access or page the value in.
+    pub fn fetch_shared(collection_name: &str, key: &str) -> Option<Arc<str>> {
+        let started = Instant::now();
+        let handle = Self::shared(collection_name);
+        let value = {
+            let engine = handle.read().expect("Failed to lock collection");
+            if engine.warm_capacity.is_none() {
+                engine.get_shared(key)
+            } else {
+                drop(engine);
+                handle
+                    .write()
+                    .expect("Failed to lock collection")
+                    .fetch(key)
             }
-        }
+        };
+        AegMetrics::record_get(started.elapsed(), value.is_some());
+        value
     }
 
-    /// Load engine from memory cache; otherwise load from disk; otherwise fresh engine.
+    /// Snapshot of the active collection's engine (a detached copy).
     pub fn load() -> Self {
-        let core = AegCore::load();
-        let collection_name = core.active_collection.clone();
+        Self::read_active(|engine| engine.clone())
+    }
 
-        // First try in-memory (global cache)
-        {
-            let mutex = Self::global_memory_mutex();
-            let guard = mutex.lock().expect("Failed to lock global memory mutex");
-            if let Some(engine) = guard.get(&collection_name).cloned() {
-                return engine;
+    /// Snapshot of a named collection's engine, regardless of which one is active.
+    pub fn load_collection(collection_name: &str) -> Self {
+        Self::read_engine(collection_name, |engine| engine.clone())
+    }
+
+    /// Apply the collection's tiering/index settings, paging entries in or out
+    /// and backfilling records when they changed.
+    fn apply_meta(&mut self, meta: &CollectionMeta) {
+        if self.is_sealed() != meta.sealed_values {
+            self.reseal(meta.sealed_values.then(ValueSealer::new));
+        }
+        if self.warm_capacity == meta.warm_capacity && self.indexed == meta.indexed {
+            return;
+        }
+        self.warm_capacity = meta.warm_capacity;
+        self.indexed = meta.indexed;
+        if self.warm_capacity.is_none() {
+            self.page_in_all();
+            if !self.indexed {
+                // nothing left to page from; the index is dropped on next save
+                self.cold_index.clear();
             }
         }
+        if self.indexed {
+            self.backfill_records();
+        }
+        self.enforce_warm_capacity();
+        // layout changed; persist it on the next save
+        self.generation += 1;
+    }
 
-        // If not in memory, load from disk
-        let path = Self::engine_file_path(&collection_name);
-
-        if path.exists() {
-            let encrypted = fs::read_to_string(&path).unwrap_or_default();
-            if encrypted.trim().is_empty() {
-                let engine = Self::new(&collection_name);
-                // store in memory
-                let mutex = Self::global_memory_mutex();
-                let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-                guard.insert(collection_name.clone(), engine.clone());
-                return engine;
+    /// Make sure every warm entry has a current record in the record file.
+    fn backfill_records(&mut self) {
+        let missing: Vec<(Arc<str>, Entry)> = self
+            .store
+            .iter()
+            .filter(|(k, _)| !self.cold_index.contains_key(&***k))
+            .map(|(k, e)| (Arc::clone(k), self.reveal_entry(e)))
+            .collect();
+        for (key, entry) in missing {
+            match self.write_cold(&key, &entry) {
+                Ok(loc) => {
+                    self.cold_index.insert(key.to_string(), loc);
+                }
+                Err(e) => error!(
+                    collection = %self.collection_name,
+                    key = %key,
+                    error = %e,
+                    "failed to index entry"
+                ),
             }
+        }
+    }
 
-            let auth_key = AegFileSystem::read_authorization_key();
-            let key_bytes = general_purpose::STANDARD
-                .decode(auth_key)
-                .expect("Invalid base64");
+    /// Bring every cold entry back into the warm tier (used when tiering is disabled).
+    fn page_in_all(&mut self) {
+        let cold: Vec<(String, ColdLocation)> = self
+            .cold_index
+            .iter()
+            .filter(|(k, _)| !self.store.contains_key(k.as_str()))
+            .map(|(k, loc)| (k.clone(), *loc))
+            .collect();
+        for (key, loc) in cold {
+            match self.read_cold(&key, loc) {
+                Ok(v) => {
+                    let v = self.seal_entry(v);
+                    self.store.insert(key.into(), v);
+                }
+                Err(e) => error!(
+                    collection = %self.collection_name,
+                    key,
+                    error = %e,
+                    "failed to page in entry"
+                ),
+            }
+        }
+    }
 
-            let key: &aes_gcm::Key<Aes256Gcm> = aes_gcm::Key::<Aes256Gcm>::from_slice(&key_bytes);
-            let cipher = Aes256Gcm::new(key);
+    /// Cached handle if present; otherwise load from disk (outside any lock)
+    /// and publish it, keeping whichever copy won a concurrent first load.
+    fn cached_or_load(collection_name: &str) -> SharedEngine {
+        let cache = Self::cache();
+        {
+            let guard = cache
+                .collections
+                .read()
+                .expect("Failed to lock memory cache");
+            if let Some(handle) = guard.get(collection_name) {
+                debug!(collection = collection_name, "cache hit");
+                AegMetrics::record_cache(true);
+                return Arc::clone(handle);
+            }
+        }
 
-            let nonce = Nonce::from_slice(&key_bytes[..12]);
+        debug!(collection = collection_name, "cache miss");
+        AegMetrics::record_cache(false);
+        let engine = Self::load_from_disk(collection_name);
+        let mut guard = cache
+            .collections
+            .write()
+            .expect("Failed to lock memory cache");
+        Arc::clone(
+            guard
+                .entry(collection_name.to_string())
+                .or_insert_with(|| Arc::new(RwLock::new(engine))),
+        )
+    }
 
-            let decoded = general_purpose::STANDARD
-                .decode(encrypted)
-                .expect("Invalid base64");
+    /// Load engine from disk; otherwise fresh engine.
+    fn load_from_disk(collection_name: &str) -> Self {
+        let _span = debug_span!("load", collection = collection_name).entered();
+        let path = Self::engine_file_path(collection_name);
+        let storage = AegFileSystem::storage();
+        if !storage.exists(&path) {
+            return Self::new(collection_name);
+        }
 
-            let decrypted = cipher
-                .decrypt(nonce, decoded.as_ref())
-                .expect("Decrypt failed");
+        let _lock = AegFileSystem::lock_store(&AegFileSystem::get_config_path())
+            .unwrap_or_else(|e| panic!("{}", e));
+        let mut encrypted = storage.read(&path).unwrap_or_default();
+        if encrypted.is_empty() {
+            return Self::new(collection_name);
+        }
+        if let Ok(master_key) = AegFileSystem::try_read_authorization_key() {
+            let name = format!("collection_{}.aekv", collection_name);
+            AegIntegrity::check(
+                &AegFileSystem::get_config_path(),
+                &name,
+                &encrypted,
+                &master_key,
+            );
+        }
 
-            let engine: AegMemoryEngine =
-                serde_json::from_slice(&decrypted).unwrap_or(Self::new(&collection_name));
+        let (mut engine, auth_key) = match Self::open_collection_file(collection_name, &encrypted) {
+            Ok(opened) => opened,
+            Err(e) => {
+                // still panics when the store key itself is wrong
+                AegRecovery::recover_collection(collection_name, &path, &e)
+                    .unwrap_or_else(|e| panic!("Decrypt failed: {}", e));
+                encrypted = storage.read(&path).unwrap_or_default();
+                if encrypted.is_empty() {
+                    (
+                        Self::new(collection_name),
+                        Self::collection_key(collection_name),
+                    )
+                } else {
+                    Self::open_collection_file(collection_name, &encrypted)
+                        .unwrap_or_else(|e| panic!("Decrypt failed: {}", e))
+                }
+            }
+        };
 
-            // Store to in-memory cache
-            let mutex = Self::global_memory_mutex();
-            let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-            guard.insert(collection_name.clone(), engine.clone());
+        // Older files embedded the cold index; the .idx file takes precedence
+        match Self::load_index(collection_name, &auth_key) {
+            Ok(Some(index)) => engine.cold_index = index,
+            Ok(None) => {}
+            Err(e) => error!(error = %e, "failed to load index"),
+        }
 
-            return engine;
+        if auth_key != Self::collection_key(collection_name) {
+            if let Err(e) = engine.rewrite_under_current_key(&auth_key) {
+                error!(error = %e, "failed to migrate collection to its own key");
+            }
+        } else if AegFileFormat::needs_upgrade(&encrypted)
+            && let Err(e) = Self::save_to_disk(&engine)
+        {
+            error!(error = %e, "failed to upgrade collection to the current file format");
         }
 
-        // Fresh engine
-        let engine = Self::new(&collection_name);
-        let mutex = Self::global_memory_mutex();
-        let mut guard = mutex.lock().expect("Failed to lock global memory mutex");
-        guard.insert(collection_name.clone(), engine.clone());
+        debug!(
+            keys = engine.store.len() + engine.cold_index.len(),
+            "loaded collection"
+        );
         engine
     }
 
-    /// Start a background thread to periodically save memory to disk.
-    /// If already started, this is a no-op.
-    pub fn start_background_saver(interval_seconds: u64) {
-        // initialize the running flag (if not already)
-        let running = SAVER_RUNNING.get_or_init(|| AtomicBool::new(false));
-        let started_flag = SAVER_STARTED.get_or_init(|| AtomicBool::new(false));
+    /// Re-encrypt a collection whose files are under `old_key` (the master
+    /// key, or its subkey before it was sealed) with its current key: page
+    /// every cold record in with the old key, save, and only then drop the
+    /// old record file. Tiering and indexing rebuild their records under
+    /// the new key when the collection's settings are applied.
+    fn rewrite_under_current_key(&mut self, old_key: &str) -> Result<(), String> {
+        let cold: Vec<(String, ColdLocation)> = self
+            .cold_index
+            .iter()
+            .filter(|(k, _)| !self.store.contains_key(k.as_str()))
+            .map(|(k, loc)| (k.clone(), *loc))
+            .collect();
+        for (key, loc) in cold {
+            let entry = Self::read_cold_record(&self.collection_name, &key, loc, old_key)
+                .map_err(|e| format!("'{}', keeping the old key: {}", key, e))?;
+            let entry = self.seal_entry(entry);
+            self.store.insert(key.into(), entry);
+        }
+        self.cold_index.clear();
+        Self::save_to_disk(self)?;
+        let cold_path = Self::cold_file_path(&self.collection_name);
+        let storage = AegFileSystem::storage();
+        if storage.exists(&cold_path) {
+            let _ = storage.remove(&cold_path);
+            Self::record_in_manifest(&AegFileSystem::get_config_path(), &self.collection_name);
+        }
+        Ok(())
+    }
+
+    /// Re-encrypt every file of a loaded collection, now under `old_key`,
+    /// with the key it has now (used when it is sealed with a passphrase),
+    /// rebuilding its paged-out and indexed records under that key.
+    pub(crate) fn rekey_collection(collection_name: &str, old_key: &str) -> Result<(), String> {
+        Self::with_engine(collection_name, |engine| {
+            engine.rewrite_under_current_key(old_key)?;
+            if engine.indexed {
+                engine.backfill_records();
+            }
+            engine.enforce_warm_capacity();
+            engine.generation += 1;
+            Self::save_to_disk(engine)?;
+            Self::mark_saved(collection_name, engine.generation);
+            Ok(())
+        })
+    }
 
-        // if already started, do nothing
-        if started_flag.load(Ordering::SeqCst) {
-            return;
+    /// Write `data`, a copy of collection `old_name`, as collection
+    /// `new_name` under that name's key, then remove the files of
+    /// `old_name` and its backups (under the old name's key, so unreadable
+    /// as `new_name`). `new_name` must already be in collection.lock.
+    pub(crate) fn move_collection(
+        old_name: &str,
+        new_name: &str,
+        data: &AegMemoryEngine,
+    ) -> Result<(), String> {
+        Self::with_engine(new_name, |engine| {
+            engine.copy_from(data);
+            if engine.indexed {
+                engine.backfill_records();
+            }
+            Self::save_to_disk(engine)?;
+            Self::mark_saved(new_name, engine.generation);
+            Ok::<_, String>(())
+        })?;
+        Self::evict(old_name);
+        let dir = AegFileSystem::get_config_path();
+        let storage = AegFileSystem::storage();
+        let _lock = AegFileSystem::lock_store(&dir)?;
+        let backups = AegBackups::generations(&dir, old_name)
+            .into_iter()
+            .map(|generation| AegBackups::backup_file(&dir, old_name, generation));
+        for path in ["aekv", "idx", "cold"]
+            .map(|ext| Self::collection_file(&dir, old_name, ext))
+            .into_iter()
+            .chain(backups)
+        {
+            if storage.exists(&path) {
+                storage
+                    .remove(&path)
+                    .map_err(|e| format!("remove {}: {}", path.display(), e))?;
+            }
         }
+        Self::record_in_manifest(&dir, old_name);
+        Ok(())
+    }
+
+    /// The key `collection_name`'s files are under now.
+    pub(crate) fn current_file_key(collection_name: &str) -> Zeroizing<String> {
+        Self::collection_key(collection_name)
+    }
+
+    /// Start a background thread that saves the autosave collections every
+    /// `interval_seconds`. If a saver is already running, this returns a
+    /// handle to it instead. Dropping the handle leaves the saver running.
+    /// While the store is open read-only no saver is started and the
+    /// handle returned is not running.
+    pub fn start_background_saver(interval_seconds: u64) -> SaverHandle {
+        Self::start_saver(SaverMode::Interval(interval_seconds.max(1)))
+    }
 
-        // mark running
-        running.store(true, Ordering::SeqCst);
-        // mark started
-        started_flag.store(true, Ordering::SeqCst);
+    fn start_saver(mode: SaverMode) -> SaverHandle {
+        if AegFileSystem::is_read_only() {
+            // a handle that is not running: a read-only store is never saved
+            return SaverHandle {
+                id: SAVER_IDS.fetch_add(1, Ordering::SeqCst),
+                commands: mpsc::channel().0,
+                thread: Arc::new(Mutex::new(None)),
+                cache: Weak::new(),
+            };
+        }
+        let cache = Self::cache();
+        let mut current = cache.saver.lock().expect("Failed to lock background saver");
+        cache.save_every_write.store(false, Ordering::SeqCst);
+        if let Some(handle) = current.as_ref() {
+            return handle.clone();
+        }
 
-        // spawn detached thread
-        let running_ref: &'static AtomicBool = running;
-        thread::spawn(move || {
-            let interval = Duration::from_secs(interval_seconds.max(1));
-            while running_ref.load(Ordering::SeqCst) {
-                // save snapshot
-                Self::save_all();
-                // sleep for interval (cooperative)
-                sleep(interval);
+        mode.publish(&cache);
+        let (commands, inbox) = mpsc::channel();
+        // the saver works on the store it was started for
+        let context = StoreContext::current();
+        let thread = thread::spawn(move || {
+            let _context = context.map(StoreContext::enter);
+            let mut mode = mode;
+            // first and last write of the burst waiting to be saved
+            let mut pending: Option<(Instant, Instant)> = None;
+            loop {
+                let received = match (mode, pending) {
+                    (SaverMode::Interval(secs), _) => inbox.recv_timeout(Duration::from_secs(secs)),
+                    (SaverMode::Debounced(ms), Some((first, last))) => {
+                        let window = Duration::from_millis(ms);
+                        let due = (last + window).min(first + window * DEBOUNCE_MAX_WINDOWS);
+                        inbox.recv_timeout(due.saturating_duration_since(Instant::now()))
+                    }
+                    (SaverMode::Debounced(_), None) => {
+                        inbox.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                    }
+                };
+                match received {
+                    // collections with autosave disabled are skipped
+                    Err(RecvTimeoutError::Timeout) => {
+                        Self::save_autosave();
+                        pending = None;
+                    }
+                    Ok(SaverCommand::SetMode(next)) => mode = next,
+                    Ok(SaverCommand::Touched) => {
+                        let now = Instant::now();
+                        pending = Some((pending.map_or(now, |(first, _)| first), now));
+                    }
+                    Ok(SaverCommand::Flush(reply)) => {
+                        let _ = reply.send(Self::save_all());
+                        pending = None;
+                    }
+                    Ok(SaverCommand::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                }
             }
-            // final flush on exit attempt
-            Self::save_all();
+            // final flush on exit
+            Self::save_autosave();
         });
+        let handle = SaverHandle {
+            id: SAVER_IDS.fetch_add(1, Ordering::SeqCst),
+            commands,
+            thread: Arc::new(Mutex::new(Some(thread))),
+            cache: Arc::downgrade(&cache),
+        };
+        *current = Some(handle.clone());
+        handle
+    }
+
+    /// Seconds between background saves while the saver runs on an
+    /// interval; `None` when it is stopped or debounced.
+    pub fn background_saver_interval() -> Option<u64> {
+        match Self::persistence_policy() {
+            PersistencePolicy::Interval(secs) => Some(secs),
+            _ => None,
+        }
+    }
+
+    /// Switch to `policy`, starting, reconfiguring or stopping the
+    /// background saver as needed. Returns the saver for `Interval` and
+    /// `Debounced`. Changes made so far are saved when switching to
+    /// `EveryWrite`. Does nothing while the store is open read-only.
+    pub fn set_persistence_policy(policy: PersistencePolicy) -> Option<SaverHandle> {
+        if AegFileSystem::is_read_only() {
+            return None;
+        }
+        let mode = match policy {
+            PersistencePolicy::Interval(secs) => SaverMode::Interval(secs.max(1)),
+            PersistencePolicy::Debounced(ms) => SaverMode::Debounced(ms.max(1)),
+            PersistencePolicy::EveryWrite | PersistencePolicy::Manual => {
+                let cache = Self::cache();
+                let running = cache
+                    .saver
+                    .lock()
+                    .expect("Failed to lock background saver")
+                    .take();
+                if let Some(handle) = running {
+                    handle.shutdown();
+                }
+                let every_write = policy == PersistencePolicy::EveryWrite;
+                cache.save_every_write.store(every_write, Ordering::SeqCst);
+                if every_write {
+                    Self::save_autosave();
+                }
+                return None;
+            }
+        };
+        let handle = Self::start_saver(mode);
+        handle.set_mode(mode);
+        Some(handle)
+    }
+
+    /// The policy in effect: `Manual` unless a saver runs or `EveryWrite`
+    /// was chosen.
+    pub fn persistence_policy() -> PersistencePolicy {
+        let cache = Self::cache();
+        if cache.save_every_write.load(Ordering::SeqCst) {
+            return PersistencePolicy::EveryWrite;
+        }
+        if cache
+            .saver
+            .lock()
+            .expect("Failed to lock background saver")
+            .is_none()
+        {
+            return PersistencePolicy::Manual;
+        }
+        match cache.saver_debounce_ms.load(Ordering::SeqCst) {
+            0 => PersistencePolicy::Interval(cache.saver_interval.load(Ordering::SeqCst)),
+            ms => PersistencePolicy::Debounced(ms),
+        }
+    }
+
+    /// Called after `collection_name` changed in memory.
+    fn note_write(collection_name: &str) {
+        let cache = Self::cache();
+        if cache.save_every_write.load(Ordering::SeqCst) {
+            if !Self::saver_paused() && AegCore::load().is_autosave_enabled(collection_name) {
+                Self::save_dirty(|name| name == collection_name);
+            }
+            return;
+        }
+        if cache.saver_debounce_ms.load(Ordering::SeqCst) > 0
+            && let Some(handle) = cache
+                .saver
+                .lock()
+                .expect("Failed to lock background saver")
+                .as_ref()
+        {
+            let _ = handle.commands.send(SaverCommand::Touched);
+        }
     }
 
-    /// Signal the background saver to stop. Thread is detached so we can't join; this just signals termination.
+    /// Have the running background saver save once more and stop, and
+    /// wait for it (see `SaverHandle::shutdown`): nothing is written by it
+    /// once this returns.
     pub fn stop_background_saver() {
-        if let Some(running) = SAVER_RUNNING.get() {
-            running.store(false, Ordering::SeqCst);
+        let current = Self::cache()
+            .saver
+            .lock()
+            .expect("Failed to lock background saver")
+            .take();
+        if let Some(handle) = current {
+            handle.shutdown();
+        }
+    }
+
+    /// Suspend background saves until the returned guard is dropped.
+    pub fn pause_saver() -> SaverPause {
+        SAVER_PAUSES.fetch_add(1, Ordering::SeqCst);
+        SaverPause { _private: () }
+    }
+
+    /// Whether a `SaverPause` is outstanding.
+    pub fn saver_paused() -> bool {
+        SAVER_PAUSES.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// Keeps the background savers (thread and async) from saving while held,
+/// so a bulk import or migration is not encrypted and written over and over
+/// half-finished. Explicit flushes still save. Pauses nest; when the last
+/// one is dropped the autosave collections are saved once.
+#[must_use = "the saver resumes as soon as the guard is dropped"]
+pub struct SaverPause {
+    _private: (),
+}
+
+impl SaverPause {
+    /// Resume now; returns how many collections the consolidated save
+    /// wrote (0 while other pauses are still held).
+    pub fn resume(self) -> usize {
+        let last = Self::release();
+        std::mem::forget(self);
+        if last {
+            AegMemoryEngine::save_autosave()
+        } else {
+            0
+        }
+    }
+
+    /// Drop this pause; whether it was the last one.
+    fn release() -> bool {
+        SAVER_PAUSES.fetch_sub(1, Ordering::SeqCst) == 1
+    }
+}
+
+impl Drop for SaverPause {
+    fn drop(&mut self) {
+        if Self::release() {
+            AegMemoryEngine::save_autosave();
+        }
+    }
+}
+
+/// When changes held in memory are written to disk (see
+/// `AegCore::set_persistence_policy`). Collections with autosave disabled
+/// are only saved by `flush_now` under every policy, and a `SaverPause`
+/// holds back saves under every policy.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", content = "value", rename_all = "kebab-case")]
+pub enum PersistencePolicy {
+    /// The background saver saves every so many seconds.
+    Interval(u64),
+    /// The background saver saves once no change has been made for so many
+    /// milliseconds, so a burst of writes is saved once, shortly after it
+    /// ends. Under a steady stream of writes it still saves at least every
+    /// `DEBOUNCE_MAX_WINDOWS` windows.
+    Debounced(u64),
+    /// Every change is saved before the call that made it returns.
+    EveryWrite,
+    /// Nothing is saved until `flush_now`.
+    Manual,
+}
+
+#[derive(Clone, Copy)]
+enum SaverMode {
+    /// Seconds between saves.
+    Interval(u64),
+    /// Milliseconds without writes before a save.
+    Debounced(u64),
+}
+
+impl SaverMode {
+    /// Make this the mode `persistence_policy` reports for `cache`.
+    fn publish(self, cache: &MemoryCache) {
+        match self {
+            Self::Interval(secs) => {
+                cache.saver_interval.store(secs, Ordering::SeqCst);
+                cache.saver_debounce_ms.store(0, Ordering::SeqCst);
+            }
+            Self::Debounced(ms) => cache.saver_debounce_ms.store(ms, Ordering::SeqCst),
+        }
+    }
+}
+
+enum SaverCommand {
+    SetMode(SaverMode),
+    /// A collection changed; restarts the debounce window.
+    Touched,
+    Flush(mpsc::Sender<usize>),
+    Stop,
+}
+
+/// Control of the background saver thread started by
+/// `start_background_saver`. Clones control the same thread.
+#[derive(Clone)]
+pub struct SaverHandle {
+    id: u64,
+    commands: mpsc::Sender<SaverCommand>,
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// The cache whose collections it saves.
+    cache: Weak<MemoryCache>,
+}
+
+impl SaverHandle {
+    /// Wake the saver to save once more and exit, without waiting out the
+    /// current interval. Returns immediately.
+    pub fn stop(&self) {
+        if let Some(cache) = self.cache.upgrade() {
+            let mut current = cache.saver.lock().expect("Failed to lock background saver");
+            if current.as_ref().is_some_and(|h| h.id == self.id) {
+                *current = None;
+            }
+        }
+        let _ = self.commands.send(SaverCommand::Stop);
+    }
+
+    /// Wait for the saver thread to exit, which it does after `stop`
+    /// (here or through `stop_background_saver`) once its final save is
+    /// written. Returns at once if another clone already joined it, or
+    /// when called from the saver thread itself.
+    pub fn join(&self) {
+        let mut slot = self.thread.lock().expect("Failed to lock saver thread");
+        if slot
+            .as_ref()
+            .is_some_and(|t| t.thread().id() == thread::current().id())
+        {
+            return;
+        }
+        let thread = slot.take();
+        drop(slot);
+        if let Some(thread) = thread
+            && let Err(panic) = thread.join()
+        {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    /// `stop`, then `join`: every autosave collection is on disk when this
+    /// returns.
+    pub fn shutdown(&self) {
+        self.stop();
+        self.join();
+    }
+
+    /// Save every dirty collection on the saver thread and wait for it;
+    /// returns how many were written. Saves on the calling thread if the
+    /// saver has stopped.
+    pub fn flush_now(&self) -> usize {
+        let (reply, answer) = mpsc::channel();
+        if self.commands.send(SaverCommand::Flush(reply)).is_ok()
+            && let Ok(written) = answer.recv()
+        {
+            return written;
         }
-        if let Some(started) = SAVER_STARTED.get() {
-            started.store(false, Ordering::SeqCst);
+        AegMemoryEngine::save_all()
+    }
+
+    /// Save every `interval_seconds` from now on, starting a new wait.
+    pub fn set_interval(&self, interval_seconds: u64) {
+        self.set_mode(SaverMode::Interval(interval_seconds.max(1)));
+    }
+
+    /// Save `window_ms` after the last write from now on (see
+    /// `PersistencePolicy::Debounced`).
+    pub fn set_debounce(&self, window_ms: u64) {
+        self.set_mode(SaverMode::Debounced(window_ms.max(1)));
+    }
+
+    fn set_mode(&self, mode: SaverMode) {
+        if self.commands.send(SaverCommand::SetMode(mode)).is_ok()
+            && let Some(cache) = self.cache.upgrade()
+        {
+            mode.publish(&cache);
         }
     }
+
+    /// Whether the saver thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .lock()
+            .expect("Failed to lock saver thread")
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
 }
 
 // ===================== USAGE GUIDE =====================
 //
 // During startup:
 // AegFileSystem::initialize_config(None, None);   // prepares configuration files
-// AegCore::start_background_saver(1);             // enables automatic persistence (1-second interval)
+// let saver = AegCore::start_background_saver(1); // enables automatic persistence (1-second interval)
+// // or: AegCore::set_persistence_policy(PersistencePolicy::Debounced(200)); // 200 ms after the last write
 //
 // Normal operations use:
 // AegCore::put_value(...);
@@ -254,5 +2649,5 @@ impl AegMemoryEngine {
 // AegCore::flush_now();
 //
 // At application shutdown:
-// AegCore::stop_background_saver();               // stops the background thread
-// AegCore::flush_now();                           // optional final save
+// saver.shutdown();                               // stops the thread after a final save
+// AegCore::flush_now();                           // saves collections with autosave disabled
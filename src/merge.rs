@@ -0,0 +1,188 @@
+use crate::audit::{AegAudit, AuditAction};
+use crate::core::AegCore;
+use crate::env::AegEnv;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How `AegMerge` treats a key that both collections hold with different
+/// values.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Keep the destination's value.
+    KeepDest,
+    /// Take the source's value.
+    Overwrite,
+    /// Change nothing if any key conflicts.
+    #[default]
+    FailOnConflict,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep-dest" | "keep" => Ok(Self::KeepDest),
+            "overwrite" => Ok(Self::Overwrite),
+            "fail-on-conflict" | "fail" => Ok(Self::FailOnConflict),
+            other => Err(format!(
+                "unknown merge strategy '{}' (expected keep-dest, overwrite or fail-on-conflict)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeepDest => write!(f, "keep-dest"),
+            Self::Overwrite => write!(f, "overwrite"),
+            Self::FailOnConflict => write!(f, "fail-on-conflict"),
+        }
+    }
+}
+
+/// A key held by both collections with different values. Values are left
+/// out so a report can be printed or logged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub key: String,
+    /// Unix seconds of the last change on each side, when known.
+    pub src_updated_at: Option<u64>,
+    pub dest_updated_at: Option<u64>,
+    /// Whether the destination now holds the source's value.
+    pub overwritten: bool,
+}
+
+/// What a merge changed in the destination collection.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub src: String,
+    pub dest: String,
+    pub strategy: MergeStrategy,
+    /// Keys copied from the source that the destination did not have.
+    pub added: Vec<String>,
+    /// Keys both held with the same value.
+    pub unchanged: usize,
+    pub conflicts: Vec<MergeConflict>,
+    /// Set when `FailOnConflict` found conflicts; nothing was changed.
+    pub aborted: bool,
+}
+
+impl MergeReport {
+    pub fn summary(&self) -> String {
+        let mut out = if self.aborted {
+            format!(
+                "✗ Merge of '{}' into '{}' aborted: {} conflicting keys (choose --strategy keep-dest or overwrite)",
+                self.src,
+                self.dest,
+                self.conflicts.len()
+            )
+        } else {
+            format!(
+                "✓ Merged '{}' into '{}': {} added, {} unchanged, {} conflicts",
+                self.src,
+                self.dest,
+                self.added.len(),
+                self.unchanged,
+                self.conflicts.len()
+            )
+        };
+        for c in &self.conflicts {
+            out.push_str(&format!(
+                "\n⚠ {}: {}",
+                c.key,
+                if self.aborted {
+                    "values differ"
+                } else if c.overwritten {
+                    "took the value of the source"
+                } else {
+                    "kept the value of the destination"
+                }
+            ));
+        }
+        out
+    }
+}
+
+/// One-way merge of one collection of the store into another. Keys the
+/// destination lacks are copied with their timestamps, tags and per-key
+/// settings; keys both hold with different values are settled by a
+/// `MergeStrategy`. The source is left as it is, and nothing is deleted.
+///
+/// The destination is changed under one write lock, so readers see the
+/// merge whole or not at all.
+pub struct AegMerge;
+
+impl AegMerge {
+    pub fn merge(src: &str, dest: &str, strategy: MergeStrategy) -> Result<MergeReport, String> {
+        AegFileSystem::ensure_writable()?;
+        if src == dest {
+            return Err("Cannot merge a collection into itself".into());
+        }
+        let core = AegCore::load();
+        for name in [src, dest] {
+            if !core.collections.iter().any(|c| c == name) {
+                return Err(format!("Collection '{}' does not exist", name));
+            }
+            core.ensure_unsealed(name)?;
+        }
+        let source = AegMemoryEngine::capture_consistent(&[src.to_string()])
+            .pop()
+            .ok_or_else(|| format!("Collection '{}' could not be read", src))?;
+        let mut entries = source.entries();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut report = MergeReport {
+            src: src.to_string(),
+            dest: dest.to_string(),
+            strategy,
+            ..MergeReport::default()
+        };
+        AegMemoryEngine::with_engine(dest, |engine| {
+            let mut copy = Vec::new();
+            for (key, entry) in entries {
+                match engine.entry(&key) {
+                    None => {
+                        report.added.push(key.to_string());
+                        copy.push((key, entry));
+                    }
+                    Some(ours) if ours.value == entry.value => report.unchanged += 1,
+                    Some(ours) => {
+                        let overwritten = strategy == MergeStrategy::Overwrite;
+                        report.conflicts.push(MergeConflict {
+                            key: key.to_string(),
+                            src_updated_at: entry.updated_at,
+                            dest_updated_at: ours.updated_at,
+                            overwritten,
+                        });
+                        if overwritten {
+                            copy.push((key, entry));
+                        }
+                    }
+                }
+            }
+            if strategy == MergeStrategy::FailOnConflict && !report.conflicts.is_empty() {
+                report.aborted = true;
+                return;
+            }
+            for (key, entry) in copy {
+                engine.insert_entry(&key, entry);
+                if let Some(meta) = source.key_meta.get(&*key) {
+                    let mut meta = meta.clone();
+                    meta.env_name = meta.env_name.filter(|n| AegEnv::is_valid_name(n));
+                    engine.key_meta.insert(key.to_string(), meta);
+                }
+            }
+        });
+        if !report.aborted {
+            AegAudit::record_collection(dest, AuditAction::MergeCollection, Some(src));
+        }
+        Ok(report)
+    }
+}
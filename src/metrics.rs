@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Count, total and slowest time of one kind of operation.
+struct Timer {
+    count: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl Timer {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total_micros: AtomicU64::new(0),
+            max_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot {
+            count: self.count.load(Ordering::Relaxed),
+            total_micros: self.total_micros.load(Ordering::Relaxed),
+            max_micros: self.max_micros.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_micros.store(0, Ordering::Relaxed);
+        self.max_micros.store(0, Ordering::Relaxed);
+    }
+}
+
+struct Registry {
+    puts: Timer,
+    gets: Timer,
+    get_misses: AtomicU64,
+    deletes: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    saves: Timer,
+    save_failures: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+static METRICS: Registry = Registry {
+    puts: Timer::new(),
+    gets: Timer::new(),
+    get_misses: AtomicU64::new(0),
+    deletes: AtomicU64::new(0),
+    cache_hits: AtomicU64::new(0),
+    cache_misses: AtomicU64::new(0),
+    saves: Timer::new(),
+    save_failures: AtomicU64::new(0),
+    bytes_written: AtomicU64::new(0),
+};
+
+/// How often an operation ran and how long it took, in microseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+}
+
+impl LatencySnapshot {
+    pub fn mean_micros(&self) -> u64 {
+        self.total_micros.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+/// The counters of `AegMetrics` at one moment, from
+/// `AegCore::metrics_snapshot`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Values stored in memory.
+    pub puts: LatencySnapshot,
+    /// Values read, found or not.
+    pub gets: LatencySnapshot,
+    /// Reads of keys that do not exist.
+    pub get_misses: u64,
+    pub deletes: u64,
+    /// Collections found already loaded.
+    pub cache_hits: u64,
+    /// Collections loaded from their files.
+    pub cache_misses: u64,
+    /// Collection files written.
+    pub saves: LatencySnapshot,
+    pub save_failures: u64,
+    /// Bytes of collection files written.
+    pub bytes_written: u64,
+}
+
+impl MetricsSnapshot {
+    /// One line per counter, for `aegisr metrics`.
+    pub fn summary(&self) -> String {
+        let timed = |name: &str, latency: &LatencySnapshot| {
+            format!(
+                "{:<14}{} (mean {} µs, max {} µs)",
+                name,
+                latency.count,
+                latency.mean_micros(),
+                latency.max_micros
+            )
+        };
+        [
+            timed("puts", &self.puts),
+            timed("gets", &self.gets),
+            format!("{:<14}{}", "get misses", self.get_misses),
+            format!("{:<14}{}", "deletes", self.deletes),
+            format!("{:<14}{}", "cache hits", self.cache_hits),
+            format!("{:<14}{}", "cache misses", self.cache_misses),
+            timed("saves", &self.saves),
+            format!("{:<14}{}", "save failures", self.save_failures),
+            format!("{:<14}{}", "bytes written", self.bytes_written),
+        ]
+        .join("\n")
+    }
+
+    /// The Prometheus text exposition format, as served at `GET /metrics`.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = write!(
+                out,
+                "# HELP aegisr_{name} {help}\n# TYPE aegisr_{name} counter\naegisr_{name} {value}\n"
+            );
+        };
+        counter(
+            "get_misses_total",
+            "Reads of keys that do not exist.",
+            self.get_misses,
+        );
+        counter("deletes_total", "Keys deleted.", self.deletes);
+        counter(
+            "cache_hits_total",
+            "Collections found already loaded.",
+            self.cache_hits,
+        );
+        counter(
+            "cache_misses_total",
+            "Collections loaded from their files.",
+            self.cache_misses,
+        );
+        counter(
+            "save_failures_total",
+            "Collection saves that failed.",
+            self.save_failures,
+        );
+        counter(
+            "bytes_written_total",
+            "Bytes of collection files written.",
+            self.bytes_written,
+        );
+        for (name, help, latency) in [
+            (
+                "put_duration_seconds",
+                "Time spent storing values in memory.",
+                &self.puts,
+            ),
+            (
+                "get_duration_seconds",
+                "Time spent reading values.",
+                &self.gets,
+            ),
+            (
+                "save_duration_seconds",
+                "Time spent writing collection files.",
+                &self.saves,
+            ),
+        ] {
+            let _ = write!(
+                out,
+                "# HELP aegisr_{name} {help}\n# TYPE aegisr_{name} summary\n\
+                 aegisr_{name}_sum {}\naegisr_{name}_count {}\n",
+                latency.total_micros as f64 / 1e6,
+                latency.count
+            );
+        }
+        out
+    }
+}
+
+/// Operation counters and latencies of this process (reset on restart):
+/// puts, gets, cache hits and misses, saves and the bytes they wrote.
+/// Recorded by the engine, whichever way it is reached (library, CLI,
+/// HTTP API, daemon).
+pub struct AegMetrics;
+
+impl AegMetrics {
+    pub fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
+            puts: METRICS.puts.snapshot(),
+            gets: METRICS.gets.snapshot(),
+            get_misses: METRICS.get_misses.load(Ordering::Relaxed),
+            deletes: METRICS.deletes.load(Ordering::Relaxed),
+            cache_hits: METRICS.cache_hits.load(Ordering::Relaxed),
+            cache_misses: METRICS.cache_misses.load(Ordering::Relaxed),
+            saves: METRICS.saves.snapshot(),
+            save_failures: METRICS.save_failures.load(Ordering::Relaxed),
+            bytes_written: METRICS.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start every counter over from zero.
+    pub fn reset() {
+        METRICS.puts.reset();
+        METRICS.gets.reset();
+        METRICS.saves.reset();
+        for counter in [
+            &METRICS.get_misses,
+            &METRICS.deletes,
+            &METRICS.cache_hits,
+            &METRICS.cache_misses,
+            &METRICS.save_failures,
+            &METRICS.bytes_written,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_put(elapsed: Duration) {
+        METRICS.puts.record(elapsed);
+    }
+
+    pub(crate) fn record_get(elapsed: Duration, found: bool) {
+        METRICS.gets.record(elapsed);
+        if !found {
+            METRICS.get_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_delete() {
+        METRICS.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache(hit: bool) {
+        let counter = if hit {
+            &METRICS.cache_hits
+        } else {
+            &METRICS.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_save(elapsed: Duration, bytes: u64) {
+        METRICS.saves.record(elapsed);
+        METRICS.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_save_failure() {
+        METRICS.save_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,143 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How the keys of a collection must be named. Checked by `put` for new
+/// keys and by `rename_key` for the new name; `/` separates namespaces in
+/// every named convention.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum KeyConvention {
+    /// `db/api_key`
+    Snake,
+    /// `db/api-key`
+    Kebab,
+    /// `DB/API_KEY`
+    Screaming,
+    /// Keys the regular expression matches in full, written `regex:<pattern>`.
+    Pattern(String),
+}
+
+impl FromStr for KeyConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(pattern) = s.strip_prefix("regex:") {
+            Regex::new(pattern).map_err(|e| format!("invalid key pattern: {}", e))?;
+            return Ok(Self::Pattern(pattern.to_string()));
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "snake" | "snake_case" => Ok(Self::Snake),
+            "kebab" | "kebab-case" => Ok(Self::Kebab),
+            "screaming" | "screaming_snake_case" => Ok(Self::Screaming),
+            other => Err(format!(
+                "unknown key convention '{}' (expected snake, kebab, screaming or regex:<pattern>)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeyConvention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Snake => write!(f, "snake"),
+            Self::Kebab => write!(f, "kebab"),
+            Self::Screaming => write!(f, "screaming"),
+            Self::Pattern(pattern) => write!(f, "regex:{}", pattern),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyConvention {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<KeyConvention> for String {
+    fn from(convention: KeyConvention) -> Self {
+        convention.to_string()
+    }
+}
+
+impl KeyConvention {
+    /// Reject a key that does not follow the convention, suggesting a
+    /// fixed name when one can be derived.
+    pub fn check(&self, key: &str) -> Result<(), String> {
+        let follows = match self {
+            Self::Pattern(pattern) => Regex::new(&format!("^(?:{})$", pattern))
+                .map(|re| re.is_match(key))
+                .unwrap_or(false),
+            _ => self.fix(key).as_deref() == Some(key),
+        };
+        if follows {
+            return Ok(());
+        }
+        let mut msg = format!("key '{}' does not follow the {} convention", key, self);
+        if let Some(fixed) = self.fix(key) {
+            msg.push_str(&format!(" (try '{}')", fixed));
+        }
+        Err(msg)
+    }
+
+    /// `key` rewritten to follow the convention: each namespace is split
+    /// into words at separators and camelCase boundaries, which are then
+    /// re-joined. `None` for a pattern, or a key with no letters or digits.
+    pub fn fix(&self, key: &str) -> Option<String> {
+        let (separator, upper) = match self {
+            Self::Snake => ("_", false),
+            Self::Kebab => ("-", false),
+            Self::Screaming => ("_", true),
+            Self::Pattern(_) => return None,
+        };
+        let segments: Vec<String> = key
+            .split('/')
+            .map(|segment| {
+                let words: Vec<String> = Self::words(segment)
+                    .into_iter()
+                    .map(|w| {
+                        if upper {
+                            w.to_uppercase()
+                        } else {
+                            w.to_lowercase()
+                        }
+                    })
+                    .collect();
+                words.join(separator)
+            })
+            .filter(|s| !s.is_empty())
+            .collect();
+        (!segments.is_empty()).then(|| segments.join("/"))
+    }
+
+    /// `apiKey`, `api_key`, `API-KEY` and `APIKey` all split into two words.
+    fn words(segment: &str) -> Vec<String> {
+        let chars: Vec<char> = segment.chars().collect();
+        let mut words = Vec::new();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if !c.is_alphanumeric() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            if c.is_uppercase() && !word.is_empty() {
+                let prev = chars[i - 1];
+                let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if !prev.is_uppercase() || next_is_lower {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+}
@@ -0,0 +1,249 @@
+//! The platform secret stores, reached without linking against their
+//! client libraries: the `security` tool on macOS, `secret-tool` (the
+//! Secret Service, as run by GNOME Keyring or KWallet) on other Unix
+//! systems, and the Credential Manager API on Windows. Secrets are passed
+//! on standard input or through the API, never on a command line.
+
+#[cfg(target_os = "macos")]
+pub(crate) use macos::{delete, get, set};
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) use secret_service::{delete, get, set};
+#[cfg(windows)]
+pub(crate) use windows::{delete, get, set};
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn get(_service: &str, _account: &str) -> Result<Option<String>, String> {
+    Err("no OS keyring on this platform".into())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn set(_service: &str, _account: &str, _secret: &str) -> Result<(), String> {
+    Err("no OS keyring on this platform".into())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn delete(_service: &str, _account: &str) -> Result<(), String> {
+    Err("no OS keyring on this platform".into())
+}
+
+/// Run `program` with `args`, feeding it `input`; returns its exit code and
+/// standard output, or the error it printed.
+#[cfg(unix)]
+fn run(program: &str, args: &[&str], input: Option<&str>) -> Result<(i32, String), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("run {}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.unwrap_or_default().as_bytes())
+            .map_err(|e| format!("write to {}: {}", program, e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("run {}: {}", program, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    match output.status.code() {
+        Some(code) => Ok((code, stdout)),
+        None => Err(format!("{} was killed", program)),
+    }
+}
+
+#[cfg(unix)]
+fn failure(program: &str, code: i32) -> String {
+    format!("{} exited with status {}", program, code)
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::{failure, run};
+
+    /// `security` exits with this when the item does not exist.
+    const ITEM_NOT_FOUND: i32 = 44;
+
+    pub(crate) fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        let args = ["find-generic-password", "-s", service, "-a", account, "-w"];
+        match run("security", &args, None)? {
+            (0, out) => Ok(Some(out.trim_end_matches('\n').to_string())),
+            (ITEM_NOT_FOUND, _) => Ok(None),
+            (code, _) => Err(failure("security", code)),
+        }
+    }
+
+    /// `security -i` reads the command from standard input, which keeps
+    /// the secret out of the process list. Service, account and secret are
+    /// base64 and identifiers, which need no quoting beyond the quotes.
+    pub(crate) fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let command = format!(
+            "add-generic-password -U -s \"{}\" -a \"{}\" -w \"{}\"\n",
+            service, account, secret
+        );
+        match run("security", &["-i"], Some(&command))? {
+            (0, _) => Ok(()),
+            (code, _) => Err(failure("security", code)),
+        }
+    }
+
+    pub(crate) fn delete(service: &str, account: &str) -> Result<(), String> {
+        let args = ["delete-generic-password", "-s", service, "-a", account];
+        match run("security", &args, None)? {
+            (0, _) | (ITEM_NOT_FOUND, _) => Ok(()),
+            (code, _) => Err(failure("security", code)),
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod secret_service {
+    use super::{failure, run};
+
+    pub(crate) fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        let args = ["lookup", "service", service, "account", account];
+        match run("secret-tool", &args, None)? {
+            (0, out) if !out.is_empty() => Ok(Some(out.trim_end_matches('\n').to_string())),
+            // secret-tool reports a missing item as a failure with no output
+            (0, _) | (1, _) => Ok(None),
+            (code, _) => Err(failure("secret-tool", code)),
+        }
+    }
+
+    pub(crate) fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let label = format!("aegisr key ({})", account);
+        let args = [
+            "store", "--label", &label, "service", service, "account", account,
+        ];
+        match run("secret-tool", &args, Some(secret))? {
+            (0, _) => Ok(()),
+            (code, _) => Err(failure("secret-tool", code)),
+        }
+    }
+
+    pub(crate) fn delete(service: &str, account: &str) -> Result<(), String> {
+        let args = ["clear", "service", service, "account", account];
+        match run("secret-tool", &args, None)? {
+            (0, _) | (1, _) => Ok(()),
+            (code, _) => Err(failure("secret-tool", code)),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr;
+
+    const CRED_TYPE_GENERIC: u32 = 1;
+    const CRED_PERSIST_LOCAL_MACHINE: u32 = 2;
+    const ERROR_NOT_FOUND: i32 = 1168;
+
+    #[repr(C)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+
+    #[repr(C)]
+    struct CredentialW {
+        flags: u32,
+        kind: u32,
+        target_name: *mut u16,
+        comment: *mut u16,
+        last_written: FileTime,
+        credential_blob_size: u32,
+        credential_blob: *mut u8,
+        persist: u32,
+        attribute_count: u32,
+        attributes: *mut c_void,
+        target_alias: *mut u16,
+        user_name: *mut u16,
+    }
+
+    #[link(name = "advapi32")]
+    unsafe extern "system" {
+        fn CredReadW(target: *const u16, kind: u32, flags: u32, out: *mut *mut CredentialW) -> i32;
+        fn CredWriteW(credential: *const CredentialW, flags: u32) -> i32;
+        fn CredDeleteW(target: *const u16, kind: u32, flags: u32) -> i32;
+        fn CredFree(buffer: *mut c_void);
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn target(service: &str, account: &str) -> Vec<u16> {
+        wide(&format!("{}:{}", service, account))
+    }
+
+    pub(crate) fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        let target = target(service, account);
+        let mut credential: *mut CredentialW = ptr::null_mut();
+        // SAFETY: `target` is NUL-terminated; on success `credential` points
+        // to a buffer owned by the system until `CredFree`.
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(ERROR_NOT_FOUND) => Ok(None),
+                _ => Err(format!("CredReadW: {}", error)),
+            };
+        }
+        // SAFETY: the blob is `credential_blob_size` bytes long.
+        let secret = unsafe {
+            let blob = std::slice::from_raw_parts(
+                (*credential).credential_blob,
+                (*credential).credential_blob_size as usize,
+            );
+            let secret = String::from_utf8(blob.to_vec());
+            CredFree(credential.cast());
+            secret
+        };
+        secret
+            .map(Some)
+            .map_err(|e| format!("credential is not UTF-8: {}", e))
+    }
+
+    pub(crate) fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let mut target = target(service, account);
+        let mut user = wide(account);
+        let mut blob = secret.as_bytes().to_vec();
+        let credential = CredentialW {
+            flags: 0,
+            kind: CRED_TYPE_GENERIC,
+            target_name: target.as_mut_ptr(),
+            comment: ptr::null_mut(),
+            last_written: FileTime { low: 0, high: 0 },
+            credential_blob_size: blob.len() as u32,
+            credential_blob: blob.as_mut_ptr(),
+            persist: CRED_PERSIST_LOCAL_MACHINE,
+            attribute_count: 0,
+            attributes: ptr::null_mut(),
+            target_alias: ptr::null_mut(),
+            user_name: user.as_mut_ptr(),
+        };
+        // SAFETY: every pointer in `credential` outlives the call.
+        let written = unsafe { CredWriteW(&credential, 0) };
+        zeroize::Zeroize::zeroize(&mut blob);
+        match written {
+            0 => Err(format!("CredWriteW: {}", io::Error::last_os_error())),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn delete(service: &str, account: &str) -> Result<(), String> {
+        let target = target(service, account);
+        // SAFETY: `target` is NUL-terminated.
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(ERROR_NOT_FOUND) {
+                return Err(format!("CredDeleteW: {}", error));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+//! Just enough of the PKCS#11 (Cryptoki 2.40) C API to HMAC a message with
+//! a secret key kept on a token. The vendor module is loaded at run time
+//! with `dlopen`, so nothing links against a particular HSM library.
+
+use crate::constant::HSM_PIN_ENV;
+use crate::hsm::HsmConfig;
+use std::ffi::{CStr, CString, c_void};
+use std::os::raw::c_ulong;
+use std::ptr;
+
+type CkUlong = c_ulong;
+type CkRv = CkUlong;
+type Unused = Option<unsafe extern "C" fn()>;
+
+const CKR_OK: CkRv = 0x000;
+const CKR_SLOT_ID_INVALID: CkRv = 0x003;
+const CKR_DEVICE_ERROR: CkRv = 0x030;
+const CKR_DEVICE_REMOVED: CkRv = 0x032;
+const CKR_PIN_INCORRECT: CkRv = 0x0a0;
+const CKR_PIN_LOCKED: CkRv = 0x0a4;
+const CKR_TOKEN_NOT_PRESENT: CkRv = 0x0e0;
+const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x100;
+const CKR_USER_NOT_LOGGED_IN: CkRv = 0x101;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x191;
+
+const CKF_OS_LOCKING_OK: CkUlong = 0x2;
+const CKF_SERIAL_SESSION: CkUlong = 0x4;
+const CKU_USER: CkUlong = 1;
+const CKA_CLASS: CkUlong = 0x000;
+const CKA_LABEL: CkUlong = 0x003;
+const CKO_SECRET_KEY: CkUlong = 0x004;
+const CKM_SHA256_HMAC: CkUlong = 0x251;
+
+#[repr(C)]
+struct CkVersion {
+    major: u8,
+    minor: u8,
+}
+
+#[repr(C)]
+struct CkInitializeArgs {
+    create_mutex: *mut c_void,
+    destroy_mutex: *mut c_void,
+    lock_mutex: *mut c_void,
+    unlock_mutex: *mut c_void,
+    flags: CkUlong,
+    reserved: *mut c_void,
+}
+
+#[repr(C)]
+struct CkAttribute {
+    kind: CkUlong,
+    value: *mut c_void,
+    len: CkUlong,
+}
+
+#[repr(C)]
+struct CkMechanism {
+    mechanism: CkUlong,
+    parameter: *mut c_void,
+    parameter_len: CkUlong,
+}
+
+/// `CK_FUNCTION_LIST` up to `C_Sign`; the entries after it are never read.
+#[repr(C)]
+struct CkFunctionList {
+    version: CkVersion,
+    initialize: Option<unsafe extern "C" fn(*mut c_void) -> CkRv>,
+    finalize: Option<unsafe extern "C" fn(*mut c_void) -> CkRv>,
+    get_info: Unused,
+    get_function_list: Unused,
+    get_slot_list: Option<unsafe extern "C" fn(u8, *mut CkUlong, *mut CkUlong) -> CkRv>,
+    get_slot_info: Unused,
+    get_token_info: Unused,
+    get_mechanism_list: Unused,
+    get_mechanism_info: Unused,
+    init_token: Unused,
+    init_pin: Unused,
+    set_pin: Unused,
+    open_session: Option<
+        unsafe extern "C" fn(CkUlong, CkUlong, *mut c_void, *mut c_void, *mut CkUlong) -> CkRv,
+    >,
+    close_session: Option<unsafe extern "C" fn(CkUlong) -> CkRv>,
+    close_all_sessions: Unused,
+    get_session_info: Unused,
+    get_operation_state: Unused,
+    set_operation_state: Unused,
+    login: Option<unsafe extern "C" fn(CkUlong, CkUlong, *const u8, CkUlong) -> CkRv>,
+    logout: Option<unsafe extern "C" fn(CkUlong) -> CkRv>,
+    create_object: Unused,
+    copy_object: Unused,
+    destroy_object: Unused,
+    get_object_size: Unused,
+    get_attribute_value: Unused,
+    set_attribute_value: Unused,
+    find_objects_init: Option<unsafe extern "C" fn(CkUlong, *mut CkAttribute, CkUlong) -> CkRv>,
+    find_objects:
+        Option<unsafe extern "C" fn(CkUlong, *mut CkUlong, CkUlong, *mut CkUlong) -> CkRv>,
+    find_objects_final: Option<unsafe extern "C" fn(CkUlong) -> CkRv>,
+    encrypt_init: Unused,
+    encrypt: Unused,
+    encrypt_update: Unused,
+    encrypt_final: Unused,
+    decrypt_init: Unused,
+    decrypt: Unused,
+    decrypt_update: Unused,
+    decrypt_final: Unused,
+    digest_init: Unused,
+    digest: Unused,
+    digest_update: Unused,
+    digest_key: Unused,
+    digest_final: Unused,
+    sign_init: Option<unsafe extern "C" fn(CkUlong, *mut CkMechanism, CkUlong) -> CkRv>,
+    sign: Option<unsafe extern "C" fn(CkUlong, *const u8, CkUlong, *mut u8, *mut CkUlong) -> CkRv>,
+}
+
+/// A loaded module, unloaded (and finalized, if this opened it) on drop.
+struct Module {
+    handle: *mut c_void,
+    functions: *const CkFunctionList,
+    initialized: bool,
+}
+
+impl Module {
+    fn load(path: &str) -> Result<Self, String> {
+        let c_path =
+            CString::new(path).map_err(|_| format!("invalid PKCS#11 module path '{}'", path))?;
+        // SAFETY: dlopen/dlsym are called with valid NUL-terminated strings,
+        // and C_GetFunctionList has the signature the standard defines.
+        unsafe {
+            let handle = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+            if handle.is_null() {
+                return Err(format!(
+                    "PKCS#11 module '{}' could not be loaded: {}",
+                    path,
+                    Self::dl_error()
+                ));
+            }
+            let mut module = Self {
+                handle,
+                functions: ptr::null(),
+                initialized: false,
+            };
+            let symbol = libc::dlsym(handle, c"C_GetFunctionList".as_ptr());
+            if symbol.is_null() {
+                return Err(format!("'{}' is not a PKCS#11 module", path));
+            }
+            let get_function_list: unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv =
+                std::mem::transmute(symbol);
+            let rv = get_function_list(&mut module.functions);
+            if rv != CKR_OK || module.functions.is_null() {
+                return Err(Self::describe("C_GetFunctionList", rv));
+            }
+            let mut args = CkInitializeArgs {
+                create_mutex: ptr::null_mut(),
+                destroy_mutex: ptr::null_mut(),
+                lock_mutex: ptr::null_mut(),
+                unlock_mutex: ptr::null_mut(),
+                flags: CKF_OS_LOCKING_OK,
+                reserved: ptr::null_mut(),
+            };
+            let initialize = module.function(|f| f.initialize, "C_Initialize")?;
+            match initialize((&mut args as *mut CkInitializeArgs).cast()) {
+                CKR_OK => module.initialized = true,
+                CKR_CRYPTOKI_ALREADY_INITIALIZED => {}
+                rv => return Err(Self::describe("C_Initialize", rv)),
+            }
+            Ok(module)
+        }
+    }
+
+    fn function<F>(
+        &self,
+        pick: impl Fn(&CkFunctionList) -> Option<F>,
+        name: &str,
+    ) -> Result<F, String> {
+        // SAFETY: `functions` was returned non-null by C_GetFunctionList and
+        // stays valid until the module is unloaded.
+        pick(unsafe { &*self.functions })
+            .ok_or_else(|| format!("the PKCS#11 module does not implement {}", name))
+    }
+
+    fn dl_error() -> String {
+        // SAFETY: dlerror returns null or a NUL-terminated message.
+        unsafe {
+            let msg = libc::dlerror();
+            if msg.is_null() {
+                "unknown error".to_string()
+            } else {
+                CStr::from_ptr(msg).to_string_lossy().into_owned()
+            }
+        }
+    }
+
+    fn describe(call: &str, rv: CkRv) -> String {
+        let reason = match rv {
+            CKR_TOKEN_NOT_PRESENT | CKR_DEVICE_REMOVED => {
+                "the token is not present (insert the smartcard or connect the HSM)".to_string()
+            }
+            CKR_SLOT_ID_INVALID => "no such slot".to_string(),
+            CKR_DEVICE_ERROR => "the token reported a device error".to_string(),
+            CKR_PIN_INCORRECT => "the PIN was rejected".to_string(),
+            CKR_PIN_LOCKED => "the PIN is locked".to_string(),
+            CKR_USER_NOT_LOGGED_IN => format!("the token requires a PIN (set {})", HSM_PIN_ENV),
+            rv => format!("error {:#x}", rv),
+        };
+        format!("PKCS#11 {} failed: {}", call, reason)
+    }
+
+    fn check(call: &str, rv: CkRv) -> Result<(), String> {
+        if rv == CKR_OK {
+            Ok(())
+        } else {
+            Err(Self::describe(call, rv))
+        }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        // SAFETY: the module is still loaded; nothing uses it afterwards.
+        unsafe {
+            if self.initialized
+                && let Ok(finalize) = self.function(|f| f.finalize, "C_Finalize")
+            {
+                finalize(ptr::null_mut());
+            }
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// HMAC-SHA256 of `message` under the secret key labelled
+/// `config.key_label`, computed on the token so the key never leaves it.
+pub(crate) fn hmac_sha256(
+    config: &HsmConfig,
+    pin: Option<&str>,
+    message: &[u8],
+) -> Result<Vec<u8>, String> {
+    let module = Module::load(&config.module)?;
+    let slot = find_slot(&module, config.slot)?;
+
+    let open_session = module.function(|f| f.open_session, "C_OpenSession")?;
+    let close_session = module.function(|f| f.close_session, "C_CloseSession")?;
+    let mut session: CkUlong = 0;
+    // SAFETY: every pointer passed below outlives the call it is passed to.
+    unsafe {
+        Module::check(
+            "C_OpenSession",
+            open_session(
+                slot,
+                CKF_SERIAL_SESSION,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut session,
+            ),
+        )?;
+    }
+    let result = sign_in_session(&module, session, config, pin, message);
+    // SAFETY: the session was opened above and is closed exactly once.
+    unsafe {
+        close_session(session);
+    }
+    result
+}
+
+/// `wanted`, if a token is present in it, or else the first slot holding one.
+fn find_slot(module: &Module, wanted: Option<u64>) -> Result<CkUlong, String> {
+    let get_slot_list = module.function(|f| f.get_slot_list, "C_GetSlotList")?;
+    let mut count: CkUlong = 0;
+    // SAFETY: the first call only writes the count; the second fills a
+    // buffer of exactly that many slots.
+    let slots = unsafe {
+        Module::check(
+            "C_GetSlotList",
+            get_slot_list(1, ptr::null_mut(), &mut count),
+        )?;
+        let mut slots: Vec<CkUlong> = vec![0; count as usize];
+        Module::check(
+            "C_GetSlotList",
+            get_slot_list(1, slots.as_mut_ptr(), &mut count),
+        )?;
+        slots.truncate(count as usize);
+        slots
+    };
+    match wanted {
+        Some(slot) if slots.contains(&(slot as CkUlong)) => Ok(slot as CkUlong),
+        Some(slot) => Err(format!(
+            "no token present in PKCS#11 slot {} (insert the smartcard or connect the HSM)",
+            slot
+        )),
+        None => slots.first().copied().ok_or_else(|| {
+            "no PKCS#11 token present (insert the smartcard or connect the HSM)".to_string()
+        }),
+    }
+}
+
+fn sign_in_session(
+    module: &Module,
+    session: CkUlong,
+    config: &HsmConfig,
+    pin: Option<&str>,
+    message: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut logged_in = false;
+    if let Some(pin) = pin {
+        let login = module.function(|f| f.login, "C_Login")?;
+        // SAFETY: the PIN buffer outlives the call.
+        match unsafe { login(session, CKU_USER, pin.as_ptr(), pin.len() as CkUlong) } {
+            CKR_OK => logged_in = true,
+            CKR_USER_ALREADY_LOGGED_IN => {}
+            rv => return Err(Module::describe("C_Login", rv)),
+        }
+    }
+    let result = find_key(module, session, &config.key_label).and_then(|key| {
+        let sign_init = module.function(|f| f.sign_init, "C_SignInit")?;
+        let sign = module.function(|f| f.sign, "C_Sign")?;
+        let mut mechanism = CkMechanism {
+            mechanism: CKM_SHA256_HMAC,
+            parameter: ptr::null_mut(),
+            parameter_len: 0,
+        };
+        let mut mac = vec![0u8; 32];
+        let mut len = mac.len() as CkUlong;
+        // SAFETY: the mechanism, message and output buffers outlive the calls,
+        // and `len` holds the output capacity.
+        unsafe {
+            Module::check("C_SignInit", sign_init(session, &mut mechanism, key))?;
+            Module::check(
+                "C_Sign",
+                sign(
+                    session,
+                    message.as_ptr(),
+                    message.len() as CkUlong,
+                    mac.as_mut_ptr(),
+                    &mut len,
+                ),
+            )?;
+        }
+        mac.truncate(len as usize);
+        Ok(mac)
+    });
+    if logged_in && let Ok(logout) = module.function(|f| f.logout, "C_Logout") {
+        // SAFETY: the session is open and was logged in above.
+        unsafe {
+            logout(session);
+        }
+    }
+    result
+}
+
+fn find_key(module: &Module, session: CkUlong, label: &str) -> Result<CkUlong, String> {
+    let find_init = module.function(|f| f.find_objects_init, "C_FindObjectsInit")?;
+    let find = module.function(|f| f.find_objects, "C_FindObjects")?;
+    let find_final = module.function(|f| f.find_objects_final, "C_FindObjectsFinal")?;
+    let mut class = CKO_SECRET_KEY;
+    let mut label = label.as_bytes().to_vec();
+    let mut template = [
+        CkAttribute {
+            kind: CKA_CLASS,
+            value: (&mut class as *mut CkUlong).cast(),
+            len: std::mem::size_of::<CkUlong>() as CkUlong,
+        },
+        CkAttribute {
+            kind: CKA_LABEL,
+            value: label.as_mut_ptr().cast(),
+            len: label.len() as CkUlong,
+        },
+    ];
+    let mut key: CkUlong = 0;
+    let mut found: CkUlong = 0;
+    // SAFETY: the template and its values outlive the search, which is
+    // always finished before returning.
+    unsafe {
+        Module::check(
+            "C_FindObjectsInit",
+            find_init(session, template.as_mut_ptr(), template.len() as CkUlong),
+        )?;
+        let rv = find(session, &mut key, 1, &mut found);
+        find_final(session);
+        Module::check("C_FindObjects", rv)?;
+    }
+    if found == 0 {
+        return Err(format!(
+            "no secret key labelled '{}' on the token",
+            String::from_utf8_lossy(&label)
+        ));
+    }
+    Ok(key)
+}
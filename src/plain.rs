@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// Unencrypted interchange formats for moving data to and from other tools.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlainFormat {
+    /// A flat JSON object of key → value.
+    Json,
+    /// Two columns with a `key,value` header, quoted per RFC 4180.
+    Csv,
+    /// `NAME="value"` lines. Exports use each key's environment variable
+    /// name (see `AegMemoryEngine::env_name_for`) rather than the key.
+    Dotenv,
+}
+
+impl FromStr for PlainFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "dotenv" | "env" => Ok(Self::Dotenv),
+            other => Err(format!(
+                "unknown format '{}' (expected json, csv or dotenv)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PlainFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+            Self::Dotenv => write!(f, "dotenv"),
+        }
+    }
+}
+
+/// What `export --format` writes: a plain format (with `--plain`), or `Html`,
+/// a passphrase-protected page for reading a collection in a browser (see
+/// `AegViewer`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Dotenv,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn plain(self) -> Option<PlainFormat> {
+        match self {
+            Self::Json => Some(PlainFormat::Json),
+            Self::Csv => Some(PlainFormat::Csv),
+            Self::Dotenv => Some(PlainFormat::Dotenv),
+            Self::Html => None,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("html") {
+            return Ok(Self::Html);
+        }
+        match PlainFormat::from_str(s) {
+            Ok(PlainFormat::Json) => Ok(Self::Json),
+            Ok(PlainFormat::Csv) => Ok(Self::Csv),
+            Ok(PlainFormat::Dotenv) => Ok(Self::Dotenv),
+            Err(_) => Err(format!(
+                "unknown format '{}' (expected json, csv, dotenv or html)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.plain() {
+            Some(format) => format.fmt(f),
+            None => write!(f, "html"),
+        }
+    }
+}
+
+pub struct AegPlain;
+
+impl AegPlain {
+    /// Render entries in `format`, sorted by key so output is stable.
+    pub fn encode(entries: &HashMap<String, String>, format: PlainFormat) -> String {
+        let sorted: BTreeMap<&String, &String> = entries.iter().collect();
+        match format {
+            PlainFormat::Json => serde_json::to_string_pretty(&sorted).expect("Serialize failed"),
+            PlainFormat::Csv => {
+                let mut out = String::from("key,value\n");
+                for (k, v) in sorted {
+                    out.push_str(&Self::csv_field(k));
+                    out.push(',');
+                    out.push_str(&Self::csv_field(v));
+                    out.push('\n');
+                }
+                out
+            }
+            PlainFormat::Dotenv => {
+                let mut out = String::new();
+                for (k, v) in sorted {
+                    out.push_str(&format!("{}=\"{}\"\n", k, Self::dotenv_escape(v)));
+                }
+                out
+            }
+        }
+    }
+
+    pub fn decode(text: &str, format: PlainFormat) -> Result<HashMap<String, String>, String> {
+        match format {
+            PlainFormat::Json => {
+                serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))
+            }
+            PlainFormat::Csv => {
+                let mut rows = Self::csv_rows(text)?.into_iter();
+                match rows.next() {
+                    Some(header) if header == ["key", "value"] => {}
+                    _ => return Err("CSV must start with a 'key,value' header".into()),
+                }
+                rows.enumerate()
+                    .map(|(i, row)| match <[String; 2]>::try_from(row) {
+                        Ok([k, v]) => Ok((k, v)),
+                        Err(row) => Err(format!(
+                            "CSV record {} has {} fields, expected 2",
+                            i + 1,
+                            row.len()
+                        )),
+                    })
+                    .collect()
+            }
+            PlainFormat::Dotenv => Self::dotenv_pairs(text),
+        }
+    }
+
+    fn dotenv_escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    }
+
+    /// Parse `NAME=value` lines: blank lines and `#` comments are skipped, an
+    /// optional `export ` prefix is allowed, double-quoted values understand
+    /// `\n`, `\"` and `\\` escapes, single-quoted values are literal. A
+    /// quoted value may span several lines and be followed by a comment.
+    fn dotenv_pairs(text: &str) -> Result<HashMap<String, String>, String> {
+        let mut pairs = HashMap::new();
+        let mut lines = text.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let Some((name, raw)) = line.split_once('=') else {
+                return Err(format!("line {}: expected NAME=value", i + 1));
+            };
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!("line {}: invalid name '{}'", i + 1, name));
+            }
+            let raw = raw.trim_start();
+            let value = match raw.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let mut quoted = raw[1..].to_string();
+                    loop {
+                        if let Some((value, rest)) = Self::dotenv_unquote(&quoted, quote) {
+                            let rest = rest.trim();
+                            if !rest.is_empty() && !rest.starts_with('#') {
+                                return Err(format!(
+                                    "line {}: unexpected text after the closing quote",
+                                    i + 1
+                                ));
+                            }
+                            break value;
+                        }
+                        let Some((_, next)) = lines.next() else {
+                            return Err(format!("line {}: unterminated quote", i + 1));
+                        };
+                        quoted.push('\n');
+                        quoted.push_str(next);
+                    }
+                }
+                // unquoted: an inline comment ends the value
+                _ => raw
+                    .split(" #")
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            };
+            pairs.insert(name.to_string(), value);
+        }
+        Ok(pairs)
+    }
+
+    /// The value of a quoted string whose opening `quote` has been removed,
+    /// and what follows its closing quote; `None` if it is not closed yet.
+    fn dotenv_unquote(text: &str, quote: char) -> Option<(String, &str)> {
+        let mut value = String::new();
+        let mut chars = text.char_indices();
+        while let Some((at, c)) = chars.next() {
+            if c == quote {
+                return Some((value, &text[at + 1..]));
+            }
+            if c != '\\' || quote == '\'' {
+                value.push(c);
+                continue;
+            }
+            match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some(other) => value.push(other),
+                None => value.push('\\'),
+            }
+        }
+        None
+    }
+
+    pub(crate) fn csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn csv_rows(text: &str) -> Result<Vec<Vec<String>>, String> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if quoted {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => quoted = false,
+                    _ => field.push(c),
+                }
+                continue;
+            }
+            match c {
+                '"' if field.is_empty() => quoted = true,
+                ',' => row.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => field.push(c),
+            }
+        }
+        if quoted {
+            return Err("unterminated quoted CSV field".into());
+        }
+        if !field.is_empty() || !row.is_empty() {
+            row.push(field);
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
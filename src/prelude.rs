@@ -0,0 +1,66 @@
+//! The embedding API on its own: engine, storage, crypto and the helpers
+//! built on them, without the CLI definitions or networking. Available in
+//! every feature combination, so `use aegisrlib::prelude::*` works with
+//! `default-features = false`.
+
+pub use crate::audit::{AegAudit, AuditAction, AuditEntry, AuditFilter, AuditHead, AuditSource};
+pub use crate::age::{AegAge, SshIdentity, SshRecipient};
+pub use crate::backups::AegBackups;
+pub use crate::builder::{AegCoreBuilder, AegStore};
+pub use crate::bundle::AegBundle;
+pub use crate::clipboard::{AegClipboard, ClipboardBackend, MemoryClipboard, OsClipboard};
+pub use crate::clock::{AegClock, ClockSkewPolicy};
+pub use crate::core::AegCore;
+pub use crate::crypto::{AegCrypto, Cipher, MasterKeyProvider, SecretFormat, SecretSpec};
+pub use crate::emergency::{AegEmergency, EmergencyBundle, EmergencyGrant};
+pub use crate::env::AegEnv;
+pub use crate::federation::{AegFederation, FederatedStore, FederatedValue, KeyProvider};
+pub use crate::file_system::{
+    AegFileSystem, CompactReport, FileKeyProvider, PassphraseConfig, ProfileManager, RekeyProgress,
+    StoreConfig,
+};
+pub use crate::hardware_key::{
+    AegHardwareKey, HardwareKeyConfig, HardwareKeyProvider, HardwareToken, YubiKeyToken,
+};
+pub use crate::hsm::{AegHsm, HsmConfig};
+pub use crate::integrity::{AegIntegrity, ManifestMismatch, ManifestProblem, ManifestReport};
+pub use crate::introspect::{
+    AegIntrospect, CollectionInfo, SaverState, StoreDescription, StoreFile, StoreFileKind,
+    StoreStatus,
+};
+pub use crate::keyring::{
+    AegKeyring, KeyBackend, KeyringBackend, KeyringConfig, KeyringKeyProvider, MemoryKeyring,
+    OsKeyring,
+};
+pub use crate::lint::{AegLint, LintFinding, LintLevel, LintRule};
+pub use crate::manifest::ProjectManifest;
+pub use crate::memory_engine::{
+    AegMemoryEngine, CollectionStats, Entry, PendingChanges, PersistencePolicy, SaverHandle,
+    SaverPause, SharedEngine, TierStats,
+};
+pub use crate::merge::{AegMerge, MergeConflict, MergeReport, MergeStrategy};
+pub use crate::metrics::{AegMetrics, LatencySnapshot, MetricsSnapshot};
+pub use crate::naming::KeyConvention;
+pub use crate::plain::{AegPlain, ExportFormat, PlainFormat};
+pub use crate::recovery::{AegRecovery, RecoveryReport};
+pub use crate::secret::SecretValue;
+pub use crate::share::{SharePrivateKey, SharePublicKey, SharedBundle};
+pub use crate::snapshot::{SnapshotInfo, SnapshotManager};
+pub use crate::snippet::{AegSnippet, Snippet};
+pub use crate::storage::{FsStorage, MemoryStorage, StorageBackend};
+pub use crate::store::KeyValueStore;
+pub use crate::test_harness::AegTestHarness;
+pub use crate::totp::{TotpAlgorithm, TotpCode, TotpSecret};
+pub use crate::sync::{AegSync, SyncConflict, SyncReport, SyncSide, SyncStrategy};
+pub use crate::transaction::AegTransaction;
+pub use crate::verbosity::Verbosity;
+pub use crate::verify::{
+    AegVerifier, CollectionVerification, IntegrityReport, VerificationReport,
+};
+pub use crate::viewer::AegViewer;
+pub use crate::watch::{AegWatch, ChangeEvent, ChangeKind};
+
+#[cfg(feature = "tokio")]
+pub use crate::async_core::{AegAsyncSaver, AegCoreAsync};
+#[cfg(feature = "s3")]
+pub use crate::s3::{S3Config, S3Storage};
@@ -0,0 +1,131 @@
+use crate::constant::{STORE_COLLECTION, STORE_CORRUPT_SUFFIX};
+use crate::file_system::AegFileSystem;
+use crate::integrity::AegIntegrity;
+use crate::memory_engine::AegMemoryEngine;
+use crate::snapshot::SnapshotManager;
+use crate::verify::AegVerifier;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+static REPORTS: Mutex<Vec<RecoveryReport>> = Mutex::new(Vec::new());
+
+/// What happened to a collection file that could not be opened.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecoveryReport {
+    pub collection: String,
+    /// Why the file could not be opened.
+    pub error: String,
+    /// Where the unreadable file was moved, for inspection.
+    pub quarantined: PathBuf,
+    /// Label of the snapshot the collection was restored from; `None` when
+    /// no snapshot had a readable copy and the collection starts out empty.
+    pub restored_from: Option<String>,
+}
+
+impl RecoveryReport {
+    pub fn summary(&self) -> String {
+        let outcome = match &self.restored_from {
+            Some(label) => format!("restored from snapshot '{}'", label),
+            None => "no snapshot had a readable copy, started empty".to_string(),
+        };
+        format!(
+            "⚠ Collection '{}' was unreadable ({}): moved to {}, {}",
+            self.collection,
+            self.error,
+            self.quarantined.display(),
+            outcome
+        )
+    }
+}
+
+/// Recovery of collection files that fail to decrypt or parse. Instead of
+/// refusing to load, the bad file is moved aside as `<file>.corrupt` and the
+/// newest snapshot holding a readable copy of it is put in its place. Only
+/// the collection's data file is replaced; its record file and index are
+/// kept. Reports are kept until `take_reports`.
+///
+/// Nothing is touched when collection.lock does not open either: the key is
+/// wrong (a wrong passphrase, another machine), not the file.
+pub struct AegRecovery;
+
+impl AegRecovery {
+    /// Quarantine the data file of `collection` at `path`, which failed with
+    /// `error`, and restore it from a snapshot if one has a readable copy.
+    pub(crate) fn recover_collection(
+        collection: &str,
+        path: &Path,
+        error: &str,
+    ) -> Result<RecoveryReport, String> {
+        let dir = AegFileSystem::get_config_path();
+        let lock = dir.join(STORE_COLLECTION);
+        let master = AegFileSystem::read_authorization_key();
+        let storage = AegFileSystem::storage();
+        if storage.exists(&lock) && !AegVerifier::verify_file(&lock, &master).passed() {
+            return Err(error.to_string());
+        }
+
+        let quarantined = Self::quarantine(path, STORE_CORRUPT_SUFFIX)?;
+        let mut report = RecoveryReport {
+            collection: collection.to_string(),
+            error: error.to_string(),
+            quarantined,
+            restored_from: None,
+        };
+        let Some(name) = path.file_name() else {
+            return Ok(Self::publish(report));
+        };
+        for snapshot in SnapshotManager::list().into_iter().rev() {
+            let Ok(copy) = storage.read(&snapshot.path.join(name)) else {
+                continue;
+            };
+            if copy.is_empty() || AegMemoryEngine::open_collection_file(collection, &copy).is_err()
+            {
+                continue;
+            }
+            storage
+                .write(path, &copy)
+                .map_err(|e| format!("restore {}: {}", path.display(), e))?;
+            report.restored_from = Some(snapshot.label);
+            break;
+        }
+        let _ = AegIntegrity::record(&dir, &[name.to_string_lossy().into_owned()]);
+        Ok(Self::publish(report))
+    }
+
+    /// Move `path` to the first free `<path><suffix>[.n]`.
+    pub(crate) fn quarantine(path: &Path, suffix: &str) -> Result<PathBuf, String> {
+        let storage = AegFileSystem::storage();
+        let base = format!("{}{}", path.display(), suffix);
+        let target = (1..)
+            .map(|n| match n {
+                1 => PathBuf::from(&base),
+                n => PathBuf::from(format!("{}.{}", base, n)),
+            })
+            .find(|p| !storage.exists(p))
+            .expect("unbounded range");
+        let content = storage
+            .read(path)
+            .map_err(|e| format!("read {}: {}", path.display(), e))?;
+        storage
+            .write(&target, &content)
+            .and_then(|_| storage.remove(path))
+            .map_err(|e| format!("quarantine {}: {}", path.display(), e))?;
+        Ok(target)
+    }
+
+    fn publish(report: RecoveryReport) -> RecoveryReport {
+        warn!("{}", report.summary());
+        REPORTS
+            .lock()
+            .expect("Failed to lock recovery reports")
+            .push(report.clone());
+        report
+    }
+
+    /// Recoveries made by this process since the last call, oldest first.
+    pub fn take_reports() -> Vec<RecoveryReport> {
+        std::mem::take(&mut *REPORTS.lock().expect("Failed to lock recovery reports"))
+    }
+}
@@ -0,0 +1,226 @@
+use crate::commands::AegisrCommand;
+use crate::constant::STORE_SHELL_HISTORY;
+use crate::core::AegCore;
+use crate::dispatch::AegDispatch;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use crate::wire::OutputFormat;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+const COMMANDS: &[&str] = &[
+    "put", "get", "del", "use", "ls", "history", "help", "exit", "quit",
+];
+
+const HELP: &str = "\
+put <key> <value>   store a value
+get <key>           print a value
+del <key>           delete a key
+use <collection>    switch the active collection
+ls [collection]     list keys
+history             show earlier commands
+exit                save and leave
+End a partial word with Tab, then Enter, to list its completions.";
+
+/// What a line typed at the prompt asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplLine {
+    Command(AegisrCommand),
+    /// The candidates for the word before a trailing Tab.
+    Complete(Vec<String>),
+    History,
+    Help,
+    Exit,
+    Empty,
+}
+
+/// The interactive prompt behind `aegisr shell`. Every command runs in this
+/// process through `AegDispatch`, so collections are loaded and decrypted
+/// once for the whole session instead of once per invocation; changes are
+/// saved when the session ends.
+///
+/// Terminals in line mode deliver a Tab as part of the line, so completion
+/// is requested by ending a line with Tab and pressing Enter. History is
+/// kept in the store's `shell_history`, with `put` values left out.
+pub struct AegRepl {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl AegRepl {
+    /// A session whose history is read from and appended to the store.
+    pub fn new() -> Self {
+        let history_path = AegFileSystem::get_config_path().join(STORE_SHELL_HISTORY);
+        let history = fs::read_to_string(&history_path)
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        Self {
+            history,
+            history_path: Some(history_path),
+        }
+    }
+
+    /// A session that keeps no history on disk.
+    pub fn ephemeral() -> Self {
+        Self {
+            history: Vec::new(),
+            history_path: None,
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Read lines from `input` until it ends or `exit`, answering on
+    /// `output`, then save the collections changed during the session.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(
+                output,
+                "aegisr:{}> ",
+                AegCore::load().get_active_collection()
+            )?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                break;
+            }
+            match self.execute_line(line.trim_end_matches(['\n', '\r'])) {
+                Some(reply) if reply.is_empty() => {}
+                Some(reply) => writeln!(output, "{}", reply)?,
+                None => break,
+            }
+        }
+        AegCore::flush_now();
+        Ok(())
+    }
+
+    /// Run one line and return what to print, or `None` to end the session.
+    pub fn execute_line(&mut self, line: &str) -> Option<String> {
+        let parsed = match Self::parse(line) {
+            Ok(parsed) => parsed,
+            Err(e) => return Some(format!("✗ {}", e)),
+        };
+        if !matches!(parsed, ReplLine::Empty | ReplLine::Complete(_)) {
+            self.remember(line);
+        }
+        match parsed {
+            ReplLine::Command(command) => {
+                Some(AegDispatch::execute(command).render(OutputFormat::Plain))
+            }
+            ReplLine::Complete(candidates) => Some(candidates.join("  ")),
+            ReplLine::History => Some(
+                self.history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, l)| format!("{:>4}  {}", i + 1, l))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ReplLine::Help => Some(HELP.to_string()),
+            ReplLine::Empty => Some(String::new()),
+            ReplLine::Exit => None,
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<ReplLine, String> {
+        if let Some(partial) = line.strip_suffix('\t') {
+            return Ok(ReplLine::Complete(Self::complete(partial)));
+        }
+        let line = line.trim();
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let one = |what: &str| -> Result<String, String> {
+            match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [arg] => Ok(arg.to_string()),
+                _ => Err(format!("usage: {} <{}>", word, what)),
+            }
+        };
+        let command = match word {
+            "" => return Ok(ReplLine::Empty),
+            "exit" | "quit" => return Ok(ReplLine::Exit),
+            "help" | "?" => return Ok(ReplLine::Help),
+            "history" => return Ok(ReplLine::History),
+            "put" => {
+                let (key, value) = rest
+                    .split_once(char::is_whitespace)
+                    .ok_or("usage: put <key> <value>")?;
+                AegisrCommand::Put {
+                    key: key.to_string(),
+                    value: value.trim_start().to_string(),
+                    env_name: None,
+                    json: false,
+                }
+            }
+            "get" => AegisrCommand::Get {
+                key: one("key")?,
+                no_cache: false,
+                clip: false,
+                clear_after: None,
+            },
+            "del" => AegisrCommand::Del { key: one("key")? },
+            "use" => AegisrCommand::Use {
+                name: one("collection")?,
+            },
+            "ls" => AegisrCommand::Keys {
+                tag: None,
+                collection: (!rest.is_empty()).then(|| one("collection")).transpose()?,
+            },
+            other => return Err(format!("unknown command '{}' (try help)", other)),
+        };
+        Ok(ReplLine::Command(command))
+    }
+
+    /// Candidates for the last word of `partial`: a command name first,
+    /// then a collection after `use`/`ls` or a key of the active collection
+    /// after `get`/`del`/`put`. Sorted.
+    pub fn complete(partial: &str) -> Vec<String> {
+        let words: Vec<&str> = partial.split_whitespace().collect();
+        let ends_with_space = partial.ends_with(char::is_whitespace) || partial.is_empty();
+        let (position, prefix) = match (words.len(), ends_with_space) {
+            (n, true) => (n, ""),
+            (n, false) => (n - 1, words[n - 1]),
+        };
+        let mut candidates: Vec<String> = match (position, words.first().copied()) {
+            (0, _) => COMMANDS.iter().map(|c| c.to_string()).collect(),
+            (1, Some("use" | "ls")) => AegCore::load().collections,
+            (1, Some("get" | "del" | "put")) => AegMemoryEngine::read_active(|engine| {
+                engine.search(|k| k.starts_with(prefix), false)
+            }),
+            _ => Vec::new(),
+        };
+        candidates.retain(|c| c.starts_with(prefix));
+        candidates.sort();
+        candidates
+    }
+
+    fn remember(&mut self, line: &str) {
+        let line = line.trim();
+        // never keep a value in the history
+        let entry = match line.strip_prefix("put ") {
+            Some(rest) => match rest.trim_start().split_once(char::is_whitespace) {
+                Some((key, _)) => format!("put {} …", key),
+                None => line.to_string(),
+            },
+            None => line.to_string(),
+        };
+        if let Some(path) = &self.history_path {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", entry));
+        }
+        self.history.push(entry);
+    }
+}
+
+impl Default for AegRepl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,630 @@
+use crate::constant::{
+    S3_ACCESS_KEY_ENV, S3_BUCKET_ENV, S3_ENDPOINT_ENV, S3_PREFIX_ENV, S3_REGION_ENV,
+    S3_SECRET_KEY_ENV, S3_SESSION_TOKEN_ENV,
+};
+use crate::storage::StorageBackend;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::runtime::Runtime;
+
+/// Where an `S3Storage` keeps the store: any service speaking the S3 API
+/// (AWS, MinIO, Cloudflare R2, ...).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct S3Config {
+    /// Service root, e.g. `https://s3.eu-west-1.amazonaws.com` or
+    /// `http://127.0.0.1:9000`.
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "S3Config::default_region")]
+    pub region: String,
+    /// Objects are named `<prefix>/<file>`; empty puts them at the top of
+    /// the bucket.
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// For temporary credentials.
+    #[serde(default)]
+    pub session_token: Option<String>,
+    /// Address the bucket as `<bucket>.<endpoint host>` instead of
+    /// `<endpoint>/<bucket>`.
+    #[serde(default)]
+    pub virtual_host: bool,
+}
+
+impl S3Config {
+    fn default_region() -> String {
+        "us-east-1".to_string()
+    }
+
+    /// Read the settings from `AEGISR_S3_ENDPOINT`, `AEGISR_S3_BUCKET`,
+    /// `AEGISR_S3_REGION` and `AEGISR_S3_PREFIX`, with the usual
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    /// credentials.
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_env_with(
+            required_env(S3_BUCKET_ENV)?,
+            std::env::var(S3_PREFIX_ENV).unwrap_or_default(),
+        )
+    }
+
+    /// `s3://bucket/prefix`, with the other settings read as in `from_env`.
+    pub fn from_url(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("'{}' is not an s3:// URL", url))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("no bucket in '{}'", url));
+        }
+        Self::from_env_with(bucket.to_string(), prefix.to_string())
+    }
+
+    fn from_env_with(bucket: String, prefix: String) -> Result<Self, String> {
+        Ok(Self {
+            endpoint: required_env(S3_ENDPOINT_ENV)?,
+            bucket,
+            region: std::env::var(S3_REGION_ENV).unwrap_or_else(|_| Self::default_region()),
+            prefix,
+            access_key_id: required_env(S3_ACCESS_KEY_ENV)?,
+            secret_access_key: required_env(S3_SECRET_KEY_ENV)?,
+            session_token: std::env::var(S3_SESSION_TOKEN_ENV).ok(),
+            virtual_host: false,
+        })
+    }
+}
+
+fn required_env(name: &str) -> Result<String, String> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("{} is not set", name))
+}
+
+/// Store files kept as objects in an S3 bucket (the `s3` feature). Each
+/// file under `root`, the store directory `AegFileSystem` resolves, maps
+/// to `<prefix>/<path relative to root>`; paths outside it are refused.
+/// The files are the same encrypted blobs `FsStorage` writes, so nothing
+/// readable leaves the process.
+///
+/// Requests are signed with AWS Signature Version 4 and sent one at a
+/// time from the calling thread, which must not be running async code
+/// (`AegCoreAsync` calls in from the blocking pool). Objects cannot be
+/// appended to, so `append` rewrites the whole record file. The store lock
+/// is an object created with `If-None-Match: *`; if a process dies holding
+/// it, delete that object to unlock the store.
+pub struct S3Storage {
+    inner: Arc<S3Client>,
+    root: PathBuf,
+    /// Directories created in this process; S3 itself has none.
+    dirs: Mutex<BTreeSet<String>>,
+}
+
+struct S3Client {
+    config: S3Config,
+    endpoint: Url,
+    http: Client,
+    runtime: Runtime,
+}
+
+/// Deletes the lock object when the lock is released.
+struct S3Lock {
+    client: Arc<S3Client>,
+    key: String,
+}
+
+impl Drop for S3Lock {
+    fn drop(&mut self) {
+        let _ = self.client.send(Method::DELETE, &self.key, &[], None, &[]);
+    }
+}
+
+impl S3Storage {
+    pub fn new(config: S3Config, root: PathBuf) -> Result<Self, String> {
+        let endpoint = Url::parse(&config.endpoint)
+            .map_err(|e| format!("invalid S3 endpoint '{}': {}", config.endpoint, e))?;
+        if endpoint.host_str().is_none() {
+            return Err(format!(
+                "invalid S3 endpoint '{}': no host",
+                config.endpoint
+            ));
+        }
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|e| format!("start S3 runtime: {}", e))?;
+        Ok(Self {
+            inner: Arc::new(S3Client {
+                config,
+                endpoint,
+                http: Client::new(),
+                runtime,
+            }),
+            root,
+            dirs: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// `new` with `S3Config::from_env`.
+    pub fn from_env(root: PathBuf) -> Result<Self, String> {
+        Self::new(S3Config::from_env()?, root)
+    }
+
+    pub fn config(&self) -> &S3Config {
+        &self.inner.config
+    }
+
+    /// The object name of `path`.
+    fn key(&self, path: &Path) -> io::Result<String> {
+        let relative = path.strip_prefix(&self.root).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is outside the S3 store root {}",
+                    path.display(),
+                    self.root.display()
+                ),
+            )
+        })?;
+        let mut parts: Vec<String> = self
+            .inner
+            .config
+            .prefix
+            .split('/')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+                Component::CurDir => {}
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{} cannot be mapped to an S3 object", path.display()),
+                    ));
+                }
+            }
+        }
+        Ok(parts.join("/"))
+    }
+
+    /// Object names starting with `dir_key/`, without that prefix.
+    fn list_under(&self, dir_key: &str, delimited: bool) -> io::Result<Vec<String>> {
+        let prefix = if dir_key.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", dir_key)
+        };
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.clone()),
+            ];
+            if delimited {
+                query.push(("delimiter".to_string(), "/".to_string()));
+            }
+            if let Some(token) = &token {
+                query.push(("continuation-token".to_string(), token.clone()));
+            }
+            let (status, body) = self.inner.send(Method::GET, "", &query, None, &[])?;
+            if !status.is_success() {
+                return Err(S3Client::failure("list", &prefix, status, &body));
+            }
+            let body = String::from_utf8_lossy(&body);
+            for contents in xml_elements(&body, "Contents") {
+                if let Some(key) = xml_elements(contents, "Key").first() {
+                    let key = xml_unescape(key);
+                    if let Some(name) = key.strip_prefix(&prefix) {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+            let truncated = xml_elements(&body, "IsTruncated").first() == Some(&"true");
+            token = xml_elements(&body, "NextContinuationToken")
+                .first()
+                .map(|t| xml_unescape(t));
+            if !truncated || token.is_none() {
+                return Ok(names);
+            }
+        }
+    }
+
+    fn dirs(&self) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+        self.dirs.lock().expect("Failed to lock S3 directories")
+    }
+}
+
+impl S3Client {
+    /// Send a signed request for object `key` (the bucket itself when
+    /// empty) and wait for the response.
+    fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(String, String)],
+        body: Option<&[u8]>,
+        extra: &[(&'static str, String)],
+    ) -> io::Result<(StatusCode, Vec<u8>)> {
+        let (url, headers) = self.sign(&method, key, query, body.unwrap_or_default(), extra)?;
+        let mut request = self.http.request(method, url).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body.to_vec());
+        }
+        self.runtime.block_on(async {
+            let response = request.send().await.map_err(io::Error::other)?;
+            let status = response.status();
+            let bytes = response.bytes().await.map_err(io::Error::other)?;
+            Ok((status, bytes.to_vec()))
+        })
+    }
+
+    /// The request URL and headers, including the SigV4 `Authorization`.
+    fn sign(
+        &self,
+        method: &Method,
+        key: &str,
+        query: &[(String, String)],
+        body: &[u8],
+        extra: &[(&'static str, String)],
+    ) -> io::Result<(Url, HeaderMap)> {
+        let config = &self.config;
+        let mut host = self.endpoint.host_str().unwrap_or_default().to_string();
+        if let Some(port) = self.endpoint.port() {
+            host = format!("{}:{}", host, port);
+        }
+        let mut path = self.endpoint.path().trim_end_matches('/').to_string();
+        if config.virtual_host {
+            host = format!("{}.{}", config.bucket, host);
+        } else {
+            path.push('/');
+            path.push_str(&uri_encode(&config.bucket, true));
+        }
+        path.push('/');
+        path.push_str(&uri_encode(key, false));
+
+        let mut pairs: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        pairs.sort();
+        let canonical_query = pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let (date, time) = amz_timestamp(SystemTime::now());
+        let payload_hash = hex(&Sha256::digest(body));
+        let mut signed = vec![
+            ("host", host.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", time.clone()),
+        ];
+        if let Some(token) = &config.session_token {
+            signed.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = signed
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = signed
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, path, canonical_query, canonical_headers, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            time,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = format!("AWS4{}", config.secret_access_key).into_bytes();
+        for part in [date.as_str(), config.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes());
+        }
+        let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            config.access_key_id, scope, signed_headers, signature
+        );
+
+        let scheme = self.endpoint.scheme();
+        let mut url = format!("{}://{}{}", scheme, host, path);
+        if !canonical_query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query);
+        }
+        let url = Url::parse(&url).map_err(io::Error::other)?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in signed
+            .into_iter()
+            .filter(|(name, _)| *name != "host")
+            .chain(extra.iter().cloned())
+            .chain([("authorization", authorization)])
+        {
+            headers.insert(
+                name,
+                HeaderValue::from_str(&value).map_err(io::Error::other)?,
+            );
+        }
+        Ok((url, headers))
+    }
+
+    fn failure(action: &str, key: &str, status: StatusCode, body: &[u8]) -> io::Error {
+        let body = String::from_utf8_lossy(body);
+        let code = xml_elements(&body, "Code").first().map(|c| c.to_string());
+        let kind = match status {
+            StatusCode::NOT_FOUND => io::ErrorKind::NotFound,
+            StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(
+            kind,
+            format!(
+                "S3 {} '{}': {}{}",
+                action,
+                key,
+                status,
+                code.map(|c| format!(" ({})", c)).unwrap_or_default()
+            ),
+        )
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let key = self.key(path)?;
+        let (status, body) = self.inner.send(Method::GET, &key, &[], None, &[])?;
+        if !status.is_success() {
+            return Err(S3Client::failure("read", &key, status, &body));
+        }
+        Ok(body)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let key = self.key(path)?;
+        let (status, body) = self
+            .inner
+            .send(Method::PUT, &key, &[], Some(contents), &[])?;
+        if !status.is_success() {
+            return Err(S3Client::failure("write", &key, status, &body));
+        }
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<u64> {
+        let mut file = match self.read(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let offset = file.len() as u64;
+        file.extend_from_slice(contents);
+        self.write(path, &file)?;
+        Ok(offset)
+    }
+
+    fn read_at(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let key = self.key(path)?;
+        let range = format!("bytes={}-{}", offset, offset + len - 1);
+        let (status, body) = self
+            .inner
+            .send(Method::GET, &key, &[], None, &[("range", range)])?;
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        if !status.is_success() {
+            return Err(S3Client::failure("read", &key, status, &body));
+        }
+        // a server ignoring the range sends the whole object
+        let body = if status == StatusCode::PARTIAL_CONTENT {
+            body
+        } else {
+            body.get(offset as usize..)
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default()
+        };
+        if (body.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        Ok(body[..len as usize].to_vec())
+    }
+
+    /// Deleting an object that does not exist is not an error in S3, so
+    /// neither is removing a missing file here.
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let key = self.key(path)?;
+        let (status, body) = self.inner.send(Method::DELETE, &key, &[], None, &[])?;
+        if !status.is_success() && status != StatusCode::NOT_FOUND {
+            return Err(S3Client::failure("delete", &key, status, &body));
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let Ok(key) = self.key(path) else {
+            return false;
+        };
+        if self.dirs().contains(&key) {
+            return true;
+        }
+        if !key.is_empty()
+            && let Ok((status, _)) = self.inner.send(Method::HEAD, &key, &[], None, &[])
+            && status.is_success()
+        {
+            return true;
+        }
+        // a directory exists while there are objects under it
+        self.list_under(&key, false)
+            .is_ok_and(|names| !names.is_empty())
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        let key = self.key(dir)?;
+        self.dirs().insert(key);
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        let key = self.key(dir)?;
+        for name in self.list_under(&key, false)? {
+            self.remove(&dir.join(name))?;
+        }
+        self.dirs()
+            .retain(|d| d != &key && !d.starts_with(&format!("{}/", key)));
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let key = self.key(dir)?;
+        Ok(self
+            .list_under(&key, true)?
+            .into_iter()
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .collect())
+    }
+
+    fn list_dirs(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let key = self.key(dir)?;
+        let prefix = if key.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", key)
+        };
+        let mut names: BTreeSet<String> = self
+            .list_under(&key, false)?
+            .into_iter()
+            .filter_map(|name| name.split_once('/').map(|(first, _)| first.to_string()))
+            .collect();
+        names.extend(
+            self.dirs()
+                .iter()
+                .filter_map(|d| d.strip_prefix(&prefix))
+                .filter_map(|rest| rest.split('/').next())
+                .filter(|first| !first.is_empty())
+                .map(str::to_string),
+        );
+        Ok(names.into_iter().collect())
+    }
+
+    fn try_lock(&self, path: &Path) -> io::Result<Option<Box<dyn Send>>> {
+        let key = self.key(path)?;
+        let owner = format!("pid {}", std::process::id());
+        let (status, body) = self.inner.send(
+            Method::PUT,
+            &key,
+            &[],
+            Some(owner.as_bytes()),
+            &[("if-none-match", "*".to_string())],
+        )?;
+        match status {
+            s if s.is_success() => Ok(Some(Box::new(S3Lock {
+                client: Arc::clone(&self.inner),
+                key,
+            }))),
+            // held by someone else, or being taken right now
+            StatusCode::PRECONDITION_FAILED | StatusCode::CONFLICT => Ok(None),
+            _ => Err(S3Client::failure("lock", &key, status, &body)),
+        }
+    }
+}
+
+/// Percent-encode everything but unreserved characters, and `/` too
+/// unless `slash` is false, as SigV4 expects.
+fn uri_encode(s: &str, slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if !slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256>>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// `(YYYYMMDD, YYYYMMDDTHHMMSSZ)` in UTC.
+fn amz_timestamp(time: SystemTime) -> (String, String) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let time = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    );
+    (date, time)
+}
+
+/// The text of every `<tag>...</tag>` in `xml`, in order. S3's responses
+/// are flat enough that this is all the parsing they need.
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else {
+            break;
+        };
+        found.push(&after[..end]);
+        rest = &after[end + close.len()..];
+    }
+    found
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
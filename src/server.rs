@@ -0,0 +1,381 @@
+use crate::audit::{AegAudit, AuditSource};
+use crate::constant::READ_ONLY_ERROR;
+use crate::constant::QUALIFIED_KEY_SEPARATOR;
+use crate::core::AegCore;
+use crate::crypto::AegCrypto;
+use crate::emergency::AegEmergency;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::{AegMemoryEngine, PersistencePolicy};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const MAX_HEADER_LINES: usize = 100;
+
+/// A minimal local HTTP/1.1 API over the store.
+///
+/// Routes (all but `/emergency` require `Authorization: Bearer <token>`,
+/// see `token`):
+/// - `GET /collections`: list collections
+/// - `PUT /collections/{name}` / `DELETE /collections/{name}`
+/// - `GET /collections/{name}/keys`: list key names
+/// - `GET|PUT|DELETE /collections/{name}/keys/{key}`: the value is the raw
+///   request/response body
+/// - `GET /metrics`: operation counters and latencies in the Prometheus
+///   text format (see `AegMetrics`)
+/// - `POST /command`: a wire-encoded `AegisrCommand` (see `AegWire`),
+///   answered with an encoded `AegisrResponse` (needs the `cli` feature)
+/// - `POST /emergency/{id}/request` / `POST /emergency/{id}/release`: a
+///   trusted contact starts the waiting period of an emergency grant, and
+///   later collects its escrow key. The bearer is the access key from the
+///   contact's bundle, not the token (see `AegEmergency`).
+///
+/// Path segments are percent-decoded, so `a%2Fb` addresses the key `a/b`.
+/// Keys are written and deleted through the same checks as the CLI (sealed
+/// collections, naming conventions, the value linter); a write they refuse
+/// answers 409 Conflict, as does every `PUT` and `DELETE` while the store is
+/// read-only. Writes land in memory and are persisted under the process's
+/// persistence policy (see `run`).
+pub struct AegServer {
+    listener: TcpListener,
+    token: String,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+
+    /// Map a core "✓ …"/"✗ …" message onto a response.
+    fn from_message(message: String, failure_status: u16) -> Self {
+        if message.starts_with('✓') {
+            Self::json(200, json!({ "message": message }))
+        } else {
+            Self::json(failure_status, json!({ "error": message }))
+        }
+    }
+}
+
+impl AegServer {
+    /// Bearer token clients must present, derived from the authorization key
+    /// so it changes whenever the key does and never reveals it. Errors
+    /// when the key cannot be read, rather than serving behind a token
+    /// anyone could compute.
+    pub fn token() -> Result<String, String> {
+        let key = AegCrypto::decode_key(&AegFileSystem::try_read_authorization_key()?)?;
+        let derived = blake3::derive_key("aegisr server token v1", &key);
+        Ok(derived.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Short identifier of `token` for the audit log, which must not hold
+    /// the token itself.
+    pub fn token_id() -> Result<String, String> {
+        Ok(Self::id_of(&Self::token()?))
+    }
+
+    fn id_of(token: &str) -> String {
+        blake3::hash(token.as_bytes()).to_hex()[..12].to_string()
+    }
+
+    pub fn bind(addr: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("bind {}: {}", addr, e))?;
+        Ok(Self {
+            listener,
+            token: Self::token()?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Serve connections until the process exits, one thread per connection.
+    /// Keeps the persistence policy already in effect; under `Manual`, which
+    /// would never save what clients write, it saves every second instead.
+    pub fn run(self) {
+        if AegCore::persistence_policy() == PersistencePolicy::Manual {
+            AegCore::set_persistence_policy(PersistencePolicy::Interval(1));
+        }
+        for stream in self.listener.incoming().flatten() {
+            let token = self.token.clone();
+            thread::spawn(move || Self::handle_connection(stream, &token));
+        }
+    }
+
+    /// Bind to `127.0.0.1:port` and serve.
+    pub fn serve(port: u16) -> Result<(), String> {
+        Self::bind(&format!("127.0.0.1:{}", port))?.run();
+        Ok(())
+    }
+
+    fn handle_connection(mut stream: TcpStream, token: &str) {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+        let response = match Self::read_request(&stream) {
+            Ok(request) if request.path.starts_with("/emergency/") => {
+                Self::emergency_route(&request)
+            }
+            Ok(request) => {
+                let authorized = request
+                    .authorization
+                    .as_deref()
+                    .and_then(|h| h.strip_prefix("Bearer "))
+                    .is_some_and(|t| constant_time_eq(t.trim().as_bytes(), token.as_bytes()));
+                if authorized {
+                    AegAudit::with_source(AuditSource::Token(Self::id_of(token)), || {
+                        Self::route(&request)
+                    })
+                } else {
+                    Response::error(401, "missing or invalid bearer token")
+                }
+            }
+            Err(e) => Response::error(400, &e),
+        };
+        let _ = Self::write_response(&mut stream, &response);
+    }
+
+    fn read_request(stream: &TcpStream) -> Result<Request, String> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err("malformed request line".into());
+        };
+        let (method, path) = (method.to_string(), path.to_string());
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        for _ in 0..MAX_HEADER_LINES {
+            line.clear();
+            reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                return Err("malformed header".into());
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .parse()
+                    .map_err(|_| "invalid Content-Length".to_string())?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            }
+        }
+        if content_length > MAX_BODY_BYTES {
+            return Err("request body too large".into());
+        }
+
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+        Ok(Request {
+            method,
+            path,
+            authorization,
+            body,
+        })
+    }
+
+    fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+        let reason = match response.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            _ => "Error",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            reason,
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(&response.body)?;
+        stream.flush()
+    }
+
+    fn route(request: &Request) -> Response {
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Result<Vec<String>, String> = path
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments = match segments {
+            Ok(s) => s,
+            Err(e) => return Response::error(400, &e),
+        };
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let method = request.method.as_str();
+
+        match (method, segments.as_slice()) {
+            #[cfg(feature = "cli")]
+            ("POST", ["command"]) => match std::str::from_utf8(&request.body) {
+                Ok(body) => Response {
+                    status: 200,
+                    content_type: "application/json",
+                    body: crate::dispatch::AegDispatch::execute_encoded(body).into_bytes(),
+                },
+                Err(_) => Response::error(400, "command must be UTF-8"),
+            },
+            ("GET", ["metrics"]) => Response {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: AegCore::metrics_snapshot().to_prometheus().into_bytes(),
+            },
+            ("GET", ["collections"]) => Response::json(200, json!(AegCore::load().collections)),
+            ("PUT" | "DELETE", ["collections", ..]) if AegFileSystem::is_read_only() => {
+                Response::error(409, READ_ONLY_ERROR)
+            }
+            ("PUT", ["collections", name]) => {
+                Response::from_message(AegCore::create_collection(name), 409)
+            }
+            ("DELETE", ["collections", name]) => {
+                Response::from_message(AegCore::delete_collection(name), 404)
+            }
+            (_, ["collections", name, ..]) if !Self::collection_exists(name) => {
+                Response::error(404, &format!("collection '{}' does not exist", name))
+            }
+            ("GET", ["collections", name, "keys"]) => {
+                let mut keys: Vec<String> = AegMemoryEngine::read_engine(name, |engine| {
+                    engine.keys().iter().map(|k| k.to_string()).collect()
+                });
+                keys.sort();
+                Response::json(200, json!(keys))
+            }
+            ("GET", ["collections", name, "keys", key]) => {
+                match AegMemoryEngine::fetch_shared(name, key) {
+                    Some(value) => {
+                        AegAudit::record_read(name, key);
+                        Response {
+                            status: 200,
+                            content_type: "text/plain; charset=utf-8",
+                            body: value.as_bytes().to_vec(),
+                        }
+                    }
+                    None => Response::error(404, &format!("key '{}' not found", key)),
+                }
+            }
+            ("PUT", ["collections", name, "keys", key]) => {
+                let Ok(value) = String::from_utf8(request.body.clone()) else {
+                    return Response::error(400, "value must be UTF-8");
+                };
+                let qualified = format!("{}{}{}", name, QUALIFIED_KEY_SEPARATOR, key);
+                Response::from_message(AegCore::put_qualified(&qualified, &value), 409)
+            }
+            ("DELETE", ["collections", name, "keys", key])
+                if !AegMemoryEngine::read_engine(name, |engine| engine.contains(key)) =>
+            {
+                Response::error(404, &format!("key '{}' not found", key))
+            }
+            ("DELETE", ["collections", name, "keys", key]) => {
+                let qualified = format!("{}{}{}", name, QUALIFIED_KEY_SEPARATOR, key);
+                Response::from_message(AegCore::delete_qualified(&qualified), 409)
+            }
+            (_, ["collections", ..]) => Response::error(405, "method not allowed"),
+            _ => Response::error(404, "no such route"),
+        }
+    }
+
+    /// The `/emergency` routes, for a contact who holds a bundle rather
+    /// than the token. Unknown grants and wrong access keys look the same.
+    fn emergency_route(request: &Request) -> Response {
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Result<Vec<String>, String> = path
+            .trim_matches('/')
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segments = match segments {
+            Ok(s) => s,
+            Err(e) => return Response::error(400, &e),
+        };
+        let [_, id, action] = segments.as_slice() else {
+            return Response::error(404, "no such route");
+        };
+        if request.method != "POST" {
+            return Response::error(405, "method not allowed");
+        }
+        let access = request
+            .authorization
+            .as_deref()
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !AegEmergency::is_authorized(id, access) {
+            return Response::error(401, "missing or invalid emergency access key");
+        }
+        match action.as_str() {
+            "request" => match AegEmergency::request(id, access) {
+                Ok(grant) => Response::json(
+                    200,
+                    json!({ "id": grant.id, "requested_at": grant.requested_at, "available_at": grant.available_at }),
+                ),
+                Err(e) => Response::error(409, &e),
+            },
+            "release" => match AegEmergency::release(id, access) {
+                Ok(key) => Response::json(200, json!({ "id": id, "escrow_key": key })),
+                Err(e) => Response::error(409, &e),
+            },
+            _ => Response::error(404, "no such route"),
+        }
+    }
+
+    fn collection_exists(name: &str) -> bool {
+        AegCore::load().collections.iter().any(|c| c == name)
+    }
+}
+
+fn percent_decode(segment: &str) -> Result<String, String> {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = segment
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("invalid escape in '{}'", segment))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| format!("path segment '{}' is not UTF-8", segment))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
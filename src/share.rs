@@ -0,0 +1,259 @@
+use crate::bundle::BundlePayload;
+use crate::crypto::AegCrypto;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+use zeroize::Zeroizing;
+
+pub const SHARED_BUNDLE_FORMAT: &str = "aegisr-shared";
+pub const SHARED_BUNDLE_VERSION: u32 = 1;
+const PUBLIC_KEY_PREFIX: &str = "aegpub:";
+const PRIVATE_KEY_PREFIX: &str = "aegsec:";
+/// blake3 context the bundle key is derived under.
+const SHARE_KEY_CONTEXT: &str = "aegisr shared bundle key v1";
+
+/// An X25519 public key to share collections with, written
+/// `aegpub:<base64>` (see `aegisr keypair new`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharePublicKey([u8; 32]);
+
+impl FromStr for SharePublicKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_key(s, PUBLIC_KEY_PREFIX, "public").map(|key| Self(*key))
+    }
+}
+
+impl fmt::Display for SharePublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            PUBLIC_KEY_PREFIX,
+            general_purpose::STANDARD_NO_PAD.encode(self.0)
+        )
+    }
+}
+
+impl SharePublicKey {
+    /// The key named by `arg`: the key itself, or the path of a file
+    /// holding it.
+    pub fn load(arg: &str) -> Result<Self, String> {
+        if arg.trim_start().starts_with(PUBLIC_KEY_PREFIX) {
+            return arg.parse();
+        }
+        fs::read_to_string(arg)
+            .map_err(|e| format!("read {}: {}", arg, e))?
+            .parse()
+    }
+
+    /// First bytes of the key's hash, to tell keys apart at a glance.
+    pub fn fingerprint(&self) -> String {
+        blake3::hash(&self.0).to_hex()[..16].to_string()
+    }
+}
+
+/// The private half of a `SharePublicKey`, written `aegsec:<base64>`.
+pub struct SharePrivateKey {
+    secret: StaticSecret,
+    public: SharePublicKey,
+}
+
+impl fmt::Debug for SharePrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharePrivateKey")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for SharePrivateKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_secret(decode_key(
+            s,
+            PRIVATE_KEY_PREFIX,
+            "private",
+        )?))
+    }
+}
+
+impl SharePrivateKey {
+    pub fn generate() -> Self {
+        Self::from_secret(Zeroizing::new(AegCrypto::generate_random_bytes()))
+    }
+
+    fn from_secret(secret: Zeroizing<[u8; 32]>) -> Self {
+        let secret = StaticSecret::from(*secret);
+        let public = SharePublicKey(PublicKey::from(&secret).to_bytes());
+        Self { secret, public }
+    }
+
+    pub fn public_key(&self) -> SharePublicKey {
+        self.public
+    }
+
+    pub fn to_text(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "{}{}",
+            PRIVATE_KEY_PREFIX,
+            general_purpose::STANDARD_NO_PAD.encode(self.secret.as_bytes())
+        ))
+    }
+
+    /// The key named by `arg`: the key itself, or the path of a file
+    /// holding it.
+    pub fn load(arg: &str) -> Result<Self, String> {
+        if arg.trim_start().starts_with(PRIVATE_KEY_PREFIX) {
+            return arg.parse();
+        }
+        Self::read(Path::new(arg))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        Zeroizing::new(
+            fs::read_to_string(path).map_err(|e| format!("read {}: {}", path.display(), e))?,
+        )
+        .parse()
+    }
+
+    /// Write the key to a new file at `path`, readable only by its owner;
+    /// an existing file is never overwritten.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(path)
+            .map_err(|e| format!("create {}: {}", path.display(), e))?;
+        writeln!(file, "{}", self.to_text().as_str())
+            .map_err(|e| format!("write {}: {}", path.display(), e))
+    }
+}
+
+fn decode_key(s: &str, prefix: &str, kind: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+    let encoded = s
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or_else(|| format!("not an aegisr {} key (expected '{}...')", kind, prefix))?;
+    let bytes = Zeroizing::new(
+        general_purpose::STANDARD_NO_PAD
+            .decode(encoded)
+            .map_err(|e| format!("invalid {} key: {}", kind, e))?,
+    );
+    let mut key = Zeroizing::new([0u8; 32]);
+    if bytes.len() != key.len() {
+        return Err(format!("{} keys are 32 bytes", kind));
+    }
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// A collection sealed to one `SharePublicKey`: an ephemeral X25519 key
+/// agrees a key with the recipient's, so only the holder of the matching
+/// `SharePrivateKey` can open it. No password is involved.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SharedBundle {
+    pub format: String,
+    pub version: u32,
+    /// The recipient's public key.
+    pub recipient: String,
+    /// base64 ephemeral public key
+    pub ephemeral: String,
+    /// base64(nonce || ciphertext) of the JSON `BundlePayload`
+    pub payload: String,
+}
+
+impl SharedBundle {
+    pub fn seal(payload: &BundlePayload, recipient: &SharePublicKey) -> Result<Self, String> {
+        let ephemeral = SharePrivateKey::generate();
+        let shared = ephemeral
+            .secret
+            .diffie_hellman(&PublicKey::from(recipient.0));
+        let key = Self::bundle_key(&shared, &ephemeral.public, recipient)?;
+        let json = Zeroizing::new(
+            serde_json::to_vec(payload).map_err(|e| format!("serialize error: {}", e))?,
+        );
+        Ok(Self {
+            format: SHARED_BUNDLE_FORMAT.to_string(),
+            version: SHARED_BUNDLE_VERSION,
+            recipient: recipient.to_string(),
+            ephemeral: general_purpose::STANDARD.encode(ephemeral.public.0),
+            payload: AegCrypto::encrypt_record(&key, &json)?,
+        })
+    }
+
+    pub fn open(&self, key: &SharePrivateKey) -> Result<BundlePayload, String> {
+        if self.format != SHARED_BUNDLE_FORMAT {
+            return Err(format!("not a shared bundle (format '{}')", self.format));
+        }
+        if self.version > SHARED_BUNDLE_VERSION {
+            return Err(format!(
+                "unsupported shared bundle version {}",
+                self.version
+            ));
+        }
+        let recipient: SharePublicKey = self.recipient.parse()?;
+        if recipient != key.public {
+            return Err(format!(
+                "the bundle was shared with another key ({}, not {})",
+                recipient.fingerprint(),
+                key.public.fingerprint()
+            ));
+        }
+        let ephemeral: [u8; 32] = general_purpose::STANDARD
+            .decode(&self.ephemeral)
+            .map_err(|e| format!("invalid ephemeral key: {}", e))?
+            .try_into()
+            .map_err(|_| "ephemeral keys are 32 bytes".to_string())?;
+        let shared = key.secret.diffie_hellman(&PublicKey::from(ephemeral));
+        let bundle_key = Self::bundle_key(&shared, &SharePublicKey(ephemeral), &key.public)?;
+        let json = AegCrypto::decrypt_record(&bundle_key, &self.payload)
+            .map_err(|_| "the bundle does not open with this key (corrupted?)".to_string())?;
+        serde_json::from_slice(&json).map_err(|e| format!("corrupt bundle payload: {}", e))
+    }
+
+    /// The key both sides agree on: the X25519 result of one side's secret
+    /// and the other's public key, bound to both public keys.
+    fn bundle_key(
+        shared: &SharedSecret,
+        ephemeral: &SharePublicKey,
+        recipient: &SharePublicKey,
+    ) -> Result<Zeroizing<String>, String> {
+        if !shared.was_contributory() {
+            return Err("not a usable public key".into());
+        }
+        let mut material = Zeroizing::new(Vec::with_capacity(96));
+        material.extend_from_slice(shared.as_bytes());
+        material.extend_from_slice(&ephemeral.0);
+        material.extend_from_slice(&recipient.0);
+        let derived = Zeroizing::new(blake3::derive_key(SHARE_KEY_CONTEXT, &material));
+        Ok(Zeroizing::new(AegCrypto::encode_base64(derived.as_ref())))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serialize failed")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid shared bundle: {}", e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_json()).map_err(|e| format!("write {}: {}", path.display(), e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        Self::from_json(
+            &fs::read_to_string(path).map_err(|e| format!("read {}: {}", path.display(), e))?,
+        )
+    }
+}
@@ -0,0 +1,175 @@
+use crate::constant::{STORE_COLLECTION, STORE_SNAPSHOTS_DIR};
+use crate::file_system::AegFileSystem;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A saved copy of the store's collection files, as listed by
+/// `SnapshotManager::list`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotInfo {
+    pub label: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub files: usize,
+    pub path: PathBuf,
+}
+
+/// Snapshots of the current store, kept under its `snapshots` directory as
+/// `<created_at>_<label>/`. Files are copied still encrypted, so a snapshot
+/// is only readable with the store's key.
+pub struct SnapshotManager;
+
+impl SnapshotManager {
+    pub fn snapshots_dir() -> PathBuf {
+        AegFileSystem::get_config_path().join(STORE_SNAPSHOTS_DIR)
+    }
+
+    fn validate_label(label: &str) -> Result<(), String> {
+        let valid = !label.is_empty()
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "invalid snapshot label '{}' (use letters, digits, '-' or '_')",
+                label
+            ))
+        }
+    }
+
+    fn parse_dir_name(name: &str) -> Option<(u64, &str)> {
+        let (created_at, label) = name.split_once('_')?;
+        Some((created_at.parse().ok()?, label))
+    }
+
+    /// Every snapshot, oldest first.
+    pub fn list() -> Vec<SnapshotInfo> {
+        let root = Self::snapshots_dir();
+        let mut snapshots: Vec<SnapshotInfo> = AegFileSystem::storage()
+            .list_dirs(&root)
+            .map(|names| {
+                names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let (created_at, label) = Self::parse_dir_name(&name)?;
+                        let path = root.join(&name);
+                        Some(SnapshotInfo {
+                            label: label.to_string(),
+                            created_at,
+                            files: AegFileSystem::list_store_files(&path).len(),
+                            path,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        snapshots.sort_by(|a, b| (a.created_at, &a.label).cmp(&(b.created_at, &b.label)));
+        snapshots
+    }
+
+    pub fn find(label: &str) -> Option<SnapshotInfo> {
+        Self::list().into_iter().find(|s| s.label == label)
+    }
+
+    /// Copy the store files of `store_dir` into a new snapshot. The copy is
+    /// written to a temporary directory and renamed into place, so a
+    /// snapshot is either complete or absent.
+    pub fn create(store_dir: &Path, label: &str) -> Result<SnapshotInfo, String> {
+        AegFileSystem::ensure_writable()?;
+        Self::validate_label(label)?;
+        if Self::find(label).is_some() {
+            return Err(format!("snapshot '{}' already exists", label));
+        }
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let root = Self::snapshots_dir();
+        let name = format!("{}_{}", created_at, label);
+        let staging = root.join(format!(".{}.tmp", name));
+        let path = root.join(&name);
+
+        let storage = AegFileSystem::storage();
+        let files = AegFileSystem::list_store_files(store_dir);
+        let copied = Self::copy_files(store_dir, &staging, &files).and_then(|_| {
+            storage
+                .rename(&staging, &path)
+                .map_err(|e| format!("finish snapshot: {}", e))
+        });
+        if let Err(e) = copied {
+            let _ = storage.remove_dir_all(&staging);
+            return Err(e);
+        }
+        Ok(SnapshotInfo {
+            label: label.to_string(),
+            created_at,
+            files: files.len(),
+            path,
+        })
+    }
+
+    /// Replace the store files of `store_dir` with those of `snapshot`.
+    /// Files are staged next to the store first, then renamed over the
+    /// current ones, collection.lock last; if a rename fails, everything
+    /// already replaced is put back. Files the snapshot does not have (for
+    /// collections created since) are removed.
+    pub fn restore(store_dir: &Path, snapshot: &SnapshotInfo) -> Result<usize, String> {
+        let mut files = AegFileSystem::list_store_files(&snapshot.path);
+        if !files.iter().any(|f| f == STORE_COLLECTION) {
+            return Err(format!(
+                "snapshot '{}' has no collection.lock",
+                snapshot.label
+            ));
+        }
+        // list_store_files puts collection.lock first; swap it in last
+        files.rotate_left(1);
+
+        let staging = Self::snapshots_dir().join(format!(".restore_{}.tmp", snapshot.label));
+        let storage = AegFileSystem::storage();
+        if let Err(e) = Self::copy_files(&snapshot.path, &staging, &files) {
+            let _ = storage.remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        let mut originals = AegFileSystem::capture_store_files(store_dir);
+        let result = files.iter().try_for_each(|file| {
+            let target = store_dir.join(file);
+            if !originals.iter().any(|(p, _)| *p == target) {
+                originals.push((target.clone(), None));
+            }
+            storage
+                .rename(&staging.join(file), &target)
+                .map_err(|e| format!("replace {}: {}", file, e))
+        });
+        let _ = storage.remove_dir_all(&staging);
+        if let Err(e) = result {
+            AegFileSystem::restore_files(&originals);
+            return Err(e);
+        }
+
+        for (path, _) in &originals {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+            if name.is_some_and(|n| !files.contains(&n)) {
+                let _ = storage.remove(path);
+            }
+        }
+        Ok(files.len())
+    }
+
+    /// Copy `files` from `src` into a new directory `dest`.
+    fn copy_files(src: &Path, dest: &Path, files: &[String]) -> Result<(), String> {
+        let storage = AegFileSystem::storage();
+        storage
+            .create_dir_all(dest)
+            .map_err(|e| format!("create {}: {}", dest.display(), e))?;
+        files.iter().try_for_each(|file| {
+            storage
+                .read(&src.join(file))
+                .and_then(|content| storage.write(&dest.join(file), &content))
+                .map_err(|e| format!("copy {}: {}", file, e))
+        })
+    }
+}
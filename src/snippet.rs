@@ -0,0 +1,187 @@
+use crate::audit::AegAudit;
+use crate::clock::AegClock;
+use crate::constant::{SNIPPET_COLLECTION, SNIPPET_ID_LEN};
+use crate::core::AegCore;
+use crate::crypto::AegCrypto;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Lowercase Crockford base32: no `i`, `l`, `o` or `u`, so IDs survive
+/// being read aloud or copied off a screen.
+const ID_ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+
+/// A stashed piece of text, stored as JSON under its ID in the snippets
+/// collection and so encrypted like any other value.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub id: String,
+    pub text: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    /// When the snippet stops being returned; `None` keeps it until deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Snippet {
+    /// Whether the expiry has passed, by `AegClock::expired`: an error
+    /// while the clock is skewed under the fail-closed policy.
+    pub fn is_expired(&self) -> Result<bool, String> {
+        match self.expires_at {
+            Some(at) => AegClock::expired(&AegSnippet::lease_name(&self.id), at),
+            None => Ok(false),
+        }
+    }
+}
+
+/// One-off secrets (a Wi-Fi password, OTP backup codes) kept without
+/// having to think of a key name: each gets a short random ID and,
+/// optionally, an expiry. They live in the `snippets` collection, created
+/// on first use; expired snippets are deleted when next looked at.
+pub struct AegSnippet;
+
+impl AegSnippet {
+    pub(crate) fn new_id(taken: impl Fn(&str) -> bool) -> String {
+        loop {
+            let bytes = AegCrypto::generate_random_bytes();
+            let id: String = bytes[..SNIPPET_ID_LEN]
+                .iter()
+                .map(|b| ID_ALPHABET[(b % 32) as usize] as char)
+                .collect();
+            if !taken(&id) {
+                return id;
+            }
+        }
+    }
+
+    fn exists() -> bool {
+        AegCore::load()
+            .collections
+            .iter()
+            .any(|c| c == SNIPPET_COLLECTION)
+    }
+
+    /// Store `text` under a new ID, expiring after `ttl` if given.
+    pub fn add(text: &str, ttl: Option<Duration>) -> Result<Snippet, String> {
+        AegFileSystem::ensure_writable()?;
+        if text.is_empty() {
+            return Err("snippet text is empty".into());
+        }
+        if !Self::exists() {
+            AegCore::create_collection(SNIPPET_COLLECTION);
+        }
+        let now = AegClock::now();
+        AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| {
+            let id = Self::new_id(|id| engine.contains(id));
+            let snippet = Snippet {
+                expires_at: ttl.map(|ttl| AegClock::lease(&Self::lease_name(&id), ttl)),
+                id,
+                text: text.to_string(),
+                created_at: now,
+            };
+            let json = serde_json::to_string(&snippet).map_err(|e| e.to_string())?;
+            engine.insert(snippet.id.clone(), json);
+            Ok(snippet)
+        })
+    }
+
+    /// The snippet `id`, or `None` if there is none or it has expired.
+    pub fn get(id: &str) -> Result<Option<Snippet>, String> {
+        if !Self::exists() {
+            return Ok(None);
+        }
+        let id = id.trim().to_ascii_lowercase();
+        let snippet =
+            AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| -> Result<_, String> {
+                let Some(json) = engine.get(&id) else {
+                    return Ok(None);
+                };
+                let snippet: Snippet = serde_json::from_str(&json)
+                    .map_err(|e| format!("snippet '{}' is corrupt: {}", id, e))?;
+                if snippet.is_expired()? {
+                    engine.delete(&id);
+                    AegClock::release(&Self::lease_name(&id));
+                    return Ok(None);
+                }
+                Ok(Some(snippet))
+            })?;
+        if snippet.is_some() {
+            AegAudit::record_read(SNIPPET_COLLECTION, &id);
+        }
+        Ok(snippet)
+    }
+
+    /// Every live snippet, oldest first, with its text left out. Expired
+    /// ones are deleted along the way; while the clock is skewed none are.
+    pub fn list() -> Vec<Snippet> {
+        if !Self::exists() {
+            return Vec::new();
+        }
+        let mut snippets: Vec<Snippet> =
+            AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| {
+                let all: HashMap<String, Option<Snippet>> = engine
+                    .list()
+                    .into_iter()
+                    .map(|(id, json)| (id.to_string(), serde_json::from_str(&json).ok()))
+                    .collect();
+                let mut live = Vec::new();
+                for (id, snippet) in all {
+                    match snippet {
+                        Some(snippet) if snippet.is_expired() == Ok(true) => {
+                            engine.delete(&id);
+                            AegClock::release(&Self::lease_name(&id));
+                        }
+                        Some(snippet) => live.push(Snippet {
+                            text: String::new(),
+                            ..snippet
+                        }),
+                        None => {}
+                    }
+                }
+                live
+            });
+        snippets.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        snippets
+    }
+
+    /// Delete snippet `id`; `false` if there was none.
+    pub fn delete(id: &str) -> bool {
+        if !Self::exists() || AegFileSystem::is_read_only() {
+            return false;
+        }
+        let id = id.trim().to_ascii_lowercase();
+        AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| {
+            let existed = engine.contains(&id);
+            engine.delete(&id);
+            AegClock::release(&Self::lease_name(&id));
+            existed
+        })
+    }
+
+    fn lease_name(id: &str) -> String {
+        format!("{}/{}", SNIPPET_COLLECTION, id)
+    }
+
+    /// A lifetime such as `90s`, `30m`, `12h` or `7d`.
+    pub fn parse_ttl(s: &str) -> Result<Duration, String> {
+        let invalid = || format!("invalid expiry '{}' (e.g. 90s, 30m, 12h or 7d)", s);
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split);
+        let amount: u64 = amount.parse().map_err(|_| invalid())?;
+        let seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            "d" => 86_400,
+            _ => return Err(invalid()),
+        };
+        match amount.checked_mul(seconds) {
+            Some(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(invalid()),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use crate::crypto::AegCrypto;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where a store's files are kept. `AegFileSystem` and the engine reach the
+/// store directory (collection.lock, config.aeg, AUTHORIZATION_KEY, the
+/// collection data, index and record files, snapshots, rolling backups, the
+/// audit log, the decoy store and other profiles) only through the backend
+/// set with `AegFileSystem::set_storage`, so the store can live somewhere
+/// other than a local disk: `MemoryStorage` keeps it in the process, e.g.
+/// for a wasm32 build whose web front end loads and saves the files to
+/// IndexedDB, and `S3Storage` (the `s3` feature) in an S3-compatible bucket.
+///
+/// Paths are those `AegFileSystem` resolves, plus `backup` and
+/// `export_portable` destinations; a backend may map them however it likes.
+/// Single files the user exports or imports are still read and written with
+/// `std::fs`.
+pub trait StorageBackend: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Replace `path` with `contents`, durably before returning.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Add `contents` to the end of `path`, creating it if needed, and
+    /// return the offset they start at.
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<u64>;
+    /// `len` bytes of `path` starting at `offset`.
+    fn read_at(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>>;
+    fn remove(&self, path: &Path) -> io::Result<()>;
+    /// Whether `path` is a file or a directory.
+    fn exists(&self, path: &Path) -> bool;
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()>;
+    /// Remove `dir` and everything in it.
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()>;
+    /// Names of the files directly in `dir`.
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>>;
+    /// Names of the directories directly in `dir`.
+    fn list_dirs(&self, dir: &Path) -> io::Result<Vec<String>>;
+
+    /// Move the file or directory `from` to `to`. The default copies and
+    /// then removes, so unlike a local rename it is not atomic.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        match self.read(from) {
+            Ok(contents) => {
+                self.write(to, &contents)?;
+                self.remove(from)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && self.exists(from) => {
+                self.create_dir_all(to)?;
+                for name in self.list(from)?.into_iter().chain(self.list_dirs(from)?) {
+                    self.rename(&from.join(&name), &to.join(&name))?;
+                }
+                self.remove_dir_all(from)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Length of the file `path` in bytes.
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+
+    /// Remove the file `path` so its contents cannot be read back. The
+    /// default just removes it, for backends that cannot overwrite in place.
+    fn shred(&self, path: &Path) -> io::Result<()> {
+        self.remove(path)
+    }
+
+    /// Lock `path` against other processes sharing this storage; the lock
+    /// is held until the returned guard is dropped. `Ok(None)` while
+    /// someone else holds it. Locking within a process is handled by
+    /// `AegFileSystem::lock_store`.
+    fn try_lock(&self, path: &Path) -> io::Result<Option<Box<dyn Send>>>;
+}
+
+/// The local file system; the default backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStorage;
+
+impl StorageBackend for FsStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<u64> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let offset = file.metadata()?.len();
+        file.write_all(contents)?;
+        Ok(offset)
+    }
+
+    fn read_at(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; len as usize];
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::remove_dir_all(dir)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect())
+    }
+
+    /// Symlinks are not listed, so walking the tree never leaves it.
+    fn list_dirs(&self, dir: &Path) -> io::Result<Vec<String>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+
+    /// Overwrite the file with random bytes and sync it before unlinking.
+    /// A symlink is only unlinked: what it points to may be outside the
+    /// store.
+    fn shred(&self, path: &Path) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(path)?;
+        if metadata.is_file() {
+            let mut file = OpenOptions::new().write(true).open(path)?;
+            let mut remaining = metadata.len();
+            while remaining > 0 {
+                let chunk = AegCrypto::generate_random_bytes();
+                let n = remaining.min(chunk.len() as u64) as usize;
+                file.write_all(&chunk[..n])?;
+                remaining -= n as u64;
+            }
+            file.sync_all()?;
+        }
+        fs::remove_file(path)
+    }
+
+    fn try_lock(&self, path: &Path) -> io::Result<Option<Box<dyn Send>>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Box::new(file))),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+/// Files held in this process only; gone when it exits unless something
+/// copies them out (`contents`) and back in (`with_files`).
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+    dirs: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Storage preloaded with `files`, e.g. as saved from `contents` earlier.
+    pub fn with_files(files: impl IntoIterator<Item = (PathBuf, Vec<u8>)>) -> Self {
+        let storage = Self::new();
+        for (path, contents) in files {
+            if let Some(parent) = path.parent() {
+                storage.dirs().insert(parent.to_path_buf());
+            }
+            storage.files().insert(path, contents);
+        }
+        storage
+    }
+
+    /// Every file and its contents, sorted by path.
+    pub fn contents(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files: Vec<_> = self
+            .files()
+            .iter()
+            .map(|(p, c)| (p.clone(), c.clone()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        self.files.lock().expect("Failed to lock memory storage")
+    }
+
+    fn dirs(&self) -> std::sync::MutexGuard<'_, BTreeSet<PathBuf>> {
+        self.dirs.lock().expect("Failed to lock memory storage")
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Self::not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files().insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<u64> {
+        let mut files = self.files();
+        let file = files.entry(path.to_path_buf()).or_default();
+        let offset = file.len() as u64;
+        file.extend_from_slice(contents);
+        Ok(offset)
+    }
+
+    fn read_at(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let files = self.files();
+        let file = files.get(path).ok_or_else(|| Self::not_found(path))?;
+        let start = offset as usize;
+        file.get(start..start + len as usize)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.files()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| Self::not_found(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        // a directory exists once created or once a file is put under it
+        self.files().keys().any(|p| p.starts_with(path)) || self.dirs().contains(path)
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        self.dirs().insert(dir.to_path_buf());
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        self.files().retain(|p, _| !p.starts_with(dir));
+        self.dirs().retain(|p| !p.starts_with(dir));
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        Ok(self
+            .files()
+            .keys()
+            .filter(|p| p.parent() == Some(dir))
+            .filter_map(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    fn list_dirs(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let files = self.files();
+        let dirs = self.dirs();
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        for path in files
+            .keys()
+            .filter_map(|p| p.parent())
+            .chain(dirs.iter().map(PathBuf::as_path))
+        {
+            if let Ok(rest) = path.strip_prefix(dir)
+                && let Some(first) = rest.components().next()
+            {
+                names.insert(first.as_os_str().to_string_lossy().into_owned());
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    /// Nothing outside this process can reach the files, so there is
+    /// nobody to lock out.
+    fn try_lock(&self, _path: &Path) -> io::Result<Option<Box<dyn Send>>> {
+        Ok(Some(Box::new(())))
+    }
+}
@@ -0,0 +1,16 @@
+/// The basic operations of a key-value store, as a trait object. Code that
+/// only needs these can take a `&dyn KeyValueStore` and be handed an
+/// `AegCore` in production and a mock in its unit tests, with no files or
+/// global state involved. `AegCore` works on the collection that was
+/// active when it was loaded.
+pub trait KeyValueStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<String>;
+    /// Store `value` under `key`; an error when the store refuses it.
+    fn put(&self, key: &str, value: &str) -> Result<(), String>;
+    /// Remove `key`. Returns whether it existed.
+    fn delete(&self, key: &str) -> Result<bool, String>;
+    /// Every key, sorted.
+    fn list(&self) -> Vec<String>;
+    /// Persist changes made so far.
+    fn flush(&self) -> Result<(), String>;
+}
@@ -0,0 +1,317 @@
+use crate::core::AegCore;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::{AegMemoryEngine, Entry};
+use crate::storage::{FsStorage, StorageBackend};
+use crate::verbosity::Verbosity;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How `AegSync` settles a key whose value differs between the two stores.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncStrategy {
+    /// The side whose value changed last wins; local wins a tie.
+    #[default]
+    LastWriterWins,
+    /// Keep the local value.
+    PreferLocal,
+    /// Keep the remote value.
+    PreferRemote,
+}
+
+impl FromStr for SyncStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "last-writer-wins" | "lww" | "newest" => Ok(Self::LastWriterWins),
+            "prefer-local" | "local" => Ok(Self::PreferLocal),
+            "prefer-remote" | "remote" => Ok(Self::PreferRemote),
+            other => Err(format!(
+                "unknown sync strategy '{}' (expected last-writer-wins, prefer-local or prefer-remote)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SyncStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LastWriterWins => write!(f, "last-writer-wins"),
+            Self::PreferLocal => write!(f, "prefer-local"),
+            Self::PreferRemote => write!(f, "prefer-remote"),
+        }
+    }
+}
+
+/// Which store's value was kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
+
+/// A key whose value differs between the two stores. Values are left out
+/// so a report can be printed or logged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyncConflict {
+    pub collection: String,
+    pub key: String,
+    /// Unix seconds of the last change on each side, when known.
+    pub local_updated_at: Option<u64>,
+    pub remote_updated_at: Option<u64>,
+    pub kept: SyncSide,
+}
+
+/// What a sync changed, as `collection::key` names.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Copied from the remote store into this one.
+    pub pulled: Vec<String>,
+    /// Copied from this store to the remote one.
+    pub pushed: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+impl SyncReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "✓ Synced: {} pulled, {} pushed, {} conflicts",
+            self.pulled.len(),
+            self.pushed.len(),
+            self.conflicts.len()
+        );
+        for c in &self.conflicts {
+            out.push_str(&format!(
+                "\n⚠ {}::{}: kept {} value (local updated {}, remote updated {})",
+                c.collection,
+                c.key,
+                match c.kept {
+                    SyncSide::Local => "local",
+                    SyncSide::Remote => "remote",
+                },
+                c.local_updated_at
+                    .map_or("unknown".to_string(), |t| t.to_string()),
+                c.remote_updated_at
+                    .map_or("unknown".to_string(), |t| t.to_string())
+            ));
+        }
+        out
+    }
+}
+
+/// Every entry of a store, by collection and key.
+type StoreEntries = BTreeMap<String, BTreeMap<String, Entry>>;
+
+/// Two-way sync between the current store and another one, e.g. the same
+/// store on another machine reached through a shared directory or bucket.
+/// Keys missing on one side are copied over; keys whose values differ are
+/// settled by a `SyncStrategy` and reported as conflicts. Entries keep
+/// their timestamps and tags, so a second sync finds nothing to do.
+///
+/// Deletions are not propagated: a key deleted on one side only comes
+/// back on the next sync. Each store stays encrypted with its own key.
+pub struct AegSync;
+
+/// Points the process at another store and back again when dropped, also
+/// when the work in between panics.
+pub(crate) struct StoreSwitch {
+    storage: Arc<dyn StorageBackend>,
+    base_dir: Option<PathBuf>,
+}
+
+impl StoreSwitch {
+    pub(crate) fn to(storage: Arc<dyn StorageBackend>, dir: PathBuf) -> Self {
+        let switch = Self {
+            storage: AegFileSystem::storage(),
+            base_dir: AegFileSystem::base_dir_override(),
+        };
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::set_storage(storage);
+        AegFileSystem::set_base_dir(dir);
+        switch
+    }
+}
+
+impl Drop for StoreSwitch {
+    fn drop(&mut self) {
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::set_storage(Arc::clone(&self.storage));
+        match self.base_dir.take() {
+            Some(dir) => AegFileSystem::set_base_dir(dir),
+            None => AegFileSystem::clear_base_dir(),
+        }
+    }
+}
+
+impl AegSync {
+    /// Sync the current store with the store at `dir` in `remote`, creating
+    /// it if needed. Both stores are saved when this returns.
+    pub fn sync(
+        remote: Arc<dyn StorageBackend>,
+        dir: PathBuf,
+        strategy: SyncStrategy,
+    ) -> Result<SyncReport, String> {
+        if AegFileSystem::in_duress_session() {
+            return Err("Cannot sync while the decoy store is open".into());
+        }
+        AegCore::flush_now();
+        let local = Self::read_store();
+
+        let mut report = SyncReport::default();
+        let mut pull: StoreEntries = BTreeMap::new();
+        {
+            let _switch = StoreSwitch::to(remote, dir);
+            AegFileSystem::initialize_config(Some(false), Verbosity::Quiet);
+            let remote = Self::read_store();
+            let mut push: StoreEntries = BTreeMap::new();
+            let names: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+            for name in names {
+                let empty = BTreeMap::new();
+                let ours = local.get(name).unwrap_or(&empty);
+                let theirs = remote.get(name).unwrap_or(&empty);
+                let keys: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+                for key in keys {
+                    let side = match (ours.get(key), theirs.get(key)) {
+                        (Some(_), None) => SyncSide::Local,
+                        (None, Some(_)) => SyncSide::Remote,
+                        (Some(a), Some(b)) if a.value == b.value => continue,
+                        (Some(a), Some(b)) => {
+                            let kept = Self::resolve(a, b, strategy);
+                            report.conflicts.push(SyncConflict {
+                                collection: name.clone(),
+                                key: key.clone(),
+                                local_updated_at: a.updated_at,
+                                remote_updated_at: b.updated_at,
+                                kept,
+                            });
+                            kept
+                        }
+                        (None, None) => continue,
+                    };
+                    let qualified = format!("{}::{}", name, key);
+                    match side {
+                        SyncSide::Local => {
+                            push.entry(name.clone())
+                                .or_default()
+                                .insert(key.clone(), ours[key].clone());
+                            report.pushed.push(qualified);
+                        }
+                        SyncSide::Remote => {
+                            pull.entry(name.clone())
+                                .or_default()
+                                .insert(key.clone(), theirs[key].clone());
+                            report.pulled.push(qualified);
+                        }
+                    }
+                }
+                // collections exist on both sides afterwards, even empty ones
+                if !remote.contains_key(name) {
+                    push.entry(name.clone()).or_default();
+                }
+                if !local.contains_key(name) {
+                    pull.entry(name.clone()).or_default();
+                }
+            }
+            Self::write_store(push)?;
+        }
+        Self::write_store(pull)?;
+        Ok(report)
+    }
+
+    fn resolve(local: &Entry, remote: &Entry, strategy: SyncStrategy) -> SyncSide {
+        match strategy {
+            SyncStrategy::PreferLocal => SyncSide::Local,
+            SyncStrategy::PreferRemote => SyncSide::Remote,
+            SyncStrategy::LastWriterWins => {
+                if remote.updated_at.unwrap_or(0) > local.updated_at.unwrap_or(0) {
+                    SyncSide::Remote
+                } else {
+                    SyncSide::Local
+                }
+            }
+        }
+    }
+
+    /// Every entry of the store the process points at.
+    fn read_store() -> StoreEntries {
+        AegCore::load()
+            .collections
+            .iter()
+            .map(|name| {
+                let entries = AegMemoryEngine::read_engine(name, |engine| {
+                    engine
+                        .entries()
+                        .into_iter()
+                        .map(|(k, e)| (k.to_string(), e))
+                        .collect()
+                });
+                (name.clone(), entries)
+            })
+            .collect()
+    }
+
+    /// Put `entries` into the store the process points at, creating missing
+    /// collections, and save it.
+    fn write_store(entries: StoreEntries) -> Result<(), String> {
+        AegFileSystem::ensure_writable()?;
+        for (name, entries) in entries {
+            if !AegCore::load().collections.contains(&name) {
+                let msg = AegCore::create_collection(&name);
+                if !msg.starts_with('✓') {
+                    return Err(msg.trim_start_matches("✗ ").to_string());
+                }
+            }
+            if entries.is_empty() {
+                continue;
+            }
+            AegMemoryEngine::with_engine(&name, |engine| {
+                for (key, entry) in entries {
+                    engine.insert_entry(&key, entry);
+                }
+            });
+        }
+        AegCore::flush_now();
+        Ok(())
+    }
+
+    /// The store named by `target`: a store directory on this machine, or
+    /// with the `s3` feature `s3://bucket/prefix`, taking the endpoint and
+    /// credentials from the environment (see `S3Config::from_url`).
+    pub fn remote_for(target: &str) -> Result<(Arc<dyn StorageBackend>, PathBuf), String> {
+        if target.starts_with("s3://") {
+            return Self::s3_remote(target);
+        }
+        if target.contains("://") {
+            return Err(format!(
+                "cannot sync with '{}': expected a directory or an s3:// URL",
+                target
+            ));
+        }
+        Ok((Arc::new(FsStorage), PathBuf::from(target)))
+    }
+
+    #[cfg(feature = "s3")]
+    fn s3_remote(target: &str) -> Result<(Arc<dyn StorageBackend>, PathBuf), String> {
+        let config = crate::s3::S3Config::from_url(target)?;
+        // the URL itself serves as the store directory; the storage maps
+        // everything under it into the bucket
+        let root = PathBuf::from(target);
+        let storage = crate::s3::S3Storage::new(config, root.clone())?;
+        Ok((Arc::new(storage), root))
+    }
+
+    #[cfg(not(feature = "s3"))]
+    fn s3_remote(target: &str) -> Result<(Arc<dyn StorageBackend>, PathBuf), String> {
+        Err(format!(
+            "cannot sync with '{}': this build has no S3 support (enable the `s3` feature)",
+            target
+        ))
+    }
+}
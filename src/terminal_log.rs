@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+
+thread_local! {
+    /// Spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: Fields,
+    refs: usize,
+}
+
+impl fmt::Display for SpanData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fields.0.trim_start() {
+            "" => f.write_str(self.name),
+            fields => write!(f, "{}{{{}}}", self.name, fields),
+        }
+    }
+}
+
+/// A `tracing` subscriber writing one line per event to standard error,
+/// `LEVEL span{field=value}: message field=value`, up to a level. This is
+/// what the CLI installs for `-q`/`-v`/`-vv` (see
+/// `StoreArgs::install_subscriber`); programs embedding the library
+/// install a subscriber of their own instead, or none to stay silent.
+pub struct TerminalSubscriber {
+    max_level: LevelFilter,
+    writer: Mutex<Box<dyn Write + Send>>,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+}
+
+impl TerminalSubscriber {
+    pub fn new(max_level: LevelFilter) -> Self {
+        Self {
+            max_level,
+            writer: Mutex::new(Box::new(io::stderr())),
+            next_id: AtomicU64::new(1),
+            spans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Write to `writer` instead of standard error.
+    pub fn with_writer(self, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            ..self
+        }
+    }
+
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, SpanData>> {
+        self.spans.lock().expect("Failed to lock tracing spans")
+    }
+}
+
+impl Subscriber for TerminalSubscriber {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.enabled(metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= &self.max_level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let data = SpanData {
+            name: span.metadata().name(),
+            fields,
+            refs: 1,
+        };
+        self.spans().insert(id, data);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(data) = self.spans().get_mut(&span.into_u64()) {
+            values.record(&mut data.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let mut line = format!("{:>5} ", event.metadata().level());
+        let spans = self.spans();
+        ENTERED.with(|entered| {
+            for id in entered.borrow().iter() {
+                if let Some(data) = spans.get(id) {
+                    let _ = write!(line, "{}:", data);
+                }
+            }
+        });
+        drop(spans);
+        if !line.ends_with(' ') {
+            line.push(' ');
+        }
+        line.push_str(fields.0.trim_start());
+        let mut writer = self.writer.lock().expect("Failed to lock tracing writer");
+        let _ = writeln!(writer, "{}", line);
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(pos) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans().get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans();
+        let Some(data) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs == 0 {
+            spans.remove(&span.into_u64());
+            return true;
+        }
+        false
+    }
+}
+
+/// The message first, then ` field=value` for the other fields.
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.insert_str(0, value);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::core::AegCore;
+use crate::crypto::AegCrypto;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use crate::storage::{FsStorage, MemoryStorage};
+use crate::verbosity::Verbosity;
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::{Engine as _, engine::general_purpose};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Held by the open harness, so tests of one binary that each open one take
+/// turns with the process-wide store instead of racing on it.
+static HARNESS: Mutex<()> = Mutex::new(());
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A throwaway store for tests and benches, never `~/.aegisr`: `memory`
+/// keeps every file in a `MemoryStorage`, `temp_dir` writes a fresh
+/// directory under the system temp dir for code that needs real files
+/// (snapshots, the decoy store and exports still write with `std::fs`).
+/// Opening one initializes the store (unencrypted key, no passphrase);
+/// dropping it stops the saver and waits for its last save, then empties
+/// the cache and puts back the default storage and store directory.
+///
+/// The store is process-wide, so a harness holds a lock while open and a
+/// second one in the same process waits for the first to be dropped.
+pub struct AegTestHarness {
+    dir: PathBuf,
+    storage: Option<Arc<MemoryStorage>>,
+    _exclusive: MutexGuard<'static, ()>,
+}
+
+impl AegTestHarness {
+    /// A store held entirely in memory.
+    pub fn memory() -> Self {
+        Self::open(Some(Arc::new(MemoryStorage::new())))
+    }
+
+    /// A store in a new directory under the system temp dir, removed on drop.
+    pub fn temp_dir() -> Self {
+        Self::open(None)
+    }
+
+    fn open(storage: Option<Arc<MemoryStorage>>) -> Self {
+        let exclusive = HARNESS.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = std::env::temp_dir().join(format!(
+            "aegisr_harness_{}_{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        AegCore::flush_now();
+        AegMemoryEngine::reset_cache();
+        match &storage {
+            Some(memory) => AegFileSystem::set_storage(memory.clone()),
+            None => AegFileSystem::set_storage(Arc::new(FsStorage)),
+        }
+        AegCore::set_store_dir(dir.clone());
+        AegFileSystem::initialize_config(Some(false), Verbosity::default());
+        Self {
+            dir,
+            storage,
+            _exclusive: exclusive,
+        }
+    }
+
+    /// The store directory, real or only in memory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The files of a `memory` harness, to look at what was written.
+    pub fn storage(&self) -> Option<&MemoryStorage> {
+        self.storage.as_deref()
+    }
+
+    /// `plaintext` in the original store format, base64 AES-256-GCM under a
+    /// nonce taken from the key, to check that old files still load.
+    /// Nothing in the store writes it any more.
+    pub fn legacy_blob(auth_key: &str, plaintext: &[u8]) -> String {
+        let key_bytes = AegCrypto::decode_key(auth_key).unwrap_or_else(|e| panic!("{}", e));
+        let cipher = Aes256Gcm::new_from_slice(&key_bytes).expect("Invalid key length");
+        let encrypted = cipher
+            .encrypt(Nonce::from_slice(&key_bytes[..12]), plaintext)
+            .expect("Encrypt failed");
+        general_purpose::STANDARD.encode(encrypted)
+    }
+}
+
+impl Drop for AegTestHarness {
+    fn drop(&mut self) {
+        AegCore::stop_background_saver();
+        AegMemoryEngine::reset_cache();
+        AegFileSystem::clear_base_dir();
+        AegFileSystem::set_storage(AegFileSystem::default_storage());
+        if self.storage.is_none() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+const OTPAUTH_TOTP_PREFIX: &str = "otpauth://totp/";
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// HMAC of a TOTP secret (RFC 6238); SHA-1 unless the URI says otherwise.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl FromStr for TotpAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(Self::Sha1),
+            "SHA256" => Ok(Self::Sha256),
+            "SHA512" => Ok(Self::Sha512),
+            other => Err(format!("unsupported TOTP algorithm '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for TotpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        })
+    }
+}
+
+/// A TOTP secret as stored in the vault: an `otpauth://totp/` URI, as
+/// encoded in the QR codes sites hand out, or the bare base32 secret
+/// (SHA-1, 6 digits, 30 seconds).
+pub struct TotpSecret {
+    key: Zeroizing<Vec<u8>>,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    /// The label of the URI, usually `issuer:account`.
+    pub label: Option<String>,
+    pub issuer: Option<String>,
+}
+
+impl fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpSecret")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("label", &self.label)
+            .field("issuer", &self.issuer)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A code and how long it stays valid.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TotpCode {
+    pub code: String,
+    /// Seconds until the next code.
+    pub remaining_secs: u64,
+    pub period: u64,
+}
+
+impl FromStr for TotpSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some(rest) = s.strip_prefix(OTPAUTH_TOTP_PREFIX) else {
+            if s.starts_with("otpauth://") {
+                return Err(
+                    "only otpauth://totp/ URIs are supported (HOTP counters are not)".into(),
+                );
+            }
+            return Ok(Self::new(Self::decode_base32(s)?));
+        };
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut secret = None;
+        let mut totp = Self::new(Zeroizing::new(Vec::new()));
+        totp.label = Some(percent_decode(label)).filter(|l| !l.is_empty());
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(Self::decode_base32(&value)?),
+                "algorithm" => totp.algorithm = value.parse()?,
+                "digits" => {
+                    totp.digits = value
+                        .parse()
+                        .ok()
+                        .filter(|d| (6..=10).contains(d))
+                        .ok_or_else(|| format!("invalid TOTP digits '{}'", value))?
+                }
+                "period" => {
+                    totp.period = value
+                        .parse()
+                        .ok()
+                        .filter(|p| *p > 0)
+                        .ok_or_else(|| format!("invalid TOTP period '{}'", value))?
+                }
+                "issuer" => totp.issuer = Some(value),
+                _ => {}
+            }
+        }
+        totp.key = secret.ok_or("the otpauth URI has no secret")?;
+        if totp.key.is_empty() {
+            return Err("the TOTP secret is empty".into());
+        }
+        Ok(totp)
+    }
+}
+
+impl TotpSecret {
+    fn new(key: Zeroizing<Vec<u8>>) -> Self {
+        Self {
+            key,
+            algorithm: TotpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+            label: None,
+            issuer: None,
+        }
+    }
+
+    /// The code for Unix time `now`, as RFC 6238 computes it.
+    pub fn code_at(&self, now: u64) -> TotpCode {
+        let counter = now / self.period;
+        let message = counter.to_be_bytes();
+        let hash = match self.algorithm {
+            TotpAlgorithm::Sha1 => hmac_sign::<Hmac<Sha1>>(&self.key, &message),
+            TotpAlgorithm::Sha256 => hmac_sign::<Hmac<Sha256>>(&self.key, &message),
+            TotpAlgorithm::Sha512 => hmac_sign::<Hmac<Sha512>>(&self.key, &message),
+        };
+        // dynamic truncation (RFC 4226, section 5.3)
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let code = u64::from(binary) % 10u64.pow(self.digits);
+        TotpCode {
+            code: format!("{:0width$}", code, width = self.digits as usize),
+            remaining_secs: self.period - now % self.period,
+            period: self.period,
+        }
+    }
+
+    /// RFC 4648 base32, as TOTP secrets are written: any case, padding and
+    /// spaces optional.
+    fn decode_base32(text: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        let mut out = Zeroizing::new(Vec::with_capacity(text.len() * 5 / 8));
+        let mut buffer = 0u64;
+        let mut bits = 0;
+        for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+            let value = BASE32_ALPHABET
+                .iter()
+                .position(|&b| b == c.to_ascii_uppercase() as u8)
+                .ok_or_else(|| "the TOTP secret is not base32".to_string())?;
+            buffer = (buffer << 5) | value as u64;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((buffer >> bits) as u8);
+                buffer &= (1 << bits) - 1;
+            }
+        }
+        if out.is_empty() {
+            return Err("the TOTP secret is empty".into());
+        }
+        Ok(out)
+    }
+}
+
+/// `%XX` escapes of an otpauth URI decoded; anything malformed is kept as
+/// it is.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// HMAC of `message` under `key` with the MAC `M` (HMAC-SHA1/256/512).
+fn hmac_sign<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
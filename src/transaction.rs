@@ -0,0 +1,81 @@
+use crate::core::AegCore;
+use crate::file_system::AegFileSystem;
+use crate::memory_engine::AegMemoryEngine;
+use std::collections::BTreeMap;
+
+/// A batch of staged puts/deletes against one collection.
+///
+/// Nothing touches the global cache until `commit`; `rollback` (or dropping
+/// the handle) discards every staged change.
+#[must_use = "a transaction does nothing unless committed"]
+#[derive(Debug)]
+pub struct AegTransaction {
+    collection_name: String,
+    /// key -> Some(value) for a put, None for a delete. Later operations on
+    /// the same key replace earlier ones.
+    staged: BTreeMap<String, Option<String>>,
+}
+
+impl AegTransaction {
+    /// Start a transaction against the collection that is active right now.
+    pub fn begin() -> Self {
+        Self {
+            collection_name: AegCore::load().active_collection,
+            staged: BTreeMap::new(),
+        }
+    }
+
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    pub fn put(&mut self, key: &str, value: &str) {
+        self.staged.insert(key.to_string(), Some(value.to_string()));
+    }
+
+    pub fn delete(&mut self, key: &str) {
+        self.staged.insert(key.to_string(), None);
+    }
+
+    /// Read through the transaction: staged changes first, then the collection.
+    pub fn get(&self, key: &str) -> Option<String> {
+        match self.staged.get(key) {
+            Some(change) => change.clone(),
+            None => AegMemoryEngine::read_engine(&self.collection_name, |engine| engine.get(key)),
+        }
+    }
+
+    /// Number of keys with a staged change.
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Apply every staged change to the in-memory collection under a single
+    /// write lock. The background saver (or `flush_now`) persists them afterwards.
+    pub fn commit(self) -> String {
+        if let Err(e) = AegFileSystem::ensure_writable() {
+            return e;
+        }
+        let count = self.staged.len();
+        AegMemoryEngine::with_engine(&self.collection_name, |engine| {
+            engine.apply_batch(self.staged)
+        });
+        format!(
+            "✓ Transaction committed {} change(s) to collection '{}' (in-memory)",
+            count, self.collection_name
+        )
+    }
+
+    /// Discard every staged change.
+    pub fn rollback(self) -> String {
+        format!(
+            "✓ Transaction rolled back {} change(s) for collection '{}'",
+            self.staged.len(),
+            self.collection_name
+        )
+    }
+}
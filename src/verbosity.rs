@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+use tracing::level_filters::LevelFilter;
+
+/// How much a library call reports, carried as a parameter instead of a
+/// verbose flag. Each level maps onto a `tracing` level; events are emitted
+/// through `tracing`, so the embedding binary decides where they go by
+/// installing a subscriber filtered with `level_filter`.
+///
+/// | CLI flag | level     | emits            |
+/// |----------|-----------|------------------|
+/// | `-q`     | `Quiet`   | errors           |
+/// | (none)   | `Normal`  | warnings and up  |
+/// | `-v`     | `Verbose` | info and up      |
+/// | `-vv`    | `Debug`   | debug and up     |
+/// | `-vvv`   | `Trace`   | everything       |
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    Debug,
+    Trace,
+}
+
+impl Verbosity {
+    /// From a counted `-v` flag and a `-q` flag; `-q` wins.
+    pub fn from_flags(verbose: u8, quiet: bool) -> Self {
+        if quiet {
+            return Self::Quiet;
+        }
+        match verbose {
+            0 => Self::Normal,
+            1 => Self::Verbose,
+            2 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+
+    /// Most detailed `tracing` level reported at this verbosity.
+    pub fn level(self) -> Level {
+        match self {
+            Self::Quiet => Level::ERROR,
+            Self::Normal => Level::WARN,
+            Self::Verbose => Level::INFO,
+            Self::Debug => Level::DEBUG,
+            Self::Trace => Level::TRACE,
+        }
+    }
+
+    /// Filter for a subscriber printing at this verbosity.
+    pub fn level_filter(self) -> LevelFilter {
+        LevelFilter::from_level(self.level())
+    }
+
+    /// Whether an event at `level` should be emitted for a call made at this
+    /// verbosity.
+    pub fn enabled(self, level: Level) -> bool {
+        level <= self.level()
+    }
+}
@@ -0,0 +1,298 @@
+use crate::crypto::AegCrypto;
+use crate::file_format::AegFileFormat;
+use crate::file_system::{AegFileSystem, CollectionLock};
+use crate::integrity::ManifestReport;
+use crate::memory_engine::{AegMemoryEngine, ColdLocation, Entry};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Outcome of decrypting one store file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileVerification {
+    pub file: String,
+    /// blake3 of the decrypted plaintext, present when the file passed.
+    pub checksum: Option<String>,
+    pub error: Option<String>,
+}
+
+impl FileVerification {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VerificationReport {
+    pub files: Vec<FileVerification>,
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> bool {
+        self.files.iter().all(|f| f.passed())
+    }
+
+    pub fn failures(&self) -> Vec<&FileVerification> {
+        self.files.iter().filter(|f| !f.passed()).collect()
+    }
+
+    /// One line per file plus a totals line.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for f in &self.files {
+            match &f.error {
+                None => out.push_str(&format!(
+                    "✓ {} ({})\n",
+                    f.file,
+                    f.checksum.as_deref().unwrap_or("-")
+                )),
+                Some(e) => out.push_str(&format!("✗ {}: {}\n", f.file, e)),
+            }
+        }
+        out.push_str(&format!(
+            "{} passed, {} failed",
+            self.files.len() - self.failures().len(),
+            self.failures().len()
+        ));
+        out
+    }
+}
+
+/// The files of one collection, as checked by `AegVerifier::verify_collections`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionVerification {
+    pub collection: String,
+    pub files: Vec<FileVerification>,
+}
+
+impl CollectionVerification {
+    pub fn passed(&self) -> bool {
+        self.files.iter().all(|f| f.passed())
+    }
+}
+
+/// Per-collection integrity of a store.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IntegrityReport {
+    /// Files that belong to no collection (collection.lock).
+    pub store: Vec<FileVerification>,
+    pub collections: Vec<CollectionVerification>,
+    /// The files checked against the store manifest, when that was asked for.
+    #[serde(default)]
+    pub manifest: Option<ManifestReport>,
+}
+
+impl IntegrityReport {
+    pub fn passed(&self) -> bool {
+        self.store.iter().all(|f| f.passed())
+            && self.collections.iter().all(|c| c.passed())
+            && self.manifest.as_ref().is_none_or(ManifestReport::passed)
+    }
+
+    /// One OK/CORRUPT line per store file and collection, with the errors
+    /// of corrupt collections and the manifest check, plus a totals line.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for f in &self.store {
+            match &f.error {
+                None => out.push_str(&format!("✓ OK       {}\n", f.file)),
+                Some(e) => out.push_str(&format!("✗ CORRUPT  {}: {}\n", f.file, e)),
+            }
+        }
+        for c in &self.collections {
+            if c.passed() {
+                out.push_str(&format!(
+                    "✓ OK       {} ({} files)\n",
+                    c.collection,
+                    c.files.len()
+                ));
+                continue;
+            }
+            out.push_str(&format!("✗ CORRUPT  {}\n", c.collection));
+            for f in c.files.iter().filter(|f| !f.passed()) {
+                out.push_str(&format!(
+                    "    {}: {}\n",
+                    f.file,
+                    f.error.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+        if let Some(manifest) = &self.manifest {
+            out.push_str(&manifest.summary());
+            out.push('\n');
+        }
+        let corrupt = self.collections.iter().filter(|c| !c.passed()).count();
+        out.push_str(&format!(
+            "{} collections OK, {} corrupt",
+            self.collections.len() - corrupt,
+            corrupt
+        ));
+        out
+    }
+}
+
+pub struct AegVerifier;
+
+impl AegVerifier {
+    /// Decrypt and checksum every file in the store with `auth_key`.
+    /// Nothing is modified. Run this after any operation that rewrites
+    /// encrypted files or swaps key material.
+    pub fn verify_all(auth_key: &str) -> VerificationReport {
+        Self::verify_dir(&AegFileSystem::get_config_path(), auth_key)
+    }
+
+    /// `verify_all` for the store files in `dir` (e.g. a snapshot).
+    pub fn verify_dir(dir: &Path, auth_key: &str) -> VerificationReport {
+        let files = AegFileSystem::list_store_files(dir)
+            .into_iter()
+            .map(|name| Self::verify_file(&dir.join(name), auth_key))
+            .collect();
+        VerificationReport { files }
+    }
+
+    /// `verify_dir`, grouped by collection. Collections listed in
+    /// collection.lock appear even when they have no files yet.
+    pub fn verify_collections(dir: &Path, auth_key: &str) -> IntegrityReport {
+        let mut report = IntegrityReport::default();
+        let mut collections: BTreeMap<String, Vec<FileVerification>> = BTreeMap::new();
+        for name in AegFileSystem::list_store_files(dir) {
+            let path = dir.join(&name);
+            let verification = Self::verify_file(&path, auth_key);
+            match AegFileSystem::collection_of_file(&name) {
+                Some(collection) => collections
+                    .entry(collection.to_string())
+                    .or_default()
+                    .push(verification),
+                None => {
+                    if verification.passed()
+                        && let Ok(plain) = Self::decrypt_file(&path, auth_key)
+                        && let Ok(lock) = serde_json::from_slice::<CollectionLock>(&plain)
+                    {
+                        for collection in lock.collections {
+                            collections.entry(collection).or_default();
+                        }
+                    }
+                    report.store.push(verification);
+                }
+            }
+        }
+        report.collections = collections
+            .into_iter()
+            .map(|(collection, files)| CollectionVerification { collection, files })
+            .collect();
+        report
+    }
+
+    /// Decrypt a single store file according to its kind.
+    pub fn verify_file(path: &Path, auth_key: &str) -> FileVerification {
+        let file = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match Self::decrypt_file(path, auth_key) {
+            Ok(plain) => FileVerification {
+                file,
+                checksum: Some(blake3::hash(&plain).to_hex().to_string()),
+                error: None,
+            },
+            Err(e) => FileVerification {
+                file,
+                checksum: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Decrypt with the key the file's kind calls for; see
+    /// `AegFileSystem::store_file_keys`.
+    fn decrypt_file(path: &Path, auth_key: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        let content = AegFileSystem::storage()
+            .read(path)
+            .map_err(|e| format!("read error: {}", e))?;
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut first_err = None;
+        for key in AegFileSystem::store_file_keys(&file_name, auth_key)? {
+            match Self::decrypt_content(path, &content, &key) {
+                Ok(plain) => return Ok(plain),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_err.unwrap_or_default())
+    }
+
+    fn decrypt_content(
+        path: &Path,
+        content: &[u8],
+        auth_key: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, String> {
+        let name = path.to_string_lossy();
+        if name.ends_with(".aekv") {
+            if content.is_empty() {
+                return Ok(Zeroizing::default());
+            }
+            let plain = AegFileFormat::decode(auth_key, content)?;
+            AegFileFormat::codec_of(content).deserialize::<AegMemoryEngine>(&plain)?;
+            return Ok(plain);
+        }
+        let content = std::str::from_utf8(content).map_err(|_| "not a text file".to_string())?;
+
+        if name.ends_with(".cold") {
+            // one record per line; checksum covers all of them in order
+            let mut plain = Zeroizing::new(Vec::new());
+            for (i, line) in content.lines().enumerate() {
+                let record = AegCrypto::decrypt_record(auth_key, line)
+                    .map_err(|e| format!("record {}: {}", i + 1, e))?;
+                serde_json::from_slice::<(String, Entry)>(&record)
+                    .map_err(|e| format!("record {}: invalid JSON: {}", i + 1, e))?;
+                plain.extend_from_slice(&record);
+            }
+            return Ok(plain);
+        }
+
+        if content.trim().is_empty() {
+            return Ok(Zeroizing::default());
+        }
+
+        if name.ends_with(".idx") {
+            let plain = AegCrypto::decrypt_record(auth_key, content)?;
+            serde_json::from_slice::<HashMap<String, ColdLocation>>(&plain)
+                .map_err(|e| format!("invalid JSON: {}", e))?;
+            return Ok(plain);
+        }
+        // collection.lock; older stores hold a bare collection name
+        let plain = AegCrypto::decrypt_blob(auth_key, content)?;
+        serde_json::from_slice::<serde_json::Value>(&plain)
+            .map_err(|e| format!("invalid JSON: {}", e))?;
+        Ok(plain)
+    }
+
+    /// Delete superseded key material, but only once every store file
+    /// decrypts with `new_auth_key`. On failure the old key is kept and the
+    /// report summary is returned as the error.
+    pub fn retire_key_material(
+        old_key_path: &Path,
+        new_auth_key: &str,
+    ) -> Result<VerificationReport, String> {
+        let report = Self::verify_all(new_auth_key);
+        if !report.passed() {
+            return Err(format!(
+                "verification failed, keeping {}:\n{}",
+                old_key_path.display(),
+                report.summary()
+            ));
+        }
+        let storage = AegFileSystem::storage();
+        if storage.exists(old_key_path) {
+            storage
+                .remove(old_key_path)
+                .map_err(|e| format!("remove {}: {}", old_key_path.display(), e))?;
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,228 @@
+use crate::bundle::BundlePayload;
+use crate::constant::VIEWER_PBKDF2_ITERATIONS;
+use crate::crypto::AegCrypto;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+
+pub const VIEWER_FORMAT: &str = "aegisr-viewer";
+pub const VIEWER_VERSION: u32 = 1;
+
+const PAYLOAD_OPEN: &str = r#"<script type="application/json" id="aegisr-payload">"#;
+const PAYLOAD_CLOSE: &str = "</script>";
+
+/// A read-only view of one collection as a single HTML page. The page holds
+/// the collection encrypted with a key derived from a passphrase and decrypts
+/// it in the browser with WebCrypto, so it can be opened from disk on a
+/// machine without Aegisr. It loads nothing from the network.
+///
+/// WebCrypto has no Argon2, so the key is derived with PBKDF2-HMAC-SHA256
+/// (`VIEWER_PBKDF2_ITERATIONS` rounds); the ciphertext is AES-256-GCM in the
+/// same layout as `AegBundle`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AegViewer {
+    pub format: String,
+    pub version: u32,
+    pub kdf: String,
+    pub iterations: u32,
+    /// base64 PBKDF2 salt
+    pub salt: String,
+    /// base64(nonce || ciphertext) of the JSON `BundlePayload`
+    pub payload: String,
+}
+
+impl AegViewer {
+    pub fn seal(payload: &BundlePayload, passphrase: &str) -> Result<Self, String> {
+        if passphrase.is_empty() {
+            return Err("the viewer passphrase must not be empty".to_string());
+        }
+        let salt = AegCrypto::generate_random_bytes();
+        let key = AegCrypto::derive_pbkdf2_key(passphrase, &salt, VIEWER_PBKDF2_ITERATIONS)?;
+        let json = serde_json::to_vec(payload).map_err(|e| format!("serialize error: {}", e))?;
+        Ok(Self {
+            format: VIEWER_FORMAT.to_string(),
+            version: VIEWER_VERSION,
+            kdf: "pbkdf2-sha256".to_string(),
+            iterations: VIEWER_PBKDF2_ITERATIONS,
+            salt: general_purpose::STANDARD.encode(salt),
+            payload: AegCrypto::encrypt_record(&key, &json)?,
+        })
+    }
+
+    pub fn open(&self, passphrase: &str) -> Result<BundlePayload, String> {
+        if self.format != VIEWER_FORMAT {
+            return Err(format!("not an Aegisr viewer (format '{}')", self.format));
+        }
+        if self.version > VIEWER_VERSION {
+            return Err(format!("unsupported viewer version {}", self.version));
+        }
+        let salt = general_purpose::STANDARD
+            .decode(&self.salt)
+            .map_err(|e| format!("invalid salt: {}", e))?;
+        let key = AegCrypto::derive_pbkdf2_key(passphrase, &salt, self.iterations)?;
+        let json = AegCrypto::decrypt_record(&key, &self.payload)
+            .map_err(|_| "wrong passphrase or corrupted viewer".to_string())?;
+        serde_json::from_slice(&json).map_err(|e| format!("corrupt viewer payload: {}", e))
+    }
+
+    /// The page, titled after `collection`. Only the collection name appears
+    /// in clear.
+    pub fn render(&self, collection: &str) -> Result<String, String> {
+        // `<` never occurs in base64, escaping it keeps the JSON from
+        // closing its script element whatever it holds.
+        let params = serde_json::to_string(self)
+            .map_err(|e| format!("serialize error: {}", e))?
+            .replace('<', "\\u003c");
+        Ok(VIEWER_TEMPLATE
+            .replace(
+                "{{payload}}",
+                &format!("{}{}{}", PAYLOAD_OPEN, params, PAYLOAD_CLOSE),
+            )
+            .replace("{{title}}", &Self::escape_html(collection)))
+    }
+
+    /// Read back the parameters embedded in a page written by `render`.
+    pub fn parse(html: &str) -> Result<Self, String> {
+        let start = html
+            .find(PAYLOAD_OPEN)
+            .ok_or_else(|| "not an Aegisr viewer page".to_string())?
+            + PAYLOAD_OPEN.len();
+        let end = html[start..]
+            .find(PAYLOAD_CLOSE)
+            .ok_or_else(|| "truncated viewer page".to_string())?;
+        serde_json::from_str(&html[start..start + end])
+            .map_err(|e| format!("invalid viewer page: {}", e))
+    }
+
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;")
+    }
+}
+
+const VIEWER_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="Content-Security-Policy" content="default-src 'none'; script-src 'unsafe-inline'; style-src 'unsafe-inline'">
+<meta name="referrer" content="no-referrer">
+<title>Aegisr · {{title}}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 60rem; padding: 0 1rem; color: #222; }
+h1 { font-size: 1.3rem; }
+form { display: flex; gap: .5rem; margin: 1rem 0; }
+input { font: inherit; padding: .4rem; }
+input[type=password], #filter { flex: 1; }
+button { font: inherit; padding: .4rem .8rem; cursor: pointer; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: .4rem; text-align: left; vertical-align: top; }
+td.value { font-family: ui-monospace, monospace; white-space: pre-wrap; word-break: break-all; }
+.error { color: #b00020; }
+.hidden { display: none; }
+</style>
+</head>
+<body>
+<h1>Aegisr · {{title}}</h1>
+<form id="unlock">
+<input type="password" id="passphrase" placeholder="Passphrase" autocomplete="off" autofocus>
+<button type="submit">Unlock</button>
+</form>
+<p id="status"></p>
+<div id="view" class="hidden">
+<form id="search">
+<input type="search" id="filter" placeholder="Filter keys">
+<button type="button" id="reveal">Show values</button>
+<button type="button" id="lock">Lock</button>
+</form>
+<table><thead><tr><th>Key</th><th>Value</th></tr></thead><tbody id="entries"></tbody></table>
+</div>
+{{payload}}
+<script>
+"use strict";
+const params = JSON.parse(document.getElementById("aegisr-payload").textContent);
+const bytes = (b64) => Uint8Array.from(atob(b64), (c) => c.charCodeAt(0));
+const $ = (id) => document.getElementById(id);
+let revealed = false;
+
+async function decrypt(passphrase) {
+  const material = await crypto.subtle.importKey(
+    "raw", new TextEncoder().encode(passphrase), "PBKDF2", false, ["deriveKey"]);
+  const key = await crypto.subtle.deriveKey(
+    { name: "PBKDF2", salt: bytes(params.salt), iterations: params.iterations, hash: "SHA-256" },
+    material, { name: "AES-GCM", length: 256 }, false, ["decrypt"]);
+  const sealed = bytes(params.payload);
+  const plain = await crypto.subtle.decrypt(
+    { name: "AES-GCM", iv: sealed.slice(0, 12) }, key, sealed.slice(12));
+  return JSON.parse(new TextDecoder().decode(plain));
+}
+
+function render(entries) {
+  const body = $("entries");
+  body.replaceChildren();
+  for (const key of Object.keys(entries).sort()) {
+    const row = body.insertRow();
+    row.dataset.key = key.toLowerCase();
+    row.insertCell().textContent = key;
+    const value = row.insertCell();
+    value.className = "value";
+    value.dataset.value = entries[key];
+    value.textContent = "••••••";
+  }
+}
+
+function reveal(show) {
+  revealed = show;
+  for (const cell of document.querySelectorAll("td.value")) {
+    cell.textContent = show ? cell.dataset.value : "••••••";
+  }
+  $("reveal").textContent = show ? "Hide values" : "Show values";
+}
+
+$("unlock").addEventListener("submit", async (event) => {
+  event.preventDefault();
+  if (params.format !== "aegisr-viewer" || params.kdf !== "pbkdf2-sha256") {
+    $("status").textContent = "Unsupported viewer format.";
+    return;
+  }
+  $("status").className = "";
+  $("status").textContent = "Decrypting…";
+  try {
+    const payload = await decrypt($("passphrase").value);
+    render(payload.entries);
+    $("passphrase").value = "";
+    $("status").textContent = Object.keys(payload.entries).length + " key(s) in '" + payload.collection + "'";
+    $("unlock").classList.add("hidden");
+    $("view").classList.remove("hidden");
+  } catch (e) {
+    $("status").className = "error";
+    $("status").textContent = "Wrong passphrase or corrupted page.";
+  }
+});
+
+$("search").addEventListener("submit", (event) => event.preventDefault());
+
+$("filter").addEventListener("input", () => {
+  const needle = $("filter").value.toLowerCase();
+  for (const row of $("entries").rows) {
+    row.classList.toggle("hidden", !row.dataset.key.includes(needle));
+  }
+});
+
+$("reveal").addEventListener("click", () => reveal(!revealed));
+
+$("lock").addEventListener("click", () => {
+  $("entries").replaceChildren();
+  reveal(false);
+  $("filter").value = "";
+  $("status").textContent = "";
+  $("view").classList.add("hidden");
+  $("unlock").classList.remove("hidden");
+  $("passphrase").focus();
+});
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,171 @@
+use crate::core::AegCore;
+use crate::memory_engine::AegMemoryEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Put,
+    Delete,
+    /// Every key of the collection was removed at once.
+    Clear,
+}
+
+/// One change to a collection. Never carries the value, so events can be
+/// logged or forwarded without leaking secrets.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub collection: String,
+    /// `None` for `Clear`.
+    pub key: Option<String>,
+    pub kind: ChangeKind,
+}
+
+impl fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Put => "put",
+            ChangeKind::Delete => "delete",
+            ChangeKind::Clear => "clear",
+        };
+        match &self.key {
+            Some(key) => write!(f, "{} {}/{}", kind, self.collection, key),
+            None => write!(f, "{} {}", kind, self.collection),
+        }
+    }
+}
+
+struct Subscriber {
+    prefix: String,
+    sender: Sender<ChangeEvent>,
+}
+
+static SUBSCRIBERS: OnceLock<Mutex<Vec<Subscriber>>> = OnceLock::new();
+
+/// Change notifications for keys. `AegMemoryEngine` reports every put,
+/// delete and clear made in this process through `notify`; `watch_disk`
+/// covers other processes. Subscribers are dropped once their receiver is.
+pub struct AegWatch;
+
+impl AegWatch {
+    fn subscribers() -> &'static Mutex<Vec<Subscriber>> {
+        SUBSCRIBERS.get_or_init(|| Mutex::new(Vec::new()))
+    }
+
+    /// Receive changes to keys starting with `prefix` (`""` for all) in any
+    /// collection of this process, plus every clear.
+    pub fn subscribe(prefix: &str) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        Self::subscribers()
+            .lock()
+            .expect("Failed to lock subscribers")
+            .push(Subscriber {
+                prefix: prefix.to_string(),
+                sender,
+            });
+        receiver
+    }
+
+    pub(crate) fn notify(collection: &str, key: Option<&str>, kind: ChangeKind) {
+        let mut subscribers = Self::subscribers()
+            .lock()
+            .expect("Failed to lock subscribers");
+        if subscribers.is_empty() {
+            return;
+        }
+        subscribers.retain(|s| {
+            if key.is_some_and(|k| !k.starts_with(&s.prefix)) {
+                return true;
+            }
+            s.sender
+                .send(ChangeEvent {
+                    collection: collection.to_string(),
+                    key: key.map(str::to_string),
+                    kind,
+                })
+                .is_ok()
+        });
+    }
+
+    /// Changes saved to disk by any process, found by re-reading every
+    /// collection each `interval` and diffing it against the previous read.
+    /// Only saved changes are seen, and a clear shows up as one delete per
+    /// key. The polling thread stops at the first change after the receiver
+    /// is dropped.
+    pub fn watch_disk(prefix: &str, interval: Duration) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        let prefix = prefix.to_string();
+        let mut previous = Self::read_store(&HashMap::new());
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                let current = Self::read_store(&previous);
+                for event in Self::diff(&previous, &current, &prefix) {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+                previous = current;
+            }
+        });
+        receiver
+    }
+
+    /// Persisted values of every collection. A collection that cannot be
+    /// read right now (locked, mid-write) keeps its `previous` values.
+    fn read_store(
+        previous: &HashMap<String, HashMap<String, String>>,
+    ) -> HashMap<String, HashMap<String, String>> {
+        AegCore::load()
+            .collections
+            .into_iter()
+            .filter_map(|name| {
+                let values = AegMemoryEngine::read_persisted(&name)
+                    .ok()
+                    .or_else(|| previous.get(&name).cloned())?;
+                Some((name, values))
+            })
+            .collect()
+    }
+
+    fn diff(
+        previous: &HashMap<String, HashMap<String, String>>,
+        current: &HashMap<String, HashMap<String, String>>,
+        prefix: &str,
+    ) -> Vec<ChangeEvent> {
+        let empty = HashMap::new();
+        let mut events = Vec::new();
+        let mut names: Vec<&String> = previous.keys().chain(current.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let before = previous.get(name).unwrap_or(&empty);
+            let after = current.get(name).unwrap_or(&empty);
+            let mut changed: Vec<(&String, ChangeKind)> = after
+                .iter()
+                .filter(|(k, v)| before.get(*k) != Some(*v))
+                .map(|(k, _)| (k, ChangeKind::Put))
+                .chain(
+                    before
+                        .keys()
+                        .filter(|k| !after.contains_key(*k))
+                        .map(|k| (k, ChangeKind::Delete)),
+                )
+                .filter(|(k, _)| k.starts_with(prefix))
+                .collect();
+            changed.sort_by(|a, b| a.0.cmp(b.0));
+            events.extend(changed.into_iter().map(|(key, kind)| ChangeEvent {
+                collection: name.clone(),
+                key: Some(key.clone()),
+                kind,
+            }));
+        }
+        events
+    }
+}
@@ -0,0 +1,526 @@
+use crate::commands::AegisrCommand;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Current wire format version, sent with every encoded message.
+///
+/// Version 1 is `{"version":1,"command":"<snake_case name>","args":{...}}`.
+/// Messages without a version are the original encoding, serde's default
+/// `{"Put":{...}}`, and are still accepted.
+pub const WIRE_VERSION: u32 = 1;
+
+/// Result of executing an `AegisrCommand`, as sent back over the wire.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AegisrResponse {
+    Ok {
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<Value>,
+    },
+    Error {
+        message: String,
+    },
+    /// The receiver does not know this command (it is older than the sender).
+    Unsupported {
+        command: String,
+    },
+    /// A status this build does not know (the sender is newer).
+    #[serde(other)]
+    Unknown,
+}
+
+impl AegisrResponse {
+    /// Map a core "✓ …"/"✗ …" message onto a response.
+    pub fn from_message(message: String) -> Self {
+        if message.starts_with('✗') {
+            Self::Error { message }
+        } else {
+            Self::Ok {
+                message,
+                data: None,
+            }
+        }
+    }
+
+    /// The value of a successful response whose data is a single string
+    /// (`get`), exactly as stored, for writing to stdout undecorated;
+    /// `None` for errors and other responses, which should be rendered
+    /// to stderr instead.
+    pub fn raw(&self) -> Option<&str> {
+        match self {
+            Self::Ok {
+                data: Some(Value::String(value)),
+                ..
+            } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// `raw` converted to `format` (`get --as`); `None` where `raw` is.
+    pub fn value_as(&self, format: ValueFormat) -> Option<Result<String, String>> {
+        self.raw().map(|value| format.convert(value))
+    }
+
+    /// The response as the CLI prints it. `Plain` is the message, `Json`
+    /// the whole response (status, message and data) for scripts, and
+    /// `Table` lays list data out in aligned columns, falling back to the
+    /// message when there is none.
+    pub fn render(&self, format: OutputFormat) -> String {
+        let message = match self {
+            Self::Ok { message, .. } | Self::Error { message } => message.clone(),
+            Self::Unsupported { command } => format!("✗ Unsupported command '{}'", command),
+            Self::Unknown => "✗ Unknown response".to_string(),
+        };
+        match (format, self) {
+            (OutputFormat::Json, _) => serde_json::to_string_pretty(self).unwrap_or(message),
+            (
+                OutputFormat::Table,
+                Self::Ok {
+                    data: Some(data), ..
+                },
+            ) => Self::table(data).unwrap_or(message),
+            _ => message,
+        }
+    }
+
+    /// Rows and header for an array of objects, an array of scalars or an
+    /// object; `None` for anything else.
+    fn table(data: &Value) -> Option<String> {
+        let cell = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        let (header, rows): (Vec<String>, Vec<Vec<String>>) = match data {
+            Value::Array(items) if items.iter().all(Value::is_object) && !items.is_empty() => {
+                let mut columns: Vec<String> = Vec::new();
+                for item in items.iter().filter_map(Value::as_object) {
+                    for key in item.keys() {
+                        if !columns.contains(key) {
+                            columns.push(key.clone());
+                        }
+                    }
+                }
+                let rows = items
+                    .iter()
+                    .map(|item| {
+                        columns
+                            .iter()
+                            .map(|c| item.get(c).map(cell).unwrap_or_default())
+                            .collect()
+                    })
+                    .collect();
+                (columns, rows)
+            }
+            Value::Array(items) => (
+                vec!["value".to_string()],
+                items.iter().map(|v| vec![cell(v)]).collect(),
+            ),
+            Value::Object(map) => (
+                vec!["key".to_string(), "value".to_string()],
+                map.iter().map(|(k, v)| vec![k.clone(), cell(v)]).collect(),
+            ),
+            _ => return None,
+        };
+        let widths: Vec<usize> = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|r| r[i].chars().count())
+                    .chain([header[i].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |cells: &[String]| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(c, w)| format!("{:<w$}", c, w = *w))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        let upper: Vec<String> = header.iter().map(|h| h.to_uppercase()).collect();
+        let mut out = vec![line(&upper)];
+        out.extend(rows.iter().map(|r| line(r)));
+        Some(out.join("\n"))
+    }
+}
+
+/// How the CLI prints responses (the global `--output` flag).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The human-readable message.
+    #[default]
+    Plain,
+    /// Data in aligned columns.
+    Table,
+    /// The full response as JSON.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown output format '{}' (expected plain, table or json)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain => write!(f, "plain"),
+            Self::Table => write!(f, "table"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// How `get --as` prints a value. Everything but `Raw` reads the stored
+/// value as JSON, which is how structured values are kept, and writes it
+/// out in the requested representation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueFormat {
+    /// The value exactly as stored.
+    #[default]
+    Raw,
+    /// Pretty-printed JSON.
+    Json,
+    Yaml,
+    /// Only for objects; TOML has no null, so values holding one are refused.
+    Toml,
+}
+
+impl FromStr for ValueFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(Self::Raw),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            other => Err(format!(
+                "unknown value format '{}' (expected json, yaml, toml or raw)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ValueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raw => write!(f, "raw"),
+            Self::Json => write!(f, "json"),
+            Self::Yaml => write!(f, "yaml"),
+            Self::Toml => write!(f, "toml"),
+        }
+    }
+}
+
+impl ValueFormat {
+    /// `value` in this representation, without a trailing newline.
+    pub fn convert(self, value: &str) -> Result<String, String> {
+        if self == Self::Raw {
+            return Ok(value.to_string());
+        }
+        let parsed: Value = serde_json::from_str(value)
+            .map_err(|e| format!("the value is not JSON ({}); use --as raw", e))?;
+        let mut out = String::new();
+        match self {
+            Self::Raw => unreachable!(),
+            Self::Json => {
+                out = serde_json::to_string_pretty(&parsed).map_err(|e| e.to_string())?;
+            }
+            Self::Yaml => match &parsed {
+                Value::Object(map) if !map.is_empty() => yaml_block(&parsed, 0, false, &mut out),
+                Value::Array(items) if !items.is_empty() => yaml_block(&parsed, 0, false, &mut out),
+                scalar => out = yaml_scalar(scalar),
+            },
+            Self::Toml => {
+                let Value::Object(map) = &parsed else {
+                    return Err("only an object can be written as TOML".into());
+                };
+                toml_table(&[], map, &mut out)?;
+            }
+        }
+        Ok(out.trim().to_string())
+    }
+}
+
+/// A YAML scalar: strings stay plain only when they cannot be read back as
+/// anything else, otherwise they are double-quoted (JSON escapes are valid
+/// YAML). Empty containers are written in flow style.
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            let reserved = [
+                "true", "false", "yes", "no", "on", "off", "null", "y", "n", "~",
+            ];
+            let plain = s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '/')
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || " _-./@+".contains(c))
+                && !s.ends_with(' ')
+                && !reserved.contains(&s.to_ascii_lowercase().as_str());
+            if plain {
+                s.clone()
+            } else {
+                Value::String(s.clone()).to_string()
+            }
+        }
+        Value::Object(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Lines of a non-empty mapping or sequence at `indent`. With `inline`,
+/// the first line continues the current one (a mapping inside `- `).
+fn yaml_block(value: &Value, indent: usize, inline: bool, out: &mut String) {
+    let mut first = inline;
+    let mut pad = |out: &mut String| {
+        if !std::mem::take(&mut first) {
+            out.push_str(&" ".repeat(indent));
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                pad(out);
+                out.push_str(&yaml_scalar(&Value::String(key.clone())));
+                out.push(':');
+                match v {
+                    Value::Object(m) if !m.is_empty() => {
+                        out.push('\n');
+                        yaml_block(v, indent + 2, false, out);
+                    }
+                    Value::Array(items) if !items.is_empty() => {
+                        out.push('\n');
+                        yaml_block(v, indent, false, out);
+                    }
+                    scalar => {
+                        out.push(' ');
+                        out.push_str(&yaml_scalar(scalar));
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                pad(out);
+                out.push_str("- ");
+                match v {
+                    Value::Object(m) if !m.is_empty() => yaml_block(v, indent + 2, true, out),
+                    Value::Array(a) if !a.is_empty() => yaml_block(v, indent + 2, true, out),
+                    scalar => {
+                        out.push_str(&yaml_scalar(scalar));
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+/// A value on the right of `=`: scalars, arrays and inline tables.
+fn toml_inline(value: &Value, path: &str) -> Result<String, String> {
+    Ok(match value {
+        Value::Null => return Err(format!("TOML has no null (at '{}')", path)),
+        Value::Array(items) => {
+            let items: Result<Vec<String>, String> =
+                items.iter().map(|v| toml_inline(v, path)).collect();
+            format!("[{}]", items?.join(", "))
+        }
+        Value::Object(map) => {
+            let pairs: Result<Vec<String>, String> = map
+                .iter()
+                .map(|(k, v)| Ok(format!("{} = {}", toml_key(k), toml_inline(v, path)?)))
+                .collect();
+            format!("{{ {} }}", pairs?.join(", "))
+        }
+        // JSON string escapes, numbers and booleans are all valid TOML
+        scalar => scalar.to_string(),
+    })
+}
+
+/// `map` as the table at `path`: its plain values first, then sub-tables
+/// and arrays of tables under their own headers.
+fn toml_table(path: &[String], map: &Map<String, Value>, out: &mut String) -> Result<(), String> {
+    let header = |key: &str| {
+        let mut keys: Vec<String> = path.to_vec();
+        keys.push(toml_key(key));
+        keys
+    };
+    let mut tables = Vec::new();
+    let mut arrays = Vec::new();
+    for (key, v) in map {
+        match v {
+            Value::Object(m) => tables.push((header(key), m)),
+            Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+                arrays.push((header(key), items))
+            }
+            _ => {
+                let at = header(key).join(".");
+                out.push_str(&format!("{} = {}\n", toml_key(key), toml_inline(v, &at)?));
+            }
+        }
+    }
+    for (keys, m) in tables {
+        out.push_str(&format!("\n[{}]\n", keys.join(".")));
+        toml_table(&keys, m, out)?;
+    }
+    for (keys, items) in arrays {
+        for item in items.iter().filter_map(Value::as_object) {
+            out.push_str(&format!("\n[[{}]]\n", keys.join(".")));
+            toml_table(&keys, item, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// A decoded request: a command this build understands, or the name of one
+/// it does not, so the receiver can answer `Unsupported` instead of failing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedCommand {
+    Command(AegisrCommand),
+    Unsupported { name: String, version: u32 },
+}
+
+/// Versioned encoding of commands and responses shared by every client and
+/// daemon. Unknown fields are ignored and newer fields default, so peers
+/// from different crate versions keep working for the commands both know.
+pub struct AegWire;
+
+impl AegWire {
+    pub fn encode_command(command: &AegisrCommand) -> Result<String, String> {
+        Self::encode(serde_json::to_value(command).map_err(|e| e.to_string())?)
+    }
+
+    pub fn decode_command(input: &str) -> Result<DecodedCommand, String> {
+        let (version, value) = Self::decode(input)?;
+        let value = if version == 0 {
+            Self::upgrade_legacy(value)?
+        } else {
+            value
+        };
+        let name = value
+            .get("command")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "missing command name".to_string())?
+            .to_string();
+        match serde_json::from_value::<AegisrCommand>(value.clone()) {
+            Ok(command) => Ok(DecodedCommand::Command(command)),
+            // a command whose only arguments were dropped (e.g. `verbose`)
+            Err(e) if e.to_string().contains("expected unit variant") => {
+                let mut value = value;
+                if let Some(object) = value.as_object_mut() {
+                    object.remove("args");
+                }
+                serde_json::from_value(value)
+                    .map(DecodedCommand::Command)
+                    .map_err(|e| format!("invalid '{}' command: {}", name, e))
+            }
+            // only the command name itself, not a nested enum like a shell
+            Err(e)
+                if e.to_string()
+                    .starts_with(&format!("unknown variant `{}`", name)) =>
+            {
+                Ok(DecodedCommand::Unsupported { name, version })
+            }
+            Err(e) => Err(format!("invalid '{}' command: {}", name, e)),
+        }
+    }
+
+    pub fn encode_response(response: &AegisrResponse) -> Result<String, String> {
+        Self::encode(serde_json::to_value(response).map_err(|e| e.to_string())?)
+    }
+
+    pub fn decode_response(input: &str) -> Result<AegisrResponse, String> {
+        let (_, value) = Self::decode(input)?;
+        serde_json::from_value(value).map_err(|e| format!("invalid response: {}", e))
+    }
+
+    fn encode(mut value: Value) -> Result<String, String> {
+        let object = value
+            .as_object_mut()
+            .ok_or_else(|| "message must encode to an object".to_string())?;
+        object.insert("version".into(), WIRE_VERSION.into());
+        serde_json::to_string(&value).map_err(|e| e.to_string())
+    }
+
+    /// Parse a message and split off its version (0 when absent).
+    fn decode(input: &str) -> Result<(u32, Value), String> {
+        let mut value: Value =
+            serde_json::from_str(input).map_err(|e| format!("malformed message: {}", e))?;
+        let version = match value.as_object_mut().and_then(|o| o.remove("version")) {
+            Some(v) => v
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| "invalid version".to_string())?,
+            None => 0,
+        };
+        Ok((version, value))
+    }
+
+    /// Rewrite an unversioned `"List"` / `{"Put":{...}}` message into the
+    /// version 1 shape.
+    fn upgrade_legacy(value: Value) -> Result<Value, String> {
+        let (variant, args) = match value {
+            Value::String(variant) => (variant, None),
+            Value::Object(object) if object.len() == 1 => {
+                let (variant, args) = object.into_iter().next().unwrap_or_default();
+                (variant, Some(args))
+            }
+            _ => return Err("unrecognized message".into()),
+        };
+        let mut upgraded = Map::new();
+        upgraded.insert("command".into(), Self::snake_case(&variant).into());
+        if let Some(args) = args {
+            upgraded.insert("args".into(), args);
+        }
+        Ok(Value::Object(upgraded))
+    }
+
+    fn snake_case(variant: &str) -> String {
+        let mut out = String::with_capacity(variant.len() + 4);
+        for (i, c) in variant.chars().enumerate() {
+            if c.is_ascii_uppercase() {
+                if i > 0 {
+                    out.push('_');
+                }
+                out.push(c.to_ascii_lowercase());
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
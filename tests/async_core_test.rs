@@ -0,0 +1,22 @@
+#![cfg(feature = "tokio")]
+
+use aegisrlib::{AegCore, AegCoreAsync, AegTestHarness};
+use std::time::Duration;
+
+#[tokio::test]
+async fn async_api_round_trip() {
+    let _store = AegTestHarness::memory();
+    let saver = AegCoreAsync::start_background_saver(Duration::from_millis(50));
+
+    AegCoreAsync::put_value("async_key", "async value").await;
+    assert_eq!(
+        AegCoreAsync::get_value("async_key").await.as_deref(),
+        Some("async value")
+    );
+    saver.shutdown().await;
+    assert_eq!(AegCoreAsync::flush().await, 0);
+
+    AegCoreAsync::delete_value("async_key").await;
+    assert!(AegCoreAsync::get_value("async_key").await.is_none());
+    AegCore::flush_now();
+}
@@ -0,0 +1,76 @@
+use aegisrlib::{AegAudit, AegCore, AegCrypto, AegTestHarness, AuditAction, AuditHead, Cipher};
+use std::fs;
+
+#[test]
+fn audit_log_is_hash_chained_and_tampering_is_detected() {
+    let _store = AegTestHarness::temp_dir();
+
+    // nothing is recorded until auditing is enabled
+    AegCore::put_value("before", "1");
+    assert!(AegCore::audit_log().unwrap().is_empty());
+    assert_eq!(AegCore::verify_audit(None), "✓ Audit log is empty");
+    assert!(AegCore::audit_anchor().is_err());
+
+    assert!(AegCore::set_audit(true).starts_with('✓'));
+    AegCore::put_value("db/password", "hunter2");
+    AegCore::put_value("api/token", "abc");
+    AegCore::delete_value("api/token");
+    let entries = AegCore::audit_log().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].seq, 1);
+    assert_eq!(entries[0].key.as_deref(), Some("db/password"));
+    assert_eq!(entries[2].kind, AuditAction::Delete);
+    assert_eq!(entries[1].prev, entries[0].hash);
+    assert_eq!(entries[2].prev, entries[1].hash);
+    let log = fs::read_to_string(AegAudit::log_path()).unwrap();
+    assert!(!log.contains("hunter2"));
+
+    let anchor = AegCore::audit_anchor().unwrap();
+    assert_eq!(anchor.seq, 3);
+    assert_eq!(anchor.to_string().parse::<AuditHead>().unwrap(), anchor);
+    let anchor = anchor.to_string();
+    AegCore::put_value("after", "2");
+    assert!(AegCore::verify_audit(Some(&anchor)).starts_with("✓ Audit log intact (4 entries"));
+
+    // editing an entry breaks its hash, even re-encrypted with the log key
+    let full = fs::read_to_string(AegAudit::log_path()).unwrap();
+    let key = AegAudit::log_key().unwrap();
+    let lines: Vec<&str> = full.lines().collect();
+    let first = AegCrypto::decrypt_record(&key, lines[0]).unwrap();
+    let edited =
+        String::from_utf8(first.to_vec())
+            .unwrap()
+            .replacen("db/password", "db/username", 1);
+    let edited = AegCrypto::seal(Cipher::default(), &key, edited.as_bytes()).unwrap();
+    let mut tampered = vec![edited.as_str()];
+    tampered.extend(&lines[1..]);
+    tampered.push("");
+    fs::write(AegAudit::log_path(), tampered.join("\n")).unwrap();
+    let report = AegCore::verify_audit(None);
+    assert!(report.contains("entry 1 was modified"), "{}", report);
+
+    // and without the key a line no longer decrypts
+    fs::write(AegAudit::log_path(), full.replacen("aeg:", "aeg:x", 1)).unwrap();
+    assert!(AegCore::verify_audit(None).starts_with('✗'));
+
+    // removing an entry breaks the sequence
+    fs::write(
+        AegAudit::log_path(),
+        [lines[0], lines[2], lines[3], ""].join("\n"),
+    )
+    .unwrap();
+    assert!(AegCore::verify_audit(None).starts_with('✗'));
+
+    // cutting entries off the end is only caught against an anchor
+    fs::write(AegAudit::log_path(), [lines[0], lines[1], ""].join("\n")).unwrap();
+    assert!(AegCore::verify_audit(None).starts_with('✓'));
+    let report = AegCore::verify_audit(Some(&anchor));
+    assert!(report.contains("truncated"), "{}", report);
+    assert!(AegCore::verify_audit(Some("3:nothex")).starts_with("✗ invalid anchor"));
+
+    // disabling keeps the log and stops recording
+    fs::write(AegAudit::log_path(), &full).unwrap();
+    AegCore::set_audit(false);
+    AegCore::put_value("untracked", "3");
+    assert_eq!(AegCore::audit_log().unwrap().len(), 4);
+}
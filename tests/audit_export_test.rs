@@ -0,0 +1,105 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegAudit, AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, AuditAction,
+    AuditSource, PlainFormat,
+};
+
+#[test]
+fn access_reports_name_who_read_what_without_values() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::set_audit(true);
+
+    AegDispatch::execute(AegisrCommand::Put {
+        key: "db/password".into(),
+        value: "s3cr3t,\"quoted\"".into(),
+        env_name: None,
+        json: false,
+    });
+    let get = AegDispatch::execute(AegisrCommand::Get {
+        key: "db/password".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert!(matches!(get, AegisrResponse::Ok { .. }), "{:?}", get);
+    // a missing key hands nothing out, so leaves no receipt
+    AegDispatch::execute(AegisrCommand::Get {
+        key: "missing".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    AegAudit::with_source(AuditSource::Token("abc123".into()), || {
+        // the inner source does not override the outer one
+        AegDispatch::execute(AegisrCommand::Del {
+            key: "db/password".into(),
+        })
+    });
+    AegCore::put_value("library/key", "v");
+
+    let entries = AegCore::audit_log().unwrap();
+    let actions: Vec<AuditAction> = entries.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        actions,
+        [
+            AuditAction::Put,
+            AuditAction::Read,
+            AuditAction::Delete,
+            AuditAction::Put
+        ]
+    );
+    assert_eq!(entries[1].source, Some(AuditSource::Cli));
+    assert_eq!(entries[2].source, Some(AuditSource::Token("abc123".into())));
+    assert_eq!(entries[3].source, None);
+    assert!(AegCore::verify_audit(None).starts_with('✓'));
+
+    let csv = AegCore::export_audit(PlainFormat::Csv, None, None).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "seq,time,user,source,action,collection,key,hash");
+    assert_eq!(lines.len(), 5);
+    assert!(lines[2].contains(",cli,read,default,db/password,"));
+    assert!(lines[3].contains(",token:abc123,delete,"));
+    assert!(lines[4].contains(",library,put,"));
+    assert!(!csv.contains("s3cr3t"));
+
+    let json = AegDispatch::execute(AegisrCommand::AuditExport {
+        format: PlainFormat::Json,
+        from: Some("1970-01-01".into()),
+        to: None,
+    });
+    let AegisrResponse::Ok { message, .. } = json else {
+        panic!("{:?}", json);
+    };
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&message).unwrap();
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[1]["action"], "read");
+    assert!(!message.contains("s3cr3t"));
+
+    // nothing was recorded in 1970 or an hour from now
+    let now = entries[0].time;
+    assert!(
+        AegCore::export_audit(PlainFormat::Json, None, Some("1970-12-31"))
+            .unwrap()
+            .trim()
+            .eq("[]")
+    );
+    let from = (now + 3600).to_string();
+    assert_eq!(
+        AegCore::export_audit(PlainFormat::Csv, Some(&from), None)
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+    assert_eq!(
+        AegAudit::parse_time("2024-03-01", false).unwrap(),
+        1_709_251_200
+    );
+    assert_eq!(
+        AegAudit::parse_time("2024-03-01", true).unwrap(),
+        1_709_337_599
+    );
+    assert!(AegAudit::parse_time("2024-13-01", false).is_err());
+    assert!(AegCore::export_audit(PlainFormat::Dotenv, None, None).is_err());
+}
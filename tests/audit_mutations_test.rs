@@ -0,0 +1,86 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegAudit, AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, AuditAction,
+    AuditEntry, AuditFilter, MergeStrategy,
+};
+use std::fs;
+
+#[test]
+fn audit_log_records_collection_operations_encrypted() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::set_audit(true);
+
+    AegCore::create_collection("staging");
+    AegCore::put_qualified("staging::db_url", "postgres://staging");
+    AegCore::duplicate_collection("staging", "prod");
+    AegCore::put_qualified("staging::api_key", "k");
+    AegCore::merge_collections("staging", "prod", MergeStrategy::KeepDest).unwrap();
+    AegCore::rename_collection("staging", "stage");
+    AegCore::delete_collection("stage");
+
+    let kinds: Vec<AuditAction> = AegCore::read_audit_log(&AuditFilter::default())
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.key.is_none())
+        .map(|e| e.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AuditAction::CreateCollection,
+            AuditAction::CloneCollection,
+            AuditAction::MergeCollection,
+            AuditAction::RenameCollection,
+            AuditAction::DeleteCollection,
+        ]
+    );
+    let renamed = AegCore::read_audit_log(&AuditFilter {
+        kind: Some(AuditAction::RenameCollection),
+        ..AuditFilter::default()
+    })
+    .unwrap();
+    assert_eq!(renamed[0].collection, "staging");
+    assert_eq!(renamed[0].target.as_deref(), Some("stage"));
+
+    // the log on disk holds neither values nor key names
+    let raw = fs::read_to_string(AegAudit::log_path()).unwrap();
+    assert!(!raw.contains("db_url") && !raw.contains("postgres"));
+    assert!(AegCore::verify_audit(None).starts_with('✓'));
+
+    let response = AegDispatch::execute(AegisrCommand::AuditLog {
+        since: Some("2000-01-01".into()),
+        until: None,
+        collection: Some("staging".into()),
+        key: Some("db_url".into()),
+    });
+    match response {
+        AegisrResponse::Ok { data, .. } => {
+            let entries: Vec<AuditEntry> = serde_json::from_value(data.unwrap()).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].kind, AuditAction::Put);
+        }
+        other => panic!("{:?}", other),
+    }
+    let future = AegCore::read_audit_log(&AuditFilter {
+        since: Some(AegAudit::parse_time("2999-01-01", false).unwrap()),
+        ..AuditFilter::default()
+    })
+    .unwrap();
+    assert!(future.is_empty());
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::AuditLog {
+            since: Some("yesterday".into()),
+            until: None,
+            collection: None,
+            key: None,
+        }),
+        AegisrResponse::Error { .. }
+    ));
+
+    // re-keying the store re-encrypts the log with it
+    let before = AegCore::audit_log().unwrap();
+    assert!(AegCore::change_passphrase(None, Some("pw"), |_| {}).starts_with('✓'));
+    assert_eq!(AegCore::audit_log().unwrap(), before);
+    assert!(AegCore::verify_audit(None).starts_with('✓'));
+}
@@ -0,0 +1,40 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness, PersistencePolicy};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[test]
+fn the_background_saver_skips_collections_without_autosave() {
+    let _store = AegTestHarness::memory();
+    AegCore::create_collection("scratch");
+    assert!(AegCore::set_autosave("scratch", false).starts_with('✓'));
+    AegCore::put_value("kept", "v");
+    AegCore::put_qualified("scratch::tmp", "x");
+
+    // what the saver does on each tick
+    assert_eq!(AegMemoryEngine::save_autosave(), 1);
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    assert_eq!(AegMemoryEngine::cached_dirty("scratch"), Some(true));
+
+    // a running saver leaves it dirty, on its ticks and when it stops
+    let saver = AegCore::set_persistence_policy(PersistencePolicy::Debounced(50)).unwrap();
+    AegCore::put_value("kept", "w");
+    AegCore::put_qualified("scratch::tmp", "y");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while AegMemoryEngine::cached_dirty("default") != Some(false) && Instant::now() < deadline {
+        sleep(Duration::from_millis(20));
+    }
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    assert_eq!(AegMemoryEngine::cached_dirty("scratch"), Some(true));
+    AegCore::set_persistence_policy(PersistencePolicy::Manual);
+    assert!(!saver.is_running());
+    assert_eq!(AegMemoryEngine::cached_dirty("scratch"), Some(true));
+
+    // only an explicit flush saves it
+    AegCore::flush_now();
+    assert_eq!(AegMemoryEngine::cached_dirty("scratch"), Some(false));
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("scratch::tmp").unwrap().as_deref(),
+        Some("y")
+    );
+}
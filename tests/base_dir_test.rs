@@ -0,0 +1,21 @@
+use aegisrlib::{AegCore, AegFileSystem, Verbosity};
+
+#[test]
+fn store_dir_can_be_redirected() {
+    let dir = std::env::temp_dir().join(format!("aegisr_home_{}", std::process::id()));
+    let msg = AegCore::set_store_dir(dir.clone());
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(
+        AegFileSystem::initialize_config(Some(false), Verbosity::default()),
+        dir
+    );
+
+    AegCore::put_value("base_dir_key", "isolated");
+    AegCore::flush_now();
+    assert!(dir.join("AUTHORIZATION_KEY").exists());
+    assert!(dir.join("collection_default.aekv").exists());
+    assert_eq!(AegCore::get_value("base_dir_key").unwrap(), "isolated");
+
+    AegFileSystem::clear_base_dir();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,66 @@
+use aegisrlib::{
+    AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness, AekvHeader,
+    Cipher, Codec,
+};
+use std::fs;
+
+#[test]
+fn collections_are_saved_as_cbor_and_json_files_still_load() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let master = AegFileSystem::read_authorization_key();
+    let key = AegCrypto::derive_collection_key(&master, "default").unwrap();
+    let file = dir.join("collection_default.aekv");
+
+    for i in 0..50 {
+        AegCore::put_value(&format!("service_{}/token", i), &format!("value-{}", i));
+    }
+    AegCore::flush_now();
+    let saved = fs::read(&file).unwrap();
+    assert_eq!(
+        AekvHeader::parse(&saved).unwrap().unwrap().codec,
+        Codec::Cbor
+    );
+    let plain = AegFileFormat::decode(&key, &saved).unwrap();
+    let engine: AegMemoryEngine = Codec::Cbor.deserialize(&plain).unwrap();
+    assert_eq!(engine.get("service_7/token").as_deref(), Some("value-7"));
+    assert!(plain.len() < serde_json::to_vec_pretty(&engine).unwrap().len());
+    let report = AegCore::verify_store();
+    assert!(report.passed(), "{}", report.summary());
+
+    // a version 1 file, from before the codec flag, holds JSON
+    let json = serde_json::to_vec_pretty(&engine).unwrap();
+    let header = AekvHeader {
+        version: 1,
+        cipher: Cipher::Aes256Gcm,
+        compressed: false,
+        codec: Codec::Json,
+        nonce: AegCrypto::random_nonce().unwrap(),
+        checksum: None,
+        wrapped_key: None,
+    };
+    let sealed = AegCrypto::seal_with_nonce(
+        header.cipher,
+        &key,
+        &header.nonce,
+        &header.to_bytes(),
+        &json,
+    )
+    .unwrap();
+    fs::write(&file, [header.to_bytes().as_slice(), &sealed].concat()).unwrap();
+    assert_eq!(
+        AegFileFormat::codec_of(&fs::read(&file).unwrap()),
+        Codec::Json
+    );
+    assert!(AegFileFormat::needs_upgrade(&fs::read(&file).unwrap()));
+
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("service_3/token").unwrap(), "value-3");
+    let upgraded = fs::read(&file).unwrap();
+    assert_eq!(AegFileFormat::codec_of(&upgraded), Codec::Cbor);
+
+    // a version 1 header cannot claim the CBOR flag it did not have
+    let mut bogus = header.to_bytes();
+    bogus[6] |= 0b10;
+    assert!(AekvHeader::parse(&bogus).is_err());
+}
@@ -0,0 +1,210 @@
+use aegisrlib::{
+    AegCore, AegCoreBuilder, AegFileSystem, AegMemoryEngine, AegTestHarness, Cipher, KeyValueStore,
+    MemoryStorage, PersistencePolicy,
+};
+use std::fs;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+#[test]
+fn independent_stores_in_one_process() {
+    let root = std::env::temp_dir().join(format!("aegisr_builder_{}", std::process::id()));
+    let first = AegCoreBuilder::new()
+        .base_dir(root.join("first"))
+        .cipher(Cipher::ChaCha20Poly1305)
+        .persistence(PersistencePolicy::Debounced(500))
+        .build()
+        .unwrap();
+    let memory = Arc::new(MemoryStorage::new());
+    let second = AegCoreBuilder::new()
+        .base_dir(root.join("second"))
+        .storage(memory.clone())
+        .build()
+        .unwrap();
+    assert!(!root.join("second").exists());
+
+    first.put("api", "first secret").unwrap();
+    second.put("api", "second secret").unwrap();
+    second.put("only_second", "x").unwrap();
+    assert_eq!(first.get("api").unwrap(), "first secret");
+    assert_eq!(second.get("api").unwrap(), "second secret");
+    assert_eq!(first.list(), vec!["api"]);
+    assert_eq!(second.list(), vec!["api", "only_second"]);
+    assert!(first.delete("api").unwrap());
+    assert!(!first.delete("api").unwrap());
+    assert_eq!(second.get("api").unwrap(), "second secret");
+
+    // each run works on its own store
+    let (cipher, policy) = first.run(|| {
+        (
+            AegFileSystem::read_store_config().cipher,
+            AegMemoryEngine::persistence_policy(),
+        )
+    });
+    assert_eq!(cipher, Cipher::ChaCha20Poly1305);
+    assert_eq!(policy, PersistencePolicy::Debounced(500));
+    first.put("api", "again").unwrap();
+    let (cipher, policy) = second.run(|| {
+        (
+            AegFileSystem::read_store_config().cipher,
+            AegMemoryEngine::persistence_policy(),
+        )
+    });
+    assert_eq!(cipher, Cipher::default());
+    assert_eq!(policy, PersistencePolicy::Manual);
+    assert_eq!(
+        second.run(|| AegCore::get_value("only_second")),
+        Some("x".into())
+    );
+
+    // dropping a store saves it to its own storage and directory
+    drop(second);
+    assert!(
+        memory
+            .contents()
+            .iter()
+            .any(|(path, _)| path.ends_with("collection_default.aekv"))
+    );
+    drop(first);
+    assert!(root.join("first").join("collection_default.aekv").exists());
+
+    let reopened = AegCoreBuilder::new()
+        .base_dir(root.join("first"))
+        .build()
+        .unwrap();
+    assert_eq!(reopened.get("api").unwrap(), "again");
+    drop(reopened);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn each_store_keeps_its_own_cache() {
+    let first = AegCoreBuilder::new()
+        .base_dir("/aegisr_cache/first")
+        .storage(Arc::new(MemoryStorage::new()))
+        .build()
+        .unwrap();
+    let second = AegCoreBuilder::new()
+        .base_dir("/aegisr_cache/second")
+        .storage(Arc::new(MemoryStorage::new()))
+        .build()
+        .unwrap();
+
+    first.put("shared_name", "first").unwrap();
+    second.put("shared_name", "second").unwrap();
+    // using another store neither unloads nor saves the first one
+    assert_eq!(
+        first.run(AegMemoryEngine::cached_collections),
+        vec!["default"]
+    );
+    assert_eq!(
+        first.run(|| AegMemoryEngine::cached_dirty("default")),
+        Some(true)
+    );
+    assert_eq!(first.get("shared_name").unwrap(), "first");
+
+    // unloading one store's collections leaves the other's alone
+    second.flush().unwrap();
+    second.run(AegMemoryEngine::reset_cache);
+    assert_eq!(
+        first.run(AegMemoryEngine::cached_collections),
+        vec!["default"]
+    );
+    assert_eq!(second.get("shared_name").unwrap(), "second");
+}
+
+#[test]
+fn stores_run_in_parallel_with_the_static_api() {
+    let harness = AegTestHarness::memory();
+    AegCore::put_value("owner", "static");
+    let stores: Vec<_> = ["/aegisr_parallel/a", "/aegisr_parallel/b"]
+        .into_iter()
+        .map(|dir| {
+            AegCoreBuilder::new()
+                .base_dir(dir)
+                .storage(Arc::new(MemoryStorage::new()))
+                .persistence(PersistencePolicy::Interval(60))
+                .build()
+                .unwrap()
+        })
+        .collect();
+
+    // both stores are inside `run` at the same time, each seeing only itself
+    let barrier = Arc::new(Barrier::new(stores.len()));
+    let workers: Vec<_> = stores
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, store)| {
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                store.run(|| {
+                    barrier.wait();
+                    for n in 0..50 {
+                        AegCore::put_value(&format!("k{}", n), &format!("store{}", i));
+                    }
+                    barrier.wait();
+                    (
+                        AegCore::list_keys().len(),
+                        AegCore::get_value("k0").map(|v| v.to_string()),
+                        AegCore::get_value("owner").is_some(),
+                        AegMemoryEngine::persistence_policy(),
+                    )
+                })
+            })
+        })
+        .collect();
+    for (i, worker) in workers.into_iter().enumerate() {
+        assert_eq!(
+            worker.join().unwrap(),
+            (
+                50,
+                Some(format!("store{}", i)),
+                false,
+                PersistencePolicy::Interval(60)
+            )
+        );
+    }
+
+    // the static API kept its own store, cache and saver throughout
+    assert_eq!(AegCore::list_keys(), vec!["owner"]);
+    assert_eq!(
+        AegMemoryEngine::persistence_policy(),
+        PersistencePolicy::Manual
+    );
+    assert_eq!(
+        stores[1].run(|| stores[0].run(|| AegCore::get_value("k1").map(|v| v.to_string()))),
+        Some("store0".to_string())
+    );
+    drop(stores);
+    assert_eq!(AegFileSystem::get_config_path(), harness.dir());
+    assert_eq!(AegCore::get_value("owner").as_deref(), Some("static"));
+}
+
+#[test]
+fn dropping_a_store_leaves_the_static_api_alone() {
+    let harness = AegTestHarness::memory();
+    AegCore::set_persistence_policy(PersistencePolicy::Interval(60));
+    AegCore::put_value("pending", "unsaved");
+    let store = AegCoreBuilder::new()
+        .base_dir("/aegisr_drop/store")
+        .storage(Arc::new(MemoryStorage::new()))
+        .build()
+        .unwrap();
+    store.put("api", "secret").unwrap();
+    drop(store);
+
+    assert_eq!(AegFileSystem::get_config_path(), harness.dir());
+    assert_eq!(
+        AegMemoryEngine::persistence_policy(),
+        PersistencePolicy::Interval(60)
+    );
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+    assert_eq!(
+        AegCore::get_value("pending").unwrap().to_string(),
+        "unsaved"
+    );
+    assert!(AegCore::get_value("api").is_none());
+    AegCore::set_persistence_policy(PersistencePolicy::Manual);
+}
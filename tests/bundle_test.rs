@@ -0,0 +1,35 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn export_and_import_collection_bundle() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "bundle_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::put_value("api_token", "t0k3n");
+    AegCore::put_value("db_url", "postgres://localhost");
+
+    let path = std::env::temp_dir().join(format!("aegisr_bundle_{}.json", std::process::id()));
+    let msg = AegCore::export_collection(collection, &path, "correct horse");
+    assert!(msg.starts_with('✓'), "{}", msg);
+
+    let msg = AegCore::import_collection(&path, "wrong password");
+    assert!(msg.starts_with('✗'), "{}", msg);
+
+    // local edits are overwritten by the bundle on import
+    AegCore::put_value("api_token", "changed");
+    AegCore::delete_value("db_url");
+    let msg = AegCore::import_collection(&path, "correct horse");
+    assert!(msg.contains("1 created, 1 overwritten"), "{}", msg);
+    assert_eq!(AegCore::get_value("api_token").unwrap(), "t0k3n");
+    assert_eq!(AegCore::get_value("db_url").unwrap(), "postgres://localhost");
+
+    std::fs::remove_file(&path).unwrap();
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
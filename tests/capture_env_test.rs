@@ -0,0 +1,31 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn capture_env_filters_by_prefix() {
+    let _store = AegTestHarness::memory();
+    let collection = "capture_env_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    // SAFETY: this test binary runs a single test, so no other thread reads the environment
+    unsafe {
+        std::env::set_var("AEGISR_CAPTURE_TEST_A", "alpha");
+        std::env::set_var("AEGISR_CAPTURE_TEST_B", "beta");
+    }
+
+    let msg = AegCore::capture_env(Some("AEGISR_CAPTURE_TEST_"));
+    assert!(msg.contains("Captured 2"), "{}", msg);
+    assert_eq!(
+        AegCore::get_value("AEGISR_CAPTURE_TEST_A").unwrap(),
+        "alpha"
+    );
+    assert_eq!(AegCore::get_value("AEGISR_CAPTURE_TEST_B").unwrap(), "beta");
+    assert!(AegCore::get_value("PATH").is_none());
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
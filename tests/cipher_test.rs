@@ -0,0 +1,32 @@
+use aegisrlib::{
+    AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness, Cipher,
+};
+use std::fs;
+
+#[test]
+fn chacha20_files_are_tagged_and_stay_readable() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    assert_eq!(AegFileSystem::read_store_config().cipher, Cipher::Aes256Gcm);
+    assert_eq!("chacha20".parse::<Cipher>(), Ok(Cipher::ChaCha20Poly1305));
+
+    // an AES-GCM file from before the envelope existed
+    let master = AegFileSystem::read_authorization_key();
+    let key = AegCrypto::derive_collection_key(&master, "default").unwrap();
+    let json = serde_json::to_vec(&AegMemoryEngine::new("default")).unwrap();
+    let file = dir.join("collection_default.aekv");
+    fs::write(&file, AegTestHarness::legacy_blob(&key, &json)).unwrap();
+    AegMemoryEngine::reset_cache();
+
+    assert!(AegCore::set_cipher(Cipher::ChaCha20Poly1305).starts_with('✓'));
+    AegCore::put_value("cipher_key", "sealed");
+    AegCore::flush_now();
+    let on_disk = fs::read(&file).unwrap();
+    assert_eq!(AegFileFormat::cipher_of(&on_disk), Cipher::ChaCha20Poly1305);
+    assert!(AegCore::verify_store().passed());
+
+    // switching back leaves the ChaCha20 file readable
+    AegCore::set_cipher(Cipher::Aes256Gcm);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("cipher_key").unwrap(), "sealed");
+}
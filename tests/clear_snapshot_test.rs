@@ -0,0 +1,41 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn clear_snapshots_first_and_restore_last_undoes_it() {
+    let _store = AegTestHarness::temp_dir();
+    assert_eq!(
+        AegCore::restore_last_snapshot(),
+        "✗ There are no snapshots to restore"
+    );
+    AegCore::put_value("saved", "on disk");
+    AegCore::flush_now();
+    AegCore::put_value("unsaved", "only in memory");
+
+    let message = AegCore::clear_values();
+    assert!(message.starts_with("✓ All keys cleared"), "{}", message);
+    assert!(message.contains("snapshot restore --last"), "{}", message);
+    let snapshots = AegCore::list_snapshots();
+    assert_eq!(snapshots.len(), 1);
+    assert!(snapshots[0].label.starts_with("pre-clear-default-"));
+    assert_eq!(AegCore::get_value("saved"), None);
+
+    // a second clear in the same second still gets its own snapshot
+    assert!(AegCore::clear_values().starts_with('✓'));
+    assert_eq!(AegCore::list_snapshots().len(), 2);
+
+    let restored = AegCore::restore_last_snapshot();
+    assert!(restored.starts_with('✓'), "{}", restored);
+    assert_eq!(AegCore::get_value("saved"), None);
+    let first = &AegCore::list_snapshots()[0].label;
+    assert!(AegCore::restore_snapshot(first).starts_with('✓'));
+    assert_eq!(AegCore::get_value("saved").as_deref(), Some("on disk"));
+    assert_eq!(
+        AegCore::get_value("unsaved").as_deref(),
+        Some("only in memory")
+    );
+
+    assert!(AegCore::set_clear_snapshot(false).contains("disabled"));
+    let message = AegCore::clear_values();
+    assert!(!message.contains("Snapshot"), "{}", message);
+    assert_eq!(AegCore::list_snapshots().len(), 2);
+}
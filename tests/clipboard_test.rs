@@ -0,0 +1,62 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegClipboard, AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse,
+    ClipboardBackend, MemoryClipboard,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn clip(key: &str, clear_after: Option<u64>) -> AegisrResponse {
+    AegDispatch::execute(AegisrCommand::Get {
+        key: key.into(),
+        no_cache: false,
+        clip: true,
+        clear_after,
+    })
+}
+
+#[test]
+fn get_clip_copies_the_value_without_echoing_it_and_clears_it() {
+    let _store = AegTestHarness::temp_dir();
+    let clipboard = Arc::new(MemoryClipboard::new());
+    AegClipboard::set_backend(clipboard.clone());
+    AegCore::put_value("db_password", "hunter2");
+
+    match clip("db_password", Some(1)) {
+        AegisrResponse::Ok {
+            message,
+            data: Some(data),
+        } => {
+            assert!(!message.contains("hunter2"), "{}", message);
+            assert!(!data.to_string().contains("hunter2"), "{}", data);
+            assert!(message.contains("clears in 1 s"), "{}", message);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(clipboard.get().unwrap().as_deref(), Some("hunter2"));
+    AegClipboard::wait_pending();
+    assert_eq!(clipboard.get().unwrap(), None);
+
+    // 0 keeps the value on the clipboard
+    assert!(matches!(
+        clip("db_password", Some(0)),
+        AegisrResponse::Ok { .. }
+    ));
+    AegClipboard::wait_pending();
+    assert_eq!(clipboard.get().unwrap().as_deref(), Some("hunter2"));
+
+    // something copied since is left alone
+    AegClipboard::copy("hunter2", Some(Duration::from_millis(50))).unwrap();
+    clipboard.set("copied by someone else").unwrap();
+    AegClipboard::wait_pending();
+    assert_eq!(
+        clipboard.get().unwrap().as_deref(),
+        Some("copied by someone else")
+    );
+
+    assert!(matches!(
+        clip("missing", None),
+        AegisrResponse::Error { .. }
+    ));
+}
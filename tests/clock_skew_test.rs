@@ -0,0 +1,55 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegClock, AegCore, AegDispatch, AegMemoryEngine, AegSnippet, AegTestHarness, AegisrCommand,
+    ClockSkewPolicy, OutputFormat, SNIPPET_COLLECTION,
+};
+use std::fs;
+use std::time::Duration;
+
+#[test]
+fn expiry_holds_off_while_the_clock_has_gone_back() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let now = AegClock::now();
+    assert_eq!(AegClock::skew_detected(), None);
+    let status = || AegDispatch::execute(AegisrCommand::Status).render(OutputFormat::Plain);
+    assert!(!status().contains('⚠'));
+
+    let forever = AegSnippet::add("no expiry", None).unwrap();
+    let leased = AegSnippet::add("leased here", Some(Duration::from_secs(3600))).unwrap();
+    let stale = AegSnippet::add("from another run", Some(Duration::from_secs(3600))).unwrap();
+    // stored by some other process, with a deadline that has passed
+    AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&engine.get(&stale.id).unwrap()).unwrap();
+        json["expires_at"] = (now - 10).into();
+        engine.insert(stale.id.clone(), json.to_string());
+    });
+
+    // an earlier run saw a time an hour ahead of the clock now
+    fs::write(dir.join("clock"), (now + 3600).to_string()).unwrap();
+    let skew = AegClock::skew_detected().unwrap();
+    assert!((-3601..=-3599).contains(&skew), "{}", skew);
+    assert!(status().contains("⚠ System clock is"), "{}", status());
+
+    // fail-open keeps serving and deletes nothing
+    let got = AegSnippet::get(&stale.id).unwrap().unwrap();
+    assert_eq!(got.text, "from another run");
+    assert_eq!(AegSnippet::list().len(), 3);
+
+    assert!(AegCore::set_clock_skew_policy(ClockSkewPolicy::FailClosed).contains("fail-closed"));
+    let error = AegSnippet::get(&stale.id).unwrap_err();
+    assert!(error.contains("clock is off"), "{}", error);
+    // no expiry, or a lease timed on this process's monotonic clock
+    assert!(AegSnippet::get(&forever.id).unwrap().is_some());
+    assert!(AegSnippet::get(&leased.id).unwrap().is_some());
+
+    // once the clock is trusted again, the deadline applies
+    fs::write(dir.join("clock"), now.to_string()).unwrap();
+    assert_eq!(AegClock::skew_detected(), None);
+    assert_eq!(AegSnippet::get(&stale.id).unwrap(), None);
+    assert_eq!(AegSnippet::list().len(), 2);
+    assert!("closed".parse::<ClockSkewPolicy>().is_ok());
+    assert!("maybe".parse::<ClockSkewPolicy>().is_err());
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegTestHarness, AegisrCommand, AegisrResponse,
+};
+
+#[test]
+fn clone_copies_entries_metadata_and_settings() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("prod");
+    AegCore::set_autosave("prod", false);
+    AegCore::put_qualified("prod::db_url", "postgres://old");
+    AegCore::put_qualified("prod::db_url", "postgres://prod");
+    AegCore::put_qualified("prod::api_key", "k");
+    AegMemoryEngine::with_engine("prod", |engine| {
+        engine.tag("api_key", "rotate");
+        engine
+            .set_env_name("db_url", Some("DATABASE_URL".into()))
+            .unwrap();
+    });
+    AegCore::flush_now();
+
+    let response = AegDispatch::execute(AegisrCommand::Clone {
+        src: "prod".into(),
+        dest: "staging".into(),
+    });
+    match response {
+        AegisrResponse::Ok { message, .. } => assert!(message.contains("2 keys"), "{}", message),
+        other => panic!("{:?}", other),
+    }
+    let core = AegCore::load();
+    assert!(core.collections.contains(&"staging".to_string()));
+    assert!(!core.is_autosave_enabled("staging"));
+
+    let (api_key, env_name, history) = AegMemoryEngine::read_engine("staging", |engine| {
+        (
+            engine.entry("api_key").unwrap(),
+            engine
+                .key_meta
+                .get("db_url")
+                .and_then(|m| m.env_name.clone()),
+            engine.history("db_url"),
+        )
+    });
+    let original = AegMemoryEngine::read_engine("prod", |engine| engine.entry("api_key").unwrap());
+    assert_eq!(api_key, original);
+    assert_eq!(api_key.tags, vec!["rotate"]);
+    assert_eq!(env_name.as_deref(), Some("DATABASE_URL"));
+    let values: Vec<&str> = history.iter().map(|v| v.value.as_str()).collect();
+    assert_eq!(values, vec!["postgres://old", "postgres://prod"]);
+
+    // the copy is independent of its source and survives a reload
+    AegCore::put_qualified("staging::db_url", "postgres://staging");
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("staging::db_url")
+            .unwrap()
+            .as_deref(),
+        Some("postgres://staging")
+    );
+    assert_eq!(
+        AegCore::get_qualified("prod::db_url").unwrap().as_deref(),
+        Some("postgres://prod")
+    );
+
+    assert!(AegCore::duplicate_collection("prod", "staging").starts_with('✗'));
+    assert!(AegCore::duplicate_collection("missing", "other").starts_with('✗'));
+    assert!(!AegCore::load().collections.contains(&"other".to_string()));
+}
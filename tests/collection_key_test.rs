@@ -0,0 +1,43 @@
+use aegisrlib::{
+    AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness,
+};
+use std::fs;
+
+#[test]
+fn collections_use_their_own_keys() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let master = AegFileSystem::read_authorization_key();
+
+    AegCore::create_collection("other");
+    AegCore::put_value("shared_name", "default value");
+    AegMemoryEngine::with_engine("other", |engine| {
+        engine.insert("shared_name", "other value")
+    });
+    AegCore::flush_now();
+
+    let default_key = AegCrypto::derive_collection_key(&master, "default").unwrap();
+    let other_key = AegCrypto::derive_collection_key(&master, "other").unwrap();
+    assert_ne!(default_key, other_key);
+    assert_ne!(default_key, master);
+
+    let default_file = dir.join("collection_default.aekv");
+    let on_disk = fs::read(&default_file).unwrap();
+    let plain = AegFileFormat::decode(&default_key, &on_disk).unwrap();
+    assert!(AegFileFormat::decode(&other_key, &on_disk).is_err());
+    assert!(AegFileFormat::decode(&master, &on_disk).is_err());
+
+    // a file written before per-collection keys still loads, and is migrated;
+    // those files were always JSON
+    let value: serde_json::Value = AegFileFormat::codec_of(&on_disk)
+        .deserialize(&plain)
+        .unwrap();
+    let plain = serde_json::to_vec(&value).unwrap();
+    fs::write(&default_file, AegTestHarness::legacy_blob(&master, &plain)).unwrap();
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("shared_name").unwrap(), "default value");
+    let migrated = fs::read(&default_file).unwrap();
+    assert!(AegFileFormat::decode(&default_key, &migrated).is_ok());
+    let report = AegCore::verify_store();
+    assert!(report.passed(), "{}", report.summary());
+}
@@ -0,0 +1,127 @@
+use aegisrlib::{
+    AegCore, AegCoreBuilder, AegCrypto, AegFileSystem, AegTestHarness, MemoryStorage,
+    STORE_COLLECTION, StorageBackend,
+};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Memory storage that keeps everything ever written to collection.lock.
+#[derive(Default)]
+struct LockWrites {
+    inner: MemoryStorage,
+    written: Mutex<Vec<Vec<u8>>>,
+}
+
+impl StorageBackend for LockWrites {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if path.ends_with(STORE_COLLECTION) {
+            self.written.lock().unwrap().push(contents.to_vec());
+        }
+        self.inner.write(path, contents)
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<u64> {
+        self.inner.append(path, contents)
+    }
+    fn read_at(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        self.inner.read_at(path, offset, len)
+    }
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(dir)
+    }
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        self.inner.remove_dir_all(dir)
+    }
+    fn list(&self, dir: &Path) -> io::Result<Vec<String>> {
+        self.inner.list(dir)
+    }
+    fn list_dirs(&self, dir: &Path) -> io::Result<Vec<String>> {
+        self.inner.list_dirs(dir)
+    }
+    fn try_lock(&self, path: &Path) -> io::Result<Option<Box<dyn Send>>> {
+        self.inner.try_lock(path)
+    }
+}
+
+#[test]
+fn every_lock_write_uses_a_fresh_nonce() {
+    let store = AegTestHarness::temp_dir();
+    let path = store.dir().join(STORE_COLLECTION);
+    let json = AegFileSystem::read_collection_lock().unwrap();
+    let auth_key = AegFileSystem::read_authorization_key();
+
+    AegFileSystem::write_collection_lock_json(&json, &auth_key);
+    let first = fs::read_to_string(&path).unwrap();
+    AegFileSystem::write_collection_lock_json(&json, &auth_key);
+    let second = fs::read_to_string(&path).unwrap();
+    assert!(AegCrypto::envelope_cipher(&first).is_some());
+    assert_ne!(first, second);
+    assert_eq!(AegFileSystem::read_collection_lock().unwrap(), json);
+}
+
+#[test]
+fn a_fixed_nonce_lock_is_read_and_rewritten() {
+    let store = AegTestHarness::temp_dir();
+    AegCore::create_collection("legacy");
+    let path = store.dir().join(STORE_COLLECTION);
+    let json = AegFileSystem::read_collection_lock().unwrap();
+    let auth_key = AegFileSystem::read_authorization_key();
+    fs::write(
+        &path,
+        AegTestHarness::legacy_blob(&auth_key, json.as_bytes()),
+    )
+    .unwrap();
+
+    assert!(AegCore::load().collections.contains(&"legacy".to_string()));
+    let migrated = fs::read_to_string(&path).unwrap();
+    assert!(AegCrypto::envelope_cipher(&migrated).is_some());
+    assert_eq!(AegFileSystem::read_collection_lock().unwrap(), json);
+}
+
+#[test]
+fn a_lock_that_does_not_decrypt_is_an_error() {
+    let store = AegTestHarness::temp_dir();
+    let path = store.dir().join(STORE_COLLECTION);
+    let other_key = AegCrypto::create_authorization_key(Default::default());
+    fs::write(&path, AegTestHarness::legacy_blob(&other_key, b"{}")).unwrap();
+
+    let err = AegFileSystem::read_collection_lock().unwrap_err();
+    assert!(err.starts_with(STORE_COLLECTION), "{}", err);
+    assert!(AegCore::try_load().is_err());
+    fs::write(&path, "not base64 at all").unwrap();
+    assert!(AegFileSystem::read_collection_lock_obj().is_err());
+}
+
+#[test]
+fn the_collection_list_is_never_written_in_plaintext() {
+    let storage = Arc::new(LockWrites::default());
+    let store = AegCoreBuilder::new()
+        .base_dir("/aegisr_lock_writes")
+        .storage(storage.clone())
+        .build()
+        .unwrap();
+    store.run(|| {
+        AegCore::create_collection("payroll");
+        AegCore::rename_collection("payroll", "salaries");
+        let mut core = AegCore::load();
+        core.set_active_collection("salaries").unwrap();
+    });
+
+    let written = storage.written.lock().unwrap();
+    assert!(written.len() > 2);
+    for contents in written.iter() {
+        let text = String::from_utf8_lossy(contents);
+        assert!(AegCrypto::envelope_cipher(&text).is_some(), "{}", text);
+        assert!(!text.contains("payroll") && !text.contains("salaries"));
+    }
+}
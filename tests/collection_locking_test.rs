@@ -0,0 +1,44 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn a_busy_collection_does_not_block_the_others() {
+    let _store = AegTestHarness::memory();
+    AegCore::create_collection("busy");
+    AegCore::create_collection("idle");
+    AegMemoryEngine::with_engine("idle", |engine| engine.insert("key", "value"));
+
+    // hold the write lock of one collection until told to let go
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let writer = thread::spawn(move || {
+        AegMemoryEngine::with_engine("busy", |engine| {
+            engine.insert("key", "value");
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+    });
+    locked_rx.recv().unwrap();
+
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let read = AegMemoryEngine::fetch_shared("idle", "key").map(|v| v.to_string());
+        AegMemoryEngine::with_engine("idle", |engine| engine.insert("other", "value"));
+        let saved = AegMemoryEngine::save_collection("idle");
+        done_tx.send((read, saved)).unwrap();
+    });
+    let outcome = done_rx.recv_timeout(Duration::from_secs(10));
+
+    release_tx.send(()).unwrap();
+    writer.join().unwrap();
+    assert_eq!(
+        outcome.expect("the idle collection waited for the busy one"),
+        (Some("value".to_string()), true)
+    );
+    assert_eq!(
+        AegMemoryEngine::fetch_shared("busy", "key").as_deref(),
+        Some("value")
+    );
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegCrypto, AegDispatch, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness,
+    AegisrCommand, AegisrResponse,
+};
+use std::fs;
+
+#[test]
+fn sealed_collection_needs_its_passphrase() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("vault");
+    AegCore::put_qualified("vault::root", "hunter2");
+    AegCore::put_qualified("vault::api", "k");
+    AegCore::flush_now();
+
+    let response = AegDispatch::execute(AegisrCommand::Seal {
+        name: "vault".into(),
+        off: false,
+        passphrase: Some("correct horse".into()),
+    });
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+    assert!(AegCore::load().is_collection_sealed("vault"));
+    assert!(AegCore::seal_collection("vault", "again").starts_with('✗'));
+
+    // locked: reads and writes are refused, and the store key alone no
+    // longer opens the file
+    assert!(AegCore::get_qualified("vault::root").is_err());
+    assert!(AegCore::put_qualified("vault::new", "x").starts_with('✗'));
+    assert!(AegCore::delete_qualified("vault::api").starts_with('✗'));
+    assert!(AegCore::duplicate_collection("vault", "copy").starts_with('✗'));
+    let subkey =
+        AegCrypto::derive_collection_key(&AegFileSystem::read_authorization_key(), "vault")
+            .unwrap();
+    let encrypted = fs::read(dir.join("collection_vault.aekv")).unwrap();
+    assert!(AegFileFormat::decode(&subkey, &encrypted).is_err());
+
+    assert!(AegCore::unseal_collection("vault", "wrong").starts_with('✗'));
+    assert!(!AegFileSystem::is_unsealed("vault"));
+    match AegDispatch::execute(AegisrCommand::Unseal {
+        name: "vault".into(),
+        passphrase: None,
+    }) {
+        AegisrResponse::Error { message } => assert!(message.contains("passphrase")),
+        other => panic!("{:?}", other),
+    }
+
+    let response = AegDispatch::execute(AegisrCommand::Unseal {
+        name: "vault".into(),
+        passphrase: Some("correct horse".into()),
+    });
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+    assert_eq!(
+        AegCore::get_qualified("vault::root").unwrap().as_deref(),
+        Some("hunter2")
+    );
+    assert!(AegCore::put_qualified("vault::new", "x").starts_with('✓'));
+
+    // locking saves the change and forgets the passphrase
+    assert!(AegCore::lock_collection("vault").starts_with('✓'));
+    AegMemoryEngine::reset_cache();
+    assert!(AegCore::get_qualified("vault::new").is_err());
+    assert!(AegCore::unseal_collection("vault", "correct horse").starts_with('✓'));
+    assert_eq!(
+        AegCore::get_qualified("vault::new").unwrap().as_deref(),
+        Some("x")
+    );
+    assert!(AegCore::unseal_collection("default", "x").starts_with('✗'));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegTestHarness, AegisrCommand, AegisrResponse,
+    CollectionStats,
+};
+
+#[test]
+fn stats_report_sizes_and_save_state() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::db", "postgres");
+    AegCore::put_qualified("prod::key", "abc");
+
+    let stats = AegCore::collection_stats("prod").unwrap();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.key_bytes, 5);
+    assert_eq!(stats.value_bytes, 11);
+    assert!(stats.dirty);
+    assert_eq!(stats.last_saved, None);
+    assert_eq!(stats.disk_bytes, 0);
+
+    AegCore::flush_now();
+    let stats = AegCore::collection_stats("prod").unwrap();
+    assert!(!stats.dirty);
+    assert!(stats.last_saved.is_some());
+    assert!(stats.disk_bytes > 0);
+
+    // after a restart the save time comes from the data file
+    AegMemoryEngine::reset_cache();
+    let reloaded = AegCore::collection_stats("prod").unwrap();
+    assert_eq!(reloaded.entries, 2);
+    assert!(reloaded.last_saved.is_some());
+    assert_eq!(reloaded.disk_bytes, stats.disk_bytes);
+
+    assert!(AegCore::collection_stats("missing").is_err());
+    let all = AegCore::all_collection_stats();
+    let names: Vec<&str> = all.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["default", "prod"]);
+
+    let response = AegDispatch::execute(AegisrCommand::Stats {
+        collection: None,
+        all: true,
+    });
+    match response {
+        AegisrResponse::Ok { message, data } => {
+            let lines: Vec<&str> = message.lines().collect();
+            assert_eq!(lines.len(), 3, "{}", message);
+            assert!(lines[0].starts_with("COLLECTION"));
+            assert!(lines[2].starts_with("prod"));
+            let rows: Vec<CollectionStats> = serde_json::from_value(data.unwrap()).unwrap();
+            assert_eq!(rows, all);
+        }
+        other => panic!("{:?}", other),
+    }
+    let response = AegDispatch::execute(AegisrCommand::Stats {
+        collection: Some("missing".into()),
+        all: false,
+    });
+    assert!(matches!(response, AegisrResponse::Error { .. }));
+}
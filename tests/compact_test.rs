@@ -0,0 +1,47 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn compact_moves_orphans_aside_and_drops_stale_records() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+
+    // a deleted collection leaves its file behind
+    AegCore::create_collection("gone");
+    AegCore::put_qualified("gone::token", "t");
+    AegCore::flush_now();
+    assert!(AegCore::delete_collection("gone").starts_with('✓'));
+    assert!(dir.join("collection_gone.aekv").exists());
+
+    // every put of an indexed collection appends a record
+    AegCore::set_indexed("default", true);
+    for i in 0..5 {
+        AegCore::put_value("rotating", &format!("value{}", i));
+    }
+    AegCore::put_value("kept", "k");
+    AegCore::delete_value("rotating");
+    AegCore::flush_now();
+    let records = dir.join("collection_default.cold");
+    let before = std::fs::metadata(&records).unwrap().len();
+
+    let report = AegFileSystem::compact().unwrap();
+    assert_eq!(report.quarantined_files, vec!["collection_gone.aekv"]);
+    assert_eq!(report.compacted, vec!["default"]);
+    assert!(!dir.join("collection_gone.aekv").exists());
+    assert!(dir.join("collection_gone.aekv.orphaned").exists());
+    let after = std::fs::metadata(&records).unwrap().len();
+    assert!(after < before);
+    assert!(report.reclaimed_bytes >= before - after);
+
+    // the rewritten index still finds the surviving record
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value_uncached("kept").unwrap().as_deref(),
+        Some("k")
+    );
+    assert!(AegCore::get_value_uncached("rotating").unwrap().is_none());
+    assert_eq!(AegCore::get_value("kept").as_deref(), Some("k"));
+
+    let again = AegFileSystem::compact().unwrap();
+    assert_eq!(again.reclaimed_bytes, 0);
+    assert_eq!(again.summary(), "✓ Nothing to compact");
+}
@@ -0,0 +1,71 @@
+use aegisrlib::{AegCompress, AegCore, AegMemoryEngine, AegTestHarness, AekvHeader};
+use std::fs;
+
+#[test]
+fn large_collections_are_compressed_before_encryption() {
+    let mut noise = Vec::new();
+    let mut x: u32 = 7;
+    for _ in 0..5000 {
+        x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        noise.push((x >> 24) as u8);
+    }
+    let samples: Vec<Vec<u8>> = vec![
+        Vec::new(),
+        b"abc".to_vec(),
+        b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_vec(),
+        b"{\"key\": \"value\", \"key\": \"value\", \"key\": \"value\"}".repeat(200),
+        noise.clone(),
+        [noise.as_slice(), &[0u8; 1000], noise.as_slice()].concat(),
+    ];
+    for sample in &samples {
+        let packed = AegCompress::compress(sample).unwrap();
+        assert_eq!(&AegCompress::decompress(&packed).unwrap(), sample);
+    }
+    let repetitive = AegCompress::compress(&samples[3]).unwrap();
+    assert!(repetitive.len() < samples[3].len() / 10);
+    assert!(AegCompress::decompress(&repetitive[..repetitive.len() - 1]).is_err());
+    assert!(AegCompress::decompress(&[5, 0, 0, 0, 0x00, 1, 0]).is_err());
+    // a block claiming far more than it could expand to is refused up front
+    assert!(AegCompress::decompress(&[0, 0, 0, 64, 0x00]).is_err());
+    // payloads written by the earlier encoder still decode
+    let written_before: &[u8] = &[
+        84, 0, 0, 0, 255, 6, 114, 101, 116, 114, 105, 101, 115, 61, 51, 59, 116, 105, 109, 101,
+        111, 117, 116, 61, 51, 48, 59, 21, 0, 39, 80, 116, 61, 51, 48, 59,
+    ];
+    assert_eq!(
+        AegCompress::decompress(written_before).unwrap(),
+        b"retries=3;timeout=30;".repeat(4)
+    );
+
+    let store = AegTestHarness::temp_dir();
+
+    let dir = store.dir();
+    let collection = AegCore::load().get_active_collection().to_string();
+    let file = dir.join(format!("collection_{}.aekv", collection));
+
+    AegCore::put_value("config", &"retries=3;timeout=30;".repeat(500));
+    AegCore::flush_now();
+    let plain = fs::read(&file).unwrap();
+    assert!(!AekvHeader::parse(&plain).unwrap().unwrap().compressed);
+
+    assert!(AegCore::set_compression(Some(1024)).starts_with('✓'));
+    AegCore::put_value("touch", "1");
+    AegCore::flush_now();
+    let packed = fs::read(&file).unwrap();
+    assert!(AekvHeader::parse(&packed).unwrap().unwrap().compressed);
+    assert!(packed.len() < plain.len() / 4);
+
+    // small collections stay uncompressed
+    AegCore::create_collection("tiny");
+    AegMemoryEngine::with_engine("tiny", |engine| engine.insert("k", "v"));
+    AegCore::flush_now();
+    let tiny = fs::read(dir.join("collection_tiny.aekv")).unwrap();
+    assert!(!AekvHeader::parse(&tiny).unwrap().unwrap().compressed);
+
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value("config").unwrap(),
+        "retries=3;timeout=30;".repeat(500)
+    );
+    assert!(AegCore::verify_store().passed());
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegAudit, AegCore, AegDaemon, AegDaemonClient, AegMemoryEngine, AegTestHarness, AegisrCommand,
+    AegisrResponse, AuditSource,
+};
+
+#[test]
+fn daemon_serves_commands_over_a_local_connection() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::set_audit(true);
+    let socket = AegDaemon::default_socket();
+    // nothing listening yet: run in this process
+    let local = AegDaemonClient::execute_or_local(AegisrCommand::List);
+    assert!(matches!(local, AegisrResponse::Ok { .. }));
+
+    // a socket left behind by a dead daemon is replaced
+    #[cfg(unix)]
+    std::fs::write(&socket, b"").unwrap();
+    let daemon = AegDaemon::spawn(&socket).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // the directory it was bound in is gone
+        let dir = socket.parent().unwrap();
+        let names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert!(
+            !names.iter().any(|name| name.starts_with('.')),
+            "{:?}",
+            names
+        );
+    }
+    let Err(error) = AegDaemon::bind(&socket) else {
+        panic!("bound a socket a daemon is listening on");
+    };
+    assert!(error.contains("already listening"), "{}", error);
+
+    let mut client = AegDaemonClient::connect(&socket).unwrap();
+    let put = client
+        .execute(&AegisrCommand::Put {
+            key: "api".into(),
+            value: "k-1".into(),
+            env_name: None,
+            json: false,
+        })
+        .unwrap();
+    assert!(matches!(put, AegisrResponse::Ok { .. }), "{:?}", put);
+    // the same connection carries any number of commands
+    let got = client
+        .execute(&AegisrCommand::Get {
+            key: "api".into(),
+            no_cache: false,
+            clip: false,
+            clear_after: None,
+        })
+        .unwrap();
+    assert_eq!(got.raw(), Some("k-1"));
+    let got = AegDaemonClient::execute_or_local(AegisrCommand::Get {
+        key: "missing".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert!(matches!(got, AegisrResponse::Error { .. }));
+
+    daemon.stop();
+    #[cfg(unix)]
+    assert!(!socket.exists());
+    assert!(AegDaemonClient::connect(&socket).is_err());
+    // stopping saved the daemon's changes
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("k-1"));
+    let entries = AegAudit::entries().unwrap();
+    assert_eq!(entries[0].source, Some(AuditSource::Daemon));
+}
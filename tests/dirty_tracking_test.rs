@@ -0,0 +1,38 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn save_all_skips_clean_collections() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "dirty_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+
+    AegCore::put_value("dirty_key", "v1");
+    assert!(AegMemoryEngine::load().is_dirty());
+    assert!(AegMemoryEngine::save_all() >= 1);
+    assert!(!AegMemoryEngine::load().is_dirty());
+
+    // nothing changed: nothing is written, even if the file disappears
+    let path = AegFileSystem::get_config_path().join(format!("collection_{}.aekv", collection));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(AegMemoryEngine::save_all(), 0);
+    assert!(!path.exists());
+
+    // reads do not dirty the collection
+    assert_eq!(AegCore::get_value("dirty_key").unwrap(), "v1");
+    assert_eq!(AegMemoryEngine::save_all(), 0);
+
+    // a write makes it dirty again and it is saved once
+    AegCore::put_value("dirty_key", "v2");
+    assert_eq!(AegMemoryEngine::save_all(), 1);
+    assert!(path.exists());
+    assert_eq!(AegMemoryEngine::save_all(), 0);
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegDispatch, AegTestHarness, AegWire, AegisrCommand, AegisrResponse};
+
+#[test]
+fn commands_execute_through_one_path() {
+    let _store = AegTestHarness::temp_dir();
+
+    let init = AegDispatch::execute(AegisrCommand::Init {
+        reset: false,
+        bind_machine: false,
+        cipher: None,
+        key_backend: None,
+        token_slot: None,
+    });
+    assert!(matches!(init, AegisrResponse::Ok { .. }), "{:?}", init);
+
+    let put = AegDispatch::execute(AegisrCommand::Put {
+        key: "api".into(),
+        value: "secret".into(),
+        env_name: None,
+        json: false,
+    });
+    assert!(matches!(put, AegisrResponse::Ok { .. }), "{:?}", put);
+
+    let get = AegDispatch::execute(AegisrCommand::Get {
+        key: "api".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert_eq!(
+        get,
+        AegisrResponse::Ok {
+            message: "secret".into(),
+            data: Some("secret".into()),
+        }
+    );
+
+    let missing = AegDispatch::execute(AegisrCommand::Use {
+        name: "nope".into(),
+    });
+    assert!(matches!(missing, AegisrResponse::Error { .. }));
+    let nuke = AegDispatch::execute(AegisrCommand::Nuke {
+        confirm: None,
+        delay: 0,
+    });
+    assert!(matches!(nuke, AegisrResponse::Error { .. }));
+
+    // the encoded entry point used by the server and daemon
+    let request = AegWire::encode_command(&AegisrCommand::Del { key: "api".into() }).unwrap();
+    let response = AegWire::decode_response(&AegDispatch::execute_encoded(&request)).unwrap();
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+    assert!(AegCore::get_value("api").is_none());
+
+    let newer = r#"{"version":2,"command":"teleport","args":{}}"#;
+    assert_eq!(
+        AegWire::decode_response(&AegDispatch::execute_encoded(newer)).unwrap(),
+        AegisrResponse::Unsupported {
+            command: "teleport".into()
+        }
+    );
+}
@@ -0,0 +1,47 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn reused_values_are_reported_on_put() {
+    let _store = AegTestHarness::temp_dir();
+
+    AegCore::put_value("github_token", "ghp_shared");
+    assert!(!AegCore::put_value("gitlab_token", "ghp_shared").contains('⚠'));
+
+    AegCore::set_duplicate_warning(true);
+    let msg = AegCore::put_value("ci_token", "ghp_shared");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(msg.contains("'github_token', 'gitlab_token'"), "{}", msg);
+    // overwriting a key with its own value is not reuse
+    let msg = AegCore::put_value("ci_token", "ghp_shared");
+    let warning = msg.lines().find(|l| l.starts_with('⚠')).unwrap();
+    assert!(!warning.contains("ci_token"), "{}", msg);
+
+    // the index follows changes and deletes
+    AegCore::put_value("github_token", "ghp_rotated");
+    AegCore::delete_value("gitlab_token");
+    let msg = AegCore::put_value("deploy_token", "ghp_shared");
+    assert!(
+        msg.contains("under 'ci_token'") && !msg.contains("github"),
+        "{}",
+        msg
+    );
+    assert!(!AegCore::put_value("unique", "only-here").contains('⚠'));
+}
+
+#[test]
+fn the_value_index_holds_no_plain_digests() {
+    let _store = AegTestHarness::memory();
+    AegCore::put_value("github_token", "ghp_guessable");
+    AegCore::put_value("gitlab_token", "ghp_guessable");
+
+    let dump = AegMemoryEngine::with_active(|engine| {
+        assert_eq!(
+            engine.keys_with_value("ghp_guessable"),
+            ["github_token", "gitlab_token"]
+        );
+        format!("{:?}", engine)
+    });
+    assert!(dump.contains("value_index: Some"), "{}", dump);
+    let digest = blake3::hash(b"ghp_guessable").to_hex();
+    assert!(!dump.contains(digest.as_str()), "{}", dump);
+}
@@ -0,0 +1,98 @@
+use aegisrlib::{AegCore, AegFileSystem, AegIntrospect, AegTestHarness};
+
+#[test]
+fn duress_passphrase_opens_isolated_decoy() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("duress_real_key", "real secret");
+    AegCore::flush_now();
+
+    let msg = AegCore::setup_duress("under pressure");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegCore::unlock_duress("not it").starts_with('✗'));
+    assert!(!AegFileSystem::in_duress_session());
+
+    let msg = AegCore::unlock_duress("under pressure");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegCore::get_value("duress_real_key").is_none());
+    AegCore::put_value("duress_decoy_key", "decoy value");
+    assert_eq!(
+        AegCore::get_value("duress_decoy_key").unwrap(),
+        "decoy value"
+    );
+
+    let msg = AegCore::lock_duress();
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(
+        AegCore::get_value("duress_real_key").unwrap(),
+        "real secret"
+    );
+    assert!(AegCore::get_value("duress_decoy_key").is_none());
+
+    AegCore::delete_value("duress_real_key");
+    AegCore::flush_now();
+}
+
+#[test]
+fn status_and_inspect_of_the_decoy_look_like_the_store() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::setup_duress("under pressure");
+    let real = serde_json::to_value(AegIntrospect::describe()).unwrap();
+    let real_status = serde_json::to_value(AegCore::status()).unwrap();
+
+    AegCore::unlock_duress("under pressure");
+    let decoy = serde_json::to_value(AegIntrospect::describe()).unwrap();
+    let decoy_status = serde_json::to_value(AegCore::status()).unwrap();
+    AegCore::lock_duress();
+    let fields = |v: &serde_json::Value| v.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    assert_eq!(fields(&decoy), fields(&real));
+    assert_eq!(decoy["store_dir"], real["store_dir"]);
+    assert!(!decoy.to_string().contains("duress"), "{}", decoy);
+    assert_eq!(fields(&decoy_status), fields(&real_status));
+    assert_eq!(decoy_status["store_dir"], real_status["store_dir"]);
+    assert!(!decoy_status.to_string().contains("duress"));
+}
+
+#[test]
+fn the_store_layout_does_not_give_the_decoy_away() {
+    let store = AegTestHarness::temp_dir();
+    AegCore::setup_duress("under pressure");
+    let names = |dir: &std::path::Path| -> Vec<String> {
+        walk(dir)
+            .iter()
+            .map(|p| p.strip_prefix(dir).unwrap().display().to_string())
+            .collect()
+    };
+    for name in names(store.dir()) {
+        let lower = name.to_lowercase();
+        assert!(
+            !lower.contains("duress") && !lower.contains("decoy"),
+            "{}",
+            name
+        );
+    }
+
+    // a decoy set up under the old names is moved and still opens
+    let decoy = AegFileSystem::get_decoy_path();
+    let legacy = store.dir().join("decoy");
+    std::fs::rename(&decoy, &legacy).unwrap();
+    std::fs::rename(legacy.join("seed"), legacy.join("DURESS_SALT")).unwrap();
+    assert!(
+        AegCore::unlock_duress("under pressure").starts_with('✓'),
+        "legacy decoy did not open"
+    );
+    AegCore::lock_duress();
+    assert!(!legacy.exists());
+    assert!(decoy.exists());
+}
+
+fn walk(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            found.extend(walk(&path));
+        }
+        found.push(path);
+    }
+    found
+}
@@ -1,4 +1,4 @@
-use aegisrlib::{AegCore, AegFileSystem};
+use aegisrlib::{AegCore, AegFileSystem, AegTestHarness, Verbosity};
 
 #[test]
 fn e2e_test() {
@@ -7,7 +7,8 @@ fn e2e_test() {
     println!("=======================================\n");
 
     println!("[0] ⚙️ Initializing Filesystem and Configuration...");
-    let config_path = AegFileSystem::initialize_config(Some(false), Some(true));
+    let _store = AegTestHarness::temp_dir();
+    let config_path = AegFileSystem::initialize_config(Some(false), Verbosity::Verbose);
     println!("  ✅ Config initialized at: {:?}\n", config_path);
 
     println!("[1] 💾 Loading Engine from Storage...");
@@ -0,0 +1,49 @@
+use aegisrlib::{
+    AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness, Cipher,
+};
+use std::fs;
+
+#[test]
+fn entries_keep_timestamps_and_tags() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+
+    // a collection written when values were bare strings
+    let master = AegFileSystem::read_authorization_key();
+    let key = AegCrypto::derive_collection_key(&master, "default").unwrap();
+    let legacy = br#"{"store":{"old_key":"old value"},"collection_name":"default"}"#;
+    fs::write(
+        dir.join("collection_default.aekv"),
+        AegFileFormat::encode(Cipher::Aes256Gcm, &key, legacy).unwrap(),
+    )
+    .unwrap();
+    let old = AegCore::get_metadata("old_key").unwrap();
+    assert_eq!(&*old.value, "old value");
+    assert_eq!(old.created_at, None);
+    assert!(old.tags.is_empty());
+
+    AegCore::put_value("db_url", "postgres://one");
+    let created = AegCore::get_metadata("db_url").unwrap();
+    assert!(created.created_at.is_some());
+    assert_eq!(created.created_at, created.updated_at);
+
+    assert!(AegCore::tag_key("db_url", "prod").starts_with('✓'));
+    assert!(AegCore::tag_key("old_key", "prod").starts_with('✓'));
+    assert!(AegCore::tag_key("db_url", "has space").starts_with('✗'));
+    assert!(AegCore::tag_key("missing", "prod").starts_with('✗'));
+    AegCore::put_value("db_url", "postgres://two");
+    let updated = AegCore::get_metadata("db_url").unwrap();
+    assert_eq!(updated.created_at, created.created_at);
+    assert_eq!(updated.tags, vec!["prod"]);
+    assert_eq!(AegCore::list_by_tag("prod"), vec!["db_url", "old_key"]);
+
+    // tags survive being paged out to the cold tier and a reload
+    AegCore::set_warm_capacity("default", Some(1));
+    AegCore::put_value("filler", "x");
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::list_by_tag("prod"), vec!["db_url", "old_key"]);
+    assert!(AegCore::untag_key("old_key", "prod").starts_with('✓'));
+    assert_eq!(AegCore::list_by_tag("prod"), vec!["db_url"]);
+    assert_eq!(AegCore::list_keys(), vec!["db_url", "filler", "old_key"]);
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegDispatch, AegEnv, AegTestHarness, AegisrCommand, OutputFormat};
+
+#[test]
+fn env_prints_exports_and_exec_injects_them() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("db/url", "postgres://it's");
+    AegCore::put_value("token", "t0k3n");
+    AegCore::set_env_name("token", Some("API_TOKEN"));
+
+    assert_eq!(
+        AegEnv::render(None, false).unwrap(),
+        "export API_TOKEN='t0k3n'\nexport DB_URL='postgres://it'\\''s'\n"
+    );
+    // the prefix is written unquoted too
+    assert!(AegEnv::render(Some("X;id;"), false).is_err());
+    assert!(AegEnv::exec(Some("$(id)"), &["true".into()]).is_err());
+    let dotenv = AegDispatch::execute(AegisrCommand::Env {
+        prefix: Some("APP_".into()),
+        dotenv: true,
+    });
+    assert_eq!(
+        dotenv.render(OutputFormat::Plain),
+        "APP_API_TOKEN=\"t0k3n\"\nAPP_DB_URL=\"postgres://it's\"\n"
+    );
+
+    let run = |script: &str| AegEnv::exec(Some("APP_"), &["sh".into(), "-c".into(), script.into()]);
+    assert_eq!(
+        run(r#"test "$APP_DB_URL" = "postgres://it's" && test "$APP_API_TOKEN" = t0k3n"#),
+        Ok(0)
+    );
+    assert_eq!(run("exit 3"), Ok(3));
+    assert!(AegEnv::exec(None, &[]).is_err());
+    assert!(AegEnv::exec(None, &["/nonexistent/aegisr-child".into()]).is_err());
+}
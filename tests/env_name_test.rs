@@ -0,0 +1,98 @@
+use aegisrlib::{
+    AegBundle, AegCore, AegEnv, AegMemoryEngine, AegTestHarness, BundlePayload, KeyMeta,
+    PlainFormat, SharePrivateKey, SharedBundle,
+};
+use std::collections::HashMap;
+
+#[test]
+fn env_name_mapping_applies_to_exports() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "env_name_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::put_value("service/db/password", "p@ss \"quoted\"");
+    AegCore::put_value("api-token", "t0k3n");
+
+    assert!(AegCore::set_env_name("service/db/password", Some("9BAD")).starts_with('✗'));
+    assert!(AegCore::set_env_name("missing", Some("MISSING")).starts_with('✗'));
+    let msg = AegCore::set_env_name("service/db/password", Some("DATABASE_PASSWORD"));
+    assert!(msg.starts_with('✓'), "{}", msg);
+
+    assert_eq!(
+        AegCore::env_vars(),
+        vec![
+            ("API_TOKEN".to_string(), "t0k3n".to_string()),
+            (
+                "DATABASE_PASSWORD".to_string(),
+                "p@ss \"quoted\"".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        AegCore::export_plain(PlainFormat::Dotenv),
+        "API_TOKEN=\"t0k3n\"\nDATABASE_PASSWORD=\"p@ss \\\"quoted\\\"\"\n"
+    );
+
+    // the mapping travels with encrypted bundles
+    let path = std::env::temp_dir().join(format!("aegisr_env_name_{}.json", std::process::id()));
+    assert!(AegCore::export_collection(collection, &path, "pw").starts_with('✓'));
+    AegCore::clear_values();
+    assert!(AegCore::import_collection(&path, "pw").starts_with('✓'));
+    assert!(
+        AegCore::env_vars()
+            .iter()
+            .any(|(name, _)| name == "DATABASE_PASSWORD")
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
+
+#[test]
+fn hostile_env_names_in_bundles_are_rejected() {
+    let _store = AegTestHarness::temp_dir();
+    let hostile = "X=1; touch /tmp/pwned; Y";
+    let payload = BundlePayload {
+        collection: "hostile".into(),
+        entries: HashMap::from([("token".to_string(), "t0k3n".to_string())]),
+        key_meta: HashMap::from([(
+            "token".to_string(),
+            KeyMeta {
+                env_name: Some(hostile.into()),
+            },
+        )]),
+    };
+
+    let path = std::env::temp_dir().join(format!("aegisr_hostile_{}.json", std::process::id()));
+    AegBundle::seal(&payload, "pw")
+        .unwrap()
+        .write(&path)
+        .unwrap();
+    let msg = AegCore::import_collection(&path, "pw");
+    assert!(msg.starts_with('✗'), "{}", msg);
+    assert!(!AegCore::load().collections.contains(&"hostile".to_string()));
+    std::fs::remove_file(&path).unwrap();
+
+    let key = SharePrivateKey::generate();
+    let shared = SharedBundle::seal(&payload, &key.public_key()).unwrap();
+    let msg = AegCore::import_shared(&shared, &key);
+    assert!(msg.starts_with('✗'), "{}", msg);
+
+    // the engine refuses it too, and never exports a bad name it holds
+    AegCore::put_value("token", "t0k3n");
+    AegMemoryEngine::with_active(|engine| {
+        assert!(engine.set_env_name("token", Some(hostile.into())).is_err());
+        assert_eq!(engine.env_name_for("token"), "TOKEN");
+    });
+    assert!(AegCore::set_env_name("token", Some(hostile)).starts_with('✗'));
+    assert_eq!(
+        AegEnv::render(None, false).unwrap(),
+        "export TOKEN='t0k3n'\n"
+    );
+}
@@ -0,0 +1,86 @@
+use aegisrlib::{
+    AEKV_FORMAT_VERSION, AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine,
+    AegTestHarness, AekvHeader,
+};
+use std::fs;
+
+#[test]
+fn collection_files_carry_their_own_wrapped_data_key() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::db", "postgres://prod");
+    AegCore::put_value("api", "secret");
+    AegCore::flush_now();
+
+    let prod_file = dir.join("collection_prod.aekv");
+    let saved = fs::read(&prod_file).unwrap();
+    let header = AekvHeader::parse(&saved).unwrap().unwrap();
+    assert_eq!(header.version, AEKV_FORMAT_VERSION);
+    assert!(header.wrapped_key.is_some());
+    let prod_key =
+        AegCrypto::derive_collection_key(&AegFileSystem::read_authorization_key(), "prod").unwrap();
+    let data_key = AegFileFormat::data_key(&prod_key, &saved).unwrap();
+    assert_ne!(data_key, prod_key);
+    let default_key =
+        AegCrypto::derive_collection_key(&AegFileSystem::read_authorization_key(), "default")
+            .unwrap();
+    let default_saved = fs::read(dir.join("collection_default.aekv")).unwrap();
+    assert_ne!(
+        AegFileFormat::data_key(&default_key, &default_saved).unwrap(),
+        data_key
+    );
+    // another collection's key does not unwrap it
+    assert!(AegFileFormat::data_key(&default_key, &saved).is_err());
+
+    // changing keys re-wraps the data key and leaves the payload as it is
+    let payload = saved[header.encoded_len()..].to_vec();
+    assert!(AegCore::change_passphrase(None, Some("pw"), |_| {}).starts_with('✓'));
+    let rekeyed = fs::read(&prod_file).unwrap();
+    let rewrapped = AekvHeader::parse(&rekeyed).unwrap().unwrap();
+    assert_eq!(&rekeyed[rewrapped.encoded_len()..], payload.as_slice());
+    assert_ne!(rewrapped.wrapped_key, header.wrapped_key);
+    assert!(AegFileFormat::data_key(&prod_key, &rekeyed).is_err());
+    let new_prod_key =
+        AegCrypto::derive_collection_key(&AegFileSystem::read_authorization_key(), "prod").unwrap();
+    assert_eq!(
+        AegFileFormat::data_key(&new_prod_key, &rekeyed).unwrap(),
+        data_key
+    );
+
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("prod::db").unwrap().as_deref(),
+        Some("postgres://prod")
+    );
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("secret"));
+
+    // a version 3 file, sealed under the collection key itself, upgrades
+    let plain = AegFileFormat::decode(&new_prod_key, &rekeyed).unwrap();
+    let old = AekvHeader {
+        version: 3,
+        checksum: Some(AekvHeader::checksum_of(&new_prod_key, &plain)),
+        wrapped_key: None,
+        nonce: AegCrypto::random_nonce().unwrap(),
+        compressed: false,
+        ..rewrapped
+    };
+    let sealed = AegCrypto::seal_with_nonce(
+        old.cipher,
+        &new_prod_key,
+        &old.nonce,
+        &old.to_bytes(),
+        &plain,
+    )
+    .unwrap();
+    fs::write(&prod_file, [old.to_bytes(), sealed].concat()).unwrap();
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("prod::db").unwrap().as_deref(),
+        Some("postgres://prod")
+    );
+    let upgraded = AekvHeader::parse(&fs::read(&prod_file).unwrap())
+        .unwrap()
+        .unwrap();
+    assert!(upgraded.wrapped_key.is_some());
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegFederation, AegFileSystem, AegTestHarness, AegisrCommand,
+    AegisrResponse, FederatedStore, KeyProvider, Verbosity,
+};
+use std::fs;
+use std::path::Path;
+
+fn make_store(dir: &Path, collection: &str, entries: &[(&str, &str)]) {
+    AegCore::set_store_dir(dir.to_path_buf());
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::create_collection(collection);
+    AegCore::load().set_active_collection(collection).unwrap();
+    for (key, value) in entries {
+        AegCore::put_value(key, value);
+    }
+    AegCore::flush_now();
+}
+
+#[test]
+fn federation_resolves_names_across_stores() {
+    let store = AegTestHarness::temp_dir();
+    let base = store.dir();
+    let (home, team, project) = (base.join("home"), base.join("team"), base.join("project"));
+
+    make_store(
+        &team,
+        "prod",
+        &[("db_password", "team-db"), ("shared", "team")],
+    );
+    // the project store's key is handed out through the environment
+    make_store(&project, "app", &[("shared", "project"), ("token", "p")]);
+    let key = fs::read_to_string(project.join("AUTHORIZATION_KEY")).unwrap();
+    fs::remove_file(project.join("AUTHORIZATION_KEY")).unwrap();
+    unsafe { std::env::set_var("AEGISR_FEDERATION_TEST_KEY", key.trim()) };
+
+    make_store(&home, "personal", &[("email", "me@example.com")]);
+    AegFederation::register(FederatedStore {
+        name: "team".into(),
+        dir: team.clone(),
+        key: KeyProvider::Store,
+        precedence: 10,
+    })
+    .unwrap();
+    let response = AegDispatch::execute(AegisrCommand::FedAdd {
+        name: "project".into(),
+        dir: project.display().to_string(),
+        key_env: Some("AEGISR_FEDERATION_TEST_KEY".into()),
+        passphrase_env: None,
+        precedence: 0,
+    });
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+    let names: Vec<String> = AegFederation::stores()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, vec!["project", "team"]);
+
+    // fully qualified, and store::key in the store's active collection
+    let found = AegFederation::get("team::prod::db_password")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (found.store.as_str(), found.value.as_str()),
+        ("team", "team-db")
+    );
+    assert_eq!(
+        AegFederation::get("project::token").unwrap().unwrap().value,
+        "p"
+    );
+    // the local store is still the one in use
+    assert_eq!(AegFileSystem::base_dir_override(), Some(home.clone()));
+    assert_eq!(
+        AegCore::get_value("email").as_deref(),
+        Some("me@example.com")
+    );
+
+    // bare keys: local first, then by precedence
+    assert_eq!(AegFederation::get("email").unwrap().unwrap().store, "local");
+    assert_eq!(
+        AegFederation::get("shared").unwrap().unwrap().value,
+        "project"
+    );
+    assert!(AegFederation::get("nowhere").unwrap().is_none());
+
+    // a local collection shadows a store of the same name
+    AegCore::create_collection("team");
+    AegCore::put_qualified("team::prod", "local-team");
+    let found = AegFederation::get("team::prod").unwrap().unwrap();
+    assert_eq!(
+        (found.store.as_str(), found.value.as_str()),
+        ("local", "local-team")
+    );
+    assert!(AegFederation::get("local::team::prod").unwrap().is_some());
+
+    // writes land in the addressed store and are saved there
+    assert!(AegFederation::put("project::app::new", "n").starts_with('✓'));
+    assert!(AegFederation::put("unknown_store::x::y", "v").starts_with('✗'));
+    let response = AegDispatch::execute(AegisrCommand::FedGet {
+        name: "project::app::new".into(),
+    });
+    match response {
+        AegisrResponse::Ok { message, .. } => assert_eq!(message, "n"),
+        other => panic!("{:?}", other),
+    }
+    let keys = AegFederation::keys().unwrap();
+    assert!(keys.contains(&"local::personal::email".to_string()));
+    assert!(keys.contains(&"team::prod::db_password".to_string()));
+    assert!(keys.contains(&"project::app::new".to_string()));
+
+    // a missing key provider is reported, not mistaken for a missing key
+    unsafe { std::env::remove_var("AEGISR_FEDERATION_TEST_KEY") };
+    assert!(AegFederation::get("project::token").is_err());
+
+    assert!(
+        AegFederation::register(FederatedStore {
+            name: "local".into(),
+            dir: team.clone(),
+            key: KeyProvider::Store,
+            precedence: 0,
+        })
+        .is_err()
+    );
+    assert!(AegFederation::unregister("team").unwrap());
+    assert!(!AegFederation::unregister("team").unwrap());
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "ffi")]
+
+use aegisrlib::ffi::{AEG_ERR_ARGUMENT, AEG_OK, aeg_flush, aeg_free, aeg_get, aeg_init, aeg_put};
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+use std::ffi::CString;
+
+#[test]
+fn c_functions_store_and_return_owned_values() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let dir_c = CString::new(dir.to_str().unwrap()).unwrap();
+    let key = CString::new("db/password").unwrap();
+    let value = CString::new("hunter2 with spaces").unwrap();
+    unsafe {
+        assert_eq!(aeg_init(dir_c.as_ptr()), AEG_OK);
+        assert_eq!(aeg_put(key.as_ptr(), value.as_ptr()), AEG_OK);
+        assert_eq!(aeg_put(key.as_ptr(), std::ptr::null()), AEG_ERR_ARGUMENT);
+
+        let mut len = 0usize;
+        let buf = aeg_get(key.as_ptr(), &mut len);
+        assert!(!buf.is_null());
+        assert_eq!(
+            std::slice::from_raw_parts(buf, len + 1),
+            b"hunter2 with spaces\0"
+        );
+        aeg_free(buf, len);
+
+        let missing = CString::new("missing").unwrap();
+        assert!(aeg_get(missing.as_ptr(), std::ptr::null_mut()).is_null());
+        aeg_free(std::ptr::null_mut(), 0);
+
+        assert_eq!(aeg_flush(), AEG_OK);
+    }
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter2 with spaces")
+    );
+}
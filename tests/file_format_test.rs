@@ -0,0 +1,38 @@
+use aegisrlib::{
+    AEKV_FORMAT_VERSION, AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine,
+    AegTestHarness, AekvHeader, Cipher,
+};
+use std::fs;
+
+#[test]
+fn aekv_files_carry_a_header_and_legacy_files_upgrade() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let master = AegFileSystem::read_authorization_key();
+    let key = AegCrypto::derive_collection_key(&master, "default").unwrap();
+    let file = dir.join("collection_default.aekv");
+
+    // an unheadered base64 file from before the header existed
+    let mut legacy = AegMemoryEngine::new("default");
+    legacy.insert("old_key", "old value");
+    let json = serde_json::to_vec(&legacy).unwrap();
+    fs::write(&file, AegTestHarness::legacy_blob(&key, &json)).unwrap();
+    assert!(AegFileFormat::needs_upgrade(&fs::read(&file).unwrap()));
+
+    // first load rewrites it in the current format
+    assert_eq!(AegCore::get_value("old_key").unwrap(), "old value");
+    let upgraded = fs::read(&file).unwrap();
+    assert!(!AegFileFormat::needs_upgrade(&upgraded));
+    let header = AekvHeader::parse(&upgraded).unwrap().unwrap();
+    assert_eq!(header.version, AEKV_FORMAT_VERSION);
+    assert_eq!(header.cipher, Cipher::Aes256Gcm);
+    assert!(!header.compressed);
+
+    // the header is authenticated, so altering it breaks decryption
+    let mut tampered = upgraded.clone();
+    tampered[5] = Cipher::ChaCha20Poly1305.id();
+    assert!(AegFileFormat::decode(&key, &tampered).is_err());
+    let mut newer = upgraded.clone();
+    newer[4] = AEKV_FORMAT_VERSION + 1;
+    assert!(AekvHeader::parse(&newer).is_err());
+}
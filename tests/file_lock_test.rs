@@ -0,0 +1,41 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness};
+use std::fs::OpenOptions;
+use std::time::Duration;
+
+#[test]
+fn store_lock_waits_for_other_holders_then_gives_up() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegFileSystem::set_lock_timeout(Duration::from_millis(100));
+
+    AegCore::put_value("api_key", "first");
+    AegCore::flush_now();
+    AegCore::put_value("api_key", "second");
+    let collection = AegCore::load().get_active_collection().to_string();
+
+    // guards taken by this process nest without blocking each other
+    let outer = AegFileSystem::lock_store(dir).expect("first guard");
+    let inner = AegFileSystem::lock_store(dir).expect("nested guard");
+    drop(inner);
+    drop(outer);
+
+    // a separate open file stands in for another process
+    let other = OpenOptions::new()
+        .write(true)
+        .open(dir.join("aegisr.lock"))
+        .expect("open lock file");
+    other.lock().expect("take lock as another process");
+
+    let err = AegFileSystem::lock_store(dir)
+        .err()
+        .expect("store should be locked");
+    assert!(err.contains("locked by another process"), "{}", err);
+    let err = AegMemoryEngine::pending_changes(&collection).unwrap_err();
+    assert!(err.contains("locked by another process"), "{}", err);
+
+    other.unlock().expect("release lock");
+    drop(other);
+    assert!(AegFileSystem::lock_store(dir).is_ok());
+    let pending = AegMemoryEngine::pending_changes(&collection).unwrap();
+    assert_eq!(pending.modified, vec!["api_key".to_string()]);
+}
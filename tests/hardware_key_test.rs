@@ -0,0 +1,79 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegHardwareKey, AegMemoryEngine, AegTestHarness, HardwareToken,
+    KeyBackend, STORE_AUTHORIZATION_KEY,
+};
+use std::fs;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// A token whose slot secret is a fixed byte string.
+struct SoftToken([u8; 32]);
+
+impl HardwareToken for SoftToken {
+    fn respond(&self, slot: u8, challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let mut input = vec![slot];
+        input.extend_from_slice(challenge);
+        Ok(Zeroizing::new(
+            blake3::keyed_hash(&self.0, &input).as_bytes().to_vec(),
+        ))
+    }
+}
+
+/// No token plugged in.
+struct Unplugged;
+
+impl HardwareToken for Unplugged {
+    fn respond(&self, _slot: u8, _challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        Err("no token present".into())
+    }
+}
+
+#[test]
+fn stored_key_is_wrapped_by_the_hardware_token() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegHardwareKey::set_token(Arc::new(SoftToken([7; 32])));
+    AegCore::put_value("api", "secret");
+    AegCore::flush_now();
+    let key_file = dir.join(STORE_AUTHORIZATION_KEY);
+    let stored = fs::read_to_string(&key_file).unwrap();
+
+    assert!(AegCore::set_hardware_key(1).starts_with('✓'));
+    assert_eq!(AegCore::key_backend(), KeyBackend::Hardware);
+    assert!(!key_file.exists());
+    let wrapped = AegFileSystem::read_store_config().hardware_key.unwrap();
+    assert_eq!(wrapped.slot, 1);
+    assert!(!wrapped.wrapped.contains(stored.trim()));
+    assert_eq!(AegHardwareKey::unwrap(&wrapped).unwrap().as_str(), stored);
+
+    // the store opens with the token alone
+    AegMemoryEngine::reset_cache();
+    AegHardwareKey::forget_keys();
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("secret"));
+    assert!(AegCore::check_key(false).starts_with('✓'));
+    assert!(AegCore::set_hardware_key(1).contains("already"));
+
+    // another token, or none, does not unwrap it
+    AegHardwareKey::set_token(Arc::new(SoftToken([8; 32])));
+    assert!(AegHardwareKey::unwrap(&wrapped).is_err());
+    AegHardwareKey::set_token(Arc::new(Unplugged));
+    assert!(AegFileSystem::key_provider().load().is_err());
+    let moved = AegCore::set_key_backend(KeyBackend::File);
+    assert!(moved.starts_with('✗'), "{}", moved);
+    assert!(!key_file.exists());
+
+    // and without a token, wrapping fails and the key stays where it was
+    AegHardwareKey::set_token(Arc::new(SoftToken([7; 32])));
+    assert!(AegCore::set_key_backend(KeyBackend::File).starts_with('✓'));
+    assert_eq!(fs::read_to_string(&key_file).unwrap(), stored);
+    assert_eq!(AegCore::key_backend(), KeyBackend::File);
+    AegHardwareKey::set_token(Arc::new(Unplugged));
+    let refused = AegCore::set_hardware_key(2);
+    assert!(
+        refused.contains("cannot wrap the key") && refused.contains("key file"),
+        "{}",
+        refused
+    );
+    assert!(key_file.exists());
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("secret"));
+}
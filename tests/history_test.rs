@@ -0,0 +1,45 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness, KEY_HISTORY_DEPTH};
+
+#[test]
+fn overwritten_values_can_be_rolled_back() {
+    let _store = AegTestHarness::memory();
+    let collection = "history_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::put_value("token", "first");
+    AegCore::put_value("token", "second");
+    AegCore::put_value("token", "second");
+    AegCore::put_value("token", "mistake");
+
+    let history = AegCore::get_history("token");
+    let values: Vec<&str> = history.iter().map(|v| v.value.as_str()).collect();
+    assert_eq!(values, vec!["first", "second", "mistake"]);
+    assert_eq!(history[2].version, 3);
+    assert!(history.iter().all(|v| v.set_at.is_some()));
+
+    assert!(AegCore::restore_version("token", 2).starts_with('✓'));
+    assert_eq!(AegCore::get_value("token").unwrap(), "second");
+    assert_eq!(AegCore::get_history("token").last().unwrap().version, 4);
+    assert!(AegCore::restore_version("token", 99).starts_with('✗'));
+
+    // history is persisted and survives deleting the key
+    AegCore::delete_value("token");
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_history("token").len(), 4);
+    AegCore::restore_version("token", 3);
+    assert_eq!(AegCore::get_value("token").unwrap(), "mistake");
+
+    for i in 0..KEY_HISTORY_DEPTH * 2 {
+        AegCore::put_value("token", &i.to_string());
+    }
+    assert_eq!(AegCore::get_history("token").len(), KEY_HISTORY_DEPTH);
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
@@ -0,0 +1,50 @@
+use aegisrlib::{AegCore, AegHook, AegTestHarness, HookState, Shell};
+
+#[test]
+fn hook_exports_on_enter_and_unsets_on_leave() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "hook_test";
+    AegCore::create_collection(collection);
+
+    let root = std::env::temp_dir().join(format!("aegisr_hook_{}", std::process::id()));
+    let project = root.join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join(".aegisr.toml"), "collection = \"hook_test\"\n").unwrap();
+    AegCore::use_collection_for_session(collection).unwrap();
+    AegCore::put_value("db/url", "it's here");
+    AegCore::clear_session_collection();
+
+    let entered = AegHook::emit(Shell::Bash, &project, &HookState::default()).unwrap();
+    assert!(
+        entered.contains("export DB_URL='it'\\''s here';"),
+        "{}",
+        entered
+    );
+    assert!(
+        entered.contains("export AEGISR_HOOK_VARS='DB_URL';"),
+        "{}",
+        entered
+    );
+
+    let state = HookState {
+        manifest: Some(project.join(".aegisr.toml")),
+        vars: vec!["DB_URL".to_string()],
+    };
+    assert_eq!(AegHook::emit(Shell::Bash, &project, &state).unwrap(), "");
+    let left = AegHook::emit(Shell::Fish, &root, &state).unwrap();
+    assert!(left.contains("set -e DB_URL;"), "{}", left);
+    let tampered = HookState {
+        manifest: Some(project.join(".aegisr.toml")),
+        vars: vec!["DB_URL".to_string(), "X;touch /tmp/pwned".to_string()],
+    };
+    let left = AegHook::emit(Shell::Bash, &root, &tampered).unwrap();
+    assert!(!left.contains("pwned"), "{}", left);
+    assert!(AegHook::script(Shell::Zsh).contains("add-zsh-hook chpwd"));
+
+    AegCore::use_collection_for_session(collection).unwrap();
+    AegCore::clear_values();
+    AegCore::flush_now();
+    AegCore::clear_session_collection();
+    AegCore::delete_collection(collection);
+    std::fs::remove_dir_all(&root).unwrap();
+}
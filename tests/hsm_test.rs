@@ -0,0 +1,32 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness, HsmConfig};
+
+#[test]
+fn binding_to_an_absent_token_fails_cleanly() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::put_value("db/password", "hunter2");
+    AegCore::flush_now();
+    assert!(AegCore::unlock_hsm().starts_with('✓'));
+
+    let missing = dir.join("no-such-pkcs11-module.so");
+    let result = AegCore::set_hsm(Some(HsmConfig {
+        module: missing.to_string_lossy().into_owned(),
+        slot: None,
+        key_label: "aegisr".to_string(),
+    }));
+    assert!(result.starts_with('✗'), "{}", result);
+    if cfg!(feature = "pkcs11") {
+        assert!(result.contains("could not be loaded"), "{}", result);
+    } else {
+        assert!(result.contains("`pkcs11` feature"), "{}", result);
+    }
+
+    // nothing was re-encrypted or recorded
+    assert_eq!(AegFileSystem::read_store_config().hsm, None);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter2")
+    );
+    assert!(AegCore::set_hsm(None).starts_with('✓'));
+}
@@ -0,0 +1,69 @@
+use aegisrlib::{AegCore, AegTestHarness};
+use std::fs;
+
+#[test]
+fn dotenv_files_import_with_quotes_comments_and_multiline_values() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("staging");
+    AegCore::put_value("API_KEY", "old");
+    let mut core = AegCore::load();
+    core.set_active_collection("staging").unwrap();
+    AegCore::put_value("API_KEY", "old");
+    core.set_active_collection("default").unwrap();
+
+    let env = dir.join(".env");
+    fs::write(
+        &env,
+        r#"# database
+export DB_URL=postgres://localhost/app  # local only
+API_KEY="k-\"1\"" # rotated
+LITERAL='no $expansion \n here'
+CERT="-----BEGIN CERT-----
+MIIB
+-----END CERT-----"
+EMPTY=
+"#,
+    )
+    .unwrap();
+    let message = AegCore::import_dotenv(&env, Some("staging"));
+    assert_eq!(
+        message,
+        format!(
+            "✓ Imported {} into collection 'staging': 4 created, 1 overwritten (in-memory)",
+            env.display()
+        )
+    );
+    // the active collection was left alone
+    assert_eq!(AegCore::get_value("API_KEY").as_deref(), Some("old"));
+    assert_eq!(AegCore::get_value("DB_URL"), None);
+
+    let mut core = AegCore::load();
+    core.set_active_collection("staging").unwrap();
+    assert_eq!(
+        AegCore::get_value("DB_URL").as_deref(),
+        Some("postgres://localhost/app")
+    );
+    assert_eq!(AegCore::get_value("API_KEY").as_deref(), Some(r#"k-"1""#));
+    assert_eq!(
+        AegCore::get_value("LITERAL").as_deref(),
+        Some(r"no $expansion \n here")
+    );
+    assert_eq!(
+        AegCore::get_value("CERT").as_deref(),
+        Some("-----BEGIN CERT-----\nMIIB\n-----END CERT-----")
+    );
+    assert_eq!(AegCore::get_value("EMPTY").as_deref(), Some(""));
+
+    fs::write(&env, "OK=1\nBROKEN=\"never closed\nNEXT=2\n").unwrap();
+    assert_eq!(
+        AegCore::import_dotenv(&env, None),
+        "✗ Import failed: line 2: unterminated quote"
+    );
+    fs::write(&env, "A=\"x\" trailing\n").unwrap();
+    assert!(AegCore::import_dotenv(&env, None).contains("after the closing quote"));
+    assert_eq!(
+        AegCore::import_dotenv(&env, Some("nope")),
+        "✗ Collection 'nope' does not exist"
+    );
+}
@@ -0,0 +1,78 @@
+use aegisrlib::{
+    AegCore, AegCrypto, AegFileFormat, AegFileSystem, AegMemoryEngine, AegTestHarness, AekvHeader,
+    Codec,
+};
+use std::fs;
+
+#[test]
+fn verify_reports_each_collection_and_catches_bad_checksums() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::put_value("token", "t");
+    AegCore::create_collection("broken");
+    AegCore::create_collection("empty");
+    AegCore::put_qualified("broken::token", "b");
+    AegCore::flush_now();
+
+    let report = AegCore::verify();
+    assert!(report.passed(), "{}", report.summary());
+    let names: Vec<&str> = report
+        .collections
+        .iter()
+        .map(|c| c.collection.as_str())
+        .collect();
+    assert_eq!(names, vec!["broken", "default", "empty"]);
+    assert!(report.collections[2].files.is_empty());
+
+    // every saved file carries the checksum of its plaintext
+    let file = dir.join("collection_broken.aekv");
+    let saved = fs::read(&file).unwrap();
+    let header = AekvHeader::parse(&saved).unwrap().unwrap();
+    let key = AegCrypto::derive_collection_key(&AegFileSystem::read_authorization_key(), "broken")
+        .unwrap();
+    let plain = AegFileFormat::decode(&key, &saved).unwrap();
+    let data_key = AegFileFormat::data_key(&key, &saved).unwrap();
+    assert_eq!(
+        header.checksum,
+        Some(AekvHeader::checksum_of(&data_key, &plain))
+    );
+
+    // a file that decrypts but whose checksum does not match
+    let forged = AekvHeader {
+        checksum: Some([0; 32]),
+        nonce: AegCrypto::random_nonce().unwrap(),
+        ..header
+    }
+    .wrap_data_key(&key, &data_key)
+    .unwrap();
+    let sealed = AegCrypto::seal_with_nonce(
+        forged.cipher,
+        &data_key,
+        &forged.nonce,
+        &forged.authenticated_bytes(),
+        &Codec::Cbor
+            .serialize(&AegMemoryEngine::new("broken"))
+            .unwrap(),
+    )
+    .unwrap();
+    let bytes = [forged.to_bytes(), sealed].concat();
+    assert!(
+        AegFileFormat::decode(&key, &bytes)
+            .unwrap_err()
+            .contains("checksum")
+    );
+    fs::write(&file, &bytes).unwrap();
+
+    let report = AegCore::verify();
+    assert!(!report.passed());
+    let broken = &report.collections[0];
+    assert!(!broken.passed());
+    assert!(report.collections[1].passed());
+    let summary = report.summary();
+    assert!(summary.contains("✗ CORRUPT  broken"), "{}", summary);
+    assert!(summary.contains("✓ OK       default"), "{}", summary);
+    assert!(summary.ends_with("2 collections OK, 1 corrupt"));
+
+    // verifying wrote nothing
+    assert_eq!(fs::read(&file).unwrap(), bytes);
+}
@@ -0,0 +1,57 @@
+use aegisrlib::{
+    AEKV_FORMAT_VERSION, AegCore, AegIntrospect, AegMemoryEngine, AegTestHarness, Cipher, Codec,
+    StoreFileKind,
+};
+
+#[test]
+fn describe_reports_files_collections_and_saver_without_loading() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("staging");
+    AegCore::create_collection("idle");
+    AegCore::set_autosave("staging", false);
+    AegCore::set_warm_capacity("staging", Some(5));
+    AegCore::put_value("saved", "x");
+    AegCore::flush_now();
+    AegCore::put_value("pending", "y");
+
+    let store = AegIntrospect::describe();
+    assert_eq!(store.store_dir, dir);
+    assert_eq!(store.format_version, AEKV_FORMAT_VERSION);
+    assert_eq!(store.cipher, Cipher::Aes256Gcm);
+    assert_eq!(store.active_collection, "default");
+    assert!(!store.saver.running);
+    let default = &store.collections[0];
+    assert!(default.active && default.loaded && default.unsaved);
+    let staging = store
+        .collections
+        .iter()
+        .find(|c| c.name == "staging")
+        .unwrap();
+    assert!(!staging.autosave);
+    assert_eq!(staging.warm_capacity, Some(5));
+    let idle = store.collections.iter().find(|c| c.name == "idle").unwrap();
+    assert!(!idle.loaded && !idle.unsaved);
+
+    assert_eq!(store.files[0].kind, StoreFileKind::Lock);
+    let data = store
+        .files
+        .iter()
+        .find(|f| f.collection.as_deref() == Some("default"))
+        .unwrap();
+    assert_eq!(data.kind, StoreFileKind::Data);
+    assert_eq!(data.format_version, Some(AEKV_FORMAT_VERSION));
+    assert_eq!(data.codec, Some(Codec::Cbor));
+    assert!(data.bytes > 0);
+    // describing never loads a collection
+    assert!(!AegMemoryEngine::cached_collections().contains(&"idle".to_string()));
+
+    AegCore::start_background_saver(3);
+    let saver = AegIntrospect::describe().saver;
+    assert!(saver.running);
+    assert_eq!(saver.interval_seconds, Some(3));
+    AegCore::stop_background_saver();
+    let json = serde_json::to_value(AegIntrospect::describe()).unwrap();
+    assert_eq!(json["files"][0]["kind"], "lock");
+    assert_eq!(json["saver"]["running"], false);
+}
@@ -0,0 +1,51 @@
+use aegisrlib::{AegCore, AegCrypto, AegFileSystem, AegTestHarness, Verbosity};
+use std::fs;
+
+#[test]
+fn malformed_keys_are_reported_and_long_material_can_be_rederived() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let key_file = dir.join("AUTHORIZATION_KEY");
+    assert_eq!(
+        AegCore::check_key(false),
+        "✓ Authorization key is valid (32 bytes) and opens the store"
+    );
+
+    assert_eq!(
+        AegCrypto::validate_key("  ").unwrap_err(),
+        "auth key is empty"
+    );
+    assert!(
+        AegCrypto::validate_key("not base64!")
+            .unwrap_err()
+            .contains("not valid base64")
+    );
+    let short = AegCrypto::encode_base64([7u8; 16]);
+    assert!(
+        AegCrypto::validate_key(&short)
+            .unwrap_err()
+            .contains("decodes to 16 bytes but must be exactly 32")
+    );
+    assert!(AegCrypto::rederive_key(&short).is_err());
+    // records and blobs refuse a bad key instead of panicking
+    assert!(AegCrypto::encrypt_record(&short, b"x").is_err());
+    assert!(AegCrypto::decrypt_record(&short, "AAAA").is_err());
+
+    let material = AegCrypto::encode_base64([42u8; 64]);
+    fs::write(&key_file, &material).unwrap();
+    let error = AegFileSystem::try_read_authorization_key().unwrap_err();
+    assert!(error.contains("decodes to 64 bytes"), "{}", error);
+    assert!(error.contains("--rederive"), "{}", error);
+    assert!(AegCore::check_key(false).starts_with("✗ auth key decodes to 64 bytes"));
+
+    assert!(AegCore::check_key(true).starts_with("✓ Authorization key re-derived"));
+    let derived = fs::read_to_string(&key_file).unwrap();
+    assert_eq!(derived, *AegCrypto::rederive_key(&material).unwrap());
+    assert!(AegCrypto::validate_key(&derived).is_ok());
+    // the store was encrypted with the key the material replaced
+    assert!(AegCore::check_key(false).contains("does not open collection.lock"));
+
+    fs::remove_file(dir.join("collection.lock")).unwrap();
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    assert!(AegCore::check_key(false).starts_with('✓'));
+}
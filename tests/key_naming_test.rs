@@ -0,0 +1,59 @@
+use aegisrlib::{AegCore, AegTestHarness, KeyConvention};
+
+#[test]
+fn new_keys_must_follow_the_collection_convention() {
+    let snake = KeyConvention::Snake;
+    assert!(snake.check("db/api_key").is_ok());
+    assert_eq!(snake.fix("DB/apiKey").as_deref(), Some("db/api_key"));
+    assert_eq!(
+        KeyConvention::Screaming
+            .fix("aws/secretAccessKey")
+            .as_deref(),
+        Some("AWS/SECRET_ACCESS_KEY")
+    );
+    assert_eq!(
+        KeyConvention::Kebab.fix("OAuthToken").as_deref(),
+        Some("o-auth-token")
+    );
+    let pattern: KeyConvention = "regex:[a-z/]+".parse().unwrap();
+    assert!(pattern.check("db/url").is_ok());
+    assert!(pattern.check("db/url2").is_err());
+    assert!("regex:[".parse::<KeyConvention>().is_err());
+
+    let _store = AegTestHarness::temp_dir();
+    let collection = AegCore::load().get_active_collection().to_string();
+
+    AegCore::put_value("LegacyKey", "v1");
+    AegCore::set_key_convention(&collection, Some(KeyConvention::Snake));
+    assert_eq!(
+        AegCore::load().key_convention(&collection),
+        Some(&KeyConvention::Snake)
+    );
+
+    let msg = AegCore::put_value("dbPassword", "hunter2");
+    assert!(
+        msg.starts_with('✗') && msg.contains("'db_password'"),
+        "{}",
+        msg
+    );
+    assert!(AegCore::put_value("db_password", "hunter2").starts_with('✓'));
+    // existing keys can still be updated
+    assert!(AegCore::put_value("LegacyKey", "v2").starts_with('✓'));
+
+    let fixes = AegCore::key_name_fixes(&collection).unwrap();
+    assert_eq!(
+        fixes,
+        vec![("LegacyKey".to_string(), Some("legacy_key".to_string()))]
+    );
+
+    assert!(AegCore::rename_key("LegacyKey", "Legacy-Key").starts_with('✗'));
+    let msg = AegCore::rename_key("LegacyKey", "legacy_key");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(AegCore::get_value("legacy_key").unwrap(), "v2");
+    assert!(AegCore::get_value("LegacyKey").is_none());
+    assert_eq!(AegCore::get_history("legacy_key").len(), 2);
+    assert!(AegCore::key_name_fixes(&collection).unwrap().is_empty());
+
+    AegCore::set_key_convention(&collection, None);
+    assert!(AegCore::put_value("AnyName", "ok").starts_with('✓'));
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse};
+
+#[test]
+fn hierarchical_keys_list_as_children_and_tree() {
+    let _store = AegTestHarness::temp_dir();
+    for key in [
+        "app/db/password",
+        "app/db/user",
+        "app/db/replica/host",
+        "app/api_key",
+        "app/db",
+        "appendix",
+        "top",
+    ] {
+        AegCore::put_value(key, "v");
+    }
+
+    assert_eq!(
+        AegCore::list_children("app/db").unwrap(),
+        vec!["password", "replica/", "user"]
+    );
+    assert_eq!(
+        AegCore::list_children("app").unwrap(),
+        vec!["api_key", "db", "db/"]
+    );
+    assert_eq!(
+        AegCore::list_children("").unwrap(),
+        vec!["app/", "appendix", "top"]
+    );
+    assert!(AegCore::list_children("nothing/here").unwrap().is_empty());
+
+    let tree = AegCore::key_tree("").unwrap();
+    assert_eq!(
+        tree,
+        vec![
+            "app/",
+            "  api_key",
+            "  db",
+            "  db/",
+            "    password",
+            "    replica/",
+            "      host",
+            "    user",
+            "appendix",
+            "top",
+        ]
+    );
+    let response = AegDispatch::execute(AegisrCommand::Tree {
+        path: Some("app/db".into()),
+    });
+    match response {
+        AegisrResponse::Ok { message, .. } => {
+            assert_eq!(message, "password\nreplica/\n  host\nuser")
+        }
+        other => panic!("{:?}", other),
+    }
+
+    // the sorted index follows later writes and deletes
+    AegCore::put_value("app/db/port", "5432");
+    AegCore::delete_value("app/db/user");
+    assert_eq!(
+        AegCore::list_children("app/db").unwrap(),
+        vec!["password", "port", "replica/"]
+    );
+
+    // deleting a subtree leaves the key named like it and its neighbours
+    assert!(AegCore::delete_subtree("").starts_with('✗'));
+    let response = AegDispatch::execute(AegisrCommand::DelTree {
+        path: "app/".into(),
+    });
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+    assert_eq!(AegCore::list_keys(), vec!["appendix", "top"]);
+    assert!(AegCore::delete_subtree("app").starts_with('✗'));
+
+    // paths may name another collection
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::svc/a", "1");
+    AegCore::put_qualified("prod::svc/b", "2");
+    assert_eq!(AegCore::list_children("prod::svc").unwrap(), vec!["a", "b"]);
+    assert!(AegCore::list_children("missing::svc").is_err());
+    assert!(AegCore::delete_subtree("prod::svc/").starts_with('✓'));
+    assert!(AegCore::list_subtree("prod::").unwrap().is_empty());
+}
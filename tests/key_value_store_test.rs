@@ -0,0 +1,66 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness, KeyValueStore};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// What a downstream crate would write to test against the trait.
+#[derive(Default)]
+struct MockStore(Mutex<BTreeMap<String, String>>);
+
+impl KeyValueStore for MockStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), String> {
+        self.0.lock().unwrap().insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, String> {
+        Ok(self.0.lock().unwrap().remove(key).is_some())
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.0.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Application code under test, written against the trait.
+fn rotate(store: &dyn KeyValueStore, key: &str) -> Result<String, String> {
+    let old = store.get(key).ok_or("nothing to rotate")?;
+    store.put(&format!("{}.previous", key), &old)?;
+    store.put(key, &format!("{}-rotated", old))?;
+    store.flush()?;
+    Ok(old)
+}
+
+#[test]
+fn mock_and_real_store_behave_alike() {
+    let mock = MockStore::default();
+    mock.put("api/token", "t1").unwrap();
+    assert_eq!(rotate(&mock, "api/token").unwrap(), "t1");
+    assert_eq!(mock.get("api/token").as_deref(), Some("t1-rotated"));
+
+    let _store = AegTestHarness::temp_dir();
+    let core = AegCore::load();
+    let store: &dyn KeyValueStore = &core;
+    store.put("api/token", "t1").unwrap();
+    assert_eq!(rotate(store, "api/token").unwrap(), "t1");
+    assert_eq!(store.list(), mock.list());
+    assert_eq!(store.get("api/token"), mock.get("api/token"));
+
+    assert!(store.delete("api/token.previous").unwrap());
+    assert!(!store.delete("api/token.previous").unwrap());
+    assert!(rotate(store, "missing").is_err());
+
+    // flushed to disk
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value("api/token").as_deref(),
+        Some("t1-rotated")
+    );
+}
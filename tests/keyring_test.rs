@@ -0,0 +1,92 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegFileSystem, AegKeyring, AegMemoryEngine, AegTestHarness,
+    AegisrCommand, AegisrResponse, KeyBackend, KeyringBackend, MemoryKeyring,
+    STORE_AUTHORIZATION_KEY,
+};
+use std::fs;
+use std::sync::Arc;
+
+/// A keyring that is not there, like a headless box without a Secret Service.
+struct NoKeyring;
+
+impl KeyringBackend for NoKeyring {
+    fn get(&self, _service: &str, _account: &str) -> Result<Option<String>, String> {
+        Err("no keyring daemon".into())
+    }
+
+    fn set(&self, _service: &str, _account: &str, _secret: &str) -> Result<(), String> {
+        Err("no keyring daemon".into())
+    }
+
+    fn delete(&self, _service: &str, _account: &str) -> Result<(), String> {
+        Err("no keyring daemon".into())
+    }
+}
+
+fn init_with_keyring() -> AegisrResponse {
+    AegDispatch::execute(AegisrCommand::Init {
+        reset: false,
+        bind_machine: false,
+        cipher: None,
+        key_backend: Some(KeyBackend::Keyring),
+        token_slot: None,
+    })
+}
+
+#[test]
+fn authorization_key_moves_into_the_os_keyring_and_back() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    let keyring = Arc::new(MemoryKeyring::new());
+    AegKeyring::set_backend(keyring.clone());
+    AegCore::put_value("api", "secret");
+    AegCore::flush_now();
+    let key_file = dir.join(STORE_AUTHORIZATION_KEY);
+    let stored = fs::read_to_string(&key_file).unwrap();
+
+    let response = init_with_keyring();
+    assert!(
+        matches!(&response, AegisrResponse::Ok { message, .. } if !message.contains('⚠')),
+        "{:?}",
+        response
+    );
+    assert_eq!(AegCore::key_backend(), KeyBackend::Keyring);
+    assert!(!key_file.exists());
+    let account = AegFileSystem::read_store_config().keyring.unwrap().account;
+    assert_eq!(
+        keyring.get("aegisr", &account).unwrap().as_deref(),
+        Some(stored.as_str())
+    );
+
+    // the store opens with the key from the keyring alone
+    AegMemoryEngine::reset_cache();
+    AegKeyring::forget_keys();
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("secret"));
+    assert!(AegCore::check_key(false).starts_with('✓'));
+    AegFileSystem::validate_files();
+    assert!(!key_file.exists());
+    assert!(AegCore::set_key_backend(KeyBackend::Keyring).contains("already"));
+
+    assert!(AegCore::set_key_backend(KeyBackend::File).starts_with('✓'));
+    assert_eq!(fs::read_to_string(&key_file).unwrap(), stored);
+    assert_eq!(keyring.get("aegisr", &account).unwrap(), None);
+    assert_eq!(AegCore::key_backend(), KeyBackend::File);
+
+    // without a usable keyring, init keeps the key file and says so
+    AegKeyring::set_backend(Arc::new(NoKeyring));
+    match init_with_keyring() {
+        AegisrResponse::Ok { message, .. } => {
+            assert!(
+                message.contains("⚠ The OS keyring cannot hold the key"),
+                "{}",
+                message
+            )
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(key_file.exists());
+    assert_eq!(AegCore::key_backend(), KeyBackend::File);
+    assert_eq!(AegCore::get_value("api").as_deref(), Some("secret"));
+}
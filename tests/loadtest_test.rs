@@ -0,0 +1,31 @@
+use aegisrlib::{AegLoadtest, AegTestHarness, LoadtestConfig};
+use std::time::Duration;
+
+#[test]
+fn loadtest_reports_every_operation() {
+    let _store = AegTestHarness::memory();
+    let config = LoadtestConfig {
+        workers: 3,
+        ops_per_worker: 500,
+        key_space: 50,
+        saver_interval: Some(Duration::from_millis(5)),
+        ..LoadtestConfig::default()
+    };
+    let report = AegLoadtest::run(&config).unwrap();
+
+    assert_eq!(report.total_ops, 1500);
+    assert_eq!(
+        report.reads.count + report.puts.count + report.deletes.count,
+        1500
+    );
+    assert_eq!(report.idle.count + report.during_save.count, 1500);
+    assert!(report.reads.p50_us <= report.reads.p99_us);
+    assert!(report.summary().contains("ops/s"));
+
+    let bad = LoadtestConfig {
+        read_pct: 90,
+        put_pct: 20,
+        ..config
+    };
+    assert!(AegLoadtest::run(&bad).is_err());
+}
@@ -0,0 +1,55 @@
+use aegisrlib::{AegCore, AegCrypto, AegFileSystem, AegTestHarness, AegVerifier, Verbosity};
+use std::fs;
+
+#[test]
+fn machine_binding_round_trip_and_portable_export() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("binding_key", "binding_value");
+
+    let stored = AegFileSystem::read_stored_authorization_key();
+    let msg = AegCore::set_machine_binding(true);
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegFileSystem::read_store_config().machine_binding);
+
+    // files are now encrypted with the bound key, not the stored one
+    let bound = AegFileSystem::read_authorization_key();
+    assert_ne!(*bound, stored);
+    assert!(AegVerifier::verify_all(&bound).passed());
+    assert!(!AegVerifier::verify_all(&stored).passed());
+
+    // the portable export opens with the plain stored key
+    let dest = std::env::temp_dir().join(format!("aegisr_portable_{}", std::process::id()));
+    let msg = AegCore::export_portable(&dest);
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(
+        fs::read_to_string(dest.join("AUTHORIZATION_KEY")).unwrap(),
+        stored
+    );
+    for name in AegFileSystem::list_store_files(&dest) {
+        let check = AegVerifier::verify_file(&dest.join(&name), &stored);
+        assert!(check.passed(), "{:?}", check);
+    }
+    fs::remove_dir_all(&dest).unwrap();
+
+    let msg = AegCore::set_machine_binding(false);
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegVerifier::verify_all(&stored).passed());
+    assert_eq!(AegCore::get_value("binding_key").unwrap(), "binding_value");
+
+    AegCore::delete_value("binding_key");
+    AegCore::flush_now();
+}
+
+#[test]
+fn bindings_refuse_keys_of_the_wrong_size() {
+    let key = AegCrypto::create_authorization_key(Verbosity::default());
+    let bound = AegCrypto::bind_key_to_machine(&key, "host").unwrap();
+    assert_eq!(bound, AegCrypto::bind_key_to_machine(&key, "host").unwrap());
+    assert!(AegCrypto::validate_key(&bound).is_ok());
+
+    // 4 bytes of key material used to be accepted and bound as is
+    let short = AegCrypto::encode_base64(b"abcd");
+    assert!(AegCrypto::bind_key_to_machine(&short, "host").is_err());
+    assert!(AegCrypto::derive_collection_key(&short, "default").is_err());
+    assert!(AegCrypto::bind_key_to_passphrase(&short, &key).is_err());
+}
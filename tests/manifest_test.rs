@@ -0,0 +1,38 @@
+use aegisrlib::{AegCore, AegFileSystem, AegTestHarness, ProjectManifest};
+
+#[test]
+fn project_manifest_selects_collection_and_checks_keys() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "manifest_test";
+    AegCore::create_collection(collection);
+    let persisted = AegCore::load().active_collection;
+
+    let project = std::env::temp_dir().join(format!("aegisr_project_{}", std::process::id()));
+    let nested = project.join("src").join("bin");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(
+        project.join(".aegisr.toml"),
+        "# pinned secrets\ncollection = \"manifest_test\"\nrequired = [\"db/password\", \"api_token\"] # both\n",
+    )
+    .unwrap();
+
+    let err = AegCore::enter_project(&nested).unwrap_err();
+    assert!(err.contains("db/password, api_token"), "{}", err);
+    assert_eq!(AegCore::load().active_collection, collection);
+    assert_eq!(
+        AegFileSystem::read_collection_lock_obj().unwrap().active,
+        persisted
+    );
+
+    AegCore::put_value("db/password", "pw");
+    AegCore::put_value("api_token", "tok");
+    let manifest = AegCore::enter_project(&nested).unwrap().unwrap();
+    assert_eq!(manifest.collection.as_deref(), Some(collection));
+    assert!(ProjectManifest::parse("required = [\"a\"").is_err());
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    AegCore::clear_session_collection();
+    AegCore::delete_collection(collection);
+    std::fs::remove_dir_all(&project).unwrap();
+}
@@ -0,0 +1,47 @@
+use aegisrlib::{AegCore, AegFileSystem, AegTestHarness};
+
+#[test]
+fn snapshots_profiles_backups_and_the_decoy_live_in_memory_storage() {
+    let store = AegTestHarness::memory();
+    let storage = AegFileSystem::storage();
+    let dir = store.dir().to_path_buf();
+
+    AegCore::put_value("db/password", "hunter2");
+    let result = AegCore::snapshot("before");
+    assert!(result.starts_with('✓'), "{}", result);
+    let snapshots = AegCore::list_snapshots();
+    assert_eq!(snapshots.len(), 1);
+    assert!(snapshots[0].files > 0);
+
+    AegCore::put_value("db/password", "changed");
+    AegCore::flush_now();
+    let result = AegCore::restore_snapshot("before");
+    assert!(result.starts_with('✓'), "{}", result);
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter2")
+    );
+
+    let dest = dir.with_file_name(format!(
+        "{}_backup",
+        dir.file_name().unwrap().to_string_lossy()
+    ));
+    let result = AegCore::backup(&dest);
+    assert!(result.starts_with('✓'), "{}", result);
+    assert!(storage.exists(&dest.join("collection.lock")));
+    assert!(AegCore::backup(&dest).starts_with('✗'));
+
+    assert!(AegCore::create_profile("work").starts_with('✓'));
+    assert!(AegCore::list_profiles().contains(&"work".to_string()));
+    assert!(AegCore::delete_profile("work").starts_with('✓'));
+    assert!(!AegCore::list_profiles().contains(&"work".to_string()));
+
+    assert!(AegCore::setup_duress("decoy passphrase").starts_with('✓'));
+    assert!(storage.exists(&AegFileSystem::get_decoy_path()));
+
+    assert!(!dir.exists());
+    assert!(!dest.exists());
+    let result = AegCore::destroy_all();
+    assert!(result.starts_with('✓'), "{}", result);
+    assert!(!storage.exists(&dir));
+}
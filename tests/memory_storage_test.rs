@@ -0,0 +1,57 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, MemoryStorage, Verbosity};
+use std::sync::Arc;
+
+#[test]
+fn store_runs_on_memory_storage_without_touching_the_disk() {
+    let dir = std::env::temp_dir().join(format!("aegisr_memory_storage_{}", std::process::id()));
+    let storage = Arc::new(MemoryStorage::new());
+    AegFileSystem::set_storage(storage.clone());
+    AegCore::set_store_dir(dir.clone());
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::create_collection("cold");
+    AegCore::set_warm_capacity("cold", Some(1));
+
+    AegCore::put_value("db/password", "hunter2");
+    AegMemoryEngine::with_engine("cold", |engine| {
+        for i in 0..5 {
+            engine.insert(format!("k{}", i), format!("v{}", i));
+        }
+    });
+    AegCore::flush_now();
+    assert!(!dir.exists());
+
+    let mut names: Vec<String> = storage
+        .contents()
+        .into_iter()
+        .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    for expected in [
+        "AUTHORIZATION_KEY",
+        "collection.lock",
+        "collection_cold.aekv",
+        "collection_cold.cold",
+        "collection_default.aekv",
+    ] {
+        assert!(names.iter().any(|n| n == expected), "{:?}", names);
+    }
+
+    // a fresh process would start from the saved files
+    AegMemoryEngine::reset_cache();
+    let reloaded = Arc::new(MemoryStorage::with_files(storage.contents()));
+    AegFileSystem::set_storage(reloaded);
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter2")
+    );
+    assert_eq!(
+        AegMemoryEngine::fetch_shared("cold", "k0").as_deref(),
+        Some("v0")
+    );
+    assert!(AegCore::load().collections.contains(&"cold".to_string()));
+    assert!(AegCore::check_key(false).starts_with('✓'));
+
+    AegFileSystem::clear_base_dir();
+    AegMemoryEngine::reset_cache();
+    assert!(!dir.exists());
+}
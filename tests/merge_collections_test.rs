@@ -0,0 +1,80 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegTestHarness, AegisrCommand, AegisrResponse,
+    MergeStrategy,
+};
+
+fn value(qualified: &str) -> Option<String> {
+    AegCore::get_qualified(qualified).unwrap()
+}
+
+#[test]
+fn merge_settles_conflicts_by_strategy() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("staging");
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("staging::db_url", "postgres://staging");
+    AegCore::put_qualified("staging::api_key", "k");
+    AegCore::put_qualified("staging::region", "eu");
+    AegMemoryEngine::with_engine("staging", |engine| {
+        engine.tag("api_key", "rotate");
+    });
+    AegCore::put_qualified("prod::db_url", "postgres://prod");
+    AegCore::put_qualified("prod::region", "eu");
+    AegCore::put_qualified("prod::only_prod", "x");
+
+    // conflicts abort the default strategy and change nothing
+    let response = AegDispatch::execute(AegisrCommand::Merge {
+        src: "staging".into(),
+        dest: "prod".into(),
+        strategy: MergeStrategy::default(),
+    });
+    match response {
+        AegisrResponse::Error { message } => {
+            assert!(message.contains("aborted"), "{}", message);
+            assert!(message.contains("db_url"), "{}", message);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(value("prod::api_key"), None);
+
+    let report = AegCore::merge_collections("staging", "prod", MergeStrategy::KeepDest).unwrap();
+    assert!(!report.aborted);
+    assert_eq!(report.added, vec!["api_key"]);
+    assert_eq!(report.unchanged, 1);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].key, "db_url");
+    assert!(!report.conflicts[0].overwritten);
+    assert_eq!(value("prod::db_url").as_deref(), Some("postgres://prod"));
+    let tags = AegMemoryEngine::read_engine("prod", |engine| engine.entry("api_key").unwrap().tags);
+    assert_eq!(tags, vec!["rotate"]);
+
+    let response = AegDispatch::execute(AegisrCommand::Merge {
+        src: "staging".into(),
+        dest: "prod".into(),
+        strategy: MergeStrategy::Overwrite,
+    });
+    match response {
+        AegisrResponse::Ok { message, .. } => {
+            assert!(
+                message.contains("0 added, 2 unchanged, 1 conflicts"),
+                "{}",
+                message
+            )
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(value("prod::db_url").as_deref(), Some("postgres://staging"));
+    assert_eq!(value("prod::only_prod").as_deref(), Some("x"));
+    assert_eq!(
+        value("staging::db_url").as_deref(),
+        Some("postgres://staging")
+    );
+
+    assert!(AegCore::merge_collections("prod", "prod", MergeStrategy::Overwrite).is_err());
+    assert!(AegCore::merge_collections("missing", "prod", MergeStrategy::Overwrite).is_err());
+    assert!(AegCore::merge_collections("prod", "missing", MergeStrategy::Overwrite).is_err());
+    assert_eq!("keep".parse::<MergeStrategy>(), Ok(MergeStrategy::KeepDest));
+    assert!("newest".parse::<MergeStrategy>().is_err());
+}
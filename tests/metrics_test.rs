@@ -0,0 +1,75 @@
+#![cfg(all(feature = "cli", feature = "server"))]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMetrics, AegServer, AegTestHarness, AegisrCommand, AegisrResponse,
+};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+#[test]
+fn operations_are_counted_and_served_to_prometheus() {
+    let _store = AegTestHarness::temp_dir();
+    AegMetrics::reset();
+
+    AegCore::put_value("api", "secret");
+    AegCore::put_value("db", "hunter2");
+    assert!(AegCore::get_value("api").is_some());
+    assert!(AegCore::get_value("missing").is_none());
+    AegCore::delete_value("db");
+    AegCore::flush_now();
+
+    let metrics = AegCore::metrics_snapshot();
+    assert_eq!(metrics.puts.count, 2);
+    assert_eq!(metrics.gets.count, 2);
+    assert_eq!(metrics.get_misses, 1);
+    assert_eq!(metrics.deletes, 1);
+    assert!(metrics.cache_misses >= 1, "{:?}", metrics);
+    assert!(metrics.cache_hits >= 4, "{:?}", metrics);
+    assert_eq!(metrics.saves.count, 1);
+    assert_eq!(metrics.save_failures, 0);
+    assert!(metrics.bytes_written > 0);
+    assert!(metrics.saves.max_micros >= metrics.saves.mean_micros());
+
+    let text = metrics.to_prometheus();
+    assert!(text.contains("# TYPE aegisr_get_misses_total counter\naegisr_get_misses_total 1\n"));
+    assert!(
+        text.contains("aegisr_put_duration_seconds_count 2\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains(&format!(
+        "aegisr_bytes_written_total {}\n",
+        metrics.bytes_written
+    )));
+
+    match AegDispatch::execute(AegisrCommand::Metrics { prometheus: false }) {
+        AegisrResponse::Ok {
+            message,
+            data: Some(data),
+        } => {
+            assert!(message.contains("get misses    1"), "{}", message);
+            assert_eq!(data["deletes"], 1);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let server = AegServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET /metrics HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\n\r\n",
+        AegServer::token().unwrap()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(
+        response.contains("aegisr_deletes_total 1\n"),
+        "{}",
+        response
+    );
+}
@@ -0,0 +1,65 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegKeyring, AegTestHarness, KeyBackend, KeyringBackend, MemoryKeyring,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn nuke_requires_token_and_shreds_directory() {
+    let _store = AegTestHarness::temp_dir();
+    let token = AegCore::nuke_token();
+    let msg = AegCore::nuke(&token, "DESTROY-WRONG", Duration::ZERO);
+    assert!(msg.starts_with('✗'), "{}", msg);
+    assert!(
+        AegFileSystem::get_real_config_path()
+            .join("AUTHORIZATION_KEY")
+            .exists()
+    );
+
+    let dir = std::env::temp_dir().join(format!("aegisr_shred_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("secret"), "top secret").unwrap();
+    std::fs::write(dir.join("nested").join("more"), "also secret").unwrap();
+    assert_eq!(AegFileSystem::shred_directory(&dir).unwrap(), 2);
+    assert!(!dir.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn shredding_unlinks_symlinks_without_following_them() {
+    let root = std::env::temp_dir().join(format!("aegisr_shred_links_{}", std::process::id()));
+    let (store, outside) = (root.join("store"), root.join("outside"));
+    std::fs::create_dir_all(&store).unwrap();
+    std::fs::create_dir_all(outside.join("dir")).unwrap();
+    std::fs::write(outside.join("file"), "not the store's").unwrap();
+    std::fs::write(outside.join("dir").join("inner"), "nor this").unwrap();
+    std::fs::write(store.join("secret"), "top secret").unwrap();
+    std::os::unix::fs::symlink(outside.join("file"), store.join("file_link")).unwrap();
+    std::os::unix::fs::symlink(outside.join("dir"), store.join("dir_link")).unwrap();
+
+    assert_eq!(AegFileSystem::shred_directory(&store).unwrap(), 1);
+    assert!(!store.exists());
+    assert_eq!(
+        std::fs::read_to_string(outside.join("file")).unwrap(),
+        "not the store's"
+    );
+    assert_eq!(
+        std::fs::read_to_string(outside.join("dir").join("inner")).unwrap(),
+        "nor this"
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn destroying_the_store_deletes_its_keyring_entry() {
+    let _store = AegTestHarness::temp_dir();
+    let keyring = Arc::new(MemoryKeyring::new());
+    AegKeyring::set_backend(keyring.clone());
+    assert!(AegCore::set_key_backend(KeyBackend::Keyring).starts_with('✓'));
+    let account = AegFileSystem::read_store_config().keyring.unwrap().account;
+    assert!(keyring.get("aegisr", &account).unwrap().is_some());
+
+    let msg = AegCore::destroy_all();
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(keyring.get("aegisr", &account).unwrap(), None);
+}
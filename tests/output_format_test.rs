@@ -0,0 +1,57 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, OutputFormat,
+};
+
+#[test]
+fn ls_and_dump_render_as_plain_table_or_json() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("db/password", "hunter2");
+    AegCore::put_value("api", "k-1");
+    AegCore::create_collection("other");
+
+    let ls = AegDispatch::execute(AegisrCommand::Keys {
+        tag: None,
+        collection: None,
+    });
+    assert_eq!(ls.render(OutputFormat::Plain), "api\ndb/password");
+    assert_eq!(ls.render(OutputFormat::Table), "VALUE\napi\ndb/password");
+    let json: serde_json::Value = serde_json::from_str(&ls.render(OutputFormat::Json)).unwrap();
+    assert_eq!(json["status"], "ok");
+    assert_eq!(json["data"], serde_json::json!(["api", "db/password"]));
+    let other = AegDispatch::execute(AegisrCommand::Keys {
+        tag: None,
+        collection: Some("other".into()),
+    });
+    assert_eq!(other.render(OutputFormat::Plain), "");
+    let missing = AegDispatch::execute(AegisrCommand::Keys {
+        tag: None,
+        collection: Some("nope".into()),
+    });
+    assert!(matches!(missing, AegisrResponse::Error { .. }));
+
+    let hidden = AegDispatch::execute(AegisrCommand::Dump {
+        collection: None,
+        show_values: false,
+    });
+    assert!(matches!(hidden, AegisrResponse::Error { .. }));
+    assert!(!hidden.render(OutputFormat::Json).contains("hunter2"));
+
+    let dump = AegDispatch::execute(AegisrCommand::Dump {
+        collection: None,
+        show_values: true,
+    });
+    assert_eq!(
+        dump.render(OutputFormat::Plain),
+        "api=k-1\ndb/password=hunter2"
+    );
+    assert_eq!(
+        dump.render(OutputFormat::Table),
+        "KEY          VALUE\napi          k-1\ndb/password  hunter2"
+    );
+    let json: serde_json::Value = serde_json::from_str(&dump.render(OutputFormat::Json)).unwrap();
+    assert_eq!(json["data"][1]["value"], "hunter2");
+    assert_eq!("JSON".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+    assert!("yaml".parse::<OutputFormat>().is_err());
+}
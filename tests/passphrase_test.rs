@@ -0,0 +1,70 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness, AegVerifier, SnapshotManager,
+};
+use std::fs;
+
+#[test]
+fn passphrase_change_reencrypts_everything_and_rolls_back() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::put_value("token", "t");
+    AegCore::create_collection("other");
+    AegCore::put_qualified("other::token", "o");
+    assert!(AegCore::snapshot("before").starts_with('✓'));
+
+    let mut progress = Vec::new();
+    let msg = AegCore::change_passphrase(None, Some("correct horse"), |p| progress.push(p.clone()));
+    assert!(
+        msg.starts_with("✓ Store protected by a passphrase"),
+        "{}",
+        msg
+    );
+    let last = progress.last().unwrap();
+    assert_eq!((last.done, last.total), (progress.len(), progress.len()));
+    assert!(progress.iter().any(|p| p.file.contains("before")));
+    assert!(AegFileSystem::read_store_config().passphrase.is_some());
+
+    // the key now depends on the passphrase
+    AegMemoryEngine::reset_cache();
+    AegFileSystem::set_passphrase(Some("wrong"));
+    assert!(!AegCore::verify().passed());
+    AegFileSystem::set_passphrase(Some("correct horse"));
+    assert!(AegCore::verify().passed());
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("t"));
+    let snapshot = &SnapshotManager::list()[0];
+    assert!(
+        AegVerifier::verify_dir(&snapshot.path, &AegFileSystem::read_authorization_key()).passed()
+    );
+
+    assert_eq!(
+        AegCore::change_passphrase(Some("wrong"), Some("x"), |_| {}),
+        "✗ Wrong passphrase"
+    );
+    assert!(AegCore::change_passphrase(None, Some("x"), |_| {}).starts_with('✗'));
+
+    // a file that cannot be re-encrypted leaves everything as it was
+    let config = AegFileSystem::read_store_config();
+    let lock = dir.join("collection.lock");
+    let before = fs::read(&lock).unwrap();
+    fs::write(snapshot.path.join("collection.lock"), "garbage").unwrap();
+    let msg = AegCore::change_passphrase(Some("correct horse"), Some("battery"), |_| {});
+    assert!(msg.starts_with('✗'), "{}", msg);
+    assert_eq!(fs::read(&lock).unwrap(), before);
+    assert_eq!(AegFileSystem::read_store_config(), config);
+    fs::remove_dir_all(&snapshot.path).unwrap();
+
+    let msg = AegCore::change_passphrase(Some("correct horse"), Some("battery"), |_| {});
+    assert!(msg.starts_with("✓ Passphrase changed"), "{}", msg);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("other::token").unwrap().as_deref(),
+        Some("o")
+    );
+
+    let msg = AegCore::change_passphrase(Some("battery"), None, |_| {});
+    assert!(msg.starts_with("✓ Passphrase removed"), "{}", msg);
+    AegFileSystem::set_passphrase(None);
+    AegMemoryEngine::reset_cache();
+    assert!(AegFileSystem::read_store_config().passphrase.is_none());
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("t"));
+}
@@ -0,0 +1,35 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn pending_lists_unsaved_keys() {
+    let _store = AegTestHarness::memory();
+    let collection = "pending_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::put_value("kept", "1");
+    AegCore::put_value("changed", "1");
+    AegCore::put_value("dropped", "1");
+    AegCore::flush_now();
+    assert!(AegCore::pending_changes(Some(collection)).unwrap()[0].is_empty());
+
+    AegCore::put_value("changed", "2");
+    AegCore::delete_value("dropped");
+    AegCore::put_value("fresh", "1");
+    let pending = AegCore::pending_changes(Some(collection))
+        .unwrap()
+        .remove(0);
+    assert_eq!(pending.added, vec!["fresh"]);
+    assert_eq!(pending.modified, vec!["changed"]);
+    assert_eq!(pending.removed, vec!["dropped"]);
+
+    AegCore::flush_now();
+    assert!(AegCore::pending_changes(Some(collection)).unwrap()[0].is_empty());
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
@@ -0,0 +1,71 @@
+use aegisrlib::{AegCore, AegIntrospect, AegMemoryEngine, AegTestHarness, PersistencePolicy};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+fn wait_until_saved(collection: &str, within: Duration) -> bool {
+    let deadline = Instant::now() + within;
+    while Instant::now() < deadline {
+        if AegMemoryEngine::cached_dirty(collection) == Some(false) {
+            return true;
+        }
+        sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn persistence_policies_decide_when_writes_reach_disk() {
+    let _store = AegTestHarness::temp_dir();
+    assert_eq!(AegCore::persistence_policy(), PersistencePolicy::Manual);
+
+    // every write is on disk before the call returns
+    assert!(AegCore::set_persistence_policy(PersistencePolicy::EveryWrite).is_none());
+    AegCore::put_value("a", "1");
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    // except in collections that opted out of autosave
+    AegCore::create_collection("scratch");
+    AegCore::set_autosave("scratch", false);
+    AegCore::put_qualified("scratch::tmp", "x");
+    assert_eq!(AegMemoryEngine::cached_dirty("scratch"), Some(true));
+    AegCore::flush_now();
+
+    // a burst is saved once it has been quiet for the window
+    let saver = AegCore::set_persistence_policy(PersistencePolicy::Debounced(500)).unwrap();
+    assert!(saver.is_running());
+    assert_eq!(
+        AegCore::persistence_policy(),
+        PersistencePolicy::Debounced(500)
+    );
+    for i in 0..3 {
+        AegCore::put_value(&format!("burst_{}", i), "v");
+        sleep(Duration::from_millis(30));
+    }
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+    assert!(wait_until_saved("default", Duration::from_secs(5)));
+
+    // switching to an interval reuses the running saver
+    let same = AegCore::set_persistence_policy(PersistencePolicy::Interval(3600)).unwrap();
+    assert_eq!(
+        AegCore::persistence_policy(),
+        PersistencePolicy::Interval(3600)
+    );
+    assert_eq!(AegMemoryEngine::background_saver_interval(), Some(3600));
+    assert_eq!(
+        AegIntrospect::describe().saver.policy,
+        PersistencePolicy::Interval(3600)
+    );
+    AegCore::put_value("b", "2");
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+
+    // manual stops the saver after its final save; later writes wait for
+    // flush_now
+    assert!(AegCore::set_persistence_policy(PersistencePolicy::Manual).is_none());
+    assert!(!same.is_running());
+    assert!(!saver.is_running());
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    AegCore::put_value("c", "3");
+    sleep(Duration::from_millis(100));
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+    AegCore::flush_now();
+    assert_eq!(AegCore::persistence_policy(), PersistencePolicy::Manual);
+}
@@ -0,0 +1,38 @@
+use aegisrlib::{AegCore, AegPlain, AegTestHarness, PlainFormat};
+
+#[test]
+fn plain_export_import_round_trip() {
+    let _store = AegTestHarness::temp_dir();
+    let collection = "plain_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::put_value("note", "line one\nsaid \"hi\", then left");
+    AegCore::put_value("plain", "value");
+
+    let path = std::env::temp_dir().join(format!("aegisr_plain_{}.csv", std::process::id()));
+    let msg = AegCore::export_plain_to(&path, PlainFormat::Csv, false);
+    assert!(msg.starts_with('✗'), "{}", msg);
+    assert!(!path.exists());
+
+    for format in [PlainFormat::Csv, PlainFormat::Json] {
+        let msg = AegCore::export_plain_to(&path, format, true);
+        assert!(msg.starts_with('✓'), "{}", msg);
+        AegCore::clear_values();
+        let msg = AegCore::import_plain(&path, format);
+        assert!(msg.contains("2 created, 0 overwritten"), "{}", msg);
+        assert_eq!(
+            AegCore::get_value("note").unwrap(),
+            "line one\nsaid \"hi\", then left"
+        );
+    }
+    assert!(AegPlain::decode("key,value\na,b,c\n", PlainFormat::Csv).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
@@ -0,0 +1,28 @@
+use aegisrlib::{AegCore, AegFileSystem, ProfileManager, Verbosity};
+
+#[test]
+fn profiles_have_isolated_stores() {
+    let root = std::env::temp_dir().join(format!("aegisr_profiles_{}", std::process::id()));
+    // SAFETY: this test binary runs a single test, so no other thread reads the environment
+    unsafe { std::env::set_var("AEGISR_HOME", &root) };
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::put_value("profile_key", "default value");
+
+    assert!(AegCore::create_profile("work").starts_with('✓'));
+    assert!(AegCore::create_profile("work").starts_with('✗'));
+    assert!(AegCore::create_profile("../escape").starts_with('✗'));
+    assert_eq!(AegCore::list_profiles(), vec!["default", "work"]);
+
+    assert!(AegCore::use_profile("work").starts_with('✓'));
+    assert_eq!(ProfileManager::current().as_deref(), Some("work"));
+    assert!(AegCore::get_value("profile_key").is_none());
+    AegCore::put_value("profile_key", "work value");
+    assert!(AegCore::delete_profile("work").starts_with('✗'));
+
+    assert!(AegCore::use_profile("default").starts_with('✓'));
+    assert_eq!(AegCore::get_value("profile_key").unwrap(), "default value");
+    assert!(AegCore::delete_profile("work").starts_with('✓'));
+    assert_eq!(AegCore::list_profiles(), vec!["default"]);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
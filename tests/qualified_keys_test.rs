@@ -0,0 +1,90 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegTestHarness, AegisrCommand, AegisrResponse,
+};
+use serde_json::json;
+
+#[test]
+fn qualified_keys_reach_other_collections_without_switching() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("prod");
+    AegCore::create_collection("staging");
+
+    assert!(AegCore::put_qualified("prod::db_url", "postgres://prod").starts_with('✓'));
+    let put = AegDispatch::execute(AegisrCommand::Put {
+        key: "staging::db_url".into(),
+        value: "postgres://staging".into(),
+        env_name: Some("DATABASE_URL".into()),
+        json: false,
+    });
+    assert!(
+        put.render(Default::default())
+            .contains("collection 'staging'")
+    );
+    AegCore::put_value("db_url", "sqlite://local");
+    assert_eq!(AegCore::load().active_collection, "default");
+    assert_eq!(
+        AegMemoryEngine::read_engine("staging", |e| e.env_name_for("db_url")),
+        "DATABASE_URL"
+    );
+
+    assert_eq!(
+        AegCore::get_qualified("prod::db_url"),
+        Ok(Some("postgres://prod".into()))
+    );
+    assert_eq!(
+        AegCore::get_qualified("db_url"),
+        Ok(Some("sqlite://local".into()))
+    );
+    assert_eq!(AegCore::get_qualified("prod::missing"), Ok(None));
+    assert_eq!(
+        AegCore::get_qualified("nope::db_url"),
+        Err("Collection 'nope' does not exist".into())
+    );
+    assert!(AegCore::get_qualified("prod::").is_err());
+    assert_eq!(AegCore::split_qualified("a::b::c"), (Some("a"), "b::c"));
+
+    let many = AegDispatch::execute(AegisrCommand::GetMany {
+        keys: vec![
+            "prod::db_url".into(),
+            "staging::db_url".into(),
+            "db_url".into(),
+        ],
+        no_cache: false,
+    });
+    let AegisrResponse::Ok { message, data } = many else {
+        panic!("{:?}", many);
+    };
+    assert_eq!(
+        message,
+        "prod::db_url = postgres://prod\nstaging::db_url = postgres://staging\ndb_url = sqlite://local"
+    );
+    assert_eq!(
+        data.unwrap()["staging::db_url"],
+        json!("postgres://staging")
+    );
+    let partly_missing = AegDispatch::execute(AegisrCommand::GetMany {
+        keys: vec!["prod::db_url".into(), "prod::gone".into()],
+        no_cache: false,
+    });
+    assert_eq!(
+        partly_missing.render(Default::default()),
+        "✗ Keys not found: 'prod::gone'"
+    );
+
+    let deleted = AegDispatch::execute(AegisrCommand::DelMany {
+        keys: vec!["prod::db_url".into(), "staging::db_url".into()],
+    });
+    assert!(
+        matches!(deleted, AegisrResponse::Ok { .. }),
+        "{:?}",
+        deleted
+    );
+    assert_eq!(AegCore::get_qualified("prod::db_url"), Ok(None));
+    assert!(AegCore::delete_qualified("staging::db_url").starts_with('✗'));
+    assert_eq!(
+        AegCore::get_value("db_url").as_deref(),
+        Some("sqlite://local")
+    );
+}
@@ -0,0 +1,104 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegMemoryEngine, AegSnippet, AegTestHarness, Cipher, ClockSkewPolicy,
+    KeyConvention, LintLevel, READ_ONLY_ERROR,
+};
+
+/// A memory store with one tagged key, switched to read-only.
+fn read_only_store() -> AegTestHarness {
+    let store = AegTestHarness::memory();
+    AegCore::put_value("a", "1");
+    AegCore::tag_key("a", "prod");
+    AegCore::set_read_only(true);
+    store
+}
+
+fn assert_unchanged() {
+    AegCore::set_read_only(false);
+    let entry = AegCore::get_metadata("a").unwrap();
+    assert_eq!(&*entry.value, "1");
+    assert_eq!(entry.tags, vec!["prod"]);
+    assert_eq!(AegCore::list_keys(), vec!["a"]);
+}
+
+#[test]
+fn key_metadata_changes_are_refused() {
+    let _store = read_only_store();
+    assert_eq!(AegCore::tag_key("a", "staging"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::untag_key("a", "prod"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_env_name("a", Some("APP_A")), READ_ONLY_ERROR);
+    assert_eq!(
+        AegCore::set_env_name_qualified("default::a", Some("APP_A")),
+        READ_ONLY_ERROR
+    );
+    assert_unchanged();
+    assert_eq!(
+        AegMemoryEngine::read_active(|engine| engine.env_name_for("a")),
+        "A"
+    );
+}
+
+#[test]
+fn transactions_and_captures_are_refused() {
+    let _store = read_only_store();
+    let mut tx = AegCore::begin_transaction();
+    tx.put("b", "2");
+    tx.delete("a");
+    assert_eq!(tx.commit(), READ_ONLY_ERROR);
+
+    // SAFETY: only this test reads or writes this variable
+    unsafe { std::env::set_var("AEGISR_READ_ONLY_CAPTURE", "x") };
+    assert_eq!(
+        AegCore::capture_env(Some("AEGISR_READ_ONLY_")),
+        READ_ONLY_ERROR
+    );
+    assert!(AegSnippet::add("note", None).is_err());
+    assert_unchanged();
+}
+
+#[test]
+fn collection_settings_are_refused() {
+    let _store = read_only_store();
+    assert_eq!(AegCore::set_autosave("default", false), READ_ONLY_ERROR);
+    assert_eq!(
+        AegCore::set_warm_capacity("default", Some(1)),
+        READ_ONLY_ERROR
+    );
+    assert_eq!(AegCore::set_indexed("default", true), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_sealed_values("default", true), READ_ONLY_ERROR);
+    assert_eq!(
+        AegCore::set_key_convention("default", Some(KeyConvention::Snake)),
+        READ_ONLY_ERROR
+    );
+    assert_unchanged();
+    let core = AegCore::load();
+    assert!(core.is_autosave_enabled("default"));
+    assert!(!core.collection_meta.contains_key("default"));
+}
+
+#[test]
+fn store_settings_are_refused() {
+    let _store = read_only_store();
+    let config = AegFileSystem::read_store_config();
+    assert_eq!(
+        AegCore::set_cipher(Cipher::ChaCha20Poly1305),
+        READ_ONLY_ERROR
+    );
+    assert_eq!(AegCore::set_lint_level(LintLevel::Warn), READ_ONLY_ERROR);
+    assert_eq!(
+        AegCore::set_clock_skew_policy(ClockSkewPolicy::FailClosed),
+        READ_ONLY_ERROR
+    );
+    assert_eq!(AegCore::set_duplicate_warning(false), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_compression(Some(16)), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_backup_generations(5), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_clear_snapshot(false), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_audit(true), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_machine_binding(true), READ_ONLY_ERROR);
+    assert_eq!(AegCore::set_hsm(None), READ_ONLY_ERROR);
+    assert_eq!(
+        AegCore::change_passphrase(None, Some("new passphrase"), |_| {}),
+        READ_ONLY_ERROR
+    );
+    assert_unchanged();
+    assert_eq!(AegFileSystem::read_store_config(), config);
+}
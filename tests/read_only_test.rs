@@ -0,0 +1,87 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegTestHarness, AegisrCommand, AegisrResponse,
+    PersistencePolicy, READ_ONLY_ERROR,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Every file under `dir` with its contents.
+fn files(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut out = BTreeMap::new();
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            out.extend(files(&path));
+        } else {
+            out.insert(path.display().to_string(), fs::read(&path).unwrap());
+        }
+    }
+    out
+}
+
+#[test]
+fn read_only_store_refuses_changes_and_writes_nothing() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::db", "postgres");
+    AegCore::put_value("a", "1");
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    let before = files(dir);
+
+    let core = AegCore::load_read_only();
+    assert!(AegCore::is_read_only());
+    assert_eq!(core.collections, vec!["default", "prod"]);
+
+    // reads still work
+    assert_eq!(AegCore::get_value("a").as_deref(), Some("1"));
+    let get = AegDispatch::execute(AegisrCommand::Get {
+        key: "prod::db".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert!(matches!(get, AegisrResponse::Ok { .. }), "{:?}", get);
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::Status),
+        AegisrResponse::Ok { .. }
+    ));
+
+    // changes are refused, through the library and the commands
+    assert_eq!(AegCore::put_value("a", "2"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::delete_value("a"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::create_collection("new"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::delete_collection("prod"), READ_ONLY_ERROR);
+    assert_eq!(AegCore::clear_values(), READ_ONLY_ERROR);
+    assert!(AegCore::load().set_active_collection("prod").is_err());
+    match AegDispatch::execute(AegisrCommand::Put {
+        key: "b".into(),
+        value: "2".into(),
+        env_name: None,
+        json: false,
+    }) {
+        AegisrResponse::Error { message } => assert_eq!(message, READ_ONLY_ERROR),
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::Compact),
+        AegisrResponse::Error { .. }
+    ));
+    assert_eq!(AegCore::get_value("a").as_deref(), Some("1"));
+
+    // no saver starts, and nothing that does slip into memory is saved
+    assert!(AegCore::set_persistence_policy(PersistencePolicy::Interval(1)).is_none());
+    assert!(!AegCore::start_background_saver(1).is_running());
+    AegMemoryEngine::with_active(|engine| engine.insert("sneaky", "x"));
+    AegCore::flush_now();
+    assert_eq!(files(dir), before);
+
+    AegCore::set_read_only(false);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("sneaky"), None);
+    assert!(AegCore::put_value("a", "2").starts_with('✓'));
+}
@@ -0,0 +1,73 @@
+use aegisrlib::{AegCore, AegCrypto, AegMemoryEngine, AegTestHarness, Verbosity};
+use std::fs;
+
+fn corrupt(path: &std::path::Path) {
+    let mut bytes = fs::read(path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn corrupt_collections_are_quarantined_and_restored() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::put_value("token", "from-snapshot");
+    AegCore::flush_now();
+    assert!(AegCore::snapshot("nightly").starts_with('✓'));
+    AegCore::put_value("token", "lost");
+    AegCore::create_collection("fresh");
+    AegCore::put_qualified("fresh::key", "v");
+    AegCore::flush_now();
+
+    let file = dir.join("collection_default.aekv");
+    corrupt(&file);
+    corrupt(&dir.join("collection_fresh.aekv"));
+    AegMemoryEngine::reset_cache();
+
+    // restored from the newest snapshot with a readable copy
+    assert_eq!(
+        AegCore::get_value("token").as_deref(),
+        Some("from-snapshot")
+    );
+    assert!(dir.join("collection_default.aekv.corrupt").exists());
+    // no snapshot has this one; it starts empty instead of failing
+    assert_eq!(AegCore::get_qualified("fresh::key").unwrap(), None);
+
+    let reports = AegCore::take_recovery_reports();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].collection, "default");
+    assert_eq!(reports[0].restored_from.as_deref(), Some("nightly"));
+    assert_eq!(
+        reports[0].quarantined,
+        dir.join("collection_default.aekv.corrupt")
+    );
+    assert_eq!(reports[1].collection, "fresh");
+    assert_eq!(reports[1].restored_from, None);
+    assert!(AegCore::take_recovery_reports().is_empty());
+
+    // a second failure keeps the first quarantined copy
+    corrupt(&file);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_value("token").as_deref(),
+        Some("from-snapshot")
+    );
+    assert!(dir.join("collection_default.aekv.corrupt.2").exists());
+    AegCore::take_recovery_reports();
+
+    // a wrong store key is not corruption: nothing is moved aside
+    let key_path = dir.join("AUTHORIZATION_KEY");
+    let real_key = fs::read(&key_path).unwrap();
+    fs::write(
+        &key_path,
+        AegCrypto::create_authorization_key(Verbosity::Quiet),
+    )
+    .unwrap();
+    AegMemoryEngine::reset_cache();
+    let loaded = std::panic::catch_unwind(|| AegCore::get_value("token"));
+    assert!(loaded.is_err());
+    assert!(file.exists());
+    assert!(AegCore::take_recovery_reports().is_empty());
+    fs::write(&key_path, real_key).unwrap();
+}
@@ -0,0 +1,79 @@
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn renamed_collections_keep_their_data_through_compact() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir().to_path_buf();
+    AegCore::create_collection("a");
+    AegCore::put_qualified("a::saved", "on disk");
+    AegCore::flush_now();
+    AegCore::put_qualified("a::pending", "in memory");
+    AegCore::set_backup_generations(2);
+    AegCore::put_qualified("a::saved", "on disk again");
+    AegCore::flush_now();
+    assert!(dir.join("backups/collection_a.aekv.1").exists());
+
+    let msg = AegCore::rename_collection("a", "b");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(dir.join("collection_b.aekv").exists());
+    assert!(!dir.join("collection_a.aekv").exists());
+    assert!(!dir.join("backups/collection_a.aekv.1").exists());
+
+    // the data reads back from the new files alone
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("b::saved").unwrap().as_deref(),
+        Some("on disk again")
+    );
+    assert_eq!(
+        AegCore::get_qualified("b::pending").unwrap().as_deref(),
+        Some("in memory")
+    );
+    let report = AegFileSystem::compact().unwrap();
+    assert!(report.quarantined_files.is_empty(), "{:?}", report);
+    AegMemoryEngine::reset_cache();
+    assert_eq!(
+        AegCore::get_qualified("b::saved").unwrap().as_deref(),
+        Some("on disk again")
+    );
+
+    // the old name starts out empty again
+    AegCore::create_collection("a");
+    assert!(AegCore::get_qualified("a::saved").unwrap().is_none());
+    assert!(
+        AegCore::rename_collection("missing", "c").starts_with('✗'),
+        "renaming a missing collection"
+    );
+    assert!(AegCore::rename_collection("a", "b").starts_with('✗'));
+}
+
+#[test]
+fn compact_moves_unknown_files_aside_instead_of_deleting_them() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir().to_path_buf();
+    AegCore::create_collection("lost");
+    AegCore::put_qualified("lost::token", "t");
+    AegCore::flush_now();
+    let content = std::fs::read(dir.join("collection_lost.aekv")).unwrap();
+    AegCore::delete_collection("lost");
+
+    let report = AegFileSystem::compact().unwrap();
+    assert_eq!(report.quarantined_files, vec!["collection_lost.aekv"]);
+    assert!(
+        report.summary().contains("moved aside"),
+        "{}",
+        report.summary()
+    );
+    assert_eq!(
+        std::fs::read(dir.join("collection_lost.aekv.orphaned")).unwrap(),
+        content
+    );
+    // already moved aside, so a second compact leaves it alone
+    assert!(
+        AegFileSystem::compact()
+            .unwrap()
+            .quarantined_files
+            .is_empty()
+    );
+    assert!(dir.join("collection_lost.aekv.orphaned").exists());
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegMemoryEngine, AegRepl, AegTestHarness, AegisrCommand, ReplLine};
+use std::io::Cursor;
+
+#[test]
+fn shell_runs_commands_completes_and_keeps_history_without_values() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("staging");
+
+    let input = "\
+put db/password hunter2 with spaces
+put db/user admin
+get db/password
+ls
+get db/\t
+us\t
+use st\t
+use staging
+ls
+bogus
+
+exit
+get never-run
+";
+    let mut output = Vec::new();
+    let mut repl = AegRepl::new();
+    repl.run(Cursor::new(input), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("hunter2 with spaces"));
+    assert!(output.contains("db/password\ndb/user"));
+    assert!(output.contains("db/password  db/user"));
+    assert!(output.contains("aegisr:staging> "));
+    assert!(output.contains("✗ unknown command 'bogus'"));
+    assert!(!output.contains("never-run"));
+    // the session's puts were saved on exit
+    AegMemoryEngine::reset_cache();
+    let mut core = AegCore::load();
+    core.set_active_collection("default").unwrap();
+    assert_eq!(AegCore::get_value("db/user").as_deref(), Some("admin"));
+
+    // completion lists commands, then collections or keys
+    assert_eq!(AegRepl::complete("us"), ["use"]);
+    assert_eq!(AegRepl::complete("use st"), ["staging"]);
+    assert_eq!(AegRepl::complete("del db/p"), ["db/password"]);
+    assert!(AegRepl::complete("get db/password ").is_empty());
+    assert_eq!(
+        AegRepl::parse("put key  spaced value").unwrap(),
+        ReplLine::Command(AegisrCommand::Put {
+            key: "key".into(),
+            value: "spaced value".into(),
+            env_name: None,
+            json: false,
+        })
+    );
+    assert!(AegRepl::parse("get").is_err());
+
+    // history survives the session but never holds a value
+    let history = AegRepl::new().history().to_vec();
+    assert_eq!(history[0], "put db/password …");
+    assert!(history.contains(&"use staging".to_string()));
+    assert!(
+        !history
+            .iter()
+            .any(|l| l.contains("hunter2") || l.contains('\t'))
+    );
+    let text = std::fs::read_to_string(dir.join("shell_history")).unwrap();
+    assert!(!text.contains("hunter2"));
+}
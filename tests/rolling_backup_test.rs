@@ -0,0 +1,71 @@
+use aegisrlib::{AegBackups, AegCore, AegFileSystem, AegTestHarness};
+use std::fs;
+
+#[test]
+fn saves_keep_rolling_backups_that_can_be_restored() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::put_value("token", "v0");
+    AegCore::flush_now();
+    // off by default
+    assert!(AegBackups::generations(dir, "default").is_empty());
+
+    assert!(AegCore::set_backup_generations(2).starts_with('✓'));
+    for value in ["v1", "v2", "v3"] {
+        AegCore::put_value("token", value);
+        AegCore::flush_now();
+    }
+    assert_eq!(AegBackups::generations(dir, "default"), vec![1, 2]);
+    assert!(dir.join("backups/collection_default.aekv.1").exists());
+
+    // a bad save rolled back: generation 2 holds v1
+    AegCore::put_value("token", "unsaved");
+    let msg = AegCore::restore_backup("default", 2);
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("v1"));
+    // the replaced file became generation 1, so the restore can be undone
+    assert!(AegCore::restore_backup("default", 1).starts_with('✓'));
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("v3"));
+
+    assert_eq!(
+        AegCore::restore_backup("default", 5),
+        "✗ Collection 'default' has no backup generation 5 (1 to 2 kept)"
+    );
+    assert!(AegCore::restore_backup("missing", 1).starts_with('✗'));
+    fs::write(AegBackups::backup_file(dir, "default", 2), "garbage").unwrap();
+    assert!(AegCore::restore_backup("default", 2).contains("unreadable"));
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("v3"));
+
+    // lowering the setting prunes on the next save
+    AegCore::set_backup_generations(1);
+    AegCore::put_value("token", "v4");
+    AegCore::flush_now();
+    assert_eq!(AegBackups::generations(dir, "default"), vec![1]);
+
+    // backups of deleted collections are moved aside by compact
+    AegCore::create_collection("gone");
+    AegCore::put_qualified("gone::k", "a");
+    AegCore::flush_now();
+    AegCore::put_qualified("gone::k", "b");
+    AegCore::flush_now();
+    assert_eq!(AegBackups::generations(dir, "gone"), vec![1]);
+    AegCore::delete_collection("gone");
+    let report = AegFileSystem::compact().unwrap();
+    assert!(
+        report
+            .quarantined_files
+            .contains(&"backups/collection_gone.aekv.1".to_string()),
+        "{:?}",
+        report
+    );
+    assert!(AegBackups::generations(dir, "gone").is_empty());
+    assert!(dir.join("backups/collection_gone.aekv.1.orphaned").exists());
+
+    // backups are re-encrypted with the rest of the store
+    let msg = AegCore::change_passphrase(None, Some("pass"), |_| {});
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegCore::restore_backup("default", 1).starts_with('✓'));
+    assert_eq!(AegCore::get_value("token").as_deref(), Some("v3"));
+    AegCore::change_passphrase(Some("pass"), None, |_| {});
+    AegFileSystem::set_passphrase(None);
+}
@@ -0,0 +1,213 @@
+#![cfg(feature = "s3")]
+
+use aegisrlib::{AegCore, AegFileSystem, AegMemoryEngine, S3Config, S3Storage, Verbosity};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+/// Just enough of the S3 API for `S3Storage`, one request per connection.
+fn fake_s3() -> (String, Objects) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let objects: Objects = Arc::default();
+    let served = Arc::clone(&objects);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            serve(stream.unwrap(), &served);
+        }
+    });
+    (endpoint, objects)
+}
+
+fn serve(stream: TcpStream, objects: &Objects) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut headers = BTreeMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':').unwrap();
+        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+    }
+    let mut body = vec![
+        0u8;
+        headers
+            .get("content-length")
+            .map_or(0, |l| l.parse().unwrap())
+    ];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap().to_string();
+    let target = parts.next().unwrap().to_string();
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let key = decode(path.trim_start_matches("/bucket").trim_start_matches('/'));
+    let query: BTreeMap<String, String> = query
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (decode(k), decode(v)))
+        .collect();
+
+    let signed = headers
+        .get("authorization")
+        .is_some_and(|a| a.starts_with("AWS4-HMAC-SHA256 Credential=test-key/"));
+    let mut objects = objects.lock().unwrap();
+    let (status, reply) = if !signed {
+        (403, b"<Error><Code>AccessDenied</Code></Error>".to_vec())
+    } else if method == "GET" && query.contains_key("list-type") {
+        let prefix = query.get("prefix").cloned().unwrap_or_default();
+        let delimited = query.contains_key("delimiter");
+        let mut xml = String::from("<ListBucketResult><IsTruncated>false</IsTruncated>");
+        for name in objects.keys().filter(|k| k.starts_with(&prefix)) {
+            if delimited && name[prefix.len()..].contains('/') {
+                continue;
+            }
+            xml.push_str(&format!("<Contents><Key>{}</Key></Contents>", name));
+        }
+        xml.push_str("</ListBucketResult>");
+        (200, xml.into_bytes())
+    } else {
+        match (method.as_str(), objects.get(&key)) {
+            ("GET", Some(object)) => match headers.get("range") {
+                Some(range) => {
+                    let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+                    let (start, end): (usize, usize) =
+                        (start.parse().unwrap(), end.parse().unwrap());
+                    (206, object[start..=end].to_vec())
+                }
+                None => (200, object.clone()),
+            },
+            ("HEAD", Some(_)) => (200, Vec::new()),
+            ("GET" | "HEAD", None) => (404, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()),
+            ("PUT", Some(_)) if headers.get("if-none-match").is_some_and(|v| v == "*") => (
+                412,
+                b"<Error><Code>PreconditionFailed</Code></Error>".to_vec(),
+            ),
+            ("PUT", _) => {
+                objects.insert(key, body);
+                (200, Vec::new())
+            }
+            ("DELETE", _) => {
+                objects.remove(&key);
+                (204, Vec::new())
+            }
+            _ => (400, Vec::new()),
+        }
+    };
+    let mut stream = stream;
+    let length = if method == "HEAD" { 0 } else { reply.len() };
+    write!(
+        stream,
+        "HTTP/1.1 {} S3\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, length
+    )
+    .unwrap();
+    if method != "HEAD" {
+        stream.write_all(&reply).unwrap();
+    }
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            out.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn store_runs_on_s3_storage() {
+    let (endpoint, objects) = fake_s3();
+    let dir = std::env::temp_dir().join(format!("aegisr_s3_storage_{}", std::process::id()));
+    let config = S3Config {
+        endpoint,
+        bucket: "bucket".to_string(),
+        region: "us-east-1".to_string(),
+        prefix: "team/store".to_string(),
+        access_key_id: "test-key".to_string(),
+        secret_access_key: "test-secret".to_string(),
+        session_token: None,
+        virtual_host: false,
+    };
+    AegFileSystem::set_storage(Arc::new(
+        S3Storage::new(config.clone(), dir.clone()).unwrap(),
+    ));
+    AegCore::set_store_dir(dir.clone());
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::create_collection("cold");
+    AegCore::set_warm_capacity("cold", Some(1));
+
+    AegCore::put_value("db/password", "hunter2");
+    AegMemoryEngine::with_engine("cold", |engine| {
+        for i in 0..5 {
+            engine.insert(format!("k{}", i), format!("v{}", i));
+        }
+    });
+    AegCore::flush_now();
+    assert!(!dir.exists());
+
+    let names: Vec<String> = objects.lock().unwrap().keys().cloned().collect();
+    for expected in [
+        "team/store/AUTHORIZATION_KEY",
+        "team/store/collection.lock",
+        "team/store/collection_cold.aekv",
+        "team/store/collection_cold.cold",
+        "team/store/collection_default.aekv",
+    ] {
+        assert!(names.iter().any(|n| n == expected), "{:?}", names);
+    }
+    // released locks are deleted
+    assert!(
+        !names.iter().any(|n| n.ends_with("aegisr.lock")),
+        "{:?}",
+        names
+    );
+    // nothing readable was uploaded
+    for (name, object) in objects.lock().unwrap().iter() {
+        assert!(
+            !String::from_utf8_lossy(object).contains("hunter2"),
+            "{}",
+            name
+        );
+    }
+
+    // another process reading the same bucket sees the same store
+    AegMemoryEngine::reset_cache();
+    AegFileSystem::set_storage(Arc::new(S3Storage::new(config, dir.clone()).unwrap()));
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter2")
+    );
+    assert_eq!(
+        AegMemoryEngine::fetch_shared("cold", "k0").as_deref(),
+        Some("v0")
+    );
+    assert!(AegCore::load().collections.contains(&"cold".to_string()));
+
+    // paths outside the store root are refused
+    let storage = AegFileSystem::storage();
+    assert!(
+        storage
+            .read(&std::env::temp_dir().join("elsewhere"))
+            .is_err()
+    );
+
+    AegFileSystem::clear_base_dir();
+    AegMemoryEngine::reset_cache();
+}
@@ -0,0 +1,83 @@
+use aegisrlib::{
+    AegCore, AegCoreBuilder, AegMemoryEngine, AegTestHarness, MemoryStorage, StorageBackend,
+};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[test]
+fn saver_handle_flushes_stops_promptly_and_joins() {
+    let _store = AegTestHarness::temp_dir();
+
+    let saver = AegCore::start_background_saver(3600);
+    assert!(saver.is_running());
+    // a second start hands back the running saver
+    let same = AegCore::start_background_saver(5);
+    assert_eq!(AegMemoryEngine::background_saver_interval(), Some(3600));
+
+    AegCore::put_value("a", "1");
+    assert_eq!(same.flush_now(), 1);
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+
+    // a shorter interval applies without restarting
+    saver.set_interval(1);
+    assert_eq!(AegMemoryEngine::background_saver_interval(), Some(1));
+    AegCore::put_value("b", "2");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while AegMemoryEngine::cached_dirty("default") != Some(false) && Instant::now() < deadline {
+        sleep(Duration::from_millis(50));
+    }
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+
+    // stop wakes the thread instead of waiting out the interval, and join
+    // returns after the final save
+    saver.set_interval(3600);
+    AegCore::put_value("c", "3");
+    let started = Instant::now();
+    saver.shutdown();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(!same.is_running());
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    assert_eq!(AegMemoryEngine::background_saver_interval(), None);
+    same.join();
+    // with the thread gone, flush_now saves in place
+    AegCore::put_value("d", "4");
+    assert_eq!(same.flush_now(), 1);
+
+    // the global stop signals the running saver too
+    let saver = AegCore::start_background_saver(3600);
+    AegCore::put_value("e", "5");
+    AegCore::stop_background_saver();
+    saver.join();
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("e").as_deref(), Some("5"));
+}
+
+#[test]
+fn stopping_the_saver_waits_for_its_final_save() {
+    let storage = Arc::new(MemoryStorage::new());
+    let store = AegCoreBuilder::new()
+        .base_dir("/aegisr_saver_stop")
+        .storage(storage.clone())
+        .build()
+        .unwrap();
+    store.run(|| {
+        let saver = AegCore::start_background_saver(3600);
+        // enough to make the final save take a while
+        for i in 0..2000 {
+            AegCore::put_value(&format!("late{}", i), &"v".repeat(100));
+        }
+        AegCore::stop_background_saver();
+        assert!(!saver.is_running());
+        assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    });
+    let files = storage
+        .list(std::path::Path::new("/aegisr_saver_stop"))
+        .unwrap();
+    assert!(
+        files.iter().any(|f| f == "collection_default.aekv"),
+        "{:?}",
+        files
+    );
+}
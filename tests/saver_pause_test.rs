@@ -0,0 +1,33 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn paused_saver_saves_once_on_resume() {
+    let _store = AegTestHarness::temp_dir();
+
+    let outer = AegCore::pause_saver();
+    let inner = AegCore::pause_saver();
+    assert!(AegMemoryEngine::saver_paused());
+    for i in 0..100 {
+        AegCore::put_value(&format!("bulk{}", i), "v");
+    }
+    // what the background saver would do on its next tick
+    assert_eq!(AegMemoryEngine::save_autosave(), 0);
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+
+    // still paused by the outer guard
+    assert_eq!(AegCore::resume_saver(inner), 0);
+    assert!(AegMemoryEngine::saver_paused());
+    assert_eq!(AegCore::resume_saver(outer), 1);
+    assert!(!AegMemoryEngine::saver_paused());
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+
+    // dropping a guard resumes too
+    {
+        let _pause = AegCore::pause_saver();
+        AegCore::put_value("late", "v");
+        assert_eq!(AegMemoryEngine::save_autosave(), 0);
+    }
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(false));
+    AegMemoryEngine::reset_cache();
+    assert_eq!(AegCore::get_value("late").as_deref(), Some("v"));
+}
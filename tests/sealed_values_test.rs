@@ -0,0 +1,74 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+
+#[test]
+fn sealed_values_stay_encrypted_in_memory_and_are_saved_as_plaintext() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("vault");
+    AegMemoryEngine::with_engine("vault", |engine| {
+        engine.insert("db/password", "hunter2");
+        engine.insert("db/password", "correct horse");
+        engine.insert("api/token", "tok_123");
+    });
+    assert!(AegCore::set_sealed_values("missing", true).starts_with('✗'));
+    assert!(AegCore::set_sealed_values("vault", true).starts_with('✓'));
+
+    let plaintext_in_memory = |engine: &AegMemoryEngine| {
+        engine.store.values().any(|e| {
+            ["hunter2", "correct horse", "tok_123"]
+                .iter()
+                .any(|v| e.value.contains(v))
+        })
+    };
+    AegMemoryEngine::with_engine("vault", |engine| {
+        assert!(engine.is_sealed());
+        assert!(!plaintext_in_memory(engine));
+        assert_eq!(engine.get("db/password").as_deref(), Some("correct horse"));
+        assert_eq!(
+            engine
+                .get_secret("api/token")
+                .as_deref()
+                .map(String::as_str),
+            Some("tok_123")
+        );
+        let history: Vec<String> = engine
+            .history("db/password")
+            .into_iter()
+            .map(|v| v.value)
+            .collect();
+        assert_eq!(history, ["hunter2", "correct horse"]);
+        assert_eq!(
+            engine.search(|v| v.contains("horse"), true),
+            ["db/password"]
+        );
+        assert_eq!(engine.keys_with_value("tok_123"), ["api/token"]);
+        // new writes and restored versions are sealed too
+        engine.insert("new", "fresh value");
+        engine.restore_version("db/password", 1).unwrap();
+        assert!(!engine.store["new"].value.contains("fresh"));
+        assert_eq!(engine.get("db/password").as_deref(), Some("hunter2"));
+    });
+
+    // paged-out entries come back sealed
+    AegCore::set_warm_capacity("vault", Some(1));
+    AegMemoryEngine::with_engine("vault", |engine| {
+        assert_eq!(engine.fetch("api/token").as_deref(), Some("tok_123"));
+        assert_eq!(engine.fetch("new").as_deref(), Some("fresh value"));
+        assert!(!plaintext_in_memory(engine));
+    });
+    AegCore::set_warm_capacity("vault", None);
+
+    // the file holds the values, not ciphertext under the in-memory key
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    AegMemoryEngine::with_engine("vault", |engine| {
+        assert!(engine.is_sealed());
+        assert_eq!(engine.get("db/password").as_deref(), Some("hunter2"));
+        assert_eq!(engine.get("new").as_deref(), Some("fresh value"));
+    });
+
+    assert!(AegCore::set_sealed_values("vault", false).starts_with('✓'));
+    AegMemoryEngine::with_engine("vault", |engine| {
+        assert!(!engine.is_sealed());
+        assert_eq!(&*engine.store["api/token"].value, "tok_123");
+    });
+}
@@ -0,0 +1,53 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn search_matches_keys_and_values_across_tiers_and_collections() {
+    let _store = AegTestHarness::temp_dir();
+    let active = AegCore::load().active_collection;
+
+    AegCore::set_warm_capacity(&active, Some(2));
+    AegCore::put_value("db/password", "hunter2");
+    AegCore::put_value("db/user", "admin");
+    AegCore::put_value("api/token", "tok_live_1234");
+    AegCore::put_value("api/url", "https://example.com/db");
+    assert!(AegCore::tier_stats().cold_entries > 0);
+
+    let keys = |found: Vec<(String, String)>| -> Vec<String> {
+        found.into_iter().map(|(_, key)| key).collect()
+    };
+    assert_eq!(
+        keys(AegCore::search("db/", false, false, false).unwrap()),
+        ["db/password", "db/user"]
+    );
+    // values are only searched when asked, cold ones included
+    assert!(
+        AegCore::search("example", false, false, false)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        keys(AegCore::search("example", false, true, false).unwrap()),
+        ["api/url"]
+    );
+    assert_eq!(
+        keys(AegCore::search("db", false, true, false).unwrap()),
+        ["api/url", "db/password", "db/user"]
+    );
+    assert_eq!(
+        keys(AegCore::search(r"^tok_\w+_\d{4}$", true, true, false).unwrap()),
+        ["api/token"]
+    );
+    assert!(AegCore::search("(", true, false, false).is_err());
+
+    AegCore::create_collection("other");
+    AegCore::load().set_active_collection("other").unwrap();
+    AegCore::put_value("db/host", "localhost");
+    assert_eq!(
+        keys(AegCore::search("db/", false, false, false).unwrap()),
+        ["db/host"]
+    );
+    let everywhere = AegCore::search("db/", false, false, true).unwrap();
+    assert_eq!(everywhere.len(), 3);
+    assert!(everywhere.contains(&("other".to_string(), "db/host".to_string())));
+    assert!(everywhere.contains(&(active.clone(), "db/user".to_string())));
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegCrypto, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, SecretFormat,
+    SecretSpec,
+};
+use std::collections::HashSet;
+
+fn gen_command(save: Option<&str>) -> AegisrCommand {
+    AegisrCommand::Gen {
+        format: SecretFormat::Chars,
+        length: Some(32),
+        symbols: true,
+        no_lowercase: false,
+        no_uppercase: false,
+        no_digits: false,
+        separator: None,
+        save: save.map(str::to_string),
+    }
+}
+
+#[test]
+fn secrets_are_generated_to_spec_and_can_be_saved() {
+    let mut spec = SecretSpec {
+        length: 40,
+        symbols: true,
+        ..SecretSpec::default()
+    };
+    let secret = AegCrypto::generate_secret(&spec).unwrap();
+    assert_eq!(secret.chars().count(), 40);
+    assert!(secret.chars().any(|c| c.is_ascii_lowercase()));
+    assert!(secret.chars().any(|c| c.is_ascii_uppercase()));
+    assert!(secret.chars().any(|c| c.is_ascii_digit()));
+    assert!(secret.chars().any(|c| c.is_ascii_punctuation()));
+    assert!(!format!("{:?}", secret).contains(secret.expose()));
+
+    spec.uppercase = false;
+    spec.symbols = false;
+    spec.digits = false;
+    spec.length = 4;
+    for _ in 0..20 {
+        let secret = AegCrypto::generate_secret(&spec).unwrap();
+        assert!(secret.chars().all(|c| c.is_ascii_lowercase()));
+    }
+    spec.lowercase = false;
+    assert!(AegCrypto::generate_secret(&spec).is_err());
+    let short = SecretSpec {
+        length: 2,
+        ..SecretSpec::default()
+    };
+    assert!(AegCrypto::generate_secret(&short).is_err());
+
+    let mut words = SecretSpec::new(SecretFormat::Passphrase);
+    words.separator = " ".into();
+    let phrase = AegCrypto::generate_secret(&words).unwrap();
+    assert_eq!(phrase.split(' ').count(), 6);
+    assert!(
+        phrase
+            .split(' ')
+            .all(|w| w.chars().all(|c| c.is_ascii_lowercase()))
+    );
+    assert_eq!(words.entropy_bits(), 60.0);
+
+    let hex = AegCrypto::generate_secret(&SecretSpec::new(SecretFormat::Hex)).unwrap();
+    assert_eq!(hex.len(), 64);
+    assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+    let base64 = AegCrypto::generate_secret(&SecretSpec::new(SecretFormat::Base64)).unwrap();
+    assert_eq!(base64.len(), 44);
+    assert_eq!(
+        "diceware".parse::<SecretFormat>(),
+        Ok(SecretFormat::Passphrase)
+    );
+
+    // no repeats across draws
+    let drawn: HashSet<String> = (0..100)
+        .map(|_| {
+            AegCrypto::generate_secret(&SecretSpec::default())
+                .unwrap()
+                .to_plain_string()
+        })
+        .collect();
+    assert_eq!(drawn.len(), 100);
+
+    let _store = AegTestHarness::temp_dir();
+
+    match AegDispatch::execute(gen_command(None)) {
+        AegisrResponse::Ok { message, .. } => assert_eq!(message.len(), 32),
+        other => panic!("{:?}", other),
+    }
+    assert!(!gen_command(None).mutates_store());
+    assert!(gen_command(Some("db_password")).mutates_store());
+
+    // saving stores the secret without printing it
+    let saved = AegDispatch::execute(gen_command(Some("db_password")));
+    let AegisrResponse::Ok { message, .. } = &saved else {
+        panic!("{:?}", saved);
+    };
+    let stored = AegCore::get_value("db_password").unwrap();
+    assert_eq!(stored.len(), 32);
+    assert!(!message.contains(stored.expose()), "{}", message);
+}
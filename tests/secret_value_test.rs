@@ -0,0 +1,23 @@
+use aegisrlib::{AegCore, AegTestHarness, SecretValue};
+
+#[test]
+fn get_value_returns_a_redacted_secret() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("api_token", "s3cr3t-value");
+
+    let value: SecretValue = AegCore::get_value("api_token").unwrap();
+    assert_eq!(value, "s3cr3t-value");
+    assert_eq!(value.expose(), "s3cr3t-value");
+    assert_eq!(value.len(), 12);
+    assert_eq!(value.to_string(), "s3cr3t-value");
+    let debug = format!("{:?}", value);
+    assert!(!debug.contains("s3cr3t"), "{}", debug);
+    assert_eq!(serde_json::to_string(&value).unwrap(), "\"s3cr3t-value\"");
+    assert_eq!(AegCore::get_value("missing"), None);
+
+    // the other read paths still hand out plain strings
+    assert_eq!(
+        AegCore::get_qualified("default::api_token").unwrap(),
+        Some("s3cr3t-value".to_string())
+    );
+}
@@ -0,0 +1,85 @@
+use aegisrlib::{AegCore, AegTestHarness, SharePrivateKey, SharePublicKey, SharedBundle};
+use base64::{Engine as _, engine::general_purpose};
+use std::fs;
+
+/// RFC 7748, section 6.1: Alice's key pair.
+const ALICE_SECRET: &str = "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a";
+const ALICE_PUBLIC: &str = "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a";
+
+fn key_text(prefix: &str, hex: &str) -> String {
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    format!(
+        "{}{}",
+        prefix,
+        general_purpose::STANDARD_NO_PAD.encode(bytes)
+    )
+}
+
+#[test]
+fn collections_shared_with_a_public_key_open_with_its_private_key() {
+    let alice: SharePrivateKey = key_text("aegsec:", ALICE_SECRET).parse().unwrap();
+    assert_eq!(
+        alice.public_key().to_string(),
+        key_text("aegpub:", ALICE_PUBLIC)
+    );
+    assert!(!format!("{:?}", alice).contains(&alice.to_text()[7..]));
+    let parsed: SharePrivateKey = alice.to_text().parse().unwrap();
+    assert_eq!(parsed.public_key(), alice.public_key());
+    assert!("aegpub:AAAA".parse::<SharePublicKey>().is_err());
+    assert!(
+        key_text("aegsec:", ALICE_SECRET)
+            .parse::<SharePublicKey>()
+            .is_err()
+    );
+
+    let store = AegTestHarness::temp_dir();
+
+    let dir = store.dir();
+    AegCore::create_collection("team");
+    AegCore::put_qualified("team::db", "postgres://team");
+    AegCore::put_qualified("team::token", "t0k3n");
+
+    let bob = SharePrivateKey::generate();
+    let bob_file = dir.join("bob.key");
+    bob.write(&bob_file).unwrap();
+    assert!(bob.write(&bob_file).is_err());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&bob_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let bob_public = SharePublicKey::load(&bob.public_key().to_string()).unwrap();
+
+    let bundle = AegCore::export_shared("team", &bob_public).unwrap();
+    assert!(!bundle.to_json().contains("postgres"));
+    assert!(AegCore::export_shared("missing", &bob_public).is_err());
+    let bundle_file = dir.join("team.shared");
+    bundle.write(&bundle_file).unwrap();
+
+    // only bob's key opens it
+    assert!(bundle.open(&alice).unwrap_err().contains("another key"));
+    let mut forged = bundle.clone();
+    forged.recipient = alice.public_key().to_string();
+    assert!(forged.open(&alice).is_err());
+    assert!(
+        AegCore::import_collection(&bundle_file, "password").contains("shared with a public key")
+    );
+
+    AegCore::delete_collection("team");
+    let bob = SharePrivateKey::load(bob_file.to_str().unwrap()).unwrap();
+    let bundle = SharedBundle::read(&bundle_file).unwrap();
+    let imported = AegCore::import_shared(&bundle, &bob);
+    assert!(imported.starts_with('✓'), "{}", imported);
+    assert_eq!(
+        AegCore::get_qualified("team::db").unwrap().as_deref(),
+        Some("postgres://team")
+    );
+    assert_eq!(
+        AegCore::get_qualified("team::token").unwrap().as_deref(),
+        Some("t0k3n")
+    );
+}
@@ -0,0 +1,49 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn every_caller_gets_the_same_handle_of_a_collection() {
+    let _store = AegTestHarness::memory();
+    AegCore::create_collection("other");
+    let first = AegMemoryEngine::shared("default");
+    let second = AegMemoryEngine::shared("default");
+    assert!(Arc::ptr_eq(&first, &second));
+    assert!(!Arc::ptr_eq(&first, &AegMemoryEngine::shared("other")));
+
+    // a write through one handle is seen through the other and the static API
+    first.write().unwrap().insert("through_handle", "v");
+    assert_eq!(
+        second.read().unwrap().get("through_handle").as_deref(),
+        Some("v")
+    );
+    assert_eq!(
+        AegCore::get_value("through_handle").unwrap().to_string(),
+        "v"
+    );
+    assert_eq!(AegMemoryEngine::cached_dirty("default"), Some(true));
+
+    // readers of one collection do not wait for each other
+    let reading = first.read().unwrap();
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let value = AegMemoryEngine::fetch_shared("default", "through_handle");
+        done_tx.send(value.map(|v| v.to_string())).unwrap();
+    });
+    assert_eq!(
+        done_rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+        Some("v".to_string())
+    );
+    drop(reading);
+
+    // once unloaded, the collection is read again into a new handle
+    AegCore::flush_now();
+    AegMemoryEngine::reset_cache();
+    let reloaded = AegMemoryEngine::shared("default");
+    assert!(!Arc::ptr_eq(&first, &reloaded));
+    assert_eq!(
+        reloaded.read().unwrap().get("through_handle").as_deref(),
+        Some("v")
+    );
+}
@@ -0,0 +1,38 @@
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness};
+use std::sync::Arc;
+
+#[test]
+fn reads_share_keys_and_values_with_the_store() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("db/password", "hunter2");
+
+    AegMemoryEngine::read_active(|engine| {
+        let (key, entry) = engine.store.get_key_value("db/password").unwrap();
+        let value = engine.get_shared("db/password").unwrap();
+        assert!(Arc::ptr_eq(&value, &entry.value));
+
+        let listed = engine.list();
+        let (listed_key, listed_value) =
+            listed.iter().find(|(k, _)| &**k == "db/password").unwrap();
+        assert!(Arc::ptr_eq(listed_key, key));
+        assert!(Arc::ptr_eq(listed_value, &entry.value));
+        assert!(engine.keys().iter().any(|k| Arc::ptr_eq(k, key)));
+        assert!(engine.contains("db/password"));
+        assert!(!engine.contains("db/missing"));
+    });
+
+    // overwriting keeps the interned key
+    let before = AegMemoryEngine::read_active(|engine| {
+        Arc::clone(engine.store.get_key_value("db/password").unwrap().0)
+    });
+    AegCore::put_value("db/password", "hunter3");
+    AegMemoryEngine::read_active(|engine| {
+        let (key, entry) = engine.store.get_key_value("db/password").unwrap();
+        assert!(Arc::ptr_eq(key, &before));
+        assert_eq!(&*entry.value, "hunter3");
+    });
+    assert_eq!(
+        AegCore::get_value("db/password").as_deref(),
+        Some("hunter3")
+    );
+}
@@ -0,0 +1,31 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn store_can_be_restored_from_a_snapshot() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+
+    AegCore::put_value("db_url", "postgres://before");
+    let msg = AegCore::snapshot("before-migration");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert!(AegCore::snapshot("before-migration").starts_with('✗'));
+    assert!(AegCore::snapshot("../escape").starts_with('✗'));
+
+    AegCore::put_value("db_url", "postgres://after");
+    AegCore::put_value("new_key", "added later");
+    AegCore::create_collection("later");
+    AegCore::flush_now();
+
+    let snapshots = AegCore::list_snapshots();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].label, "before-migration");
+
+    let msg = AegCore::restore_snapshot("before-migration");
+    assert!(msg.starts_with('✓'), "{}", msg);
+    assert_eq!(AegCore::get_value("db_url").unwrap(), "postgres://before");
+    assert!(AegCore::get_value("new_key").is_none());
+    assert!(!AegCore::load().collections.contains(&"later".to_string()));
+    assert!(!dir.join("collection_later.aekv").exists());
+    assert!(AegCore::verify_store().passed());
+    assert!(AegCore::restore_snapshot("missing").starts_with('✗'));
+}
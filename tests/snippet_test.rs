@@ -0,0 +1,78 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegMemoryEngine, AegSnippet, AegTestHarness, AegisrCommand,
+    AegisrResponse, SNIPPET_COLLECTION,
+};
+use std::time::Duration;
+
+#[test]
+fn snippets_get_generated_ids_and_expire() {
+    let _store = AegTestHarness::temp_dir();
+    assert!(AegSnippet::list().is_empty());
+    assert_eq!(AegSnippet::get("missing").unwrap(), None);
+
+    let wifi = AegSnippet::add("correct horse battery staple", None).unwrap();
+    assert_eq!(wifi.id.len(), 8);
+    assert!(
+        wifi.id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    );
+    assert!(
+        AegCore::load()
+            .collections
+            .contains(&SNIPPET_COLLECTION.to_string())
+    );
+    let codes = AegSnippet::add("1234-5678\n9012-3456", Some(Duration::from_secs(3600))).unwrap();
+    assert_ne!(wifi.id, codes.id);
+    assert_eq!(
+        AegSnippet::get(&wifi.id.to_uppercase())
+            .unwrap()
+            .unwrap()
+            .text,
+        "correct horse battery staple"
+    );
+    // listing never shows the text
+    let listed = AegSnippet::list();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|s| s.text.is_empty()));
+
+    // an expired snippet is gone the next time it is looked at
+    AegMemoryEngine::with_engine(SNIPPET_COLLECTION, |engine| {
+        let mut stale: serde_json::Value =
+            serde_json::from_str(&engine.get(&codes.id).unwrap()).unwrap();
+        stale["expires_at"] = 1.into();
+        engine.insert(codes.id.clone(), stale.to_string());
+    });
+    assert_eq!(AegSnippet::get(&codes.id).unwrap(), None);
+    assert_eq!(AegSnippet::list().len(), 1);
+
+    assert_eq!(
+        AegSnippet::parse_ttl("30m").unwrap(),
+        Duration::from_secs(1800)
+    );
+    assert!(AegSnippet::parse_ttl("0d").is_err());
+    assert!(AegSnippet::parse_ttl("soon").is_err());
+    assert!(AegSnippet::add("", None).is_err());
+
+    let response = AegDispatch::execute(AegisrCommand::SnippetAdd {
+        text: "otp seed".into(),
+        expires: Some("1d".into()),
+    });
+    let AegisrResponse::Ok { message, data } = response else {
+        panic!("snippet add failed");
+    };
+    assert!(message.starts_with("✓ Snippet stored as "));
+    let id = data.unwrap()["id"].as_str().unwrap().to_string();
+    let response = AegDispatch::execute(AegisrCommand::SnippetGet { id: id.clone() });
+    assert!(matches!(response, AegisrResponse::Ok { ref message, .. } if message == "otp seed"));
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::SnippetDelete { id: id.clone() }),
+        AegisrResponse::Ok { .. }
+    ));
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::SnippetGet { id }),
+        AegisrResponse::Error { .. }
+    ));
+}
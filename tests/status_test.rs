@@ -0,0 +1,57 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AEKV_FORMAT_VERSION, AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse,
+    PersistencePolicy,
+};
+
+#[test]
+fn status_reports_saver_formats_and_unsaved_changes() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::a", "1");
+    AegCore::flush_now();
+
+    let status = AegCore::status();
+    assert_eq!(status.active_collection, "default");
+    assert_eq!(status.collections, vec!["default", "prod"]);
+    assert_eq!(status.saver.policy, PersistencePolicy::Manual);
+    assert!(!status.saver.running);
+    assert!(status.saver.last_flush.is_some());
+    assert_eq!(status.format_version, AEKV_FORMAT_VERSION);
+    assert_eq!(status.file_format_versions, vec![AEKV_FORMAT_VERSION]);
+    assert!(status.pending.is_empty());
+
+    AegCore::put_qualified("prod::a", "2");
+    AegCore::put_qualified("prod::b", "3");
+    let status = AegCore::status();
+    assert_eq!(status.pending.len(), 1);
+    assert_eq!(status.pending[0].collection, "prod");
+    assert_eq!(status.pending[0].added, vec!["b"]);
+    assert_eq!(status.pending[0].modified, vec!["a"]);
+
+    match AegDispatch::execute(AegisrCommand::Status) {
+        AegisrResponse::Ok { message, data } => {
+            assert!(
+                message.contains("Collections: default, prod"),
+                "{}",
+                message
+            );
+            assert!(
+                message.contains("Unsaved changes in 'prod': 1 added, 1 modified, 0 removed"),
+                "{}",
+                message
+            );
+            assert_eq!(data.unwrap()["active_collection"], "default");
+        }
+        other => panic!("{:?}", other),
+    }
+
+    AegCore::flush_now();
+    match AegDispatch::execute(AegisrCommand::Status) {
+        AegisrResponse::Ok { message, .. } => {
+            assert!(message.contains("Unsaved changes: none"), "{}", message)
+        }
+        other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, PutArgs};
+use std::io::Cursor;
+
+#[test]
+fn put_reads_dash_from_stdin_and_get_raw_round_trips() {
+    let _store = AegTestHarness::temp_dir();
+    let pem = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+    let args = PutArgs {
+        key: "tls_cert".into(),
+        value: "-".into(),
+        env_name: None,
+        json: false,
+    };
+    let value = args.read_value(Cursor::new(pem)).unwrap();
+    assert_eq!(value, pem);
+    let inline = PutArgs {
+        value: "literal".into(),
+        ..args
+    };
+    assert_eq!(
+        inline.read_value(Cursor::new("ignored")).unwrap(),
+        "literal"
+    );
+    let dash = PutArgs {
+        value: "-".into(),
+        ..inline
+    };
+    assert!(dash.read_value(Cursor::new(vec![0xff, 0xfe])).is_err());
+
+    AegDispatch::execute(AegisrCommand::Put {
+        key: "tls_cert".into(),
+        value,
+        env_name: None,
+        json: false,
+    });
+    let got = AegDispatch::execute(AegisrCommand::Get {
+        key: "tls_cert".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert_eq!(got.raw(), Some(pem));
+    let missing = AegDispatch::execute(AegisrCommand::Get {
+        key: "nope".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert!(matches!(missing, AegisrResponse::Error { .. }));
+    assert_eq!(missing.raw(), None);
+}
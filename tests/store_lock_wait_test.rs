@@ -0,0 +1,40 @@
+use aegisrlib::AegFileSystem;
+use std::fs::{self, OpenOptions};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn waiting_for_one_store_does_not_block_the_others() {
+    let root = std::env::temp_dir().join(format!("aegisr_lock_wait_{}", std::process::id()));
+    let (busy, idle) = (root.join("busy"), root.join("idle"));
+    fs::create_dir_all(&busy).unwrap();
+    fs::create_dir_all(&idle).unwrap();
+    AegFileSystem::set_lock_timeout(Duration::from_secs(3));
+
+    // another process holds the busy store
+    let other = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(busy.join("aegisr.lock"))
+        .unwrap();
+    other.lock().unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let waiter = thread::spawn(move || {
+        started_tx.send(()).unwrap();
+        AegFileSystem::lock_store(&busy).is_ok()
+    });
+    started_rx.recv().unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    // the idle store is taken and released while the waiter keeps trying
+    let started = Instant::now();
+    drop(AegFileSystem::lock_store(&idle).unwrap());
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    other.unlock().unwrap();
+    assert!(waiter.join().unwrap());
+    fs::remove_dir_all(&root).unwrap();
+}
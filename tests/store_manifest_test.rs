@@ -0,0 +1,71 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegIntegrity, AegMemoryEngine, AegTestHarness, ManifestMismatch,
+    ManifestProblem,
+};
+use std::fs;
+
+#[test]
+fn manifest_detects_files_changed_behind_the_stores_back() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::db", "postgres://v1");
+    AegCore::flush_now();
+
+    assert!(AegIntegrity::manifest_path(dir).exists());
+    let report = AegCore::verify_manifest();
+    assert!(report.passed(), "{}", report.summary());
+    assert!(AegCore::verify().passed());
+
+    // roll prod back to an older copy of its own file, which still decrypts
+    let old = fs::read(dir.join("collection_prod.aekv")).unwrap();
+    AegCore::put_qualified("prod::db", "postgres://v2");
+    AegCore::flush_now();
+    assert!(AegCore::verify_manifest().passed());
+    fs::write(dir.join("collection_prod.aekv"), &old).unwrap();
+
+    let report = AegCore::verify_manifest();
+    assert_eq!(
+        report.mismatches,
+        vec![ManifestMismatch {
+            file: "collection_prod.aekv".into(),
+            problem: ManifestProblem::Modified,
+        }]
+    );
+    assert!(!AegCore::verify().passed());
+
+    // loading it reports the mismatch
+    AegMemoryEngine::reset_cache();
+    AegCore::take_manifest_mismatches();
+    let _ = AegCore::get_qualified("prod::db");
+    assert_eq!(AegCore::take_manifest_mismatches(), report.mismatches);
+
+    // saving through the store accepts the file again
+    AegCore::put_qualified("prod::db", "postgres://v3");
+    AegCore::flush_now();
+    assert!(AegCore::verify_manifest().passed());
+
+    // files the store never wrote are reported too
+    fs::write(dir.join("collection_extra.aekv"), b"planted").unwrap();
+    assert_eq!(
+        AegCore::verify_manifest().mismatches,
+        vec![ManifestMismatch {
+            file: "collection_extra.aekv".into(),
+            problem: ManifestProblem::Unlisted,
+        }]
+    );
+    fs::remove_file(dir.join("collection_extra.aekv")).unwrap();
+
+    // editing the manifest itself breaks its signature
+    let path = AegIntegrity::manifest_path(dir);
+    let edited = fs::read_to_string(&path).unwrap().replacen('a', "b", 1);
+    fs::write(&path, edited).unwrap();
+    let report = AegCore::verify_manifest();
+    assert!(report.present && !report.signed, "{:?}", report);
+    assert!(report.summary().starts_with('✗'));
+
+    // re-keying the store signs the manifest with the new key
+    AegIntegrity::refresh(dir, &AegFileSystem::read_authorization_key()).unwrap();
+    assert!(AegCore::change_passphrase(None, Some("pw"), |_| {}).starts_with('✓'));
+    assert!(AegCore::verify_manifest().passed());
+}
@@ -0,0 +1,98 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegMemoryEngine, AegSync, AegTestHarness, Entry, FsStorage, SyncSide,
+    SyncStrategy, Verbosity,
+};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn sync_copies_missing_keys_and_settles_conflicts() {
+    let store = AegTestHarness::temp_dir();
+    let base = store.dir();
+    let (here, there) = (base.join("here"), base.join("there"));
+
+    // the other machine's store, with a newer value for `shared`
+    AegCore::set_store_dir(there.clone());
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::put_value("only_there", "2");
+    AegMemoryEngine::with_active(|engine| {
+        engine.insert_entry(
+            "shared",
+            Entry {
+                value: "theirs".into(),
+                created_at: Some(now()),
+                updated_at: Some(now() + 1000),
+                tags: vec!["prod".to_string()],
+            },
+        )
+    });
+    AegCore::flush_now();
+
+    AegCore::set_store_dir(here.clone());
+    AegFileSystem::initialize_config(Some(false), Verbosity::default());
+    AegCore::put_value("shared", "ours");
+    AegCore::put_value("only_here", "1");
+    AegCore::create_collection("extra");
+    AegCore::put_qualified("extra::token", "t");
+
+    let report = AegCore::sync_with(
+        Arc::new(FsStorage),
+        there.clone(),
+        SyncStrategy::LastWriterWins,
+    )
+    .unwrap();
+    assert_eq!(
+        report.pulled,
+        vec!["default::only_there", "default::shared"]
+    );
+    assert_eq!(report.pushed, vec!["default::only_here", "extra::token"]);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].key, "shared");
+    assert_eq!(report.conflicts[0].kept, SyncSide::Remote);
+
+    // back on the local store, which took the newer value and its metadata
+    assert_eq!(AegFileSystem::base_dir_override(), Some(here.clone()));
+    assert_eq!(AegCore::get_value("shared").as_deref(), Some("theirs"));
+    assert_eq!(AegCore::get_value("only_there").as_deref(), Some("2"));
+    assert_eq!(AegCore::get_metadata("shared").unwrap().tags, vec!["prod"]);
+
+    // nothing left to do
+    let again = AegSync::sync(
+        Arc::new(FsStorage),
+        there.clone(),
+        SyncStrategy::LastWriterWins,
+    )
+    .unwrap();
+    assert!(again.pulled.is_empty() && again.pushed.is_empty() && again.conflicts.is_empty());
+
+    // the local value wins when asked to, even though it is older
+    AegCore::put_value("shared", "ours again");
+    let report = AegCore::sync_with(
+        Arc::new(FsStorage),
+        there.clone(),
+        SyncStrategy::PreferLocal,
+    )
+    .unwrap();
+    assert_eq!(report.pushed, vec!["default::shared"]);
+    assert_eq!(report.conflicts[0].kept, SyncSide::Local);
+
+    AegCore::set_store_dir(there.clone());
+    assert_eq!(AegCore::get_value("shared").as_deref(), Some("ours again"));
+    assert_eq!(AegCore::get_value("only_here").as_deref(), Some("1"));
+    assert_eq!(
+        AegCore::get_qualified("extra::token").unwrap().as_deref(),
+        Some("t")
+    );
+
+    assert!(AegSync::remote_for("https://example.com/store").is_err());
+    if !cfg!(feature = "s3") {
+        assert!(AegSync::remote_for("s3://bucket/store").is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use aegisrlib::{
+    AegCore, AegFileSystem, AegTestHarness, PersistencePolicy, STORE_HOME_ENV, STORE_STORAGE_ENV,
+};
+
+#[test]
+fn harness_stores_stay_off_the_real_home() {
+    let dir = {
+        let store = AegTestHarness::memory();
+        AegCore::put_value("api", "secret");
+        AegCore::flush_now();
+        assert_eq!(AegCore::get_value("api").unwrap(), "secret");
+        assert_eq!(AegFileSystem::get_config_path(), store.dir());
+        assert!(!store.dir().exists());
+        let files = store.storage().unwrap().contents();
+        assert!(
+            files
+                .iter()
+                .any(|(path, _)| path.ends_with("collection_default.aekv")),
+            "{:?}",
+            files.iter().map(|(path, _)| path).collect::<Vec<_>>()
+        );
+        store.dir().to_path_buf()
+    };
+    assert!(AegFileSystem::base_dir_override().is_none());
+
+    // each harness starts from an empty store
+    let store = AegTestHarness::temp_dir();
+    assert_ne!(store.dir(), dir);
+    assert!(store.storage().is_none());
+    assert!(AegCore::get_value("api").is_none());
+    AegCore::put_value("api", "on disk");
+    AegCore::flush_now();
+    assert!(store.dir().join("collection_default.aekv").exists());
+    let dir = store.dir().to_path_buf();
+    drop(store);
+    assert!(!dir.exists());
+
+    // the saver is done writing before the directory is removed
+    let store = AegTestHarness::temp_dir();
+    let saver = AegCore::set_persistence_policy(PersistencePolicy::Debounced(1)).unwrap();
+    for i in 0..500 {
+        AegCore::put_value(&format!("key_{}", i), "v");
+    }
+    let dir = store.dir().to_path_buf();
+    drop(store);
+    assert!(!saver.is_running());
+    assert!(!dir.exists());
+
+    // SAFETY: the only test in this binary, no other thread reads the environment
+    unsafe { std::env::set_var(STORE_STORAGE_ENV, "memory") };
+    let home = std::env::var_os(STORE_HOME_ENV);
+    unsafe { std::env::remove_var(STORE_HOME_ENV) };
+    let virtual_dir = AegFileSystem::default_store_dir();
+    assert!(virtual_dir.starts_with(std::env::temp_dir()));
+    let storage = AegFileSystem::default_storage();
+    storage.write(&virtual_dir.join("probe"), b"x").unwrap();
+    assert!(!virtual_dir.join("probe").exists());
+    unsafe { std::env::remove_var(STORE_STORAGE_ENV) };
+    if let Some(home) = home {
+        unsafe { std::env::set_var(STORE_HOME_ENV, home) };
+    }
+}
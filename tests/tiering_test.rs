@@ -0,0 +1,66 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn warm_cold_tiering() {
+    let _store = AegTestHarness::memory();
+    let collection = "tiering_test";
+    AegCore::create_collection(collection);
+
+    let mut core = AegCore::load();
+    let previous = core.active_collection.clone();
+    core.set_active_collection(collection).unwrap();
+    AegCore::clear_values();
+    AegCore::set_warm_capacity(collection, Some(2));
+
+    for i in 0..5 {
+        AegCore::put_value(&format!("key{}", i), &format!("value{}", i));
+    }
+
+    let stats = AegCore::tier_stats();
+    assert_eq!(stats.warm_entries, 2);
+    assert_eq!(stats.cold_entries, 3);
+    assert_eq!(stats.evictions, 3);
+
+    // key0 was evicted first; reading it pages it back in
+    assert_eq!(AegCore::get_value("key0").unwrap(), "value0");
+    let stats = AegCore::tier_stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.warm_entries, 2);
+
+    // key0 is now warm
+    assert_eq!(AegCore::get_value("key0").unwrap(), "value0");
+    assert_eq!(AegCore::tier_stats().hits, 1);
+
+    // every value survives regardless of tier
+    for i in 0..5 {
+        assert_eq!(
+            AegCore::get_value(&format!("key{}", i)).unwrap(),
+            format!("value{}", i)
+        );
+    }
+
+    AegCore::set_warm_capacity(collection, None);
+    let stats = AegCore::tier_stats();
+    assert_eq!(stats.warm_entries, 5);
+    assert_eq!(stats.cold_entries, 0);
+
+    // indexed collections can serve single keys straight from disk
+    AegCore::set_indexed(collection, true);
+    AegCore::put_value("key5", "value5");
+    AegCore::flush_now();
+    assert_eq!(
+        AegCore::get_value_uncached("key1").unwrap().as_deref(),
+        Some("value1")
+    );
+    assert_eq!(
+        AegCore::get_value_uncached("key5").unwrap().as_deref(),
+        Some("value5")
+    );
+    assert!(AegCore::get_value_uncached("missing").unwrap().is_none());
+    AegCore::set_indexed(collection, false);
+
+    AegCore::clear_values();
+    AegCore::flush_now();
+    core.set_active_collection(&previous).unwrap();
+    AegCore::delete_collection(collection);
+}
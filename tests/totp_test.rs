@@ -0,0 +1,101 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse, TotpAlgorithm, TotpSecret,
+};
+
+const SHA1_SEED: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+const SHA256_SEED: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA====";
+const SHA512_SEED: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA=";
+
+fn uri(seed: &str, algorithm: &str) -> TotpSecret {
+    format!(
+        "otpauth://totp/Example:alice%40example.com?secret={}&issuer=Example&algorithm={}&digits=8&period=30",
+        seed, algorithm
+    )
+    .parse()
+    .unwrap()
+}
+
+#[test]
+fn codes_match_the_rfc_6238_vectors() {
+    let sha1 = uri(SHA1_SEED, "SHA1");
+    let sha256 = uri(SHA256_SEED, "SHA256");
+    let sha512 = uri(SHA512_SEED, "SHA512");
+    assert_eq!(sha256.algorithm, TotpAlgorithm::Sha256);
+    assert_eq!(sha1.label.as_deref(), Some("Example:alice@example.com"));
+    assert_eq!(sha1.issuer.as_deref(), Some("Example"));
+
+    for (time, codes) in [
+        (59, ["94287082", "46119246", "90693936"]),
+        (1111111109, ["07081804", "68084774", "25091201"]),
+        (1234567890, ["89005924", "91819424", "93441116"]),
+        (20000000000, ["65353130", "77737706", "47863826"]),
+    ] {
+        assert_eq!(sha1.code_at(time).code, codes[0], "SHA1 at {}", time);
+        assert_eq!(sha256.code_at(time).code, codes[1], "SHA256 at {}", time);
+        assert_eq!(sha512.code_at(time).code, codes[2], "SHA512 at {}", time);
+    }
+    let code = sha1.code_at(59);
+    assert_eq!((code.remaining_secs, code.period), (1, 30));
+
+    // a bare secret is SHA-1, 6 digits, 30 seconds; spaces and case do not matter
+    let bare: TotpSecret = "gezd gnbv gy3t qojq gezd gnbv gy3t qojq".parse().unwrap();
+    assert_eq!(bare.code_at(59).code, "287082");
+    assert!(!format!("{:?}", bare).contains("GEZD"));
+
+    assert!(
+        "otpauth://hotp/x?secret=GEZDGNBV&counter=1"
+            .parse::<TotpSecret>()
+            .is_err()
+    );
+    assert!(
+        "otpauth://totp/x?issuer=Example"
+            .parse::<TotpSecret>()
+            .is_err()
+    );
+    assert!("not base32!".parse::<TotpSecret>().is_err());
+}
+
+#[test]
+fn totp_command_shows_the_code_for_a_stored_secret() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::create_collection("2fa");
+    AegCore::put_qualified(
+        "2fa::github",
+        &format!(
+            "otpauth://totp/GitHub:alice?secret={}&issuer=GitHub",
+            SHA1_SEED
+        ),
+    );
+    AegCore::put_qualified("2fa::note", "see the wiki!");
+
+    let code = AegCore::totp_now("2fa::github").unwrap();
+    assert_eq!(code.code.len(), 6);
+    assert!((1..=30).contains(&code.remaining_secs));
+
+    match AegDispatch::execute(AegisrCommand::Totp {
+        key: "2fa::github".into(),
+    }) {
+        AegisrResponse::Ok {
+            message,
+            data: Some(data),
+        } => {
+            assert!(message.contains("valid for"), "{}", message);
+            assert_eq!(data["code"].as_str().unwrap().len(), 6);
+            assert_eq!(data["period"], 30);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(
+        AegDispatch::execute(AegisrCommand::Totp {
+            key: "2fa::note".into()
+        }),
+        AegisrResponse::Error { .. }
+    ));
+    assert!(
+        AegCore::totp_now("2fa::missing")
+            .unwrap_err()
+            .contains("not found")
+    );
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegMemoryEngine, AegTestHarness, TerminalSubscriber, Verbosity};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Lines written by the subscriber, shared with the test.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+fn traced(verbosity: Verbosity, f: impl FnOnce()) -> String {
+    let captured = Captured::default();
+    let subscriber =
+        TerminalSubscriber::new(verbosity.level_filter()).with_writer(captured.clone());
+    tracing::subscriber::with_default(subscriber, f);
+    captured.text()
+}
+
+#[test]
+fn engine_reports_loads_and_saves_through_tracing() {
+    let _store = AegTestHarness::temp_dir();
+
+    let debug = traced(Verbosity::Debug, || {
+        AegCore::put_value("api", "secret");
+        AegCore::flush_now();
+        AegCore::get_value("api");
+        // a fresh cache loads the collection from its file
+        AegMemoryEngine::reset_cache();
+        AegCore::get_value("api");
+    });
+    assert!(
+        debug.contains("DEBUG load{collection=default}: loaded collection"),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("cache miss collection=default"), "{}", debug);
+    assert!(debug.contains("cache hit collection=default"), "{}", debug);
+    assert!(
+        debug.contains(" INFO save{collection=default}: saved collection bytes="),
+        "{}",
+        debug
+    );
+    assert!(!debug.contains("secret"), "{}", debug);
+
+    let verbose = traced(Verbosity::Verbose, || {
+        AegCore::put_value("api", "rotated");
+        AegCore::flush_now();
+    });
+    assert!(verbose.contains("saved collection"), "{}", verbose);
+    assert!(!verbose.contains("DEBUG"), "{}", verbose);
+
+    let normal = traced(Verbosity::Normal, || {
+        AegCore::put_value("api", "again");
+        AegCore::flush_now();
+    });
+    assert_eq!(normal, "");
+}
@@ -0,0 +1,33 @@
+use aegisrlib::{AegCore, AegTestHarness};
+
+#[test]
+fn transaction_commit_and_rollback() {
+    let _store = AegTestHarness::memory();
+    AegCore::put_value("tx_existing", "before");
+
+    let mut tx = AegCore::begin_transaction();
+    tx.put("tx_a", "1");
+    tx.put("tx_b", "2");
+    tx.delete("tx_existing");
+    assert_eq!(tx.get("tx_a").as_deref(), Some("1"));
+    assert!(tx.get("tx_existing").is_none());
+
+    // nothing visible before commit
+    assert!(AegCore::get_value("tx_a").is_none());
+    assert_eq!(AegCore::get_value("tx_existing").unwrap(), "before");
+
+    tx.commit();
+    assert_eq!(AegCore::get_value("tx_a").unwrap(), "1");
+    assert_eq!(AegCore::get_value("tx_b").unwrap(), "2");
+    assert!(AegCore::get_value("tx_existing").is_none());
+
+    let mut tx = AegCore::begin_transaction();
+    tx.put("tx_a", "changed");
+    tx.delete("tx_b");
+    tx.rollback();
+    assert_eq!(AegCore::get_value("tx_a").unwrap(), "1");
+    assert_eq!(AegCore::get_value("tx_b").unwrap(), "2");
+
+    AegCore::delete_value("tx_a");
+    AegCore::delete_value("tx_b");
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegDispatch, AegTestHarness, AegisrCommand, AegisrResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct DbConfig {
+    host: String,
+    port: u16,
+    replicas: Vec<String>,
+    options: BTreeMap<String, bool>,
+}
+
+#[test]
+fn typed_values_round_trip_through_json() {
+    let _store = AegTestHarness::temp_dir();
+
+    let config = DbConfig {
+        host: "db.internal".into(),
+        port: 5432,
+        replicas: vec!["r1".into(), "r2".into()],
+        options: BTreeMap::from([("tls".to_string(), true)]),
+    };
+    assert!(AegCore::put_typed("db_config", &config).starts_with('✓'));
+    assert_eq!(
+        AegCore::get_typed::<DbConfig>("db_config").unwrap(),
+        Some(config)
+    );
+    assert_eq!(AegCore::get_typed::<DbConfig>("missing").unwrap(), None);
+
+    // qualified keys, and plain values that are not the expected JSON
+    AegCore::create_collection("prod");
+    assert!(AegCore::put_typed("prod::limits", &[1u32, 2, 3]).starts_with('✓'));
+    assert_eq!(
+        AegCore::get_typed::<Vec<u32>>("prod::limits").unwrap(),
+        Some(vec![1, 2, 3])
+    );
+    AegCore::put_value("plain", "not json");
+    let err = AegCore::get_typed::<DbConfig>("plain").unwrap_err();
+    assert!(err.contains("'plain'"), "{}", err);
+    assert!(AegCore::get_typed::<DbConfig>("prod::limits").is_err());
+
+    // `put --json` validates and stores the document compacted
+    let put = AegDispatch::execute(AegisrCommand::Put {
+        key: "config".into(),
+        value: "{ \"host\": \"h\", \"port\": 1, \"replicas\": [], \"options\": {} }".into(),
+        env_name: None,
+        json: true,
+    });
+    assert!(matches!(put, AegisrResponse::Ok { .. }), "{:?}", put);
+    assert_eq!(
+        AegCore::get_value("config").as_deref(),
+        Some("{\"host\":\"h\",\"options\":{},\"port\":1,\"replicas\":[]}")
+    );
+    assert_eq!(
+        AegCore::get_typed::<DbConfig>("config")
+            .unwrap()
+            .unwrap()
+            .port,
+        1
+    );
+    let bad = AegDispatch::execute(AegisrCommand::Put {
+        key: "config".into(),
+        value: "{ host: h }".into(),
+        env_name: None,
+        json: true,
+    });
+    match bad {
+        AegisrResponse::Error { message } => assert!(message.contains("not valid JSON")),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        AegCore::get_typed::<DbConfig>("config")
+            .unwrap()
+            .unwrap()
+            .host,
+        "h"
+    );
+}
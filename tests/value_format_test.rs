@@ -0,0 +1,93 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegCore, AegDispatch, AegTestHarness, AegisrCommand, ValueFormat};
+
+#[test]
+fn get_as_converts_json_values_for_other_tools() {
+    let _store = AegTestHarness::temp_dir();
+    let config = r#"{"name":"api","port":8080,"debug":false,"tags":["a b","1"],
+        "db":{"host":"localhost","user name":"admin"},
+        "replicas":[{"host":"r1","weight":0.5},{"host":"r2","weight":1}]}"#;
+    AegCore::put_value("service/config", config);
+    AegCore::put_value("plain", "not json");
+
+    let response = AegDispatch::execute(AegisrCommand::Get {
+        key: "service/config".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    let yaml = response.value_as(ValueFormat::Yaml).unwrap().unwrap();
+    assert_eq!(
+        yaml,
+        "\
+db:
+  host: localhost
+  user name: admin
+debug: false
+name: api
+port: 8080
+replicas:
+- host: r1
+  weight: 0.5
+- host: r2
+  weight: 1
+tags:
+- a b
+- \"1\""
+    );
+    let toml = response.value_as(ValueFormat::Toml).unwrap().unwrap();
+    assert_eq!(
+        toml,
+        "\
+debug = false
+name = \"api\"
+port = 8080
+tags = [\"a b\", \"1\"]
+
+[db]
+host = \"localhost\"
+\"user name\" = \"admin\"
+
+[[replicas]]
+host = \"r1\"
+weight = 0.5
+
+[[replicas]]
+host = \"r2\"
+weight = 1"
+    );
+    let json = response.value_as(ValueFormat::Json).unwrap().unwrap();
+    assert!(json.starts_with("{\n  \"db\": {"));
+    assert_eq!(
+        response.value_as(ValueFormat::Raw).unwrap().unwrap(),
+        config
+    );
+
+    // refusals say why
+    assert!(
+        ValueFormat::Yaml
+            .convert("not json")
+            .unwrap_err()
+            .contains("use --as raw")
+    );
+    assert_eq!(
+        ValueFormat::Toml.convert("[1]").unwrap_err(),
+        "only an object can be written as TOML"
+    );
+    assert_eq!(
+        ValueFormat::Toml
+            .convert(r#"{"a":{"b":null}}"#)
+            .unwrap_err(),
+        "TOML has no null (at 'a.b')"
+    );
+    assert_eq!(ValueFormat::Yaml.convert(r#""yes""#).unwrap(), "\"yes\"");
+    assert_eq!("yml".parse::<ValueFormat>(), Ok(ValueFormat::Yaml));
+    let missing = AegDispatch::execute(AegisrCommand::Get {
+        key: "missing".into(),
+        no_cache: false,
+        clip: false,
+        clear_after: None,
+    });
+    assert!(missing.value_as(ValueFormat::Json).is_none());
+}
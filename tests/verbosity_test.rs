@@ -0,0 +1,17 @@
+use aegisrlib::Verbosity;
+use tracing::Level;
+
+#[test]
+fn flags_map_onto_tracing_levels() {
+    assert_eq!(Verbosity::from_flags(0, false), Verbosity::Normal);
+    assert_eq!(Verbosity::from_flags(2, false), Verbosity::Debug);
+    assert_eq!(Verbosity::from_flags(9, false), Verbosity::Trace);
+    assert_eq!(Verbosity::from_flags(3, true), Verbosity::Quiet);
+
+    assert_eq!(Verbosity::Verbose.level(), Level::INFO);
+    assert!(Verbosity::Normal.enabled(Level::WARN));
+    assert!(!Verbosity::Normal.enabled(Level::INFO));
+    assert!(Verbosity::Quiet.enabled(Level::ERROR));
+    assert!(!Verbosity::Quiet.enabled(Level::WARN));
+    assert!(Verbosity::Trace.enabled(Level::TRACE));
+}
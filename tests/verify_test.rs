@@ -0,0 +1,28 @@
+use aegisrlib::{AegCore, AegCrypto, AegFileSystem, AegTestHarness, AegVerifier, Verbosity};
+use std::fs;
+
+#[test]
+fn verification_pass_and_guarded_key_retirement() {
+    let _store = AegTestHarness::temp_dir();
+    AegCore::put_value("verify_key", "verify_value");
+
+    let report = AegCore::verify_store();
+    assert!(report.passed(), "{}", report.summary());
+    assert!(report.files.iter().any(|f| f.file == "collection.lock"));
+
+    // a key that did not encrypt the store must fail and keep old material
+    let wrong_key = AegCrypto::create_authorization_key(Verbosity::default());
+    let report = AegVerifier::verify_all(&wrong_key);
+    assert!(!report.passed());
+
+    let old_key_path = AegFileSystem::get_config_path().join("AUTHORIZATION_KEY.old");
+    fs::write(&old_key_path, "old").unwrap();
+    assert!(AegVerifier::retire_key_material(&old_key_path, &wrong_key).is_err());
+    assert!(old_key_path.exists());
+
+    let current = AegFileSystem::read_authorization_key();
+    assert!(AegVerifier::retire_key_material(&old_key_path, &current).is_ok());
+    assert!(!old_key_path.exists());
+
+    AegCore::delete_value("verify_key");
+}
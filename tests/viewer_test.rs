@@ -0,0 +1,56 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{
+    AegCore, AegDispatch, AegTestHarness, AegViewer, AegisrCommand, AegisrResponse, ExportFormat,
+};
+use std::fs;
+
+#[test]
+fn html_export_is_a_self_contained_encrypted_page() {
+    let store = AegTestHarness::temp_dir();
+    let dir = store.dir();
+    AegCore::create_collection("prod");
+    AegCore::put_qualified("prod::db_password", "hunter2</script>");
+    AegCore::put_qualified("prod::api_key", "k");
+
+    let path = dir.join("prod.html");
+    let response = AegDispatch::execute(AegisrCommand::Export {
+        portable: false,
+        collection: Some("prod".into()),
+        password: Some("open sesame".into()),
+        recipients: Vec::new(),
+        plain: false,
+        format: Some(ExportFormat::Html),
+        yes: false,
+        path: path.display().to_string(),
+    });
+    assert!(
+        matches!(response, AegisrResponse::Ok { .. }),
+        "{:?}",
+        response
+    );
+
+    let html = fs::read_to_string(&path).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Aegisr · prod</title>"));
+    assert!(html.contains("crypto.subtle"));
+    // nothing readable without the passphrase, nothing loaded from elsewhere
+    assert!(!html.contains("hunter2"));
+    assert!(!html.contains("db_password"));
+    assert!(!html.contains("src=\"http"));
+
+    // the page holds what WebCrypto needs: PBKDF2-SHA256 and AES-GCM
+    let viewer = AegViewer::parse(&html).unwrap();
+    assert_eq!(viewer.kdf, "pbkdf2-sha256");
+    assert!(viewer.iterations >= 600_000);
+    let payload = viewer.open("open sesame").unwrap();
+    assert_eq!(payload.collection, "prod");
+    assert_eq!(payload.entries["db_password"], "hunter2</script>");
+    assert_eq!(payload.entries.len(), 2);
+    assert!(viewer.open("wrong").is_err());
+
+    assert!("HTML".parse::<ExportFormat>().is_ok());
+    assert_eq!(ExportFormat::Html.plain(), None);
+    assert!(AegCore::export_viewer("prod", &path, "").starts_with('✗'));
+    assert!(AegCore::export_viewer("missing", &path, "x").starts_with('✗'));
+}
@@ -0,0 +1,57 @@
+use aegisrlib::{AegCore, AegTestHarness, ChangeEvent, ChangeKind};
+use std::time::Duration;
+
+#[test]
+fn subscribers_receive_key_changes() {
+    let _store = AegTestHarness::temp_dir();
+    // keep clear in memory; its snapshot would save the pending puts first
+    AegCore::set_clear_snapshot(false);
+    let collection = AegCore::load().get_active_collection().to_string();
+    let event = |key: Option<&str>, kind| ChangeEvent {
+        collection: collection.clone(),
+        key: key.map(str::to_string),
+        kind,
+    };
+    let timeout = Duration::from_secs(2);
+
+    let changes = AegCore::subscribe("db/");
+    AegCore::put_value("db/url", "postgres://a");
+    AegCore::put_value("api_key", "ignored");
+    AegCore::delete_value("db/url");
+    AegCore::delete_value("db/missing");
+    AegCore::clear_values();
+    assert_eq!(
+        changes.recv_timeout(timeout).unwrap(),
+        event(Some("db/url"), ChangeKind::Put)
+    );
+    assert_eq!(
+        changes.recv_timeout(timeout).unwrap(),
+        event(Some("db/url"), ChangeKind::Delete)
+    );
+    assert_eq!(
+        changes.recv_timeout(timeout).unwrap(),
+        event(None, ChangeKind::Clear)
+    );
+    assert!(changes.try_recv().is_err());
+    drop(changes);
+    AegCore::put_value("db/url", "after the receiver is gone");
+
+    let saved = AegCore::watch_store("", Duration::from_millis(20));
+    AegCore::put_value("token", "t1");
+    // unsaved changes are not on disk yet
+    assert!(saved.recv_timeout(Duration::from_millis(200)).is_err());
+    AegCore::flush_now();
+    let mut events = vec![
+        saved.recv_timeout(timeout).unwrap(),
+        saved.recv_timeout(timeout).unwrap(),
+    ];
+    events.sort_by(|a, b| a.key.cmp(&b.key));
+    assert_eq!(
+        events,
+        vec![
+            event(Some("db/url"), ChangeKind::Put),
+            event(Some("token"), ChangeKind::Put),
+        ]
+    );
+    assert_eq!(events[0].to_string(), format!("put {}/db/url", collection));
+}
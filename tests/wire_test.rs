@@ -0,0 +1,65 @@
+#![cfg(feature = "cli")]
+
+use aegisrlib::{AegWire, AegisrCommand, AegisrResponse, DecodedCommand, Shell, WIRE_VERSION};
+
+#[test]
+fn wire_format_is_versioned_and_tolerant() {
+    let put = AegisrCommand::Put {
+        key: "db".into(),
+        value: "secret".into(),
+        env_name: None,
+        json: false,
+    };
+    let encoded = AegWire::encode_command(&put).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&encoded).unwrap();
+    assert_eq!(value["version"], WIRE_VERSION);
+    assert_eq!(value["command"], "put");
+    assert_eq!(
+        AegWire::decode_command(&encoded).unwrap(),
+        DecodedCommand::Command(put.clone())
+    );
+
+    // older peers: unversioned serde encoding, missing newer fields
+    let legacy = r#"{"Put":{"verbose":false,"key":"db","value":"secret"}}"#;
+    assert_eq!(
+        AegWire::decode_command(legacy).unwrap(),
+        DecodedCommand::Command(put)
+    );
+    assert_eq!(
+        AegWire::decode_command(r#""ProfileList""#).unwrap(),
+        DecodedCommand::Command(AegisrCommand::ProfileList)
+    );
+    // `verbose` was a field of every command before it became a global flag
+    assert_eq!(
+        AegWire::decode_command(r#"{"Clear":{"verbose":true}}"#).unwrap(),
+        DecodedCommand::Command(AegisrCommand::Clear)
+    );
+
+    // newer peers: unknown fields are ignored, unknown commands reported
+    let newer = r#"{"version":7,"command":"hook","args":{"verbose":false,"shell":"fish","emit":true,"extra":1}}"#;
+    assert_eq!(
+        AegWire::decode_command(newer).unwrap(),
+        DecodedCommand::Command(AegisrCommand::Hook {
+            shell: Shell::Fish,
+            emit: true
+        })
+    );
+    assert_eq!(
+        AegWire::decode_command(r#"{"version":7,"command":"teleport","args":{}}"#).unwrap(),
+        DecodedCommand::Unsupported {
+            name: "teleport".into(),
+            version: 7
+        }
+    );
+    let bad_shell =
+        r#"{"version":1,"command":"hook","args":{"verbose":false,"shell":"tcsh","emit":true}}"#;
+    assert!(AegWire::decode_command(bad_shell).is_err());
+
+    let response = AegisrResponse::from_message("✓ Key 'db' stored".into());
+    let encoded = AegWire::encode_response(&response).unwrap();
+    assert_eq!(AegWire::decode_response(&encoded).unwrap(), response);
+    assert_eq!(
+        AegWire::decode_response(r#"{"version":9,"status":"redirect","to":"x"}"#).unwrap(),
+        AegisrResponse::Unknown
+    );
+}